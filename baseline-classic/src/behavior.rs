@@ -8,14 +8,16 @@ pub mod commit;
 pub use commit::*;
 
 use crate::db::DBPtr;
+use slimchain_chain::genesis::genesis_chain_id;
 use slimchain_common::{
-    basic::{AccountData, Address, Code, Nonce, StateKey, StateValue, H256},
+    basic::{AccountData, Address, BlockHeight, Code, Nonce, StateKey, StateValue, H256},
     error::Result,
     tx_req::SignedTxRequest,
 };
 use slimchain_merkle_trie::prelude::*;
 use slimchain_tx_executor::execute_tx;
 use slimchain_tx_state::{
+    read_code,
     trie_view::{AccountTrieView, StateTrieView},
     update_tx_state, TxStateUpdate, TxStateView, TxStateViewWithUpdate,
 };
@@ -53,7 +55,8 @@ impl<'a, StateView: TxStateView + ?Sized> slimchain_tx_executor::Backend
     }
 
     fn get_code(&self, acc_address: Address) -> Result<Code> {
-        self.map_acc_data(acc_address, Default::default, |d| d.code.clone())
+        let code_hash = self.map_acc_data(acc_address, H256::zero, |d| d.code_hash)?;
+        read_code(self.state_view, code_hash)
     }
 
     fn get_value(&self, acc_address: Address, key: StateKey) -> Result<StateValue> {
@@ -68,13 +71,21 @@ impl<'a, StateView: TxStateView + ?Sized> slimchain_tx_executor::Backend
 pub(crate) async fn exec_tx(
     db: &DBPtr,
     pending_update: &TxStateUpdate,
+    last_block_height: BlockHeight,
+    last_block_timestamp: u64,
     signed_tx_req: &SignedTxRequest,
 ) -> Result<TxStateUpdate> {
     tokio::task::block_in_place(|| {
         let state_root = pending_update.root;
         let state_view = TxStateViewWithUpdate::new(db, pending_update);
         let backend = ExecutorBackend::new(&state_view, state_root);
-        let output = execute_tx(signed_tx_req.clone(), &backend)?;
+        let output = execute_tx(
+            signed_tx_req.clone(),
+            genesis_chain_id(),
+            last_block_height,
+            last_block_timestamp,
+            &backend,
+        )?;
         let new_update = update_tx_state(&state_view, state_root, &output.writes)?;
 
         let mut update = pending_update.clone();
@@ -3,7 +3,7 @@ use crate::{
     db::{DBPtr, Transaction},
 };
 use serde::Serialize;
-use slimchain_chain::latest::LatestTxCountPtr;
+use slimchain_chain::{latest::LatestTxCountPtr, receipt::TxReceipt};
 use slimchain_common::error::Result;
 use slimchain_tx_state::TxStateUpdate;
 use slimchain_utils::record_event;
@@ -22,9 +22,12 @@ where
     let txs = new_block.tx_list();
     let tx_len = txs.len();
 
-    let mut db_tx = Transaction::with_capacity(1);
+    let mut db_tx = Transaction::with_capacity(2 + tx_len);
     db_tx.insert_meta_object("height", &block_height)?;
     db_tx.insert_block(new_block)?;
+    for (idx, tx) in txs.iter().enumerate() {
+        db_tx.insert_tx_receipt(tx.id(), &TxReceipt::committed(block_height, idx))?;
+    }
     db_tx.update_state(update)?;
     db.write_async(db_tx).await?;
 
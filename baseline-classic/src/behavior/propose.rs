@@ -32,7 +32,7 @@ where
     NewBlockFnOutput: Future<Output = Result<Block>> + Send + 'static,
 {
     let begin = Instant::now();
-    let deadline = begin + miner_cfg.max_block_interval;
+    let deadline = begin + miner_cfg.current_max_block_interval();
 
     let mut txs: Vec<SignedTxRequest> = Vec::with_capacity(miner_cfg.max_txs);
 
@@ -41,6 +41,7 @@ where
     let last_block: Block = db
         .get_block(last_block_height)
         .context("Failed to get the last block.")?;
+    let last_block_timestamp = last_block.time_stamp().timestamp_millis() as u64;
     let mut update = TxStateUpdate {
         root: last_block.state_root(),
         ..TxStateUpdate::default()
@@ -74,7 +75,15 @@ where
 
         record_event!("blk_recv_tx", "tx_id": tx_id, "height": next_block_height.0);
 
-        match exec_tx(db, &update, &tx_req).await {
+        match exec_tx(
+            db,
+            &update,
+            last_block_height,
+            last_block_timestamp,
+            &tx_req,
+        )
+        .await
+        {
             Ok(new_update) => {
                 update = new_update;
             }
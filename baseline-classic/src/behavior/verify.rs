@@ -32,13 +32,15 @@ where
     new_block.verify_block_header(&last_block)?;
     verify_consensus_fn(new_block, &last_block)?;
 
+    let last_block_timestamp = last_block.time_stamp().timestamp_millis() as u64;
     let mut update = TxStateUpdate {
         root: last_block.state_root(),
         ..TxStateUpdate::default()
     };
 
     for tx_req in new_block.tx_list().iter() {
-        let new_update = exec_tx(db, &update, tx_req).await?;
+        let new_update =
+            exec_tx(db, &update, last_block_height, last_block_timestamp, tx_req).await?;
         update = new_update;
     }
 
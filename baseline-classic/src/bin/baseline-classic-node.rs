@@ -2,8 +2,15 @@
 extern crate tracing;
 
 use baseline_classic::{config::ChainConfig, db::DB, init_tracing};
-use slimchain_chain::{config::MinerConfig, consensus::Consensus, role::Role};
-use slimchain_common::error::{bail, Context as _, Result};
+use slimchain_chain::{
+    config::{DBConfig, MinerConfig},
+    consensus::Consensus,
+    role::Role,
+};
+use slimchain_common::{
+    digest::set_digest_algorithm,
+    error::{bail, Context as _, Result},
+};
 use slimchain_utils::{config::Config, path::binary_directory};
 use std::{path::PathBuf, time::Duration};
 use structopt::StructOpt;
@@ -55,6 +62,11 @@ async fn main() -> Result<()> {
     info!("Role: {}", role);
     let chain_cfg: ChainConfig = cfg.get("chain")?;
     info!("Chain Cfg: {:#?}", chain_cfg);
+    set_digest_algorithm(chain_cfg.digest);
+
+    let db_cfg: DBConfig = cfg.get("db").unwrap_or_default();
+    info!("DB Cfg: {:#?}", db_cfg);
+    db_cfg.install_as_global()?;
 
     let db = DB::open_or_create_in_dir(&opts.data.unwrap_or(bin_dir), role, opts.db_statistics)?;
 
@@ -66,14 +78,16 @@ async fn main() -> Result<()> {
 
             let net_cfg: NetworkConfig = cfg.get("network")?;
             let pow_cfg: PoWConfig = cfg.get("pow").unwrap_or_default();
-            info!("PoW initial difficulty: {}", pow_cfg.init_diff);
+            info!(
+                "PoW initial difficulty: {}, target block interval: {:?} ms",
+                pow_cfg.init_diff, pow_cfg.target_block_interval
+            );
             pow_cfg.install_as_global()?;
 
             match role {
                 Role::Client => {
                     let behavior = ClientBehavior::new(db, &net_cfg).await?;
-                    let swarmer =
-                        Swarmer::new(net_cfg.keypair.to_libp2p_keypair(), behavior).await?;
+                    let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
                     let mut ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
                     let _miner_peer_id = ctrl
                         .call_with_sender(|swarm, ret| {
@@ -91,8 +105,7 @@ async fn main() -> Result<()> {
                     let miner_cfg: MinerConfig = cfg.get("miner")?;
                     info!("Miner Cfg: {:#?}", miner_cfg);
                     let behavior = MinerBehavior::new(db, &miner_cfg, &net_cfg).await?;
-                    let swarmer =
-                        Swarmer::new(net_cfg.keypair.to_libp2p_keypair(), behavior).await?;
+                    let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
                     let ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
                     ctrl.run_until_interrupt().await?;
                 }
@@ -104,6 +117,7 @@ async fn main() -> Result<()> {
             use slimchain_network::http::config::{NetworkConfig, RaftConfig};
 
             let net_cfg: NetworkConfig = cfg.get("network")?;
+            net_cfg.install_as_global()?;
             let raft_cfg: RaftConfig = cfg.get("raft")?;
 
             match role {
@@ -115,8 +129,8 @@ async fn main() -> Result<()> {
                     info!("Quitting.");
                     client.shutdown().await?;
                 }
-                Role::Storage(_) | Role::Miner => {
-                    bail!("Role cannot be storage or miner.");
+                Role::Storage(_) | Role::Miner | Role::LightClient | Role::Auditor => {
+                    bail!("Role can only be client.");
                 }
             }
         }
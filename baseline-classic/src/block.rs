@@ -2,7 +2,7 @@ use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{BlockHeight, H256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     error::{ensure, Result},
     tx_req::SignedTxRequest,
     utils::derive_more::{Deref, DerefMut},
@@ -26,14 +26,13 @@ pub fn block_header_to_digest(
     tx_list_root: H256,
     state_root: H256,
 ) -> H256 {
-    let mut hash_state = default_blake2().to_state();
+    let mut hash_state = digest_state();
     hash_state.update(height.to_digest().as_bytes());
     hash_state.update(prev_blk_hash.as_bytes());
     hash_state.update(time_stamp.timestamp_millis().to_digest().as_bytes());
     hash_state.update(tx_list_root.as_bytes());
     hash_state.update(state_root.as_bytes());
-    let hash = hash_state.finalize();
-    blake2b_hash_to_h256(hash)
+    hash_state.finalize()
 }
 
 impl Digestible for BlockHeader {
@@ -80,12 +79,11 @@ impl Digestible for BlockTxList {
             return H256::zero();
         }
 
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         for tx in self.iter() {
             hash_state.update(tx.to_digest().as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
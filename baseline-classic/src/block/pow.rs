@@ -2,10 +2,10 @@ use crate::block::{block_header_to_digest, BlockHeader, BlockTrait, BlockTxList}
 use chrono::{DateTime, Utc};
 use futures::prelude::*;
 use serde::{Deserialize, Serialize};
-use slimchain_chain::config::PoWConfig;
+use slimchain_chain::{config::PoWConfig, consensus::pow::next_diff};
 use slimchain_common::{
     basic::{Nonce, H256, U256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     error::{ensure, Error, Result},
 };
 use slimchain_utils::record_time;
@@ -19,12 +19,11 @@ pub struct Block {
 }
 
 fn block_hash(header_hash: H256, diff: u64, nonce: Nonce) -> H256 {
-    let mut hash_state = default_blake2().to_state();
+    let mut hash_state = digest_state();
     hash_state.update(header_hash.as_bytes());
     hash_state.update(diff.to_digest().as_bytes());
     hash_state.update(nonce.to_digest().as_bytes());
-    let hash = hash_state.finalize();
-    blake2b_hash_to_h256(hash)
+    hash_state.finalize()
 }
 
 impl Digestible for Block {
@@ -59,21 +58,14 @@ impl BlockTrait for Block {
     }
 }
 
-// Ref:
-// https://ethereum.stackexchange.com/a/1910
-// https://ethereum.github.io/yellowpaper/paper.pdf
 #[inline]
 fn compute_diff(time_stamp: DateTime<Utc>, prev_blk: &Block) -> u64 {
-    compute_diff_inner(time_stamp, prev_blk.diff, prev_blk.header.time_stamp)
-}
-
-#[inline]
-fn compute_diff_inner(time_stamp: DateTime<Utc>, prev_diff: u64, prev_ts: DateTime<Utc>) -> u64 {
-    let prev_diff = prev_diff as i64;
-    let delta = prev_diff / 2048;
-    let time_span = (time_stamp - prev_ts).num_seconds() as i64;
-    let coeff = core::cmp::max(1 - time_span / 10, -99);
-    (prev_diff + delta * coeff) as u64
+    next_diff(
+        time_stamp,
+        prev_blk.header.height,
+        prev_blk.diff,
+        prev_blk.header.time_stamp,
+    )
 }
 
 #[inline]
@@ -94,9 +86,10 @@ pub fn create_new_block(
 ) -> impl Future<Output = Result<Block>> {
     debug!("Begin mining");
     let begin = Instant::now();
+    let prev_height = prev_blk.header.height;
     let prev_diff = prev_blk.diff;
     let prev_ts = prev_blk.header.time_stamp;
-    let diff = compute_diff_inner(header.time_stamp, prev_diff, prev_ts);
+    let diff = next_diff(header.time_stamp, prev_height, prev_diff, prev_ts);
 
     tokio::task::spawn_blocking(move || {
         let mut blk = Block {
@@ -122,7 +115,7 @@ pub fn create_new_block(
             blk.diff,
         ) {
             blk.header.set_ts(Utc::now());
-            blk.diff = compute_diff_inner(blk.header.time_stamp, prev_diff, prev_ts);
+            blk.diff = next_diff(blk.header.time_stamp, prev_height, prev_diff, prev_ts);
             blk.nonce += 1.into();
         }
 
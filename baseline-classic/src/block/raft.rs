@@ -4,7 +4,7 @@ use futures::prelude::*;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::H256,
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     error::Result,
 };
 
@@ -15,10 +15,9 @@ pub struct Block {
 
 impl Digestible for Block {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.header.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -1,8 +1,13 @@
 use serde::Deserialize;
 use slimchain_chain::consensus::Consensus;
+use slimchain_common::digest::DigestAlgorithm;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChainConfig {
     /// Consensus method. Possible values: pow, raft.
     pub consensus: Consensus,
+    /// The hash function of the tx ids, the state roots, and the blocks. Possible values:
+    /// blake2b, keccak256, and blake3 (requires the blake3 feature). Default blake2b.
+    #[serde(default)]
+    pub digest: DigestAlgorithm,
 }
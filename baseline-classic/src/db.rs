@@ -1,10 +1,10 @@
 use crate::block::{BlockLoaderTrait, BlockTrait};
-use kvdb::{DBKey, DBTransaction, KeyValueDB};
+use kvdb::{DBKey, DBTransaction};
 use serde::{Deserialize, Serialize};
-use slimchain_chain::role::Role;
+use slimchain_chain::{config::DBConfig, receipt::TxReceipt, role::Role};
 use slimchain_common::{
-    basic::{AccountData, Address, BlockHeight, StateValue, H256},
-    error::{bail, Context as _, Error, Result},
+    basic::{AccountData, Address, BlockHeight, Code, StateValue, H256},
+    error::{bail, Context as _, Result},
 };
 use slimchain_tx_state::{TrieNode, TxStateUpdate, TxStateView};
 use slimchain_utils::{
@@ -14,23 +14,20 @@ use slimchain_utils::{
 use std::{path::Path, sync::Arc};
 
 pub use slimchain_chain::db::{
-    block_height_to_db_key, h256_to_db_key, str_to_db_key, u64_to_db_key, BLOCK_DB_COL, LOG_DB_COL,
-    META_DB_COL, STATE_DB_COL, TOTAL_COLS,
+    block_height_to_db_key, h256_to_db_key, open_backend, str_to_db_key, u64_to_db_key, KvBackend,
+    BLOCK_DB_COL, CODE_DB_COL, LOG_DB_COL, META_DB_COL, RECEIPT_DB_COL, STATE_DB_COL, TOTAL_COLS,
 };
 
 pub struct DB {
-    db: Box<dyn KeyValueDB>,
+    db: Box<dyn KvBackend>,
 }
 
 pub type DBPtr = Arc<DB>;
 
 impl DB {
     pub fn open_or_create(path: &Path, enable_statistics: bool) -> Result<Arc<Self>> {
-        info!("Open database at {}", path.display());
-        let mut cfg = kvdb_rocksdb::DatabaseConfig::with_columns(TOTAL_COLS);
-        cfg.enable_statistics = enable_statistics;
-        let db = kvdb_rocksdb::Database::open(&cfg, &path)?;
-        Ok(Arc::new(Self { db: Box::new(db) }))
+        let db = open_backend(path, &DBConfig::get(), enable_statistics)?;
+        Ok(Arc::new(Self { db }))
     }
 
     pub fn open_or_create_in_dir(
@@ -52,8 +49,7 @@ impl DB {
         key: &DBKey,
     ) -> Result<Option<T>> {
         self.db
-            .get(col, key)
-            .map_err(Error::msg)?
+            .get(col, key)?
             .map(|bin| binary_decode::<T>(&bin[..]))
             .transpose()
     }
@@ -79,21 +75,23 @@ impl DB {
         self.get_object(LOG_DB_COL, &u64_to_db_key(idx))
     }
 
+    pub fn get_tx_receipt(&self, tx_id: H256) -> Result<Option<TxReceipt>> {
+        self.get_object(RECEIPT_DB_COL, &h256_to_db_key(tx_id))
+    }
+
     pub fn write_sync(&self, tx: Transaction) -> Result<()> {
-        self.db.write(tx.inner).map_err(Error::msg)
+        self.db.write(tx.inner)
     }
 
     pub async fn write_async(self: &Arc<Self>, tx: Transaction) -> Result<()> {
         let this = self.clone();
-        tokio::task::spawn_blocking(move || this.db.write(tx.inner))
-            .await?
-            .map_err(Error::msg)
+        tokio::task::spawn_blocking(move || this.db.write(tx.inner)).await?
     }
 }
 
 impl Drop for DB {
     fn drop(&mut self) {
-        let stats = self.db.io_stats(kvdb::IoStatsKind::Overall);
+        let stats = self.db.io_stats();
         record_event!("db-io-stats",
             "transactions": stats.transactions,
             "reads": stats.reads,
@@ -141,6 +139,17 @@ impl TxStateView for DB {
                 )
             })
     }
+
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    fn code(&self, code_hash: H256) -> Result<Code> {
+        self.get_existing_object(CODE_DB_COL, &h256_to_db_key(code_hash))
+            .with_context(|| {
+                format!(
+                    "Failed to get code from the database. code_hash: {}",
+                    code_hash
+                )
+            })
+    }
 }
 
 #[derive(Default)]
@@ -181,6 +190,10 @@ impl Transaction {
         )
     }
 
+    pub fn insert_tx_receipt(&mut self, tx_id: H256, receipt: &TxReceipt) -> Result<()> {
+        self.insert_object(RECEIPT_DB_COL, &h256_to_db_key(tx_id), receipt)
+    }
+
     pub fn update_state(&mut self, update: &TxStateUpdate) -> Result<()> {
         for (&addr, node) in update.acc_nodes.iter() {
             self.insert_object(STATE_DB_COL, &h256_to_db_key(addr), node)?;
@@ -192,6 +205,10 @@ impl Transaction {
             }
         }
 
+        for (&code_hash, code) in update.codes.iter() {
+            self.insert_object(CODE_DB_COL, &h256_to_db_key(code_hash), code)?;
+        }
+
         Ok(())
     }
 
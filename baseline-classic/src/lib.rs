@@ -7,25 +7,20 @@ pub mod config;
 pub mod db;
 pub mod network;
 
-use slimchain_common::error::{Error, Result};
-use slimchain_utils::metrics;
+use slimchain_common::error::Result;
+use slimchain_utils::{init_tracing_subscriber_with_directives, metrics, TracingGuard};
 use std::path::Path;
-use tracing_subscriber::EnvFilter;
 
 pub fn init_tracing_subscriber(default_level: &str) -> Result<()> {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new(format!(
+    init_tracing_subscriber_with_directives(default_level, |level| {
+        format!(
             "slimchain={},baseline_classic={},warp::reject=off,warn",
-            default_level, default_level
-        ))
-    });
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .try_init()
-        .map_err(Error::msg)
+            level, level
+        )
+    })
 }
 
-pub fn init_tracing(default_level: &str, metrics_file: &Path) -> Result<metrics::Guard> {
+pub fn init_tracing(default_level: &str, metrics_file: &Path) -> Result<TracingGuard> {
     init_tracing_subscriber(default_level)?;
-    metrics::init_metrics_subscriber_using_file(metrics_file)
+    metrics::init_metrics_subscriber_using_file(metrics_file).map(TracingGuard::from)
 }
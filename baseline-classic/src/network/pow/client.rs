@@ -13,6 +13,7 @@ use slimchain_network::p2p::{
     discovery::{Discovery, DiscoveryEvent},
     http::{ClientHttpServer, TxHttpRequest},
     pubsub::{PubSub, PubSubEvent, PubSubTopic},
+    relay::{Relay, WithRelay},
 };
 use slimchain_utils::record_event;
 
@@ -28,22 +29,27 @@ pub struct ClientBehavior {
 impl ClientBehavior {
     pub async fn new(db: DBPtr, net_cfg: &NetworkConfig) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let mut pubsub = PubSub::new(keypair, &[PubSubTopic::BlockProposal], &[])?;
+        let mut pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::BlockProposal], &[])?;
         pubsub.add_peers_from_net_config(net_cfg);
         let height = db.get_meta_object("height")?.unwrap_or_default();
         let latest_tx_count = LatestTxCount::new(0);
         let worker = BlockImportWorker::new(db.clone(), height, latest_tx_count.clone());
 
+        let db_copy = db.clone();
         let http_server = ClientHttpServer::new(
             &net_cfg.http_listen,
             move || latest_tx_count.get(),
             move || {
-                db.get_meta_object("height")
+                db_copy
+                    .get_meta_object("height")
                     .expect("Failed to get the block height.")
                     .unwrap_or_default()
             },
+            move |tx_id| db.get_tx_receipt(tx_id),
         )?;
 
         Ok(Self {
@@ -92,6 +98,12 @@ impl NetworkBehaviourEventProcess<PubSubEvent<SignedTxRequest, Block>> for Clien
     }
 }
 
+impl WithRelay for ClientBehavior {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
 impl Shutdown for ClientBehavior {
     async fn shutdown(&mut self) -> Result<()> {
@@ -12,6 +12,7 @@ use slimchain_network::p2p::{
     control::Shutdown,
     discovery::{Discovery, DiscoveryEvent},
     pubsub::{PubSub, PubSubEvent, PubSubTopic},
+    relay::{Relay, WithRelay},
 };
 use slimchain_utils::record_event;
 use std::task::{Context, Poll};
@@ -28,9 +29,11 @@ pub struct MinerBehavior {
 impl MinerBehavior {
     pub async fn new(db: DBPtr, miner_cfg: &MinerConfig, net_cfg: &NetworkConfig) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let mut pubsub = PubSub::new(keypair, &[PubSubTopic::TxProposal], &[])?;
+        let mut pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::TxProposal], &[])?;
         pubsub.add_peers_from_net_config(net_cfg);
         let height = db.get_meta_object("height")?.unwrap_or_default();
         let latest_tx_count = LatestTxCount::new(0);
@@ -71,6 +74,12 @@ impl NetworkBehaviourEventProcess<PubSubEvent<SignedTxRequest, Block>> for Miner
     }
 }
 
+impl WithRelay for MinerBehavior {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
 impl Shutdown for MinerBehavior {
     async fn shutdown(&mut self) -> Result<()> {
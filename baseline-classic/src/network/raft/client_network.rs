@@ -35,6 +35,10 @@ impl ClientNodeNetwork {
         }
     }
 
+    pub fn route_table(&self) -> &NetworkRouteTable {
+        &self.route_table
+    }
+
     pub async fn set_leader(&self, leader_id: PeerId) {
         *self.leader_id.write().await = Some(leader_id);
     }
@@ -1,9 +1,11 @@
 use crate::block_proposal::BlockProposal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use slimchain_chain::{
+    archive::prune_tx_bodies,
     block::BlockTrait,
     db::{DBPtr, Transaction},
     latest::{LatestBlockHeaderPtr, LatestTxCountPtr},
+    receipt::TxReceipt,
 };
 use slimchain_common::{error::Result, tx::TxTrait};
 use slimchain_tx_state::TxStateUpdate;
@@ -34,18 +36,20 @@ pub async fn commit_block<Tx, Block>(
 ) -> Result<()>
 where
     Tx: TxTrait + Serialize,
-    Block: BlockTrait + Serialize,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
 {
     let mut db_tx = Transaction::new();
     let blk = blk_proposal.get_block();
     let txs = blk_proposal.get_txs();
 
     db_tx.insert_block(blk)?;
-    for (&tx_hash, tx) in blk.tx_list().iter().zip(txs.iter()) {
+    for (idx, (&tx_hash, tx)) in blk.tx_list().iter().zip(txs.iter()).enumerate() {
         debug_assert_eq!(tx_hash, tx.to_digest());
         db_tx.insert_tx(tx_hash, tx)?;
+        db_tx.insert_tx_receipt(tx.id(), &TxReceipt::committed(blk.block_height(), idx))?;
     }
     db_tx.update_state(state_update)?;
+    prune_tx_bodies::<Block>(db, &mut db_tx, blk.block_height())?;
 
     db.write_async(db_tx).await?;
     latest_block_header.set_from_block(blk);
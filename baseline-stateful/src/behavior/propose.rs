@@ -5,6 +5,7 @@ use slimchain_chain::{
     block::{BlockHeader, BlockTrait, BlockTxList},
     config::{ChainConfig, MinerConfig},
     db::DBPtr,
+    genesis::genesis_chain_id,
 };
 use slimchain_common::{
     error::{Context as _, Result},
@@ -33,7 +34,7 @@ where
     NewBlockFnOutput: Future<Output = Result<Block>> + Send + 'static,
 {
     let begin = Instant::now();
-    let deadline = begin + miner_cfg.max_block_interval;
+    let deadline = begin + miner_cfg.current_max_block_interval();
 
     let mut txs: Vec<Tx> = Vec::with_capacity(miner_cfg.max_txs);
 
@@ -44,6 +45,7 @@ where
 
     let last_block_height = snapshot.current_height();
     let next_block_height = last_block_height.next_height();
+    let chain_id = genesis_chain_id();
 
     debug_assert_eq!(last_block.block_height(), last_block_height);
 
@@ -78,7 +80,18 @@ where
         let tx_id = tx.id();
         record_event!("blk_recv_tx", "tx_id": tx_id, "height": next_block_height.0);
 
+        if let Err(e) = tx.tx_input().check_replay(chain_id, next_block_height) {
+            debug!("Tx proposal cannot be included. Error: {}", e);
+            record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_expired", "detail": std::format!("{}", e));
+            continue;
+        }
+
         let tx_block_height = tx.tx_block_height();
+        if let Some(age) = miner_cfg.expired_tx_age(next_block_height, tx_block_height) {
+            debug!(age, "Tx proposal is expired.");
+            record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_ttl_expired", "detail": std::format!("age={}", age));
+            continue;
+        }
         if tx_block_height < snapshot.access_map.oldest_block_height() {
             debug!("Tx proposal is outdated.");
             record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_outdated");
@@ -110,6 +123,11 @@ where
             record_event!("discard_tx", "tx_id": tx_id, "reason": "invalid_state_root");
             continue;
         }
+        if tx.tx_block_timestamp() != tx_block.time_stamp().timestamp_millis() as u64 {
+            warn!("Received a tx with invalid block timestamp.");
+            record_event!("discard_tx", "tx_id": tx_id, "reason": "invalid_block_timestamp");
+            continue;
+        }
 
         if let Err(e) = tx.verify_sig() {
             warn!("Received a tx with invalid sig. Error: {:?}", e);
@@ -134,10 +152,15 @@ where
     let last_block = snapshot
         .get_block(last_block_height)
         .context("Failed to get the last block.")?;
+    // Keep the block valid even if the local clock is behind the previous proposers.
+    let time_stamp = match snapshot.median_time_past(chain_cfg.median_time_span) {
+        Some(median_time_past) => Utc::now().max(median_time_past),
+        None => Utc::now(),
+    };
     let block_header = BlockHeader::new(
         next_block_height,
         last_block.to_digest(),
-        Utc::now(),
+        time_stamp,
         tx_list,
         state_update.root,
     );
@@ -1,7 +1,10 @@
 use crate::{block_proposal::BlockProposal, snapshot::Snapshot};
-use slimchain_chain::{block::BlockTrait, config::ChainConfig, db::DBPtr};
+use slimchain_chain::{
+    block::BlockTrait, config::ChainConfig, db::DBPtr, genesis::genesis_chain_id,
+    tx_sig::verify_txs_sig,
+};
 use slimchain_common::{
-    error::{bail, ensure, Context as _, Result},
+    error::{bail, ensure, ConsensusError, Context as _, Result},
     rw_set::TxWriteData,
     tx::TxTrait,
 };
@@ -20,7 +23,7 @@ pub async fn verify_block<Tx, Block, VerifyConsensusFn>(
 where
     Tx: TxTrait,
     Block: BlockTrait,
-    VerifyConsensusFn: Fn(&Block, &Block) -> Result<()>,
+    VerifyConsensusFn: Fn(&Block, &Block) -> Result<(), ConsensusError>,
 {
     let begin = Instant::now();
     let last_block = snapshot
@@ -29,12 +32,26 @@ where
     let prev_state_root = last_block.state_root();
 
     blk_proposal.get_block().verify_block_header(last_block)?;
+    let median_time_past = snapshot
+        .median_time_past(chain_cfg.median_time_span)
+        .context("Failed to get the median time past")?;
+    blk_proposal
+        .get_block()
+        .verify_time_stamp(median_time_past, chain_cfg.max_future_drift)?;
     verify_consensus_fn(blk_proposal.get_block(), last_block)?;
+    blk_proposal.verify_structure()?;
+
+    verify_txs_sig(blk_proposal.get_txs()).context("Tx with invalid sig.")?;
 
     snapshot.access_map.alloc_new_block();
     let mut writes = TxWriteData::default();
+    let chain_id = genesis_chain_id();
 
     for tx in blk_proposal.get_txs() {
+        tx.tx_input()
+            .check_replay(chain_id, blk_proposal.get_block_height())
+            .context("Tx not allowed in the block proposal.")?;
+
         let tx_block_height = tx.tx_block_height();
         let tx_block = match snapshot.get_block(tx_block_height) {
             Some(blk) => blk,
@@ -49,8 +66,10 @@ where
             tx.tx_state_root() == tx_block.state_root(),
             "Tx with invalid state root."
         );
-
-        tx.verify_sig().context("Tx with invalid sig.")?;
+        ensure!(
+            tx.tx_block_timestamp() == tx_block.time_stamp().timestamp_millis() as u64,
+            "Tx with invalid block timestamp."
+        );
 
         ensure!(
             !chain_cfg.conflict_check.has_conflict(
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
-use slimchain_chain::{block::BlockTrait, loader::TxLoaderTrait};
+use slimchain_chain::{block::BlockTrait, loader::TxLoaderTrait, tx_proof::tx_merkle_root};
 use slimchain_common::{
     basic::BlockHeight,
+    collections::HashSet,
     error::{ensure, Result},
     tx::TxTrait,
 };
@@ -47,4 +48,36 @@ impl<Block: BlockTrait, Tx: TxTrait> BlockProposal<Block, Tx> {
     pub fn unpack(self) -> (Block, Vec<Tx>) {
         (self.block, self.txs)
     }
+
+    // Check that the txs match the tx list of the block, as in slimchain. It needs no chain
+    // state, so that the block proposals can be checked before they are gossiped.
+    pub fn verify_structure(&self) -> Result<()> {
+        ensure!(
+            !self.get_block_height().is_zero(),
+            "Unexpected genesis block in the block proposal."
+        );
+        let tx_list = self.block.tx_list();
+        ensure!(
+            tx_list.len() == self.txs.len(),
+            "Mismatched tx list. Expect {} txs, got {}.",
+            tx_list.len(),
+            self.txs.len()
+        );
+        ensure!(
+            tx_list
+                .iter()
+                .zip(self.txs.iter())
+                .all(|(&hash, tx)| hash == tx.to_digest()),
+            "Mismatched tx list."
+        );
+        ensure!(
+            tx_list.iter().collect::<HashSet<_>>().len() == tx_list.len(),
+            "Duplicate txs in the block proposal."
+        );
+        ensure!(
+            self.block.tx_root() == tx_merkle_root(tx_list),
+            "Invalid tx root."
+        );
+        Ok(())
+    }
 }
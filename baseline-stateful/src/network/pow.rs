@@ -25,6 +25,8 @@ use slimchain_chain::{
     latest::{LatestBlockHeaderPtr, LatestTxCountPtr},
 };
 use slimchain_common::{
+    basic::H256,
+    digest::Digestible,
     error::{bail, Result},
     tx::TxTrait,
 };
@@ -248,3 +250,12 @@ impl<Tx: TxTrait + Serialize> BlockProposalWorker<Tx> {
         Ok(())
     }
 }
+
+// Drop the malformed block proposals before relaying them. The PoW itself is checked against
+// the parent block on import.
+pub(crate) fn validate_block_proposal<Tx: TxTrait>(
+    proposal: &BlockProposal<Block, Tx>,
+) -> Result<H256> {
+    proposal.verify_structure()?;
+    Ok(proposal.get_block().to_digest())
+}
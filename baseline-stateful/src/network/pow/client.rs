@@ -1,4 +1,4 @@
-use super::BlockImportWorker;
+use super::{validate_block_proposal, BlockImportWorker};
 use crate::{block_proposal::BlockProposal, snapshot::Snapshot};
 use async_trait::async_trait;
 use libp2p::{swarm::NetworkBehaviourEventProcess, NetworkBehaviour, PeerId};
@@ -7,22 +7,22 @@ use slimchain_chain::{
     config::ChainConfig, consensus::pow::Block, db::DBPtr, latest::LatestTxCount, role::Role,
 };
 use slimchain_common::{
-    basic::{ShardId, H256},
-    collections::HashMap,
-    error::Result,
-    tx::TxTrait,
-    tx_req::SignedTxRequest,
+    basic::H256, collections::HashMap, error::Result, tx::TxTrait, tx_req::SignedTxRequest,
 };
-use slimchain_network::p2p::{
-    config::NetworkConfig,
-    control::Shutdown,
-    discovery::{Discovery, DiscoveryEvent, QueryId as DiscoveryQueryId},
-    http::{ClientHttpServer, TxHttpRequest},
-    pubsub::{PubSub, PubSubEvent, PubSubTopic},
-    rpc::{
-        create_request_response_client, handle_request_response_client_event, RpcInstant,
-        RpcRequestId, RpcRequestResponseEvent,
+use slimchain_network::{
+    p2p::{
+        config::NetworkConfig,
+        control::Shutdown,
+        discovery::{Discovery, DiscoveryEvent, QueryId as DiscoveryQueryId},
+        http::{ClientHttpServer, TxHttpRequest},
+        pubsub::{PubSub, PubSubEvent, PubSubTopic},
+        relay::{Relay, WithRelay},
+        rpc::{
+            create_request_response_client, handle_request_response_client_event, RpcInstant,
+            RpcRequestId, RpcRequestResponseEvent,
+        },
     },
+    tx_route::resolve_tx_shard,
 };
 use slimchain_utils::record_event;
 use std::time::Duration;
@@ -46,9 +46,12 @@ impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
         let peer_id = PeerId::from(keypair.public());
 
-        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let mut pubsub = PubSub::new(keypair, &[PubSubTopic::BlockProposal], &[])?;
+        let mut pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::BlockProposal], &[])?
+            .with_block_proposal_validator(validate_block_proposal);
         pubsub.add_peers_from_net_config(net_cfg);
         let mut rpc_client = create_request_response_client("/tx_req/1");
 
@@ -66,13 +69,14 @@ impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
             snapshot,
             latest_block_header.clone(),
             latest_tx_count.clone(),
-            db,
+            db.clone(),
         );
 
         let http_server = ClientHttpServer::new(
             &net_cfg.http_listen,
             move || latest_tx_count.get(),
             move || latest_block_header.get_height(),
+            move |tx_id| db.get_tx_receipt(tx_id),
         )?;
 
         Ok(Self {
@@ -97,11 +101,12 @@ impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
 
 impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<TxHttpRequest> for ClientBehavior<Tx> {
     fn inject_event(&mut self, tx_http_req: TxHttpRequest) {
-        let TxHttpRequest { req, .. } = tx_http_req;
+        let TxHttpRequest { req, shard_id } = tx_http_req;
         trace!(tx_req_id = %req.id(), "Recv TxReq from http.");
-        let discv_query_id = self
-            .discv
-            .find_random_peer(Role::Storage(ShardId::default()), Duration::from_secs(5));
+        // As in slimchain, only the shard given by the sender is corrected, since the shards
+        // served by the storage nodes are not fully known here.
+        let shard_id = resolve_tx_shard(&req, shard_id, std::iter::empty());
+        let discv_query_id = self.discv.find_shard_peer(shard_id, Duration::from_secs(5));
         self.pending_discv_queries.insert(discv_query_id, req);
     }
 }
@@ -167,6 +172,12 @@ impl<Tx: TxTrait + Serialize>
     }
 }
 
+impl<Tx: TxTrait + Serialize> WithRelay for ClientBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
 impl<Tx: TxTrait + Serialize> Shutdown for ClientBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
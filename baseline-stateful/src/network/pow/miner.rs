@@ -19,6 +19,7 @@ use slimchain_network::p2p::{
     control::Shutdown,
     discovery::{Discovery, DiscoveryEvent},
     pubsub::{PubSub, PubSubEvent, PubSubTopic},
+    relay::{Relay, WithRelay},
 };
 use slimchain_utils::record_event;
 use std::task::{Context, Poll};
@@ -40,9 +41,11 @@ impl<Tx: TxTrait + Serialize + 'static> MinerBehavior<Tx> {
         net_cfg: &NetworkConfig,
     ) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let pubsub = PubSub::new(keypair, &[PubSubTopic::TxProposal], &[])?;
+        let pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::TxProposal], &[])?;
         let snapshot = Snapshot::<Block>::load_from_db(&db, chain_cfg.state_len)?;
         let latest_block_header = snapshot.to_latest_block_header();
         let latest_tx_count = LatestTxCount::new(0);
@@ -92,6 +95,12 @@ impl<Tx: TxTrait + Serialize>
     }
 }
 
+impl<Tx: TxTrait + Serialize> WithRelay for MinerBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
 impl<Tx: TxTrait + Serialize> Shutdown for MinerBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
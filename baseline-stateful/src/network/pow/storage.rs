@@ -1,4 +1,4 @@
-use super::BlockImportWorker;
+use super::{validate_block_proposal, BlockImportWorker};
 use crate::{block_proposal::BlockProposal, snapshot::Snapshot};
 use async_trait::async_trait;
 use futures::{channel::mpsc, prelude::*};
@@ -17,6 +17,7 @@ use slimchain_network::p2p::{
     control::Shutdown,
     discovery::{Discovery, DiscoveryEvent},
     pubsub::{PubSub, PubSubEvent, PubSubTopic},
+    relay::{Relay, WithRelay},
     rpc::{
         create_request_response_server, handle_request_response_server_event, RpcInstant,
         RpcRequestResponseEvent,
@@ -53,22 +54,24 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
     pub async fn new(
         db: DBPtr,
         engine: TxEngine<Tx>,
+        shard_id: ShardId,
         chain_cfg: &ChainConfig,
         net_cfg: &NetworkConfig,
     ) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv = Discovery::new(
-            keypair.public(),
-            Role::Storage(ShardId::default()),
-            net_cfg.mdns,
-        )
-        .await?;
+        // The shard only decides which txs are sent here. The full state is kept, since baseline
+        // blocks carry no write set proofs for the accounts outside the shard.
+        let mut discv = Discovery::new(keypair.public(), Role::Storage(shard_id), net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
         let mut pubsub = PubSub::new(
             keypair,
+            &net_cfg.pubsub,
             &[PubSubTopic::BlockProposal],
             &[PubSubTopic::TxProposal],
-        )?;
+        )?
+        .with_block_proposal_validator(validate_block_proposal);
         pubsub.add_peers_from_net_config(net_cfg);
         let rpc_server = create_request_response_server("/tx_req/1");
         let snapshot = Snapshot::<Block>::load_from_db(&db, chain_cfg.state_len)?;
@@ -159,6 +162,12 @@ impl<Tx: TxTrait + Serialize>
     }
 }
 
+impl<Tx: TxTrait + Serialize> WithRelay for StorageBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
 impl<Tx: TxTrait + Serialize> Shutdown for StorageBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
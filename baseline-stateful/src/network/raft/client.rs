@@ -21,18 +21,22 @@ use slimchain_common::{
     tx::TxTrait,
 };
 use slimchain_network::{
-    behavior::raft::utils::{get_current_leader, node_is_leader},
+    behavior::raft::utils::{
+        get_current_leader, leader_read_index, linearizable_read_barrier, node_is_leader,
+    },
     http::{
+        admission::Admission,
         client_rpc::*,
         common::*,
         config::{NetworkConfig, RaftConfig},
+        metrics::metrics_server,
         node_rpc::*,
     },
 };
 use slimchain_utils::record_event;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::task::JoinHandle;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 pub type ClientNodeRaft<Tx> =
     Raft<NewBlockRequest<Tx>, NewBlockResponse, ClientNodeNetwork<Tx>, ClientNodeStorage<Tx>>;
@@ -92,7 +96,14 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
             let network_worker_req_tx = network_worker.get_req_tx();
             let raft_storage_copy1 = raft_storage.clone();
             let raft_storage_copy2 = raft_storage.clone();
-            client_rpc_server(
+            let raft_storage_copy3 = raft_storage.clone();
+            let raft_copy1 = raft.clone();
+            let raft_copy2 = raft.clone();
+            let raft_copy3 = raft.clone();
+            let route_table = raft_network.route_table().clone();
+            let route_table_copy = route_table.clone();
+            raft_client_rpc_server(
+                Arc::new(Admission::unlimited()),
                 move |reqs: Vec<TxHttpRequest>| {
                     let mut network_worker_req_tx = network_worker_req_tx.clone();
                     async move {
@@ -105,6 +116,18 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                 },
                 move || raft_storage_copy1.latest_tx_count().get(),
                 move || raft_storage_copy2.latest_block_header().get_height(),
+                move |tx_id| raft_storage_copy3.db().get_tx_receipt(tx_id),
+                move || {
+                    let leader = raft_copy1.metrics().borrow().current_leader?;
+                    route_table.peer_address(leader.into()).ok().cloned()
+                },
+                move || node_is_leader(raft_copy2.as_ref()),
+                Arc::new(move || {
+                    let raft = raft_copy3.clone();
+                    let route_table = route_table_copy.clone();
+                    async move { linearizable_read_barrier(raft.as_ref(), &route_table).await }
+                        .boxed()
+                }),
             )
         };
 
@@ -201,7 +224,21 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                     }
                 });
 
-            leader_id_rpc.or(leader_req_rpc)
+            let raft_copy = raft.clone();
+            let read_index_rpc = warp::get()
+                .and(warp::path(CLIENT_READ_INDEX_ROUTE_PATH))
+                .and_then(move || {
+                    let raft_copy = raft_copy.clone();
+                    async move {
+                        let resp = match leader_read_index(raft_copy.as_ref()).await {
+                            Ok(index) => warp_reply_binary(&index).into_response(),
+                            Err(e) => reply_error(&e),
+                        };
+                        Ok::<_, warp::Rejection>(resp)
+                    }
+                });
+
+            leader_id_rpc.or(leader_req_rpc).or(read_index_rpc)
         };
 
         let block_rpc_srv = {
@@ -222,14 +259,22 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
         };
 
         info!("Create http server, listen on {}", net_cfg.http_listen);
-        let listen_addr: SocketAddr = net_cfg.http_listen.parse()?;
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
         let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
-        let (_, srv) = warp::serve(client_rpc_srv.or(
-            warp::path(NODE_RPC_ROUTE_PATH).and(raft_rpc_srv.or(leader_rpc_srv).or(block_rpc_srv)),
-        ))
-        .bind_with_graceful_shutdown(listen_addr, async {
+        let routes = client_rpc_srv
+            .or(metrics_server())
+            .or(warp::path(NODE_RPC_ROUTE_PATH)
+                .and(raft_rpc_srv.or(leader_rpc_srv).or(block_rpc_srv)));
+        let signal = async {
             srv_shutdown_rx.await.ok();
-        });
+        }
+        .shared();
+        let srv = future::join_all(listen_addrs.into_iter().map(|addr| {
+            warp::serve(routes.clone())
+                .bind_with_graceful_shutdown(addr, signal.clone())
+                .1
+        }))
+        .map(|_| ());
         let srv_handle = tokio::spawn(srv);
 
         info!("Initialize Raft Node");
@@ -13,9 +13,8 @@ use futures::{
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
-use slimchain_chain::{consensus::raft::Block, role::Role};
+use slimchain_chain::{consensus::raft::Block, discard_tx, role::Role};
 use slimchain_common::{
-    basic::ShardId,
     error::{bail, Result},
     tx::TxTrait,
 };
@@ -27,6 +26,10 @@ use slimchain_network::{
         config::{NetworkRouteTable, PeerId},
         node_rpc::*,
     },
+    tx_route::{
+        record_tx_retried, record_tx_route_failed, record_tx_routed, resolve_tx_shard,
+        MAX_ROUTE_ATTEMPTS,
+    },
 };
 use slimchain_utils::{bytes::Bytes, record_event, serde::binary_encode};
 use std::{marker::PhantomData, sync::Arc};
@@ -53,48 +56,78 @@ where
         }
     }
 
+    // Unlike slimchain, every baseline storage node keeps the full state, as baseline blocks
+    // carry no write set proofs to build the tries of other shards from. Only the execution of
+    // txs is partitioned, using the same shard routing as slimchain.
     #[tracing::instrument(level = "debug", skip(self, tx_req))]
     pub async fn forward_tx_to_storage_node(&self, tx_req: TxHttpRequest) {
-        let TxHttpRequest { req, .. } = tx_req;
+        let TxHttpRequest { req, shard_id } = tx_req;
         let tx_req_id = req.id();
+        let shard_id = resolve_tx_shard(&req, shard_id, self.route_table.storage_shard_totals());
 
-        let storage_node_peer_id = match self
-            .route_table
-            .random_peer(&Role::Storage(ShardId::default()))
-        {
-            Some(peer) => peer,
-            None => {
-                error!(%tx_req_id , "Failed to find the storage node.");
-                return;
-            }
-        };
-        debug_assert_ne!(storage_node_peer_id, self.route_table.peer_id());
+        let storage_node_peer_ids = self.route_table.shuffled_peers(&Role::Storage(shard_id));
+        if storage_node_peer_ids.is_empty() {
+            error!(%tx_req_id , "Failed to find the storage node. ShardId: {:?}", shard_id);
+            record_tx_route_failed();
+            discard_tx!(tx_req_id, "no_storage_node", format!("{:?}", shard_id));
+            return;
+        }
+
+        record_event!("tx_begin", "tx_id": tx_req_id);
 
-        let storage_node_addr = match self.route_table.peer_address(storage_node_peer_id) {
-            Ok(addr) => addr,
-            Err(_) => {
-                error!(%tx_req_id , "Failed to get the storage address. PeerId: {}", storage_node_peer_id);
-                return;
+        let mut last_err = None;
+        for (attempt, storage_node_peer_id) in storage_node_peer_ids
+            .into_iter()
+            .take(MAX_ROUTE_ATTEMPTS)
+            .enumerate()
+        {
+            debug_assert_ne!(storage_node_peer_id, self.route_table.peer_id());
+            if attempt > 0 {
+                record_tx_retried();
+                record_event!("tx_route_retry", "tx_id": tx_req_id, "peer_id": storage_node_peer_id.0, "attempt": attempt);
             }
-        };
 
-        record_event!("tx_begin", "tx_id": tx_req_id);
+            let storage_node_addr = match self.route_table.peer_address(storage_node_peer_id) {
+                Ok(addr) => addr,
+                Err(e) => {
+                    last_err = Some(e);
+                    continue;
+                }
+            };
 
-        let resp: Result<()> = send_post_request_using_binary(
-            &format!(
-                "http://{}/{}/{}",
-                storage_node_addr, NODE_RPC_ROUTE_PATH, STORAGE_TX_REQ_ROUTE_PATH
-            ),
-            &req,
-        )
-        .await;
+            let resp: Result<()> = send_post_request_using_binary(
+                &format!(
+                    "http://{}/{}/{}",
+                    storage_node_addr, NODE_RPC_ROUTE_PATH, STORAGE_TX_REQ_ROUTE_PATH
+                ),
+                &req,
+            )
+            .await;
 
-        if let Err(e) = resp {
-            error!(
-                %tx_req_id,
-                "Failed to forward TX to storage node. Error: {}", e
-            );
+            match resp {
+                Ok(()) => {
+                    record_tx_routed();
+                    return;
+                }
+                Err(e) => {
+                    warn!(
+                        %tx_req_id,
+                        %storage_node_peer_id,
+                        "Failed to forward TX to storage node. Error: {}", e
+                    );
+                    last_err = Some(e);
+                }
+            }
         }
+
+        let e = last_err.map(|e| e.to_string()).unwrap_or_default();
+        error!(%tx_req_id, "Failed to forward TX to any storage node. Error: {}", e);
+        record_tx_route_failed();
+        discard_tx!(tx_req_id, "storage_route_failed", e);
+    }
+
+    pub fn route_table(&self) -> &NetworkRouteTable {
+        &self.route_table
     }
 
     pub async fn set_leader(&self, leader_id: PeerId) {
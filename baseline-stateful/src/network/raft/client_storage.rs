@@ -107,6 +107,10 @@ where
         BlockProposal::from_existing_block(block, &self.db)
     }
 
+    pub fn db(&self) -> DBPtr {
+        self.db.clone()
+    }
+
     pub fn latest_block_header(&self) -> LatestBlockHeaderPtr {
         self.latest_block_header.clone()
     }
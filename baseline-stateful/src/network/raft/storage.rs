@@ -26,6 +26,7 @@ use slimchain_network::{
     http::{
         common::*,
         config::{NetworkConfig, NetworkRouteTable, PeerId},
+        metrics::metrics_server,
         node_rpc::*,
     },
 };
@@ -323,13 +324,20 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> StorageNode<
             });
 
         info!("Create http server, listen on {}", net_cfg.http_listen);
-        let listen_addr: SocketAddr = net_cfg.http_listen.parse()?;
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
         let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
-        let (_, srv) =
-            warp::serve(warp::path(NODE_RPC_ROUTE_PATH).and(tx_exec_srv.or(block_import_srv)))
-                .bind_with_graceful_shutdown(listen_addr, async {
-                    srv_shutdown_rx.await.ok();
-                });
+        let routes = metrics_server()
+            .or(warp::path(NODE_RPC_ROUTE_PATH).and(tx_exec_srv.or(block_import_srv)));
+        let signal = async {
+            srv_shutdown_rx.await.ok();
+        }
+        .shared();
+        let srv = future::join_all(listen_addrs.into_iter().map(|addr| {
+            warp::serve(routes.clone())
+                .bind_with_graceful_shutdown(addr, signal.clone())
+                .1
+        }))
+        .map(|_| ());
         let srv_handle = tokio::spawn(srv);
 
         Ok(Self {
@@ -1,18 +1,24 @@
 use crate::init_tracing;
 use serde::{Deserialize, Serialize};
 use slimchain_chain::{
-    config::{ChainConfig, MinerConfig},
+    config::{AccessMapConfig, ChainConfig, DBConfig, MinerConfig, PruneConfig},
     consensus::Consensus,
-    db::DB,
+    db::{spawn_db_stats_recorder, DB},
+    genesis::{GenesisConfig, GENESIS_FILE_NAME},
     role::Role,
 };
 use slimchain_common::{
+    basic::set_shard_assignment,
+    digest::set_digest_algorithm,
     error::{bail, Context as _, Result},
     tx::TxTrait,
 };
 use slimchain_network::p2p::control::Swarmer;
-use slimchain_tx_engine::TxEngine;
-use slimchain_utils::{config::Config, path::binary_directory};
+use slimchain_tx_engine::{TxEngine, TxEngineConfig};
+use slimchain_utils::{
+    config::{Config, ConfigWatcher, LogConfig, CONFIG_FILE_NAME},
+    path::binary_directory,
+};
 use std::{path::PathBuf, time::Duration};
 use structopt::StructOpt;
 
@@ -23,6 +29,10 @@ struct Opts {
     #[structopt(short, long, parse(from_os_str))]
     config: Option<PathBuf>,
 
+    /// Path to the genesis.toml file.
+    #[structopt(short, long, parse(from_os_str))]
+    genesis: Option<PathBuf>,
+
     /// Path to the enclave file.
     #[structopt(short, long, parse(from_os_str))]
     enclave: Option<PathBuf>,
@@ -42,6 +52,10 @@ struct Opts {
     /// Enable RocksDB statistics.
     #[structopt(long)]
     db_statistics: bool,
+
+    /// Restore the database from the backup before starting the node.
+    #[structopt(long, parse(from_os_str))]
+    restore: Option<PathBuf>,
 }
 
 pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static>(
@@ -57,19 +71,63 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
         init_tracing(log_level, &metrics)?
     };
 
-    let cfg = if let Some(config) = opts.config {
-        info!("Load config from {}.", config.display());
-        Config::load(&config)?
-    } else {
-        Config::load_in_the_same_dir()?
-    };
+    let cfg_file = opts
+        .config
+        .unwrap_or_else(|| bin_dir.join(CONFIG_FILE_NAME));
+    info!("Load config from {}.", cfg_file.display());
+    let cfg = Config::load(&cfg_file)?;
+
+    let log_cfg: LogConfig = cfg.get("log").unwrap_or_default();
+    if opts.log_level.is_none() {
+        log_cfg.apply()?;
+    }
+    // The log level, the admission limits, the block interval and the engine threads are
+    // reloaded on SIGHUP or once the config file is modified.
+    let config_watcher = ConfigWatcher::new(&cfg_file)
+        .on_reload(|cfg| cfg.get::<LogConfig>("log").unwrap_or_default().apply());
 
     let role: Role = cfg.get("role")?;
     info!("Role: {}", role);
     let chain_cfg: ChainConfig = cfg.get("chain")?;
     info!("Chain Cfg: {:#?}", chain_cfg);
+    set_digest_algorithm(chain_cfg.digest);
+    set_shard_assignment(chain_cfg.shard_assignment);
+
+    let genesis_file = opts
+        .genesis
+        .or_else(|| Some(bin_dir.join(GENESIS_FILE_NAME)).filter(|f| f.exists()));
+    let genesis_cfg = if let Some(genesis_file) = genesis_file {
+        info!("Load genesis from {}.", genesis_file.display());
+        GenesisConfig::load(&genesis_file)?
+    } else {
+        GenesisConfig::default()
+    };
+    info!(
+        "Genesis: chain id {}, {} accounts.",
+        genesis_cfg.chain_id,
+        genesis_cfg.accounts.len()
+    );
+    genesis_cfg.install_as_global()?;
+
+    let access_map_cfg: AccessMapConfig = cfg.get("access_map").unwrap_or_default();
+    info!("AccessMap Cfg: {:#?}", access_map_cfg);
+    access_map_cfg.install_as_global()?;
+
+    let prune_cfg: PruneConfig = cfg.get("prune").unwrap_or_default();
+    info!("Prune Cfg: {:#?}", prune_cfg);
+    prune_cfg.install_as_global()?;
+
+    let db_cfg: DBConfig = cfg.get("db").unwrap_or_default();
+    info!("DB Cfg: {:#?}", db_cfg);
+    let db_stats_interval = db_cfg.stats_interval;
+    db_cfg.install_as_global()?;
 
     let db = DB::open_or_create_in_dir(&opts.data.unwrap_or(bin_dir), role, opts.db_statistics)?;
+    if let Some(backup) = opts.restore {
+        db.restore(&backup)?;
+    }
+    let _db_stats_recorder = (db_stats_interval > 0)
+        .then(|| spawn_db_stats_recorder(&db, Duration::from_millis(db_stats_interval)));
 
     match chain_cfg.consensus {
         Consensus::PoW => {
@@ -80,14 +138,16 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
             let net_cfg: NetworkConfig = cfg.get("network")?;
 
             let pow_cfg: PoWConfig = cfg.get("pow").unwrap_or_default();
-            info!("PoW initial difficulty: {}", pow_cfg.init_diff);
+            info!(
+                "PoW initial difficulty: {}, target block interval: {:?} ms",
+                pow_cfg.init_diff, pow_cfg.target_block_interval
+            );
             pow_cfg.install_as_global()?;
 
             match role {
                 Role::Client => {
                     let behavior = ClientBehavior::<Tx>::new(db, &chain_cfg, &net_cfg).await?;
-                    let swarmer =
-                        Swarmer::new(net_cfg.keypair.to_libp2p_keypair(), behavior).await?;
+                    let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
                     let mut ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
                     let _miner_peer_id = ctrl
                         .call_with_sender(|swarm, ret| {
@@ -99,24 +159,36 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
                         })
                         .await?
                         .context("Failed to find miner.")?;
+                    let _config_watcher = config_watcher.spawn();
                     ctrl.run_until_interrupt().await?;
                 }
                 Role::Miner => {
                     let miner_cfg: MinerConfig = cfg.get("miner")?;
                     info!("Miner Cfg: {:#?}", miner_cfg);
+                    let config_watcher = config_watcher.on_reload(reload_miner_cfg);
                     let behavior =
                         MinerBehavior::<Tx>::new(db, &chain_cfg, &miner_cfg, &net_cfg).await?;
-                    let swarmer =
-                        Swarmer::new(net_cfg.keypair.to_libp2p_keypair(), behavior).await?;
+                    let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
                     let ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
+                    let _config_watcher = config_watcher.spawn();
                     ctrl.run_until_interrupt().await?;
                 }
-                Role::Storage(_) => {
+                Role::LightClient | Role::Auditor => {
+                    bail!("Role cannot be light client or auditor.");
+                }
+                Role::Storage(shard_id) => {
                     let engine = create_tx_engine(&cfg, &opts.enclave)?;
+                    let engine_threads = engine.thread_limit();
+                    engine_threads.apply(&cfg.get("tx_engine").unwrap_or_default());
+                    let config_watcher = config_watcher.on_reload(move |cfg| {
+                        engine_threads
+                            .apply(&cfg.get::<TxEngineConfig>("tx_engine").unwrap_or_default());
+                        Ok(())
+                    });
                     let behavior =
-                        StorageBehavior::<Tx>::new(db, engine, &chain_cfg, &net_cfg).await?;
-                    let swarmer =
-                        Swarmer::new(net_cfg.keypair.to_libp2p_keypair(), behavior).await?;
+                        StorageBehavior::<Tx>::new(db, engine, shard_id, &chain_cfg, &net_cfg)
+                            .await?;
+                    let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
                     let mut ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
                     let _miner_peer_id = ctrl
                         .call_with_sender(|swarm, ret| {
@@ -128,6 +200,7 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
                         })
                         .await?
                         .context("Failed to find miner.")?;
+                    let _config_watcher = config_watcher.spawn();
                     ctrl.run_until_interrupt().await?;
                 }
             }
@@ -137,14 +210,17 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
             use slimchain_network::http::config::{NetworkConfig, RaftConfig};
 
             let net_cfg: NetworkConfig = cfg.get("network")?;
+            net_cfg.install_as_global()?;
             let raft_cfg: RaftConfig = cfg.get("raft")?;
 
             match role {
                 Role::Client => {
                     let miner_cfg: MinerConfig = cfg.get("miner")?;
                     info!("Miner Cfg: {:#?}", miner_cfg);
+                    let config_watcher = config_watcher.on_reload(reload_miner_cfg);
                     let mut client: ClientNode<Tx> =
                         ClientNode::new(db, &chain_cfg, &miner_cfg, &net_cfg, &raft_cfg).await?;
+                    let _config_watcher = config_watcher.spawn();
                     info!("Press Ctrl-C to quit.");
                     tokio::signal::ctrl_c().await?;
                     info!("Quitting.");
@@ -152,14 +228,22 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
                 }
                 Role::Storage(_) => {
                     let engine = create_tx_engine(&cfg, &opts.enclave)?;
+                    let engine_threads = engine.thread_limit();
+                    engine_threads.apply(&cfg.get("tx_engine").unwrap_or_default());
+                    let config_watcher = config_watcher.on_reload(move |cfg| {
+                        engine_threads
+                            .apply(&cfg.get::<TxEngineConfig>("tx_engine").unwrap_or_default());
+                        Ok(())
+                    });
                     let mut storage = StorageNode::new(db, engine, &chain_cfg, &net_cfg).await?;
+                    let _config_watcher = config_watcher.spawn();
                     info!("Press Ctrl-C to quit.");
                     tokio::signal::ctrl_c().await?;
                     info!("Quitting.");
                     storage.shutdown().await?;
                 }
-                Role::Miner => {
-                    bail!("Role cannot be miner.");
+                Role::Miner | Role::LightClient | Role::Auditor => {
+                    bail!("Role cannot be miner, light client or auditor.");
                 }
             }
         }
@@ -167,3 +251,9 @@ pub async fn node_main<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'st
 
     Ok(())
 }
+
+fn reload_miner_cfg(cfg: &Config) -> Result<()> {
+    let miner_cfg: MinerConfig = cfg.get("miner")?;
+    MinerConfig::reload_max_block_interval(miner_cfg.max_block_interval);
+    Ok(())
+}
@@ -1,13 +1,15 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     access_map::AccessMap,
-    block::BlockTrait,
+    block::{median_time_past, BlockTrait},
     db::{DBPtr, Transaction},
+    genesis::genesis_state_update,
     latest::{LatestBlockHeader, LatestBlockHeaderPtr},
     snapshot::load_recent_blocks,
 };
 use slimchain_common::{
-    basic::BlockHeight,
+    basic::{BlockHeight, ShardId},
     error::{Context as _, Result},
 };
 
@@ -56,6 +58,10 @@ impl<Block: BlockTrait> Snapshot<Block> {
         }
     }
 
+    pub fn median_time_past(&self, span: usize) -> Option<DateTime<Utc>> {
+        median_time_past(self.recent_blocks.iter().rev().take(span.max(1)))
+    }
+
     pub fn commit_block(&mut self, block: Block) {
         self.recent_blocks.push_back(block);
     }
@@ -100,6 +106,13 @@ impl<Block: BlockTrait + for<'de> Deserialize<'de>> Snapshot<Block> {
             assert_eq!(height, access_map.latest_block_height());
             Ok(Self::new(recent_blocks, access_map))
         } else {
+            // All nodes maintain the full state in the baseline.
+            if let Some(update) = genesis_state_update(ShardId::default()) {
+                let mut tx = Transaction::new();
+                tx.update_state(&update)?;
+                db.write_sync(tx)
+                    .context("Failed to write genesis state to the database.")?;
+            }
             let genesis_block = Block::genesis_block();
             Ok(Self::genesis_snapshot(genesis_block, state_len))
         }
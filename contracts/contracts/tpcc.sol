@@ -0,0 +1,148 @@
+pragma solidity >=0.4.0 <0.7.0;
+
+// A TPC-C-like workload with the new-order and payment transactions.
+// The tables are keyed by the packed ids. The stock is lazily initialized.
+contract TPCC {
+    uint256 constant DISTRICTS_PER_WAREHOUSE = 10;
+    uint256 constant CUSTOMERS_PER_DISTRICT = 3000;
+    uint256 constant ITEMS = 100000;
+    uint256 constant INITIAL_STOCK_QUANTITY = 100;
+
+    // warehouse
+    mapping(uint256 => uint256) warehouseYtd;
+
+    // district
+    mapping(uint256 => uint256) districtYtd;
+    mapping(uint256 => uint256) districtNextOrderId;
+
+    // customer
+    mapping(uint256 => int256) customerBalance;
+    mapping(uint256 => uint256) customerYtdPayment;
+    mapping(uint256 => uint256) customerPaymentCnt;
+    mapping(uint256 => uint256) customerLastOrderId;
+
+    // stock
+    mapping(uint256 => uint256) stockQuantity;
+    mapping(uint256 => uint256) stockYtd;
+    mapping(uint256 => uint256) stockOrderCnt;
+    mapping(uint256 => uint256) stockRemoteCnt;
+
+    // order
+    mapping(uint256 => uint256) orderCustomer;
+    mapping(uint256 => uint256) orderLineCnt;
+    mapping(uint256 => bool) orderAllLocal;
+
+    function districtKey(uint256 w_id, uint256 d_id)
+        internal
+        pure
+        returns (uint256)
+    {
+        return w_id * DISTRICTS_PER_WAREHOUSE + d_id;
+    }
+
+    function customerKey(
+        uint256 w_id,
+        uint256 d_id,
+        uint256 c_id
+    ) internal pure returns (uint256) {
+        return districtKey(w_id, d_id) * CUSTOMERS_PER_DISTRICT + c_id;
+    }
+
+    function stockKey(uint256 w_id, uint256 i_id)
+        internal
+        pure
+        returns (uint256)
+    {
+        return w_id * ITEMS + i_id;
+    }
+
+    function orderKey(
+        uint256 w_id,
+        uint256 d_id,
+        uint256 o_id
+    ) internal pure returns (uint256) {
+        return (districtKey(w_id, d_id) << 64) | o_id;
+    }
+
+    function newOrder(
+        uint256 w_id,
+        uint256 d_id,
+        uint256 c_id,
+        uint256[] memory item_ids,
+        uint256[] memory supply_w_ids,
+        uint256[] memory quantities
+    ) public {
+        require(
+            item_ids.length == supply_w_ids.length &&
+                item_ids.length == quantities.length,
+            "mismatched order lines"
+        );
+
+        uint256 d_key = districtKey(w_id, d_id);
+        uint256 o_id = districtNextOrderId[d_key] + 1;
+        districtNextOrderId[d_key] = o_id;
+
+        bool all_local = true;
+        for (uint256 i = 0; i < item_ids.length; i++) {
+            uint256 s_key = stockKey(supply_w_ids[i], item_ids[i]);
+            uint256 quantity = stockQuantity[s_key];
+            if (quantity == 0) {
+                quantity = INITIAL_STOCK_QUANTITY;
+            }
+            if (quantity >= quantities[i] + 10) {
+                quantity -= quantities[i];
+            } else {
+                quantity = quantity + 91 - quantities[i];
+            }
+            stockQuantity[s_key] = quantity;
+            stockYtd[s_key] += quantities[i];
+            stockOrderCnt[s_key] += 1;
+            if (supply_w_ids[i] != w_id) {
+                stockRemoteCnt[s_key] += 1;
+                all_local = false;
+            }
+        }
+
+        uint256 o_key = orderKey(w_id, d_id, o_id);
+        orderCustomer[o_key] = c_id;
+        orderLineCnt[o_key] = item_ids.length;
+        orderAllLocal[o_key] = all_local;
+        customerLastOrderId[customerKey(w_id, d_id, c_id)] = o_id;
+    }
+
+    function payment(
+        uint256 w_id,
+        uint256 d_id,
+        uint256 c_w_id,
+        uint256 c_d_id,
+        uint256 c_id,
+        uint256 amount
+    ) public {
+        warehouseYtd[w_id] += amount;
+        districtYtd[districtKey(w_id, d_id)] += amount;
+
+        uint256 c_key = customerKey(c_w_id, c_d_id, c_id);
+        customerBalance[c_key] -= int256(amount);
+        customerYtdPayment[c_key] += amount;
+        customerPaymentCnt[c_key] += 1;
+    }
+
+    function orderStatus(
+        uint256 w_id,
+        uint256 d_id,
+        uint256 c_id
+    )
+        public
+        view
+        returns (
+            int256 balance,
+            uint256 o_id,
+            uint256 ol_cnt
+        )
+    {
+        uint256 c_key = customerKey(w_id, d_id, c_id);
+        balance = customerBalance[c_key];
+        o_id = customerLastOrderId[c_key];
+        ol_cnt = orderLineCnt[orderKey(w_id, d_id, o_id)];
+    }
+}
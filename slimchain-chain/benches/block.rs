@@ -0,0 +1,325 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    prelude::*,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use slimchain_chain::{
+    behavior::{
+        commit_block, commit_block_storage_node, propose_block, verify_block, TxExecuteStream,
+    },
+    block_proposal::BlockProposal,
+    config::{ChainConfig, DBConfig, MinerConfig},
+    conflict_check::ConflictCheck,
+    consensus::{
+        raft::{create_new_block, verify_consensus, Block},
+        Consensus,
+    },
+    db::{DBPtr, InMemoryDb},
+    genesis::genesis_chain_id,
+    latest::{LatestBlockHeaderPtr, LatestTxCount},
+    snapshot::Snapshot,
+    tx_ordering::TxOrdering,
+};
+use slimchain_common::{
+    basic::{Address, ShardId, U256},
+    ed25519::Keypair,
+    tx::SignedTx,
+    tx_req::{caller_address_from_pk, SignedTxRequest, TxRequest},
+};
+use slimchain_tx_engine::{TxEngine, TxEngineWorker, TxTaskId};
+use slimchain_tx_engine_simple::SimpleTxEngineWorker;
+use slimchain_tx_state::{StorageTxTrie, TxTrie, TxTrieTrait};
+use slimchain_utils::contract::{contract_address, Contract, Token};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::runtime::Runtime;
+
+// The contract is filled with 1k values before the benchmarks.
+const STATE_SIZE: usize = 1_000;
+const FILL_BLOCK_TXS: usize = 250;
+const BLOCK_SIZES: [usize; 2] = [10, 100];
+
+struct Node<T: TxTrieTrait> {
+    db: DBPtr,
+    snapshot: Snapshot<Block, T>,
+    blk_latest: LatestBlockHeaderPtr,
+    tx_latest: Arc<LatestTxCount>,
+}
+
+struct Fixture {
+    rng: StdRng,
+    chain_cfg: ChainConfig,
+    contract: Contract,
+    contract_address: Address,
+    req_tx: UnboundedSender<SignedTxRequest>,
+    tx_rx: TxExecuteStream<SignedTx, UnboundedReceiver<SignedTxRequest>>,
+    miner: Node<TxTrie>,
+    client: Node<TxTrie>,
+    storage: Node<StorageTxTrie>,
+    num_calls: usize,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let chain_cfg = ChainConfig {
+            conflict_check: ConflictCheck::SSI,
+            state_len: 2,
+            consensus: Consensus::Raft,
+            digest: Default::default(),
+            shard_assignment: Default::default(),
+            median_time_span: 11,
+            max_future_drift: Duration::from_secs(30),
+        };
+        let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("contracts/build/contracts/SimpleStorage.json");
+        let contract = Contract::from_json_file(&contract_file).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(1u64);
+        let deployer = Keypair::generate(&mut rng);
+        let contract_address = contract_address(
+            caller_address_from_pk(&deployer.public),
+            U256::from(0).into(),
+        );
+
+        let miner_db = InMemoryDb::new()
+            .open_with_config(&DBConfig::default())
+            .unwrap();
+        let miner_snapshot =
+            Snapshot::<Block, TxTrie>::load_from_db(&miner_db, chain_cfg.state_len).unwrap();
+        let client_db = InMemoryDb::new()
+            .open_with_config(&DBConfig::default())
+            .unwrap();
+        let client_snapshot =
+            Snapshot::<Block, TxTrie>::load_from_db(&client_db, chain_cfg.state_len).unwrap();
+        let storage_db = InMemoryDb::new()
+            .open_with_config(&DBConfig::default())
+            .unwrap();
+        let storage_snapshot = Snapshot::<Block, StorageTxTrie>::load_from_db(
+            &storage_db,
+            chain_cfg.state_len,
+            ShardId::default(),
+        )
+        .unwrap();
+
+        let miner = Node {
+            blk_latest: miner_snapshot.to_latest_block_header(),
+            db: miner_db,
+            snapshot: miner_snapshot,
+            tx_latest: LatestTxCount::new(0),
+        };
+        let client = Node {
+            blk_latest: client_snapshot.to_latest_block_header(),
+            db: client_db,
+            snapshot: client_snapshot,
+            tx_latest: LatestTxCount::new(0),
+        };
+        let storage = Node {
+            blk_latest: storage_snapshot.to_latest_block_header(),
+            db: storage_db,
+            snapshot: storage_snapshot,
+            tx_latest: LatestTxCount::new(0),
+        };
+
+        let engine = TxEngine::new(2, || {
+            let mut rng = StdRng::seed_from_u64(1u64);
+            Box::new(SimpleTxEngineWorker::new(Keypair::generate(&mut rng)))
+        });
+        let (req_tx, req_rx) = unbounded();
+        let tx_rx = TxExecuteStream::new(req_rx, engine, &storage.db, &storage.blk_latest);
+
+        let mut fixture = Self {
+            rng,
+            chain_cfg,
+            contract,
+            contract_address,
+            req_tx,
+            tx_rx,
+            miner,
+            client,
+            storage,
+            num_calls: 0,
+        };
+
+        let deploy = TxRequest::Create {
+            nonce: U256::from(0).into(),
+            code: fixture.contract.code().clone(),
+            chain_id: 0,
+            expiry: None,
+        }
+        .sign(&deployer);
+        fixture.commit(vec![deploy]).await;
+        while fixture.num_calls < STATE_SIZE {
+            let tx_reqs = fixture.create_calls(FILL_BLOCK_TXS);
+            fixture.commit(tx_reqs).await;
+        }
+        fixture
+    }
+
+    // Each call is sent from a new account, so that the txs in one block do not conflict.
+    fn create_calls(&mut self, num: usize) -> Vec<SignedTxRequest> {
+        (0..num)
+            .map(|_| {
+                let key = U256::from(self.num_calls);
+                self.num_calls += 1;
+                TxRequest::Call {
+                    address: self.contract_address,
+                    nonce: U256::from(0).into(),
+                    data: self
+                        .contract
+                        .encode_tx_input("set", &[Token::Uint(key), Token::Uint(key)])
+                        .unwrap(),
+                    chain_id: 0,
+                    expiry: None,
+                }
+                .sign(&Keypair::generate(&mut self.rng))
+            })
+            .collect()
+    }
+
+    // Propose a block on top of the latest one, without committing it.
+    async fn propose(&mut self, tx_reqs: Vec<SignedTxRequest>) -> BlockProposal<Block, SignedTx> {
+        let miner_cfg = MinerConfig {
+            compress_trie: true,
+            max_txs: tx_reqs.len(),
+            min_txs: tx_reqs.len(),
+            max_block_interval: Duration::from_secs(60),
+            tx_ordering: TxOrdering::FCFS,
+            pipeline: false,
+            max_tx_age: None,
+        };
+        for tx_req in tx_reqs {
+            self.req_tx.send(tx_req).await.unwrap();
+        }
+        let mut snapshot = self.miner.snapshot.clone();
+        propose_block(
+            &self.chain_cfg,
+            &miner_cfg,
+            &mut snapshot,
+            &mut self.tx_rx,
+            create_new_block,
+        )
+        .await
+        .unwrap()
+        .unwrap()
+    }
+
+    async fn commit(&mut self, tx_reqs: Vec<SignedTxRequest>) {
+        let blk_proposal = self.propose(tx_reqs).await;
+        // The miner catches up by verifying its own block, as the proposal is made on a copy.
+        for node in [&mut self.miner, &mut self.client] {
+            verify_block(
+                &self.chain_cfg,
+                &mut node.snapshot,
+                &blk_proposal,
+                verify_consensus,
+            )
+            .await
+            .unwrap();
+            commit_block(&blk_proposal, &node.db, &node.blk_latest, &node.tx_latest)
+                .await
+                .unwrap();
+        }
+        let storage_update = verify_block(
+            &self.chain_cfg,
+            &mut self.storage.snapshot,
+            &blk_proposal,
+            verify_consensus,
+        )
+        .await
+        .unwrap();
+        commit_block_storage_node(
+            &blk_proposal,
+            &storage_update,
+            &self.storage.db,
+            &self.storage.blk_latest,
+            &self.storage.tx_latest,
+        )
+        .await
+        .unwrap();
+    }
+}
+
+fn bench_tx_execute(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut fixture = rt.block_on(Fixture::new());
+    let tx_req = fixture.create_calls(1).remove(0);
+    let (height, timestamp, state_root) = fixture
+        .storage
+        .blk_latest
+        .get_height_timestamp_and_state_root();
+    let worker = SimpleTxEngineWorker::new(Keypair::generate(&mut fixture.rng));
+
+    c.bench_function("tx_execute", |b| {
+        b.iter(|| {
+            black_box(
+                worker
+                    .execute(
+                        TxTaskId::next_id(),
+                        genesis_chain_id(),
+                        height,
+                        timestamp,
+                        fixture.storage.db.clone(),
+                        state_root,
+                        tx_req.clone(),
+                    )
+                    .unwrap(),
+            )
+        })
+    });
+}
+
+fn bench_verify_block(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut fixture = rt.block_on(Fixture::new());
+
+    let mut group = c.benchmark_group("verify_block");
+    for &size in &BLOCK_SIZES {
+        let tx_reqs = fixture.create_calls(size);
+        let blk_proposal = rt.block_on(fixture.propose(tx_reqs));
+
+        group.bench_with_input(
+            BenchmarkId::new("client", size),
+            &blk_proposal,
+            |b, blk_proposal| {
+                b.iter_batched(
+                    || fixture.client.snapshot.clone(),
+                    |mut snapshot| {
+                        rt.block_on(verify_block(
+                            &fixture.chain_cfg,
+                            &mut snapshot,
+                            blk_proposal,
+                            verify_consensus,
+                        ))
+                        .unwrap()
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("storage", size),
+            &blk_proposal,
+            |b, blk_proposal| {
+                b.iter_batched(
+                    || fixture.storage.snapshot.clone(),
+                    |mut snapshot| {
+                        rt.block_on(verify_block(
+                            &fixture.chain_cfg,
+                            &mut snapshot,
+                            blk_proposal,
+                            verify_consensus,
+                        ))
+                        .unwrap()
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tx_execute, bench_verify_block);
+criterion_main!(benches);
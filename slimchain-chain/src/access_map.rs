@@ -1,7 +1,8 @@
+use crate::config::AccessMapConfig;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{Address, BlockDistance, BlockHeight},
-    rw_set::{TxReadSet, TxWriteData},
+    rw_set::{AccountWriteData, TxReadSet, TxWriteData},
 };
 
 pub mod block_height_list;
@@ -13,6 +14,12 @@ pub use acc_access_map::*;
 pub mod acc_rev_access_map;
 pub use acc_rev_access_map::*;
 
+pub mod bloom;
+pub use bloom::*;
+
+pub mod delta;
+pub use delta::*;
+
 pub mod pruning;
 pub use pruning::*;
 
@@ -24,10 +31,39 @@ pub struct AccessMap {
     write_map: imbl::Vector<WriteAccessItem>,
     read_rev_map: imbl::HashMap<Address, ReadRevAccessItem>,
     write_rev_map: imbl::OrdMap<Address, WriteRevAccessItem>,
+    // If set, the read sets are kept in the bloom filters instead of `read_rev_map`. The write
+    // sets are always exact, since they are required to prune the tx trie.
+    read_blooms: Option<ReadBlooms>,
 }
 
 impl AccessMap {
     pub fn new(max_blocks: usize) -> Self {
+        let cfg = AccessMapConfig::get();
+        if cfg.bloom {
+            Self::with_read_blooms(
+                max_blocks,
+                BloomParams::new(cfg.bloom_items, cfg.bloom_fp_rate),
+            )
+        } else {
+            Self::new_exact(max_blocks)
+        }
+    }
+
+    pub fn with_read_blooms(max_blocks: usize, params: BloomParams) -> Self {
+        let mut map = Self::new_exact(max_blocks);
+        map.read_blooms = Some(ReadBlooms::new(params));
+        map
+    }
+
+    // The access map whose oldest block is at `block_height`, e.g., to be rebuilt from the blocks
+    // after it.
+    pub fn new_at(max_blocks: usize, block_height: BlockHeight) -> Self {
+        let mut map = Self::new(max_blocks);
+        map.block_height = block_height;
+        map
+    }
+
+    fn new_exact(max_blocks: usize) -> Self {
         Self {
             max_blocks,
             block_height: 0.into(),
@@ -35,6 +71,7 @@ impl AccessMap {
             write_map: imbl::vector![WriteAccessItem::default()],
             read_rev_map: imbl::HashMap::new(),
             write_rev_map: imbl::OrdMap::new(),
+            read_blooms: None,
         }
     }
 
@@ -55,13 +92,51 @@ impl AccessMap {
         self.write_rev_map.get(&acc_addr)
     }
 
+    pub fn read_conflicts_in_write_set(
+        &self,
+        acc_addr: Address,
+        block_height: BlockHeight,
+        acc_write: &AccountWriteData,
+    ) -> bool {
+        match self.read_blooms.as_ref() {
+            Some(blooms) => blooms
+                .rev_access_item(self.oldest_block_height(), acc_addr)
+                .has_conflict_in_write_set(block_height, acc_write),
+            None => self.get_read_rev(acc_addr).map_or(false, |entry| {
+                entry.has_conflict_in_write_set(block_height, acc_write)
+            }),
+        }
+    }
+
     pub fn alloc_new_block(&mut self) {
         self.block_height = self.block_height.next_height();
         self.read_map.push_back(ReadAccessItem::default());
         self.write_map.push_back(WriteAccessItem::default());
+        if let Some(blooms) = self.read_blooms.as_mut() {
+            blooms.alloc_new_block();
+        }
     }
 
     pub fn add_read(&mut self, reads: &TxReadSet) {
+        if let Some(blooms) = self.read_blooms.as_mut() {
+            let bloom = blooms.latest_bloom_mut();
+            for (&acc_addr, read) in reads.iter() {
+                if read.get_nonce() {
+                    bloom.insert(AccessKey::Nonce(acc_addr));
+                }
+
+                if read.get_code() {
+                    bloom.insert(AccessKey::Code(acc_addr));
+                }
+
+                for &key in read.get_values().iter() {
+                    bloom.insert(AccessKey::Values(acc_addr));
+                    bloom.insert(AccessKey::Value(acc_addr, key));
+                }
+            }
+            return;
+        }
+
         let read_entry = self
             .read_map
             .back_mut()
@@ -130,6 +205,10 @@ impl AccessMap {
 
         let old_block_height = self.oldest_block_height();
 
+        if let Some(blooms) = self.read_blooms.as_mut() {
+            blooms.remove_oldest_block();
+        }
+
         let read_entry = self
             .read_map
             .pop_front()
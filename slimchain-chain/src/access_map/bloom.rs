@@ -0,0 +1,221 @@
+use super::RevAccessItem;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{Address, BlockHeight, StateKey, H256},
+    digest::{blake2b_hash_to_h256, default_blake2},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BloomParams {
+    num_bits: usize,
+    num_hashes: u32,
+}
+
+impl BloomParams {
+    // Ref: https://en.wikipedia.org/wiki/Bloom_filter#Optimal_number_of_hash_functions
+    pub fn new(num_items: usize, fp_rate: f64) -> Self {
+        let num_items = num_items.max(1) as f64;
+        let fp_rate = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-num_items * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.) as usize;
+        let num_hashes = (num_bits as f64 / num_items * ln2).round().max(1.) as u32;
+        Self {
+            num_bits,
+            num_hashes,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AccessKey {
+    Nonce(Address),
+    Code(Address),
+    // Any value of the account has been accessed.
+    Values(Address),
+    Value(Address, StateKey),
+}
+
+impl AccessKey {
+    fn to_hash(self) -> H256 {
+        let mut hash_state = default_blake2().to_state();
+        match self {
+            Self::Nonce(acc_addr) => {
+                hash_state.update(b"n");
+                hash_state.update(acc_addr.as_bytes());
+            }
+            Self::Code(acc_addr) => {
+                hash_state.update(b"c");
+                hash_state.update(acc_addr.as_bytes());
+            }
+            Self::Values(acc_addr) => {
+                hash_state.update(b"s");
+                hash_state.update(acc_addr.as_bytes());
+            }
+            Self::Value(acc_addr, key) => {
+                hash_state.update(b"v");
+                hash_state.update(acc_addr.as_bytes());
+                hash_state.update(key.as_bytes());
+            }
+        }
+        let hash = hash_state.finalize();
+        blake2b_hash_to_h256(hash)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AccessBloom {
+    bits: Vec<u64>,
+    num_bits: usize,
+    num_hashes: u32,
+}
+
+impl AccessBloom {
+    pub fn new(params: BloomParams) -> Self {
+        Self {
+            bits: vec![0; params.num_bits.div_ceil(64)],
+            num_bits: params.num_bits,
+            num_hashes: params.num_hashes,
+        }
+    }
+
+    // Use double hashing to derive the bit positions.
+    fn bit_positions(&self, key: AccessKey) -> impl Iterator<Item = usize> {
+        let hash = key.to_hash();
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&hash[..8]);
+        let h1 = u64::from_le_bytes(buf);
+        buf.copy_from_slice(&hash[8..16]);
+        let h2 = u64::from_le_bytes(buf);
+        let num_bits = self.num_bits as u64;
+        (0..self.num_hashes as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+
+    pub fn insert(&mut self, key: AccessKey) {
+        for pos in self.bit_positions(key) {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    pub fn contains(&self, key: AccessKey) -> bool {
+        self.bit_positions(key)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+}
+
+// The read sets of the recent blocks kept in the bloom filters, one for each block.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReadBlooms {
+    params: BloomParams,
+    blooms: imbl::Vector<AccessBloom>,
+}
+
+impl ReadBlooms {
+    pub fn new(params: BloomParams) -> Self {
+        Self {
+            params,
+            blooms: imbl::vector![AccessBloom::new(params)],
+        }
+    }
+
+    pub fn alloc_new_block(&mut self) {
+        self.blooms.push_back(AccessBloom::new(self.params));
+    }
+
+    pub fn remove_oldest_block(&mut self) {
+        self.blooms.pop_front();
+    }
+
+    pub(crate) fn latest_blooms(&self, n: usize) -> Vec<AccessBloom> {
+        let skip = self.blooms.len().saturating_sub(n);
+        self.blooms.iter().skip(skip).cloned().collect()
+    }
+
+    pub(crate) fn replace_blooms(&mut self, removed: usize, blooms: &[AccessBloom]) {
+        for _ in 0..removed {
+            self.blooms.pop_front();
+        }
+        self.blooms.extend(blooms.iter().cloned());
+    }
+
+    pub fn latest_bloom_mut(&mut self) -> &mut AccessBloom {
+        self.blooms
+            .back_mut()
+            .expect("ReadBlooms: Failed to access blooms.")
+    }
+
+    pub fn rev_access_item(
+        &self,
+        oldest_block_height: BlockHeight,
+        acc_addr: Address,
+    ) -> BloomRevAccessItem<'_> {
+        BloomRevAccessItem {
+            blooms: &self.blooms,
+            oldest_block_height,
+            acc_addr,
+        }
+    }
+}
+
+// A view of the bloom filters for one account, which may report false conflicts.
+pub struct BloomRevAccessItem<'a> {
+    blooms: &'a imbl::Vector<AccessBloom>,
+    oldest_block_height: BlockHeight,
+    acc_addr: Address,
+}
+
+impl<'a> BloomRevAccessItem<'a> {
+    // Whether `key` is accessed in any block after `block_height`.
+    fn key_conflicts_with(&self, block_height: BlockHeight, key: AccessKey) -> bool {
+        let skip = (block_height - self.oldest_block_height).0 + 1;
+        self.blooms
+            .iter()
+            .skip(skip.max(0) as usize)
+            .any(|bloom| bloom.contains(key))
+    }
+}
+
+impl<'a> RevAccessItem for BloomRevAccessItem<'a> {
+    fn nonce_conflicts_with(&self, block_height: BlockHeight) -> bool {
+        self.key_conflicts_with(block_height, AccessKey::Nonce(self.acc_addr))
+    }
+
+    fn code_conflicts_with(&self, block_height: BlockHeight) -> bool {
+        self.key_conflicts_with(block_height, AccessKey::Code(self.acc_addr))
+    }
+
+    fn reset_values_conflict_with(&self, block_height: BlockHeight) -> bool {
+        self.key_conflicts_with(block_height, AccessKey::Values(self.acc_addr))
+    }
+
+    fn value_conflicts_with(&self, block_height: BlockHeight, key: StateKey) -> bool {
+        self.key_conflicts_with(block_height, AccessKey::Value(self.acc_addr, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::basic::H160;
+
+    fn addr(i: u64) -> Address {
+        H160::from_low_u64_be(i).into()
+    }
+
+    #[test]
+    fn test_access_bloom() {
+        let params = BloomParams::new(100, 0.01);
+        let mut bloom = AccessBloom::new(params);
+        for i in 0..100u64 {
+            bloom.insert(AccessKey::Nonce(addr(i)));
+        }
+        for i in 0..100u64 {
+            assert!(bloom.contains(AccessKey::Nonce(addr(i))));
+        }
+        let false_positives = (100..10_100u64)
+            .filter(|&i| bloom.contains(AccessKey::Nonce(addr(i))))
+            .count();
+        assert!(false_positives < 300);
+        assert!(!bloom.contains(AccessKey::Code(addr(1))));
+    }
+}
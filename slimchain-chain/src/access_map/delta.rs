@@ -0,0 +1,133 @@
+use super::*;
+use slimchain_common::error::{ensure, Result};
+
+// The changes of an access map since an older version of it.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AccessMapDelta {
+    block_height: BlockHeight,
+    // The number of the oldest blocks removed from the older access map.
+    removed_blocks: usize,
+    read_items: Vec<ReadAccessItem>,
+    write_items: Vec<WriteAccessItem>,
+    read_rev_updates: Vec<(Address, Option<ReadRevAccessItem>)>,
+    write_rev_updates: Vec<(Address, Option<WriteRevAccessItem>)>,
+    read_blooms: Option<Vec<AccessBloom>>,
+}
+
+fn latest_items<T: Clone>(items: &imbl::Vector<T>, n: usize) -> Vec<T> {
+    let skip = items.len().saturating_sub(n);
+    items.iter().skip(skip).cloned().collect()
+}
+
+impl AccessMap {
+    pub fn diff(&self, older: &AccessMap) -> Result<AccessMapDelta> {
+        ensure!(
+            self.max_blocks == older.max_blocks,
+            "AccessMap#diff: Mismatched max blocks."
+        );
+        ensure!(
+            self.read_blooms.is_some() == older.read_blooms.is_some(),
+            "AccessMap#diff: Mismatched bloom mode."
+        );
+        ensure!(
+            self.block_height >= older.block_height,
+            "AccessMap#diff: The older access map is newer."
+        );
+
+        let new_blocks = (self.block_height - older.block_height).0 as usize;
+        let kept_blocks = self.read_map.len().saturating_sub(new_blocks);
+        let removed_blocks = older.read_map.len() - kept_blocks.min(older.read_map.len());
+
+        let mut read_rev_updates: Vec<_> = self
+            .read_rev_map
+            .iter()
+            .filter(|(addr, item)| older.read_rev_map.get(*addr) != Some(*item))
+            .map(|(addr, item)| (*addr, Some(item.clone())))
+            .collect();
+        read_rev_updates.extend(
+            older
+                .read_rev_map
+                .keys()
+                .filter(|addr| !self.read_rev_map.contains_key(*addr))
+                .map(|addr| (*addr, None)),
+        );
+
+        let mut write_rev_updates: Vec<_> = self
+            .write_rev_map
+            .iter()
+            .filter(|(addr, item)| older.write_rev_map.get(*addr) != Some(*item))
+            .map(|(addr, item)| (*addr, Some(item.clone())))
+            .collect();
+        write_rev_updates.extend(
+            older
+                .write_rev_map
+                .keys()
+                .filter(|addr| !self.write_rev_map.contains_key(*addr))
+                .map(|addr| (*addr, None)),
+        );
+
+        Ok(AccessMapDelta {
+            block_height: self.block_height,
+            removed_blocks,
+            read_items: latest_items(&self.read_map, new_blocks),
+            write_items: latest_items(&self.write_map, new_blocks),
+            read_rev_updates,
+            write_rev_updates,
+            read_blooms: self
+                .read_blooms
+                .as_ref()
+                .map(|blooms| blooms.latest_blooms(new_blocks)),
+        })
+    }
+
+    pub fn apply_delta(&mut self, delta: &AccessMapDelta) -> Result<()> {
+        ensure!(
+            delta.removed_blocks <= self.read_map.len(),
+            "AccessMap#apply_delta: Too many blocks to remove."
+        );
+        ensure!(
+            self.read_blooms.is_some() == delta.read_blooms.is_some(),
+            "AccessMap#apply_delta: Mismatched bloom mode."
+        );
+
+        for _ in 0..delta.removed_blocks {
+            self.read_map.pop_front();
+            self.write_map.pop_front();
+        }
+        self.read_map.extend(delta.read_items.iter().cloned());
+        self.write_map.extend(delta.write_items.iter().cloned());
+        if let (Some(blooms), Some(new_blooms)) =
+            (self.read_blooms.as_mut(), delta.read_blooms.as_ref())
+        {
+            blooms.replace_blooms(delta.removed_blocks, new_blooms);
+        }
+        self.block_height = delta.block_height;
+
+        for (addr, item) in &delta.read_rev_updates {
+            match item {
+                Some(item) => {
+                    self.read_rev_map.insert(*addr, item.clone());
+                }
+                None => {
+                    self.read_rev_map.remove(addr);
+                }
+            }
+        }
+        for (addr, item) in &delta.write_rev_updates {
+            match item {
+                Some(item) => {
+                    self.write_rev_map.insert(*addr, item.clone());
+                }
+                None => {
+                    self.write_rev_map.remove(addr);
+                }
+            }
+        }
+
+        ensure!(
+            !self.read_map.is_empty() && self.read_map.len() <= self.max_blocks + 1,
+            "AccessMap#apply_delta: Invalid number of blocks."
+        );
+        Ok(())
+    }
+}
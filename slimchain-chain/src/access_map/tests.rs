@@ -113,3 +113,61 @@ fn test_access_map3() {
     assert_eq!(prune.accounts.len(), 1);
     assert_eq!(prune.values.len(), 0);
 }
+
+fn check_delta(mut map: AccessMap) {
+    let write_set1 = create_tx_write_set! {
+        "0000000000000000000000000000000000000000" => {
+            nonce: 1,
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000000" => 1,
+            }
+        },
+    };
+    let write_set2 = create_tx_write_set! {
+        "0000000000000000000000000000000000000001" => {
+            code: b"code",
+        },
+    };
+    let read_set = create_tx_read_set! {
+        "0000000000000000000000000000000000000002" => {
+            nonce: true,
+            values: [
+                "0000000000000000000000000000000000000000000000000000000000000001",
+            ]
+        },
+    };
+
+    map.alloc_new_block();
+    map.add_read(&read_set);
+    map.add_write(&write_set1);
+    let _ = map.remove_oldest_block();
+    let older = map.clone();
+
+    map.alloc_new_block();
+    map.add_write(&write_set2);
+    let _ = map.remove_oldest_block();
+    map.alloc_new_block();
+    map.add_write(&write_set1);
+    let _ = map.remove_oldest_block();
+
+    let delta = map.diff(&older).unwrap();
+    let mut applied = older.clone();
+    applied.apply_delta(&delta).unwrap();
+    assert_eq!(map, applied);
+    assert!(older.diff(&map).is_err());
+
+    let delta = map.diff(&map).unwrap();
+    let mut applied = map.clone();
+    applied.apply_delta(&delta).unwrap();
+    assert_eq!(map, applied);
+}
+
+#[test]
+fn test_access_map_delta() {
+    check_delta(AccessMap::new(2));
+}
+
+#[test]
+fn test_access_map_delta_with_bloom() {
+    check_delta(AccessMap::with_read_blooms(2, BloomParams::new(16, 0.01)));
+}
@@ -0,0 +1,195 @@
+use crate::{
+    block::BlockTrait,
+    config::PruneConfig,
+    db::{decode_tx, DBPtr, Transaction},
+    loader::BlockLoaderTrait,
+};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    collections::HashMap,
+    error::{anyhow, ensure, Context as _, Result},
+    tx::TxTrait,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+// The cold storage of the pruned tx bodies. The txs are kept in the same bytes as in the database,
+// i.e., the compressed canonical encoding.
+pub trait TxArchive: Send + Sync {
+    fn archive_txs(&self, txs: &[(H256, Vec<u8>)]) -> Result<()>;
+    fn get_tx_bytes(&self, tx_hash: H256) -> Result<Option<Vec<u8>>>;
+}
+
+struct FileTxArchiveInner {
+    file: File,
+    // tx_hash -> (offset, len)
+    index: HashMap<H256, (u64, usize)>,
+}
+
+// An append-only archive file. Each record is stored as `tx_hash || len (u32 LE) || bytes`.
+pub struct FileTxArchive(Mutex<FileTxArchiveInner>);
+
+impl FileTxArchive {
+    pub fn open_or_create(path: &Path) -> Result<Self> {
+        info!("Open tx archive at {}", path.display());
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}.", path.display()))?;
+
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        let mut index = HashMap::new();
+        let mut pos = 0;
+        while pos < buf.len() {
+            ensure!(pos + 36 <= buf.len(), "Corrupted tx archive.");
+            let tx_hash = H256::from_slice(&buf[pos..pos + 32]);
+            let mut len_bytes = [0u8; 4];
+            len_bytes.copy_from_slice(&buf[pos + 32..pos + 36]);
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            pos += 36;
+            ensure!(pos + len <= buf.len(), "Corrupted tx archive.");
+            index.insert(tx_hash, (pos as u64, len));
+            pos += len;
+        }
+
+        Ok(Self(Mutex::new(FileTxArchiveInner { file, index })))
+    }
+}
+
+impl TxArchive for FileTxArchive {
+    fn archive_txs(&self, txs: &[(H256, Vec<u8>)]) -> Result<()> {
+        let mut inner = self.0.lock().expect("Failed to lock FileTxArchive.");
+        let mut offset = inner.file.seek(SeekFrom::End(0))?;
+        let mut buf = Vec::new();
+        let mut new_entries = Vec::with_capacity(txs.len());
+        for (tx_hash, bytes) in txs {
+            buf.extend_from_slice(tx_hash.as_bytes());
+            buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(bytes);
+            offset += 36;
+            new_entries.push((*tx_hash, (offset, bytes.len())));
+            offset += bytes.len() as u64;
+        }
+        inner.file.write_all(&buf)?;
+        inner.file.sync_data()?;
+        inner.index.extend(new_entries);
+        Ok(())
+    }
+
+    fn get_tx_bytes(&self, tx_hash: H256) -> Result<Option<Vec<u8>>> {
+        let mut inner = self.0.lock().expect("Failed to lock FileTxArchive.");
+        let (offset, len) = match inner.index.get(&tx_hash) {
+            Some(&entry) => entry,
+            None => return Ok(None),
+        };
+        let mut buf = vec![0u8; len];
+        inner.file.seek(SeekFrom::Start(offset))?;
+        inner.file.read_exact(&mut buf)?;
+        Ok(Some(buf))
+    }
+}
+
+static GLOBAL_TX_ARCHIVE: OnceCell<Box<dyn TxArchive>> = OnceCell::new();
+
+pub fn install_tx_archive(archive: Box<dyn TxArchive>) -> Result<()> {
+    GLOBAL_TX_ARCHIVE
+        .set(archive)
+        .map_err(|_| anyhow!("Failed to set TxArchive."))
+}
+
+pub fn get_archived_tx<Tx: TxTrait>(tx_hash: H256) -> Result<Option<Tx>> {
+    match GLOBAL_TX_ARCHIVE.get() {
+        Some(archive) => archive
+            .get_tx_bytes(tx_hash)?
+            .map(|bytes| decode_tx(&bytes))
+            .transpose(),
+        None => Ok(None),
+    }
+}
+
+// Remove the tx bodies of the block which falls out of the prune depth after committing the block
+// at `height`. The block itself (i.e., the header) is retained.
+pub fn prune_tx_bodies<Block: BlockTrait + for<'de> Deserialize<'de>>(
+    db: &DBPtr,
+    db_tx: &mut Transaction,
+    height: BlockHeight,
+) -> Result<()> {
+    let depth = match PruneConfig::get().tx_depth {
+        Some(depth) => depth,
+        None => return Ok(()),
+    };
+    if height.0 <= depth {
+        return Ok(());
+    }
+
+    let pruned_height = BlockHeight(height.0 - depth);
+    let pruned_blk: Block = db.get_block(pruned_height)?;
+    if pruned_blk.tx_list().is_empty() {
+        return Ok(());
+    }
+
+    if let Some(archive) = GLOBAL_TX_ARCHIVE.get() {
+        let txs = pruned_blk
+            .tx_list()
+            .iter()
+            .map(|&tx_hash| {
+                let bytes = db.get_tx_bytes(tx_hash)?.with_context(|| {
+                    format!("Failed to get tx from the database. tx_hash: {}", tx_hash)
+                })?;
+                Ok((tx_hash, bytes))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        archive.archive_txs(&txs)?;
+    }
+
+    debug!(
+        "Prune {} tx bodies in block #{}.",
+        pruned_blk.tx_list().len(),
+        pruned_height
+    );
+    for &tx_hash in pruned_blk.tx_list().iter() {
+        db_tx.delete_tx(tx_hash);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_tx_archive() {
+        let path =
+            std::env::temp_dir().join(format!("slimchain-test-archive-{}.bin", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let tx1 = (H256::repeat_byte(1), b"tx1".to_vec());
+        let tx2 = (H256::repeat_byte(2), b"tx2-body".to_vec());
+        let tx3 = (H256::repeat_byte(3), Vec::new());
+
+        {
+            let archive = FileTxArchive::open_or_create(&path).unwrap();
+            assert_eq!(None, archive.get_tx_bytes(tx1.0).unwrap());
+            archive.archive_txs(&[tx1.clone(), tx2.clone()]).unwrap();
+            assert_eq!(Some(tx1.1.clone()), archive.get_tx_bytes(tx1.0).unwrap());
+            archive.archive_txs(std::slice::from_ref(&tx3)).unwrap();
+        }
+
+        let archive = FileTxArchive::open_or_create(&path).unwrap();
+        assert_eq!(Some(tx1.1), archive.get_tx_bytes(tx1.0).unwrap());
+        assert_eq!(Some(tx2.1), archive.get_tx_bytes(tx2.0).unwrap());
+        assert_eq!(Some(tx3.1), archive.get_tx_bytes(tx3.0).unwrap());
+        assert_eq!(None, archive.get_tx_bytes(H256::repeat_byte(4)).unwrap());
+
+        std::fs::remove_file(&path).ok();
+    }
+}
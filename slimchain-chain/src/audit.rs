@@ -0,0 +1,191 @@
+use crate::{
+    behavior::{insert_tx_receipts, verify_block},
+    block::BlockTrait,
+    block_proposal::BlockProposal,
+    config::ChainConfig,
+    db::{DBPtr, Transaction, DB},
+    latest::LatestBlockHeaderPtr,
+    snapshot::Snapshot,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    digest::{digest_state, Digestible},
+    ed25519::{Keypair, PubSigPair},
+    error::{ConsensusError, Result},
+    tx::TxTrait,
+};
+use slimchain_tx_state::TxTrieTrait;
+use slimchain_utils::{record_event, record_time};
+use std::time::{Duration, Instant};
+
+const AUDIT_HEIGHT_KEY: &str = "audit-height";
+
+fn audit_key(height: BlockHeight) -> String {
+    format!("audit-{}", height.0)
+}
+
+// The signed result of independently re-verifying a block proposal, including the
+// TEE signatures of its txs, the read/write proofs, and the consensus proof.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditAttestation {
+    pub height: BlockHeight,
+    pub block_hash: H256,
+    pub valid: bool,
+    pub verify_time_in_us: u64,
+    pub pk_sig: PubSigPair,
+}
+
+fn attestation_hash(
+    height: BlockHeight,
+    block_hash: H256,
+    valid: bool,
+    verify_time_in_us: u64,
+) -> H256 {
+    let mut hash_state = digest_state();
+    hash_state.update(height.to_digest().as_bytes());
+    hash_state.update(block_hash.as_bytes());
+    hash_state.update(&[valid as u8]);
+    hash_state.update(&verify_time_in_us.to_le_bytes());
+    hash_state.finalize()
+}
+
+impl AuditAttestation {
+    pub fn create(
+        keypair: &Keypair,
+        height: BlockHeight,
+        block_hash: H256,
+        valid: bool,
+        verify_time: Duration,
+    ) -> Self {
+        let verify_time_in_us = verify_time.as_micros() as u64;
+        let hash = attestation_hash(height, block_hash, valid, verify_time_in_us);
+        Self {
+            height,
+            block_hash,
+            valid,
+            verify_time_in_us,
+            pk_sig: PubSigPair::create(keypair, hash),
+        }
+    }
+
+    pub fn verify_sig(&self) -> Result<()> {
+        let hash = attestation_hash(
+            self.height,
+            self.block_hash,
+            self.valid,
+            self.verify_time_in_us,
+        );
+        self.pk_sig.verify(hash)
+    }
+}
+
+// Re-verify the block proposal against the snapshot. The snapshot is only advanced when the
+// block is valid. Otherwise, it is left untouched so that the next proposal of the same height
+// can still be audited.
+#[tracing::instrument(level = "info", skip(chain_cfg, snapshot, blk_proposal, verify_consensus_fn, keypair), fields(height = blk_proposal.get_block_height().0))]
+pub async fn audit_block<Tx, Block, TxTrie, VerifyConsensusFn>(
+    chain_cfg: &ChainConfig,
+    snapshot: &mut Snapshot<Block, TxTrie>,
+    blk_proposal: &BlockProposal<Block, Tx>,
+    verify_consensus_fn: VerifyConsensusFn,
+    keypair: &Keypair,
+) -> AuditAttestation
+where
+    Tx: TxTrait,
+    Block: BlockTrait,
+    TxTrie: TxTrieTrait + 'static,
+    VerifyConsensusFn: Fn(&Block, &Block) -> Result<(), ConsensusError>,
+{
+    let begin = Instant::now();
+    let height = blk_proposal.get_block_height();
+    let block_hash = blk_proposal.get_block().to_digest();
+    let snapshot_backup = snapshot.clone();
+    let valid = match verify_block(chain_cfg, snapshot, blk_proposal, verify_consensus_fn).await {
+        Ok(_) => true,
+        Err(e) => {
+            *snapshot = snapshot_backup;
+            warn!(%height, "Audit failed. Error: {}", e);
+            false
+        }
+    };
+    let verify_time = begin.elapsed();
+    record_time!("audit_block", verify_time, "height": height.0, "valid": valid, "tx_num": blk_proposal.get_txs().len());
+    record_event!("audit_result", "height": height.0, "valid": valid);
+    AuditAttestation::create(keypair, height, block_hash, valid, verify_time)
+}
+
+// Persist the attestation. The block and its tx receipts are stored only if it is valid.
+#[tracing::instrument(level = "info", skip(blk_proposal, attestation, db, latest_block_header), fields(height = blk_proposal.get_block_height().0), err)]
+pub async fn commit_audit<Tx, Block>(
+    blk_proposal: &BlockProposal<Block, Tx>,
+    attestation: &AuditAttestation,
+    db: &DBPtr,
+    latest_block_header: &LatestBlockHeaderPtr,
+) -> Result<()>
+where
+    Tx: TxTrait + Serialize,
+    Block: BlockTrait + Serialize,
+{
+    let mut db_tx = Transaction::with_capacity(3 + blk_proposal.get_txs().len());
+    db_tx.insert_meta_object(&audit_key(attestation.height), attestation)?;
+    if attestation.valid {
+        let blk = blk_proposal.get_block();
+        db_tx.insert_meta_object(AUDIT_HEIGHT_KEY, &attestation.height)?;
+        db_tx.insert_block(blk)?;
+        insert_tx_receipts(&mut db_tx, blk_proposal)?;
+        db.write_async(db_tx).await?;
+        latest_block_header.set_from_block(blk);
+    } else {
+        db.write_async(db_tx).await?;
+    }
+    Ok(())
+}
+
+pub fn get_audit_attestation(db: &DB, height: BlockHeight) -> Result<Option<AuditAttestation>> {
+    db.get_meta_object(&audit_key(height))
+}
+
+pub fn get_audited_height(db: &DB) -> Result<BlockHeight> {
+    Ok(db.get_meta_object(AUDIT_HEIGHT_KEY)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_attestation() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let attestation = AuditAttestation::create(
+            &keypair,
+            1.into(),
+            1u64.to_digest(),
+            true,
+            Duration::from_millis(10),
+        );
+        assert_eq!(10_000, attestation.verify_time_in_us);
+        attestation.verify_sig().unwrap();
+
+        let mut bad_attestation = attestation.clone();
+        bad_attestation.valid = false;
+        assert!(bad_attestation.verify_sig().is_err());
+        let mut bad_attestation = attestation.clone();
+        bad_attestation.block_hash = 2u64.to_digest();
+        assert!(bad_attestation.verify_sig().is_err());
+
+        let db = DB::load_test();
+        assert_eq!(BlockHeight::default(), get_audited_height(&db).unwrap());
+        let mut db_tx = Transaction::new();
+        db_tx
+            .insert_meta_object(&audit_key(attestation.height), &attestation)
+            .unwrap();
+        db.write_sync(db_tx).unwrap();
+        assert_eq!(
+            Some(attestation),
+            get_audit_attestation(&db, 1.into()).unwrap()
+        );
+        assert_eq!(None, get_audit_attestation(&db, 2.into()).unwrap());
+    }
+}
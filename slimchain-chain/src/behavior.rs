@@ -9,3 +9,6 @@ pub use verify::*;
 
 pub mod commit;
 pub use commit::*;
+
+pub mod call;
+pub use call::*;
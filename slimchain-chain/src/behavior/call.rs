@@ -0,0 +1,91 @@
+use crate::{db::DBPtr, genesis::genesis_chain_id, latest::LatestBlockHeaderPtr};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{AccountData, Address, BlockHeight, Code, Nonce, StateKey, StateValue, H256},
+    error::Result,
+};
+use slimchain_merkle_trie::prelude::*;
+use slimchain_tx_state::{
+    read_code,
+    trie_view::{AccountTrieView, StateTrieView},
+    TxStateView,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewCallRequest {
+    pub caller: Address,
+    pub address: Address,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewCallOutput {
+    // The block whose state is queried.
+    pub height: BlockHeight,
+    pub output: Vec<u8>,
+}
+
+struct StateBackend<'a, StateView: TxStateView + ?Sized> {
+    state_view: &'a StateView,
+    state_root: H256,
+}
+
+impl<'a, StateView: TxStateView + ?Sized> StateBackend<'a, StateView> {
+    fn map_acc_data<T>(
+        &self,
+        acc_address: Address,
+        default: impl FnOnce() -> T,
+        f: impl FnOnce(&AccountData) -> T,
+    ) -> Result<T> {
+        let view = AccountTrieView::new(self.state_view);
+        let acc_data = read_trie_without_proof(&view, self.state_root, &acc_address)?;
+        Ok(acc_data.as_ref().map_or_else(default, f))
+    }
+}
+
+impl<'a, StateView: TxStateView + ?Sized> slimchain_tx_executor::Backend
+    for StateBackend<'a, StateView>
+{
+    fn get_nonce(&self, acc_address: Address) -> Result<Nonce> {
+        self.map_acc_data(acc_address, Default::default, |d| d.nonce)
+    }
+
+    fn get_code(&self, acc_address: Address) -> Result<Code> {
+        let code_hash = self.map_acc_data(acc_address, H256::zero, |d| d.code_hash)?;
+        read_code(self.state_view, code_hash)
+    }
+
+    fn get_value(&self, acc_address: Address, key: StateKey) -> Result<StateValue> {
+        let acc_state_root = self.map_acc_data(acc_address, H256::zero, |d| d.acc_state_root)?;
+
+        let view = StateTrieView::new(self.state_view, acc_address);
+        let value = read_trie_without_proof(&view, acc_state_root, &key)?.unwrap_or_default();
+        Ok(value)
+    }
+}
+
+// Query the latest state of the storage node. It runs outside the tx engine, since nothing is
+// signed or committed.
+#[tracing::instrument(level = "debug", skip(db, latest_block_header, req), fields(address = %req.address))]
+pub fn call_view(
+    db: &DBPtr,
+    latest_block_header: &LatestBlockHeaderPtr,
+    req: ViewCallRequest,
+) -> Result<ViewCallOutput> {
+    let header = latest_block_header.get();
+    let (height, state_root) = (header.height, header.state_root);
+    let backend = StateBackend {
+        state_view: db.as_ref(),
+        state_root,
+    };
+    let output = slimchain_tx_executor::call_view(
+        req.caller,
+        req.address,
+        req.data,
+        genesis_chain_id(),
+        height,
+        header.time_stamp.timestamp_millis() as u64,
+        &backend,
+    )?;
+    Ok(ViewCallOutput { height, output })
+}
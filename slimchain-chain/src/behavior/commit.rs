@@ -1,10 +1,13 @@
 use crate::{
+    archive::prune_tx_bodies,
     block::BlockTrait,
     block_proposal::BlockProposal,
     db::{DBPtr, Transaction},
     latest::{LatestBlockHeaderPtr, LatestTxCountPtr},
+    receipt::TxReceipt,
+    tx_event::{has_tx_event_subscribers, publish_tx_event, TxEvent},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use slimchain_common::{error::Result, tx::TxTrait};
 use slimchain_tx_state::TxStateUpdate;
 use slimchain_utils::record_event;
@@ -21,7 +24,28 @@ fn record_txs<Tx, Block>(
     info!("Commit {} TX.", tx_len);
     latest_tx_count.add(tx_len);
     let tx_ids: Vec<_> = txs.iter().map(|tx| tx.id()).collect();
-    record_event!("tx_commit", "tx_ids": tx_ids, "height": blk_proposal.get_block_height().0);
+    let height = blk_proposal.get_block_height();
+    record_event!("tx_commit", "tx_ids": tx_ids, "height": height.0);
+    if has_tx_event_subscribers() {
+        for tx_id in tx_ids {
+            publish_tx_event(TxEvent::Committed { tx_id, height });
+        }
+    }
+}
+
+pub fn insert_tx_receipts<Tx, Block>(
+    db_tx: &mut Transaction,
+    blk_proposal: &BlockProposal<Block, Tx>,
+) -> Result<()>
+where
+    Tx: TxTrait,
+    Block: BlockTrait,
+{
+    let height = blk_proposal.get_block_height();
+    for (idx, tx) in blk_proposal.get_txs().iter().enumerate() {
+        db_tx.insert_tx_receipt(tx.id(), &TxReceipt::committed(height, idx))?;
+    }
+    Ok(())
 }
 
 #[tracing::instrument(level = "info", skip(blk_proposal, db, latest_block_header, latest_tx_count), fields(height = blk_proposal.get_block_height().0), err)]
@@ -35,9 +59,10 @@ where
     Tx: TxTrait + Serialize,
     Block: BlockTrait + Serialize,
 {
-    let mut db_tx = Transaction::with_capacity(1);
+    let mut db_tx = Transaction::with_capacity(1 + blk_proposal.get_txs().len());
     let blk = blk_proposal.get_block();
     db_tx.insert_block(blk)?;
+    insert_tx_receipts(&mut db_tx, blk_proposal)?;
     db.write_async(db_tx).await?;
     latest_block_header.set_from_block(blk);
     record_txs(blk_proposal, latest_tx_count);
@@ -54,7 +79,7 @@ pub async fn commit_block_storage_node<Tx, Block>(
 ) -> Result<()>
 where
     Tx: TxTrait + Serialize,
-    Block: BlockTrait + Serialize,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
 {
     let mut db_tx = Transaction::new();
     let blk = blk_proposal.get_block();
@@ -65,7 +90,9 @@ where
         debug_assert_eq!(tx_hash, tx.to_digest());
         db_tx.insert_tx(tx_hash, tx)?;
     }
+    insert_tx_receipts(&mut db_tx, blk_proposal)?;
     db_tx.update_state(state_update)?;
+    prune_tx_bodies::<Block>(db, &mut db_tx, blk.block_height())?;
 
     db.write_async(db_tx).await?;
     latest_block_header.set_from_block(blk);
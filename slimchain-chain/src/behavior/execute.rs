@@ -1,4 +1,4 @@
-use crate::{db::DBPtr, latest::LatestBlockHeaderPtr};
+use crate::{db::DBPtr, genesis::genesis_chain_id, latest::LatestBlockHeaderPtr};
 use futures::{prelude::*, ready, stream::Fuse};
 use pin_project::pin_project;
 use slimchain_common::{
@@ -48,9 +48,10 @@ impl<Tx: TxTrait, Input: Stream<Item = SignedTxRequest>> Stream for TxExecuteStr
         let mut this = self.project();
         while let Poll::Ready(Some(req)) = this.input.as_mut().poll_next(cx) {
             let latest_block_header = this.latest_block_header.clone();
-            let task = TxTask::new(this.db.clone(), req, move || -> (BlockHeight, H256) {
-                latest_block_header.get_height_and_state_root()
-            });
+            let task = TxTask::new(this.db.clone(), req, move || -> (BlockHeight, u64, H256) {
+                latest_block_header.get_height_timestamp_and_state_root()
+            })
+            .with_chain_id(genesis_chain_id());
             this.engine.push_task(task);
         }
 
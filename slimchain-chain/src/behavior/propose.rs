@@ -2,7 +2,10 @@ use crate::{
     block::{BlockHeader, BlockTrait, BlockTxList},
     block_proposal::{BlockProposal, BlockProposalTrie},
     config::{ChainConfig, MinerConfig},
+    discard_tx,
+    genesis::genesis_chain_id,
     snapshot::Snapshot,
+    tx_ordering::{dependency_order, TxOrdering},
 };
 use chrono::Utc;
 use futures::prelude::*;
@@ -16,7 +19,7 @@ use slimchain_common::{
 use slimchain_tx_state::{
     merge_tx_trie_diff, TxProposal, TxTrie, TxTrieDiff, TxTrieTrait, TxWriteSetTrie,
 };
-use slimchain_utils::record_event;
+use slimchain_utils::{record_event, record_time, serde::serialized_size};
 use std::time::Instant;
 use tokio::time::timeout_at;
 
@@ -25,6 +28,8 @@ enum TxTries {
     UncompressedTries(Vec<(BlockHeight, TxWriteSetTrie)>),
 }
 
+// The snapshot is advanced to the proposed block before it gets committed. Hence, a clone of it
+// can be used to propose the next block while the current one is still being committed.
 #[tracing::instrument(level = "info", skip(chain_cfg, miner_cfg, snapshot, tx_proposals, new_block_fn), fields(height = snapshot.current_height().0 + 1), err)]
 pub async fn propose_block<Tx, Block, TxStream, NewBlockFn, NewBlockFnOutput>(
     chain_cfg: &ChainConfig,
@@ -41,7 +46,7 @@ where
     NewBlockFnOutput: Future<Output = Result<Block>> + Send + 'static,
 {
     let begin = Instant::now();
-    let deadline = begin + miner_cfg.max_block_interval;
+    let deadline = begin + miner_cfg.current_max_block_interval();
 
     let mut txs: Vec<Tx> = Vec::with_capacity(miner_cfg.max_txs);
     let mut tx_tries = if miner_cfg.compress_trie {
@@ -52,12 +57,14 @@ where
 
     let last_block_height = snapshot.current_height();
     let next_block_height = last_block_height.next_height();
+    let chain_id = genesis_chain_id();
 
     snapshot.access_map.alloc_new_block();
     let mut writes = TxWriteData::default();
+    let mut candidates: Vec<(TxProposal<Tx>, BlockHeight)> = Vec::new();
 
-    while txs.len() < miner_cfg.max_txs {
-        let tx_proposal = if txs.len() < miner_cfg.min_txs {
+    while txs.len() + candidates.len() < miner_cfg.max_txs {
+        let tx_proposal = if txs.len() + candidates.len() < miner_cfg.min_txs {
             tx_proposals.next().await
         } else {
             if Instant::now() > deadline {
@@ -84,15 +91,28 @@ where
         let tx_id = tx.id();
         record_event!("blk_recv_tx", "tx_id": tx_id, "height": next_block_height.0);
 
+        // The tx may have waited in the pool until its expiry.
+        if let Err(e) = tx.tx_input().check_replay(chain_id, next_block_height) {
+            debug!("Tx proposal cannot be included. Error: {}", e);
+            discard_tx!(tx_id, "tx_expired", std::format!("{}", e));
+            continue;
+        }
+
         let tx_block_height = tx.tx_block_height();
+        // Expire the proposals which waited too long in the queue, before the costly checks.
+        if let Some(age) = miner_cfg.expired_tx_age(next_block_height, tx_block_height) {
+            debug!(age, "Tx proposal is expired.");
+            discard_tx!(tx_id, "tx_ttl_expired", std::format!("age={}", age));
+            continue;
+        }
         if tx_block_height < snapshot.access_map.oldest_block_height() {
             debug!("Tx proposal is outdated.");
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_outdated");
+            discard_tx!(tx_id, "tx_outdated");
             continue;
         }
         if tx_block_height > last_block_height {
             warn!("Tx proposal is too new.");
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_too_new");
+            discard_tx!(tx_id, "tx_too_new");
             continue;
         }
 
@@ -103,7 +123,7 @@ where
             tx.tx_writes(),
         ) {
             debug!("Received a tx with conflict");
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_conflict");
+            discard_tx!(tx_id, "tx_conflict");
             continue;
         }
 
@@ -113,35 +133,85 @@ where
 
         if tx.tx_state_root() != tx_block.state_root() {
             warn!("Received a tx with invalid state root.");
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "invalid_state_root");
+            discard_tx!(tx_id, "invalid_state_root");
+            continue;
+        }
+        if tx.tx_block_timestamp() != tx_block.time_stamp().timestamp_millis() as u64 {
+            warn!("Received a tx with invalid block timestamp.");
+            discard_tx!(tx_id, "invalid_block_timestamp");
             continue;
         }
 
         if let Err(e) = tx.verify_sig() {
             warn!("Received a tx with invalid sig. Error: {:?}", e);
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "invalid_sig", "detail": std::format!("{}", e));
+            discard_tx!(tx_id, "invalid_sig", std::format!("{}", e));
             continue;
         }
 
         if let Err(e) = write_trie.verify(tx_block.state_root()) {
             warn!("Received a tx with invalid write trie. Error: {:?}", e);
-            record_event!("discard_tx", "tx_id": tx_id, "reason": "invalid_write_trie", "detail": std::format!("{}", e));
+            discard_tx!(tx_id, "invalid_write_trie", std::format!("{}", e));
             continue;
         }
 
-        snapshot.access_map.add_read(tx.tx_reads());
-        snapshot.access_map.add_write(tx.tx_writes());
-        writes.merge(tx.tx_writes());
-
-        txs.push(tx);
-        match &mut tx_tries {
-            TxTries::Diff(diffs) => {
-                let diff = snapshot.tx_trie.diff_missing_branches(&write_trie);
-                diffs.push(diff);
+        match miner_cfg.tx_ordering {
+            TxOrdering::FCFS => {
+                admit_tx(
+                    snapshot,
+                    &mut txs,
+                    &mut tx_tries,
+                    &mut writes,
+                    TxProposal { tx, write_trie },
+                    tx_block_height,
+                );
             }
-            TxTries::UncompressedTries(tries) => {
-                tries.push((tx_block_height, write_trie));
+            TxOrdering::DepGraph => {
+                candidates.push((TxProposal { tx, write_trie }, tx_block_height));
+            }
+        }
+    }
+
+    if !candidates.is_empty() {
+        let (order, dropped) = {
+            let rw_sets: Vec<_> = candidates
+                .iter()
+                .map(|(TxProposal { tx, .. }, _)| (tx.tx_reads(), tx.tx_writes()))
+                .collect();
+            dependency_order(&rw_sets)
+        };
+
+        for idx in dropped {
+            let tx_id = candidates[idx].0.tx.id();
+            debug!("Received a tx with conflict");
+            discard_tx!(tx_id, "tx_conflict");
+        }
+
+        let mut candidates: Vec<_> = candidates.into_iter().map(Some).collect();
+        for idx in order {
+            let (tx_proposal, tx_block_height) = candidates[idx]
+                .take()
+                .expect("Failed to get the tx proposal.");
+
+            // Re-check the conflict since other txs in this block have been admitted.
+            if chain_cfg.conflict_check.has_conflict(
+                &snapshot.access_map,
+                tx_block_height,
+                tx_proposal.tx.tx_reads(),
+                tx_proposal.tx.tx_writes(),
+            ) {
+                debug!("Received a tx with conflict");
+                discard_tx!(tx_proposal.tx.id(), "tx_conflict");
+                continue;
             }
+
+            admit_tx(
+                snapshot,
+                &mut txs,
+                &mut tx_tries,
+                &mut writes,
+                tx_proposal,
+                tx_block_height,
+            );
         }
     }
 
@@ -177,10 +247,15 @@ where
     let last_block = snapshot
         .get_block(last_block_height)
         .context("Failed to get the last block.")?;
+    // Keep the block valid even if the local clock is behind the previous proposers.
+    let time_stamp = match snapshot.median_time_past(chain_cfg.median_time_span) {
+        Some(median_time_past) => Utc::now().max(median_time_past),
+        None => Utc::now(),
+    };
     let block_header = BlockHeader::new(
         next_block_height,
         last_block.to_digest(),
-        Utc::now(),
+        time_stamp,
         tx_list,
         new_state_root,
     );
@@ -191,7 +266,35 @@ where
     snapshot.commit_block(blk_proposal.get_block().clone());
 
     let end = Instant::now();
-    record_event!("propose_end", "height": blk_proposal.get_block_height().0);
+    let proof_size = serialized_size(blk_proposal.get_trie()).unwrap_or_default();
+    record_event!("propose_end", "height": blk_proposal.get_block_height().0, "proof_size": proof_size);
+    record_time!("propose_block", end - begin, "height": blk_proposal.get_block_height().0, "tx_num": blk_proposal.get_txs().len());
     info!(time = ?(end - begin));
     Ok(Some(blk_proposal))
 }
+
+fn admit_tx<Tx: TxTrait, Block: BlockTrait>(
+    snapshot: &mut Snapshot<Block, TxTrie>,
+    txs: &mut Vec<Tx>,
+    tx_tries: &mut TxTries,
+    writes: &mut TxWriteData,
+    tx_proposal: TxProposal<Tx>,
+    tx_block_height: BlockHeight,
+) {
+    let TxProposal { tx, write_trie } = tx_proposal;
+
+    snapshot.access_map.add_read(tx.tx_reads());
+    snapshot.access_map.add_write(tx.tx_writes());
+    writes.merge(tx.tx_writes());
+
+    txs.push(tx);
+    match tx_tries {
+        TxTries::Diff(diffs) => {
+            let diff = snapshot.tx_trie.diff_missing_branches(&write_trie);
+            diffs.push(diff);
+        }
+        TxTries::UncompressedTries(tries) => {
+            tries.push((tx_block_height, write_trie));
+        }
+    }
+}
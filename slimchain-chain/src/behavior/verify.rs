@@ -2,11 +2,13 @@ use crate::{
     block::BlockTrait,
     block_proposal::{BlockProposal, BlockProposalTrie},
     config::ChainConfig,
+    genesis::genesis_chain_id,
     snapshot::Snapshot,
+    tx_sig::verify_txs_sig,
 };
 use slimchain_common::{
     basic::H256,
-    error::{bail, ensure, Context as _, Result},
+    error::{bail, ensure, ConsensusError, Context as _, Result, StateError},
     rw_set::TxWriteData,
     tx::TxTrait,
 };
@@ -25,7 +27,7 @@ where
     Tx: TxTrait,
     Block: BlockTrait,
     TxTrie: TxTrieTrait + 'static,
-    VerifyConsensusFn: Fn(&Block, &Block) -> Result<()>,
+    VerifyConsensusFn: Fn(&Block, &Block) -> Result<(), ConsensusError>,
 {
     let begin = Instant::now();
     let last_block = snapshot
@@ -33,6 +35,12 @@ where
         .context("Failed to get the last block")?;
 
     blk_proposal.get_block().verify_block_header(last_block)?;
+    let median_time_past = snapshot
+        .median_time_past(chain_cfg.median_time_span)
+        .context("Failed to get the median time past")?;
+    blk_proposal
+        .get_block()
+        .verify_time_stamp(median_time_past, chain_cfg.max_future_drift)?;
     verify_consensus_fn(blk_proposal.get_block(), last_block)?;
 
     match blk_proposal.get_trie() {
@@ -54,26 +62,31 @@ where
         }
     }
 
+    verify_txs_sig(blk_proposal.get_txs()).context("Tx with invalid sig.")?;
+
     snapshot.access_map.alloc_new_block();
     let mut writes = TxWriteData::default();
+    let chain_id = genesis_chain_id();
 
     for tx in blk_proposal.get_txs() {
+        tx.tx_input()
+            .check_replay(chain_id, blk_proposal.get_block_height())
+            .context("Tx not allowed in the block proposal.")?;
+
         let tx_block_height = tx.tx_block_height();
         let tx_block = match snapshot.get_block(tx_block_height) {
             Some(blk) => blk,
-            None => bail!(
-                "Outdated tx in the block proposal. blk_height={}, tx_height={}",
-                blk_proposal.get_block_height(),
-                tx_block_height
-            ),
+            None => bail!(StateError::Outdated(tx_block_height)),
         };
 
         ensure!(
             tx.tx_state_root() == tx_block.state_root(),
             "Tx with invalid state root."
         );
-
-        tx.verify_sig().context("Tx with invalid sig.")?;
+        ensure!(
+            tx.tx_block_timestamp() == tx_block.time_stamp().timestamp_millis() as u64,
+            "Tx with invalid block timestamp."
+        );
 
         ensure!(
             !chain_cfg.conflict_check.has_conflict(
@@ -82,7 +95,7 @@ where
                 tx.tx_reads(),
                 tx.tx_writes(),
             ),
-            "Tx with conflict."
+            StateError::Conflict
         );
 
         snapshot.access_map.add_read(tx.tx_reads());
@@ -103,9 +116,11 @@ where
 
     ensure!(
         blk_proposal.get_block().state_root() == new_state_root,
-        "Invalid state root in the block proposal (expect: {}, actual: {}).",
-        blk_proposal.get_block().state_root(),
-        new_state_root,
+        ConsensusError::InvalidBlock(format!(
+            "Invalid state root in the block proposal (expect: {}, actual: {}).",
+            blk_proposal.get_block().state_root(),
+            new_state_root,
+        ))
     );
 
     snapshot.commit_block(blk_proposal.get_block().clone());
@@ -1,14 +1,14 @@
-use crate::loader::TxLoaderTrait;
+use crate::{epoch::epoch_root, loader::TxLoaderTrait, tx_proof::tx_merkle_root};
 use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{BlockHeight, H256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
-    error::{ensure, Result},
+    digest::{digest_state, Digestible},
+    error::{ConsensusError, Result},
     tx::TxTrait,
     utils::derive_more::{Deref, DerefMut},
 };
-use std::iter::FromIterator;
+use std::{iter::FromIterator, time::Duration};
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct BlockHeader {
@@ -17,24 +17,27 @@ pub struct BlockHeader {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub time_stamp: DateTime<Utc>,
     pub tx_list: BlockTxList,
+    pub tx_root: H256,
     pub state_root: H256,
+    pub epoch_root: H256,
 }
 
 pub fn block_header_to_digest(
     height: BlockHeight,
     prev_blk_hash: H256,
     time_stamp: DateTime<Utc>,
-    tx_list_root: H256,
+    tx_root: H256,
     state_root: H256,
+    epoch_root: H256,
 ) -> H256 {
-    let mut hash_state = default_blake2().to_state();
+    let mut hash_state = digest_state();
     hash_state.update(height.to_digest().as_bytes());
     hash_state.update(prev_blk_hash.as_bytes());
     hash_state.update(time_stamp.timestamp_millis().to_digest().as_bytes());
-    hash_state.update(tx_list_root.as_bytes());
+    hash_state.update(tx_root.as_bytes());
     hash_state.update(state_root.as_bytes());
-    let hash = hash_state.finalize();
-    blake2b_hash_to_h256(hash)
+    hash_state.update(epoch_root.as_bytes());
+    hash_state.finalize()
 }
 
 impl Digestible for BlockHeader {
@@ -43,8 +46,9 @@ impl Digestible for BlockHeader {
             self.height,
             self.prev_blk_hash,
             self.time_stamp,
-            self.tx_list.to_digest(),
+            self.tx_root,
             self.state_root,
+            self.epoch_root,
         )
     }
 }
@@ -58,12 +62,15 @@ impl BlockHeader {
         state_root: H256,
     ) -> Self {
         let time_stamp = Utc.timestamp_millis(time_stamp.timestamp_millis());
+        let tx_root = tx_merkle_root(&tx_list);
         Self {
             height,
             prev_blk_hash,
             time_stamp,
             tx_list,
+            tx_root,
             state_root,
+            epoch_root: epoch_root(height),
         }
     }
 
@@ -81,12 +88,11 @@ impl Digestible for BlockTxList {
             return H256::zero();
         }
 
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         for tx in self.iter() {
             hash_state.update(tx.as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -131,7 +137,7 @@ pub trait BlockTrait: Digestible + Clone + Sized + Send + Sync {
         self.block_header().time_stamp
     }
     fn tx_root(&self) -> H256 {
-        self.block_header().tx_list.to_digest()
+        self.block_header().tx_root
     }
     fn tx_list(&self) -> &BlockTxList {
         &self.block_header().tx_list
@@ -142,24 +148,60 @@ pub trait BlockTrait: Digestible + Clone + Sized + Send + Sync {
     fn state_root(&self) -> H256 {
         self.block_header().state_root
     }
+    fn epoch_root(&self) -> H256 {
+        self.block_header().epoch_root
+    }
+
+    fn verify_block_header(&self, prev_blk: &Self) -> Result<(), ConsensusError> {
+        let invalid = |msg: &str| Err(ConsensusError::InvalidBlock(msg.to_string()));
+        if self.block_height() != prev_blk.block_height().next_height() {
+            return invalid("Invalid block height.");
+        }
+        if self.prev_blk_hash() != prev_blk.to_digest() {
+            return invalid("Invalid previous block hash.");
+        }
+        if self.epoch_root() != epoch_root(self.block_height()) {
+            return invalid("Invalid epoch root.");
+        }
+        // The block hash only commits to the tx root, so the tx list has to be checked against it.
+        if self.tx_root() != tx_merkle_root(self.tx_list()) {
+            return invalid("Invalid tx root.");
+        }
+        Ok(())
+    }
 
-    fn verify_block_header(&self, prev_blk: &Self) -> Result<()> {
-        ensure!(
-            self.block_height() == prev_blk.block_height().next_height(),
-            "Invalid block height."
-        );
-        ensure!(
-            self.prev_blk_hash() == prev_blk.to_digest(),
-            "Invalid previous block hash."
-        );
-        ensure!(
-            self.time_stamp() >= prev_blk.time_stamp(),
-            "Invalid timestamp."
-        );
-        ensure!(
-            self.time_stamp() <= Utc::now() + chrono::Duration::seconds(30),
-            "Future timestamp is not allowed."
-        );
+    // Only checked when a new block is verified against the local clock, see `verify_block`.
+    // A block may precede its parent, e.g., proposed by a leader with a slower clock, as long as
+    // it does not precede the median time past of the recent blocks.
+    fn verify_time_stamp(
+        &self,
+        median_time_past: DateTime<Utc>,
+        max_future_drift: Duration,
+    ) -> Result<(), ConsensusError> {
+        if self.time_stamp() < median_time_past {
+            return Err(ConsensusError::InvalidBlock(format!(
+                "Timestamp {} precedes the median time past {}.",
+                self.time_stamp(),
+                median_time_past
+            )));
+        }
+        let max_time_stamp = chrono::Duration::from_std(max_future_drift)
+            .ok()
+            .and_then(|drift| Utc::now().checked_add_signed(drift));
+        if !max_time_stamp.map_or(true, |max_ts| self.time_stamp() <= max_ts) {
+            return Err(ConsensusError::InvalidBlock(
+                "Future timestamp is not allowed.".to_string(),
+            ));
+        }
         Ok(())
     }
 }
+
+// The median time stamp of the given blocks.
+pub fn median_time_past<'a, Block: BlockTrait + 'a>(
+    blocks: impl Iterator<Item = &'a Block>,
+) -> Option<DateTime<Utc>> {
+    let mut time_stamps: Vec<_> = blocks.map(|blk| blk.time_stamp()).collect();
+    time_stamps.sort_unstable();
+    time_stamps.get(time_stamps.len() / 2).copied()
+}
@@ -2,6 +2,7 @@ use crate::{
     block::BlockTrait,
     db::DBPtr,
     loader::{BlockLoaderTrait, TxLoaderTrait},
+    tx_proof::tx_merkle_root,
 };
 use serde::{
     de::{self, Deserializer, MapAccess, SeqAccess, Visitor},
@@ -10,6 +11,7 @@ use serde::{
 };
 use slimchain_common::{
     basic::BlockHeight,
+    collections::HashSet,
     error::{ensure, Result},
     rw_set::TxWriteData,
     tx::TxTrait,
@@ -80,6 +82,38 @@ impl<Block: BlockTrait, Tx: TxTrait> BlockProposal<Block, Tx> {
     pub fn unpack(self) -> (Block, Vec<Tx>) {
         (self.block, self.txs)
     }
+
+    // The checks which need no chain state. It is cheap enough to run before relaying the
+    // proposal. `verify_block` is still required before importing it.
+    pub fn verify_structure(&self) -> Result<()> {
+        ensure!(
+            !self.get_block_height().is_zero(),
+            "Unexpected genesis block in the block proposal."
+        );
+        let tx_list = self.block.tx_list();
+        ensure!(
+            tx_list.len() == self.txs.len(),
+            "Mismatched tx list. Expect {} txs, got {}.",
+            tx_list.len(),
+            self.txs.len()
+        );
+        ensure!(
+            tx_list
+                .iter()
+                .zip(self.txs.iter())
+                .all(|(&hash, tx)| hash == tx.to_digest()),
+            "Mismatched tx list."
+        );
+        ensure!(
+            tx_list.iter().collect::<HashSet<_>>().len() == tx_list.len(),
+            "Duplicate txs in the block proposal."
+        );
+        ensure!(
+            self.block.tx_root() == tx_merkle_root(tx_list),
+            "Invalid tx root."
+        );
+        Ok(())
+    }
 }
 
 impl<Block: BlockTrait + for<'de> Deserialize<'de>, Tx: TxTrait + for<'de> Deserialize<'de>>
@@ -221,6 +255,7 @@ mod tests {
     use slimchain_common::{
         basic::{Address, BlockHeight, H256},
         digest::Digestible,
+        encoding::{CanonicalDecode, CanonicalEncode, Reader},
         rw_set::{TxReadSet, TxWriteData},
         tx::TxTrait,
         tx_req::TxRequest,
@@ -238,6 +273,16 @@ mod tests {
             }
         }
 
+        impl CanonicalEncode for DummyTx {
+            fn encode_to(&self, _out: &mut Vec<u8>) {}
+        }
+
+        impl CanonicalDecode for DummyTx {
+            fn decode_from(_reader: &mut Reader<'_>) -> Result<Self> {
+                Ok(DummyTx)
+            }
+        }
+
         impl TxTrait for DummyTx {
             fn tx_caller(&self) -> Address {
                 unreachable!();
@@ -248,6 +293,9 @@ mod tests {
             fn tx_block_height(&self) -> BlockHeight {
                 unreachable!();
             }
+            fn tx_block_timestamp(&self) -> u64 {
+                unreachable!();
+            }
             fn tx_state_root(&self) -> H256 {
                 unreachable!();
             }
@@ -303,4 +351,63 @@ mod tests {
         let json = serde_json::to_string(&proposal).unwrap();
         assert_eq!(proposal, serde_json::from_str(&json).unwrap());
     }
+
+    #[test]
+    fn test_verify_structure() {
+        use crate::consensus::raft::Block;
+        use slimchain_common::tx::RawTx;
+
+        let tx = |nonce: u64| RawTx {
+            caller: Address::default(),
+            input: TxRequest::Call {
+                nonce: nonce.into(),
+                address: Address::default(),
+                data: Vec::new(),
+                chain_id: 0,
+                expiry: None,
+            },
+            block_height: BlockHeight(0),
+            block_timestamp: 0,
+            state_root: H256::zero(),
+            reads: TxReadSet::default(),
+            writes: TxWriteData::default(),
+        };
+        let proposal = |height: u64, txs: Vec<RawTx>, tx_list: BlockTxList| {
+            let header = BlockHeader::new(
+                BlockHeight(height),
+                H256::zero(),
+                chrono::Utc::now(),
+                tx_list,
+                H256::zero(),
+            );
+            let mut block = Block::genesis_block();
+            *block.block_header_mut() = header;
+            BlockProposal::new(block, txs, BlockProposalTrie::Diff(Default::default()))
+        };
+
+        let txs = vec![tx(1), tx(2)];
+        let tx_list: BlockTxList = txs.iter().collect();
+        assert!(proposal(1, txs.clone(), tx_list.clone())
+            .verify_structure()
+            .is_ok());
+        assert!(proposal(0, txs.clone(), tx_list.clone())
+            .verify_structure()
+            .is_err());
+        assert!(proposal(1, vec![tx(1)], tx_list.clone())
+            .verify_structure()
+            .is_err());
+        assert!(proposal(1, vec![tx(2), tx(1)], tx_list.clone())
+            .verify_structure()
+            .is_err());
+
+        let mut bad_root = proposal(1, txs, tx_list);
+        bad_root.get_block_mut().block_header_mut().tx_root = H256::repeat_byte(1);
+        assert!(bad_root.verify_structure().is_err());
+
+        let dup_txs = vec![tx(1), tx(1)];
+        let dup_tx_list: BlockTxList = dup_txs.iter().collect();
+        assert!(proposal(1, dup_txs, dup_tx_list)
+            .verify_structure()
+            .is_err());
+    }
 }
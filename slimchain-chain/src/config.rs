@@ -1,17 +1,57 @@
-use crate::{conflict_check::ConflictCheck, consensus::Consensus};
+use crate::{
+    archive::{install_tx_archive, FileTxArchive},
+    conflict_check::ConflictCheck,
+    consensus::Consensus,
+    tx_ordering::TxOrdering,
+};
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
-use slimchain_common::error::{anyhow, Result};
-use std::time::Duration;
+use slimchain_common::{
+    basic::{BlockHeight, ShardAssignment},
+    digest::DigestAlgorithm,
+    error::{anyhow, ensure, Result},
+};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChainConfig {
-    /// Conflict check method. Possible values: ssi, occ.
+    /// Conflict check method. Possible values: ssi, occ, mvcc.
     pub conflict_check: ConflictCheck,
     /// The number of blocks in the temp state.
     pub state_len: usize,
     /// Consensus method. Possible values: pow, raft.
     pub consensus: Consensus,
+    /// The hash function of the tx ids, the state roots, and the blocks. Possible values:
+    /// blake2b, keccak256, and blake3 (requires the blake3 feature). Default blake2b.
+    #[serde(default)]
+    pub digest: DigestAlgorithm,
+    /// How the accounts are assigned to the storage shards. Possible values: modulo, prefix, and
+    /// { consistent_hash = { virtual_nodes = N } }. Default modulo.
+    #[serde(default)]
+    pub shard_assignment: ShardAssignment,
+    /// The number of the recent blocks whose median time stamp a new block should not precede.
+    /// Default 11.
+    #[serde(default = "default_median_time_span")]
+    pub median_time_span: usize,
+    /// Max time span (in ms) a block time stamp can be ahead of the local clock. Default 30000.
+    #[serde(
+        default = "default_max_future_drift",
+        deserialize_with = "slimchain_utils::config::deserialize_duration_from_millis"
+    )]
+    pub max_future_drift: Duration,
+}
+
+fn default_median_time_span() -> usize {
+    11
+}
+
+fn default_max_future_drift() -> Duration {
+    Duration::from_secs(30)
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -28,6 +68,47 @@ pub struct MinerConfig {
     /// Whether to compress partial tries. Default true.
     #[serde(default = "default_compress_trie")]
     pub compress_trie: bool,
+    /// How to order the received txs in a block. Possible values: fcfs, depgraph. Default fcfs.
+    #[serde(default)]
+    pub tx_ordering: TxOrdering,
+    /// Whether to start proposing the next block while the current one is being committed.
+    /// Default false.
+    #[serde(default)]
+    pub pipeline: bool,
+    /// Max age (in blocks) of a tx proposal, i.e., the height of the new block minus the one its
+    /// state is read from. The older ones are expired before the conflict check. If missing, it
+    /// is only bounded by `state_len`.
+    #[serde(default)]
+    pub max_tx_age: Option<u64>,
+}
+
+// The max block interval (in ms) reloaded at runtime. 0 if it has not been reloaded.
+static RELOADED_MAX_BLOCK_INTERVAL: AtomicU64 = AtomicU64::new(0);
+
+impl MinerConfig {
+    // Change the max block interval of the running miner. It takes effect from the next block.
+    pub fn reload_max_block_interval(interval: Duration) {
+        let ms = (interval.as_millis() as u64).max(1);
+        RELOADED_MAX_BLOCK_INTERVAL.store(ms, Ordering::Release);
+    }
+
+    // The age of a tx proposal to be included in `next_block_height`, if it exceeds `max_tx_age`.
+    pub fn expired_tx_age(
+        &self,
+        next_block_height: BlockHeight,
+        tx_block_height: BlockHeight,
+    ) -> Option<u64> {
+        let max_age = self.max_tx_age?;
+        let age = next_block_height.0.saturating_sub(tx_block_height.0);
+        (age > max_age).then(|| age)
+    }
+
+    pub fn current_max_block_interval(&self) -> Duration {
+        match RELOADED_MAX_BLOCK_INTERVAL.load(Ordering::Acquire) {
+            0 => self.max_block_interval,
+            ms => Duration::from_millis(ms),
+        }
+    }
 }
 
 fn default_max_txs() -> usize {
@@ -43,12 +124,20 @@ fn default_compress_trie() -> bool {
 pub struct PoWConfig {
     /// The initial difficulty used by PoW.
     pub init_diff: u64,
+    /// The target block interval in milliseconds. If set, the difficulty is retargeted toward it
+    /// using the moving average of the recent block intervals. Otherwise, it follows the
+    /// per-block adjustment of Ethereum.
+    pub target_block_interval: Option<u64>,
+    /// The number of blocks in the moving average used in retargeting. Default 32.
+    pub retarget_window: u64,
 }
 
 impl Default for PoWConfig {
     fn default() -> Self {
         Self {
             init_diff: 5_000_000,
+            target_block_interval: None,
+            retarget_window: 32,
         }
     }
 }
@@ -66,3 +155,222 @@ impl PoWConfig {
         GLOBAL_POW_CONFIG.get().copied().unwrap_or_default()
     }
 }
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccessMapConfig {
+    /// Keep the read sets in per-block bloom filters to bound the memory. Default false.
+    pub bloom: bool,
+    /// The expected number of read items in one block. Default 4096.
+    pub bloom_items: usize,
+    /// The false positive rate of each bloom filter. Default 0.01.
+    pub bloom_fp_rate: f64,
+}
+
+impl Default for AccessMapConfig {
+    fn default() -> Self {
+        Self {
+            bloom: false,
+            bloom_items: 4096,
+            bloom_fp_rate: 0.01,
+        }
+    }
+}
+
+static GLOBAL_ACCESS_MAP_CONFIG: OnceCell<AccessMapConfig> = OnceCell::new();
+
+impl AccessMapConfig {
+    pub fn install_as_global(self) -> Result<()> {
+        GLOBAL_ACCESS_MAP_CONFIG
+            .set(self)
+            .map_err(|_| anyhow!("Failed to set AccessMapConfig."))
+    }
+
+    pub fn get() -> Self {
+        GLOBAL_ACCESS_MAP_CONFIG.get().cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PruneConfig {
+    /// Prune the tx bodies of the blocks older than this depth, which should be positive. Default
+    /// disabled.
+    pub tx_depth: Option<u64>,
+    /// Path to the archive file storing the pruned tx bodies. If missing, they are dropped.
+    pub archive: Option<PathBuf>,
+}
+
+static GLOBAL_PRUNE_CONFIG: OnceCell<PruneConfig> = OnceCell::new();
+
+impl PruneConfig {
+    pub fn install_as_global(self) -> Result<()> {
+        // The block at depth 0 is the one being committed, whose txs are not in the db yet.
+        ensure!(self.tx_depth != Some(0), "Prune depth should be positive.");
+
+        if let Some(archive) = self.archive.as_ref() {
+            install_tx_archive(Box::new(FileTxArchive::open_or_create(archive)?))?;
+        }
+
+        GLOBAL_PRUNE_CONFIG
+            .set(self)
+            .map_err(|_| anyhow!("Failed to set PruneConfig."))
+    }
+
+    pub fn get() -> Self {
+        GLOBAL_PRUNE_CONFIG.get().cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DBBackend {
+    RocksDB,
+    Memory,
+}
+
+impl Default for DBBackend {
+    fn default() -> Self {
+        Self::RocksDB
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DBCompaction {
+    Ssd,
+    Hdd,
+}
+
+impl Default for DBCompaction {
+    fn default() -> Self {
+        Self::Ssd
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DBCompression {
+    None,
+    Snappy,
+    Zstd,
+    Lz4,
+}
+
+impl Default for DBCompression {
+    fn default() -> Self {
+        Self::Snappy
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DBConfig {
+    /// Storage engine. Possible values: rocksdb, memory. Default rocksdb.
+    pub backend: DBBackend,
+    /// Memory budget (in MiB) of each column, used for its block cache and write buffer.
+    /// Default 128.
+    pub memory_budget: usize,
+    /// Memory budget (in MiB) of the individual columns, which overrides `memory_budget`.
+    /// Possible columns: meta, block, tx, state, log, receipt.
+    pub column_memory_budget: HashMap<String, usize>,
+    /// Max number of open files. Default 512.
+    pub max_open_files: i32,
+    /// Compaction profile. Possible values: ssd, hdd. Default ssd.
+    pub compaction: DBCompaction,
+    /// Compression of the values. Possible values: none, snappy, zstd, lz4. Default snappy.
+    pub compression: DBCompression,
+    /// Compression of the individual columns, which overrides `compression`.
+    pub column_compression: HashMap<String, DBCompression>,
+    /// Compression level of zstd. Default 3.
+    pub zstd_level: i32,
+    /// Interval (in ms) of recording the database statistics in the metrics.
+    /// Default 0, i.e., disabled.
+    pub stats_interval: u64,
+    /// Whether to merge the concurrent async writes into one write. Default false.
+    pub group_commit: bool,
+    /// Max time (in us) a write waits for the others to join its group. Default 1000.
+    pub group_commit_max_latency: u64,
+    /// Max number of writes in a group. Default 128.
+    pub group_commit_max_batch: usize,
+    /// Directory of the backups taken through the admin endpoints. The requested paths are
+    /// relative to it. The backups are refused if missing.
+    pub backup_dir: Option<PathBuf>,
+}
+
+impl Default for DBConfig {
+    fn default() -> Self {
+        Self {
+            backend: DBBackend::default(),
+            memory_budget: 128,
+            column_memory_budget: HashMap::new(),
+            max_open_files: 512,
+            compaction: DBCompaction::default(),
+            compression: DBCompression::default(),
+            column_compression: HashMap::new(),
+            zstd_level: 3,
+            stats_interval: 0,
+            group_commit: false,
+            group_commit_max_latency: 1000,
+            group_commit_max_batch: 128,
+            backup_dir: None,
+        }
+    }
+}
+
+static GLOBAL_DB_CONFIG: OnceCell<DBConfig> = OnceCell::new();
+
+impl DBConfig {
+    pub fn install_as_global(self) -> Result<()> {
+        GLOBAL_DB_CONFIG
+            .set(self)
+            .map_err(|_| anyhow!("Failed to set DBConfig."))
+    }
+
+    pub fn get() -> Self {
+        GLOBAL_DB_CONFIG.get().cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ShardBlockConfig {
+    /// Let the storage nodes pre-aggregate the tx proposals of their shard into shard blocks,
+    /// dropping the txs whose writes overlap within a shard block (Storage only). Default false.
+    pub enabled: bool,
+    /// Max number of tx proposals in one shard block (PoW only). In raft, the shard blocks follow
+    /// the batches sent to the leader. Default 64.
+    pub max_txs: usize,
+}
+
+impl Default for ShardBlockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_txs: 64,
+        }
+    }
+}
+
+static GLOBAL_SHARD_BLOCK_CONFIG: OnceCell<ShardBlockConfig> = OnceCell::new();
+
+impl ShardBlockConfig {
+    pub fn install_as_global(self) -> Result<()> {
+        GLOBAL_SHARD_BLOCK_CONFIG
+            .set(self)
+            .map_err(|_| anyhow!("Failed to set ShardBlockConfig."))
+    }
+
+    pub fn get() -> Self {
+        GLOBAL_SHARD_BLOCK_CONFIG.get().copied().unwrap_or_default()
+    }
+
+    // Without the shard blocks, every tx proposal is gossiped on its own.
+    pub fn batch_size(&self) -> usize {
+        if self.enabled {
+            self.max_txs.max(1)
+        } else {
+            1
+        }
+    }
+}
@@ -11,6 +11,7 @@ use slimchain_common::{
 pub enum ConflictCheck {
     OCC,
     SSI,
+    MVCC,
 }
 
 impl ConflictCheck {
@@ -24,6 +25,7 @@ impl ConflictCheck {
         match self {
             Self::OCC => occ_conflict_check(access_map, tx_block_height, reads, writes),
             Self::SSI => ssi_conflict_check(access_map, tx_block_height, reads, writes),
+            Self::MVCC => mvcc_conflict_check(access_map, tx_block_height, writes),
         }
     }
 }
@@ -78,29 +80,47 @@ fn ssi_conflict_check(
             return true;
         }
 
-        let read_rev_map = match access_map.get_read_rev(acc_addr) {
+        flag1 |= access_map.read_conflicts_in_write_set(acc_addr, tx_block_height, acc_write);
+    }
+
+    for (&acc_addr, acc_read) in reads.iter() {
+        let write_rev_map = match access_map.get_write_rev(acc_addr) {
             Some(entry) => entry,
             None => continue,
         };
 
-        flag1 |= read_rev_map.has_conflict_in_write_set(tx_block_height, acc_write);
+        flag2 |= write_rev_map.has_conflict_in_read_set(tx_block_height, acc_read);
     }
 
-    for (&acc_addr, acc_read) in reads.iter() {
+    flag1 && flag2
+}
+
+// Each tx reads from the versions committed up to `tx_block_height`. Such reads remain
+// consistent as long as no newer version of the data the tx is going to overwrite has been
+// committed within the `state_len` window (first-committer-wins).
+fn mvcc_conflict_check(
+    access_map: &AccessMap,
+    tx_block_height: BlockHeight,
+    writes: &TxWriteData,
+) -> bool {
+    for (&acc_addr, acc_write) in writes.iter() {
         let write_rev_map = match access_map.get_write_rev(acc_addr) {
             Some(entry) => entry,
             None => continue,
         };
 
-        flag2 |= write_rev_map.has_conflict_in_read_set(tx_block_height, acc_read);
+        if write_rev_map.has_conflict_in_write_set(tx_block_height, acc_write) {
+            return true;
+        }
     }
 
-    flag1 && flag2
+    false
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::access_map::BloomParams;
     use slimchain_common::{create_tx_read_set, create_tx_write_set};
 
     #[test]
@@ -123,11 +143,25 @@ mod tests {
             ConflictCheck::SSI,
             input.try_into::<Test>().unwrap().conflict_check
         );
+
+        let input = toml::toml! { conflict_check = "mvcc" };
+        assert_eq!(
+            ConflictCheck::MVCC,
+            input.try_into::<Test>().unwrap().conflict_check
+        );
     }
 
     #[test]
     fn test_conflict_check() {
-        let mut map = AccessMap::new(2);
+        check_conflict(AccessMap::new(2));
+    }
+
+    #[test]
+    fn test_conflict_check_with_bloom() {
+        check_conflict(AccessMap::with_read_blooms(2, BloomParams::new(16, 0.01)));
+    }
+
+    fn check_conflict(mut map: AccessMap) {
         map.alloc_new_block();
         map.add_read(&create_tx_read_set! {
             "0000000000000000000000000000000000000000" => {
@@ -178,6 +212,7 @@ mod tests {
         };
         assert!(!ConflictCheck::OCC.has_conflict(&map, 1.into(), &tx1_read, &tx1_write));
         assert!(!ConflictCheck::SSI.has_conflict(&map, 1.into(), &tx1_read, &tx1_write));
+        assert!(!ConflictCheck::MVCC.has_conflict(&map, 1.into(), &tx1_read, &tx1_write));
 
         let tx2_read = create_tx_read_set! {
             "0000000000000000000000000000000000000000" => {
@@ -195,6 +230,7 @@ mod tests {
         };
         assert!(ConflictCheck::OCC.has_conflict(&map, 1.into(), &tx2_read, &tx2_write));
         assert!(!ConflictCheck::SSI.has_conflict(&map, 1.into(), &tx2_read, &tx2_write));
+        assert!(!ConflictCheck::MVCC.has_conflict(&map, 1.into(), &tx2_read, &tx2_write));
 
         let tx3_read = create_tx_read_set! {
             "0000000000000000000000000000000000000000" => {
@@ -212,5 +248,25 @@ mod tests {
         };
         assert!(ConflictCheck::OCC.has_conflict(&map, 1.into(), &tx3_read, &tx3_write));
         assert!(ConflictCheck::SSI.has_conflict(&map, 1.into(), &tx3_read, &tx3_write));
+        assert!(!ConflictCheck::MVCC.has_conflict(&map, 1.into(), &tx3_read, &tx3_write));
+
+        let tx4_read = create_tx_read_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: [
+                    "0000000000000000000000000000000000000000000000000000000000000010",
+                ]
+            },
+        };
+        let tx4_write = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000000" => 1,
+                }
+            },
+        };
+        assert!(ConflictCheck::OCC.has_conflict(&map, 1.into(), &tx4_read, &tx4_write));
+        assert!(ConflictCheck::SSI.has_conflict(&map, 1.into(), &tx4_read, &tx4_write));
+        assert!(ConflictCheck::MVCC.has_conflict(&map, 1.into(), &tx4_read, &tx4_write));
+        assert!(!ConflictCheck::MVCC.has_conflict(&map, 2.into(), &tx4_read, &tx4_write));
     }
 }
@@ -1,17 +1,24 @@
 use crate::{
     block::{block_header_to_digest, BlockHeader, BlockTrait, BlockTxList},
     config::PoWConfig,
+    genesis::genesis_state_root,
 };
 use chrono::{DateTime, Utc};
-use futures::prelude::*;
+use futures::{channel::oneshot, prelude::*};
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
-    basic::{Nonce, H256, U256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
-    error::{ensure, Error, Result},
+    basic::{BlockHeight, Nonce, H256, U256},
+    digest::{digest_state, Digestible},
+    error::{anyhow, ConsensusError, Error, NetworkError, Result},
 };
 use slimchain_utils::record_time;
-use std::time::Instant;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Block {
@@ -21,12 +28,11 @@ pub struct Block {
 }
 
 fn block_hash(header_hash: H256, diff: u64, nonce: Nonce) -> H256 {
-    let mut hash_state = default_blake2().to_state();
+    let mut hash_state = digest_state();
     hash_state.update(header_hash.as_bytes());
     hash_state.update(diff.to_digest().as_bytes());
     hash_state.update(nonce.to_digest().as_bytes());
-    let hash = hash_state.finalize();
-    blake2b_hash_to_h256(hash)
+    hash_state.finalize()
 }
 
 impl Digestible for Block {
@@ -45,7 +51,9 @@ impl BlockTrait for Block {
                     .expect("Failed to parse the timestamp.")
                     .with_timezone(&Utc),
                 tx_list: BlockTxList::default(),
-                state_root: H256::zero(),
+                tx_root: H256::zero(),
+                state_root: genesis_state_root(),
+                epoch_root: H256::zero(),
             },
             diff: PoWConfig::get().init_diff,
             nonce: Nonce::zero(),
@@ -61,16 +69,54 @@ impl BlockTrait for Block {
     }
 }
 
-// Ref:
-// https://ethereum.stackexchange.com/a/1910
-// https://ethereum.github.io/yellowpaper/paper.pdf
 #[inline]
 fn compute_diff(time_stamp: DateTime<Utc>, prev_blk: &Block) -> u64 {
-    compute_diff_inner(time_stamp, prev_blk.diff, prev_blk.header.time_stamp)
+    next_diff(
+        time_stamp,
+        prev_blk.header.height,
+        prev_blk.diff,
+        prev_blk.header.time_stamp,
+    )
+}
+
+// The difficulty of the block following the one at `prev_height`. It is retargeted toward
+// `target_block_interval` if set, or adjusted as in Ethereum otherwise.
+pub fn next_diff(
+    time_stamp: DateTime<Utc>,
+    prev_height: BlockHeight,
+    prev_diff: u64,
+    prev_ts: DateTime<Utc>,
+) -> u64 {
+    let pow_cfg = PoWConfig::get();
+    match pow_cfg.target_block_interval {
+        // The genesis block has a fixed time stamp, which says nothing about the mining power.
+        Some(_) if prev_height == BlockHeight::default() => prev_diff,
+        Some(target) => {
+            let time_span = (time_stamp - prev_ts).num_milliseconds().max(0) as u64;
+            retarget_diff(time_span, prev_diff, target, pow_cfg.retarget_window)
+        }
+        None => homestead_diff(time_stamp, prev_diff, prev_ts),
+    }
+}
+
+// Scale the difficulty by the ratio between the target interval and the moving average of the
+// last `window` intervals, where the latter is approximated by blending the new interval into
+// the target. Applied on every block, it settles at the difficulty whose mean interval is the
+// target, and reacts to a change of the mining power over about `window` blocks.
+#[inline]
+fn retarget_diff(time_span: u64, prev_diff: u64, target: u64, window: u64) -> u64 {
+    let target = target.max(1) as u128;
+    let window = window.max(1) as u128;
+    let avg_time_span = target * (window - 1) + time_span as u128;
+    let diff = prev_diff as u128 * target * window / avg_time_span;
+    diff.clamp(1, u64::MAX as u128) as u64
 }
 
+// Ref:
+// https://ethereum.stackexchange.com/a/1910
+// https://ethereum.github.io/yellowpaper/paper.pdf
 #[inline]
-fn compute_diff_inner(time_stamp: DateTime<Utc>, prev_diff: u64, prev_ts: DateTime<Utc>) -> u64 {
+fn homestead_diff(time_stamp: DateTime<Utc>, prev_diff: u64, prev_ts: DateTime<Utc>) -> u64 {
     let prev_diff = prev_diff as i64;
     let delta = prev_diff / 2048;
     let time_span = (time_stamp - prev_ts).num_seconds() as i64;
@@ -96,9 +142,10 @@ pub fn create_new_block(
 ) -> impl Future<Output = Result<Block>> {
     debug!("Begin mining");
     let begin = Instant::now();
+    let prev_height = prev_blk.header.height;
     let prev_diff = prev_blk.diff;
     let prev_ts = prev_blk.header.time_stamp;
-    let diff = compute_diff_inner(header.time_stamp, prev_diff, prev_ts);
+    let diff = next_diff(header.time_stamp, prev_height, prev_diff, prev_ts);
 
     tokio::task::spawn_blocking(move || {
         let mut blk = Block {
@@ -107,24 +154,23 @@ pub fn create_new_block(
             nonce: Nonce::zero(),
         };
 
-        let tx_list_root = blk.header.tx_list.to_digest();
-
         while !nonce_is_valid(
             block_hash(
                 block_header_to_digest(
                     blk.header.height,
                     blk.header.prev_blk_hash,
                     blk.header.time_stamp,
-                    tx_list_root,
+                    blk.header.tx_root,
                     blk.header.state_root,
+                    blk.header.epoch_root,
                 ),
                 blk.diff,
                 blk.nonce,
             ),
             blk.diff,
         ) {
-            blk.header.set_ts(Utc::now());
-            blk.diff = compute_diff_inner(blk.header.time_stamp, prev_diff, prev_ts);
+            blk.header.set_ts(Utc::now().max(blk.header.time_stamp));
+            blk.diff = next_diff(blk.header.time_stamp, prev_height, prev_diff, prev_ts);
             blk.nonce += 1.into();
         }
 
@@ -136,12 +182,115 @@ pub fn create_new_block(
     .map_err(Error::msg)
 }
 
-pub fn verify_consensus(blk: &Block, prev_blk: &Block) -> Result<()> {
-    ensure!(
-        blk.diff == compute_diff(blk.header.time_stamp, prev_blk),
-        "Invalid difficult."
-    );
-    ensure!(nonce_is_valid(blk.to_digest(), blk.diff), "Invalid nonce");
+// The nonce search of a block, done by a miner outside of the node. The header and the
+// difficulty are fixed, so the miner only needs to vary the nonce.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PoWWork {
+    pub id: u64,
+    pub height: BlockHeight,
+    pub header_hash: H256,
+    pub diff: u64,
+}
+
+impl PoWWork {
+    pub fn block_hash(&self, nonce: Nonce) -> H256 {
+        block_hash(self.header_hash, self.diff, nonce)
+    }
+
+    pub fn nonce_is_valid(&self, nonce: Nonce) -> bool {
+        nonce_is_valid(self.block_hash(nonce), self.diff)
+    }
+}
+
+struct PendingWork {
+    work: PoWWork,
+    nonce_tx: oneshot::Sender<Nonce>,
+}
+
+// Hand out the blocks to mine to the external miners, in place of `create_new_block`. Only the
+// latest work is kept, as the miner builds one block at a time.
+#[derive(Clone, Default)]
+pub struct ExternalMiner {
+    next_id: Arc<AtomicU64>,
+    pending: Arc<Mutex<Option<PendingWork>>>,
+}
+
+impl ExternalMiner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[tracing::instrument(skip(self, header, prev_blk), fields(height = header.height.0))]
+    pub fn create_new_block(
+        &self,
+        header: BlockHeader,
+        prev_blk: &Block,
+    ) -> impl Future<Output = Result<Block>> {
+        debug!("Wait for external miners");
+        let begin = Instant::now();
+        let diff = compute_diff(header.time_stamp, prev_blk);
+        let work = PoWWork {
+            id: self.next_id.fetch_add(1, Ordering::AcqRel),
+            height: header.height,
+            header_hash: header.to_digest(),
+            diff,
+        };
+        let (nonce_tx, nonce_rx) = oneshot::channel();
+        *self
+            .pending
+            .lock()
+            .expect("Failed to lock the pending work.") = Some(PendingWork { work, nonce_tx });
+
+        nonce_rx
+            .map_err(|_| anyhow!("The pending work is dropped."))
+            .map_ok(move |nonce| {
+                let mining_time = Instant::now() - begin;
+                record_time!("mining", mining_time, "height": header.height.0);
+                info!(?mining_time, diff);
+                Block {
+                    header,
+                    diff,
+                    nonce,
+                }
+            })
+    }
+
+    pub fn get_work(&self) -> Option<PoWWork> {
+        self.pending
+            .lock()
+            .expect("Failed to lock the pending work.")
+            .as_ref()
+            .map(|pending| pending.work.clone())
+    }
+
+    pub fn submit_work(&self, id: u64, nonce: Nonce) -> Result<(), NetworkError> {
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("Failed to lock the pending work.");
+        let work = match pending.as_ref() {
+            Some(p) if p.work.id == id => &p.work,
+            _ => return Err(NetworkError::InvalidRequest(format!("Stale work {}.", id))),
+        };
+        if !work.nonce_is_valid(nonce) {
+            return Err(NetworkError::InvalidRequest("Invalid nonce.".to_string()));
+        }
+        if let Some(p) = pending.take() {
+            p.nonce_tx.send(nonce).ok();
+        }
+        Ok(())
+    }
+}
+
+pub fn verify_consensus(blk: &Block, prev_blk: &Block) -> Result<(), ConsensusError> {
+    if blk.diff != compute_diff(blk.header.time_stamp, prev_blk) {
+        return Err(ConsensusError::InvalidBlock(
+            "Invalid difficult.".to_string(),
+        ));
+    }
+    if !nonce_is_valid(blk.to_digest(), blk.diff) {
+        return Err(ConsensusError::InvalidBlock("Invalid nonce.".to_string()));
+    }
 
     Ok(())
 }
@@ -151,6 +300,53 @@ mod tests {
     use super::*;
     use slimchain_utils::config::Config;
 
+    #[tokio::test]
+    async fn test_external_miner() {
+        let miner = ExternalMiner::new();
+        assert!(miner.get_work().is_none());
+
+        let prev_blk = Block::genesis_block();
+        let mut header = prev_blk.header.clone();
+        header.height = header.height.next_height();
+        header.prev_blk_hash = prev_blk.to_digest();
+        header.set_ts(Utc::now());
+        let blk_fut = miner.create_new_block(header, &prev_blk);
+
+        let work = miner.get_work().unwrap();
+        assert_eq!(BlockHeight(1), work.height);
+        assert!(miner.submit_work(work.id + 1, Nonce::zero()).is_err());
+        let nonce = (0u64..)
+            .map(Nonce::from)
+            .find(|&nonce| work.nonce_is_valid(nonce))
+            .unwrap();
+        miner.submit_work(work.id, nonce).unwrap();
+        assert!(miner.get_work().is_none());
+        assert!(miner.submit_work(work.id, nonce).is_err());
+
+        let blk = blk_fut.await.unwrap();
+        assert_eq!(work.block_hash(nonce), blk.to_digest());
+        verify_consensus(&blk, &prev_blk).unwrap();
+    }
+
+    #[test]
+    fn test_retarget_diff() {
+        assert_eq!(1000, retarget_diff(2000, 1000, 2000, 10));
+        assert!(retarget_diff(1000, 1000, 2000, 10) > 1000);
+        assert!(retarget_diff(4000, 1000, 2000, 10) < 1000);
+        assert_eq!(1, retarget_diff(u64::MAX, 1, 2000, 10));
+
+        // The mean interval is diff / hash_rate. Start from a difficulty far from the one
+        // reaching the target, i.e., hash_rate * target.
+        let hash_rate = 500;
+        let target = 2000;
+        let mut diff = 10_000;
+        for _ in 0..500 {
+            diff = retarget_diff(diff / hash_rate, diff, target, 32);
+        }
+        let expect = hash_rate * target;
+        assert!(diff.max(expect) - diff.min(expect) < expect / 20);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_pow() {
@@ -1,11 +1,14 @@
-use crate::block::{BlockHeader, BlockTrait, BlockTxList};
+use crate::{
+    block::{BlockHeader, BlockTrait, BlockTxList},
+    genesis::genesis_state_root,
+};
 use chrono::{DateTime, Utc};
 use futures::prelude::*;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::H256,
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
-    error::Result,
+    digest::{digest_state, Digestible},
+    error::{ConsensusError, Result},
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -15,10 +18,9 @@ pub struct Block {
 
 impl Digestible for Block {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.header.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -32,7 +34,9 @@ impl BlockTrait for Block {
                     .expect("Failed to parse the timestamp.")
                     .with_timezone(&Utc),
                 tx_list: BlockTxList::default(),
-                state_root: H256::zero(),
+                tx_root: H256::zero(),
+                state_root: genesis_state_root(),
+                epoch_root: H256::zero(),
             },
         }
     }
@@ -53,7 +57,7 @@ pub fn create_new_block(
     async move { Ok(Block { header }) }
 }
 
-pub fn verify_consensus(_blk: &Block, _prev_blk: &Block) -> Result<()> {
+pub fn verify_consensus(_blk: &Block, _prev_blk: &Block) -> Result<(), ConsensusError> {
     // the consensus is verified by external Raft network protocol.
     Ok(())
 }
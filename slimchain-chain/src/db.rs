@@ -1,23 +1,47 @@
 use crate::{
+    archive::get_archived_tx,
     block::BlockTrait,
+    config::{DBCompaction, DBConfig},
     loader::{BlockLoaderTrait, TxLoaderTrait},
+    receipt::TxReceipt,
     role::Role,
 };
-use kvdb::{DBKey, DBTransaction, KeyValueDB};
+use kvdb::{DBKey, DBOp, DBTransaction};
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
-    basic::{AccountData, Address, BlockHeight, StateValue, H256},
-    error::{Context as _, Error, Result},
+    basic::{AccountData, Address, BlockHeight, Code, StateValue, H256},
+    error::{bail, ensure, Context as _, Result},
     tx::TxTrait,
 };
 use slimchain_tx_state::{TrieNode, TxStateUpdate, TxStateView};
-use slimchain_utils::{
-    record_event,
-    serde::{binary_decode, binary_encode},
+use slimchain_utils::record_event;
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
-use std::{path::Path, sync::Arc};
 
-pub const TOTAL_COLS: u32 = 5;
+pub mod backend;
+pub use backend::*;
+
+pub mod backup;
+pub use backup::*;
+
+pub mod compression;
+pub use compression::*;
+
+pub mod group_commit;
+pub use group_commit::*;
+
+pub mod iter;
+
+pub mod migration;
+pub use migration::DB_SCHEMA_VERSION;
+
+pub mod stats;
+pub use stats::*;
+
+pub const TOTAL_COLS: u32 = 7;
 // store meta data
 pub const META_DB_COL: u32 = 0;
 // store block height <-> block
@@ -28,6 +52,39 @@ pub const TX_DB_COL: u32 = 2;
 pub const STATE_DB_COL: u32 = 3;
 // store log_idx <-> log
 pub const LOG_DB_COL: u32 = 4;
+// store tx_id <-> tx receipt
+pub const RECEIPT_DB_COL: u32 = 5;
+// store code_hash <-> code
+pub const CODE_DB_COL: u32 = 6;
+
+// The names of the columns used in the config.
+pub const DB_COL_NAMES: [&str; TOTAL_COLS as usize] =
+    ["meta", "block", "tx", "state", "log", "receipt", "code"];
+
+pub fn db_col_from_name(name: &str) -> Result<u32> {
+    match DB_COL_NAMES.iter().position(|&n| n == name) {
+        Some(col) => Ok(col as u32),
+        None => bail!("Unknown database column: {}.", name),
+    }
+}
+
+pub fn rocksdb_config(cfg: &DBConfig) -> Result<kvdb_rocksdb::DatabaseConfig> {
+    let mut rocksdb_cfg = kvdb_rocksdb::DatabaseConfig::with_columns(TOTAL_COLS);
+    rocksdb_cfg.max_open_files = cfg.max_open_files;
+    rocksdb_cfg.compaction = match cfg.compaction {
+        DBCompaction::Ssd => kvdb_rocksdb::CompactionProfile::ssd(),
+        DBCompaction::Hdd => kvdb_rocksdb::CompactionProfile::hdd(),
+    };
+    for col in 0..TOTAL_COLS {
+        rocksdb_cfg.memory_budget.insert(col, cfg.memory_budget);
+    }
+    for (name, &budget) in cfg.column_memory_budget.iter() {
+        rocksdb_cfg
+            .memory_budget
+            .insert(db_col_from_name(name)?, budget);
+    }
+    Ok(rocksdb_cfg)
+}
 
 #[inline]
 pub fn h256_to_db_key(input: H256) -> DBKey {
@@ -57,18 +114,64 @@ pub fn u64_to_db_key(input: u64) -> DBKey {
 }
 
 pub struct DB {
-    db: Box<dyn KeyValueDB>,
+    db: Arc<dyn KvBackend>,
+    compression: ColumnCompression,
+    group_commit: Option<GroupCommit>,
+    // Held exclusively by the backup to block the writes.
+    write_lock: Arc<RwLock<()>>,
 }
 
 pub type DBPtr = Arc<DB>;
 
 impl DB {
+    // The storage engine and its options are taken from the global `DBConfig`.
     pub fn open_or_create(path: &Path, enable_statistics: bool) -> Result<Arc<Self>> {
-        info!("Open database at {}", path.display());
-        let mut cfg = kvdb_rocksdb::DatabaseConfig::with_columns(TOTAL_COLS);
-        cfg.enable_statistics = enable_statistics;
-        let db = kvdb_rocksdb::Database::open(&cfg, &path)?;
-        Ok(Arc::new(Self { db: Box::new(db) }))
+        let cfg = DBConfig::get();
+        let db = open_backend(path, &cfg, enable_statistics)?;
+        Self::new(db, &cfg)
+    }
+
+    // Open an existing database without writing to it. Unlike `open_or_create`, the schema is not
+    // migrated, and it fails if the schema version does not match.
+    pub fn open_read_only(path: &Path) -> Result<Arc<Self>> {
+        let cfg = DBConfig::get();
+        let db: Arc<dyn KvBackend> = Arc::from(open_read_only_backend(path, &cfg)?);
+        let db = Arc::new(Self {
+            db,
+            compression: ColumnCompression::new(&cfg)?,
+            group_commit: None,
+            write_lock: Arc::new(RwLock::new(())),
+        });
+        let version = db.schema_version()?;
+        ensure!(
+            version == DB_SCHEMA_VERSION,
+            "The database schema version {} does not match the supported version {}. \
+             Open it with the node to migrate first.",
+            version,
+            DB_SCHEMA_VERSION
+        );
+        Ok(db)
+    }
+
+    pub fn new(db: Box<dyn KvBackend>, cfg: &DBConfig) -> Result<Arc<Self>> {
+        let db: Arc<dyn KvBackend> = Arc::from(db);
+        let write_lock = Arc::new(RwLock::new(()));
+        let group_commit = cfg.group_commit.then(|| {
+            GroupCommit::new(
+                db.clone(),
+                write_lock.clone(),
+                Duration::from_micros(cfg.group_commit_max_latency),
+                cfg.group_commit_max_batch,
+            )
+        });
+        let db = Arc::new(Self {
+            db,
+            compression: ColumnCompression::new(cfg)?,
+            group_commit,
+            write_lock,
+        });
+        db.migrate()?;
+        Ok(db)
     }
 
     pub fn open_or_create_in_dir(
@@ -78,16 +181,19 @@ impl DB {
     ) -> Result<Arc<Self>> {
         let db_file = match role {
             Role::Client => "client.db",
+            Role::LightClient => "light_client.db",
             Role::Miner => "miner.db",
             Role::Storage(_) => "storage.db",
+            Role::Auditor => "auditor.db",
         };
         Self::open_or_create(&dir.join(db_file), enable_statistics)
     }
 
     #[cfg(test)]
     pub fn load_test() -> Arc<Self> {
-        let db = kvdb_memorydb::create(TOTAL_COLS);
-        Arc::new(Self { db: Box::new(db) })
+        InMemoryDb::new()
+            .open_with_config(&DBConfig::default())
+            .expect("Failed to create the test database.")
     }
 
     pub fn get_object<T: for<'de> Deserialize<'de>>(
@@ -96,12 +202,19 @@ impl DB {
         key: &DBKey,
     ) -> Result<Option<T>> {
         self.db
-            .get(col, key)
-            .map_err(Error::msg)?
-            .map(|bin| binary_decode::<T>(&bin[..]))
+            .get(col, key)?
+            .map(|bin| decode_object::<T>(&bin[..]))
             .transpose()
     }
 
+    pub fn get_tx_bytes(&self, tx_hash: H256) -> Result<Option<Vec<u8>>> {
+        self.db.get(TX_DB_COL, &h256_to_db_key(tx_hash))
+    }
+
+    pub fn get_tx_receipt(&self, tx_id: H256) -> Result<Option<TxReceipt>> {
+        self.get_object(RECEIPT_DB_COL, &h256_to_db_key(tx_id))
+    }
+
     pub fn get_existing_object<T: for<'de> Deserialize<'de>>(
         &self,
         col: u32,
@@ -123,25 +236,70 @@ impl DB {
         self.get_object(LOG_DB_COL, &u64_to_db_key(idx))
     }
 
+    // Iterate the objects in the column whose keys start with `prefix`.
+    pub fn iter_objects_with_prefix<'a, T: for<'de> Deserialize<'de>>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> impl Iterator<Item = Result<(Box<[u8]>, T)>> + 'a {
+        self.db
+            .iter_with_prefix(col, prefix)
+            .map(|(key, value)| Ok((key, decode_object::<T>(&value[..])?)))
+    }
+
+    pub fn get_meta_objects_with_prefix<T: for<'de> Deserialize<'de>>(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, T)>> {
+        self.iter_objects_with_prefix(META_DB_COL, prefix.as_bytes())
+            .map(|item| {
+                let (key, value) = item?;
+                Ok((String::from_utf8(key.into_vec())?, value))
+            })
+            .collect()
+    }
+
     pub fn get_table_size(&self, col: u32) -> usize {
         self.db.iter(col).map(|(k, v)| k.len() + v.len()).sum()
     }
 
+    // Compress the values according to their columns before writing them.
+    fn compress(&self, tx: Transaction) -> Result<DBTransaction> {
+        let mut inner = tx.inner;
+        for op in inner.ops.iter_mut() {
+            if let DBOp::Insert { col, value, .. } = op {
+                *value = self.compression.compress(*col, value)?;
+            }
+        }
+        Ok(inner)
+    }
+
+    fn write(&self, tx: Transaction) -> Result<()> {
+        let batch = self.compress(tx)?;
+        let _guard = self.write_lock.read().expect("Failed to lock DB.");
+        self.db.write(batch)
+    }
+
     pub fn write_sync(&self, tx: Transaction) -> Result<()> {
-        self.db.write(tx.inner).map_err(Error::msg)
+        self.write(tx)
     }
 
+    // With the group commit enabled, the concurrent writes are merged into one.
     pub async fn write_async(self: &Arc<Self>, tx: Transaction) -> Result<()> {
         let this = self.clone();
-        tokio::task::spawn_blocking(move || this.db.write(tx.inner))
-            .await?
-            .map_err(Error::msg)
+        match self.group_commit.as_ref() {
+            Some(group_commit) => {
+                let batch = tokio::task::spawn_blocking(move || this.compress(tx)).await??;
+                group_commit.write(batch).await
+            }
+            None => tokio::task::spawn_blocking(move || this.write(tx)).await?,
+        }
     }
 }
 
 impl Drop for DB {
     fn drop(&mut self) {
-        let stats = self.db.io_stats(kvdb::IoStatsKind::Overall);
+        let stats = self.db.io_stats();
         record_event!("db-io-stats",
             "transactions": stats.transactions,
             "reads": stats.reads,
@@ -152,6 +310,10 @@ impl Drop for DB {
             "bytes_written": stats.bytes_written,
             "span": stats.span,
         );
+        self.compression.record_stats();
+        if let Some(group_commit) = self.group_commit.as_ref() {
+            group_commit.record_stats();
+        }
     }
 }
 
@@ -163,10 +325,14 @@ impl<Block: BlockTrait + for<'de> Deserialize<'de>> BlockLoaderTrait<Block> for
     }
 }
 
-impl<Tx: TxTrait + for<'de> Deserialize<'de>> TxLoaderTrait<Tx> for DB {
+impl<Tx: TxTrait> TxLoaderTrait<Tx> for DB {
     #[tracing::instrument(level = "debug", skip(self), err)]
     fn get_tx(&self, tx_hash: H256) -> Result<Tx> {
-        self.get_existing_object(TX_DB_COL, &h256_to_db_key(tx_hash))
+        if let Some(bytes) = self.get_tx_bytes(tx_hash)? {
+            return decode_tx(&bytes);
+        }
+
+        get_archived_tx(tx_hash)?
             .with_context(|| format!("Failed to get tx from the database. tx_hash: {}", tx_hash))
     }
 }
@@ -197,6 +363,17 @@ impl TxStateView for DB {
                 )
             })
     }
+
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    fn code(&self, code_hash: H256) -> Result<Code> {
+        self.get_existing_object(CODE_DB_COL, &h256_to_db_key(code_hash))
+            .with_context(|| {
+                format!(
+                    "Failed to get code from the database. code_hash: {}",
+                    code_hash
+                )
+            })
+    }
 }
 
 #[derive(Default)]
@@ -215,8 +392,9 @@ impl Transaction {
         }
     }
 
+    // The value is compressed when the transaction is written to the database.
     pub fn insert_object<T: Serialize>(&mut self, col: u32, key: &DBKey, value: &T) -> Result<()> {
-        let bin = binary_encode(value)?;
+        let bin = encode_object(value)?;
         self.inner.put_vec(col, key, bin);
         Ok(())
     }
@@ -237,8 +415,14 @@ impl Transaction {
         )
     }
 
-    pub fn insert_tx<Tx: TxTrait + Serialize>(&mut self, tx_hash: H256, tx: &Tx) -> Result<()> {
-        self.insert_object(TX_DB_COL, &h256_to_db_key(tx_hash), tx)
+    pub fn insert_tx<Tx: TxTrait>(&mut self, tx_hash: H256, tx: &Tx) -> Result<()> {
+        self.inner
+            .put_vec(TX_DB_COL, &h256_to_db_key(tx_hash), tx.to_canonical_bytes());
+        Ok(())
+    }
+
+    pub fn insert_tx_receipt(&mut self, tx_id: H256, receipt: &TxReceipt) -> Result<()> {
+        self.insert_object(RECEIPT_DB_COL, &h256_to_db_key(tx_id), receipt)
     }
 
     pub fn update_state(&mut self, update: &TxStateUpdate) -> Result<()> {
@@ -252,6 +436,10 @@ impl Transaction {
             }
         }
 
+        for (&code_hash, code) in update.codes.iter() {
+            self.insert_object(CODE_DB_COL, &h256_to_db_key(code_hash), code)?;
+        }
+
         Ok(())
     }
 
@@ -262,4 +450,70 @@ impl Transaction {
     pub fn delete_log_object(&mut self, idx: u64) {
         self.delete_object(LOG_DB_COL, &u64_to_db_key(idx))
     }
+
+    pub fn delete_tx(&mut self, tx_hash: H256) {
+        self.delete_object(TX_DB_COL, &h256_to_db_key(tx_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rocksdb_config() {
+        let mut cfg = DBConfig::default();
+        cfg.column_memory_budget.insert("state".to_string(), 512);
+        let rocksdb_cfg = rocksdb_config(&cfg).unwrap();
+        assert_eq!(Some(&512), rocksdb_cfg.memory_budget.get(&STATE_DB_COL));
+        assert_eq!(Some(&128), rocksdb_cfg.memory_budget.get(&BLOCK_DB_COL));
+
+        cfg.column_memory_budget.insert("unknown".to_string(), 64);
+        assert!(rocksdb_config(&cfg).is_err());
+    }
+
+    #[test]
+    fn test_iter_with_prefix() {
+        let db = DB::load_test();
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object("audit-1", &1u64).unwrap();
+        db_tx.insert_meta_object("audit-2", &2u64).unwrap();
+        db_tx.insert_meta_object("height", &3u64).unwrap();
+        db.write_sync(db_tx).unwrap();
+
+        let mut objs = db.get_meta_objects_with_prefix::<u64>("audit-").unwrap();
+        objs.sort();
+        assert_eq!(
+            vec![("audit-1".to_string(), 1), ("audit-2".to_string(), 2)],
+            objs
+        );
+        assert!(db
+            .get_meta_objects_with_prefix::<u64>("none")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_async_with_group_commit() {
+        let cfg = DBConfig {
+            group_commit: true,
+            ..Default::default()
+        };
+        let db = DB::new(Box::new(MemoryBackend::new(TOTAL_COLS)), &cfg).unwrap();
+        futures::future::try_join_all((0..16u64).map(|i| {
+            let mut db_tx = Transaction::new();
+            db_tx.insert_meta_object(&format!("key-{}", i), &i).unwrap();
+            db.write_async(db_tx)
+        }))
+        .await
+        .unwrap();
+
+        for i in 0..16u64 {
+            assert_eq!(
+                i,
+                db.get_existing_meta_object::<u64>(&format!("key-{}", i))
+                    .unwrap()
+            );
+        }
+    }
 }
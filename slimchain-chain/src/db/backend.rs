@@ -0,0 +1,341 @@
+use super::{DBPtr, DB, TOTAL_COLS};
+use crate::config::{DBBackend, DBConfig};
+use kvdb::{DBOp, DBTransaction, IoStats, IoStatsKind, KeyValueDB};
+use slimchain_common::error::{anyhow, bail, Error, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+pub type KeyValuePair = (Box<[u8]>, Box<[u8]>);
+
+// The storage engine underlying `DB`. The keys are grouped into columns.
+pub trait KvBackend: Send + Sync {
+    fn get(&self, col: u32, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    // Apply all operations in the batch atomically.
+    fn write(&self, batch: DBTransaction) -> Result<()>;
+
+    fn put(&self, col: u32, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut batch = DBTransaction::with_capacity(1);
+        batch.put(col, key, value);
+        self.write(batch)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a>;
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a>;
+
+    fn io_stats(&self) -> IoStats {
+        IoStats::empty()
+    }
+
+    fn num_keys(&self, col: u32) -> Result<u64> {
+        Ok(self.iter(col).count() as u64)
+    }
+
+    // The counters reported by the storage engine, if any.
+    fn statistics(&self) -> HashMap<String, u64> {
+        HashMap::new()
+    }
+}
+
+// Any database implementing `kvdb`.
+impl<T: KeyValueDB> KvBackend for T {
+    fn get(&self, col: u32, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        KeyValueDB::get(self, col, key).map_err(Error::msg)
+    }
+
+    fn write(&self, batch: DBTransaction) -> Result<()> {
+        KeyValueDB::write(self, batch).map_err(Error::msg)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        KeyValueDB::iter(self, col)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        KeyValueDB::iter_with_prefix(self, col, prefix)
+    }
+
+    fn io_stats(&self) -> IoStats {
+        KeyValueDB::io_stats(self, IoStatsKind::Overall)
+    }
+}
+
+pub struct RocksDBBackend(kvdb_rocksdb::Database);
+
+impl KvBackend for RocksDBBackend {
+    fn get(&self, col: u32, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.0.get(col, key).map_err(Error::msg)
+    }
+
+    fn write(&self, batch: DBTransaction) -> Result<()> {
+        self.0.write(batch).map_err(Error::msg)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        KeyValueDB::iter(&self.0, col)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        KeyValueDB::iter_with_prefix(&self.0, col, prefix)
+    }
+
+    fn io_stats(&self) -> IoStats {
+        KeyValueDB::io_stats(&self.0, IoStatsKind::Overall)
+    }
+
+    // It is an estimation given by RocksDB.
+    fn num_keys(&self, col: u32) -> Result<u64> {
+        self.0.num_keys(col).map_err(Error::msg)
+    }
+
+    // Only available when the statistics are enabled.
+    fn statistics(&self) -> HashMap<String, u64> {
+        self.0
+            .get_statistics()
+            .into_iter()
+            .map(|(k, v)| (k, v.count))
+            .collect()
+    }
+}
+
+// Keep everything in ordered maps. Nothing is persisted.
+pub struct MemoryBackend {
+    cols: RwLock<Vec<BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl MemoryBackend {
+    pub fn new(num_cols: u32) -> Self {
+        Self {
+            cols: RwLock::new((0..num_cols).map(|_| BTreeMap::new()).collect()),
+        }
+    }
+
+    fn collect<'a>(
+        col: &BTreeMap<Vec<u8>, Vec<u8>>,
+        prefix: &[u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        let items: Vec<KeyValuePair> = col
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone().into_boxed_slice(), v.clone().into_boxed_slice()))
+            .collect();
+        Box::new(items.into_iter())
+    }
+}
+
+impl KvBackend for MemoryBackend {
+    fn get(&self, col: u32, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cols = self.cols.read().expect("Failed to lock MemoryBackend.");
+        match cols.get(col as usize) {
+            Some(map) => Ok(map.get(key).cloned()),
+            None => Err(anyhow!("No such column: {}.", col)),
+        }
+    }
+
+    fn write(&self, batch: DBTransaction) -> Result<()> {
+        let mut cols = self.cols.write().expect("Failed to lock MemoryBackend.");
+        if let Some(op) = batch.ops.iter().find(|op| op.col() as usize >= cols.len()) {
+            bail!("No such column: {}.", op.col());
+        }
+        for op in batch.ops {
+            match op {
+                DBOp::Insert { col, key, value } => {
+                    cols[col as usize].insert(key.into_vec(), value);
+                }
+                DBOp::Delete { col, key } => {
+                    cols[col as usize].remove(&key[..]);
+                }
+                DBOp::DeletePrefix { col, prefix } => {
+                    cols[col as usize].retain(|k, _| !k.starts_with(&prefix[..]));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.iter_with_prefix(col, &[])
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        let cols = self.cols.read().expect("Failed to lock MemoryBackend.");
+        match cols.get(col as usize) {
+            Some(map) => Self::collect(map, prefix),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+// A handle to the in-memory storage, which is kept as long as any handle is alive. It lets the
+// tests and simulations run multiple nodes in one process, and reopen a node with its data.
+#[derive(Clone)]
+pub struct InMemoryDb(Arc<MemoryBackend>);
+
+impl Default for InMemoryDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryDb {
+    pub fn new() -> Self {
+        Self(Arc::new(MemoryBackend::new(TOTAL_COLS)))
+    }
+
+    pub fn open(&self) -> Result<DBPtr> {
+        self.open_with_config(&DBConfig::get())
+    }
+
+    pub fn open_with_config(&self, cfg: &DBConfig) -> Result<DBPtr> {
+        DB::new(Box::new(self.clone()), cfg)
+    }
+}
+
+impl KvBackend for InMemoryDb {
+    fn get(&self, col: u32, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.0.get(col, key)
+    }
+
+    fn write(&self, batch: DBTransaction) -> Result<()> {
+        self.0.write(batch)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.0.iter(col)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.0.iter_with_prefix(col, prefix)
+    }
+}
+
+pub fn open_backend(
+    path: &Path,
+    cfg: &DBConfig,
+    enable_statistics: bool,
+) -> Result<Box<dyn KvBackend>> {
+    match cfg.backend {
+        DBBackend::RocksDB => {
+            info!("Open database at {}", path.display());
+            let mut rocksdb_cfg = super::rocksdb_config(cfg)?;
+            rocksdb_cfg.enable_statistics = enable_statistics;
+            let db = kvdb_rocksdb::Database::open(&rocksdb_cfg, path)?;
+            Ok(Box::new(RocksDBBackend(db)))
+        }
+        DBBackend::Memory => {
+            warn!(
+                "Use in-memory database. {} will not be used.",
+                path.display()
+            );
+            Ok(Box::new(InMemoryDb::new()))
+        }
+    }
+}
+
+// Open the database as a RocksDB secondary instance, which never writes to the primary, so that
+// it can be inspected while the node is running. It sees the data as of the time it is opened.
+pub fn open_read_only_backend(path: &Path, cfg: &DBConfig) -> Result<Box<dyn KvBackend>> {
+    match cfg.backend {
+        DBBackend::RocksDB => {
+            info!("Open database at {} (read-only)", path.display());
+            let mut rocksdb_cfg = super::rocksdb_config(cfg)?;
+            rocksdb_cfg.create_if_missing = false;
+            // The secondary instance requires all the files to be kept open.
+            rocksdb_cfg.max_open_files = -1;
+            // The info logs of the secondary instance are kept apart from the primary.
+            let secondary_path =
+                std::env::temp_dir().join(format!("slimchain-secondary-{}", std::process::id()));
+            rocksdb_cfg.secondary = Some(secondary_path.to_string_lossy().into_owned().into());
+            let db = kvdb_rocksdb::Database::open(&rocksdb_cfg, path)?;
+            Ok(Box::new(RocksDBBackend(db)))
+        }
+        DBBackend::Memory => bail!("The in-memory database cannot be opened read-only."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_backend(backend: &dyn KvBackend) {
+        backend.put(0, b"a1", b"1").unwrap();
+        let mut batch = DBTransaction::new();
+        batch.put(0, b"a2", b"2");
+        batch.put(0, b"b1", b"3");
+        batch.put(1, b"a1", b"4");
+        backend.write(batch).unwrap();
+
+        assert_eq!(Some(b"1".to_vec()), backend.get(0, b"a1").unwrap());
+        assert_eq!(Some(b"4".to_vec()), backend.get(1, b"a1").unwrap());
+        assert_eq!(None, backend.get(1, b"a2").unwrap());
+        assert_eq!(3, backend.iter(0).count());
+        let mut keys: Vec<_> = backend
+            .iter_with_prefix(0, b"a")
+            .map(|(k, _)| k.into_vec())
+            .collect();
+        keys.sort();
+        assert_eq!(vec![b"a1".to_vec(), b"a2".to_vec()], keys);
+
+        let mut batch = DBTransaction::new();
+        batch.delete(0, b"a1");
+        batch.delete_prefix(0, b"b");
+        backend.write(batch).unwrap();
+        assert_eq!(None, backend.get(0, b"a1").unwrap());
+        assert_eq!(1, backend.iter(0).count());
+    }
+
+    #[test]
+    fn test_memory_backend() {
+        let backend = MemoryBackend::new(2);
+        check_backend(&backend);
+        assert!(backend.get(2, b"a1").is_err());
+        assert!(backend.put(2, b"a1", b"1").is_err());
+    }
+
+    #[test]
+    fn test_kvdb_backend() {
+        check_backend(&kvdb_memorydb::create(2));
+    }
+
+    #[test]
+    fn test_in_memory_db() {
+        let storage = InMemoryDb::new();
+        let db = storage.open_with_config(&DBConfig::default()).unwrap();
+        let mut db_tx = crate::db::Transaction::new();
+        db_tx.insert_meta_object("height", &1u64).unwrap();
+        db.write_sync(db_tx).unwrap();
+        drop(db);
+
+        let db = storage.open_with_config(&DBConfig::default()).unwrap();
+        assert_eq!(Some(1u64), db.get_meta_object("height").unwrap());
+        let db2 = InMemoryDb::new()
+            .open_with_config(&DBConfig::default())
+            .unwrap();
+        assert_eq!(None, db2.get_meta_object::<u64>("height").unwrap());
+    }
+}
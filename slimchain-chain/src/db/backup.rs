@@ -0,0 +1,182 @@
+use super::{open_backend, KeyValuePair, KvBackend, DB, TOTAL_COLS};
+use crate::config::{DBBackend, DBConfig};
+use kvdb::DBTransaction;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::BlockHeight,
+    error::{anyhow, ensure, Result},
+};
+use std::path::{Component, Path, PathBuf};
+
+// Limit the memory used when copying the keys.
+const BACKUP_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupInfo {
+    // The height of the latest committed block in the backup.
+    pub height: BlockHeight,
+    pub num_keys: u64,
+}
+
+type ColumnIter<'a> = Box<dyn Iterator<Item = KeyValuePair> + 'a>;
+
+fn copy_cols<'a>(cols: Vec<(u32, ColumnIter<'a>)>, dst: &dyn KvBackend) -> Result<u64> {
+    let mut num_keys = 0;
+    for (col, iter) in cols {
+        let mut batch = DBTransaction::with_capacity(BACKUP_BATCH_SIZE);
+        for (key, value) in iter {
+            batch.put(col, &key, &value);
+            num_keys += 1;
+            if batch.ops.len() >= BACKUP_BATCH_SIZE {
+                dst.write(std::mem::take(&mut batch))?;
+            }
+        }
+        dst.write(batch)?;
+    }
+    Ok(num_keys)
+}
+
+fn copy_db(src: &dyn KvBackend, dst: &dyn KvBackend) -> Result<u64> {
+    copy_cols(
+        (0..TOTAL_COLS).map(|col| (col, src.iter(col))).collect(),
+        dst,
+    )
+}
+
+// The backups are confined to the backup directory, so that the admin endpoints cannot be used
+// to overwrite the other files on the node.
+fn backup_path(backup_dir: Option<&Path>, path: &Path) -> Result<PathBuf> {
+    let backup_dir =
+        backup_dir.ok_or_else(|| anyhow!("The backups require backup_dir to be set."))?;
+    ensure!(
+        path.components().next().is_some()
+            && path.components().all(|c| matches!(c, Component::Normal(_))),
+        "The backup path {} should be relative to backup_dir, without \"..\".",
+        path.display()
+    );
+    let path = backup_dir.join(path);
+    ensure!(
+        !path.exists(),
+        "The backup {} already exists.",
+        path.display()
+    );
+    Ok(path)
+}
+
+impl DB {
+    fn backup_info(&self, num_keys: u64) -> Result<BackupInfo> {
+        Ok(BackupInfo {
+            height: self.get_meta_object("height")?.unwrap_or_default(),
+            num_keys,
+        })
+    }
+
+    // Each column is copied through an iterator, which reads from the snapshot taken once it is
+    // created (implicitly in RocksDB). So the writes are only blocked while the iterators are
+    // created, and the backup is still consistent with the latest committed block. The values are
+    // copied as they are, without being decompressed.
+    pub fn backup_to(&self, dst: &dyn KvBackend) -> Result<BackupInfo> {
+        let (height, cols) = {
+            let _guard = self.write_lock.write().expect("Failed to lock DB.");
+            let height = self.get_meta_object("height")?.unwrap_or_default();
+            let cols: Vec<_> = (0..TOTAL_COLS)
+                .map(|col| (col, self.db.iter(col)))
+                .collect();
+            (height, cols)
+        };
+        let num_keys = copy_cols(cols, dst)?;
+        Ok(BackupInfo { height, num_keys })
+    }
+
+    // Back up the database to a new RocksDB database at `path` in the configured backup_dir.
+    pub fn backup(&self, path: &Path) -> Result<BackupInfo> {
+        let cfg = DBConfig {
+            backend: DBBackend::RocksDB,
+            ..DBConfig::get()
+        };
+        let path = backup_path(cfg.backup_dir.as_deref(), path)?;
+        info!("Back up database to {}", path.display());
+        let dst = open_backend(&path, &cfg, false)?;
+        let info = self.backup_to(dst.as_ref())?;
+        info!("Backup done. {:?}", info);
+        Ok(info)
+    }
+
+    // Replace everything in the database with the backup.
+    // It should be done before the node starts, as the states kept in memory are not reloaded.
+    pub fn restore_from(&self, src: &dyn KvBackend) -> Result<BackupInfo> {
+        let num_keys = {
+            let _guard = self.write_lock.write().expect("Failed to lock DB.");
+            let mut batch = DBTransaction::with_capacity(TOTAL_COLS as usize);
+            for col in 0..TOTAL_COLS {
+                batch.delete_prefix(col, &[]);
+            }
+            self.db.write(batch)?;
+            copy_db(src, self.db.as_ref())?
+        };
+        // The backup may be taken by an older version.
+        self.migrate()?;
+        self.backup_info(num_keys)
+    }
+
+    pub fn restore(&self, path: &Path) -> Result<BackupInfo> {
+        info!("Restore database from {}", path.display());
+        let cfg = DBConfig {
+            backend: DBBackend::RocksDB,
+            ..DBConfig::get()
+        };
+        let src = open_backend(path, &cfg, false)?;
+        let info = self.restore_from(src.as_ref())?;
+        info!("Restore done. {:?}", info);
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{MemoryBackend, Transaction};
+
+    #[test]
+    fn test_backup_and_restore() {
+        let db = DB::load_test();
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object("height", &BlockHeight(2)).unwrap();
+        for i in 0..100u64 {
+            db_tx.insert_log_object(i, &i).unwrap();
+        }
+        db.write_sync(db_tx).unwrap();
+
+        let backup = MemoryBackend::new(TOTAL_COLS);
+        let info = db.backup_to(&backup).unwrap();
+        assert_eq!(BlockHeight(2), info.height);
+        assert_eq!(102, info.num_keys);
+
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object("height", &BlockHeight(3)).unwrap();
+        db_tx.insert_log_object(100, &100u64).unwrap();
+        db_tx.delete_log_object(0);
+        db.write_sync(db_tx).unwrap();
+
+        let info = db.restore_from(&backup).unwrap();
+        assert_eq!(BlockHeight(2), info.height);
+        assert_eq!(102, info.num_keys);
+        assert_eq!(Some(0u64), db.get_log_object(0).unwrap());
+        assert_eq!(None, db.get_log_object::<u64>(100).unwrap());
+    }
+
+    #[test]
+    fn test_backup_path() {
+        let backup_dir = Path::new("/tmp/slimchain-backup-test");
+        assert!(backup_path(None, Path::new("a")).is_err());
+        assert_eq!(
+            backup_dir.join("a/b"),
+            backup_path(Some(backup_dir), Path::new("a/b")).unwrap()
+        );
+        assert!(backup_path(Some(backup_dir), Path::new("")).is_err());
+        assert!(backup_path(Some(backup_dir), Path::new("/etc")).is_err());
+        assert!(backup_path(Some(backup_dir), Path::new("../a")).is_err());
+        assert!(backup_path(Some(backup_dir), Path::new("a/../../b")).is_err());
+        assert!(backup_path(Some(backup_dir), Path::new("./a")).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use super::{db_col_from_name, DB_COL_NAMES, TOTAL_COLS};
+use crate::config::{DBCompression, DBConfig};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    error::{bail, Result},
+    tx::TxTrait,
+};
+use slimchain_utils::{
+    record_event,
+    serde::{binary_decode_raw, binary_encode_raw},
+};
+use snap::{read::FrameDecoder, write::FrameEncoder};
+use std::{
+    io::{Read, Write},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// The first byte of the values indicates how they are compressed. Snappy values are written in
+// the framed format used by `binary_encode`, which always starts with `SNAPPY_FRAME_TAG`.
+const SNAPPY_FRAME_TAG: u8 = 0xff;
+const NONE_TAG: u8 = 0;
+const ZSTD_TAG: u8 = 1;
+const LZ4_TAG: u8 = 2;
+
+fn check_compression(compression: DBCompression) -> Result<()> {
+    match compression {
+        #[cfg(not(feature = "zstd"))]
+        DBCompression::Zstd => bail!("Zstd compression is not enabled in this build."),
+        #[cfg(not(feature = "lz4_flex"))]
+        DBCompression::Lz4 => bail!("Lz4 compression is not enabled in this build."),
+        _ => Ok(()),
+    }
+}
+
+#[allow(unused_variables)]
+pub fn compress_value(compression: DBCompression, zstd_level: i32, raw: &[u8]) -> Result<Vec<u8>> {
+    check_compression(compression)?;
+    let mut out = match compression {
+        DBCompression::Snappy => {
+            let mut encoder = FrameEncoder::new(Vec::with_capacity(raw.len() / 2 + 16));
+            encoder.write_all(raw)?;
+            return Ok(encoder.into_inner()?);
+        }
+        DBCompression::None => {
+            let mut out = Vec::with_capacity(raw.len() + 1);
+            out.push(NONE_TAG);
+            out.extend_from_slice(raw);
+            out
+        }
+        #[cfg(feature = "zstd")]
+        DBCompression::Zstd => {
+            let mut out = vec![ZSTD_TAG];
+            out.extend(zstd::stream::encode_all(raw, zstd_level)?);
+            out
+        }
+        #[cfg(feature = "lz4_flex")]
+        DBCompression::Lz4 => {
+            let mut out = vec![LZ4_TAG];
+            out.extend(lz4_flex::compress_prepend_size(raw));
+            out
+        }
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    };
+    out.shrink_to_fit();
+    Ok(out)
+}
+
+pub fn decompress_value(bytes: &[u8]) -> Result<Vec<u8>> {
+    match bytes.first() {
+        None | Some(&SNAPPY_FRAME_TAG) => {
+            let mut raw = Vec::new();
+            FrameDecoder::new(bytes).read_to_end(&mut raw)?;
+            Ok(raw)
+        }
+        Some(&NONE_TAG) => Ok(bytes[1..].to_vec()),
+        #[cfg(feature = "zstd")]
+        Some(&ZSTD_TAG) => Ok(zstd::stream::decode_all(&bytes[1..])?),
+        #[cfg(not(feature = "zstd"))]
+        Some(&ZSTD_TAG) => bail!("Zstd compression is not enabled in this build."),
+        #[cfg(feature = "lz4_flex")]
+        Some(&LZ4_TAG) => Ok(lz4_flex::decompress_size_prepended(&bytes[1..])
+            .map_err(slimchain_common::error::Error::msg)?),
+        #[cfg(not(feature = "lz4_flex"))]
+        Some(&LZ4_TAG) => bail!("Lz4 compression is not enabled in this build."),
+        Some(tag) => bail!("Unknown compression tag {}.", tag),
+    }
+}
+
+pub fn encode_object<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    binary_encode_raw(value)
+}
+
+// Decode the object stored in the database, whichever compression is used.
+pub fn decode_object<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T> {
+    binary_decode_raw(&decompress_value(bytes)?)
+}
+
+// The txs are stored in the canonical encoding instead. See `TxTrait`.
+pub fn decode_tx<Tx: TxTrait>(bytes: &[u8]) -> Result<Tx> {
+    Tx::from_canonical_bytes(&decompress_value(bytes)?)
+}
+
+// How the values of each column are compressed, together with the compression ratio achieved.
+pub struct ColumnCompression {
+    compression: [DBCompression; TOTAL_COLS as usize],
+    zstd_level: i32,
+    raw_bytes: [AtomicU64; TOTAL_COLS as usize],
+    compressed_bytes: [AtomicU64; TOTAL_COLS as usize],
+}
+
+impl ColumnCompression {
+    pub fn new(cfg: &DBConfig) -> Result<Self> {
+        let mut compression = [cfg.compression; TOTAL_COLS as usize];
+        for (name, &c) in cfg.column_compression.iter() {
+            compression[db_col_from_name(name)? as usize] = c;
+        }
+        for &c in compression.iter() {
+            check_compression(c)?;
+        }
+        Ok(Self {
+            compression,
+            zstd_level: cfg.zstd_level,
+            raw_bytes: Default::default(),
+            compressed_bytes: Default::default(),
+        })
+    }
+
+    pub fn compress(&self, col: u32, raw: &[u8]) -> Result<Vec<u8>> {
+        let compressed = compress_value(self.compression[col as usize], self.zstd_level, raw)?;
+        self.raw_bytes[col as usize].fetch_add(raw.len() as u64, Ordering::Relaxed);
+        self.compressed_bytes[col as usize].fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        Ok(compressed)
+    }
+
+    // The bytes written to the column, before and after the compression.
+    pub fn bytes_written(&self, col: u32) -> (u64, u64) {
+        (
+            self.raw_bytes[col as usize].load(Ordering::Relaxed),
+            self.compressed_bytes[col as usize].load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn record_stats(&self) {
+        for (col, name) in DB_COL_NAMES.iter().enumerate() {
+            let (raw_bytes, compressed_bytes) = self.bytes_written(col as u32);
+            if raw_bytes == 0 {
+                continue;
+            }
+            record_event!("db-compression-stats",
+                "col": name,
+                "compression": format!("{:?}", self.compression[col]),
+                "raw_bytes": raw_bytes,
+                "compressed_bytes": compressed_bytes,
+                "ratio": compressed_bytes as f64 / raw_bytes as f64,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{BLOCK_DB_COL, STATE_DB_COL};
+    use slimchain_utils::serde::binary_encode;
+
+    #[test]
+    fn test_compression() {
+        let value: Vec<u64> = (0..1000).map(|i| i % 10).collect();
+        let raw = encode_object(&value).unwrap();
+
+        let mut compressions = vec![DBCompression::Snappy, DBCompression::None];
+        #[cfg(feature = "zstd")]
+        compressions.push(DBCompression::Zstd);
+        #[cfg(feature = "lz4_flex")]
+        compressions.push(DBCompression::Lz4);
+        for compression in compressions {
+            let bytes = compress_value(compression, 3, &raw).unwrap();
+            assert_eq!(raw, decompress_value(&bytes).unwrap());
+            assert_eq!(value, decode_object::<Vec<u64>>(&bytes).unwrap());
+        }
+
+        // Values written by `binary_encode` can still be read.
+        let bytes = binary_encode(&value).unwrap();
+        assert_eq!(
+            bytes,
+            compress_value(DBCompression::Snappy, 3, &raw).unwrap()
+        );
+        assert_eq!(value, decode_object::<Vec<u64>>(&bytes).unwrap());
+        assert!(decompress_value(&[42]).is_err());
+    }
+
+    #[test]
+    fn test_column_compression() {
+        let mut cfg = DBConfig::default();
+        cfg.column_compression
+            .insert("block".to_string(), DBCompression::None);
+        let compression = ColumnCompression::new(&cfg).unwrap();
+        let raw = encode_object(&vec![0u64; 100]).unwrap();
+        let bytes = compression.compress(BLOCK_DB_COL, &raw).unwrap();
+        assert_eq!(raw.len() + 1, bytes.len());
+        let bytes = compression.compress(STATE_DB_COL, &raw).unwrap();
+        assert!(bytes.len() < raw.len());
+
+        cfg.column_compression
+            .insert("unknown".to_string(), DBCompression::None);
+        assert!(ColumnCompression::new(&cfg).is_err());
+    }
+}
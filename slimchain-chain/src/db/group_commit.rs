@@ -0,0 +1,187 @@
+use super::KvBackend;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use kvdb::DBTransaction;
+use slimchain_common::error::{anyhow, Result};
+use slimchain_utils::record_event;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+
+struct WriteRequest {
+    batch: DBTransaction,
+    resp_tx: oneshot::Sender<Result<()>>,
+}
+
+#[derive(Default)]
+struct GroupCommitStats {
+    writes: AtomicU64,
+    transactions: AtomicU64,
+}
+
+// Merge the concurrent transactions into one write to the backend.
+//
+// The first transaction waits at most `max_latency` for the others to join the group. A group
+// contains at most `max_batch` transactions, which are applied in their arrival order.
+pub struct GroupCommit {
+    req_tx: Option<Sender<WriteRequest>>,
+    handler: Option<JoinHandle<()>>,
+    stats: Arc<GroupCommitStats>,
+}
+
+impl GroupCommit {
+    pub fn new(
+        db: Arc<dyn KvBackend>,
+        write_lock: Arc<RwLock<()>>,
+        max_latency: Duration,
+        max_batch: usize,
+    ) -> Self {
+        let (req_tx, req_rx) = unbounded();
+        let stats = Arc::new(GroupCommitStats::default());
+        let stats_copy = stats.clone();
+        let handler = thread::spawn(move || {
+            Self::write_worker(
+                db,
+                write_lock,
+                req_rx,
+                max_latency,
+                max_batch.max(1),
+                stats_copy,
+            )
+        });
+        Self {
+            req_tx: Some(req_tx),
+            handler: Some(handler),
+            stats,
+        }
+    }
+
+    fn write_worker(
+        db: Arc<dyn KvBackend>,
+        write_lock: Arc<RwLock<()>>,
+        req_rx: Receiver<WriteRequest>,
+        max_latency: Duration,
+        max_batch: usize,
+        stats: Arc<GroupCommitStats>,
+    ) {
+        while let Ok(first) = req_rx.recv() {
+            let deadline = Instant::now() + max_latency;
+            let mut reqs = vec![first];
+            while reqs.len() < max_batch {
+                match req_rx.recv_deadline(deadline) {
+                    Ok(req) => reqs.push(req),
+                    Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+
+            let mut batch =
+                DBTransaction::with_capacity(reqs.iter().map(|r| r.batch.ops.len()).sum());
+            let mut resp_txs = Vec::with_capacity(reqs.len());
+            for req in reqs {
+                batch.ops.extend(req.batch.ops);
+                resp_txs.push(req.resp_tx);
+            }
+
+            stats.writes.fetch_add(1, Ordering::Relaxed);
+            stats
+                .transactions
+                .fetch_add(resp_txs.len() as u64, Ordering::Relaxed);
+            let res = {
+                let _guard = write_lock.read().expect("Failed to lock DB.");
+                db.write(batch)
+            };
+            match res {
+                Ok(()) => {
+                    for resp_tx in resp_txs {
+                        resp_tx.send(Ok(())).ok();
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to write to the database. Error: {}", e);
+                    for resp_tx in resp_txs {
+                        resp_tx
+                            .send(Err(anyhow!(
+                                "Failed to write to the database. Error: {}",
+                                e
+                            )))
+                            .ok();
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn write(&self, batch: DBTransaction) -> Result<()> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.req_tx
+            .as_ref()
+            .ok_or_else(|| anyhow!("Group commit is shut down."))?
+            .send(WriteRequest { batch, resp_tx })
+            .map_err(|_| anyhow!("Failed to send the write request."))?;
+        resp_rx.await?
+    }
+
+    pub fn record_stats(&self) {
+        let writes = self.stats.writes.load(Ordering::Relaxed);
+        let transactions = self.stats.transactions.load(Ordering::Relaxed);
+        if writes == 0 {
+            return;
+        }
+        record_event!("db-group-commit-stats",
+            "writes": writes,
+            "transactions": transactions,
+            "avg_group_size": transactions as f64 / writes as f64,
+        );
+    }
+}
+
+impl Drop for GroupCommit {
+    fn drop(&mut self) {
+        // Closing the channel stops the worker once the pending writes are done.
+        self.req_tx.take();
+        if let Some(handler) = self.handler.take() {
+            handler.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryBackend;
+    use futures::future::try_join_all;
+
+    #[tokio::test]
+    async fn test_group_commit() {
+        let db: Arc<dyn KvBackend> = Arc::new(MemoryBackend::new(1));
+        let group_commit = GroupCommit::new(
+            db.clone(),
+            Arc::new(RwLock::new(())),
+            Duration::from_millis(10),
+            8,
+        );
+        try_join_all((0..32u8).map(|i| {
+            let mut batch = DBTransaction::new();
+            batch.put(0, &[i], &[i]);
+            batch.delete(0, &[i, 0]);
+            group_commit.write(batch)
+        }))
+        .await
+        .unwrap();
+
+        assert_eq!(32, db.iter(0).count());
+        assert_eq!(Some(vec![31]), db.get(0, &[31]).unwrap());
+        let writes = group_commit.stats.writes.load(Ordering::Relaxed);
+        assert!((4..32).contains(&writes));
+        assert_eq!(32, group_commit.stats.transactions.load(Ordering::Relaxed));
+
+        let mut batch = DBTransaction::new();
+        batch.put(1, b"k", b"v");
+        assert!(group_commit.write(batch).await.is_err());
+    }
+}
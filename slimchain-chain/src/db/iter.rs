@@ -0,0 +1,149 @@
+use super::DB;
+use crate::{
+    block::BlockTrait,
+    loader::{BlockLoaderTrait, TxLoaderTrait},
+};
+use serde::Deserialize;
+use slimchain_common::{basic::BlockHeight, error::Result, tx::TxTrait};
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+
+impl DB {
+    // An unbounded range ends at the latest committed block.
+    fn resolve_height_range(
+        &self,
+        range: impl RangeBounds<BlockHeight>,
+    ) -> Result<RangeInclusive<u64>> {
+        let start = match range.start_bound() {
+            Bound::Included(h) => h.0,
+            Bound::Excluded(h) => h.0.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(h) => h.0,
+            Bound::Excluded(h) if h.0 == 0 => return Ok(RangeInclusive::new(1, 0)),
+            Bound::Excluded(h) => h.0 - 1,
+            Bound::Unbounded => {
+                let height: BlockHeight = self.get_meta_object("height")?.unwrap_or_default();
+                height.0
+            }
+        };
+        Ok(start..=end)
+    }
+
+    // Iterate the blocks in the range of heights, in the ascending order.
+    pub fn iter_blocks<'a, Block: BlockTrait + for<'de> Deserialize<'de> + 'a>(
+        &'a self,
+        range: impl RangeBounds<BlockHeight>,
+    ) -> Result<impl Iterator<Item = Result<Block>> + 'a> {
+        Ok(self
+            .resolve_height_range(range)?
+            .map(move |height| self.get_block(BlockHeight(height))))
+    }
+
+    // Iterate the txs in the blocks in the range of heights, together with the block heights.
+    pub fn iter_txs<'a, Tx, Block>(
+        &'a self,
+        range: impl RangeBounds<BlockHeight>,
+    ) -> Result<impl Iterator<Item = Result<(BlockHeight, Tx)>> + 'a>
+    where
+        Tx: TxTrait + for<'de> Deserialize<'de> + 'a,
+        Block: BlockTrait + for<'de> Deserialize<'de> + 'a,
+    {
+        Ok(self.iter_blocks::<Block>(range)?.flat_map(
+            move |block| -> Box<dyn Iterator<Item = Result<(BlockHeight, Tx)>> + 'a> {
+                match block {
+                    Ok(block) => {
+                        let height = block.block_height();
+                        let tx_hashes: Vec<_> = block.tx_list().iter().copied().collect();
+                        Box::new(
+                            tx_hashes
+                                .into_iter()
+                                .map(move |tx_hash| self.get_tx(tx_hash).map(|tx| (height, tx))),
+                        )
+                    }
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::{BlockHeader, BlockTxList},
+        consensus::raft::{create_new_block, Block},
+        db::Transaction,
+        tests::create_signed_tx,
+    };
+    use chrono::Utc;
+    use slimchain_common::{basic::H256, digest::Digestible, ed25519::Keypair, tx::SignedTx};
+
+    #[tokio::test]
+    async fn test_iter_blocks_and_txs() {
+        let db = DB::load_test();
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let txs: Vec<SignedTx> = (1..=3).map(|i| create_signed_tx(i, &keypair)).collect();
+
+        let mut db_tx = Transaction::new();
+        let mut prev_blk = Block::genesis_block();
+        let mut blocks = vec![prev_blk.clone()];
+        for (height, block_txs) in [(1, &txs[..2]), (2, &txs[2..])] {
+            let blk = create_new_block(
+                BlockHeader::new(
+                    BlockHeight(height),
+                    prev_blk.to_digest(),
+                    Utc::now(),
+                    block_txs.iter().collect::<BlockTxList>(),
+                    H256::zero(),
+                ),
+                &prev_blk,
+            )
+            .await
+            .unwrap();
+            for tx in block_txs {
+                db_tx.insert_tx(tx.to_digest(), tx).unwrap();
+            }
+            db_tx.insert_block(&blk).unwrap();
+            blocks.push(blk.clone());
+            prev_blk = blk;
+        }
+        db_tx.insert_meta_object("height", &BlockHeight(2)).unwrap();
+        db.write_sync(db_tx).unwrap();
+
+        let all: Vec<Block> = db.iter_blocks(..).unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(blocks, all);
+        let part: Vec<Block> = db
+            .iter_blocks(BlockHeight(1)..BlockHeight(2))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(&blocks[1..2], &part[..]);
+        assert_eq!(
+            0,
+            db.iter_blocks::<Block>(..BlockHeight(0)).unwrap().count()
+        );
+        assert!(db
+            .iter_blocks::<Block>(BlockHeight(2)..=BlockHeight(3))
+            .unwrap()
+            .last()
+            .unwrap()
+            .is_err());
+
+        let all_txs: Vec<(BlockHeight, SignedTx)> = db
+            .iter_txs::<_, Block>(BlockHeight(1)..)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (BlockHeight(1), txs[0].clone()),
+                (BlockHeight(1), txs[1].clone()),
+                (BlockHeight(2), txs[2].clone()),
+            ],
+            all_txs
+        );
+    }
+}
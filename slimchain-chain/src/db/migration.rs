@@ -0,0 +1,173 @@
+use super::{Transaction, DB, LOG_DB_COL, STATE_DB_COL, TOTAL_COLS, TX_DB_COL};
+use slimchain_common::error::{bail, Context as _, Result};
+
+// Bump it whenever the column layout or the serialization format changes, and register the
+// migration from the previous version in `MIGRATIONS`.
+pub const DB_SCHEMA_VERSION: u32 = 4;
+const DB_SCHEMA_VERSION_KEY: &str = "schema-version";
+
+pub struct Migration {
+    // The version it migrates from. It migrates to `from + 1`.
+    pub from: u32,
+    pub description: &'static str,
+    pub migrate: fn(&DB) -> Result<()>,
+}
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "Databases created before the schema is versioned. \
+                      The values without the compression tag are still readable.",
+        migrate: |_db| Ok(()),
+    },
+    // Version 2 also covers the chain id and the expiry height added to the tx requests, which
+    // landed without a version bump of its own.
+    Migration {
+        from: 1,
+        description: "The tx requests gain the chain id and the expiry height. \
+                      The code is moved out of the account trie nodes into the code column.",
+        migrate: |db| {
+            ensure_no_txs(db)?;
+            migrate_inline_code(db)
+        },
+    },
+    Migration {
+        from: 2,
+        description: "The txs gain the block timestamp.",
+        migrate: ensure_no_txs,
+    },
+    Migration {
+        from: 3,
+        description: "The txs are stored in the canonical encoding, and signed over it.",
+        migrate: ensure_no_txs,
+    },
+];
+
+// The account trie nodes and the state trie nodes share the column and cannot be told apart,
+// so the nodes with the inline code are not rewritten in place.
+fn migrate_inline_code(db: &DB) -> Result<()> {
+    if db.db.iter(STATE_DB_COL).next().is_some() {
+        bail!(
+            "The state stores the code inline in the account trie nodes. \
+             Resync the node from the genesis instead."
+        );
+    }
+    Ok(())
+}
+
+// The txs are stored as a whole, in the tx column and in the raft log, so a change of the tx
+// format cannot be migrated in place.
+fn ensure_no_txs(db: &DB) -> Result<()> {
+    if db.db.iter(TX_DB_COL).next().is_some() || db.db.iter(LOG_DB_COL).next().is_some() {
+        bail!("The txs are stored in an older format. Resync the node from the genesis instead.");
+    }
+    Ok(())
+}
+
+impl DB {
+    fn is_empty(&self) -> bool {
+        (0..TOTAL_COLS).all(|col| self.db.iter(col).next().is_none())
+    }
+
+    pub fn schema_version(&self) -> Result<u32> {
+        Ok(self
+            .get_meta_object(DB_SCHEMA_VERSION_KEY)
+            .context("Failed to read the database schema version.")?
+            .unwrap_or_default())
+    }
+
+    fn set_schema_version(&self, version: u32) -> Result<()> {
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object(DB_SCHEMA_VERSION_KEY, &version)?;
+        self.write_sync(db_tx)
+    }
+
+    // Run the migrations needed to bring the database to the current version.
+    pub(crate) fn migrate(&self) -> Result<()> {
+        if self.is_empty() {
+            return self.set_schema_version(DB_SCHEMA_VERSION);
+        }
+
+        let mut version = self.schema_version()?;
+        if version > DB_SCHEMA_VERSION {
+            bail!(
+                "The database schema version {} is newer than the supported version {}.",
+                version,
+                DB_SCHEMA_VERSION
+            );
+        }
+
+        while version < DB_SCHEMA_VERSION {
+            let migration = match MIGRATIONS.iter().find(|m| m.from == version) {
+                Some(migration) => migration,
+                None => bail!("No migration from the database schema version {}.", version),
+            };
+            info!(
+                "Migrate database schema from version {} to {}. {}",
+                version,
+                version + 1,
+                migration.description
+            );
+            (migration.migrate)(self).with_context(|| {
+                format!(
+                    "Failed to migrate database schema from version {}.",
+                    version
+                )
+            })?;
+            version += 1;
+            self.set_schema_version(version)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::DBConfig,
+        db::{str_to_db_key, InMemoryDb, META_DB_COL},
+    };
+
+    #[test]
+    fn test_migrate() {
+        let cfg = DBConfig::default();
+        let storage = InMemoryDb::new();
+        let db = storage.open_with_config(&cfg).unwrap();
+        assert_eq!(DB_SCHEMA_VERSION, db.schema_version().unwrap());
+
+        // Mimic the databases created before the versioning.
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object("height", &1u64).unwrap();
+        db_tx.delete_object(META_DB_COL, &str_to_db_key(DB_SCHEMA_VERSION_KEY));
+        db.write_sync(db_tx).unwrap();
+        assert_eq!(0, db.schema_version().unwrap());
+        drop(db);
+
+        let db = storage.open_with_config(&cfg).unwrap();
+        assert_eq!(DB_SCHEMA_VERSION, db.schema_version().unwrap());
+        assert_eq!(Some(1u64), db.get_meta_object("height").unwrap());
+
+        db.set_schema_version(DB_SCHEMA_VERSION + 1).unwrap();
+        drop(db);
+        assert!(storage.open_with_config(&cfg).is_err());
+    }
+
+    #[test]
+    fn test_migrate_old_txs() {
+        let cfg = DBConfig::default();
+        let storage = InMemoryDb::new();
+        let db = storage.open_with_config(&cfg).unwrap();
+
+        // The txs without the block timestamp.
+        let mut db_tx = Transaction::new();
+        db_tx
+            .insert_object(TX_DB_COL, &str_to_db_key("tx"), &1u64)
+            .unwrap();
+        db.write_sync(db_tx).unwrap();
+        db.set_schema_version(2).unwrap();
+        drop(db);
+        assert!(storage.open_with_config(&cfg).is_err());
+    }
+}
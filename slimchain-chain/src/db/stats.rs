@@ -0,0 +1,163 @@
+use super::{DB, DB_COL_NAMES};
+use serde::{Deserialize, Serialize};
+use slimchain_common::error::Result;
+use slimchain_utils::record_event;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    pub name: String,
+    pub num_keys: u64,
+    // The bytes written since the database is opened, before and after the compression.
+    pub raw_bytes_written: u64,
+    pub bytes_written: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DBStats {
+    pub columns: Vec<ColumnStats>,
+    pub reads: u64,
+    pub cache_reads: u64,
+    pub writes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    // The followings are only available in RocksDB with the statistics enabled.
+    pub block_cache_hit_rate: Option<f64>,
+    pub write_amplification: Option<f64>,
+    pub compaction_read_bytes: Option<u64>,
+    pub compaction_write_bytes: Option<u64>,
+    // The time of the writes being stalled, which indicates too many pending compactions.
+    pub stall_micros: Option<u64>,
+}
+
+impl DBStats {
+    fn fill_statistics(&mut self, statistics: &HashMap<String, u64>) {
+        let get = |key: &str| statistics.get(key).copied();
+        if let (Some(hit), Some(miss)) = (
+            get("rocksdb.block.cache.hit"),
+            get("rocksdb.block.cache.miss"),
+        ) {
+            if hit + miss > 0 {
+                self.block_cache_hit_rate = Some(hit as f64 / (hit + miss) as f64);
+            }
+        }
+        if let (Some(written), Some(flush), Some(compact)) = (
+            get("rocksdb.bytes.written"),
+            get("rocksdb.flush.write.bytes"),
+            get("rocksdb.compact.write.bytes"),
+        ) {
+            if written > 0 {
+                self.write_amplification = Some((flush + compact) as f64 / written as f64);
+            }
+        }
+        self.compaction_read_bytes = get("rocksdb.compact.read.bytes");
+        self.compaction_write_bytes = get("rocksdb.compact.write.bytes");
+        self.stall_micros = get("rocksdb.stall.micros");
+    }
+}
+
+impl DB {
+    pub fn stats(&self) -> Result<DBStats> {
+        let columns = DB_COL_NAMES
+            .iter()
+            .enumerate()
+            .map(|(col, name)| {
+                let (raw_bytes_written, bytes_written) = self.compression.bytes_written(col as u32);
+                Ok(ColumnStats {
+                    name: name.to_string(),
+                    num_keys: self.db.num_keys(col as u32)?,
+                    raw_bytes_written,
+                    bytes_written,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let io_stats = self.db.io_stats();
+        let mut stats = DBStats {
+            columns,
+            reads: io_stats.reads,
+            cache_reads: io_stats.cache_reads,
+            writes: io_stats.writes,
+            bytes_read: io_stats.bytes_read,
+            bytes_written: io_stats.bytes_written,
+            ..Default::default()
+        };
+        stats.fill_statistics(&self.db.statistics());
+        Ok(stats)
+    }
+
+    pub fn record_stats(&self) -> Result<()> {
+        let stats = self.stats()?;
+        record_event!("db-stats", "stats": stats);
+        Ok(())
+    }
+}
+
+// Record the statistics periodically until the database is dropped.
+pub fn spawn_db_stats_recorder(db: &Arc<DB>, interval: Duration) -> JoinHandle<()> {
+    let db = Arc::downgrade(db);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let db = match db.upgrade() {
+                Some(db) => db,
+                None => break,
+            };
+            match tokio::task::spawn_blocking(move || db.record_stats()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("Failed to record the database statistics. Error: {}", e),
+                Err(e) => warn!("Failed to record the database statistics. Error: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Transaction, BLOCK_DB_COL, META_DB_COL};
+
+    #[test]
+    fn test_db_stats() {
+        let db = DB::load_test();
+        let mut db_tx = Transaction::new();
+        db_tx.insert_meta_object("height", &1u64).unwrap();
+        db_tx
+            .insert_meta_object("tx-trie", &vec![0u64; 100])
+            .unwrap();
+        db.write_sync(db_tx).unwrap();
+
+        let stats = db.stats().unwrap();
+        assert_eq!(DB_COL_NAMES.len(), stats.columns.len());
+        let meta = &stats.columns[META_DB_COL as usize];
+        assert_eq!("meta", meta.name);
+        // Together with the schema version.
+        assert_eq!(3, meta.num_keys);
+        assert!(meta.raw_bytes_written > meta.bytes_written);
+        assert_eq!(0, stats.columns[BLOCK_DB_COL as usize].num_keys);
+        assert_eq!(None, stats.block_cache_hit_rate);
+    }
+
+    #[test]
+    fn test_fill_statistics() {
+        let mut stats = DBStats::default();
+        let statistics: HashMap<String, u64> = [
+            ("rocksdb.block.cache.hit", 3),
+            ("rocksdb.block.cache.miss", 1),
+            ("rocksdb.bytes.written", 100),
+            ("rocksdb.flush.write.bytes", 100),
+            ("rocksdb.compact.write.bytes", 50),
+            ("rocksdb.stall.micros", 10),
+        ]
+        .iter()
+        .map(|&(k, v)| (k.to_string(), v))
+        .collect();
+        stats.fill_statistics(&statistics);
+        assert_eq!(Some(0.75), stats.block_cache_hit_rate);
+        assert_eq!(Some(1.5), stats.write_amplification);
+        assert_eq!(Some(50), stats.compaction_write_bytes);
+        assert_eq!(None, stats.compaction_read_bytes);
+        assert_eq!(Some(10), stats.stall_micros);
+    }
+}
@@ -0,0 +1,123 @@
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    digest::{digest_state, Digestible},
+    error::{anyhow, ensure, Result},
+};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EpochConfig {
+    /// The number of blocks in one epoch. Default 0, i.e., epoch is disabled.
+    pub length: u64,
+    /// The number of members in each committee. Default 0, i.e., all members.
+    pub committee_size: usize,
+    /// The candidates of the committee, i.e., the peer ids of the raft nodes. Only raft
+    /// consensus supports epoch.
+    pub members: Vec<String>,
+}
+
+static GLOBAL_EPOCH_CONFIG: OnceCell<EpochConfig> = OnceCell::new();
+
+impl EpochConfig {
+    pub fn install_as_global(self) -> Result<()> {
+        ensure!(
+            self.length == 0 || !self.members.is_empty(),
+            "Epoch members should not be empty."
+        );
+        GLOBAL_EPOCH_CONFIG
+            .set(self)
+            .map_err(|_| anyhow!("Failed to set EpochConfig."))
+    }
+
+    pub fn get() -> Option<&'static Self> {
+        GLOBAL_EPOCH_CONFIG.get().filter(|cfg| cfg.length > 0)
+    }
+
+    // The genesis block belongs to epoch 0. Epoch `e` consists of blocks `e * len + 1` to
+    // `(e + 1) * len`.
+    pub fn epoch_of(&self, height: BlockHeight) -> u64 {
+        height.0.saturating_sub(1) / self.length
+    }
+
+    pub fn is_last_block_of_epoch(&self, height: BlockHeight) -> bool {
+        height.0 % self.length == 0
+    }
+
+    // The committee rotates among the members in a round-robin manner.
+    pub fn committee(&self, epoch: u64) -> Vec<&str> {
+        let total = self.members.len();
+        if self.committee_size == 0 || self.committee_size >= total {
+            return self.members.iter().map(|m| m.as_str()).collect();
+        }
+
+        let start = (epoch as usize).wrapping_mul(self.committee_size) % total;
+        self.members
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(self.committee_size)
+            .map(|m| m.as_str())
+            .collect()
+    }
+
+    pub fn epoch_root(&self, height: BlockHeight) -> H256 {
+        if height.is_zero() {
+            return H256::zero();
+        }
+
+        let epoch = self.epoch_of(height);
+        let mut hash_state = digest_state();
+        hash_state.update(epoch.to_digest().as_bytes());
+        for member in self.committee(epoch) {
+            hash_state.update(member.to_digest().as_bytes());
+        }
+        hash_state.finalize()
+    }
+}
+
+// The commitment of the committee schedule stored in the block header. Zero if epoch is disabled.
+pub fn epoch_root(height: BlockHeight) -> H256 {
+    match EpochConfig::get() {
+        Some(cfg) => cfg.epoch_root(height),
+        None => H256::zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch() {
+        let cfg = EpochConfig {
+            length: 2,
+            committee_size: 2,
+            members: vec!["1".into(), "2".into(), "3".into()],
+        };
+
+        assert_eq!(0, cfg.epoch_of(0.into()));
+        assert_eq!(0, cfg.epoch_of(1.into()));
+        assert_eq!(0, cfg.epoch_of(2.into()));
+        assert_eq!(1, cfg.epoch_of(3.into()));
+        assert!(!cfg.is_last_block_of_epoch(1.into()));
+        assert!(cfg.is_last_block_of_epoch(2.into()));
+
+        assert_eq!(vec!["1", "2"], cfg.committee(0));
+        assert_eq!(vec!["3", "1"], cfg.committee(1));
+        assert_eq!(vec!["2", "3"], cfg.committee(2));
+        assert_eq!(vec!["1", "2"], cfg.committee(3));
+
+        assert_eq!(H256::zero(), cfg.epoch_root(0.into()));
+        assert_eq!(cfg.epoch_root(1.into()), cfg.epoch_root(2.into()));
+        assert_ne!(cfg.epoch_root(2.into()), cfg.epoch_root(3.into()));
+
+        let cfg = EpochConfig {
+            length: 2,
+            committee_size: 0,
+            members: vec!["1".into(), "2".into()],
+        };
+        assert_eq!(vec!["1", "2"], cfg.committee(5));
+    }
+}
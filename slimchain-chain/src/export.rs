@@ -0,0 +1,534 @@
+use crate::{
+    block::BlockTrait,
+    consensus::Consensus,
+    db::{h256_to_db_key, Transaction, CODE_DB_COL, DB, STATE_DB_COL},
+    receipt::TxReceipt,
+    tx_proof::tx_merkle_root,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{AccountData, Address, BlockHeight, Code, StateValue, H160, H256},
+    digest::Digestible,
+    error::{bail, ensure, ConsensusError, Context as _, Result},
+    tx::TxTrait,
+};
+use slimchain_merkle_trie::u4::U4;
+use slimchain_tx_state::{TrieNode, TxStateView};
+use slimchain_utils::serde::{binary_decode_raw, binary_encode_raw};
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+};
+
+// The magic bytes at the beginning of an exported chain.
+pub const EXPORT_MAGIC: &[u8; 8] = b"SLIMCHEX";
+// Bump it whenever the records change, including the blocks and the txs in them.
+// 2: the txs gain the block timestamp.
+// 3: the txs are signed over their canonical encoding.
+pub const EXPORT_VERSION: u32 = 3;
+
+// Limit the memory used when importing the chain.
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportHeader {
+    pub version: u32,
+    pub consensus: Consensus,
+    pub start: BlockHeight,
+    pub end: BlockHeight,
+    // Whether the state at the end block is included.
+    pub with_state: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportInfo {
+    pub num_blocks: u64,
+    pub num_txs: u64,
+    pub num_state_nodes: u64,
+    pub num_codes: u64,
+}
+
+// Each block is followed by its txs in the order of the tx list. The state comes after the last
+// block. The stream always ends with `End`, so that a truncated file is rejected.
+#[derive(Serialize, Deserialize)]
+enum ExportRecord<Block, Tx> {
+    Block(Block),
+    Tx(Tx),
+    AccountNode(TrieNode<AccountData>),
+    StateNode(TrieNode<StateValue>),
+    Code(Code),
+    End(ExportInfo),
+}
+
+// Every record is stored as `len (u32 LE) || bytes`, after `EXPORT_MAGIC`.
+fn write_record<T: Serialize>(out: &mut impl Write, record: &T) -> Result<()> {
+    let bytes = binary_encode_raw(record)?;
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_record<T: for<'de> Deserialize<'de>>(input: &mut impl Read) -> Result<T> {
+    let mut len_bytes = [0u8; 4];
+    input
+        .read_exact(&mut len_bytes)
+        .context("Unexpected end of the exported chain.")?;
+    let mut buf = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    input
+        .read_exact(&mut buf)
+        .context("Unexpected end of the exported chain.")?;
+    binary_decode_raw(&buf)
+}
+
+// Stream the blocks in `start..=end` together with their txs. With `with_state`, the state at
+// `end` is appended, which is only available in the database of a storage node.
+pub fn export_chain<Tx, Block>(
+    db: &DB,
+    consensus: Consensus,
+    start: BlockHeight,
+    end: BlockHeight,
+    with_state: bool,
+    out: &mut impl Write,
+) -> Result<ExportInfo>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de>,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
+{
+    ensure!(start <= end, "Invalid block range: {}..={}.", start, end);
+    out.write_all(EXPORT_MAGIC)?;
+    write_record(
+        out,
+        &ExportHeader {
+            version: EXPORT_VERSION,
+            consensus,
+            start,
+            end,
+            with_state,
+        },
+    )?;
+
+    let mut info = ExportInfo::default();
+    let mut state_root = H256::zero();
+    for block in db.iter_blocks::<Block>(start..=end)? {
+        let block = block?;
+        let txs: Vec<Tx> = block.tx_list().to_txs(db)?;
+        state_root = block.state_root();
+        write_record(out, &ExportRecord::<Block, Tx>::Block(block))?;
+        info.num_blocks += 1;
+        for tx in txs {
+            write_record(out, &ExportRecord::<Block, Tx>::Tx(tx))?;
+            info.num_txs += 1;
+        }
+    }
+
+    if with_state {
+        export_state::<Tx, Block>(db, state_root, out, &mut info)?;
+    }
+
+    write_record(out, &ExportRecord::<Block, Tx>::End(info.clone()))?;
+    out.flush()?;
+    Ok(info)
+}
+
+// Visit the account trie and the state tries reachable from `state_root`. The nodes shared by
+// multiple tries are only written once.
+fn export_state<Tx: Serialize, Block: Serialize>(
+    view: &impl TxStateView,
+    state_root: H256,
+    out: &mut impl Write,
+    info: &mut ExportInfo,
+) -> Result<()> {
+    let mut visited = HashSet::new();
+    let mut codes = HashSet::new();
+    let mut acc_stack: Vec<(H256, Vec<U4>)> = vec![(state_root, Vec::new())];
+
+    while let Some((address, nibbles)) = acc_stack.pop() {
+        if address.is_zero() || !visited.insert(address) {
+            continue;
+        }
+        let node = view.account_trie_node(address)?;
+        match &node {
+            TrieNode::Extension(n) => {
+                let mut c_nibbles = nibbles;
+                c_nibbles.extend(n.nibbles.iter());
+                acc_stack.push((n.child, c_nibbles));
+            }
+            TrieNode::Branch(n) => {
+                for (i, child) in n.children.iter().enumerate() {
+                    if let Some(c) = child {
+                        let mut c_nibbles = nibbles.clone();
+                        c_nibbles.push(U4::from_u8(i as u8));
+                        acc_stack.push((*c, c_nibbles));
+                    }
+                }
+            }
+            TrieNode::Leaf(n) => {
+                let mut c_nibbles = nibbles;
+                c_nibbles.extend(n.nibbles.iter());
+                let acc_address = nibbles_to_address(&c_nibbles)?;
+                export_state_trie::<Tx, Block>(
+                    view,
+                    acc_address,
+                    n.value.acc_state_root,
+                    &mut visited,
+                    out,
+                    info,
+                )?;
+
+                let code_hash = n.value.code_hash;
+                if !code_hash.is_zero() && codes.insert(code_hash) {
+                    let code = view.code(code_hash)?;
+                    write_record(out, &ExportRecord::<Block, Tx>::Code(code))?;
+                    info.num_codes += 1;
+                }
+            }
+        }
+        write_record(out, &ExportRecord::<Block, Tx>::AccountNode(node))?;
+        info.num_state_nodes += 1;
+    }
+
+    Ok(())
+}
+
+fn export_state_trie<Tx: Serialize, Block: Serialize>(
+    view: &impl TxStateView,
+    acc_address: Address,
+    root: H256,
+    visited: &mut HashSet<H256>,
+    out: &mut impl Write,
+    info: &mut ExportInfo,
+) -> Result<()> {
+    let mut stack = vec![root];
+    while let Some(address) = stack.pop() {
+        if address.is_zero() || !visited.insert(address) {
+            continue;
+        }
+        let node = view.state_trie_node(acc_address, address)?;
+        match &node {
+            TrieNode::Extension(n) => stack.push(n.child),
+            TrieNode::Branch(n) => stack.extend(n.children.iter().flatten().copied()),
+            TrieNode::Leaf(_) => {}
+        }
+        write_record(out, &ExportRecord::<Block, Tx>::StateNode(node))?;
+        info.num_state_nodes += 1;
+    }
+    Ok(())
+}
+
+fn nibbles_to_address(nibbles: &[U4]) -> Result<Address> {
+    ensure!(
+        nibbles.len() == 40,
+        "Invalid account key length: {} nibbles.",
+        nibbles.len()
+    );
+    let bytes: Vec<u8> = nibbles
+        .chunks(2)
+        .map(|pair| (u8::from(pair[0]) << 4) | u8::from(pair[1]))
+        .collect();
+    Ok(Address(H160::from_slice(&bytes)))
+}
+
+// Import an exported chain into an empty database. The block headers are verified in the same way
+// as the light client does, and the txs are checked against the tx lists in the blocks. The first
+// block is trusted unless it is the genesis block. The trie nodes are stored by their digests, so
+// the state is only usable if it matches the state root of the last block.
+//
+// The imported database is meant for offline auditing and inspection. It does not contain the
+// snapshots required to start a node.
+pub fn import_chain<Tx, Block>(
+    db: &DB,
+    consensus: Consensus,
+    input: &mut impl Read,
+    verify_consensus_fn: impl Fn(&Block, &Block) -> Result<(), ConsensusError>,
+) -> Result<(ExportHeader, ExportInfo)>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de>,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
+{
+    let mut magic = [0u8; 8];
+    input
+        .read_exact(&mut magic)
+        .context("Failed to read the exported chain.")?;
+    ensure!(&magic == EXPORT_MAGIC, "Not an exported chain.");
+    let header: ExportHeader = read_record(input)?;
+    ensure!(
+        header.version == EXPORT_VERSION,
+        "Unsupported export version: {}.",
+        header.version
+    );
+    ensure!(
+        header.consensus == consensus,
+        "The chain is exported with the {:?} consensus.",
+        header.consensus
+    );
+    ensure!(
+        db.get_meta_object::<BlockHeight>("height")?.is_none(),
+        "The database is not empty."
+    );
+
+    let mut info = ExportInfo::default();
+    let mut db_tx = Transaction::with_capacity(IMPORT_BATCH_SIZE);
+    let mut batch_len = 0;
+    let mut prev_blk: Option<Block> = None;
+    // The number of txs of the previous block that have been imported.
+    let mut tx_idx = 0;
+
+    let expected_info = loop {
+        let record: ExportRecord<Block, Tx> = read_record(input)?;
+
+        let tx_list_done = prev_blk
+            .as_ref()
+            .map_or(true, |blk| tx_idx == blk.tx_list().len());
+        if !matches!(record, ExportRecord::Tx(_)) {
+            ensure!(tx_list_done, "Missing txs in the exported chain.");
+        }
+
+        match record {
+            ExportRecord::Block(blk) => {
+                match prev_blk.as_ref() {
+                    Some(prev_blk) => {
+                        blk.verify_block_header(prev_blk)?;
+                        verify_consensus_fn(&blk, prev_blk)?;
+                    }
+                    None => {
+                        ensure!(
+                            blk.block_height() == header.start,
+                            "The first block should be #{}.",
+                            header.start
+                        );
+                        if blk.block_height().is_zero() {
+                            ensure!(
+                                blk.to_digest() == Block::genesis_block().to_digest(),
+                                "Invalid genesis block."
+                            );
+                        } else {
+                            warn!(
+                                "The chain starts at block #{}, which is trusted.",
+                                blk.block_height()
+                            );
+                        }
+                    }
+                }
+                ensure!(
+                    tx_merkle_root(blk.tx_list()) == blk.tx_root(),
+                    "Invalid tx root in block #{}.",
+                    blk.block_height()
+                );
+                // The genesis block is built in, and is never stored.
+                if !blk.block_height().is_zero() {
+                    db_tx.insert_block(&blk)?;
+                    batch_len += 1;
+                }
+                info.num_blocks += 1;
+                prev_blk = Some(blk);
+                tx_idx = 0;
+            }
+            ExportRecord::Tx(tx) => {
+                let blk = prev_blk
+                    .as_ref()
+                    .context("Unexpected tx before the first block.")?;
+                ensure!(!tx_list_done, "Unexpected tx in the exported chain.");
+                let tx_hash = tx.to_digest();
+                ensure!(
+                    tx_hash == blk.tx_list()[tx_idx],
+                    "Tx {} does not match block #{}.",
+                    tx_hash,
+                    blk.block_height()
+                );
+                tx.verify_sig()
+                    .with_context(|| format!("Invalid signature of tx {}.", tx_hash))?;
+                db_tx.insert_tx(tx_hash, &tx)?;
+                db_tx.insert_tx_receipt(
+                    tx.id(),
+                    &TxReceipt::committed(blk.block_height(), tx_idx),
+                )?;
+                batch_len += 2;
+                tx_idx += 1;
+                info.num_txs += 1;
+            }
+            ExportRecord::AccountNode(node) => {
+                ensure!(header.with_state, "Unexpected state in the exported chain.");
+                db_tx.insert_object(STATE_DB_COL, &h256_to_db_key(node.to_digest()), &node)?;
+                batch_len += 1;
+                info.num_state_nodes += 1;
+            }
+            ExportRecord::StateNode(node) => {
+                ensure!(header.with_state, "Unexpected state in the exported chain.");
+                db_tx.insert_object(STATE_DB_COL, &h256_to_db_key(node.to_digest()), &node)?;
+                batch_len += 1;
+                info.num_state_nodes += 1;
+            }
+            ExportRecord::Code(code) => {
+                ensure!(header.with_state, "Unexpected state in the exported chain.");
+                db_tx.insert_object(CODE_DB_COL, &h256_to_db_key(code.to_digest()), &code)?;
+                batch_len += 1;
+                info.num_codes += 1;
+            }
+            ExportRecord::End(expected_info) => break expected_info,
+        }
+
+        if batch_len >= IMPORT_BATCH_SIZE {
+            db.write_sync(std::mem::take(&mut db_tx))?;
+            batch_len = 0;
+        }
+    };
+
+    ensure!(
+        info == expected_info,
+        "The exported chain is incomplete. Expected: {:?}, got: {:?}.",
+        expected_info,
+        info
+    );
+    let last_blk = match prev_blk {
+        Some(blk) => blk,
+        None => bail!("No block in the exported chain."),
+    };
+    ensure!(
+        last_blk.block_height() == header.end,
+        "The last block should be #{}.",
+        header.end
+    );
+
+    db_tx.insert_meta_object("height", &last_blk.block_height())?;
+    db.write_sync(db_tx)?;
+
+    if header.with_state && !last_blk.state_root().is_zero() {
+        db.account_trie_node(last_blk.state_root())
+            .context("The state does not match the state root of the last block.")?;
+    }
+
+    Ok((header, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::{BlockHeader, BlockTxList},
+        consensus::raft::{create_new_block, verify_consensus, Block},
+        loader::BlockLoaderTrait,
+        tests::create_signed_tx,
+    };
+    use chrono::Utc;
+    use slimchain_common::{basic::StateKey, ed25519::Keypair, rw_set::TxWriteData, tx::SignedTx};
+    use slimchain_merkle_trie::prelude::read_trie_without_proof;
+    use slimchain_tx_state::{read_code, trie_view::AccountTrieView, update_tx_state};
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let db = DB::load_test();
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+
+        let mut writes = TxWriteData::default();
+        for i in 1..=3u64 {
+            let address = Address(H160::from_low_u64_be(i));
+            writes.add_nonce(address, i.into());
+            writes.add_code(address, Code::from(b"code".to_vec()));
+            writes.add_value(
+                address,
+                StateKey(H256::from_low_u64_be(i)),
+                StateValue(H256::from_low_u64_be(i * 10)),
+            );
+        }
+        let update = update_tx_state(db.as_ref(), H256::zero(), &writes).unwrap();
+        let state_root = update.root;
+
+        let mut db_tx = Transaction::new();
+        db_tx.update_state(&update).unwrap();
+        let mut prev_blk = Block::genesis_block();
+        let mut last_txs = Vec::new();
+        for height in 1..=3u64 {
+            let txs = vec![
+                create_signed_tx(height * 2, &keypair),
+                create_signed_tx(height * 2 + 1, &keypair),
+            ];
+            let blk = create_new_block(
+                BlockHeader::new(
+                    height.into(),
+                    prev_blk.to_digest(),
+                    Utc::now(),
+                    txs.iter().collect::<BlockTxList>(),
+                    state_root,
+                ),
+                &prev_blk,
+            )
+            .await
+            .unwrap();
+            db_tx.insert_block(&blk).unwrap();
+            for tx in &txs {
+                db_tx.insert_tx(tx.to_digest(), tx).unwrap();
+            }
+            prev_blk = blk;
+            last_txs = txs;
+        }
+        db_tx.insert_meta_object("height", &BlockHeight(3)).unwrap();
+        db.write_sync(db_tx).unwrap();
+
+        let mut buf = Vec::new();
+        let info = export_chain::<SignedTx, Block>(
+            &db,
+            Consensus::Raft,
+            BlockHeight(0),
+            BlockHeight(3),
+            true,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(4, info.num_blocks);
+        assert_eq!(6, info.num_txs);
+        assert_eq!(1, info.num_codes);
+
+        let new_db = DB::load_test();
+        let (header, new_info) = import_chain::<SignedTx, Block>(
+            &new_db,
+            Consensus::Raft,
+            &mut buf.as_slice(),
+            verify_consensus,
+        )
+        .unwrap();
+        assert_eq!(BlockHeight(3), header.end);
+        assert_eq!(info, new_info);
+        assert_eq!(prev_blk, new_db.get_block(3.into()).unwrap());
+        assert_eq!(
+            Some(TxReceipt::committed(3.into(), 1)),
+            new_db.get_tx_receipt(last_txs[1].id()).unwrap()
+        );
+        let acc_data: AccountData = read_trie_without_proof(
+            &AccountTrieView::new(new_db.as_ref()),
+            state_root,
+            &Address(H160::from_low_u64_be(2)),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            Code::from(b"code".to_vec()),
+            read_code(new_db.as_ref(), acc_data.code_hash).unwrap()
+        );
+
+        // The database is no longer empty.
+        assert!(import_chain::<SignedTx, Block>(
+            &new_db,
+            Consensus::Raft,
+            &mut buf.as_slice(),
+            verify_consensus,
+        )
+        .is_err());
+        // The consensus is mismatched.
+        assert!(import_chain::<SignedTx, Block>(
+            &DB::load_test(),
+            Consensus::PoW,
+            &mut buf.as_slice(),
+            |_, _| Ok(()),
+        )
+        .is_err());
+        // The file is truncated.
+        assert!(import_chain::<SignedTx, Block>(
+            &DB::load_test(),
+            Consensus::Raft,
+            &mut &buf[..buf.len() - 1],
+            verify_consensus,
+        )
+        .is_err());
+    }
+}
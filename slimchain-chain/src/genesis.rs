@@ -0,0 +1,201 @@
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use slimchain_common::{
+    basic::{Address, Code, Nonce, ShardId, StateKey, StateValue, H256},
+    error::{anyhow, ensure, Result},
+    rw_set::TxWriteData,
+};
+use slimchain_tx_state::{update_tx_state, MemTxState, TxStateUpdate};
+use std::{collections::BTreeMap, fs, path::Path};
+
+pub const GENESIS_FILE_NAME: &str = "genesis.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisConfig {
+    /// The chain id. The tx requests signed for other chains are rejected. Default 0.
+    #[serde(default)]
+    pub chain_id: u64,
+    /// The initial number of shards. Default 1.
+    #[serde(default = "default_shard_total")]
+    pub shard_total: u64,
+    /// Accounts that exist in the genesis state.
+    #[serde(default = "Vec::new")]
+    pub accounts: Vec<GenesisAccount>,
+}
+
+fn default_shard_total() -> u64 {
+    1
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: 0,
+            shard_total: default_shard_total(),
+            accounts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisAccount {
+    /// The account address. The mixed case one should match its EIP-55 checksum.
+    pub address: Address,
+    /// The account nonce. Default 0.
+    #[serde(default)]
+    pub nonce: u64,
+    /// The runtime bytecode of the pre-deployed contract in hex (without 0x prefix).
+    #[serde(
+        default,
+        deserialize_with = "slimchain_utils::config::deserialize_from_hex"
+    )]
+    pub code: Vec<u8>,
+    /// The initial storage values, e.g., the token balances of pre-funded accounts.
+    #[serde(default)]
+    pub values: BTreeMap<StateKey, StateValue>,
+}
+
+impl GenesisConfig {
+    pub fn load(file: &Path) -> Result<Self> {
+        slimchain_utils::toml::from_str(
+            &fs::read_to_string(file)
+                .map_err(|e| anyhow!("Failed to open {}. Reason: {}.", file.display(), e))?,
+        )
+        .map_err(|e| anyhow!("Failed to load {}. Reason: {}.", file.display(), e))
+    }
+
+    pub fn to_tx_write_data(&self) -> TxWriteData {
+        let mut writes = TxWriteData::default();
+        for acc in &self.accounts {
+            let acc_writes = writes.entry(acc.address).or_default();
+            acc_writes.nonce = Some(Nonce::from(acc.nonce));
+            if !acc.code.is_empty() {
+                acc_writes.code = Some(Code::from(acc.code.clone()));
+            }
+            for (&key, &value) in acc.values.iter() {
+                acc_writes.values.insert(key, value);
+            }
+        }
+        writes
+    }
+
+    pub fn to_state_update(&self) -> Result<TxStateUpdate> {
+        ensure!(
+            self.shard_total > 0,
+            "Genesis: shard_total should be greater than 0."
+        );
+        if self.accounts.is_empty() {
+            return Ok(TxStateUpdate::default());
+        }
+        update_tx_state(&*MemTxState::new(), H256::zero(), &self.to_tx_write_data())
+    }
+
+    pub fn install_as_global(self) -> Result<()> {
+        let state_update = self.to_state_update()?;
+        GLOBAL_GENESIS
+            .set((self, state_update))
+            .map_err(|_| anyhow!("Failed to set GenesisConfig."))
+    }
+
+    pub fn get() -> Option<&'static Self> {
+        GLOBAL_GENESIS.get().map(|(cfg, _)| cfg)
+    }
+}
+
+static GLOBAL_GENESIS: OnceCell<(GenesisConfig, TxStateUpdate)> = OnceCell::new();
+
+// The chain id which the tx requests should be signed with.
+pub fn genesis_chain_id() -> u64 {
+    GenesisConfig::get().map_or(0, |cfg| cfg.chain_id)
+}
+
+pub fn genesis_state_root() -> H256 {
+    GLOBAL_GENESIS
+        .get()
+        .map_or_else(H256::zero, |(_, update)| update.root)
+}
+
+// Return the genesis state nodes needed by the storage node within the shard.
+pub fn genesis_state_update(shard_id: ShardId) -> Option<TxStateUpdate> {
+    let (_, update) = GLOBAL_GENESIS.get()?;
+    if update.root.is_zero() {
+        return None;
+    }
+
+    Some(TxStateUpdate {
+        root: update.root,
+        acc_nodes: update.acc_nodes.clone(),
+        state_nodes: update
+            .state_nodes
+            .iter()
+            .filter(|(&acc_addr, _)| shard_id.contains(acc_addr))
+            .map(|(&acc_addr, nodes)| (acc_addr, nodes.clone()))
+            .collect(),
+        codes: update.codes.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_tx_state::{TxTrie, TxTrieTrait};
+    use slimchain_utils::toml;
+
+    #[test]
+    fn test_genesis() {
+        let input = toml::toml! {
+            chain_id = 1
+
+            [[accounts]]
+            address = "0x0000000000000000000000000000000000000001"
+            nonce = 1
+            code = "6080604052"
+
+            [[accounts]]
+            address = "0x0000000000000000000000000000000000000002"
+
+            [accounts.values]
+            "0x0000000000000000000000000000000000000000000000000000000000000001" = "0x0000000000000000000000000000000000000000000000000000000000000064"
+        };
+        let genesis: GenesisConfig = input.try_into().unwrap();
+        assert_eq!(1, genesis.chain_id);
+        assert_eq!(1, genesis.shard_total);
+        assert_eq!(2, genesis.accounts.len());
+        assert_eq!(vec![0x60, 0x80, 0x60, 0x40, 0x52], genesis.accounts[0].code);
+        assert_eq!(1, genesis.accounts[1].values.len());
+
+        let update = genesis.to_state_update().unwrap();
+        assert!(!update.root.is_zero());
+        assert_eq!(update.root, genesis.to_state_update().unwrap().root);
+
+        let mut tx_trie = TxTrie::default();
+        tx_trie.apply_writes(&genesis.to_tx_write_data()).unwrap();
+        assert_eq!(update.root, tx_trie.root_hash());
+
+        assert!(GenesisConfig::default()
+            .to_state_update()
+            .unwrap()
+            .root
+            .is_zero());
+    }
+
+    #[test]
+    fn test_genesis_address_checksum() {
+        let input = toml::toml! {
+            [[accounts]]
+            address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        };
+        let genesis: GenesisConfig = input.try_into().unwrap();
+        assert_eq!(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            genesis.accounts[0].address.to_string()
+        );
+
+        // A typo in the mixed case address.
+        let input = toml::toml! {
+            [[accounts]]
+            address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAee"
+        };
+        assert!(input.try_into::<GenesisConfig>().is_err());
+    }
+}
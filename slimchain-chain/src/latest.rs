@@ -5,17 +5,25 @@ use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
 };
+use tokio::sync::watch;
 
 pub struct LatestBlockHeader {
     header: ArcSwap<BlockHeader>,
+    notify_tx: watch::Sender<Arc<BlockHeader>>,
+    // Keep one receiver alive, so that the sender never fails.
+    notify_rx: watch::Receiver<Arc<BlockHeader>>,
 }
 
 pub type LatestBlockHeaderPtr = Arc<LatestBlockHeader>;
 
 impl LatestBlockHeader {
     pub fn new(header: BlockHeader) -> Arc<Self> {
+        let header = Arc::new(header);
+        let (notify_tx, notify_rx) = watch::channel(header.clone());
         Arc::new(Self {
-            header: ArcSwap::from_pointee(header),
+            header: ArcSwap::new(header),
+            notify_tx,
+            notify_rx,
         })
     }
 
@@ -24,7 +32,9 @@ impl LatestBlockHeader {
     }
 
     pub fn set(self: &Arc<Self>, header: BlockHeader) {
-        self.header.store(Arc::new(header));
+        let header = Arc::new(header);
+        self.header.store(header.clone());
+        self.notify_tx.send(header).ok();
     }
 
     pub fn set_from_block(self: &Arc<Self>, block: &impl BlockTrait) {
@@ -47,6 +57,24 @@ impl LatestBlockHeader {
     pub fn get_height_and_state_root(self: &Arc<Self>) -> (BlockHeight, H256) {
         self.get_inner(|h| (h.height, h.state_root))
     }
+
+    // The timestamp is in ms.
+    pub fn get_height_timestamp_and_state_root(self: &Arc<Self>) -> (BlockHeight, u64, H256) {
+        self.get_inner(|h| {
+            (
+                h.height,
+                h.time_stamp.timestamp_millis() as u64,
+                h.state_root,
+            )
+        })
+    }
+
+    // Get notified whenever a new block header is set, instead of polling the getters.
+    pub fn subscribe(self: &Arc<Self>) -> watch::Receiver<Arc<BlockHeader>> {
+        let mut rx = self.notify_rx.clone();
+        rx.borrow_and_update();
+        rx
+    }
 }
 
 #[derive(Debug, Default)]
@@ -100,6 +128,31 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_latest_block_subscribe() {
+        let mut block = crate::consensus::raft::Block::genesis_block();
+        let latest_blk_header = LatestBlockHeader::new_from_block(&block);
+        let mut rx = latest_blk_header.subscribe();
+        assert!(rx.borrow().height.is_zero());
+
+        block.block_header_mut().height = 1.into();
+        latest_blk_header.set_from_block(&block);
+        rx.changed().await.unwrap();
+        assert_eq!(BlockHeight::from(1), rx.borrow().height);
+
+        let handle = tokio::spawn(async move {
+            while rx.borrow().height < 3.into() {
+                rx.changed().await.unwrap();
+            }
+            rx.borrow().height
+        });
+        for height in 2..=3 {
+            block.block_header_mut().height = height.into();
+            latest_blk_header.set_from_block(&block);
+        }
+        assert_eq!(BlockHeight::from(3), handle.await.unwrap());
+    }
+
     #[test]
     fn test_latest_tx_count() {
         let cnt = LatestTxCount::new(1);
@@ -2,6 +2,8 @@
 extern crate tracing;
 
 pub mod access_map;
+pub mod archive;
+pub mod audit;
 pub mod behavior;
 pub mod block;
 pub mod block_proposal;
@@ -9,10 +11,21 @@ pub mod config;
 pub mod conflict_check;
 pub mod consensus;
 pub mod db;
+pub mod epoch;
+pub mod export;
+pub mod genesis;
 pub mod latest;
+pub mod light_client;
 pub mod loader;
+pub mod receipt;
 pub mod role;
+pub mod shard_block;
 pub mod snapshot;
+pub mod state_proof;
+pub mod tx_event;
+pub mod tx_ordering;
+pub mod tx_proof;
+pub mod tx_sig;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,196 @@
+use crate::{
+    block::{BlockHeader, BlockTrait},
+    db::{DBPtr, Transaction},
+    latest::{LatestBlockHeader, LatestBlockHeaderPtr},
+    loader::BlockLoaderTrait,
+    tx_proof::TxInclusionProof,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    error::{ensure, ConsensusError, Context as _, Result},
+    tx::TxTrait,
+};
+
+// The chain of block headers maintained by a light client. Only the blocks (i.e., the headers
+// with their consensus proofs) are stored. Neither tx bodies nor partial tries are kept.
+pub struct HeaderChain<Block: BlockTrait> {
+    db: DBPtr,
+    latest_block: Block,
+    latest_block_header: LatestBlockHeaderPtr,
+}
+
+impl<Block: BlockTrait + Serialize + for<'de> Deserialize<'de>> HeaderChain<Block> {
+    pub fn load_from_db(db: DBPtr) -> Result<Self> {
+        let height: BlockHeight = db
+            .get_meta_object("height")
+            .context("Failed to get block height from the database.")?
+            .unwrap_or_default();
+        let latest_block: Block = db.get_block(height)?;
+        let latest_block_header = LatestBlockHeader::new_from_block(&latest_block);
+        Ok(Self {
+            db,
+            latest_block,
+            latest_block_header,
+        })
+    }
+
+    pub fn latest_block(&self) -> &Block {
+        &self.latest_block
+    }
+
+    pub fn latest_block_header(&self) -> LatestBlockHeaderPtr {
+        self.latest_block_header.clone()
+    }
+
+    pub fn get_block(&self, height: BlockHeight) -> Result<Block> {
+        ensure!(
+            height <= self.latest_block.block_height(),
+            "Block #{} has not been synced yet.",
+            height
+        );
+        self.db.get_block(height)
+    }
+
+    // Verify the headers and their consensus proofs against the local chain before persisting them.
+    // The blocks should be sorted by their heights and start right after the latest block.
+    pub async fn import_blocks(
+        &mut self,
+        blocks: Vec<Block>,
+        verify_consensus_fn: impl Fn(&Block, &Block) -> Result<(), ConsensusError>,
+    ) -> Result<()> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let mut prev_blk = &self.latest_block;
+        for blk in &blocks {
+            blk.verify_block_header(prev_blk)?;
+            verify_consensus_fn(blk, prev_blk)?;
+            prev_blk = blk;
+        }
+
+        let mut db_tx = Transaction::with_capacity(blocks.len() + 1);
+        for blk in &blocks {
+            db_tx.insert_block(blk)?;
+        }
+        let latest_block = blocks
+            .into_iter()
+            .last()
+            .expect("Failed to get the latest block.");
+        db_tx.insert_meta_object("height", &latest_block.block_height())?;
+        self.db.write_async(db_tx).await?;
+
+        debug!("Synced headers up to #{}.", latest_block.block_height());
+        self.latest_block_header.set_from_block(&latest_block);
+        self.latest_block = latest_block;
+        Ok(())
+    }
+}
+
+// Verify that the tx fetched from the remote node is included in the block, by checking it against
+// the tx root in the block header using the inclusion proof. Return the position of the tx in the
+// block.
+//
+// The signature is checked by `TxTrait::verify_sig`. So the TEE attestation is verified only if
+// `Tx` carries one (i.e., `TEESignedTx`). A plain `SignedTx` only proves which key signed it.
+pub fn verify_tx_inclusion_proof<Tx: TxTrait>(
+    header: &BlockHeader,
+    tx_hash: H256,
+    tx: &Tx,
+    proof: &TxInclusionProof,
+) -> Result<usize> {
+    ensure!(tx.to_digest() == tx_hash, "Tx hash mismatched.");
+    tx.verify_sig().context("Invalid tx signature.")?;
+    ensure!(proof.tx_hash == tx_hash, "Tx hash mismatched in the proof.");
+    proof.verify(header)?;
+    Ok(proof.index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::{BlockHeader, BlockTxList},
+        consensus::raft::{create_new_block, verify_consensus, Block},
+        db::DB,
+        tests::create_signed_tx,
+    };
+    use chrono::Utc;
+    use slimchain_common::{digest::Digestible, ed25519::Keypair};
+
+    #[tokio::test]
+    async fn test_header_chain() {
+        let db = DB::load_test();
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let tx1 = create_signed_tx(1, &keypair);
+        let tx2 = create_signed_tx(2, &keypair);
+
+        let mut chain = HeaderChain::<Block>::load_from_db(db.clone()).unwrap();
+        assert!(chain.latest_block().block_height().is_zero());
+
+        let blk0 = Block::genesis_block();
+        let blk1 = create_new_block(
+            BlockHeader::new(
+                1.into(),
+                blk0.to_digest(),
+                Utc::now(),
+                [&tx1].iter().copied().collect::<BlockTxList>(),
+                H256::zero(),
+            ),
+            &blk0,
+        )
+        .await
+        .unwrap();
+        let blk2 = create_new_block(
+            BlockHeader::new(
+                2.into(),
+                blk1.to_digest(),
+                Utc::now(),
+                BlockTxList::new(),
+                H256::zero(),
+            ),
+            &blk1,
+        )
+        .await
+        .unwrap();
+
+        assert!(chain
+            .import_blocks(vec![blk2.clone()], verify_consensus)
+            .await
+            .is_err());
+        // The tx list is swapped without changing the block hash.
+        let mut fake_blk1 = blk1.clone();
+        *fake_blk1.tx_list_mut() = [&tx2].iter().copied().collect::<BlockTxList>();
+        assert_eq!(blk1.to_digest(), fake_blk1.to_digest());
+        assert!(chain
+            .import_blocks(vec![fake_blk1, blk2.clone()], verify_consensus)
+            .await
+            .is_err());
+        chain
+            .import_blocks(vec![blk1.clone(), blk2.clone()], verify_consensus)
+            .await
+            .unwrap();
+        assert_eq!(blk2, *chain.latest_block());
+        assert_eq!(2, chain.latest_block_header().get_height().0);
+        assert_eq!(blk1, chain.get_block(1.into()).unwrap());
+        assert!(chain.get_block(3.into()).is_err());
+
+        let chain = HeaderChain::<Block>::load_from_db(db).unwrap();
+        assert_eq!(blk2, *chain.latest_block());
+
+        let proof = TxInclusionProof::from_block(&blk1, tx1.to_digest()).unwrap();
+        assert_eq!(
+            0,
+            verify_tx_inclusion_proof(blk1.block_header(), tx1.to_digest(), &tx1, &proof).unwrap()
+        );
+        assert!(
+            verify_tx_inclusion_proof(blk1.block_header(), tx1.to_digest(), &tx2, &proof).is_err()
+        );
+        assert!(
+            verify_tx_inclusion_proof(blk2.block_header(), tx1.to_digest(), &tx1, &proof).is_err()
+        );
+        assert!(TxInclusionProof::from_block(&blk1, tx2.to_digest()).is_err());
+    }
+}
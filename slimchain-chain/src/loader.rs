@@ -1,7 +1,8 @@
 use crate::block::BlockTrait;
 use slimchain_common::{
     basic::{BlockHeight, H256},
-    error::Result,
+    collections::HashMap,
+    error::{Context as _, Result},
     tx::TxTrait,
 };
 use std::sync::Arc;
@@ -16,6 +17,15 @@ impl<Tx: TxTrait, Loader: TxLoaderTrait<Tx> + ?Sized> TxLoaderTrait<Tx> for Arc<
     }
 }
 
+// The txs fetched ahead, e.g., from a remote node.
+impl<Tx: TxTrait> TxLoaderTrait<Tx> for HashMap<H256, Tx> {
+    fn get_tx(&self, tx_hash: H256) -> Result<Tx> {
+        self.get(&tx_hash)
+            .cloned()
+            .with_context(|| format!("Tx {} is not available.", tx_hash))
+    }
+}
+
 pub trait BlockLoaderTrait<Block: BlockTrait> {
     fn get_non_genesis_block(&self, height: BlockHeight) -> Result<Block>;
 
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use slimchain_common::basic::{Address, BlockHeight, H256};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TxReceiptStatus {
+    Committed,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxReceipt {
+    pub status: TxReceiptStatus,
+    pub block_height: BlockHeight,
+    // The position of the tx in the block.
+    pub index: usize,
+    // The gas usage and the logs are not reported by the tx engine yet.
+    pub gas_used: Option<u64>,
+    pub logs: Vec<TxLog>,
+}
+
+impl TxReceipt {
+    pub fn committed(block_height: BlockHeight, index: usize) -> Self {
+        Self {
+            status: TxReceiptStatus::Committed,
+            block_height,
+            index,
+            gas_used: None,
+            logs: Vec::new(),
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use serde::{de::Error as SerdeError, Deserialize, Deserializer};
+use serde::{
+    de::Error as SerdeError, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer,
+};
 use slimchain_common::{
     basic::ShardId,
     error::{Context as _, Result},
@@ -8,8 +10,10 @@ use std::fmt;
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Role {
     Client,
+    LightClient,
     Miner,
     Storage(ShardId),
+    Auditor,
 }
 
 impl Default for Role {
@@ -27,8 +31,11 @@ impl<'de> Deserialize<'de> for Role {
         #[serde(rename_all = "lowercase")]
         enum RoleType {
             Client,
+            #[serde(rename = "light_client")]
+            LightClient,
             Miner,
             Storage,
+            Auditor,
         }
 
         impl Default for RoleType {
@@ -63,6 +70,20 @@ impl<'de> Deserialize<'de> for Role {
 
                 Ok(Self::Client)
             }
+            RoleType::LightClient => {
+                if data.shard_id.is_some() {
+                    return Err(SerdeError::custom(
+                        "Field shard_id is only valid for storage node.",
+                    ));
+                }
+                if data.shard_total.is_some() {
+                    return Err(SerdeError::custom(
+                        "Field shard_total is only valid for storage node.",
+                    ));
+                }
+
+                Ok(Self::LightClient)
+            }
             RoleType::Miner => {
                 if data.shard_id.is_some() {
                     return Err(SerdeError::custom(
@@ -77,6 +98,20 @@ impl<'de> Deserialize<'de> for Role {
 
                 Ok(Self::Miner)
             }
+            RoleType::Auditor => {
+                if data.shard_id.is_some() {
+                    return Err(SerdeError::custom(
+                        "Field shard_id is only valid for storage node.",
+                    ));
+                }
+                if data.shard_total.is_some() {
+                    return Err(SerdeError::custom(
+                        "Field shard_total is only valid for storage node.",
+                    ));
+                }
+
+                Ok(Self::Auditor)
+            }
             RoleType::Storage => match (data.shard_id, data.shard_total) {
                 (Some(id), Some(total)) => Ok(Self::Storage(ShardId::new(id, total))),
                 (None, None) => Ok(Self::Storage(ShardId::default())),
@@ -87,12 +122,37 @@ impl<'de> Deserialize<'de> for Role {
     }
 }
 
+// The same layout as the one accepted by `Deserialize`.
+impl Serialize for Role {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let role = match self {
+            Self::Client => "client",
+            Self::LightClient => "light_client",
+            Self::Miner => "miner",
+            Self::Storage(_) => "storage",
+            Self::Auditor => "auditor",
+        };
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("role", role)?;
+        if let Self::Storage(shard_id) = self {
+            map.serialize_entry("shard_id", &shard_id.id)?;
+            map.serialize_entry("shard_total", &shard_id.total)?;
+        }
+        map.end()
+    }
+}
+
 impl fmt::Display for Role {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Client => write!(f, "Client"),
+            Self::LightClient => write!(f, "LightClient"),
             Self::Miner => write!(f, "Miner"),
             Self::Storage(ShardId { id, total }) => write!(f, "Storage-{}-{}", id, total),
+            Self::Auditor => write!(f, "Auditor"),
         }
     }
 }
@@ -105,7 +165,9 @@ impl Role {
     pub fn from_user_agent(input: &str) -> Result<Self> {
         match input {
             "Client" => return Ok(Self::Client),
+            "LightClient" => return Ok(Self::LightClient),
             "Miner" => return Ok(Self::Miner),
+            "Auditor" => return Ok(Self::Auditor),
             _ => {}
         }
 
@@ -133,12 +195,27 @@ mod tests {
         };
         assert_eq!(Role::Client, Config::from_toml(input).get("role").unwrap());
 
+        let input = toml::toml! {
+            [role]
+            role = "light_client"
+        };
+        assert_eq!(
+            Role::LightClient,
+            Config::from_toml(input).get("role").unwrap()
+        );
+
         let input = toml::toml! {
             [role]
             role = "miner"
         };
         assert_eq!(Role::Miner, Config::from_toml(input).get("role").unwrap());
 
+        let input = toml::toml! {
+            [role]
+            role = "auditor"
+        };
+        assert_eq!(Role::Auditor, Config::from_toml(input).get("role").unwrap());
+
         let input = toml::toml! {
             [role]
             role = "storage"
@@ -182,14 +259,36 @@ mod tests {
         assert!(Config::from_toml(input).get::<Role>("role").is_err());
     }
 
+    #[test]
+    fn test_serde_round_trip() {
+        for role in [
+            Role::Client,
+            Role::LightClient,
+            Role::Miner,
+            Role::Auditor,
+            Role::Storage(ShardId::new(1, 2)),
+        ] {
+            let json = serde_json::to_string(&role).unwrap();
+            assert_eq!(role, serde_json::from_str::<Role>(&json).unwrap());
+        }
+        assert_eq!(
+            r#"{"role":"storage","shard_id":1,"shard_total":2}"#,
+            serde_json::to_string(&Role::Storage(ShardId::new(1, 2))).unwrap()
+        );
+    }
+
     #[test]
     fn test_user_agent() {
         let role = Role::Client;
         assert_eq!(role, Role::from_user_agent(&role.to_user_agent()).unwrap());
+        let role = Role::LightClient;
+        assert_eq!(role, Role::from_user_agent(&role.to_user_agent()).unwrap());
         let role = Role::Miner;
         assert_eq!(role, Role::from_user_agent(&role.to_user_agent()).unwrap());
         let role = Role::Storage(ShardId::default());
         assert_eq!(role, Role::from_user_agent(&role.to_user_agent()).unwrap());
+        let role = Role::Auditor;
+        assert_eq!(role, Role::from_user_agent(&role.to_user_agent()).unwrap());
         assert!(Role::from_user_agent("").is_err());
         assert!(Role::from_user_agent("foo").is_err());
         assert!(Role::from_user_agent("Storage").is_err());
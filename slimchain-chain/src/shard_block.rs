@@ -0,0 +1,177 @@
+use crate::discard_tx;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{ShardId, H256},
+    digest::{digest_state, Digestible},
+    error::{ensure, Result},
+    rw_set::{AccountWriteData, TxWriteData},
+    tx::TxTrait,
+};
+use slimchain_tx_state::TxProposal;
+
+// The tx proposals of one storage shard, pre-aggregated by its committee before they reach the
+// miner. The write tries are kept per tx, since each of them is verified against the state root
+// of its own tx block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardBlock<Tx: TxTrait> {
+    shard_id: ShardId,
+    tx_proposals: Vec<TxProposal<Tx>>,
+}
+
+impl<Tx: TxTrait> ShardBlock<Tx> {
+    pub fn new(shard_id: ShardId, tx_proposals: Vec<TxProposal<Tx>>) -> Self {
+        Self {
+            shard_id,
+            tx_proposals: aggregate_tx_proposals(tx_proposals),
+        }
+    }
+
+    pub fn shard_id(&self) -> ShardId {
+        self.shard_id
+    }
+
+    pub fn tx_proposals(&self) -> &[TxProposal<Tx>] {
+        &self.tx_proposals
+    }
+
+    pub fn into_tx_proposals(self) -> Vec<TxProposal<Tx>> {
+        self.tx_proposals
+    }
+
+    pub fn len(&self) -> usize {
+        self.tx_proposals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tx_proposals.is_empty()
+    }
+
+    pub fn verify_sigs(&self) -> Result<()> {
+        ensure!(!self.is_empty(), "Empty shard block.");
+        for tx_proposal in &self.tx_proposals {
+            tx_proposal.tx.verify_sig()?;
+        }
+        Ok(())
+    }
+}
+
+impl<Tx: TxTrait> Digestible for ShardBlock<Tx> {
+    fn to_digest(&self) -> H256 {
+        let mut hash_state = digest_state();
+        hash_state.update(&self.shard_id.id.to_le_bytes());
+        hash_state.update(&self.shard_id.total.to_le_bytes());
+        for tx_proposal in &self.tx_proposals {
+            hash_state.update(tx_proposal.tx.to_digest().as_bytes());
+        }
+        hash_state.finalize()
+    }
+}
+
+// Drop the txs whose writes overlap with an earlier tx in the same batch. Every conflict check
+// rejects such a tx once the earlier one is admitted, so the miner does not need to see it.
+pub fn aggregate_tx_proposals<Tx: TxTrait>(
+    tx_proposals: Vec<TxProposal<Tx>>,
+) -> Vec<TxProposal<Tx>> {
+    if tx_proposals.len() <= 1 {
+        return tx_proposals;
+    }
+
+    let mut writes = ShardWriteSet::default();
+    tx_proposals
+        .into_iter()
+        .filter(|tx_proposal| {
+            let admitted = writes.try_add(tx_proposal.tx.tx_writes());
+            if !admitted {
+                discard_tx!(tx_proposal.tx.id(), "shard_block_conflict");
+            }
+            admitted
+        })
+        .collect()
+}
+
+// The union of the write sets admitted into a shard block.
+#[derive(Debug, Default)]
+pub struct ShardWriteSet(TxWriteData);
+
+impl ShardWriteSet {
+    // Add the writes unless they overlap with the admitted ones.
+    pub fn try_add(&mut self, writes: &TxWriteData) -> bool {
+        let overlap = writes.iter().any(|(addr, acc_write)| {
+            self.0
+                .get(addr)
+                .map_or(false, |admitted| acc_write_overlap(admitted, acc_write))
+        });
+        if !overlap {
+            self.0.merge(writes);
+        }
+        !overlap
+    }
+}
+
+// A reset of the values is treated as a write to every value of the account.
+fn acc_write_overlap(lhs: &AccountWriteData, rhs: &AccountWriteData) -> bool {
+    (lhs.has_nonce() && rhs.has_nonce())
+        || (lhs.has_code() && rhs.has_code())
+        || (lhs.has_reset_values() && (rhs.has_reset_values() || !rhs.values.is_empty()))
+        || (rhs.has_reset_values() && !lhs.values.is_empty())
+        || rhs.value_keys().any(|key| lhs.values.contains_key(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::create_tx_write_set;
+
+    #[test]
+    fn test_shard_write_set() {
+        let write_0 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000000" => 1,
+                }
+            },
+        };
+        let write_1 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000001" => 1,
+                }
+            },
+        };
+        let write_2 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000001" => 2,
+                }
+            },
+        };
+        let write_3 = create_tx_write_set! {
+            "0000000000000000000000000000000000000001" => {
+                nonce: 1,
+            },
+        };
+        let write_4 = create_tx_write_set! {
+            "0000000000000000000000000000000000000001" => {
+                nonce: 2,
+            },
+        };
+        let write_5 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                reset_values: true,
+            },
+        };
+
+        let mut writes = ShardWriteSet::default();
+        assert!(writes.try_add(&write_0));
+        assert!(writes.try_add(&write_1));
+        assert!(!writes.try_add(&write_2));
+        assert!(writes.try_add(&write_3));
+        assert!(!writes.try_add(&write_4));
+        assert!(!writes.try_add(&write_5));
+
+        let mut writes = ShardWriteSet::default();
+        assert!(writes.try_add(&write_5));
+        assert!(!writes.try_add(&write_0));
+        assert!(writes.try_add(&write_4));
+    }
+}
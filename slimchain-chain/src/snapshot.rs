@@ -1,16 +1,22 @@
 use crate::{
-    access_map::AccessMap,
-    block::BlockTrait,
+    access_map::{AccessMap, AccessMapDelta},
+    block::{median_time_past, BlockTrait},
     db::{DBPtr, Transaction},
+    genesis::genesis_state_update,
     latest::{LatestBlockHeader, LatestBlockHeaderPtr},
-    loader::BlockLoaderTrait,
+    loader::{BlockLoaderTrait, TxLoaderTrait},
+    tx_proof::tx_merkle_root,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use slimchain_common::{
     basic::{BlockHeight, ShardId, H256},
-    error::{Context as _, Result},
+    error::{ensure, ConsensusError, Context as _, Result},
+    tx::TxTrait,
+};
+use slimchain_tx_state::{
+    InShardData, OutShardData, StorageTxTrie, TxTrie, TxTrieDelta, TxTrieTrait,
 };
-use slimchain_tx_state::{InShardData, OutShardData, StorageTxTrie, TxTrie, TxTrieTrait};
 
 #[derive(Clone)]
 pub struct Snapshot<Block: BlockTrait, TxTrie: TxTrieTrait> {
@@ -51,6 +57,10 @@ impl<Block: BlockTrait, TxTrie: TxTrieTrait> Snapshot<Block, TxTrie> {
         )
     }
 
+    pub fn recent_blocks(&self) -> impl Iterator<Item = &Block> {
+        self.recent_blocks.iter()
+    }
+
     pub fn get_block(&self, height: BlockHeight) -> Option<&Block> {
         let idx = height - self.access_map.oldest_block_height();
         if idx.is_negative() {
@@ -60,6 +70,12 @@ impl<Block: BlockTrait, TxTrie: TxTrieTrait> Snapshot<Block, TxTrie> {
         }
     }
 
+    // The median time stamp of the latest `span` blocks, limited by the ones kept in the
+    // snapshot. The next block should not precede it.
+    pub fn median_time_past(&self, span: usize) -> Option<DateTime<Utc>> {
+        median_time_past(self.recent_blocks.iter().rev().take(span.max(1)))
+    }
+
     pub fn commit_block(&mut self, block: Block) {
         self.recent_blocks.push_back(block);
     }
@@ -74,6 +90,73 @@ impl<Block: BlockTrait, TxTrie: TxTrieTrait> Snapshot<Block, TxTrie> {
         }
         Ok(())
     }
+
+    // Verify a snapshot fetched from a remote peer before bootstrapping from it. The latest block
+    // in the snapshot should match the `trusted_block`, which is obtained independently. The
+    // received access map is not trusted. It is rebuilt from the txs of the verified blocks, which
+    // are loaded from `tx_loader` and checked against the tx lists.
+    pub fn verify<Tx: TxTrait>(
+        &mut self,
+        trusted_block: &Block,
+        state_len: usize,
+        tx_loader: &impl TxLoaderTrait<Tx>,
+        verify_consensus_fn: impl Fn(&Block, &Block) -> Result<(), ConsensusError>,
+    ) -> Result<()> {
+        let latest_block = self
+            .get_latest_block()
+            .context("Empty snapshot is not allowed.")?;
+        ensure!(
+            latest_block.to_digest() == trusted_block.to_digest(),
+            "Latest block mismatched with the trusted block."
+        );
+        ensure!(
+            self.recent_blocks.len() as u64
+                == (state_len as u64).min(latest_block.block_height().0 + 1),
+            "Recent blocks mismatched with the state length."
+        );
+        for (prev_blk, blk) in self
+            .recent_blocks
+            .iter()
+            .zip(self.recent_blocks.iter().skip(1))
+        {
+            blk.verify_block_header(prev_blk)?;
+            verify_consensus_fn(blk, prev_blk)?;
+        }
+        ensure!(
+            self.tx_trie.root_hash() == latest_block.state_root(),
+            "State root mismatched."
+        );
+
+        let mut access_map = AccessMap::new_at(
+            state_len,
+            self.recent_blocks
+                .front()
+                .context("Empty snapshot is not allowed.")?
+                .block_height(),
+        );
+        for (i, blk) in self.recent_blocks.iter().enumerate() {
+            if i > 0 {
+                access_map.alloc_new_block();
+            }
+            ensure!(
+                blk.tx_root() == tx_merkle_root(blk.tx_list()),
+                "Invalid tx root in block #{}.",
+                blk.block_height()
+            );
+            for &tx_hash in blk.tx_list().iter() {
+                let tx = tx_loader.get_tx(tx_hash)?;
+                ensure!(
+                    tx.to_digest() == tx_hash,
+                    "Tx mismatched with block #{}.",
+                    blk.block_height()
+                );
+                access_map.add_read(tx.tx_reads());
+                access_map.add_write(tx.tx_writes());
+            }
+        }
+        self.access_map = access_map;
+        Ok(())
+    }
 }
 
 impl<Block: BlockTrait + for<'de> Deserialize<'de>> Snapshot<Block, TxTrie> {
@@ -86,6 +169,18 @@ impl<Block: BlockTrait + for<'de> Deserialize<'de>> Snapshot<Block, TxTrie> {
         Ok(tx)
     }
 
+    // Persist the recent blocks together with the snapshot so that the node can start from it.
+    pub fn write_bootstrap_db_tx(&self) -> Result<Transaction>
+    where
+        Block: Serialize,
+    {
+        let mut tx = self.write_db_tx()?;
+        for blk in self.recent_blocks.iter() {
+            tx.insert_block(blk)?;
+        }
+        Ok(tx)
+    }
+
     pub fn write_sync(&self, db: &DBPtr) -> Result<()> {
         db.write_sync(self.write_db_tx()?)
     }
@@ -111,12 +206,103 @@ impl<Block: BlockTrait + for<'de> Deserialize<'de>> Snapshot<Block, TxTrie> {
             Ok(Self::new(recent_blocks, tx_trie, access_map))
         } else {
             let genesis_block = Block::genesis_block();
-            Ok(Self::genesis_snapshot(
-                Default::default(),
-                genesis_block,
-                state_len,
-            ))
+            let tx_trie = TxTrie::from_root_hash(genesis_block.state_root());
+            Ok(Self::genesis_snapshot(tx_trie, genesis_block, state_len))
+        }
+    }
+}
+
+// The changes of a snapshot since an older one. Only the new blocks and the changed parts of the
+// access map and the tx trie are included, which is much smaller than the full snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotDelta<Block> {
+    base_height: BlockHeight,
+    base_hash: H256,
+    new_blocks: Vec<Block>,
+    access_map: AccessMapDelta,
+    tx_trie: TxTrieDelta,
+}
+
+impl<Block> SnapshotDelta<Block> {
+    pub fn base_height(&self) -> BlockHeight {
+        self.base_height
+    }
+}
+
+impl<Block: BlockTrait> Snapshot<Block, TxTrie> {
+    pub fn diff(&self, older: &Self) -> Result<SnapshotDelta<Block>> {
+        let base_block = older
+            .get_latest_block()
+            .context("Empty snapshot is not allowed.")?;
+        let base_height = base_block.block_height();
+        let base_hash = base_block.to_digest();
+        if let Some(blk) = self.get_block(base_height) {
+            ensure!(
+                blk.to_digest() == base_hash,
+                "The older snapshot is not on the same chain."
+            );
+        }
+
+        Ok(SnapshotDelta {
+            base_height,
+            base_hash,
+            new_blocks: self
+                .recent_blocks
+                .iter()
+                .filter(|blk| blk.block_height() > base_height)
+                .cloned()
+                .collect(),
+            access_map: self.access_map.diff(&older.access_map)?,
+            tx_trie: self.tx_trie.diff(&older.tx_trie),
+        })
+    }
+
+    // The blocks in the delta are not verified here. Use `verify` to check the result against a
+    // trusted block.
+    pub fn apply_delta(&mut self, delta: &SnapshotDelta<Block>) -> Result<()> {
+        let latest_block = self
+            .get_latest_block()
+            .context("Empty snapshot is not allowed.")?;
+        ensure!(
+            latest_block.block_height() == delta.base_height
+                && latest_block.to_digest() == delta.base_hash,
+            "Snapshot delta is not based on the latest block."
+        );
+
+        let mut new = self.clone();
+        new.access_map.apply_delta(&delta.access_map)?;
+        new.tx_trie.apply_delta(&delta.tx_trie)?;
+
+        let oldest_height = new.access_map.oldest_block_height();
+        match delta.new_blocks.first() {
+            Some(blk) if blk.block_height() != delta.base_height.next_height() => {
+                new.recent_blocks = delta.new_blocks.iter().cloned().collect();
+            }
+            _ => new.recent_blocks.extend(delta.new_blocks.iter().cloned()),
         }
+        while new
+            .recent_blocks
+            .front()
+            .is_some_and(|blk| blk.block_height() < oldest_height)
+        {
+            new.recent_blocks.pop_front();
+        }
+
+        let latest_block = new
+            .get_latest_block()
+            .context("Empty snapshot is not allowed.")?;
+        ensure!(
+            new.recent_blocks.front().map(|blk| blk.block_height()) == Some(oldest_height)
+                && latest_block.block_height() == new.current_height(),
+            "Recent blocks mismatched with the access map."
+        );
+        ensure!(
+            new.tx_trie.root_hash() == latest_block.state_root(),
+            "State root mismatched."
+        );
+
+        *self = new;
+        Ok(())
     }
 }
 
@@ -197,12 +383,18 @@ impl<Block: BlockTrait + for<'de> Deserialize<'de>> Snapshot<Block, StorageTxTri
             assert_eq!(height, access_map.latest_block_height());
             Ok(Self::new(recent_blocks, tx_trie, access_map))
         } else {
+            let genesis_block = Block::genesis_block();
+            if let Some(update) = genesis_state_update(shard_id) {
+                let mut tx = Transaction::new();
+                tx.update_state(&update)?;
+                db.write_sync(tx)
+                    .context("Failed to write genesis state to the database.")?;
+            }
             let tx_trie = StorageTxTrie::new(
                 shard_id,
-                InShardData::new(db.clone(), H256::zero()),
+                InShardData::new(db.clone(), genesis_block.state_root()),
                 OutShardData::default(),
             );
-            let genesis_block = Block::genesis_block();
             Ok(Self::genesis_snapshot(tx_trie, genesis_block, state_len))
         }
     }
@@ -229,3 +421,169 @@ pub fn load_recent_blocks<Block: BlockTrait + for<'de> Deserialize<'de>>(
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::{BlockHeader, BlockTxList},
+        consensus::raft::{create_new_block, verify_consensus, Block},
+        db::DB,
+    };
+    use chrono::{TimeZone, Utc};
+    use slimchain_common::{basic::H256, collections::HashMap, digest::Digestible, tx::SignedTx};
+
+    async fn create_block(height: u64, prev_blk: &Block, state_root: H256) -> Block {
+        create_new_block(
+            BlockHeader::new(
+                height.into(),
+                prev_blk.to_digest(),
+                Utc::now(),
+                BlockTxList::new(),
+                state_root,
+            ),
+            prev_blk,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_snapshot() {
+        let blk0 = Block::genesis_block();
+        let blk1 = create_block(1, &blk0, H256::zero()).await;
+        let blk2 = create_block(2, &blk1, H256::zero()).await;
+        let bad_blk2 = create_block(2, &blk1, H256::repeat_byte(1)).await;
+
+        let mut access_map = AccessMap::new(8);
+        access_map.alloc_new_block();
+        access_map.alloc_new_block();
+        let txs = HashMap::<H256, SignedTx>::new();
+        let mut snapshot = Snapshot::<Block, TxTrie>::new(
+            imbl::vector![blk0.clone(), blk1.clone(), blk2.clone()],
+            TxTrie::default(),
+            access_map.clone(),
+        );
+        snapshot.verify(&blk2, 8, &txs, verify_consensus).unwrap();
+        assert!(snapshot.verify(&blk1, 8, &txs, verify_consensus).is_err());
+        assert!(snapshot.verify(&blk2, 2, &txs, verify_consensus).is_err());
+
+        // The access map received is replaced by the one rebuilt from the blocks.
+        let mut tampered = Snapshot::<Block, TxTrie>::new(
+            imbl::vector![blk0.clone(), blk1.clone(), blk2.clone()],
+            TxTrie::default(),
+            AccessMap::new(8),
+        );
+        tampered.verify(&blk2, 8, &txs, verify_consensus).unwrap();
+        assert_eq!(access_map, tampered.access_map);
+
+        let mut bad_snapshot = Snapshot::<Block, TxTrie>::new(
+            imbl::vector![blk0.clone(), blk1.clone(), bad_blk2.clone()],
+            TxTrie::default(),
+            access_map.clone(),
+        );
+        assert!(bad_snapshot
+            .verify(&bad_blk2, 8, &txs, verify_consensus)
+            .is_err());
+
+        let mut bad_snapshot = Snapshot::<Block, TxTrie>::new(
+            imbl::vector![blk0, blk2.clone()],
+            TxTrie::default(),
+            access_map,
+        );
+        assert!(bad_snapshot
+            .verify(&blk2, 8, &txs, verify_consensus)
+            .is_err());
+
+        let db = DB::load_test();
+        snapshot
+            .write_bootstrap_db_tx()
+            .and_then(|tx| db.write_sync(tx))
+            .unwrap();
+        let loaded = Snapshot::<Block, TxTrie>::load_from_db(&db, 8).unwrap();
+        assert_eq!(2, loaded.current_height().0);
+        assert_eq!(Some(&blk2), loaded.get_latest_block());
+        assert_eq!(Some(&blk1), loaded.get_block(1.into()));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_delta() {
+        let mut blks = vec![Block::genesis_block()];
+        for i in 1..=6 {
+            let blk = create_block(i, blks.last().unwrap(), H256::zero()).await;
+            blks.push(blk);
+        }
+
+        let mut snapshot =
+            Snapshot::<Block, TxTrie>::genesis_snapshot(TxTrie::default(), blks[0].clone(), 3);
+        let mut snapshots = vec![snapshot.clone()];
+        for blk in blks.iter().skip(1) {
+            snapshot.access_map.alloc_new_block();
+            snapshot.remove_oldest_block().unwrap();
+            snapshot.commit_block(blk.clone());
+            snapshots.push(snapshot.clone());
+        }
+
+        for (older, newer) in [(0, 2), (2, 3), (1, 6), (4, 4)] {
+            let delta = snapshots[newer].diff(&snapshots[older]).unwrap();
+            assert_eq!(older as u64, delta.base_height().0);
+            let mut applied = snapshots[older].clone();
+            applied.apply_delta(&delta).unwrap();
+            assert_eq!(snapshots[newer].recent_blocks, applied.recent_blocks);
+            assert_eq!(snapshots[newer].access_map, applied.access_map);
+            assert_eq!(snapshots[newer].tx_trie, applied.tx_trie);
+            applied
+                .verify(
+                    &blks[newer],
+                    3,
+                    &HashMap::<H256, SignedTx>::new(),
+                    verify_consensus,
+                )
+                .unwrap();
+        }
+
+        let delta = snapshots[3].diff(&snapshots[2]).unwrap();
+        assert!(snapshots[1].clone().apply_delta(&delta).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_median_time_past() {
+        // The block timestamps are kept in milliseconds.
+        let base =
+            Utc.timestamp_millis(Utc::now().timestamp_millis()) - chrono::Duration::seconds(10);
+        let mut snapshot = Snapshot::<Block, TxTrie>::genesis_snapshot(
+            TxTrie::default(),
+            Block::genesis_block(),
+            3,
+        );
+        for secs in [3, 1, 2] {
+            let prev_blk = snapshot.get_latest_block().unwrap().clone();
+            let blk = create_new_block(
+                BlockHeader::new(
+                    prev_blk.block_height().next_height(),
+                    prev_blk.to_digest(),
+                    base + chrono::Duration::seconds(secs),
+                    BlockTxList::new(),
+                    H256::zero(),
+                ),
+                &prev_blk,
+            )
+            .await
+            .unwrap();
+            snapshot.commit_block(blk);
+        }
+
+        // The genesis block is out of the span.
+        let median_time_past = snapshot.median_time_past(3).unwrap();
+        assert_eq!(base + chrono::Duration::seconds(2), median_time_past);
+
+        let drift = std::time::Duration::from_secs(30);
+        let mut blk = snapshot.get_latest_block().unwrap().clone();
+        blk.block_header_mut().time_stamp = base + chrono::Duration::seconds(2);
+        blk.verify_time_stamp(median_time_past, drift).unwrap();
+        blk.block_header_mut().time_stamp = base + chrono::Duration::seconds(1);
+        assert!(blk.verify_time_stamp(median_time_past, drift).is_err());
+        blk.block_header_mut().time_stamp = Utc::now() + chrono::Duration::seconds(60);
+        assert!(blk.verify_time_stamp(median_time_past, drift).is_err());
+    }
+}
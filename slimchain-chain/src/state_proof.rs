@@ -0,0 +1,204 @@
+use crate::{
+    block::{BlockHeader, BlockTrait},
+    db::DB,
+    loader::BlockLoaderTrait,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{AccountData, Address, BlockHeight, StateKey, StateValue, H256},
+    digest::Digestible,
+    error::{bail, ensure, Result},
+};
+use slimchain_merkle_trie::prelude::*;
+use slimchain_tx_state::trie_view::{AccountTrieView, StateTrieView};
+
+// The proof of an account, and optionally one of its values, in the state after the block at
+// `height`. It can be verified against the `state_root` in the block header. A missing account
+// or value is proven to be absent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateProof {
+    pub height: BlockHeight,
+    pub address: Address,
+    pub acc_data: Option<AccountData>,
+    pub acc_proof: Proof,
+    pub key: Option<StateKey>,
+    pub value: Option<StateValue>,
+    pub value_proof: Option<Proof>,
+}
+
+impl StateProof {
+    pub fn acc_state_root(&self) -> H256 {
+        self.acc_data
+            .as_ref()
+            .map(|acc| acc.acc_state_root)
+            .unwrap_or_default()
+    }
+
+    pub fn verify(&self, header: &BlockHeader) -> Result<()> {
+        ensure!(self.height == header.height, "Block height mismatched.");
+        ensure!(
+            self.acc_proof.root_hash() == header.state_root,
+            "Invalid state proof (expect: {}, actual: {}).",
+            header.state_root,
+            self.acc_proof.root_hash()
+        );
+        let acc_hash = self
+            .acc_data
+            .as_ref()
+            .map_or_else(H256::zero, |acc| acc.to_digest());
+        ensure!(
+            self.acc_proof.value_hash(&self.address) == Some(acc_hash),
+            "Invalid account {} in block #{}.",
+            self.address,
+            self.height
+        );
+
+        match (self.key, self.value_proof.as_ref()) {
+            (None, None) => {
+                ensure!(self.value.is_none(), "Unexpected value without a key.");
+            }
+            (Some(key), Some(value_proof)) => {
+                ensure!(
+                    value_proof.root_hash() == self.acc_state_root(),
+                    "Invalid value proof (expect: {}, actual: {}).",
+                    self.acc_state_root(),
+                    value_proof.root_hash()
+                );
+                let value_hash = self.value.map_or_else(H256::zero, |v| v.to_digest());
+                ensure!(
+                    value_proof.value_hash(&key) == Some(value_hash),
+                    "Invalid value {} of account {} in block #{}.",
+                    key,
+                    self.address,
+                    self.height
+                );
+            }
+            _ => bail!("The key and the value proof mismatched."),
+        }
+
+        Ok(())
+    }
+}
+
+// Prove the account (and the value) against the state root of a past block. Every historical
+// state root is retained in the database, as the trie nodes are never pruned.
+pub fn prove_at_height<Block>(
+    db: &DB,
+    height: BlockHeight,
+    address: Address,
+    key: Option<StateKey>,
+) -> Result<StateProof>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de>,
+{
+    let block: Block = db.get_block(height)?;
+    let (acc_data, acc_proof) = read_trie(&AccountTrieView::new(db), block.state_root(), &address)?;
+    let mut proof = StateProof {
+        height,
+        address,
+        acc_data,
+        acc_proof,
+        key,
+        value: None,
+        value_proof: None,
+    };
+
+    if let Some(key) = key {
+        let (value, value_proof) = read_trie(
+            &StateTrieView::new(db, address),
+            proof.acc_state_root(),
+            &key,
+        )?;
+        proof.value = value;
+        proof.value_proof = Some(value_proof);
+    }
+
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::BlockTxList,
+        consensus::raft::{create_new_block, Block},
+        db::Transaction,
+    };
+    use chrono::Utc;
+    use slimchain_common::{
+        basic::{Nonce, H160},
+        rw_set::TxWriteData,
+    };
+    use slimchain_tx_state::update_tx_state;
+
+    #[tokio::test]
+    async fn test_state_proof() {
+        let db = DB::load_test();
+        let address = Address(H160::from_low_u64_be(1));
+        let key = StateKey(H256::from_low_u64_be(1));
+
+        let mut state_root = H256::zero();
+        let mut prev_blk = Block::genesis_block();
+        let mut headers = Vec::new();
+        for height in 1..=2u64 {
+            let mut writes = TxWriteData::default();
+            writes.add_nonce(address, height.into());
+            writes.add_value(address, key, StateValue(H256::from_low_u64_be(height * 10)));
+            let update = update_tx_state(db.as_ref(), state_root, &writes).unwrap();
+            state_root = update.root;
+            let mut db_tx = Transaction::new();
+            db_tx.update_state(&update).unwrap();
+            let blk = create_new_block(
+                BlockHeader::new(
+                    height.into(),
+                    prev_blk.to_digest(),
+                    Utc::now(),
+                    BlockTxList::new(),
+                    state_root,
+                ),
+                &prev_blk,
+            )
+            .await
+            .unwrap();
+            db_tx.insert_block(&blk).unwrap();
+            db.write_sync(db_tx).unwrap();
+            headers.push(blk.block_header().clone());
+            prev_blk = blk;
+        }
+
+        for (i, header) in headers.iter().enumerate() {
+            let height = header.height;
+            let proof = prove_at_height::<Block>(&db, height, address, Some(key)).unwrap();
+            assert_eq!(
+                Some(StateValue(H256::from_low_u64_be((i as u64 + 1) * 10))),
+                proof.value
+            );
+            proof.verify(header).unwrap();
+
+            let mut bad_proof = proof.clone();
+            bad_proof.value = Some(StateValue(H256::zero()));
+            assert!(bad_proof.verify(header).is_err());
+            let mut bad_proof = proof.clone();
+            bad_proof.height = height.next_height();
+            assert!(bad_proof.verify(header).is_err());
+
+            let proof = prove_at_height::<Block>(&db, height, address, None).unwrap();
+            assert_eq!(
+                Some(Nonce::from(i as u64 + 1)),
+                proof.acc_data.as_ref().map(|acc| acc.nonce)
+            );
+            proof.verify(header).unwrap();
+        }
+
+        // The proof against block #1 does not hold in block #2.
+        let proof = prove_at_height::<Block>(&db, 1.into(), address, Some(key)).unwrap();
+        assert!(proof.verify(&headers[1]).is_err());
+
+        // An absent account is proven as such.
+        let other = Address(H160::from_low_u64_be(2));
+        let proof = prove_at_height::<Block>(&db, 2.into(), other, Some(key)).unwrap();
+        assert!(proof.acc_data.is_none());
+        assert!(proof.value.is_none());
+        proof.verify(&headers[1]).unwrap();
+    }
+}
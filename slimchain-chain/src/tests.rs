@@ -12,13 +12,15 @@ use crate::{
     db::DB,
     latest::LatestTxCount,
     snapshot::Snapshot,
+    tx_ordering::TxOrdering,
 };
 use futures::{channel::mpsc::unbounded, prelude::*};
 use rand::SeedableRng;
 use slimchain_common::{
-    basic::{ShardId, U256},
+    basic::{Address, ShardId, H256, U256},
     ed25519::Keypair,
-    tx::SignedTx,
+    rw_set::{TxReadSet, TxWriteData},
+    tx::{RawTx, SignedTx},
     tx_req::{caller_address_from_pk, TxRequest},
 };
 use slimchain_tx_engine::TxEngine;
@@ -30,6 +32,26 @@ use slimchain_utils::{
 };
 use std::{path::PathBuf, time::Duration};
 
+// A tx calling the default address, signed by `keypair`. Used by the tests across the crate.
+pub(crate) fn create_signed_tx(nonce: u64, keypair: &Keypair) -> SignedTx {
+    RawTx {
+        caller: Address::default(),
+        input: TxRequest::Call {
+            nonce: nonce.into(),
+            address: Address::default(),
+            data: Vec::new(),
+            chain_id: 0,
+            expiry: None,
+        },
+        block_height: 0.into(),
+        block_timestamp: 0,
+        state_root: H256::zero(),
+        reads: TxReadSet::default(),
+        writes: TxWriteData::default(),
+    }
+    .sign(keypair)
+}
+
 async fn test_chain_cycle(chain_cfg: &ChainConfig, miner_cfg: &MinerConfig) {
     let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -76,6 +98,8 @@ async fn test_chain_cycle(chain_cfg: &ChainConfig, miner_cfg: &MinerConfig) {
     let mut tx_reqs = vec![TxRequest::Create {
         nonce: U256::from(0).into(),
         code: contract.code().clone(),
+        chain_id: 0,
+        expiry: None,
     }];
 
     for i in 0..5 {
@@ -88,6 +112,8 @@ async fn test_chain_cycle(chain_cfg: &ChainConfig, miner_cfg: &MinerConfig) {
                     &[Token::Uint(U256::from(i)), Token::Uint(U256::from(i))],
                 )
                 .unwrap(),
+            chain_id: 0,
+            expiry: None,
         });
     }
 
@@ -222,14 +248,21 @@ async fn test1() {
         max_txs: 1,
         min_txs: 1,
         max_block_interval: Duration::from_millis(100),
+        tx_ordering: TxOrdering::FCFS,
+        pipeline: false,
+        max_tx_age: None,
     };
 
     for state_len in 1..=3 {
-        for &conflict_check in &[ConflictCheck::SSI, ConflictCheck::OCC] {
+        for &conflict_check in &[ConflictCheck::SSI, ConflictCheck::OCC, ConflictCheck::MVCC] {
             let chain_cfg = ChainConfig {
                 conflict_check,
                 state_len,
                 consensus: Consensus::Raft,
+                digest: Default::default(),
+                shard_assignment: Default::default(),
+                median_time_span: 11,
+                max_future_drift: Duration::from_secs(30),
             };
             warn!(state_len, ?conflict_check);
             test_chain_cycle(&chain_cfg, &miner_cfg).await;
@@ -246,6 +279,9 @@ async fn test2() {
         max_txs: 1,
         min_txs: 1,
         max_block_interval: Duration::from_millis(100),
+        tx_ordering: TxOrdering::DepGraph,
+        pipeline: false,
+        max_tx_age: None,
     };
 
     for state_len in 1..=3 {
@@ -253,6 +289,10 @@ async fn test2() {
             conflict_check: ConflictCheck::SSI,
             state_len,
             consensus: Consensus::Raft,
+            digest: Default::default(),
+            shard_assignment: Default::default(),
+            median_time_span: 11,
+            max_future_drift: Duration::from_secs(30),
         };
         warn!(state_len);
         test_chain_cycle(&chain_cfg, &miner_cfg).await;
@@ -0,0 +1,105 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use slimchain_common::basic::{BlockHeight, H256};
+use tokio::sync::broadcast;
+
+// The slow subscribers lose the oldest events once the buffer is full.
+const TX_EVENT_BUFFER_SIZE: usize = 16_384;
+
+static TX_EVENT_SENDER: Lazy<broadcast::Sender<TxEvent>> =
+    Lazy::new(|| broadcast::channel(TX_EVENT_BUFFER_SIZE).0);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxEvent {
+    Committed {
+        tx_id: H256,
+        height: BlockHeight,
+    },
+    Discarded {
+        tx_id: H256,
+        reason: String,
+        detail: Option<String>,
+    },
+}
+
+pub fn has_tx_event_subscribers() -> bool {
+    TX_EVENT_SENDER.receiver_count() > 0
+}
+
+pub fn publish_tx_event(event: TxEvent) {
+    TX_EVENT_SENDER.send(event).ok();
+}
+
+pub fn subscribe_tx_events() -> broadcast::Receiver<TxEvent> {
+    TX_EVENT_SENDER.subscribe()
+}
+
+// Record the discarded tx in the metrics and notify the subscribers.
+#[macro_export]
+macro_rules! discard_tx {
+    ($tx_id:expr, $reason:literal) => {{
+        let tx_id = $tx_id;
+        ::slimchain_utils::record_event!("discard_tx", "tx_id": tx_id, "reason": $reason);
+        if $crate::tx_event::has_tx_event_subscribers() {
+            $crate::tx_event::publish_tx_event($crate::tx_event::TxEvent::Discarded {
+                tx_id,
+                reason: $reason.to_string(),
+                detail: None,
+            });
+        }
+    }};
+    ($tx_id:expr, $reason:literal, $detail:expr) => {{
+        let tx_id = $tx_id;
+        let detail: String = $detail;
+        ::slimchain_utils::record_event!("discard_tx", "tx_id": tx_id, "reason": $reason, "detail": detail);
+        if $crate::tx_event::has_tx_event_subscribers() {
+            $crate::tx_event::publish_tx_event($crate::tx_event::TxEvent::Discarded {
+                tx_id,
+                reason: $reason.to_string(),
+                detail: Some(detail),
+            });
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tx_event() {
+        let mut rx = subscribe_tx_events();
+        let tx_id = H256::repeat_byte(1);
+        crate::discard_tx!(tx_id, "tx_conflict");
+        crate::discard_tx!(tx_id, "invalid_sig", format!("{}", 42));
+        publish_tx_event(TxEvent::Committed {
+            tx_id,
+            height: BlockHeight(1),
+        });
+
+        assert_eq!(
+            TxEvent::Discarded {
+                tx_id,
+                reason: "tx_conflict".to_string(),
+                detail: None,
+            },
+            rx.recv().await.unwrap()
+        );
+        assert_eq!(
+            TxEvent::Discarded {
+                tx_id,
+                reason: "invalid_sig".to_string(),
+                detail: Some("42".to_string()),
+            },
+            rx.recv().await.unwrap()
+        );
+        assert_eq!(
+            TxEvent::Committed {
+                tx_id,
+                height: BlockHeight(1),
+            },
+            rx.recv().await.unwrap()
+        );
+    }
+}
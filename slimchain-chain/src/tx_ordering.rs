@@ -0,0 +1,197 @@
+use serde::Deserialize;
+use slimchain_common::{
+    collections::HashSet,
+    rw_set::{TxReadSet, TxWriteData},
+};
+use std::collections::BTreeSet;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxOrdering {
+    FCFS,
+    DepGraph,
+}
+
+impl Default for TxOrdering {
+    fn default() -> Self {
+        Self::FCFS
+    }
+}
+
+pub fn read_write_overlap(reads: &TxReadSet, writes: &TxWriteData) -> bool {
+    for (acc_addr, acc_write) in writes.iter() {
+        let acc_read = match reads.get(acc_addr) {
+            Some(entry) => entry,
+            None => continue,
+        };
+
+        if acc_read.get_nonce() && acc_write.has_nonce() {
+            return true;
+        }
+
+        if acc_read.get_code() && acc_write.has_code() {
+            return true;
+        }
+
+        if acc_write.has_reset_values() && !acc_read.get_values().is_empty() {
+            return true;
+        }
+
+        if acc_write
+            .value_keys()
+            .any(|key| acc_read.get_values().contains(key))
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+// Build the read/write dependency graph over the txs, where an edge i -> j means that tx i reads
+// the data written by tx j and therefore should be placed before tx j. The txs are then sorted
+// topologically, preferring the arrival order. Whenever a cycle is encountered, the tx blocking
+// the most remaining txs is dropped.
+//
+// Return the order of the admitted txs and the list of the dropped txs.
+pub fn dependency_order(rw_sets: &[(&TxReadSet, &TxWriteData)]) -> (Vec<usize>, Vec<usize>) {
+    let len = rw_sets.len();
+    let mut succ: Vec<HashSet<usize>> = vec![HashSet::new(); len];
+    let mut in_deg: Vec<usize> = vec![0; len];
+
+    for (i, (reads, _)) in rw_sets.iter().enumerate() {
+        for (j, (_, writes)) in rw_sets.iter().enumerate() {
+            if i != j && read_write_overlap(reads, writes) {
+                succ[i].insert(j);
+                in_deg[j] += 1;
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(len);
+    let mut dropped = Vec::new();
+    let mut remaining: BTreeSet<usize> = (0..len).collect();
+    let mut ready: BTreeSet<usize> = (0..len).filter(|&i| in_deg[i] == 0).collect();
+
+    while !remaining.is_empty() {
+        let (idx, keep) = match ready.iter().next().copied() {
+            Some(idx) => (idx, true),
+            None => {
+                let victim = remaining
+                    .iter()
+                    .copied()
+                    .max_by_key(|&i| succ[i].iter().filter(|j| remaining.contains(j)).count())
+                    .expect("Failed to find the tx to drop.");
+                (victim, false)
+            }
+        };
+
+        ready.remove(&idx);
+        remaining.remove(&idx);
+        if keep {
+            order.push(idx);
+        } else {
+            dropped.push(idx);
+        }
+
+        for &j in &succ[idx] {
+            if remaining.contains(&j) {
+                in_deg[j] -= 1;
+                if in_deg[j] == 0 {
+                    ready.insert(j);
+                }
+            }
+        }
+    }
+
+    (order, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::{create_tx_read_set, create_tx_write_set};
+
+    #[test]
+    fn test_deserialize() {
+        use slimchain_utils::toml;
+
+        #[derive(Deserialize)]
+        struct Test {
+            tx_ordering: TxOrdering,
+        }
+
+        let input = toml::toml! { tx_ordering = "fcfs" };
+        assert_eq!(
+            TxOrdering::FCFS,
+            input.try_into::<Test>().unwrap().tx_ordering
+        );
+
+        let input = toml::toml! { tx_ordering = "depgraph" };
+        assert_eq!(
+            TxOrdering::DepGraph,
+            input.try_into::<Test>().unwrap().tx_ordering
+        );
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let read_0 = create_tx_read_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: [
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                ]
+            },
+        };
+        let read_1 = create_tx_read_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: [
+                    "0000000000000000000000000000000000000000000000000000000000000001",
+                ]
+            },
+        };
+        let write_0 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000000" => 1,
+                }
+            },
+        };
+        let write_1 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000001" => 1,
+                }
+            },
+        };
+        let write_2 = create_tx_write_set! {
+            "0000000000000000000000000000000000000000" => {
+                values: {
+                    "0000000000000000000000000000000000000000000000000000000000000002" => 1,
+                }
+            },
+        };
+
+        assert!(read_write_overlap(&read_0, &write_0));
+        assert!(!read_write_overlap(&read_0, &write_1));
+
+        // tx0 writes the data read by tx1. Thus tx1 should be placed first.
+        let (order, dropped) = dependency_order(&[(&read_1, &write_0), (&read_0, &write_2)]);
+        assert_eq!(vec![1, 0], order);
+        assert!(dropped.is_empty());
+
+        // tx0 and tx1 depend on each other. One of them has to be dropped.
+        let (order, dropped) = dependency_order(&[
+            (&read_1, &write_0),
+            (&read_0, &write_1),
+            (&read_0, &write_2),
+        ]);
+        assert_eq!(vec![2, 0], order);
+        assert_eq!(vec![1], dropped);
+
+        let (order, dropped) = dependency_order(&[]);
+        assert!(order.is_empty());
+        assert!(dropped.is_empty());
+    }
+}
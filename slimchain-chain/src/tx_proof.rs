@@ -0,0 +1,150 @@
+use crate::{
+    block::{BlockHeader, BlockTrait, BlockTxList},
+    db::DB,
+    loader::BlockLoaderTrait,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    collections::HashMap,
+    digest::Digestible,
+    error::{bail, ensure, Context as _, Result},
+};
+use slimchain_merkle_trie::prelude::*;
+
+// The merkle trie built on the tx list of a block, which maps each tx hash to its position.
+#[derive(Debug, Default)]
+struct TxListTrie {
+    root: H256,
+    nodes: HashMap<H256, TrieNode<u64>>,
+}
+
+impl NodeLoader<u64> for TxListTrie {
+    fn load_node(&self, address: H256) -> Result<TrieNode<u64>> {
+        self.nodes
+            .get(&address)
+            .cloned()
+            .with_context(|| format!("TxListTrie: failed to load node {}", address))
+    }
+}
+
+impl TxListTrie {
+    fn build(tx_list: &BlockTxList) -> Result<Self> {
+        let mut ctx: WriteTrieContext<H256, u64, _> =
+            WriteTrieContext::new(TxListTrie::default(), H256::zero());
+        for (idx, tx_hash) in tx_list.iter().enumerate() {
+            ctx.insert(tx_hash, idx as u64)?;
+        }
+        let apply = ctx.changes();
+        Ok(Self {
+            root: apply.root,
+            nodes: apply.nodes,
+        })
+    }
+}
+
+pub fn tx_merkle_root(tx_list: &BlockTxList) -> H256 {
+    TxListTrie::build(tx_list)
+        .expect("Failed to build the tx merkle trie.")
+        .root
+}
+
+// The proof that a tx is included in the block at `height`. It can be verified against the
+// `tx_root` in the block header without knowing the whole tx list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxInclusionProof {
+    pub height: BlockHeight,
+    pub index: usize,
+    pub tx_hash: H256,
+    pub proof: Proof,
+}
+
+impl TxInclusionProof {
+    pub fn from_block(block: &impl BlockTrait, tx_hash: H256) -> Result<Self> {
+        let trie = TxListTrie::build(block.tx_list())?;
+        let (index, proof) = read_trie(&trie, trie.root, &tx_hash)?;
+        let index = index.with_context(|| {
+            format!(
+                "Tx {} is not included in block #{}.",
+                tx_hash,
+                block.block_height()
+            )
+        })?;
+        Ok(Self {
+            height: block.block_height(),
+            index: index as usize,
+            tx_hash,
+            proof,
+        })
+    }
+
+    pub fn verify(&self, header: &BlockHeader) -> Result<()> {
+        ensure!(self.height == header.height, "Block height mismatched.");
+        ensure!(
+            self.proof.root_hash() == header.tx_root,
+            "Invalid tx inclusion proof (expect: {}, actual: {}).",
+            header.tx_root,
+            self.proof.root_hash()
+        );
+        ensure!(
+            self.proof.value_hash(&self.tx_hash) == Some((self.index as u64).to_digest()),
+            "Tx {} is not included in block #{}.",
+            self.tx_hash,
+            self.height
+        );
+        Ok(())
+    }
+}
+
+// Locate the tx using its receipt and prove its inclusion in the committed block.
+pub fn get_tx_inclusion_proof<Block>(db: &DB, tx_id: H256) -> Result<TxInclusionProof>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de>,
+{
+    let receipt = match db.get_tx_receipt(tx_id)? {
+        Some(receipt) => receipt,
+        None => bail!("Tx {} has not been committed.", tx_id),
+    };
+    let block: Block = db.get_block(receipt.block_height)?;
+    let tx_hash = *block
+        .tx_list()
+        .get(receipt.index)
+        .context("Invalid tx receipt.")?;
+    TxInclusionProof::from_block(&block, tx_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::raft::{create_new_block, Block};
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_tx_inclusion_proof() {
+        let tx_list: BlockTxList = (1..=10u64).map(|i| i.to_digest()).collect();
+        let tx_root = tx_merkle_root(&tx_list);
+        assert_ne!(H256::zero(), tx_root);
+        assert_eq!(H256::zero(), tx_merkle_root(&BlockTxList::new()));
+
+        let header = BlockHeader::new(1.into(), H256::zero(), Utc::now(), tx_list, H256::zero());
+        assert_eq!(tx_root, header.tx_root);
+        let block = create_new_block(header.clone(), &Block::genesis_block())
+            .await
+            .unwrap();
+
+        for i in 1..=10u64 {
+            let proof = TxInclusionProof::from_block(&block, i.to_digest()).unwrap();
+            assert_eq!((i - 1) as usize, proof.index);
+            proof.verify(&header).unwrap();
+
+            let mut bad_proof = proof.clone();
+            bad_proof.index += 1;
+            assert!(bad_proof.verify(&header).is_err());
+            let mut bad_proof = proof.clone();
+            bad_proof.height = 2.into();
+            assert!(bad_proof.verify(&header).is_err());
+        }
+
+        assert!(TxInclusionProof::from_block(&block, 11u64.to_digest()).is_err());
+    }
+}
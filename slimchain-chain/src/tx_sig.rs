@@ -0,0 +1,42 @@
+use rayon::prelude::*;
+use slimchain_common::{
+    ed25519,
+    error::{Context as _, Result},
+    tx::TxTrait,
+};
+
+// The number of ed25519 signatures verified in a single batch. The batches are verified in
+// parallel, so that a large block still spreads over all the cores.
+const BATCH_SIZE: usize = 64;
+
+// Verify the signatures of all the txs in a block. The ed25519 signatures are verified in batch,
+// and the rest, e.g., the attestation reports, in parallel across the txs.
+pub fn verify_txs_sig<Tx: TxTrait>(txs: &[Tx]) -> Result<()> {
+    txs.par_iter()
+        .try_for_each(|tx| tx.verify_sig_except_batch())?;
+
+    let batch: Vec<_> = txs.par_iter().filter_map(|tx| tx.batch_sig()).collect();
+    batch
+        .par_chunks(BATCH_SIZE)
+        .try_for_each(ed25519::verify_batch)
+        .context("Invalid ed25519 signature in batch.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_signed_tx;
+    use slimchain_common::{ed25519::Keypair, tx::SignedTx};
+
+    #[test]
+    fn test_verify_txs_sig() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let mut txs: Vec<_> = (0..200).map(|i| create_signed_tx(i, &keypair)).collect();
+        verify_txs_sig(&txs).unwrap();
+        verify_txs_sig::<SignedTx>(&[]).unwrap();
+
+        txs[150].raw_tx.block_height = 1.into();
+        assert!(verify_txs_sig(&txs).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::basic::{H160, H256};
-use crate::digest::Digestible;
+use crate::digest::{keccak256, Digestible};
+use crate::error::{anyhow, ensure, Error, Result};
+use core::{fmt, str::FromStr};
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(
     Debug,
@@ -11,18 +14,136 @@ use crate::digest::Digestible;
     Ord,
     PartialOrd,
     Hash,
-    serde::Serialize,
-    serde::Deserialize,
     derive_more::Deref,
     derive_more::DerefMut,
-    derive_more::Display,
     derive_more::From,
     derive_more::Into,
 )]
 pub struct Address(pub H160);
 
+impl Address {
+    // The hex of the address in the mixed case of EIP-55, without the 0x prefix.
+    fn checksum_hex(&self) -> [u8; 40] {
+        const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = [0u8; 40];
+        for (i, byte) in self.0.as_bytes().iter().enumerate() {
+            hex[2 * i] = HEX_CHARS[(byte >> 4) as usize];
+            hex[2 * i + 1] = HEX_CHARS[(byte & 0xf) as usize];
+        }
+
+        // Capitalize the letter if the corresponding nibble of the hash is at least 8.
+        let hash = keccak256(&hex);
+        for (i, c) in hex.iter_mut().enumerate() {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0xf;
+            if nibble >= 8 {
+                c.make_ascii_uppercase();
+            }
+        }
+        hex
+    }
+}
+
 impl Digestible for Address {
     fn to_digest(&self) -> H256 {
         self.0.as_bytes().to_digest()
     }
 }
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = self.checksum_hex();
+        let hex = core::str::from_utf8(&hex).map_err(|_| fmt::Error)?;
+        write!(f, "0x{}", hex)
+    }
+}
+
+// Both the all lowercase and the all uppercase are accepted. Otherwise, the mixed case should
+// match the checksum.
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let hex = input.strip_prefix("0x").unwrap_or(input);
+        ensure!(hex.len() == 40, "Invalid address length: {}.", input);
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(hex, &mut bytes)
+            .map_err(|e| anyhow!("Invalid address: {}. Error: {}", input, e))?;
+        let address = Address(H160(bytes));
+
+        let has_lower = hex.bytes().any(|c| c.is_ascii_lowercase());
+        let has_upper = hex.bytes().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper {
+            ensure!(
+                address.checksum_hex()[..] == *hex.as_bytes(),
+                "Invalid address checksum: {}.",
+                input
+            );
+        }
+        Ok(address)
+    }
+}
+
+// The binary format is kept the same as H160.
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let input = alloc::string::String::deserialize(deserializer)?;
+            input.parse().map_err(DeError::custom)
+        } else {
+            H160::deserialize(deserializer).map(Address)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_checksum_address() {
+        let inputs = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ];
+        for input in inputs {
+            let address: Address = input.parse().unwrap();
+            assert_eq!(input, address.to_string());
+            assert_eq!(address, input.to_lowercase().parse().unwrap());
+            assert_eq!(address, input[2..].to_uppercase().parse().unwrap());
+            assert_eq!(address, input[2..].parse().unwrap());
+        }
+
+        assert!("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            .parse::<Address>()
+            .is_err());
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"
+            .parse::<Address>()
+            .is_err());
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"
+            .parse::<Address>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_address_serde() {
+        let address: Address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            .parse()
+            .unwrap();
+        let bin = postcard::to_allocvec(&address).unwrap();
+        assert_eq!(bin, postcard::to_allocvec(&address.0).unwrap());
+        assert_eq!(address, postcard::from_bytes::<Address>(&bin).unwrap());
+    }
+}
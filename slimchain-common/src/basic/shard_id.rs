@@ -1,6 +1,123 @@
-use crate::basic::Address;
+use crate::{
+    basic::Address,
+    error::{anyhow, Error, Result},
+};
+use core::{
+    str::FromStr,
+    sync::atomic::{AtomicU32, AtomicU8, Ordering},
+};
 use serde::{Deserialize, Serialize};
 
+// How the addresses are assigned to the shards. All nodes of a chain must use the same one.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardAssignment {
+    // The low 64 bits of the address modulo the number of shards.
+    #[default]
+    Modulo,
+    // Split the address space into contiguous ranges by the high 64 bits of the address.
+    Prefix,
+    // A hash ring with `virtual_nodes` points per shard.
+    ConsistentHash {
+        virtual_nodes: u32,
+    },
+}
+
+impl ShardAssignment {
+    pub fn shard_of(self, addr: Address, total: u64) -> u64 {
+        if total <= 1 {
+            return 0;
+        }
+        match self {
+            ShardAssignment::Modulo => addr.to_low_u64_be() % total,
+            ShardAssignment::Prefix => {
+                let prefix = u64::from_be_bytes(addr.0[..8].try_into().expect("Invalid address."));
+                ((prefix as u128 * total as u128) >> 64) as u64
+            }
+            ShardAssignment::ConsistentHash { virtual_nodes } => {
+                consistent_hash_shard(addr, total, virtual_nodes.max(1))
+            }
+        }
+    }
+}
+
+impl FromStr for ShardAssignment {
+    type Err = Error;
+
+    // `modulo`, `prefix`, or `consistent_hash:<virtual_nodes>`.
+    fn from_str(input: &str) -> Result<Self> {
+        match input.split_once(':') {
+            None if input == "modulo" => Ok(ShardAssignment::Modulo),
+            None if input == "prefix" => Ok(ShardAssignment::Prefix),
+            Some(("consistent_hash", num)) => {
+                let virtual_nodes = num
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid number of virtual nodes {}.", num))?;
+                Ok(ShardAssignment::ConsistentHash { virtual_nodes })
+            }
+            _ => Err(anyhow!("Unsupported shard assignment {}.", input)),
+        }
+    }
+}
+
+// The bits of the points on the ring should be well spread, even if the inputs are sequential.
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// The shard owning the first virtual node clockwise from the address on the ring. The ring is
+// small enough to be scanned on each lookup, which saves keeping it around.
+fn consistent_hash_shard(addr: Address, total: u64, virtual_nodes: u32) -> u64 {
+    let key = splitmix64(addr.to_low_u64_be());
+    let mut next: Option<(u64, u64)> = None;
+    let mut first: Option<(u64, u64)> = None;
+    for shard in 0..total {
+        for vnode in 0..virtual_nodes {
+            let point = splitmix64((shard << 32) | vnode as u64);
+            if first.is_none_or(|(p, _)| point < p) {
+                first = Some((point, shard));
+            }
+            if point >= key && next.is_none_or(|(p, _)| point < p) {
+                next = Some((point, shard));
+            }
+        }
+    }
+    next.or(first).map_or(0, |(_, shard)| shard)
+}
+
+const MODULO_TAG: u8 = 0;
+const PREFIX_TAG: u8 = 1;
+const CONSISTENT_HASH_TAG: u8 = 2;
+
+static SHARD_ASSIGNMENT: AtomicU8 = AtomicU8::new(MODULO_TAG);
+static VIRTUAL_NODES: AtomicU32 = AtomicU32::new(0);
+
+// It should be set once on start, before any address is assigned.
+pub fn set_shard_assignment(assignment: ShardAssignment) {
+    let tag = match assignment {
+        ShardAssignment::Modulo => MODULO_TAG,
+        ShardAssignment::Prefix => PREFIX_TAG,
+        ShardAssignment::ConsistentHash { virtual_nodes } => {
+            VIRTUAL_NODES.store(virtual_nodes, Ordering::Relaxed);
+            CONSISTENT_HASH_TAG
+        }
+    };
+    SHARD_ASSIGNMENT.store(tag, Ordering::Relaxed);
+}
+
+pub fn shard_assignment() -> ShardAssignment {
+    match SHARD_ASSIGNMENT.load(Ordering::Relaxed) {
+        PREFIX_TAG => ShardAssignment::Prefix,
+        CONSISTENT_HASH_TAG => ShardAssignment::ConsistentHash {
+            virtual_nodes: VIRTUAL_NODES.load(Ordering::Relaxed),
+        },
+        _ => ShardAssignment::Modulo,
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct ShardId {
     pub id: u64,
@@ -19,7 +136,7 @@ impl ShardId {
     }
 
     pub fn contains(&self, addr: Address) -> bool {
-        addr.to_low_u64_be() % self.total == self.id
+        shard_assignment().shard_of(addr, self.total) == self.id
     }
 
     pub fn is_full_shard(&self) -> bool {
@@ -30,9 +147,9 @@ impl ShardId {
         addr: Address,
         total_values: impl Iterator<Item = u64>,
     ) -> impl Iterator<Item = ShardId> {
-        let addr_low_u64 = addr.to_low_u64_be();
+        let assignment = shard_assignment();
         total_values.map(move |total| {
-            let id = addr_low_u64 % total;
+            let id = assignment.shard_of(addr, total);
             Self { id, total }
         })
     }
@@ -49,4 +166,43 @@ mod tests {
         assert!(shard_id.contains(H160::repeat_byte(0xff).into()));
         assert!(!shard_id.contains(H160::repeat_byte(0x00).into()));
     }
+
+    #[test]
+    fn test_shard_assignment() {
+        let addr = |i: u64| -> Address { H160::from_low_u64_be(splitmix64(i)).into() };
+        let addr_high = |i: u8| -> Address { H160::repeat_byte(i).into() };
+
+        assert_eq!(
+            1,
+            ShardAssignment::Modulo.shard_of(H160::from_low_u64_be(7).into(), 3)
+        );
+        assert_eq!(0, ShardAssignment::Prefix.shard_of(addr_high(0x00), 4));
+        assert_eq!(1, ShardAssignment::Prefix.shard_of(addr_high(0x80), 2));
+        assert_eq!(3, ShardAssignment::Prefix.shard_of(addr_high(0xff), 4));
+
+        let ring = ShardAssignment::ConsistentHash { virtual_nodes: 64 };
+        let mut counts = [0usize; 4];
+        for i in 0..4000 {
+            let shard = ring.shard_of(addr(i), 4);
+            assert!(shard < 4);
+            counts[shard as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 500), "{:?}", counts);
+
+        // Adding a shard only moves the addresses to the new shard.
+        for i in 0..1000 {
+            let old = ring.shard_of(addr(i), 4);
+            let new = ring.shard_of(addr(i), 5);
+            assert!(new == old || new == 4);
+        }
+
+        for assignment in [ShardAssignment::Modulo, ShardAssignment::Prefix, ring] {
+            assert_eq!(0, assignment.shard_of(addr(1), 1));
+        }
+
+        assert_eq!(ShardAssignment::Prefix, "prefix".parse().unwrap());
+        assert_eq!(ring, "consistent_hash:64".parse().unwrap());
+        assert!("consistent_hash".parse::<ShardAssignment>().is_err());
+        assert!("range".parse::<ShardAssignment>().is_err());
+    }
 }
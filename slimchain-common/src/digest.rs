@@ -1,4 +1,12 @@
-use crate::basic::{H160, H256};
+use crate::{
+    basic::{H160, H256},
+    error::{bail, Error, Result},
+};
+use core::{
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+use serde::{Deserialize, Serialize};
 
 pub use blake2b_simd::{Hash as Blake2bHash, Params as Blake2bParams};
 
@@ -26,14 +34,139 @@ pub fn default_blake2() -> Blake2bParams {
     blake2(DEFAULT_DIGEST_LEN)
 }
 
+// Keccak-256 as used by Ethereum, which differs from the standardized SHA3-256 in padding.
+pub fn keccak256(input: &[u8]) -> H256 {
+    use tiny_keccak::{Hasher, Keccak};
+
+    let mut hasher = Keccak::v256();
+    hasher.update(input);
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+    H256(output)
+}
+
+// The hash function behind `Digestible`, which determines the tx ids, the state roots, and the
+// block hashes. All nodes of a chain must use the same one.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestAlgorithm {
+    #[default]
+    Blake2b,
+    Keccak256,
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            DigestAlgorithm::Blake2b => 0,
+            DigestAlgorithm::Keccak256 => 1,
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(DigestAlgorithm::Blake2b),
+            1 => Ok(DigestAlgorithm::Keccak256),
+            #[cfg(feature = "blake3")]
+            2 => Ok(DigestAlgorithm::Blake3),
+            _ => bail!("Unsupported digest algorithm {}.", value),
+        }
+    }
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "blake2b" => Ok(DigestAlgorithm::Blake2b),
+            "keccak256" => Ok(DigestAlgorithm::Keccak256),
+            #[cfg(feature = "blake3")]
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            _ => bail!("Unsupported digest algorithm {}.", input),
+        }
+    }
+}
+
+static DIGEST_ALGORITHM: AtomicU8 = AtomicU8::new(0);
+
+// It should be set once on start, before any digest is computed.
+pub fn set_digest_algorithm(algorithm: DigestAlgorithm) {
+    DIGEST_ALGORITHM.store(algorithm.to_u8(), Ordering::Relaxed);
+}
+
+pub fn digest_algorithm() -> DigestAlgorithm {
+    DigestAlgorithm::from_u8(DIGEST_ALGORITHM.load(Ordering::Relaxed))
+        .expect("Invalid digest algorithm.")
+}
+
+// The incremental hasher of the current digest algorithm.
+pub enum DigestState {
+    Blake2b(blake2b_simd::State),
+    Keccak256(tiny_keccak::Keccak),
+    #[cfg(feature = "blake3")]
+    Blake3(alloc::boxed::Box<blake3::Hasher>),
+}
+
+impl DigestState {
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Blake2b => DigestState::Blake2b(default_blake2().to_state()),
+            DigestAlgorithm::Keccak256 => DigestState::Keccak256(tiny_keccak::Keccak::v256()),
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => DigestState::Blake3(alloc::boxed::Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, input: &[u8]) {
+        use tiny_keccak::Hasher as _;
+
+        match self {
+            DigestState::Blake2b(state) => {
+                state.update(input);
+            }
+            DigestState::Keccak256(state) => state.update(input),
+            #[cfg(feature = "blake3")]
+            DigestState::Blake3(state) => {
+                state.update(input);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> H256 {
+        use tiny_keccak::Hasher as _;
+
+        match self {
+            DigestState::Blake2b(state) => blake2b_hash_to_h256(state.finalize()),
+            DigestState::Keccak256(state) => {
+                let mut output = [0u8; 32];
+                state.finalize(&mut output);
+                H256(output)
+            }
+            #[cfg(feature = "blake3")]
+            DigestState::Blake3(state) => H256(*state.finalize().as_bytes()),
+        }
+    }
+}
+
+#[inline]
+pub fn digest_state() -> DigestState {
+    DigestState::new(digest_algorithm())
+}
+
 pub trait Digestible {
     fn to_digest(&self) -> H256;
 }
 
 impl Digestible for [u8] {
     fn to_digest(&self) -> H256 {
-        let hash = default_blake2().hash(self);
-        blake2b_hash_to_h256(hash)
+        let mut hash_state = digest_state();
+        hash_state.update(self);
+        hash_state.finalize()
     }
 }
 
@@ -82,4 +215,48 @@ mod tests {
         assert_eq!("hello".to_digest(), expect);
         assert_eq!("hello".to_string().to_digest(), expect);
     }
+
+    #[test]
+    fn test_digest_state() {
+        let mut hash_state = DigestState::new(DigestAlgorithm::Keccak256);
+        hash_state.update(b"hel");
+        hash_state.update(b"lo");
+        assert_eq!(keccak256(b"hello"), hash_state.finalize());
+
+        let mut hash_state = DigestState::new(DigestAlgorithm::Blake2b);
+        hash_state.update(b"hello");
+        assert_eq!(
+            blake2b_hash_to_h256(default_blake2().hash(b"hello")),
+            hash_state.finalize()
+        );
+
+        assert_eq!(
+            DigestAlgorithm::Keccak256,
+            "keccak256".parse::<DigestAlgorithm>().unwrap()
+        );
+        assert!("md5".parse::<DigestAlgorithm>().is_err());
+        for algorithm in [DigestAlgorithm::Blake2b, DigestAlgorithm::Keccak256] {
+            assert_eq!(
+                algorithm,
+                DigestAlgorithm::from_u8(algorithm.to_u8()).unwrap()
+            );
+        }
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3() {
+        let mut hash_state = DigestState::new(DigestAlgorithm::Blake3);
+        hash_state.update(b"hello");
+        assert_eq!(
+            H256(*blake3::hash(b"hello").as_bytes()),
+            hash_state.finalize()
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let expect = H256(*b"\xc5\xd2\x46\x01\x86\xf7\x23\x3c\x92\x7e\x7d\xb2\xdc\xc7\x03\xc0\xe5\x00\xb6\x53\xca\x82\x27\x3b\x7b\xfa\xd8\x04\x5d\x85\xa4\x70");
+        assert_eq!(keccak256(b""), expect);
+    }
 }
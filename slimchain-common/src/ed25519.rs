@@ -1,7 +1,8 @@
 use crate::{
     basic::H256,
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     error::{Error, Result},
+    hd::{ensure_all_hardened, DerivationPath, ExtendedKey},
 };
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -40,14 +41,41 @@ impl PubSigPair {
 
 impl Digestible for PubSigPair {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(&self.pk.to_bytes()[..]);
         hash_state.update(&self.sig.to_bytes()[..]);
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
+// Verify the signatures together, which is about twice as fast as verifying them one by one.
+// It only tells whether all of them are valid.
+#[cfg(feature = "std")]
+pub fn verify_batch(items: &[(H256, &PubSigPair)]) -> Result<()> {
+    let msgs: alloc::vec::Vec<&[u8]> = items.iter().map(|(hash, _)| hash.as_bytes()).collect();
+    let sigs: alloc::vec::Vec<Signature> = items.iter().map(|(_, pk_sig)| pk_sig.sig).collect();
+    let pks: alloc::vec::Vec<PublicKey> = items.iter().map(|(_, pk_sig)| pk_sig.pk).collect();
+    ed25519_dalek::verify_batch(&msgs, &sigs, &pks).map_err(Error::msg)
+}
+
+const HD_CURVE_SEED: &[u8] = b"ed25519 seed";
+
+// Derive the keypair from the seed following SLIP-10, in which ed25519 only supports the
+// hardened derivation.
+pub fn derive_keypair(seed: &[u8], path: &DerivationPath) -> Result<Keypair> {
+    ensure_all_hardened(path)?;
+    let mut ext = ExtendedKey::master(HD_CURVE_SEED, seed)?;
+    for &index in path.indexes() {
+        ext = ExtendedKey::from_hmac(
+            &ext.chain_code,
+            &[&[0u8][..], &ext.key[..], &index.to_be_bytes()[..]],
+        );
+    }
+    let secret = SecretKey::from_bytes(&ext.key[..]).map_err(Error::msg)?;
+    let public = PublicKey::from(&secret);
+    Ok(Keypair { secret, public })
+}
+
 pub mod keypair_serde_impl {
     use super::*;
 
@@ -179,6 +207,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_verify_batch() {
+        let mut rng = rand::thread_rng();
+        let pk_sigs: alloc::vec::Vec<_> = (0..8u8)
+            .map(|i| {
+                let hash = H256::repeat_byte(i);
+                (hash, PubSigPair::create(&Keypair::generate(&mut rng), hash))
+            })
+            .collect();
+        let mut items: alloc::vec::Vec<_> = pk_sigs
+            .iter()
+            .map(|(hash, pk_sig)| (*hash, pk_sig))
+            .collect();
+        verify_batch(&items).unwrap();
+        verify_batch(&[]).unwrap();
+
+        items[3].0 = H256::repeat_byte(0xff);
+        assert!(verify_batch(&items).is_err());
+    }
+
+    #[test]
+    fn test_derive_keypair() {
+        // The test vector 1 of SLIP-10.
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let keypair = derive_keypair(&seed, &"m".parse().unwrap()).unwrap();
+        assert_eq!(
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+            hex::encode(keypair.secret.as_bytes())
+        );
+        let path = "m/0'/1'/2'/2'/1000000000'".parse().unwrap();
+        let keypair = derive_keypair(&seed, &path).unwrap();
+        assert_eq!(
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+            hex::encode(keypair.secret.as_bytes())
+        );
+
+        assert!(derive_keypair(&seed, &DerivationPath::bip44(0, 0, false)).is_err());
+        derive_keypair(&seed, &DerivationPath::bip44(0, 0, true)).unwrap();
+    }
+
     #[test]
     fn test_sign_and_verify() {
         let mut rng = rand::thread_rng();
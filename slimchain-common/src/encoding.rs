@@ -0,0 +1,725 @@
+// The canonical encoding of the tx requests and the txs. Unlike the serde formats, it does not
+// depend on the struct layout, so that it can be implemented by the external signers and kept
+// stable when the structs evolve. The tx requests and the txs are signed over the digest of their
+// encoding (see `signing_digest`), and the txs are stored in it in the database.
+//
+// The encoding starts with the version byte of the type (see `ENCODING_VERSION`), followed by the
+// fields in the declared order.
+// - Integers are fixed-size and big-endian.
+// - Byte strings are prefixed with their length in u32.
+// - Options are prefixed with 0 (none) or 1 (some).
+// - Enums are prefixed with the variant tag in u8.
+// - Maps and sets are prefixed with their length in u32 and sorted by the keys.
+
+use crate::{
+    basic::{Address, BlockHeight, Code, Nonce, StateKey, StateValue, H160, H256, U256},
+    collections::{HashMap, HashSet},
+    digest::digest_state,
+    ed25519,
+    error::{anyhow, bail, ensure, Error, Result},
+    rw_set::{AccountReadSet, AccountWriteData, ReadAccessFlags, TxReadSet, TxWriteData},
+    secp256k1,
+    signature::SignatureScheme,
+    tx::{RawTx, SignedTx},
+    tx_req::{SignedTxRequest, TxRequest},
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::convert::TryFrom;
+
+pub trait CanonicalEncode {
+    // Bump it whenever the encoding of the type, including the types nested in it, changes. The
+    // other versions are rejected, so that the types whose layout did not change keep accepting
+    // the older encodings.
+    const ENCODING_VERSION: u8 = 1;
+
+    fn encode_to(&self, out: &mut Vec<u8>);
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = alloc::vec![Self::ENCODING_VERSION];
+        self.encode_to(&mut out);
+        out
+    }
+
+    // The digest signed by the signers of the tx requests and the txs. So an external signer only
+    // needs to implement the encoding, instead of the digests of the fields.
+    fn signing_digest(&self) -> H256 {
+        let mut hash_state = digest_state();
+        hash_state.update(&self.to_canonical_bytes());
+        hash_state.finalize()
+    }
+}
+
+pub trait CanonicalDecode: Sized {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self>;
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self>
+    where
+        Self: CanonicalEncode,
+    {
+        let mut reader = Reader::new(bytes);
+        let version = reader.read_u8()?;
+        ensure!(
+            version == Self::ENCODING_VERSION,
+            "Unsupported encoding version {}.",
+            version
+        );
+        let value = Self::decode_from(&mut reader)?;
+        reader.finish()?;
+        Ok(value)
+    }
+}
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(
+            self.buf.len() >= len,
+            "Unexpected end of input (expected: {} bytes, remaining: {} bytes).",
+            len,
+            self.buf.len()
+        );
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.read_bytes(N)?);
+        Ok(out)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => bail!("Invalid bool {}.", v),
+        }
+    }
+
+    // The length of a byte string, a map, or a set.
+    pub fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_u32()? as usize;
+        ensure!(
+            len <= self.buf.len(),
+            "Invalid length {} (remaining: {} bytes).",
+            len,
+            self.buf.len()
+        );
+        Ok(len)
+    }
+
+    pub fn read_var_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_len()?;
+        self.read_bytes(len)
+    }
+
+    pub fn finish(self) -> Result<()> {
+        ensure!(
+            self.buf.is_empty(),
+            "Unexpected {} trailing bytes.",
+            self.buf.len()
+        );
+        Ok(())
+    }
+}
+
+pub fn write_len(out: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("Length exceeds u32.");
+    out.extend_from_slice(&len.to_be_bytes());
+}
+
+pub fn write_var_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_len(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+impl CanonicalEncode for u64 {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl CanonicalDecode for u64 {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        reader.read_u64()
+    }
+}
+
+impl CanonicalEncode for bool {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl CanonicalDecode for bool {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        reader.read_bool()
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for Option<T> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(v) => {
+                out.push(1);
+                v.encode_to(out);
+            }
+            None => out.push(0),
+        }
+    }
+}
+
+impl<T: CanonicalDecode> CanonicalDecode for Option<T> {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(if reader.read_bool()? {
+            Some(T::decode_from(reader)?)
+        } else {
+            None
+        })
+    }
+}
+
+macro_rules! impl_canonical_for_hash {
+    ($t:ty, $inner:ty, $len:expr) => {
+        impl CanonicalEncode for $t {
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(self.as_bytes());
+            }
+        }
+
+        impl CanonicalDecode for $t {
+            fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+                Ok(<$inner>::from(reader.read_array::<$len>()?).into())
+            }
+        }
+    };
+}
+
+impl_canonical_for_hash!(H256, H256, 32);
+impl_canonical_for_hash!(Address, H160, 20);
+impl_canonical_for_hash!(StateKey, H256, 32);
+impl_canonical_for_hash!(StateValue, H256, 32);
+
+impl CanonicalEncode for Nonce {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        let mut bytes = [0u8; 32];
+        self.0.to_big_endian(&mut bytes);
+        out.extend_from_slice(&bytes);
+    }
+}
+
+impl CanonicalDecode for Nonce {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(U256::from_big_endian(&reader.read_array::<32>()?).into())
+    }
+}
+
+impl CanonicalEncode for BlockHeight {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.0.encode_to(out);
+    }
+}
+
+impl CanonicalDecode for BlockHeight {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        reader.read_u64().map(Self)
+    }
+}
+
+impl CanonicalEncode for Code {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        write_var_bytes(out, &self.0);
+    }
+}
+
+impl CanonicalDecode for Code {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(Code(reader.read_var_bytes()?.to_vec()))
+    }
+}
+
+const TX_REQ_CREATE: u8 = 0;
+const TX_REQ_CALL: u8 = 1;
+
+impl CanonicalEncode for TxRequest {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            TxRequest::Create {
+                nonce,
+                code,
+                chain_id,
+                expiry,
+            } => {
+                out.push(TX_REQ_CREATE);
+                chain_id.encode_to(out);
+                expiry.encode_to(out);
+                nonce.encode_to(out);
+                code.encode_to(out);
+            }
+            TxRequest::Call {
+                nonce,
+                address,
+                data,
+                chain_id,
+                expiry,
+            } => {
+                out.push(TX_REQ_CALL);
+                chain_id.encode_to(out);
+                expiry.encode_to(out);
+                nonce.encode_to(out);
+                address.encode_to(out);
+                write_var_bytes(out, data);
+            }
+        }
+    }
+}
+
+impl CanonicalDecode for TxRequest {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let tag = reader.read_u8()?;
+        let chain_id = u64::decode_from(reader)?;
+        let expiry = Option::<BlockHeight>::decode_from(reader)?;
+        let nonce = Nonce::decode_from(reader)?;
+        Ok(match tag {
+            TX_REQ_CREATE => TxRequest::Create {
+                nonce,
+                code: Code::decode_from(reader)?,
+                chain_id,
+                expiry,
+            },
+            TX_REQ_CALL => TxRequest::Call {
+                nonce,
+                address: Address::decode_from(reader)?,
+                data: reader.read_var_bytes()?.to_vec(),
+                chain_id,
+                expiry,
+            },
+            _ => bail!("Invalid tx request type {}.", tag),
+        })
+    }
+}
+
+const SIG_ED25519: u8 = 0;
+const SIG_SECP256K1: u8 = 1;
+
+impl CanonicalEncode for ed25519::PubSigPair {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.pk.as_bytes());
+        out.extend_from_slice(&self.sig.to_bytes());
+    }
+}
+
+impl CanonicalDecode for ed25519::PubSigPair {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let pk = ed25519::PublicKey::from_bytes(reader.read_bytes(32)?).map_err(Error::msg)?;
+        let sig = ed25519::Signature::try_from(reader.read_bytes(64)?).map_err(Error::msg)?;
+        Ok(ed25519::PubSigPair { pk, sig })
+    }
+}
+
+// The ed25519 key takes 32 bytes, and the secp256k1 key takes 33 bytes in the compressed form.
+// Both signatures take 64 bytes.
+impl CanonicalEncode for SignatureScheme {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            SignatureScheme::Ed25519(pk_sig) => {
+                out.push(SIG_ED25519);
+                pk_sig.encode_to(out);
+            }
+            SignatureScheme::Secp256k1(pk_sig) => {
+                out.push(SIG_SECP256K1);
+                out.extend_from_slice(&pk_sig.pk.serialize_compressed());
+                out.extend_from_slice(&pk_sig.sig.serialize());
+            }
+        }
+    }
+}
+
+impl CanonicalDecode for SignatureScheme {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let tag = reader.read_u8()?;
+        Ok(match tag {
+            SIG_ED25519 => SignatureScheme::Ed25519(ed25519::PubSigPair::decode_from(reader)?),
+            SIG_SECP256K1 => {
+                let pk = secp256k1::pk_from_bytes(reader.read_bytes(33)?)?;
+                let sig = secp256k1::sig_from_bytes(reader.read_bytes(64)?)?;
+                SignatureScheme::Secp256k1(secp256k1::PubSigPair { pk, sig })
+            }
+            _ => bail!("Invalid signature scheme {}.", tag),
+        })
+    }
+}
+
+impl CanonicalEncode for SignedTxRequest {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.input.encode_to(out);
+        self.pk_sig.encode_to(out);
+    }
+}
+
+impl CanonicalDecode for SignedTxRequest {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(SignedTxRequest {
+            input: TxRequest::decode_from(reader)?,
+            pk_sig: SignatureScheme::decode_from(reader)?,
+        })
+    }
+}
+
+impl CanonicalEncode for AccountReadSet {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(self.access_flags.bits());
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_unstable();
+        write_len(out, values.len());
+        for v in values {
+            v.encode_to(out);
+        }
+    }
+}
+
+impl CanonicalDecode for AccountReadSet {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let flags = reader.read_u8()?;
+        let access_flags = ReadAccessFlags::from_bits(flags)
+            .ok_or_else(|| anyhow!("Invalid read access flags {:#b}.", flags))?;
+        let len = reader.read_len()?;
+        let mut values = HashSet::with_capacity(len);
+        for _ in 0..len {
+            values.insert(StateKey::decode_from(reader)?);
+        }
+        ensure!(values.len() == len, "Duplicated keys in the read set.");
+        Ok(AccountReadSet {
+            access_flags,
+            values,
+        })
+    }
+}
+
+impl CanonicalEncode for TxReadSet {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        let mut accounts: Vec<_> = self.0.iter().collect();
+        accounts.sort_unstable_by_key(|(address, _)| *address);
+        write_len(out, accounts.len());
+        for (address, acc_reads) in accounts {
+            address.encode_to(out);
+            acc_reads.encode_to(out);
+        }
+    }
+}
+
+impl CanonicalDecode for TxReadSet {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let len = reader.read_len()?;
+        let mut accounts = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let address = Address::decode_from(reader)?;
+            accounts.insert(address, AccountReadSet::decode_from(reader)?);
+        }
+        ensure!(
+            accounts.len() == len,
+            "Duplicated accounts in the read set."
+        );
+        Ok(TxReadSet(accounts))
+    }
+}
+
+impl CanonicalEncode for AccountWriteData {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.nonce.encode_to(out);
+        self.code.encode_to(out);
+        self.reset_values.encode_to(out);
+        write_len(out, self.values.len());
+        for (k, v) in self.values.iter() {
+            k.encode_to(out);
+            v.encode_to(out);
+        }
+    }
+}
+
+impl CanonicalDecode for AccountWriteData {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let nonce = Option::<Nonce>::decode_from(reader)?;
+        let code = Option::<Code>::decode_from(reader)?;
+        let reset_values = reader.read_bool()?;
+        let len = reader.read_len()?;
+        let mut values = BTreeMap::new();
+        for _ in 0..len {
+            let key = StateKey::decode_from(reader)?;
+            values.insert(key, StateValue::decode_from(reader)?);
+        }
+        ensure!(values.len() == len, "Duplicated keys in the write set.");
+        Ok(AccountWriteData {
+            nonce,
+            code,
+            values,
+            reset_values,
+        })
+    }
+}
+
+impl CanonicalEncode for TxWriteData {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        write_len(out, self.0.len());
+        for (address, acc_writes) in self.0.iter() {
+            address.encode_to(out);
+            acc_writes.encode_to(out);
+        }
+    }
+}
+
+impl CanonicalDecode for TxWriteData {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let len = reader.read_len()?;
+        let mut accounts = BTreeMap::new();
+        for _ in 0..len {
+            let address = Address::decode_from(reader)?;
+            accounts.insert(address, AccountWriteData::decode_from(reader)?);
+        }
+        ensure!(
+            accounts.len() == len,
+            "Duplicated accounts in the write set."
+        );
+        Ok(TxWriteData(accounts))
+    }
+}
+
+impl CanonicalEncode for RawTx {
+    // 2: the block timestamp is added.
+    const ENCODING_VERSION: u8 = 2;
+
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.caller.encode_to(out);
+        self.input.encode_to(out);
+        self.block_height.encode_to(out);
+        self.block_timestamp.encode_to(out);
+        self.state_root.encode_to(out);
+        self.reads.encode_to(out);
+        self.writes.encode_to(out);
+    }
+}
+
+impl CanonicalDecode for RawTx {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(RawTx {
+            caller: Address::decode_from(reader)?,
+            input: TxRequest::decode_from(reader)?,
+            block_height: BlockHeight::decode_from(reader)?,
+            block_timestamp: u64::decode_from(reader)?,
+            state_root: H256::decode_from(reader)?,
+            reads: TxReadSet::decode_from(reader)?,
+            writes: TxWriteData::decode_from(reader)?,
+        })
+    }
+}
+
+impl CanonicalEncode for SignedTx {
+    const ENCODING_VERSION: u8 = RawTx::ENCODING_VERSION;
+
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.raw_tx.encode_to(out);
+        self.pk_sig.encode_to(out);
+    }
+}
+
+impl CanonicalDecode for SignedTx {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(SignedTx {
+            raw_tx: RawTx::decode_from(reader)?,
+            pk_sig: SignatureScheme::decode_from(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_tx_req() -> TxRequest {
+        TxRequest::Call {
+            nonce: 1.into(),
+            address: H160::repeat_byte(0xf).into(),
+            data: b"data".to_vec(),
+            chain_id: 1,
+            expiry: Some(BlockHeight(10)),
+        }
+    }
+
+    fn test_keypair() -> ed25519::Keypair {
+        let secret = ed25519::SecretKey::from_bytes(&[0x11; 32]).unwrap();
+        let public = ed25519::PublicKey::from(&secret);
+        ed25519::Keypair { secret, public }
+    }
+
+    #[test]
+    fn test_tx_req_golden() {
+        let tx_req = test_tx_req();
+        let bytes = tx_req.to_canonical_bytes();
+        assert_eq!(
+            concat!(
+                "01",                                                               // version
+                "01",                                                               // call
+                "0000000000000001",                                                 // chain id
+                "01",                                                               // has expiry
+                "000000000000000a",                                                 // expiry
+                "0000000000000000000000000000000000000000000000000000000000000001", // nonce
+                "0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f",                         // address
+                "00000004",                                                         // data len
+                "64617461",                                                         // data
+            ),
+            hex::encode(&bytes)
+        );
+        assert_eq!(tx_req, TxRequest::from_canonical_bytes(&bytes).unwrap());
+
+        let create = TxRequest::Create {
+            nonce: 0.into(),
+            code: Code(alloc::vec![0x60, 0x80]),
+            chain_id: 0,
+            expiry: None,
+        };
+        let bytes = create.to_canonical_bytes();
+        assert_eq!(
+            concat!(
+                "01",
+                "00",
+                "0000000000000000",
+                "00",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "00000002",
+                "6080",
+            ),
+            hex::encode(&bytes)
+        );
+        assert_eq!(create, TxRequest::from_canonical_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_signed_tx_req_golden() {
+        let signed_tx_req = test_tx_req().sign(&test_keypair());
+        let bytes = signed_tx_req.to_canonical_bytes();
+        // ed25519 signatures are deterministic.
+        assert_eq!(
+            concat!(
+                "01010000000000000001",
+                "01000000000000000a",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f",
+                "0000000464617461",
+                "00", // ed25519
+                "d04ab232742bb4ab3a1368bd4615e4e6d0224ab71a016baf8520a332c9778737",
+                "adc38dce8d567f1170a1831506e2961fe0e8f31338ba13f39bb7058319554652",
+                "3d88e6abbe7f43468194e837b45151f7d3a1ec37bf4bd5e3d73d4685e2dcb604",
+            ),
+            hex::encode(&bytes)
+        );
+        assert_eq!(
+            signed_tx_req,
+            SignedTxRequest::from_canonical_bytes(&bytes).unwrap()
+        );
+        SignedTxRequest::from_canonical_bytes(&bytes)
+            .unwrap()
+            .verify()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_raw_tx_golden() {
+        let mut reads = TxReadSet::default();
+        let mut acc_reads = AccountReadSet::default();
+        acc_reads.set_nonce(true);
+        acc_reads.values.insert(H256::repeat_byte(2).into());
+        acc_reads.values.insert(H256::repeat_byte(1).into());
+        reads.insert(H160::repeat_byte(0xa).into(), acc_reads);
+
+        let mut writes = TxWriteData::default();
+        writes.add_nonce(H160::repeat_byte(0xb).into(), 2.into());
+        writes.add_value(
+            H160::repeat_byte(0xb).into(),
+            H256::repeat_byte(3).into(),
+            H256::repeat_byte(4).into(),
+        );
+
+        let raw_tx = RawTx {
+            caller: H160::repeat_byte(0xc).into(),
+            input: test_tx_req(),
+            block_height: BlockHeight(5),
+            block_timestamp: 6,
+            state_root: H256::repeat_byte(0xd),
+            reads,
+            writes,
+        };
+        let bytes = raw_tx.to_canonical_bytes();
+        let mut expect = alloc::vec![2];
+        expect.extend_from_slice(&[0xc; 20]);
+        expect.extend_from_slice(&test_tx_req().to_canonical_bytes()[1..]);
+        expect.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 5]);
+        expect.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 6]);
+        expect.extend_from_slice(&[0xd; 32]);
+        // reads, sorted by the keys
+        expect.extend_from_slice(&[0, 0, 0, 1]);
+        expect.extend_from_slice(&[0xa; 20]);
+        expect.push(ReadAccessFlags::NONCE.bits());
+        expect.extend_from_slice(&[0, 0, 0, 2]);
+        expect.extend_from_slice(&[1; 32]);
+        expect.extend_from_slice(&[2; 32]);
+        // writes
+        expect.extend_from_slice(&[0, 0, 0, 1]);
+        expect.extend_from_slice(&[0xb; 20]);
+        expect.push(1);
+        expect.extend_from_slice(&[0; 31]);
+        expect.push(2);
+        expect.push(0);
+        expect.push(0);
+        expect.extend_from_slice(&[0, 0, 0, 1]);
+        expect.extend_from_slice(&[3; 32]);
+        expect.extend_from_slice(&[4; 32]);
+        assert_eq!(expect, bytes);
+        assert_eq!(raw_tx, RawTx::from_canonical_bytes(&bytes).unwrap());
+        let mut v1 = bytes.clone();
+        v1[0] = 1;
+        assert!(RawTx::from_canonical_bytes(&v1).is_err());
+
+        let signed_tx = raw_tx.sign(&test_keypair());
+        let bytes = signed_tx.to_canonical_bytes();
+        assert_eq!(signed_tx, SignedTx::from_canonical_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let bytes = test_tx_req().to_canonical_bytes();
+
+        let mut bad = bytes.clone();
+        bad[0] = 2;
+        assert!(TxRequest::from_canonical_bytes(&bad).is_err());
+
+        let mut bad = bytes.clone();
+        bad[1] = 2;
+        assert!(TxRequest::from_canonical_bytes(&bad).is_err());
+
+        let mut bad = bytes.clone();
+        bad.push(0);
+        assert!(TxRequest::from_canonical_bytes(&bad).is_err());
+
+        assert!(TxRequest::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+// The typed errors raised at the crate boundaries. The public APIs return them as they are. Once
+// wrapped in `anyhow::Error`, they can still be told apart by `error_code`, also after being sent
+// over RPC.
+
+pub use anyhow::{anyhow, bail, ensure, Context, Error, Result};
+
+use crate::basic::BlockHeight;
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Internal,
+    InvalidSignature,
+    InvalidChainId,
+    TxExpired,
+    ExecutionFailed,
+    Conflict,
+    Outdated,
+    NotFound,
+    InvalidProof,
+    InvalidBlock,
+    NotLeader,
+    RateLimited,
+    Overloaded,
+    ShuttingDown,
+    InvalidRequest,
+    Unauthorized,
+}
+
+impl ErrorCode {
+    // The numeric code, grouped by the category: 1xx for the execution, 2xx for the state, 3xx
+    // for the consensus, and 4xx for the network.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            ErrorCode::Internal => 1,
+            ErrorCode::InvalidSignature => 100,
+            ErrorCode::InvalidChainId => 101,
+            ErrorCode::TxExpired => 102,
+            ErrorCode::ExecutionFailed => 103,
+            ErrorCode::Conflict => 200,
+            ErrorCode::Outdated => 201,
+            ErrorCode::NotFound => 202,
+            ErrorCode::InvalidProof => 203,
+            ErrorCode::InvalidBlock => 300,
+            ErrorCode::NotLeader => 301,
+            ErrorCode::RateLimited => 400,
+            ErrorCode::Overloaded => 401,
+            ErrorCode::ShuttingDown => 402,
+            ErrorCode::InvalidRequest => 403,
+            ErrorCode::Unauthorized => 404,
+        }
+    }
+
+    // Whether the same request may succeed if it is sent again later.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::NotLeader
+                | ErrorCode::RateLimited
+                | ErrorCode::Overloaded
+                | ErrorCode::ShuttingDown
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, derive_more::Display)]
+pub enum ExecutionError {
+    #[display(fmt = "Invalid signature. Error: {}", _0)]
+    InvalidSignature(String),
+    #[display(fmt = "Invalid chain id (expected: {}, actual: {}).", expected, actual)]
+    InvalidChainId { expected: u64, actual: u64 },
+    #[display(
+        fmt = "Tx request expired (expiry: {}, block height: {}).",
+        expiry,
+        block_height
+    )]
+    Expired {
+        expiry: BlockHeight,
+        block_height: BlockHeight,
+    },
+    #[display(fmt = "Failed to execute the tx. Error: {}", _0)]
+    Failed(String),
+}
+
+impl ExecutionError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ExecutionError::InvalidSignature(_) => ErrorCode::InvalidSignature,
+            ExecutionError::InvalidChainId { .. } => ErrorCode::InvalidChainId,
+            ExecutionError::Expired { .. } => ErrorCode::TxExpired,
+            ExecutionError::Failed(_) => ErrorCode::ExecutionFailed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, derive_more::Display)]
+pub enum StateError {
+    #[display(fmt = "Tx with conflict.")]
+    Conflict,
+    #[display(fmt = "Outdated tx (tx height: {}).", _0)]
+    Outdated(BlockHeight),
+    #[display(fmt = "Not found: {}.", _0)]
+    NotFound(String),
+    #[display(fmt = "Invalid proof. Error: {}", _0)]
+    InvalidProof(String),
+}
+
+impl StateError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            StateError::Conflict => ErrorCode::Conflict,
+            StateError::Outdated(_) => ErrorCode::Outdated,
+            StateError::NotFound(_) => ErrorCode::NotFound,
+            StateError::InvalidProof(_) => ErrorCode::InvalidProof,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, derive_more::Display)]
+pub enum ConsensusError {
+    #[display(fmt = "Invalid block. Error: {}", _0)]
+    InvalidBlock(String),
+    #[display(fmt = "Not the leader.")]
+    NotLeader,
+}
+
+impl ConsensusError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ConsensusError::InvalidBlock(_) => ErrorCode::InvalidBlock,
+            ConsensusError::NotLeader => ErrorCode::NotLeader,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, derive_more::Display)]
+pub enum NetworkError {
+    #[display(fmt = "Rate limit exceeded. Error: {}", _0)]
+    RateLimited(String),
+    #[display(fmt = "Too many pending txs. Queue depth: {}.", _0)]
+    Overloaded(usize),
+    #[display(fmt = "The node is shutting down.")]
+    ShuttingDown,
+    #[display(fmt = "Invalid request. Error: {}", _0)]
+    InvalidRequest(String),
+    #[display(fmt = "Missing or invalid admin token.")]
+    Unauthorized,
+    // The error replied by the remote node.
+    #[display(fmt = "{} (code: {}).", msg, "code.as_u16()")]
+    Remote { code: ErrorCode, msg: String },
+}
+
+impl NetworkError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            NetworkError::RateLimited(_) => ErrorCode::RateLimited,
+            NetworkError::Overloaded(_) => ErrorCode::Overloaded,
+            NetworkError::ShuttingDown => ErrorCode::ShuttingDown,
+            NetworkError::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            NetworkError::Unauthorized => ErrorCode::Unauthorized,
+            NetworkError::Remote { code, .. } => *code,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExecutionError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConsensusError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NetworkError {}
+
+// The code of the typed error carried by `err`, either directly or under the contexts. The
+// untyped ones are internal failures.
+pub fn error_code(err: &Error) -> ErrorCode {
+    if let Some(e) = err.downcast_ref::<ExecutionError>() {
+        e.code()
+    } else if let Some(e) = err.downcast_ref::<StateError>() {
+        e.code()
+    } else if let Some(e) = err.downcast_ref::<ConsensusError>() {
+        e.code()
+    } else if let Some(e) = err.downcast_ref::<NetworkError>() {
+        e.code()
+    } else {
+        ErrorCode::Internal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_error_code() {
+        let err = anyhow!(ExecutionError::InvalidChainId {
+            expected: 1,
+            actual: 2,
+        });
+        assert_eq!(ErrorCode::InvalidChainId, error_code(&err));
+        assert_eq!(
+            "Invalid chain id (expected: 1, actual: 2).",
+            err.to_string()
+        );
+
+        let err = Error::msg(StateError::Conflict).context("Failed to propose the block.");
+        assert_eq!(ErrorCode::Conflict, error_code(&err));
+
+        let err = Error::msg(NetworkError::Remote {
+            code: ErrorCode::RateLimited,
+            msg: "Global rate limit exceeded.".to_string(),
+        });
+        assert_eq!(ErrorCode::RateLimited, error_code(&err));
+        assert!(error_code(&err).is_retryable());
+
+        let err = anyhow!("Failed to open the db.");
+        assert_eq!(ErrorCode::Internal, error_code(&err));
+        assert!(!error_code(&err).is_retryable());
+    }
+}
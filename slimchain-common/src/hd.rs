@@ -0,0 +1,174 @@
+use crate::error::{anyhow, bail, ensure, Error, Result};
+use alloc::vec::Vec;
+use core::{fmt, str::FromStr};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+// The coin type of Ethereum, so that the secp256k1 accounts match those of the Ethereum wallets.
+pub const BIP44_COIN_TYPE: u32 = 60;
+
+// The key derivation path, e.g., `m/44'/60'/0'/0/1`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    // `m/44'/60'/{account}'/0/{index}`, where the last two levels are hardened if required.
+    pub fn bip44(account: u32, index: u32, hardened: bool) -> Self {
+        let soft = |i: u32| if hardened { i | HARDENED_OFFSET } else { i };
+        Self(alloc::vec![
+            44 | HARDENED_OFFSET,
+            BIP44_COIN_TYPE | HARDENED_OFFSET,
+            account | HARDENED_OFFSET,
+            soft(0),
+            soft(index),
+        ])
+    }
+
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut parts = input.split('/');
+        ensure!(
+            parts.next() == Some("m"),
+            "Invalid derivation path {}. It should start with m.",
+            input
+        );
+        let indexes = parts
+            .map(|part| {
+                let (num, hardened) = match part.strip_suffix(&['\'', 'h'][..]) {
+                    Some(num) => (num, true),
+                    None => (part, false),
+                };
+                let index: u32 = num
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid derivation index {}.", part))?;
+                ensure!(
+                    index < HARDENED_OFFSET,
+                    "Derivation index {} is too large.",
+                    part
+                );
+                Ok(if hardened {
+                    index | HARDENED_OFFSET
+                } else {
+                    index
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self(indexes))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for &index in &self.0 {
+            if index >= HARDENED_OFFSET {
+                write!(f, "/{}'", index - HARDENED_OFFSET)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The private key and the chain code of a node in the key tree.
+pub(crate) struct ExtendedKey {
+    pub(crate) key: [u8; 32],
+    pub(crate) chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    // `curve_seed` is the HMAC key defined by BIP-32 or SLIP-10 for the curve.
+    pub(crate) fn master(curve_seed: &[u8], seed: &[u8]) -> Result<Self> {
+        ensure!(
+            (16..=64).contains(&seed.len()),
+            "Invalid seed length {}.",
+            seed.len()
+        );
+        Ok(Self::from_hmac(curve_seed, &[seed]))
+    }
+
+    // Compute HMAC-SHA512 and split it into the key and the chain code.
+    pub(crate) fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC takes the key of any size.");
+        for d in data {
+            mac.update(d);
+        }
+        let output = mac.finalize().into_bytes();
+        let mut ext = Self {
+            key: [0u8; 32],
+            chain_code: [0u8; 32],
+        };
+        ext.key.copy_from_slice(&output[..32]);
+        ext.chain_code.copy_from_slice(&output[32..]);
+        ext
+    }
+}
+
+// The 64-byte seed from the BIP-39 mnemonic and the optional passphrase.
+#[cfg(feature = "std")]
+pub fn seed_from_mnemonic(phrase: &str, passphrase: &str) -> Result<Vec<u8>> {
+    use bip39::{Language, Mnemonic, Seed};
+
+    let mnemonic = Mnemonic::from_phrase(phrase.trim(), Language::English)
+        .map_err(|e| anyhow!("Invalid mnemonic. Error: {}", e))?;
+    Ok(Seed::new(&mnemonic, passphrase).as_bytes().to_vec())
+}
+
+pub(crate) fn ensure_all_hardened(path: &DerivationPath) -> Result<()> {
+    if let Some(index) = path.indexes().iter().find(|&&i| i < HARDENED_OFFSET) {
+        bail!(
+            "Only the hardened derivation is supported. Invalid index {} in {}.",
+            index,
+            path
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/44'/60'/0'/0/1".parse().unwrap();
+        assert_eq!(DerivationPath::bip44(0, 1, false), path);
+        assert_eq!("m/44'/60'/0'/0/1", path.to_string());
+        assert!(ensure_all_hardened(&path).is_err());
+
+        let path: DerivationPath = "m/44h/60h/0h/0h/1h".parse().unwrap();
+        assert_eq!(DerivationPath::bip44(0, 1, true), path);
+        ensure_all_hardened(&path).unwrap();
+
+        assert_eq!(
+            DerivationPath::default(),
+            "m".parse::<DerivationPath>().unwrap()
+        );
+        assert!("44'/60'".parse::<DerivationPath>().is_err());
+        assert!("m/x".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648".parse::<DerivationPath>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_seed_from_mnemonic() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = seed_from_mnemonic(phrase, "TREZOR").unwrap();
+        assert_eq!(
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            hex::encode(&seed)
+        );
+        assert!(seed_from_mnemonic("abandon abandon", "").is_err());
+    }
+}
@@ -4,13 +4,16 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
-pub use anyhow as error;
-
 pub mod basic;
 pub mod collections;
 pub mod digest;
 pub mod ed25519;
+pub mod encoding;
+pub mod error;
+pub mod hd;
 pub mod rw_set;
+pub mod secp256k1;
+pub mod signature;
 pub mod tx;
 pub mod tx_req;
 pub mod utils;
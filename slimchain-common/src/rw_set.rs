@@ -1,7 +1,7 @@
 use crate::{
     basic::{Address, Code, Nonce, StateKey, StateValue, H256},
     collections::{hash_map, HashMap, HashSet},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
 };
 use alloc::collections::{btree_map, BTreeMap};
 use alloc::vec::Vec;
@@ -84,15 +84,14 @@ pub struct TxReadSet(pub HashMap<Address, AccountReadSet>);
 
 impl Digestible for TxReadSet {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         let mut sorted: Vec<_> = self.0.iter().collect();
         sorted.sort_unstable_by_key(|input| input.0);
         for (k, v) in &sorted {
             hash_state.update(k.as_bytes());
             hash_state.update(v.to_digest().as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -104,7 +103,7 @@ pub struct AccountReadSet {
 
 impl Digestible for AccountReadSet {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         if self.get_nonce() {
             hash_state.update(b"\x01");
         } else {
@@ -120,8 +119,7 @@ impl Digestible for AccountReadSet {
         for v in &values_sorted {
             hash_state.update(v.as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -203,17 +201,12 @@ impl TxReadData {
         *acc_data.nonce.get_or_insert_with(f)
     }
 
-    pub fn get_code(&self, address: Address) -> Option<&Code> {
-        self.0.get(&address).and_then(|acc| acc.code.as_ref())
+    pub fn get_code_hash(&self, address: Address) -> Option<H256> {
+        self.0.get(&address).and_then(|acc| acc.code_hash)
     }
 
-    pub fn add_code(&mut self, address: Address, code: Code) {
-        self.0.entry(address).or_default().code = Some(code);
-    }
-
-    pub fn get_or_add_code(&mut self, address: Address, f: impl FnOnce() -> Code) -> &Code {
-        let acc_data = self.0.entry(address).or_default();
-        acc_data.code.get_or_insert_with(f)
+    pub fn add_code_hash(&mut self, address: Address, code_hash: H256) {
+        self.0.entry(address).or_default().code_hash = Some(code_hash);
     }
 
     pub fn get_value(&self, address: Address, key: StateKey) -> Option<StateValue> {
@@ -241,7 +234,8 @@ impl TxReadData {
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct AccountReadData {
     pub nonce: Option<Nonce>,
-    pub code: Option<Code>,
+    // Only the hash of the code read is recorded, which is what the read proof is against.
+    pub code_hash: Option<H256>,
     pub values: HashMap<StateKey, StateValue>,
 }
 
@@ -249,7 +243,7 @@ impl AccountReadData {
     pub fn to_set(&self) -> AccountReadSet {
         let mut access_flags = ReadAccessFlags::empty();
         access_flags.set_nonce(self.nonce.is_some());
-        access_flags.set_code(self.code.is_some());
+        access_flags.set_code(self.code_hash.is_some());
         AccountReadSet {
             access_flags,
             values: self.values.keys().copied().collect(),
@@ -257,7 +251,7 @@ impl AccountReadData {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.nonce.is_none() && self.code.is_none() && self.values.is_empty()
+        self.nonce.is_none() && self.code_hash.is_none() && self.values.is_empty()
     }
 }
 
@@ -276,13 +270,12 @@ pub struct TxWriteData(pub BTreeMap<Address, AccountWriteData>);
 
 impl Digestible for TxWriteData {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         for (k, v) in self.0.iter() {
             hash_state.update(k.as_bytes());
             hash_state.update(v.to_digest().as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -345,7 +338,7 @@ pub struct AccountWriteData {
 
 impl Digestible for AccountWriteData {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         match &self.nonce {
             Some(n) => {
                 hash_state.update(b"\x01");
@@ -373,8 +366,7 @@ impl Digestible for AccountWriteData {
             hash_state.update(k.as_bytes());
             hash_state.update(v.as_bytes());
         }
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -420,16 +412,13 @@ mod tests {
     #[test]
     fn test_read_data() {
         let mut read = TxReadData::default();
-        read.add_code(
+        read.add_code_hash(
             crate::create_address!("0000000000000000000000000000000000000000"),
-            b"code".to_vec().into(),
+            Code::from(b"code".to_vec()).to_digest(),
         );
-        assert_eq!(
-            read.get_or_add_code(
-                crate::create_address!("0000000000000000000000000000000000000002"),
-                || b"code2".to_vec().into()
-            ),
-            &Code::from(b"code2".to_vec()),
+        read.add_code_hash(
+            crate::create_address!("0000000000000000000000000000000000000002"),
+            Code::from(b"code2".to_vec()).to_digest(),
         );
         read.add_nonce(
             crate::create_address!("0000000000000000000000000000000000000000"),
@@ -496,13 +485,13 @@ mod tests {
             Some(1.into()),
         );
         assert_eq!(
-            read.get_code(crate::create_address!(
+            read.get_code_hash(crate::create_address!(
                 "0000000000000000000000000000000000000000"
             )),
-            Some(&Code::from(b"code".to_vec())),
+            Some(Code::from(b"code".to_vec()).to_digest()),
         );
         assert_eq!(
-            read.get_code(crate::create_address!(
+            read.get_code_hash(crate::create_address!(
                 "0000000000000000000000000000000000000001"
             )),
             None,
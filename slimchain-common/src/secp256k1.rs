@@ -0,0 +1,335 @@
+use crate::{
+    basic::H256,
+    digest::{digest_state, Digestible},
+    error::{anyhow, Result},
+    hd::{DerivationPath, ExtendedKey, HARDENED_OFFSET},
+};
+use rand_core::{CryptoRng, RngCore};
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+
+pub use libsecp256k1;
+pub use libsecp256k1::{Message, PublicKey, SecretKey, Signature};
+
+pub const PUBLIC_KEY_LENGTH: usize = 33;
+pub const SECRET_KEY_LENGTH: usize = 32;
+pub const SIGNATURE_LENGTH: usize = 64;
+
+#[derive(Clone)]
+pub struct Keypair {
+    pub secret: SecretKey,
+    pub public: PublicKey,
+}
+
+impl Keypair {
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        loop {
+            let mut bytes = [0u8; SECRET_KEY_LENGTH];
+            rng.fill_bytes(&mut bytes);
+            // Retry in the negligible case that the bytes are out of the curve order.
+            if let Ok(keypair) = Self::from_secret_bytes(&bytes) {
+                return keypair;
+            }
+        }
+    }
+
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self> {
+        let secret = SecretKey::parse_slice(bytes)
+            .map_err(|e| anyhow!("Invalid secp256k1 secret key. Error: {:?}", e))?;
+        let public = PublicKey::from_secret_key(&secret);
+        Ok(Self { secret, public })
+    }
+
+    pub fn sign(&self, msg_hash: H256) -> Signature {
+        let msg = Message::parse(msg_hash.as_fixed_bytes());
+        libsecp256k1::sign(&msg, &self.secret).0
+    }
+}
+
+impl core::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Keypair")
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PubSigPair {
+    #[serde(with = "crate::secp256k1::pk_serde_impl")]
+    pub pk: PublicKey,
+    #[serde(with = "crate::secp256k1::sig_serde_impl")]
+    pub sig: Signature,
+}
+
+impl PubSigPair {
+    pub fn create(keypair: &Keypair, msg_hash: H256) -> Self {
+        Self {
+            pk: keypair.public,
+            sig: keypair.sign(msg_hash),
+        }
+    }
+
+    // The signature in the high-s form is rejected, same as Ethereum does since EIP-2.
+    pub fn verify(&self, msg_hash: H256) -> Result<()> {
+        let msg = Message::parse(msg_hash.as_fixed_bytes());
+        if libsecp256k1::verify(&msg, &self.sig, &self.pk) {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid secp256k1 signature."))
+        }
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.sig
+    }
+}
+
+impl Digestible for PubSigPair {
+    fn to_digest(&self) -> H256 {
+        let mut hash_state = digest_state();
+        hash_state.update(&self.pk.serialize_compressed()[..]);
+        hash_state.update(&self.sig.serialize()[..]);
+        hash_state.finalize()
+    }
+}
+
+pub fn pk_from_bytes(bytes: &[u8]) -> Result<PublicKey> {
+    PublicKey::parse_slice(bytes, None)
+        .map_err(|e| anyhow!("Invalid secp256k1 public key. Error: {:?}", e))
+}
+
+pub fn sig_from_bytes(bytes: &[u8]) -> Result<Signature> {
+    // Drop the recovery id of the 65-byte signature produced by the Ethereum wallets.
+    let bytes = match bytes.len() {
+        65 => &bytes[..SIGNATURE_LENGTH],
+        _ => bytes,
+    };
+    Signature::parse_standard_slice(bytes)
+        .map_err(|e| anyhow!("Invalid secp256k1 signature. Error: {:?}", e))
+}
+
+const HD_CURVE_SEED: &[u8] = b"Bitcoin seed";
+
+fn parse_hd_key(key: &[u8; SECRET_KEY_LENGTH]) -> Result<SecretKey> {
+    // It fails with the probability lower than 1 in 2^127.
+    SecretKey::parse(key).map_err(|e| anyhow!("Invalid derived key. Error: {:?}", e))
+}
+
+// Derive the keypair from the seed following BIP-32, same as the Ethereum wallets.
+pub fn derive_keypair(seed: &[u8], path: &DerivationPath) -> Result<Keypair> {
+    let master = ExtendedKey::master(HD_CURVE_SEED, seed)?;
+    let mut secret = parse_hd_key(&master.key)?;
+    let mut chain_code = master.chain_code;
+    for &index in path.indexes() {
+        let index_bytes = index.to_be_bytes();
+        let ext = if index >= HARDENED_OFFSET {
+            ExtendedKey::from_hmac(
+                &chain_code,
+                &[&[0u8][..], &secret.serialize()[..], &index_bytes[..]],
+            )
+        } else {
+            let public = PublicKey::from_secret_key(&secret);
+            ExtendedKey::from_hmac(
+                &chain_code,
+                &[&public.serialize_compressed()[..], &index_bytes[..]],
+            )
+        };
+        let mut child = parse_hd_key(&ext.key)?;
+        child
+            .tweak_add_assign(&secret)
+            .map_err(|e| anyhow!("Invalid derived key. Error: {:?}", e))?;
+        secret = child;
+        chain_code = ext.chain_code;
+    }
+    let public = PublicKey::from_secret_key(&secret);
+    Ok(Keypair { secret, public })
+}
+
+pub mod pk_serde_impl {
+    use super::*;
+
+    // In the compressed form, i.e., the parity prefix followed by the x coordinate. It is split
+    // into a tuple, since serde only implements the arrays up to 32 elements.
+    pub fn serialize<S>(value: &PublicKey, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bytes: [u8; PUBLIC_KEY_LENGTH] = value.serialize_compressed();
+        let mut x = [0u8; 32];
+        x.copy_from_slice(&bytes[1..]);
+        (bytes[0], x).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<PublicKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (prefix, x) = <(u8, [u8; 32])>::deserialize(deserializer)?;
+        let mut bytes = [0u8; PUBLIC_KEY_LENGTH];
+        bytes[0] = prefix;
+        bytes[1..].copy_from_slice(&x);
+        PublicKey::parse_compressed(&bytes).map_err(|e| DeError::custom(format_args!("{:?}", e)))
+    }
+}
+
+pub mod sk_serde_impl {
+    use super::*;
+
+    pub fn serialize<S>(value: &SecretKey, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bytes: [u8; SECRET_KEY_LENGTH] = value.serialize();
+        bytes.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<SecretKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; SECRET_KEY_LENGTH]>::deserialize(deserializer)?;
+        SecretKey::parse(&bytes).map_err(|e| DeError::custom(format_args!("{:?}", e)))
+    }
+}
+
+pub mod sig_serde_impl {
+    use super::*;
+
+    // The 64-byte `r || s`, split into two halves as well.
+    pub fn serialize<S>(value: &Signature, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bytes: [u8; SIGNATURE_LENGTH] = value.serialize();
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..]);
+        (r, s).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<Signature, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (r, s) = <([u8; 32], [u8; 32])>::deserialize(deserializer)?;
+        let mut bytes = [0u8; SIGNATURE_LENGTH];
+        bytes[..32].copy_from_slice(&r);
+        bytes[32..].copy_from_slice(&s);
+        Signature::parse_standard(&bytes).map_err(|e| DeError::custom(format_args!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_pk() {
+        #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+        struct PubKey(#[serde(with = "crate::secp256k1::pk_serde_impl")] PublicKey);
+
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let pk = PubKey(keypair.public);
+        let bin = postcard::to_allocvec(&pk).unwrap();
+        assert_eq!(postcard::from_bytes::<PubKey>(&bin[..]).unwrap(), pk);
+    }
+
+    #[test]
+    fn test_serde_sk() {
+        #[derive(Serialize, Deserialize)]
+        struct SecKey(#[serde(with = "crate::secp256k1::sk_serde_impl")] SecretKey);
+
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let sk = SecKey(keypair.secret);
+        let bin = postcard::to_allocvec(&sk).unwrap();
+        let sk2: SecKey = postcard::from_bytes(&bin[..]).unwrap();
+        assert_eq!(sk.0.serialize(), sk2.0.serialize());
+    }
+
+    #[test]
+    fn test_serde_pk_sig() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let pk_sig = PubSigPair::create(&keypair, H256::zero());
+        let bin = postcard::to_allocvec(&pk_sig).unwrap();
+        assert_eq!(
+            postcard::from_bytes::<PubSigPair>(&bin[..]).unwrap(),
+            pk_sig
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut rng = rand::thread_rng();
+        let hash = H256::repeat_byte(0x12);
+        let keypair = Keypair::generate(&mut rng);
+        let pk_sig = PubSigPair::create(&keypair, hash);
+        pk_sig.verify(hash).unwrap();
+        assert!(pk_sig.verify(H256::repeat_byte(0x34)).is_err());
+
+        let other = Keypair::generate(&mut rng);
+        let pk_sig = PubSigPair {
+            pk: other.public,
+            sig: pk_sig.sig,
+        };
+        assert!(pk_sig.verify(hash).is_err());
+    }
+
+    #[test]
+    fn test_derive_keypair() {
+        // The test vector 1 of BIP-32.
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let keypair = derive_keypair(&seed, &"m".parse().unwrap()).unwrap();
+        assert_eq!(
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+            hex::encode(keypair.secret.serialize())
+        );
+        let path = "m/0'/1/2'/2/1000000000".parse().unwrap();
+        let keypair = derive_keypair(&seed, &path).unwrap();
+        assert_eq!(
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+            hex::encode(keypair.secret.serialize())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_derive_ethereum_account() {
+        use crate::{hd::seed_from_mnemonic, tx_req::caller_address_from_pk};
+
+        // The first account of the default mnemonic of Hardhat.
+        let seed = seed_from_mnemonic(
+            "test test test test test test test test test test test junk",
+            "",
+        )
+        .unwrap();
+        let keypair = derive_keypair(&seed, &DerivationPath::bip44(0, 0, false)).unwrap();
+        assert_eq!(
+            "f39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            hex::encode(caller_address_from_pk(&keypair.public).as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let sig = keypair.sign(H256::zero());
+
+        let pk = keypair.public;
+        assert_eq!(pk, pk_from_bytes(&pk.serialize_compressed()).unwrap());
+        assert_eq!(pk, pk_from_bytes(&pk.serialize()).unwrap());
+
+        let mut bytes = sig.serialize().to_vec();
+        assert_eq!(sig, sig_from_bytes(&bytes).unwrap());
+        bytes.push(27);
+        assert_eq!(sig, sig_from_bytes(&bytes).unwrap());
+    }
+}
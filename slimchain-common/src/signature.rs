@@ -0,0 +1,117 @@
+use crate::{
+    basic::{Address, H256},
+    digest::Digestible,
+    ed25519,
+    error::Result,
+    secp256k1,
+    tx_req::caller_address_from_pk,
+};
+use serde::{Deserialize, Serialize};
+
+// The public key and the signature of a tx or a tx request. Ed25519 is used by the nodes and
+// the enclave, while secp256k1 allows the Ethereum wallets to sign the tx requests.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    Ed25519(ed25519::PubSigPair),
+    Secp256k1(secp256k1::PubSigPair),
+}
+
+impl SignatureScheme {
+    pub fn verify(&self, msg_hash: H256) -> Result<()> {
+        match self {
+            SignatureScheme::Ed25519(pk_sig) => pk_sig.verify(msg_hash),
+            SignatureScheme::Secp256k1(pk_sig) => pk_sig.verify(msg_hash),
+        }
+    }
+
+    pub fn caller_address(&self) -> Address {
+        match self {
+            SignatureScheme::Ed25519(pk_sig) => caller_address_from_pk(pk_sig.public()),
+            SignatureScheme::Secp256k1(pk_sig) => caller_address_from_pk(pk_sig.public()),
+        }
+    }
+
+    pub fn as_ed25519(&self) -> Option<&ed25519::PubSigPair> {
+        match self {
+            SignatureScheme::Ed25519(pk_sig) => Some(pk_sig),
+            SignatureScheme::Secp256k1(_) => None,
+        }
+    }
+}
+
+// The digests of the two variants never collide, since their keys differ in length.
+impl Digestible for SignatureScheme {
+    fn to_digest(&self) -> H256 {
+        match self {
+            SignatureScheme::Ed25519(pk_sig) => pk_sig.to_digest(),
+            SignatureScheme::Secp256k1(pk_sig) => pk_sig.to_digest(),
+        }
+    }
+}
+
+impl From<ed25519::PubSigPair> for SignatureScheme {
+    fn from(pk_sig: ed25519::PubSigPair) -> Self {
+        SignatureScheme::Ed25519(pk_sig)
+    }
+}
+
+impl From<secp256k1::PubSigPair> for SignatureScheme {
+    fn from(pk_sig: secp256k1::PubSigPair) -> Self {
+        SignatureScheme::Secp256k1(pk_sig)
+    }
+}
+
+pub trait TxSigner {
+    fn sign_digest(&self, msg_hash: H256) -> SignatureScheme;
+}
+
+impl TxSigner for ed25519::Keypair {
+    fn sign_digest(&self, msg_hash: H256) -> SignatureScheme {
+        ed25519::PubSigPair::create(self, msg_hash).into()
+    }
+}
+
+impl TxSigner for secp256k1::Keypair {
+    fn sign_digest(&self, msg_hash: H256) -> SignatureScheme {
+        secp256k1::PubSigPair::create(self, msg_hash).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_scheme() {
+        let mut rng = rand::thread_rng();
+        let hash = H256::repeat_byte(0x12);
+
+        let ed25519_keypair = ed25519::Keypair::generate(&mut rng);
+        let pk_sig = ed25519_keypair.sign_digest(hash);
+        pk_sig.verify(hash).unwrap();
+        assert_eq!(
+            caller_address_from_pk(&ed25519_keypair.public),
+            pk_sig.caller_address()
+        );
+        assert!(pk_sig.as_ed25519().is_some());
+
+        let secp256k1_keypair = secp256k1::Keypair::generate(&mut rng);
+        let pk_sig2 = secp256k1_keypair.sign_digest(hash);
+        pk_sig2.verify(hash).unwrap();
+        assert!(pk_sig2.verify(H256::repeat_byte(0x34)).is_err());
+        assert_eq!(
+            caller_address_from_pk(&secp256k1_keypair.public),
+            pk_sig2.caller_address()
+        );
+        assert!(pk_sig2.as_ed25519().is_none());
+        assert_ne!(pk_sig.to_digest(), pk_sig2.to_digest());
+
+        for pk_sig in [pk_sig, pk_sig2] {
+            let bin = postcard::to_allocvec(&pk_sig).unwrap();
+            assert_eq!(
+                postcard::from_bytes::<SignatureScheme>(&bin[..]).unwrap(),
+                pk_sig
+            );
+        }
+    }
+}
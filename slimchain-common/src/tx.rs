@@ -1,6 +1,8 @@
 use crate::{
     basic::{Address, BlockHeight, H256},
     digest::Digestible,
+    ed25519::PubSigPair,
+    encoding::{CanonicalDecode, CanonicalEncode},
     error::Result,
     rw_set::{TxReadSet, TxWriteData},
     tx_req::{tx_id_from_caller_and_input, TxRequest},
@@ -12,10 +14,14 @@ pub use raw_tx::*;
 pub mod signed_tx;
 pub use signed_tx::*;
 
-pub trait TxTrait: Digestible + Clone + Sized + Send + Sync {
+// The txs are persisted in the canonical encoding, so that they survive the struct evolving.
+pub trait TxTrait:
+    Digestible + CanonicalEncode + CanonicalDecode + Clone + Sized + Send + Sync
+{
     fn tx_caller(&self) -> Address;
     fn tx_input(&self) -> &TxRequest;
     fn tx_block_height(&self) -> BlockHeight;
+    fn tx_block_timestamp(&self) -> u64;
     fn tx_state_root(&self) -> H256;
     fn tx_reads(&self) -> &TxReadSet;
     fn tx_writes(&self) -> &TxWriteData;
@@ -25,6 +31,20 @@ pub trait TxTrait: Digestible + Clone + Sized + Send + Sync {
     }
 
     fn verify_sig(&self) -> Result<()>;
+
+    // The ed25519 signature and the digest it signs, if any. It allows the signatures of a
+    // block to be verified in batch.
+    fn batch_sig(&self) -> Option<(H256, &PubSigPair)> {
+        None
+    }
+
+    // Verify all but the signature returned by `batch_sig`.
+    fn verify_sig_except_batch(&self) -> Result<()> {
+        match self.batch_sig() {
+            Some(_) => Ok(()),
+            None => self.verify_sig(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -39,12 +59,15 @@ mod tests {
             nonce: 1.into(),
             address: H160::repeat_byte(0xf).into(),
             data: b"data".to_vec(),
+            chain_id: 0,
+            expiry: None,
         };
 
         let raw_tx = RawTx {
             caller: Address::default(),
             input: tx_req,
             block_height: 1.into(),
+            block_timestamp: 0,
             state_root: H256::zero(),
             reads: TxReadSet::default(),
             writes: TxWriteData::default(),
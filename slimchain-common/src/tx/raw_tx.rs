@@ -1,10 +1,11 @@
 use super::{SignedTx, TxTrait};
 use crate::{
     basic::{Address, BlockHeight, H256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
-    ed25519::{Keypair, PubSigPair},
+    digest::{digest_state, Digestible},
+    encoding::CanonicalEncode,
     error::Result,
     rw_set::{TxReadSet, TxWriteData},
+    signature::TxSigner,
     tx_req::TxRequest,
 };
 use serde::{Deserialize, Serialize};
@@ -14,6 +15,8 @@ pub struct RawTx {
     pub caller: Address,
     pub input: TxRequest,
     pub block_height: BlockHeight,
+    // The timestamp (in ms) of the block at `block_height`, exposed to the contracts.
+    pub block_timestamp: u64,
     pub state_root: H256,
     pub reads: TxReadSet,
     pub writes: TxWriteData,
@@ -21,15 +24,15 @@ pub struct RawTx {
 
 impl Digestible for RawTx {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.caller.as_bytes());
         hash_state.update(self.input.to_digest().as_bytes());
         hash_state.update(self.block_height.to_digest().as_bytes());
+        hash_state.update(self.block_timestamp.to_digest().as_bytes());
         hash_state.update(self.state_root.as_bytes());
         hash_state.update(self.reads.to_digest().as_bytes());
         hash_state.update(self.writes.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -46,6 +49,10 @@ impl TxTrait for RawTx {
         self.block_height
     }
 
+    fn tx_block_timestamp(&self) -> u64 {
+        self.block_timestamp
+    }
+
     fn tx_state_root(&self) -> H256 {
         self.state_root
     }
@@ -64,11 +71,11 @@ impl TxTrait for RawTx {
 }
 
 impl RawTx {
-    pub fn sign(self, keypair: &Keypair) -> SignedTx {
-        let hash = self.to_digest();
+    pub fn sign(self, keypair: &impl TxSigner) -> SignedTx {
+        let hash = self.signing_digest();
         SignedTx {
             raw_tx: self,
-            pk_sig: PubSigPair::create(keypair, hash),
+            pk_sig: keypair.sign_digest(hash),
         }
     }
 }
@@ -1,10 +1,12 @@
 use super::{RawTx, TxTrait};
 use crate::{
     basic::{Address, BlockHeight, H256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     ed25519::PubSigPair,
+    encoding::CanonicalEncode,
     error::Result,
     rw_set::{TxReadSet, TxWriteData},
+    signature::SignatureScheme,
     tx_req::TxRequest,
 };
 use serde::{Deserialize, Serialize};
@@ -12,16 +14,15 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SignedTx {
     pub raw_tx: RawTx,
-    pub pk_sig: PubSigPair,
+    pub pk_sig: SignatureScheme,
 }
 
 impl Digestible for SignedTx {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.raw_tx.to_digest().as_bytes());
         hash_state.update(self.pk_sig.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
@@ -38,6 +39,10 @@ impl TxTrait for SignedTx {
         self.raw_tx.tx_block_height()
     }
 
+    fn tx_block_timestamp(&self) -> u64 {
+        self.raw_tx.tx_block_timestamp()
+    }
+
     fn tx_state_root(&self) -> H256 {
         self.raw_tx.tx_state_root()
     }
@@ -51,7 +56,12 @@ impl TxTrait for SignedTx {
     }
 
     fn verify_sig(&self) -> Result<()> {
-        let hash = self.raw_tx.to_digest();
+        let hash = self.raw_tx.signing_digest();
         self.pk_sig.verify(hash)
     }
+
+    fn batch_sig(&self) -> Option<(H256, &PubSigPair)> {
+        let pk_sig = self.pk_sig.as_ed25519()?;
+        Some((self.raw_tx.signing_digest(), pk_sig))
+    }
 }
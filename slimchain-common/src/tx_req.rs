@@ -1,60 +1,108 @@
 use crate::{
-    basic::{Address, Code, Nonce, H256},
-    digest::{blake2, blake2b_hash_to_h160, blake2b_hash_to_h256, default_blake2, Digestible},
-    ed25519::{Keypair, PubSigPair, PublicKey},
-    error::Result,
+    basic::{Address, BlockHeight, Code, Nonce, H160, H256},
+    digest::{blake2, blake2b_hash_to_h160, digest_state, keccak256, DigestState, Digestible},
+    ed25519,
+    encoding::CanonicalEncode,
+    error::{ExecutionError, Result},
+    secp256k1,
+    signature::{SignatureScheme, TxSigner},
 };
-use alloc::vec::Vec;
+use alloc::{string::ToString, vec::Vec};
 use serde::{Deserialize, Serialize};
 
-pub fn caller_address_from_pk(pk: &PublicKey) -> Address {
-    let hash = blake2(20).hash(&pk.to_bytes()[..]);
-    blake2b_hash_to_h160(hash).into()
+pub trait CallerPublicKey {
+    fn to_caller_address(&self) -> Address;
+}
+
+impl CallerPublicKey for ed25519::PublicKey {
+    fn to_caller_address(&self) -> Address {
+        let hash = blake2(20).hash(&self.to_bytes()[..]);
+        blake2b_hash_to_h160(hash).into()
+    }
+}
+
+// Same as Ethereum, i.e., the last 20 bytes of the keccak hash of the uncompressed key without
+// its prefix.
+impl CallerPublicKey for secp256k1::PublicKey {
+    fn to_caller_address(&self) -> Address {
+        let hash = keccak256(&self.serialize()[1..]);
+        H160::from_slice(&hash.as_bytes()[12..]).into()
+    }
+}
+
+pub fn caller_address_from_pk(pk: &impl CallerPublicKey) -> Address {
+    pk.to_caller_address()
 }
 
 pub(crate) fn tx_id_from_caller_and_input(caller: Address, input: &TxRequest) -> H256 {
-    let mut hash_state = default_blake2().to_state();
+    let mut hash_state = digest_state();
     hash_state.update(caller.to_digest().as_bytes());
     hash_state.update(input.to_digest().as_bytes());
-    blake2b_hash_to_h256(hash_state.finalize())
+    hash_state.finalize()
 }
 
+// `chain_id` and `expiry` guard against replaying the tx request on another chain or long after
+// it was signed. `expiry` is the last block height in which the tx can be included.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum TxRequest {
     Create {
         nonce: Nonce,
         code: Code,
+        chain_id: u64,
+        expiry: Option<BlockHeight>,
     },
     Call {
         nonce: Nonce,
         address: Address,
         data: Vec<u8>,
+        chain_id: u64,
+        expiry: Option<BlockHeight>,
     },
 }
 
+fn update_replay_guard(hash_state: &mut DigestState, chain_id: u64, expiry: Option<BlockHeight>) {
+    hash_state.update(chain_id.to_digest().as_bytes());
+    match expiry {
+        Some(expiry) => {
+            hash_state.update(&[1]);
+            hash_state.update(expiry.to_digest().as_bytes());
+        }
+        None => {
+            hash_state.update(&[0]);
+        }
+    }
+}
+
 impl Digestible for TxRequest {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
-        let hash = match self {
-            TxRequest::Create { nonce, code } => {
+        let mut hash_state = digest_state();
+        match self {
+            TxRequest::Create {
+                nonce,
+                code,
+                chain_id,
+                expiry,
+            } => {
                 hash_state.update(b"Create");
                 hash_state.update(nonce.to_digest().as_bytes());
                 hash_state.update(code.to_digest().as_bytes());
-                hash_state.finalize()
+                update_replay_guard(&mut hash_state, *chain_id, *expiry);
             }
             TxRequest::Call {
                 nonce,
                 address,
                 data,
+                chain_id,
+                expiry,
             } => {
                 hash_state.update(b"Call");
                 hash_state.update(nonce.to_digest().as_bytes());
                 hash_state.update(address.to_digest().as_bytes());
+                update_replay_guard(&mut hash_state, *chain_id, *expiry);
                 hash_state.update(&data[..]);
-                hash_state.finalize()
             }
-        };
-        blake2b_hash_to_h256(hash)
+        }
+        hash_state.finalize()
     }
 }
 
@@ -65,11 +113,44 @@ impl TxRequest {
         }
     }
 
-    pub fn sign(self, keypair: &Keypair) -> SignedTxRequest {
-        let hash = self.to_digest();
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            TxRequest::Call { chain_id, .. } | TxRequest::Create { chain_id, .. } => *chain_id,
+        }
+    }
+
+    pub fn expiry(&self) -> Option<BlockHeight> {
+        match self {
+            TxRequest::Call { expiry, .. } | TxRequest::Create { expiry, .. } => *expiry,
+        }
+    }
+
+    // Check whether the tx can be included in the block at `block_height` of the chain.
+    pub fn check_replay(
+        &self,
+        chain_id: u64,
+        block_height: BlockHeight,
+    ) -> Result<(), ExecutionError> {
+        if self.chain_id() != chain_id {
+            return Err(ExecutionError::InvalidChainId {
+                expected: chain_id,
+                actual: self.chain_id(),
+            });
+        }
+        match self.expiry() {
+            Some(expiry) if block_height > expiry => Err(ExecutionError::Expired {
+                expiry,
+                block_height,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn sign(self, keypair: &impl TxSigner) -> SignedTxRequest {
+        let hash = self.signing_digest();
         SignedTxRequest {
             input: self,
-            pk_sig: PubSigPair::create(keypair, hash),
+            pk_sig: keypair.sign_digest(hash),
         }
     }
 }
@@ -77,27 +158,28 @@ impl TxRequest {
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SignedTxRequest {
     pub input: TxRequest,
-    pub pk_sig: PubSigPair,
+    pub pk_sig: SignatureScheme,
 }
 
 impl Digestible for SignedTxRequest {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.input.to_digest().as_bytes());
         hash_state.update(self.pk_sig.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
     }
 }
 
 impl SignedTxRequest {
-    pub fn verify(&self) -> Result<()> {
-        let hash = self.input.to_digest();
-        self.pk_sig.verify(hash)
+    pub fn verify(&self) -> Result<(), ExecutionError> {
+        let hash = self.input.signing_digest();
+        self.pk_sig
+            .verify(hash)
+            .map_err(|e| ExecutionError::InvalidSignature(e.to_string()))
     }
 
     pub fn caller_address(&self) -> Address {
-        caller_address_from_pk(&self.pk_sig.public())
+        self.pk_sig.caller_address()
     }
 
     pub fn id(&self) -> H256 {
@@ -108,7 +190,7 @@ impl SignedTxRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::basic::H160;
+    use crate::error::ErrorCode;
 
     #[test]
     fn test_sign_verify_tx_req() {
@@ -116,12 +198,76 @@ mod tests {
             nonce: 1.into(),
             address: H160::repeat_byte(0xf).into(),
             data: b"data".to_vec(),
+            chain_id: 0,
+            expiry: None,
         };
 
         let mut rng = rand::thread_rng();
-        let keypair = Keypair::generate(&mut rng);
+        let keypair = ed25519::Keypair::generate(&mut rng);
+        let signed_tx_req = tx_req.clone().sign(&keypair);
+        signed_tx_req.verify().unwrap();
+
+        let keypair = secp256k1::Keypair::generate(&mut rng);
         let signed_tx_req = tx_req.sign(&keypair);
         signed_tx_req.verify().unwrap();
+        assert_eq!(
+            caller_address_from_pk(&keypair.public),
+            signed_tx_req.caller_address()
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_caller_address() {
+        // The well-known key and address used in the Ethereum test suites.
+        let keypair = secp256k1::Keypair::from_secret_bytes(&[
+            0x45, 0xa9, 0x15, 0xe4, 0xd0, 0x60, 0x14, 0x9e, 0xb4, 0x36, 0x59, 0x60, 0xe6, 0xa7,
+            0xa4, 0x5f, 0x33, 0x43, 0x93, 0x09, 0x30, 0x61, 0x11, 0x6b, 0x19, 0x7e, 0x32, 0x40,
+            0x06, 0x5f, 0xf2, 0xd8,
+        ])
+        .unwrap();
+        let expect: Address = H160::from_slice(&[
+            0xa9, 0x4f, 0x53, 0x74, 0xfc, 0xe5, 0xed, 0xbc, 0x8e, 0x2a, 0x86, 0x97, 0xc1, 0x53,
+            0x31, 0x67, 0x7e, 0x6e, 0xbf, 0x0b,
+        ])
+        .into();
+        assert_eq!(expect, caller_address_from_pk(&keypair.public));
+    }
+
+    #[test]
+    fn test_tx_req_replay_guard() {
+        let tx_req = TxRequest::Call {
+            nonce: 1.into(),
+            address: H160::repeat_byte(0xf).into(),
+            data: b"data".to_vec(),
+            chain_id: 1,
+            expiry: Some(BlockHeight(10)),
+        };
+        tx_req.check_replay(1, BlockHeight(9)).unwrap();
+        tx_req.check_replay(1, BlockHeight(10)).unwrap();
+        let err = tx_req.check_replay(1, BlockHeight(11)).unwrap_err();
+        assert_eq!(ErrorCode::TxExpired, err.code());
+        let err = tx_req.check_replay(2, BlockHeight(9)).unwrap_err();
+        assert_eq!(ErrorCode::InvalidChainId, err.code());
+
+        let mut tx_req2 = tx_req.clone();
+        if let TxRequest::Call { chain_id, .. } = &mut tx_req2 {
+            *chain_id = 2;
+        }
+        assert_ne!(tx_req.to_digest(), tx_req2.to_digest());
+
+        let mut tx_req3 = tx_req.clone();
+        if let TxRequest::Call { expiry, .. } = &mut tx_req3 {
+            *expiry = None;
+        }
+        assert_ne!(tx_req.to_digest(), tx_req3.to_digest());
+        tx_req3.check_replay(1, BlockHeight(100)).unwrap();
+
+        let mut rng = rand::thread_rng();
+        let keypair = ed25519::Keypair::generate(&mut rng);
+        let mut signed_tx_req = tx_req.sign(&keypair);
+        signed_tx_req.input = tx_req2;
+        let err = signed_tx_req.verify().unwrap_err();
+        assert_eq!(ErrorCode::InvalidSignature, err.code());
     }
 
     #[test]
@@ -130,10 +276,12 @@ mod tests {
             nonce: 1.into(),
             address: H160::repeat_byte(0xf).into(),
             data: b"data".to_vec(),
+            chain_id: 0,
+            expiry: None,
         };
 
         let mut rng = rand::thread_rng();
-        let keypair = Keypair::generate(&mut rng);
+        let keypair = ed25519::Keypair::generate(&mut rng);
         let signed_tx_req = tx_req.sign(&keypair);
 
         let bin = postcard::to_allocvec(&signed_tx_req).unwrap();
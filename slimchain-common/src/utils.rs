@@ -186,7 +186,9 @@ macro_rules! create_acc_read_data {
         $reads.nonce = Some($crate::basic::Nonce::from($x));
     };
     (@data $reads:ident @parse code $x:expr) => {
-        $reads.code = Some($crate::basic::Code::from($x.to_vec()));
+        $reads.code_hash = Some($crate::digest::Digestible::to_digest(
+            &$crate::basic::Code::from($x.to_vec()),
+        ));
     };
     (@data $reads:ident @parse values { $($key:expr => $value:expr,)+ }) => {
         $crate::create_acc_read_data!(@data $reads @parse values { $($key => $value),+ });
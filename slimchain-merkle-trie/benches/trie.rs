@@ -0,0 +1,166 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use slimchain_common::{
+    basic::{StateKey, StateValue},
+    collections::HashMap,
+    error::Context as _,
+};
+use slimchain_merkle_trie::prelude::*;
+
+// From the state trie of a small contract to that of a popular one.
+const TRIE_SIZES: [u64; 3] = [1_000, 10_000, 100_000];
+// Roughly the number of keys touched by one block.
+const NUM_KEYS: u64 = 100;
+
+#[derive(Default)]
+struct BenchTrie {
+    root: H256,
+    nodes: HashMap<H256, TrieNode<StateValue>>,
+}
+
+impl NodeLoader<StateValue> for BenchTrie {
+    fn load_node(&self, id: H256) -> Result<TrieNode<StateValue>> {
+        self.nodes.get(&id).cloned().context("Unknown node")
+    }
+}
+
+impl NodeLoader<StateValue> for &'_ BenchTrie {
+    fn load_node(&self, id: H256) -> Result<TrieNode<StateValue>> {
+        self.nodes.get(&id).cloned().context("Unknown node")
+    }
+}
+
+impl BenchTrie {
+    fn new(size: u64) -> Self {
+        let mut trie = Self::default();
+        let mut ctx = WriteTrieContext::new(&trie, trie.root);
+        for i in 0..size {
+            ctx.insert(&key(i), value(i)).unwrap();
+        }
+        let apply = ctx.changes();
+        trie.root = apply.root;
+        trie.nodes.extend(apply.nodes.into_iter());
+        trie
+    }
+
+    fn read_proof(&self, keys: &[StateKey]) -> PartialTrie {
+        let mut ctx = ReadTrieContext::new(self, self.root);
+        for k in keys {
+            ctx.read(k).unwrap();
+        }
+        ctx.into_proof().into()
+    }
+}
+
+fn key(i: u64) -> StateKey {
+    i.to_digest().into()
+}
+
+fn value(i: u64) -> StateValue {
+    (i + 1).into()
+}
+
+// Spread the keys over the whole trie. An offset selects a disjoint set of keys.
+fn sample_keys(size: u64, offset: u64) -> Vec<StateKey> {
+    let step = size / NUM_KEYS;
+    (0..NUM_KEYS).map(|i| key(i * step + offset)).collect()
+}
+
+fn bench_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trie_read");
+    for &size in &TRIE_SIZES {
+        let trie = BenchTrie::new(size);
+        let keys = sample_keys(size, 0);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &keys, |b, keys| {
+            b.iter(|| {
+                for k in keys {
+                    black_box(read_trie_without_proof(&trie, trie.root, k).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_read_proof(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trie_read_proof");
+    for &size in &TRIE_SIZES {
+        let trie = BenchTrie::new(size);
+        let keys = sample_keys(size, 0);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &keys, |b, keys| {
+            b.iter(|| {
+                let mut ctx = ReadTrieContext::new(&trie, trie.root);
+                for k in keys {
+                    ctx.read(k).unwrap();
+                }
+                black_box(ctx.into_proof())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trie_write");
+    for &size in &TRIE_SIZES {
+        let trie = BenchTrie::new(size);
+        // Half of the writes update existing keys, and the other half insert new ones.
+        let writes: Vec<(StateKey, StateValue)> = sample_keys(size, 0)
+            .into_iter()
+            .take((NUM_KEYS / 2) as usize)
+            .map(|k| (k, value(size)))
+            .chain((size..size + NUM_KEYS / 2).map(|i| (key(i), value(i))))
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &writes, |b, writes| {
+            b.iter(|| {
+                let mut ctx = WriteTrieContext::new(&trie, trie.root);
+                for (k, v) in writes {
+                    ctx.insert(k, *v).unwrap();
+                }
+                black_box(ctx.changes())
+            })
+        });
+    }
+    group.finish();
+}
+
+// The client holds the partial trie of the previous block, and receives the proof of the next one.
+fn bench_partial_trie(c: &mut Criterion) {
+    let mut group = c.benchmark_group("partial_trie");
+    for &size in &TRIE_SIZES {
+        let trie = BenchTrie::new(size);
+        let main = trie.read_proof(&sample_keys(size, 0));
+        let fork_keys = sample_keys(size, 1);
+        let fork = trie.read_proof(&fork_keys);
+        let diff = diff_missing_branches(&main, &fork);
+        let applied = apply_diff(&main, &diff, true).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("diff", size), &fork, |b, fork| {
+            b.iter(|| black_box(diff_missing_branches(&main, fork)))
+        });
+        group.bench_with_input(BenchmarkId::new("apply_diff", size), &diff, |b, diff| {
+            b.iter(|| black_box(apply_diff(&main, diff, true).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("write", size), &fork_keys, |b, keys| {
+            b.iter_batched(
+                || WritePartialTrieContext::new(applied.clone()),
+                |mut ctx| {
+                    for (i, k) in keys.iter().enumerate() {
+                        ctx.insert_with_value(k, &value(i as u64)).unwrap();
+                    }
+                    ctx.finish()
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_read,
+    bench_read_proof,
+    bench_write,
+    bench_partial_trie
+);
+criterion_main!(benches);
@@ -1,33 +1,50 @@
 pub mod client;
 pub use client::*;
 
+pub mod fork;
+pub use fork::*;
+
+pub mod light_client;
+pub use light_client::*;
+
 pub mod miner;
 pub use miner::*;
 
 pub mod storage;
 pub use storage::*;
 
+use crate::p2p::{
+    discovery::Discovery,
+    sync::{BlockSync, RecentBlockProposals},
+};
 use futures::{
     channel::{mpsc, oneshot},
     prelude::*,
     stream::Fuse,
 };
-use serde::Serialize;
+use libp2p::PeerId;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     behavior::{commit_block, commit_block_storage_node, propose_block, verify_block},
+    block::BlockTrait,
     block_proposal::BlockProposal,
     config::{ChainConfig, MinerConfig},
-    consensus::pow::{create_new_block, verify_consensus, Block},
+    consensus::pow::{create_new_block, verify_consensus, Block, ExternalMiner},
     db::{DBPtr, Transaction as DBTx},
     latest::{LatestBlockHeaderPtr, LatestTxCountPtr},
+    role::Role,
+    shard_block::ShardBlock,
     snapshot::Snapshot,
 };
 use slimchain_common::{
+    basic::H256,
+    digest::Digestible,
     error::{bail, Result},
     tx::TxTrait,
 };
 use slimchain_tx_state::{TxProposal, TxTrie, TxTrieTrait};
-use slimchain_utils::ordered_stream::OrderedStream;
+use slimchain_utils::{ordered_stream::OrderedStream, record_event};
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -48,21 +65,55 @@ impl<Tx: TxTrait + Serialize> BlockImportWorker<Tx> {
         latest_block_header: LatestBlockHeaderPtr,
         latest_tx_count: LatestTxCountPtr,
         db: DBPtr,
+        recent: RecentBlockProposals<Block, Tx>,
         snapshot_to_db_tx: impl Fn(&Snapshot<Block, TxTrie>) -> Result<DBTx> + Send + Sync + 'static,
     ) -> Self {
-        let (blk_tx, blk_rx) = mpsc::unbounded::<BlockProposal<Block, Tx>>();
-        let mut blk_rx = OrderedStream::new(
-            blk_rx.map(|blk| (blk.get_block_height(), blk)),
+        let (blk_tx, mut blk_rx) = mpsc::unbounded::<BlockProposal<Block, Tx>>();
+        let (mut main_blk_tx, main_blk_rx) = mpsc::unbounded::<BlockProposal<Block, Tx>>();
+        let mut main_blk_rx = OrderedStream::new(
+            main_blk_rx.map(|blk| (blk.get_block_height(), blk)),
             latest_block_header.get_height().next_height(),
             |height| height.next_height(),
         );
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
+        let latest_blk = snapshot
+            .get_latest_block()
+            .expect("Failed to get the latest block.");
+        let mut fork_tracker = ForkTracker::new(latest_blk.block_height(), latest_blk.to_digest());
+        let mut orphans = OrphanPool::new(MAX_ORPHAN_BLOCKS);
+        register_fork_stats();
+
         let handle: JoinHandle<()> = tokio::spawn(async move {
             loop {
                 tokio::select! {
                     _ = &mut shutdown_rx => break,
                     Some(blk_proposal) = blk_rx.next() => {
+                        let blk = blk_proposal.get_block();
+                        let height = blk.block_height();
+                        let blk_hash = blk.to_digest();
+                        let prev_hash = blk.prev_blk_hash();
+                        match fork_tracker.classify(height, blk_hash, prev_hash) {
+                            BlockClass::Main => {
+                                main_blk_tx.start_send(blk_proposal).ok();
+                            }
+                            class @ (BlockClass::Stale | BlockClass::Orphan) => {
+                                let stale = class == BlockClass::Stale;
+                                record_event!("pow_fork_block", "height": height.0, "stale": stale);
+                                orphans.insert(height, blk_hash, prev_hash, blk_proposal);
+                                // Only the first block seen at each height is imported.
+                                if height > fork_tracker.tip_height() {
+                                    warn!(
+                                        height = height.0,
+                                        fork_height = ?orphans.fork_height(&blk_hash).map(|h| h.0),
+                                        "A longer fork is found, but reorg is not supported yet."
+                                    );
+                                }
+                            }
+                            BlockClass::Duplicate | BlockClass::Outdated => {}
+                        }
+                    }
+                    Some(blk_proposal) = main_blk_rx.next() => {
                         let snapshot_backup = snapshot.clone();
                         let state_update = match verify_block(
                             &chain_cfg,
@@ -99,6 +150,8 @@ impl<Tx: TxTrait + Serialize> BlockImportWorker<Tx> {
                             }
                             panic!("Failed to commit the block. Error: {}", e);
                         }
+
+                        recent.insert(blk_proposal);
                     }
                 }
             }
@@ -152,6 +205,7 @@ impl<Tx: TxTrait + Serialize> BlockProposalWorker<Tx> {
         latest_block_header: LatestBlockHeaderPtr,
         latest_tx_count: LatestTxCountPtr,
         db: DBPtr,
+        external_miner: Option<ExternalMiner>,
     ) -> Self {
         let (tx_tx, tx_rx) = mpsc::unbounded::<TxProposal<Tx>>();
         let mut tx_rx = tx_rx.fuse().peekable();
@@ -173,20 +227,32 @@ impl<Tx: TxTrait + Serialize> BlockProposalWorker<Tx> {
                 }
 
                 let snapshot_backup = snapshot.clone();
-                let blk_proposal = match propose_block(
-                    &chain_cfg,
-                    &miner_cfg,
-                    &mut snapshot,
-                    &mut tx_rx,
-                    create_new_block,
-                )
-                .await
-                {
-                    Ok(blk_proposal) => blk_proposal,
-                    Err(e) => {
+                let new_block_fn = |header, prev_blk: &Block| match external_miner.as_ref() {
+                    Some(miner) => miner.create_new_block(header, prev_blk).boxed(),
+                    None => create_new_block(header, prev_blk).boxed(),
+                };
+                // The external miners may never submit the nonce, so do not wait for them on
+                // shutdown.
+                let res = tokio::select! {
+                    _ = &mut shutdown_rx => None,
+                    res = propose_block(
+                        &chain_cfg,
+                        &miner_cfg,
+                        &mut snapshot,
+                        &mut tx_rx,
+                        new_block_fn,
+                    ) => Some(res),
+                };
+                let blk_proposal = match res {
+                    Some(Ok(blk_proposal)) => blk_proposal,
+                    Some(Err(e)) => {
                         snapshot_backup.write_async(&db).await.ok();
                         panic!("Failed to build the new block. Error: {}", e);
                     }
+                    None => {
+                        snapshot = snapshot_backup;
+                        break;
+                    }
                 };
 
                 match blk_proposal {
@@ -251,3 +317,48 @@ impl<Tx: TxTrait + Serialize> BlockProposalWorker<Tx> {
         Ok(())
     }
 }
+
+// Validators installed on the pubsub, so that the proposals with bad signatures or malformed
+// blocks are dropped instead of being relayed.
+pub(crate) fn validate_shard_block<Tx: TxTrait>(shard_block: &ShardBlock<Tx>) -> Result<H256> {
+    shard_block.verify_sigs()?;
+    Ok(shard_block.to_digest())
+}
+
+pub(crate) fn validate_block_proposal<Tx: TxTrait>(
+    proposal: &BlockProposal<Block, Tx>,
+) -> Result<H256> {
+    proposal.verify_structure()?;
+    Ok(proposal.get_block().to_digest())
+}
+
+// Any peer keeping the block proposals, i.e., the miners, the clients and the storage nodes.
+fn random_sync_peer(discv: &Discovery) -> Option<PeerId> {
+    let roles: Vec<Role> = discv
+        .known_roles()
+        .filter(|role| matches!(role, Role::Miner | Role::Client | Role::Storage(_)))
+        .copied()
+        .collect();
+    roles
+        .choose(&mut rand::thread_rng())
+        .and_then(|role| discv.random_known_peer(role))
+}
+
+// Request the missing block proposals from a random peer once the import stalls.
+pub(crate) fn poll_block_sync<Tx>(
+    sync: &mut BlockSync<Block, Tx>,
+    discv: &Discovery,
+    cx: &mut Context<'_>,
+) where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    if let Poll::Ready(req) = sync.poll_stalled(cx) {
+        match random_sync_peer(discv) {
+            Some(peer) => sync.request_blocks(&peer, req.from, req.count),
+            None => warn!(
+                from = req.from.0,
+                "No peer to sync the block proposals from."
+            ),
+        }
+    }
+}
@@ -1,51 +1,86 @@
-use super::BlockImportWorker;
-use crate::p2p::{
-    config::NetworkConfig,
-    control::Shutdown,
-    discovery::{Discovery, DiscoveryEvent, QueryId as DiscoveryQueryId},
-    http::{ClientHttpServer, TxHttpRequest},
-    pubsub::{PubSub, PubSubEvent, PubSubTopic},
-    rpc::{
-        create_request_response_client, handle_request_response_client_event, RpcInstant,
-        RpcRequestId, RpcRequestResponseEvent,
+use super::{poll_block_sync, validate_block_proposal, BlockImportWorker};
+use crate::{
+    grpc::server::{spawn_node_rpc_server, NodeRpcServerHandle, NodeRpcService},
+    http::status::StatusService,
+    p2p::{
+        config::NetworkConfig,
+        control::Shutdown,
+        discovery::{Discovery, DiscoveryEvent, QueryId as DiscoveryQueryId},
+        http::{ClientHttpServer, TxHttpRequest},
+        pubsub::{PubSub, PubSubEvent, PubSubTopic},
+        relay::{Relay, WithRelay},
+        rpc::{
+            create_request_response_client, handle_request_response_client_event, RpcInstant,
+            RpcRequestId, RpcRequestResponseEvent,
+        },
+        sync::{BlockSync, BlockSyncEvent, RecentBlockProposals},
+    },
+    tx_route::{
+        record_tx_retried, record_tx_route_failed, record_tx_routed, resolve_tx_shard,
+        MAX_ROUTE_ATTEMPTS,
     },
 };
 use async_trait::async_trait;
-use libp2p::{swarm::NetworkBehaviourEventProcess, NetworkBehaviour, PeerId};
-use serde::Serialize;
+use futures::{prelude::*, stream};
+use libp2p::{
+    swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
+    NetworkBehaviour, PeerId,
+};
+use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     block_proposal::BlockProposal, config::ChainConfig, consensus::pow::Block, db::DBPtr,
-    latest::LatestTxCount, role::Role, snapshot::Snapshot,
+    discard_tx, latest::LatestTxCount, role::Role, shard_block::ShardBlock, snapshot::Snapshot,
 };
 use slimchain_common::{
-    basic::H256, collections::HashMap, error::Result, tx::TxTrait, tx_req::SignedTxRequest,
+    basic::ShardId,
+    collections::HashMap,
+    error::{Error, Result},
+    tx::TxTrait,
+    tx_req::SignedTxRequest,
 };
-use slimchain_tx_state::{TxProposal, TxTrie};
+use slimchain_tx_state::TxTrie;
 use slimchain_utils::record_event;
-use std::time::Duration;
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
 
 #[derive(NetworkBehaviour)]
-pub struct ClientBehavior<Tx: TxTrait + Serialize + 'static> {
+#[behaviour(poll_method = "poll_inner")]
+pub struct ClientBehavior<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> {
     discv: Discovery,
-    pubsub: PubSub<TxProposal<Tx>, BlockProposal<Block, Tx>>,
+    pubsub: PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>>,
     http_server: ClientHttpServer,
     rpc_client: RpcInstant<SignedTxRequest, ()>,
+    sync: BlockSync<Block, Tx>,
     #[behaviour(ignore)]
     worker: BlockImportWorker<Tx>,
     #[behaviour(ignore)]
-    pending_discv_queries: HashMap<DiscoveryQueryId, SignedTxRequest>,
+    grpc_srv: Option<NodeRpcServerHandle>,
     #[behaviour(ignore)]
-    pending_rpc_queries: HashMap<RpcRequestId, H256>,
+    pending_discv_queries: HashMap<DiscoveryQueryId, (SignedTxRequest, ShardId)>,
+    #[behaviour(ignore)]
+    pending_rpc_queries: HashMap<RpcRequestId, PendingTxRoute>,
 }
 
-impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
+// A tx sent to a storage node. It is kept to retry on another node of the same shard.
+struct PendingTxRoute {
+    req: SignedTxRequest,
+    shard_id: ShardId,
+    tried: Vec<PeerId>,
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientBehavior<Tx> {
     pub async fn new(db: DBPtr, chain_cfg: &ChainConfig, net_cfg: &NetworkConfig) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
         let peer_id = PeerId::from(keypair.public());
 
-        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Client, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let mut pubsub = PubSub::new(keypair, &[PubSubTopic::BlockProposal], &[])?;
+        let mut pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::BlockProposal], &[])?
+            .with_block_proposal_validator(validate_block_proposal);
         pubsub.add_peers_from_net_config(net_cfg);
         let mut rpc_client = create_request_response_client("/tx_req/1");
 
@@ -58,28 +93,59 @@ impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
         let snapshot = Snapshot::<Block, TxTrie>::load_from_db(&db, chain_cfg.state_len)?;
         let latest_block_header = snapshot.to_latest_block_header();
         let latest_tx_count = LatestTxCount::new(0);
+        let recent = RecentBlockProposals::default();
         let worker = BlockImportWorker::new(
             false,
             chain_cfg.clone(),
             snapshot,
             latest_block_header.clone(),
             latest_tx_count.clone(),
-            db,
+            db.clone(),
+            recent.clone(),
             |snapshot| snapshot.write_db_tx(),
         );
+        let sync = BlockSync::new(recent).with_latest_block_header(latest_block_header.clone());
 
-        let http_server = ClientHttpServer::new(
+        let status =
+            StatusService::new::<Block>(Role::Client, db.clone(), latest_block_header.clone())
+                .with_peer_counts(discv.peer_counts());
+        let db_copy = db.clone();
+        let http_server = ClientHttpServer::new_with_subscription(
             &net_cfg.http_listen,
+            net_cfg.tls.as_ref(),
+            &net_cfg.admission,
             move || latest_tx_count.get(),
-            move || latest_block_header.get_height(),
+            latest_block_header.clone(),
+            move |tx_id| db_copy.get_tx_receipt(tx_id),
+            status,
         )?;
 
+        let grpc_srv = match net_cfg.grpc_listen.as_deref() {
+            Some(grpc_listen) => {
+                let tx_req_tx = http_server.tx_req_sender();
+                let service = NodeRpcService::<Block>::new(db, latest_block_header, move |reqs| {
+                    let mut tx_req_tx = tx_req_tx.clone();
+                    async move {
+                        tx_req_tx
+                            .send_all(&mut stream::iter(reqs).map(Ok))
+                            .await
+                            .map_err(Error::msg)
+                    }
+                })
+                .with_admission(http_server.admission());
+                Some(spawn_node_rpc_server(grpc_listen, service)?)
+            }
+            None => None,
+        };
+
         Ok(Self {
             discv,
             pubsub,
             http_server,
             rpc_client,
+            sync,
             worker,
+            grpc_srv,
             pending_discv_queries: HashMap::new(),
             pending_rpc_queries: HashMap::new(),
         })
@@ -89,27 +155,73 @@ impl<Tx: TxTrait + Serialize + 'static> ClientBehavior<Tx> {
         &mut self.discv
     }
 
-    pub fn pubsub_mut(&mut self) -> &mut PubSub<TxProposal<Tx>, BlockProposal<Block, Tx>> {
+    pub fn pubsub_mut(&mut self) -> &mut PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>> {
         &mut self.pubsub
     }
+
+    fn poll_inner<T>(
+        &mut self,
+        cx: &mut Context,
+        _: &mut impl PollParameters,
+    ) -> Poll<NetworkBehaviourAction<T, ()>> {
+        poll_block_sync(&mut self.sync, &self.discv, cx);
+        Poll::Pending
+    }
+
+    fn send_tx_to_storage_node(&mut self, mut route: PendingTxRoute, peer_id: PeerId) {
+        route.tried.push(peer_id);
+        let rpc_query_id = self.rpc_client.send_request(&peer_id, route.req.clone());
+        self.pending_rpc_queries.insert(rpc_query_id, route);
+    }
+
+    fn retry_tx_on_another_storage_node(&mut self, route: PendingTxRoute, e: Error) {
+        let tx_req_id = route.req.id();
+        if route.tried.len() < MAX_ROUTE_ATTEMPTS {
+            let next_peer = self
+                .discv
+                .random_known_peers(
+                    &Role::Storage(route.shard_id),
+                    route.tried.len() + MAX_ROUTE_ATTEMPTS,
+                )
+                .into_iter()
+                .find(|peer_id| !route.tried.contains(peer_id));
+            if let Some(peer_id) = next_peer {
+                warn!(%tx_req_id, %peer_id, "Retry TX on another storage node. Error: {}", e);
+                record_tx_retried();
+                record_event!("tx_route_retry", "tx_id": tx_req_id, "peer_id": peer_id.to_base58(), "attempt": route.tried.len());
+                self.send_tx_to_storage_node(route, peer_id);
+                return;
+            }
+        }
+
+        error!(%tx_req_id, "Failed to send TX to any storage node. Error: {}", e);
+        record_tx_route_failed();
+        discard_tx!(tx_req_id, "storage_route_failed", e.to_string());
+    }
 }
 
-impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<TxHttpRequest> for ClientBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<TxHttpRequest> for ClientBehavior<Tx>
+{
     fn inject_event(&mut self, tx_http_req: TxHttpRequest) {
         let TxHttpRequest { req, shard_id } = tx_http_req;
         trace!(tx_req_id = %req.id(), "Recv TxReq from http.");
-        let discv_query_id = self
-            .discv
-            .find_random_peer(Role::Storage(shard_id), Duration::from_secs(5));
-        self.pending_discv_queries.insert(discv_query_id, req);
+        // The shards served by the storage nodes are not fully known here. Only correct the
+        // shard given by the sender.
+        let shard_id = resolve_tx_shard(&req, shard_id, std::iter::empty());
+        let discv_query_id = self.discv.find_shard_peer(shard_id, Duration::from_secs(5));
+        self.pending_discv_queries
+            .insert(discv_query_id, (req, shard_id));
     }
 }
 
-impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<DiscoveryEvent> for ClientBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<DiscoveryEvent> for ClientBehavior<Tx>
+{
     fn inject_event(&mut self, event: DiscoveryEvent) {
         match event {
             DiscoveryEvent::FindPeerResult { query_id, peer } => {
-                let tx_req = match self.pending_discv_queries.remove(&query_id) {
+                let (tx_req, shard_id) = match self.pending_discv_queries.remove(&query_id) {
                     Some(req) => req,
                     None => return,
                 };
@@ -117,12 +229,18 @@ impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<DiscoveryEvent> for C
 
                 match peer {
                     Ok(peer_id) => {
-                        record_event!("tx_begin", "tx_id": tx_req.id());
-                        let rpc_query_id = self.rpc_client.send_request(&peer_id, tx_req);
-                        self.pending_rpc_queries.insert(rpc_query_id, tx_req_id);
+                        record_event!("tx_begin", "tx_id": tx_req_id);
+                        let route = PendingTxRoute {
+                            req: tx_req,
+                            shard_id,
+                            tried: Vec::with_capacity(MAX_ROUTE_ATTEMPTS),
+                        };
+                        self.send_tx_to_storage_node(route, peer_id);
                     }
                     Err(e) => {
                         error!(%tx_req_id, "Failed to find the storage node. Error: {}", e);
+                        record_tx_route_failed();
+                        discard_tx!(tx_req_id, "no_storage_node", e.to_string());
                     }
                 }
             }
@@ -130,7 +248,7 @@ impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<DiscoveryEvent> for C
     }
 }
 
-impl<Tx: TxTrait + Serialize>
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
     NetworkBehaviourEventProcess<RpcRequestResponseEvent<SignedTxRequest, ()>>
     for ClientBehavior<Tx>
 {
@@ -139,39 +257,61 @@ impl<Tx: TxTrait + Serialize>
             Some(res) => res,
             None => return,
         };
-        let tx_req_id = self
+        let route = self
             .pending_rpc_queries
             .remove(&rpc_query_id)
             .expect("Cannot find tx_req_id");
 
-        if let Err(e) = result {
-            error!(
-                %tx_req_id,
-                "Storage node returns failure. Error: {}", e
-            );
+        match result {
+            Ok(()) => record_tx_routed(),
+            Err(e) => self.retry_tx_on_another_storage_node(route, e),
         }
     }
 }
 
-impl<Tx: TxTrait + Serialize>
-    NetworkBehaviourEventProcess<PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>>
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>>
     for ClientBehavior<Tx>
 {
-    fn inject_event(&mut self, event: PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>) {
+    fn inject_event(&mut self, event: PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>) {
         if let PubSubEvent::BlockProposal(input) = event {
             trace!(
                 height = input.get_block_height().0,
                 txs = input.get_txs().len(),
                 "Recv block proposal."
             );
+            self.sync.observe_height(input.get_block_height());
             self.worker.add_block_proposal(input);
         }
     }
 }
 
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<BlockSyncEvent<Block, Tx>> for ClientBehavior<Tx>
+{
+    fn inject_event(&mut self, event: BlockSyncEvent<Block, Tx>) {
+        let BlockSyncEvent::BlockProposals(proposals) = event;
+        for proposal in proposals {
+            match validate_block_proposal(&proposal) {
+                Ok(_) => self.worker.add_block_proposal(proposal),
+                Err(e) => warn!("Invalid synced block proposal. Error: {}", e),
+            }
+        }
+    }
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> WithRelay for ClientBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
-impl<Tx: TxTrait + Serialize> Shutdown for ClientBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> Shutdown for ClientBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
+        if let Some(grpc_srv) = self.grpc_srv.take() {
+            grpc_srv.shutdown().await?;
+        }
         self.worker.shutdown().await
     }
 }
@@ -0,0 +1,288 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    collections::HashMap,
+};
+use slimchain_utils::prometheus::{self, MetricKind};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// Max number of the stale and orphan blocks kept for a potential reorg.
+pub const MAX_ORPHAN_BLOCKS: usize = 64;
+// Max number of the recent heights whose main chain block is remembered. The blocks below them
+// are dropped as outdated.
+const MAX_TRACKED_HEIGHTS: usize = 1024;
+
+static FORK_STATS: Lazy<ForkStats> = Lazy::new(ForkStats::default);
+
+#[derive(Debug, Default)]
+struct ForkStats {
+    imported: AtomicU64,
+    stale: AtomicU64,
+    orphan: AtomicU64,
+    pooled: AtomicU64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkStatsSnapshot {
+    /// Number of blocks accepted into the main chain.
+    pub imported: u64,
+    /// Number of blocks competing with a main chain block at the same height.
+    pub stale: u64,
+    /// Number of blocks whose parent is not in the main chain.
+    pub orphan: u64,
+    /// Number of blocks in the orphan pool.
+    pub pooled: u64,
+}
+
+impl ForkStatsSnapshot {
+    // The fraction of the received blocks that did not make it into the main chain.
+    pub fn fork_rate(&self) -> f64 {
+        let forked = self.stale + self.orphan;
+        match self.imported + forked {
+            0 => 0.,
+            total => forked as f64 / total as f64,
+        }
+    }
+}
+
+pub fn fork_stats() -> ForkStatsSnapshot {
+    ForkStatsSnapshot {
+        imported: FORK_STATS.imported.load(Ordering::Relaxed),
+        stale: FORK_STATS.stale.load(Ordering::Relaxed),
+        orphan: FORK_STATS.orphan.load(Ordering::Relaxed),
+        pooled: FORK_STATS.pooled.load(Ordering::Relaxed),
+    }
+}
+
+pub fn register_fork_stats() {
+    prometheus::register_value(
+        "slimchain_pow_stale_blocks_total",
+        "Number of the blocks competing with a main chain block at the same height.",
+        MetricKind::Counter,
+        || fork_stats().stale as f64,
+    );
+    prometheus::register_value(
+        "slimchain_pow_orphan_blocks_total",
+        "Number of the blocks whose parent is not in the main chain.",
+        MetricKind::Counter,
+        || fork_stats().orphan as f64,
+    );
+    prometheus::register_value(
+        "slimchain_pow_orphan_pool_size",
+        "Number of the stale and orphan blocks kept for a potential reorg.",
+        MetricKind::Gauge,
+        || fork_stats().pooled as f64,
+    );
+    prometheus::register_value(
+        "slimchain_pow_fork_rate",
+        "Fraction of the received blocks which did not make it into the main chain.",
+        MetricKind::Gauge,
+        || fork_stats().fork_rate(),
+    );
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlockClass {
+    // The next block of the main chain, to be imported.
+    Main,
+    // Already seen in the main chain, e.g., received from both the gossip and the sync.
+    Duplicate,
+    // Another block at a height of the main chain. The first one seen wins, as in Bitcoin.
+    Stale,
+    // Built on a block which is not in the main chain.
+    Orphan,
+    // Below the tracked heights.
+    Outdated,
+}
+
+// Classify the received blocks against the main chain, before they are imported in order.
+// Forwarding a competing block to the import would fail its verification, and the block of
+// the main chain at the same height would then be dropped as outdated.
+#[derive(Debug)]
+pub struct ForkTracker {
+    main: BTreeMap<BlockHeight, H256>,
+}
+
+impl ForkTracker {
+    pub fn new(height: BlockHeight, blk_hash: H256) -> Self {
+        let mut main = BTreeMap::new();
+        main.insert(height, blk_hash);
+        Self { main }
+    }
+
+    pub fn classify(&mut self, height: BlockHeight, blk_hash: H256, prev_hash: H256) -> BlockClass {
+        let class = match self.main.get(&height) {
+            Some(&hash) if hash == blk_hash => BlockClass::Duplicate,
+            Some(_) => BlockClass::Stale,
+            None if self.main.len() >= MAX_TRACKED_HEIGHTS
+                && self.main.keys().next().map_or(false, |&min| height < min) =>
+            {
+                BlockClass::Outdated
+            }
+            None => match height
+                .0
+                .checked_sub(1)
+                .and_then(|prev| self.main.get(&BlockHeight(prev)))
+            {
+                Some(&hash) if hash != prev_hash => BlockClass::Orphan,
+                // The parent may be unknown yet, if the blocks are received out of order.
+                _ => BlockClass::Main,
+            },
+        };
+
+        match class {
+            BlockClass::Main => {
+                self.main.insert(height, blk_hash);
+                while self.main.len() > MAX_TRACKED_HEIGHTS {
+                    let min = *self
+                        .main
+                        .keys()
+                        .next()
+                        .expect("Failed to get the min height.");
+                    self.main.remove(&min);
+                }
+                FORK_STATS.imported.fetch_add(1, Ordering::Relaxed);
+            }
+            BlockClass::Stale => {
+                FORK_STATS.stale.fetch_add(1, Ordering::Relaxed);
+            }
+            BlockClass::Orphan => {
+                FORK_STATS.orphan.fetch_add(1, Ordering::Relaxed);
+            }
+            BlockClass::Duplicate | BlockClass::Outdated => {}
+        }
+        class
+    }
+
+    pub fn tip_height(&self) -> BlockHeight {
+        self.main.keys().next_back().copied().unwrap_or_default()
+    }
+}
+
+// The blocks left out of the main chain. The oldest ones are evicted once it is full.
+#[derive(Debug)]
+pub struct OrphanPool<V> {
+    capacity: usize,
+    blocks: HashMap<H256, (BlockHeight, H256, V)>,
+    order: VecDeque<H256>,
+}
+
+impl<V> OrphanPool<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            blocks: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, height: BlockHeight, blk_hash: H256, prev_hash: H256, blk: V) {
+        if self.capacity == 0 || self.blocks.contains_key(&blk_hash) {
+            return;
+        }
+        while self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.blocks.remove(&oldest);
+            }
+        }
+        self.blocks.insert(blk_hash, (height, prev_hash, blk));
+        self.order.push_back(blk_hash);
+        FORK_STATS
+            .pooled
+            .store(self.blocks.len() as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, blk_hash: &H256) -> Option<&V> {
+        self.blocks.get(blk_hash).map(|(_, _, blk)| blk)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    // The number of the pooled blocks chained up to `blk_hash`, including itself.
+    pub fn branch_len(&self, blk_hash: &H256) -> usize {
+        let mut len = 0;
+        let mut hash = *blk_hash;
+        while let Some((_, prev_hash, _)) = self.blocks.get(&hash) {
+            len += 1;
+            hash = *prev_hash;
+            if len > self.blocks.len() {
+                break;
+            }
+        }
+        len
+    }
+
+    // The height where the branch ending at `blk_hash` forks from the main chain.
+    pub fn fork_height(&self, blk_hash: &H256) -> Option<BlockHeight> {
+        let (height, _, _) = self.blocks.get(blk_hash)?;
+        let len = self.branch_len(blk_hash) as u64;
+        height.0.checked_sub(len).map(BlockHeight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(i: u64) -> H256 {
+        H256::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn test_fork_tracker() {
+        let mut tracker = ForkTracker::new(BlockHeight(0), hash(0));
+        assert_eq!(
+            BlockClass::Main,
+            tracker.classify(BlockHeight(1), hash(1), hash(0))
+        );
+        assert_eq!(
+            BlockClass::Duplicate,
+            tracker.classify(BlockHeight(1), hash(1), hash(0))
+        );
+        assert_eq!(
+            BlockClass::Stale,
+            tracker.classify(BlockHeight(1), hash(11), hash(0))
+        );
+        assert_eq!(
+            BlockClass::Orphan,
+            tracker.classify(BlockHeight(2), hash(12), hash(11))
+        );
+        assert_eq!(
+            BlockClass::Main,
+            tracker.classify(BlockHeight(2), hash(2), hash(1))
+        );
+        // The parent is not received yet.
+        assert_eq!(
+            BlockClass::Main,
+            tracker.classify(BlockHeight(4), hash(4), hash(3))
+        );
+        assert_eq!(BlockHeight(4), tracker.tip_height());
+    }
+
+    #[test]
+    fn test_orphan_pool() {
+        let mut pool = OrphanPool::new(3);
+        pool.insert(BlockHeight(1), hash(11), hash(0), 11);
+        pool.insert(BlockHeight(2), hash(12), hash(11), 12);
+        pool.insert(BlockHeight(3), hash(13), hash(12), 13);
+        assert_eq!(3, pool.branch_len(&hash(13)));
+        assert_eq!(Some(BlockHeight(0)), pool.fork_height(&hash(13)));
+
+        pool.insert(BlockHeight(4), hash(14), hash(13), 14);
+        assert_eq!(3, pool.len());
+        assert!(pool.get(&hash(11)).is_none());
+        assert_eq!(Some(&14), pool.get(&hash(14)));
+        assert_eq!(3, pool.branch_len(&hash(14)));
+        assert_eq!(Some(BlockHeight(1)), pool.fork_height(&hash(14)));
+    }
+}
@@ -0,0 +1,266 @@
+use super::{poll_block_sync, validate_block_proposal};
+use crate::{
+    http::{
+        common::warp_serve,
+        db_rpc::db_rpc_server,
+        light_client_rpc::*,
+        metrics::metrics_server,
+        status::{status_server, StatusService},
+    },
+    p2p::{
+        config::NetworkConfig,
+        control::Shutdown,
+        discovery::{Discovery, DiscoveryEvent},
+        pubsub::{PubSub, PubSubEvent, PubSubTopic},
+        relay::{Relay, WithRelay},
+        sync::{BlockSync, BlockSyncEvent, RecentBlockProposals},
+    },
+};
+use async_trait::async_trait;
+use futures::{
+    channel::{mpsc, oneshot},
+    prelude::*,
+};
+use libp2p::{
+    swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
+    NetworkBehaviour,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockTrait,
+    block_proposal::BlockProposal,
+    consensus::pow::{verify_consensus, Block},
+    db::DBPtr,
+    light_client::HeaderChain,
+    loader::BlockLoaderTrait,
+    role::Role,
+    shard_block::ShardBlock,
+    state_proof::StateProof,
+};
+use slimchain_common::{
+    error::{anyhow, bail, ensure, Error, Result},
+    tx::TxTrait,
+};
+use std::{
+    net::SocketAddr,
+    task::{Context, Poll},
+};
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+// Import the headers of the block proposals received from the gossip or the sync. Each of them is
+// verified by its proof of work against the previous one. The out-of-order ones are dropped, and
+// fetched again by the sync once the import stalls.
+struct HeaderImportWorker {
+    handle: Option<JoinHandle<()>>,
+    blk_tx: mpsc::UnboundedSender<Block>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
+
+impl HeaderImportWorker {
+    fn new(mut chain: HeaderChain<Block>) -> Self {
+        let (blk_tx, mut blk_rx) = mpsc::unbounded::<Block>();
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    Some(blk) = blk_rx.next() => {
+                        let next_height = chain.latest_block().block_height().next_height();
+                        if blk.block_height() != next_height {
+                            continue;
+                        }
+                        if let Err(e) = chain.import_blocks(vec![blk], verify_consensus).await {
+                            warn!("Failed to import block header. Error: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            handle: Some(handle),
+            blk_tx,
+            shutdown_tx: Some(shutdown_tx),
+        }
+    }
+
+    fn add_block(&mut self, blk: Block) {
+        if let Err(e) = self.blk_tx.start_send(blk) {
+            error!("Failed to send block header. Error: {}", e);
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.blk_tx.close_channel();
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            shutdown_tx.send(()).ok();
+        } else {
+            bail!("Already shutdown.");
+        }
+        if let Some(handler) = self.handle.take() {
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
+
+// A client that only keeps the block headers, following the chain through the gossip and the
+// block sync of the PoW network. Unlike in raft, the headers are self-verifying, so they can be
+// taken from any peer. The tx and state proofs are only served by the storage nodes of raft, so
+// the verification endpoints are not supported.
+#[derive(NetworkBehaviour)]
+#[behaviour(poll_method = "poll_inner")]
+pub struct LightClientBehavior<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> {
+    discv: Discovery,
+    pubsub: PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>>,
+    sync: BlockSync<Block, Tx>,
+    #[behaviour(ignore)]
+    worker: HeaderImportWorker,
+    #[behaviour(ignore)]
+    srv: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> LightClientBehavior<Tx> {
+    pub async fn new(db: DBPtr, net_cfg: &NetworkConfig) -> Result<Self> {
+        let keypair = net_cfg.keypair.to_libp2p_keypair();
+        let mut discv = Discovery::new(keypair.public(), Role::LightClient, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
+        discv.add_address_from_net_config(net_cfg);
+        let mut pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::BlockProposal], &[])?
+            .with_block_proposal_validator(validate_block_proposal);
+        pubsub.add_peers_from_net_config(net_cfg);
+
+        let chain = HeaderChain::<Block>::load_from_db(db.clone())?;
+        let latest_block_header = chain.latest_block_header();
+        info!(
+            "Start syncing block headers from #{}.",
+            latest_block_header.get_height()
+        );
+        // It serves no block proposals to the others, as the tx bodies are not kept.
+        let sync = BlockSync::new(RecentBlockProposals::new(0))
+            .with_latest_block_header(latest_block_header.clone());
+        let status =
+            StatusService::new::<Block>(Role::LightClient, db.clone(), latest_block_header.clone())
+                .with_peer_counts(discv.peer_counts());
+        let worker = HeaderImportWorker::new(chain);
+
+        let light_client_rpc_srv = {
+            let latest_block_header_copy = latest_block_header.clone();
+            let db_copy = db.clone();
+            light_client_rpc_server(
+                move || latest_block_header_copy.get_height(),
+                move |height| {
+                    ensure!(
+                        height <= latest_block_header.get_height(),
+                        "Block #{} has not been synced yet.",
+                        height
+                    );
+                    let blk: Block = db_copy.get_block(height)?;
+                    Ok(blk.block_header().clone())
+                },
+                |_: VerifyTxHttpRequest| {
+                    future::err::<Tx, Error>(anyhow!(
+                        "Tx verification is only supported in raft consensus."
+                    ))
+                },
+                |_: VerifyStateHttpRequest| {
+                    future::err::<StateProof, Error>(anyhow!(
+                        "State verification is only supported in raft consensus."
+                    ))
+                },
+            )
+        };
+
+        info!("Create http server, listen on {}", net_cfg.http_listen);
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
+        let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
+        let srv = warp_serve!(
+            light_client_rpc_srv
+                .or(db_rpc_server(db))
+                .or(status_server(status))
+                .or(metrics_server()),
+            listen_addrs,
+            net_cfg.tls.as_ref(),
+            async {
+                srv_shutdown_rx.await.ok();
+            }
+        );
+        let srv_handle = tokio::spawn(srv);
+
+        Ok(Self {
+            discv,
+            pubsub,
+            sync,
+            worker,
+            srv: Some((srv_shutdown_tx, srv_handle)),
+        })
+    }
+
+    pub fn discv_mut(&mut self) -> &mut Discovery {
+        &mut self.discv
+    }
+
+    fn poll_inner<T>(
+        &mut self,
+        cx: &mut Context,
+        _: &mut impl PollParameters,
+    ) -> Poll<NetworkBehaviourAction<T, ()>> {
+        poll_block_sync(&mut self.sync, &self.discv, cx);
+
+        Poll::Pending
+    }
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<DiscoveryEvent> for LightClientBehavior<Tx>
+{
+    fn inject_event(&mut self, _: DiscoveryEvent) {}
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>>
+    for LightClientBehavior<Tx>
+{
+    fn inject_event(&mut self, event: PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>) {
+        if let PubSubEvent::BlockProposal(input) = event {
+            self.sync.observe_height(input.get_block_height());
+            self.worker.add_block(input.get_block().clone());
+        }
+    }
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<BlockSyncEvent<Block, Tx>> for LightClientBehavior<Tx>
+{
+    fn inject_event(&mut self, event: BlockSyncEvent<Block, Tx>) {
+        let BlockSyncEvent::BlockProposals(proposals) = event;
+        for proposal in proposals {
+            match validate_block_proposal(&proposal) {
+                Ok(_) => self.worker.add_block(proposal.get_block().clone()),
+                Err(e) => warn!("Invalid synced block proposal. Error: {}", e),
+            }
+        }
+    }
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> WithRelay for LightClientBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
+#[async_trait]
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> Shutdown for LightClientBehavior<Tx> {
+    async fn shutdown(&mut self) -> Result<()> {
+        if let Some((shutdown_tx, handler)) = self.srv.take() {
+            shutdown_tx.send(()).ok();
+            handler.await?;
+        }
+        self.worker.shutdown().await
+    }
+}
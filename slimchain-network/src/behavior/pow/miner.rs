@@ -1,40 +1,65 @@
-use super::BlockProposalWorker;
-use crate::p2p::{
-    config::NetworkConfig,
-    control::Shutdown,
-    discovery::{Discovery, DiscoveryEvent},
-    pubsub::{PubSub, PubSubEvent, PubSubTopic},
+use super::{validate_shard_block, BlockProposalWorker};
+use crate::{
+    http::{metrics::spawn_metrics_server, mining_rpc::spawn_mining_server, status::StatusService},
+    p2p::{
+        config::NetworkConfig,
+        control::Shutdown,
+        discovery::{Discovery, DiscoveryEvent},
+        pubsub::{PubSub, PubSubEvent, PubSubTopic},
+        relay::{Relay, WithRelay},
+        sync::{BlockSync, BlockSyncEvent, RecentBlockProposals},
+    },
 };
 use async_trait::async_trait;
+use futures::prelude::*;
+use futures_timer::Delay;
 use libp2p::{
     swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
     NetworkBehaviour,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     block_proposal::BlockProposal,
     config::{ChainConfig, MinerConfig},
-    consensus::pow::Block,
+    consensus::pow::{Block, ExternalMiner},
     db::DBPtr,
     latest::LatestTxCount,
     role::Role,
+    shard_block::ShardBlock,
     snapshot::Snapshot,
 };
 use slimchain_common::{error::Result, tx::TxTrait};
-use slimchain_tx_state::{TxProposal, TxTrie};
+use slimchain_tx_state::TxTrie;
 use slimchain_utils::record_event;
-use std::task::{Context, Poll};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+// How often the miner checks for storage shards it has not subscribed to yet.
+const SHARD_SUBSCRIBE_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(NetworkBehaviour)]
 #[behaviour(poll_method = "poll_inner")]
-pub struct MinerBehavior<Tx: TxTrait + Serialize + 'static> {
+pub struct MinerBehavior<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> {
     discv: Discovery,
-    pubsub: PubSub<TxProposal<Tx>, BlockProposal<Block, Tx>>,
+    pubsub: PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>>,
+    sync: BlockSync<Block, Tx>,
     #[behaviour(ignore)]
     worker: BlockProposalWorker<Tx>,
+    #[behaviour(ignore)]
+    recent: RecentBlockProposals<Block, Tx>,
+    #[behaviour(ignore)]
+    next_shard_subscribe: Delay,
+    #[behaviour(ignore)]
+    metrics_srv: Option<JoinHandle<()>>,
+    #[behaviour(ignore)]
+    mining_srv: Option<JoinHandle<()>>,
 }
 
-impl<Tx: TxTrait + Serialize + 'static> MinerBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> MinerBehavior<Tx> {
     pub async fn new(
         db: DBPtr,
         chain_cfg: &ChainConfig,
@@ -42,12 +67,42 @@ impl<Tx: TxTrait + Serialize + 'static> MinerBehavior<Tx> {
         net_cfg: &NetworkConfig,
     ) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Miner, net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
-        let pubsub = PubSub::new(keypair, &[PubSubTopic::TxProposal], &[])?;
+        let pubsub = PubSub::new(keypair, &net_cfg.pubsub, &[PubSubTopic::TxProposal], &[])?
+            .with_tx_proposal_validator(validate_shard_block);
         let snapshot = Snapshot::<Block, TxTrie>::load_from_db(&db, chain_cfg.state_len)?;
         let latest_block_header = snapshot.to_latest_block_header();
         let latest_tx_count = LatestTxCount::new(0);
+
+        let metrics_srv = match net_cfg.metrics_listen.as_deref() {
+            Some(endpoint) => {
+                let status = StatusService::new::<Block>(
+                    Role::Miner,
+                    db.clone(),
+                    latest_block_header.clone(),
+                )
+                .with_peer_counts(discv.peer_counts());
+                Some(spawn_metrics_server(
+                    endpoint,
+                    net_cfg.tls.as_ref(),
+                    status,
+                )?)
+            }
+            None => None,
+        };
+
+        let (external_miner, mining_srv) = match net_cfg.mining_listen.as_deref() {
+            Some(endpoint) => {
+                let miner = ExternalMiner::new();
+                let srv = spawn_mining_server(endpoint, net_cfg.tls.as_ref(), miner.clone())?;
+                (Some(miner), Some(srv))
+            }
+            None => (None, None),
+        };
+
         let worker = BlockProposalWorker::new(
             chain_cfg.clone(),
             miner_cfg.clone(),
@@ -55,49 +110,108 @@ impl<Tx: TxTrait + Serialize + 'static> MinerBehavior<Tx> {
             latest_block_header,
             latest_tx_count,
             db,
+            external_miner,
         );
 
+        // The miner only serves its own block proposals to the peers.
+        let recent = RecentBlockProposals::default();
+        let sync = BlockSync::new(recent.clone());
+
         Ok(Self {
             discv,
             pubsub,
+            sync,
             worker,
+            recent,
+            next_shard_subscribe: Delay::new(Duration::from_secs(0)),
+            metrics_srv,
+            mining_srv,
         })
     }
 
+    // The tx proposals are gossiped per shard. Subscribe to every shard served by the storage
+    // nodes known so far.
+    fn subscribe_storage_shards(&mut self) {
+        let shards: Vec<_> = self
+            .discv
+            .known_roles()
+            .filter_map(|role| match role {
+                Role::Storage(shard_id) => Some(PubSubTopic::tx_proposal(*shard_id)),
+                _ => None,
+            })
+            .filter(|topic| !self.pubsub.is_subscribed(*topic))
+            .collect();
+        for topic in shards {
+            if let Err(e) = self.pubsub.subscribe(topic) {
+                warn!("Failed to subscribe to {:?}. Error: {}", topic, e);
+            }
+        }
+    }
+
     fn poll_inner<T>(
         &mut self,
         cx: &mut Context,
         _: &mut impl PollParameters,
     ) -> Poll<NetworkBehaviourAction<T, ()>> {
+        while Pin::new(&mut self.next_shard_subscribe).poll(cx).is_ready() {
+            self.subscribe_storage_shards();
+            self.next_shard_subscribe = Delay::new(SHARD_SUBSCRIBE_INTERVAL);
+        }
+
         if let Poll::Ready(blk_proposal) = self.worker.poll_block_proposal(cx) {
             self.pubsub
                 .publish_block_proposal(&blk_proposal)
                 .expect("Failed to publish block proposal.");
+            self.recent.insert(blk_proposal);
         }
 
         Poll::Pending
     }
 }
 
-impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<DiscoveryEvent> for MinerBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<DiscoveryEvent> for MinerBehavior<Tx>
+{
     fn inject_event(&mut self, _: DiscoveryEvent) {}
 }
 
-impl<Tx: TxTrait + Serialize>
-    NetworkBehaviourEventProcess<PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>>
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<BlockSyncEvent<Block, Tx>> for MinerBehavior<Tx>
+{
+    fn inject_event(&mut self, _: BlockSyncEvent<Block, Tx>) {}
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>>
     for MinerBehavior<Tx>
 {
-    fn inject_event(&mut self, event: PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>) {
+    fn inject_event(&mut self, event: PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>) {
         if let PubSubEvent::TxProposal(input) = event {
-            record_event!("miner_recv_tx", "tx_id": input.tx.id());
-            self.worker.add_tx_proposal(input);
+            let shard_id = input.shard_id();
+            record_event!("miner_recv_shard_block", "shard_id": shard_id, "txs": input.len());
+            for tx_proposal in input.into_tx_proposals() {
+                record_event!("miner_recv_tx", "tx_id": tx_proposal.tx.id());
+                self.worker.add_tx_proposal(tx_proposal);
+            }
         }
     }
 }
 
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> WithRelay for MinerBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
-impl<Tx: TxTrait + Serialize> Shutdown for MinerBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> Shutdown for MinerBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
+        if let Some(metrics_srv) = self.metrics_srv.take() {
+            metrics_srv.abort();
+        }
+        if let Some(mining_srv) = self.mining_srv.take() {
+            mining_srv.abort();
+        }
         self.worker.shutdown().await
     }
 }
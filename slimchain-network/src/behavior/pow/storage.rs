@@ -1,28 +1,40 @@
-use super::BlockImportWorker;
-use crate::p2p::{
-    config::NetworkConfig,
-    control::Shutdown,
-    discovery::{Discovery, DiscoveryEvent},
-    pubsub::{PubSub, PubSubEvent, PubSubTopic},
-    rpc::{
-        create_request_response_server, handle_request_response_server_event, RpcInstant,
-        RpcRequestResponseEvent,
+use super::{poll_block_sync, validate_block_proposal, validate_shard_block, BlockImportWorker};
+use crate::{
+    http::{metrics::spawn_metrics_server, status::StatusService},
+    p2p::{
+        config::NetworkConfig,
+        control::Shutdown,
+        discovery::{Discovery, DiscoveryEvent},
+        pubsub::{PubSub, PubSubEvent, PubSubTopic},
+        relay::{Relay, WithRelay},
+        rpc::{
+            create_request_response_server, handle_request_response_server_event, RpcInstant,
+            RpcRequestResponseEvent,
+        },
+        sync::{BlockSync, BlockSyncEvent, RecentBlockProposals},
     },
 };
 use async_trait::async_trait;
-use futures::{channel::mpsc, prelude::*};
+use futures::{channel::mpsc, prelude::*, stream::ReadyChunks};
 use libp2p::{
     swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
     NetworkBehaviour,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use slimchain_chain::{
-    behavior::TxExecuteStream, block_proposal::BlockProposal, config::ChainConfig,
-    consensus::pow::Block, db::DBPtr, latest::LatestTxCount, role::Role, snapshot::Snapshot,
+    behavior::TxExecuteStream,
+    block_proposal::BlockProposal,
+    config::{ChainConfig, ShardBlockConfig},
+    consensus::pow::Block,
+    db::DBPtr,
+    latest::LatestTxCount,
+    role::Role,
+    shard_block::ShardBlock,
+    snapshot::Snapshot,
 };
 use slimchain_common::{basic::ShardId, error::Result, tx::TxTrait, tx_req::SignedTxRequest};
 use slimchain_tx_engine::TxEngine;
-use slimchain_tx_state::{StorageTxTrie, TxProposal};
+use slimchain_tx_state::StorageTxTrie;
 use slimchain_utils::record_event;
 use std::{
     pin::Pin,
@@ -32,24 +44,30 @@ use std::{
     },
     task::{Context, Poll},
 };
+use tokio::task::JoinHandle;
 
 #[derive(NetworkBehaviour)]
 #[behaviour(poll_method = "poll_inner")]
-pub struct StorageBehavior<Tx: TxTrait + Serialize + 'static> {
+pub struct StorageBehavior<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> {
     discv: Discovery,
-    pubsub: PubSub<TxProposal<Tx>, BlockProposal<Block, Tx>>,
+    pubsub: PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>>,
     rpc_server: RpcInstant<SignedTxRequest, ()>,
+    sync: BlockSync<Block, Tx>,
     #[behaviour(ignore)]
     import_worker: BlockImportWorker<Tx>,
     #[behaviour(ignore)]
     tx_req_tx: mpsc::UnboundedSender<SignedTxRequest>,
     #[behaviour(ignore)]
-    tx_exec_stream: TxExecuteStream<Tx, mpsc::UnboundedReceiver<SignedTxRequest>>,
+    tx_exec_stream: ReadyChunks<TxExecuteStream<Tx, mpsc::UnboundedReceiver<SignedTxRequest>>>,
     #[behaviour(ignore)]
     tx_engine_shutdown_token: Arc<AtomicBool>,
+    #[behaviour(ignore)]
+    shard_id: ShardId,
+    #[behaviour(ignore)]
+    metrics_srv: Option<JoinHandle<()>>,
 }
 
-impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> StorageBehavior<Tx> {
     pub async fn new(
         db: DBPtr,
         engine: TxEngine<Tx>,
@@ -58,14 +76,19 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
         net_cfg: &NetworkConfig,
     ) -> Result<Self> {
         let keypair = net_cfg.keypair.to_libp2p_keypair();
-        let mut discv =
-            Discovery::new(keypair.public(), Role::Storage(shard_id), net_cfg.mdns).await?;
+        let mut discv = Discovery::new(keypair.public(), Role::Storage(shard_id), net_cfg.mdns)
+            .await?
+            .with_conn_limit(&net_cfg.conn_limit);
         discv.add_address_from_net_config(net_cfg);
+        // Only the tx proposals of its own shard are relayed.
         let mut pubsub = PubSub::new(
             keypair,
+            &net_cfg.pubsub,
             &[PubSubTopic::BlockProposal],
-            &[PubSubTopic::TxProposal],
-        )?;
+            &[PubSubTopic::tx_proposal(shard_id)],
+        )?
+        .with_tx_proposal_validator(validate_shard_block)
+        .with_block_proposal_validator(validate_block_proposal);
         pubsub.add_peers_from_net_config(net_cfg);
         let rpc_server = create_request_response_server("/tx_req/1");
         let snapshot =
@@ -75,7 +98,34 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
 
         let tx_engine_shutdown_token = engine.shutdown_token();
         let (tx_req_tx, tx_req_rx) = mpsc::unbounded::<SignedTxRequest>();
-        let tx_exec_stream = TxExecuteStream::new(tx_req_rx, engine, &db, &latest_block_header);
+        // The tx proposals ready at once are gossiped together as a shard block.
+        let tx_exec_stream = TxExecuteStream::new(tx_req_rx, engine, &db, &latest_block_header)
+            .ready_chunks(ShardBlockConfig::get().batch_size());
+
+        let metrics_srv = match net_cfg.metrics_listen.as_deref() {
+            Some(endpoint) => {
+                let status = StatusService::new::<Block>(
+                    Role::Storage(shard_id),
+                    db.clone(),
+                    latest_block_header.clone(),
+                )
+                .with_peer_counts(discv.peer_counts());
+                Some(spawn_metrics_server(
+                    endpoint,
+                    net_cfg.tls.as_ref(),
+                    status,
+                )?)
+            }
+            None => None,
+        };
+
+        let recent = RecentBlockProposals::default();
+        let mut sync =
+            BlockSync::new(recent.clone()).with_latest_block_header(latest_block_header.clone());
+        // Only the full state can rebuild the older block proposals.
+        if shard_id.is_full_shard() {
+            sync = sync.with_db(db.clone());
+        }
 
         let import_worker = BlockImportWorker::new(
             true,
@@ -84,6 +134,7 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
             latest_block_header,
             latest_tx_count,
             db,
+            recent,
             |snapshot| snapshot.write_db_tx(),
         );
 
@@ -91,10 +142,13 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
             discv,
             pubsub,
             rpc_server,
+            sync,
             import_worker,
             tx_req_tx,
             tx_exec_stream,
             tx_engine_shutdown_token,
+            shard_id,
+            metrics_srv,
         })
     }
 
@@ -102,7 +156,7 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
         &mut self.discv
     }
 
-    pub fn pubsub_mut(&mut self) -> &mut PubSub<TxProposal<Tx>, BlockProposal<Block, Tx>> {
+    pub fn pubsub_mut(&mut self) -> &mut PubSub<ShardBlock<Tx>, BlockProposal<Block, Tx>> {
         &mut self.pubsub
     }
 
@@ -111,21 +165,26 @@ impl<Tx: TxTrait + Serialize + 'static> StorageBehavior<Tx> {
         cx: &mut Context,
         _: &mut impl PollParameters,
     ) -> Poll<NetworkBehaviourAction<T, ()>> {
-        if let Poll::Ready(Some(tx_proposal)) = Pin::new(&mut self.tx_exec_stream).poll_next(cx) {
+        if let Poll::Ready(Some(tx_proposals)) = Pin::new(&mut self.tx_exec_stream).poll_next(cx) {
+            let shard_block = ShardBlock::new(self.shard_id, tx_proposals);
             self.pubsub
-                .publish_tx_proposal(&tx_proposal)
-                .expect("Failed to publish tx proposal.");
+                .publish_shard_tx_proposal(&shard_block, self.shard_id)
+                .expect("Failed to publish shard block.");
         }
 
+        poll_block_sync(&mut self.sync, &self.discv, cx);
+
         Poll::Pending
     }
 }
 
-impl<Tx: TxTrait + Serialize> NetworkBehaviourEventProcess<DiscoveryEvent> for StorageBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<DiscoveryEvent> for StorageBehavior<Tx>
+{
     fn inject_event(&mut self, _: DiscoveryEvent) {}
 }
 
-impl<Tx: TxTrait + Serialize>
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
     NetworkBehaviourEventProcess<RpcRequestResponseEvent<SignedTxRequest, ()>>
     for StorageBehavior<Tx>
 {
@@ -143,27 +202,51 @@ impl<Tx: TxTrait + Serialize>
     }
 }
 
-impl<Tx: TxTrait + Serialize>
-    NetworkBehaviourEventProcess<PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>>
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>>
     for StorageBehavior<Tx>
 {
-    fn inject_event(&mut self, event: PubSubEvent<TxProposal<Tx>, BlockProposal<Block, Tx>>) {
+    fn inject_event(&mut self, event: PubSubEvent<ShardBlock<Tx>, BlockProposal<Block, Tx>>) {
         if let PubSubEvent::BlockProposal(input) = event {
             trace!(
                 height = input.get_block_height().0,
                 txs = input.get_txs().len(),
                 "Recv block proposal."
             );
+            self.sync.observe_height(input.get_block_height());
             self.import_worker.add_block_proposal(input);
         }
     }
 }
 
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>>
+    NetworkBehaviourEventProcess<BlockSyncEvent<Block, Tx>> for StorageBehavior<Tx>
+{
+    fn inject_event(&mut self, event: BlockSyncEvent<Block, Tx>) {
+        let BlockSyncEvent::BlockProposals(proposals) = event;
+        for proposal in proposals {
+            match validate_block_proposal(&proposal) {
+                Ok(_) => self.import_worker.add_block_proposal(proposal),
+                Err(e) => warn!("Invalid synced block proposal. Error: {}", e),
+            }
+        }
+    }
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> WithRelay for StorageBehavior<Tx> {
+    fn set_relay(&mut self, relay: Relay) {
+        self.discv.set_relay(relay);
+    }
+}
+
 #[async_trait]
-impl<Tx: TxTrait + Serialize> Shutdown for StorageBehavior<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> Shutdown for StorageBehavior<Tx> {
     async fn shutdown(&mut self) -> Result<()> {
         self.tx_req_tx.close_channel();
         self.tx_engine_shutdown_token.store(true, Ordering::Release);
+        if let Some(metrics_srv) = self.metrics_srv.take() {
+            metrics_srv.abort();
+        }
         self.import_worker.shutdown().await
     }
 }
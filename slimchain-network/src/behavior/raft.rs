@@ -1,7 +1,13 @@
+pub mod auditor;
 pub mod client;
 pub mod client_block_proposal;
+pub mod client_bootstrap;
+pub mod client_leader_transfer;
+pub mod client_learner;
 pub mod client_network;
 pub mod client_storage;
+pub mod light_client;
 pub mod message;
 pub mod storage;
+pub mod storage_batch;
 pub mod utils;
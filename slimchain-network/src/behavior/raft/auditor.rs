@@ -0,0 +1,238 @@
+use crate::http::{
+    auditor_rpc::*,
+    common::*,
+    config::NetworkConfig,
+    db_rpc::*,
+    metrics::metrics_server,
+    node_rpc::*,
+    status::{status_server, PeerCounts, StatusService},
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    prelude::*,
+    stream,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    audit::{audit_block, commit_audit, get_audit_attestation, get_audited_height},
+    block_proposal::BlockProposal,
+    config::ChainConfig,
+    consensus::raft::{verify_consensus, Block},
+    db::{DBPtr, Transaction as DBTransaction},
+    latest::LatestBlockHeaderPtr,
+    role::Role,
+    snapshot::Snapshot,
+};
+use slimchain_common::{
+    ed25519::{keypair_serde_impl, Keypair},
+    error::{bail, Result},
+    tx::TxTrait,
+    utils::hex,
+};
+use slimchain_tx_state::TxTrie;
+use slimchain_utils::{ordered_stream::OrderedStream, record_event};
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+#[derive(Serialize, Deserialize)]
+struct AuditorKeypair(#[serde(with = "keypair_serde_impl")] Keypair);
+
+// The keypair used to sign the audit attestations. It is generated on the first run and kept
+// in the database afterwards.
+fn load_or_create_keypair(db: &DBPtr) -> Result<Keypair> {
+    if let Some(AuditorKeypair(keypair)) = db.get_meta_object("auditor-keypair")? {
+        return Ok(keypair);
+    }
+
+    let mut rng = rand::thread_rng();
+    let keypair = AuditorKeypair(Keypair::generate(&mut rng));
+    let mut db_tx = DBTransaction::new();
+    db_tx.insert_meta_object("auditor-keypair", &keypair)?;
+    db.write_sync(db_tx)?;
+    Ok(keypair.0)
+}
+
+struct AuditWorker<Tx: TxTrait + 'static> {
+    handle: Option<JoinHandle<()>>,
+    blk_tx: mpsc::UnboundedSender<BlockProposal<Block, Tx>>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
+
+impl<Tx: TxTrait + Serialize> AuditWorker<Tx> {
+    fn new(
+        chain_cfg: ChainConfig,
+        mut snapshot: Snapshot<Block, TxTrie>,
+        latest_block_header: LatestBlockHeaderPtr,
+        keypair: Keypair,
+        db: DBPtr,
+    ) -> Self {
+        let (blk_tx, blk_rx) = mpsc::unbounded::<BlockProposal<Block, Tx>>();
+        let mut blk_rx = OrderedStream::new(
+            blk_rx.map(|blk| (blk.get_block_height(), blk)),
+            latest_block_header.get_height().next_height(),
+            |height| height.next_height(),
+        );
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    Some(blk_proposal) = blk_rx.next() => {
+                        let attestation = audit_block(
+                            &chain_cfg,
+                            &mut snapshot,
+                            &blk_proposal,
+                            verify_consensus,
+                            &keypair,
+                        )
+                        .await;
+
+                        if !attestation.valid {
+                            error!(height = %attestation.height, "Block proposal failed the audit.");
+                        }
+
+                        if let Err(e) =
+                            commit_audit(&blk_proposal, &attestation, &db, &latest_block_header)
+                                .await
+                        {
+                            if let Ok(db_tx) = snapshot.write_db_tx() {
+                                db.write_async(db_tx).await.ok();
+                            }
+                            panic!("Failed to commit the audit. Error: {}", e);
+                        }
+                    }
+                }
+            }
+
+            db.write_async(
+                snapshot
+                    .write_db_tx()
+                    .expect("Failed to save the snapshot."),
+            )
+            .await
+            .expect("Failed to save the snapshot.");
+        });
+
+        Self {
+            handle: Some(handle),
+            blk_tx,
+            shutdown_tx: Some(shutdown_tx),
+        }
+    }
+
+    fn get_blk_tx(&self) -> mpsc::UnboundedSender<BlockProposal<Block, Tx>> {
+        self.blk_tx.clone()
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.blk_tx.close_channel();
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            shutdown_tx.send(()).ok();
+        } else {
+            bail!("Already shutdown.");
+        }
+        if let Some(handler) = self.handle.take() {
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct AuditorNodeReqError(mpsc::SendError);
+
+impl warp::reject::Reject for AuditorNodeReqError {}
+
+// A node that independently re-verifies every block proposal broadcast by the raft leader,
+// and publishes a signed attestation for each of them.
+pub struct AuditorNode<Tx: TxTrait + 'static> {
+    srv: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+    audit_worker: AuditWorker<Tx>,
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> AuditorNode<Tx> {
+    pub async fn new(db: DBPtr, chain_cfg: &ChainConfig, net_cfg: &NetworkConfig) -> Result<Self> {
+        let keypair = load_or_create_keypair(&db)?;
+        info!(
+            "Auditor public key: {}",
+            hex::encode(keypair.public.as_bytes())
+        );
+
+        let snapshot = Snapshot::<Block, TxTrie>::load_from_db(&db, chain_cfg.state_len)?;
+        let latest_block_header = snapshot.to_latest_block_header();
+
+        let status_srv = status_server(
+            StatusService::new::<Block>(Role::Auditor, db.clone(), latest_block_header.clone())
+                .with_peer_counts(PeerCounts::from_route_table(&net_cfg.to_route_table())),
+        );
+
+        let audit_worker = AuditWorker::new(
+            chain_cfg.clone(),
+            snapshot,
+            latest_block_header,
+            keypair,
+            db.clone(),
+        );
+        let audit_worker_blk_tx = audit_worker.get_blk_tx();
+
+        let block_import_srv = warp::post()
+            .and(warp::path(AUDITOR_BLOCK_IMPORT_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(move |block_proposals: Vec<BlockProposal<Block, Tx>>| {
+                record_event!("auditor_recv_block", "heights": block_proposals.iter().map(|b| b.get_block_height()).collect::<Vec<_>>());
+                let mut audit_worker_blk_tx = audit_worker_blk_tx.clone();
+                async move {
+                    audit_worker_blk_tx
+                        .send_all(&mut stream::iter(block_proposals).map(Ok))
+                        .await
+                        .map(|_| warp_reply_binary(&()))
+                        .map_err(|e| warp::reject::custom(AuditorNodeReqError(e)))
+                }
+            });
+
+        let db_rpc_srv = db_rpc_server(db.clone());
+
+        let auditor_rpc_srv = {
+            let db_copy = db.clone();
+            auditor_rpc_server(
+                move || get_audited_height(&db_copy).unwrap_or_default(),
+                move |height| get_audit_attestation(&db, height),
+            )
+        };
+
+        info!("Create http server, listen on {}", net_cfg.http_listen);
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
+        let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
+        let routes = auditor_rpc_srv
+            .or(db_rpc_srv)
+            .or(status_srv)
+            .or(metrics_server())
+            .or(warp::path(NODE_RPC_ROUTE_PATH).and(block_import_srv));
+        let srv = warp_serve!(routes, listen_addrs, net_cfg.tls.as_ref(), async {
+            srv_shutdown_rx.await.ok();
+        });
+        let srv_handle = tokio::spawn(srv);
+
+        Ok(Self {
+            srv: Some((srv_shutdown_tx, srv_handle)),
+            audit_worker,
+        })
+    }
+
+    pub async fn shutdown(&mut self) -> Result<()> {
+        info!("Shutting down AuditWorker...");
+        self.audit_worker.shutdown().await?;
+        info!("Shutting down HTTP Server...");
+        if let Some((shutdown_tx, handler)) = self.srv.take() {
+            shutdown_tx.send(()).ok();
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
@@ -1,41 +1,63 @@
 use crate::{
     behavior::raft::{
         client_block_proposal::BlockProposalWorker,
+        client_bootstrap::bootstrap_from_snapshot,
+        client_leader_transfer::{transfer_leader, LeaderTransferRequest},
+        client_learner::{LearnerWorker, Learners},
         client_network::{ClientNodeNetwork, ClientNodeNetworkWorker},
         client_storage::ClientNodeStorage,
         message::{NewBlockRequest, NewBlockResponse},
-        utils::{get_current_leader, node_is_leader},
+        utils::{
+            change_membership, current_members, get_current_leader, leader_read_index,
+            linearizable_read_barrier, node_is_leader,
+        },
     },
+    control::ShutdownCoordinator,
+    grpc::server::{spawn_node_rpc_server, NodeRpcServerHandle, NodeRpcService},
     http::{
+        admin::{raft_admin_server, shutdown_admin_server, MembershipChange, RaftMembership},
+        admission::Admission,
         client_rpc::*,
         common::*,
         config::{NetworkConfig, RaftConfig},
+        db_rpc::*,
+        metrics::{metrics_server, register_queue_depth},
         node_rpc::*,
+        status::{status_server, PeerCounts, StatusService},
+        subscription::subscription_server,
     },
 };
 use async_raft::{
     error::{InitializeError, RaftError},
+    raft::{VoteRequest, VoteResponse},
     Raft,
 };
 use futures::{channel::oneshot, prelude::*, stream};
 use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     config::{ChainConfig, MinerConfig},
+    consensus::raft::Block,
     db::DBPtr,
+    role::Role,
 };
 use slimchain_common::{
+    basic::{BlockHeight, H256},
     error::{anyhow, bail, Error, Result},
     tx::TxTrait,
 };
 use slimchain_tx_state::TxProposal;
 use slimchain_utils::record_event;
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::task::JoinHandle;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 pub type ClientNodeRaft<Tx> =
     Raft<NewBlockRequest<Tx>, NewBlockResponse, ClientNodeNetwork<Tx>, ClientNodeStorage<Tx>>;
 
+// The leader transfer fails if the target is not elected within this number of the max
+// election timeouts.
+const LEADER_TRANSFER_ELECTIONS: u64 = 10;
+
 #[derive(Debug)]
 enum ClientNodeError {
     RaftError(RaftError),
@@ -48,8 +70,12 @@ pub struct ClientNode<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'sta
     raft_storage: Arc<ClientNodeStorage<Tx>>,
     raft: Option<Arc<ClientNodeRaft<Tx>>>,
     srv: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+    grpc_srv: Option<NodeRpcServerHandle>,
     proposal_worker: BlockProposalWorker<Tx>,
     network_worker: ClientNodeNetworkWorker<Tx>,
+    learner_worker: LearnerWorker,
+    admission: Arc<Admission>,
+    coordinator: ShutdownCoordinator,
 }
 
 impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<Tx> {
@@ -62,19 +88,40 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
     ) -> Result<Self> {
         let net_route_table = net_cfg.to_route_table();
         let peer_id = net_route_table.peer_id();
-        let all_peers = net_route_table.all_client_peer_ids();
+        let initial_members = raft_cfg.initial_members(&net_route_table);
+
+        if raft_cfg.snapshot_bootstrap {
+            if let Err(e) = bootstrap_from_snapshot::<Tx>(&db, chain_cfg, &net_route_table).await {
+                warn!(
+                    "Failed to bootstrap from snapshot, start from the local database instead. Error: {}",
+                    e
+                );
+            }
+        }
 
         let raft_storage = Arc::new(ClientNodeStorage::new(db, chain_cfg, net_cfg)?);
-        let raft_network = Arc::new(ClientNodeNetwork::new(net_route_table));
+        let raft_network = Arc::new(
+            ClientNodeNetwork::new(net_route_table.clone())
+                .with_tx_redundancy(net_cfg.tx_redundancy),
+        );
+        let raft_config = raft_cfg.to_raft_config()?;
+        let heartbeat_interval = Duration::from_millis(raft_config.heartbeat_interval);
+        let leader_transfer_timeout =
+            Duration::from_millis(raft_config.election_timeout_max * LEADER_TRANSFER_ELECTIONS);
         let raft = Arc::new(ClientNodeRaft::new(
             peer_id.into(),
-            raft_cfg.to_raft_config()?,
+            raft_config,
             raft_network.clone(),
             raft_storage.clone(),
         ));
 
-        let network_worker =
-            ClientNodeNetworkWorker::new(raft_network.clone(), raft_cfg.async_broadcast_storage);
+        let admission = Arc::new(Admission::new(&net_cfg.admission));
+        register_queue_depth(admission.queue_depth());
+        let network_worker = ClientNodeNetworkWorker::new(
+            raft_network.clone(),
+            raft_cfg.async_broadcast_storage,
+            admission.queue_depth(),
+        );
 
         let proposal_worker = BlockProposalWorker::new(
             chain_cfg,
@@ -90,7 +137,14 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
             let network_worker_req_tx = network_worker.get_req_tx();
             let raft_storage_copy1 = raft_storage.clone();
             let raft_storage_copy2 = raft_storage.clone();
-            client_rpc_server(
+            let raft_storage_copy3 = raft_storage.clone();
+            let raft_copy1 = raft.clone();
+            let raft_copy2 = raft.clone();
+            let raft_copy3 = raft.clone();
+            let route_table = raft_network.route_table().clone();
+            let route_table_copy = route_table.clone();
+            raft_client_rpc_server(
+                admission.clone(),
                 move |reqs: Vec<TxHttpRequest>| {
                     let mut network_worker_req_tx = network_worker_req_tx.clone();
                     async move {
@@ -103,6 +157,18 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                 },
                 move || raft_storage_copy1.latest_tx_count().get(),
                 move || raft_storage_copy2.latest_block_header().get_height(),
+                move |tx_id| raft_storage_copy3.db().get_tx_receipt(tx_id),
+                move || {
+                    let leader = raft_copy1.metrics().borrow().current_leader?;
+                    route_table.peer_address(leader.into()).ok().cloned()
+                },
+                move || node_is_leader(raft_copy2.as_ref()),
+                Arc::new(move || {
+                    let raft = raft_copy3.clone();
+                    let route_table = route_table_copy.clone();
+                    async move { linearizable_read_barrier(raft.as_ref(), &route_table).await }
+                        .boxed()
+                }),
             )
         };
 
@@ -138,12 +204,24 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                 });
 
             let raft_copy = raft.clone();
+            let raft_network_copy = raft_network.clone();
             let vote_rpc = warp::post()
                 .and(warp::path(RAFT_VOTE_ROUTE_PATH))
                 .and(warp_body_binary())
-                .and_then(move |rpc| {
+                .and_then(move |rpc: VoteRequest| {
                     let raft_copy = raft_copy.clone();
+                    let transfer = raft_network_copy.leader_transfer();
                     async move {
+                        if let Some(transfer) = transfer {
+                            if !transfer.accept_candidate(rpc.candidate_id.into()) {
+                                let term = raft_copy.metrics().borrow().current_term;
+                                return Ok(warp_reply_binary(&VoteResponse {
+                                    term,
+                                    vote_granted: false,
+                                }));
+                            }
+                        }
+
                         raft_copy
                             .vote(rpc)
                             .await
@@ -171,6 +249,7 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
 
             let raft_copy = raft.clone();
             let tx_tx = proposal_worker.get_tx_tx();
+            let in_flight_tx_ids = proposal_worker.get_in_flight_tx_ids();
             let leader_req_rpc = warp::post()
                 .and(warp::path(CLIENT_LEADER_REQ_ROUTE_PATH))
                 .and(warp_body_binary())
@@ -181,7 +260,7 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
 
                     let raft_copy = raft_copy.clone();
                     let mut tx_tx_copy = tx_tx.clone();
-                    let mut input = stream::iter(txs).map(Ok);
+                    let in_flight_tx_ids = in_flight_tx_ids.clone();
                     async move {
                         if !node_is_leader(raft_copy.as_ref()) {
                             return Err(warp::reject::custom(ClientNodeError::Other(anyhow!(
@@ -189,6 +268,17 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                             ))));
                         }
 
+                        // Drop the copies of a tx forwarded to several storage nodes.
+                        let txs = txs.into_iter().filter(|tx| {
+                            let tx_id = tx.tx.id();
+                            let first = in_flight_tx_ids.insert(tx_id);
+                            if !first {
+                                record_event!("miner_dup_tx", "tx_id": tx_id);
+                            }
+                            first
+                        });
+                        let mut input = stream::iter(txs).map(Ok);
+
                         tx_tx_copy
                             .send_all(&mut input)
                             .await
@@ -199,36 +289,230 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
                     }
                 });
 
-            leader_id_rpc.or(leader_req_rpc)
+            let raft_storage_copy = raft_storage.clone();
+            let snapshot_rpc = warp::get()
+                .and(warp::path(CLIENT_SNAPSHOT_ROUTE_PATH))
+                .and_then(move || {
+                    let raft_storage_copy = raft_storage_copy.clone();
+                    async move {
+                        let snapshot = raft_storage_copy.latest_snapshot().await;
+                        Ok::<_, warp::Rejection>(warp_reply_binary(&snapshot))
+                    }
+                });
+
+            let raft_storage_copy = raft_storage.clone();
+            let snapshot_delta_rpc = warp::post()
+                .and(warp::path(CLIENT_SNAPSHOT_DELTA_ROUTE_PATH))
+                .and(warp_body_binary())
+                .and_then(move |(base_height, base_hash): (BlockHeight, H256)| {
+                    let raft_storage_copy = raft_storage_copy.clone();
+                    async move {
+                        raft_storage_copy
+                            .snapshot_delta(base_height, base_hash)
+                            .await
+                            .map(|delta| warp_reply_binary(&delta))
+                            .map_err(|e| warp::reject::custom(ClientNodeError::Other(e)))
+                    }
+                });
+
+            let raft_network_copy = raft_network.clone();
+            let leader_transfer_rpc = warp::post()
+                .and(warp::path(CLIENT_LEADER_TRANSFER_ROUTE_PATH))
+                .and(warp_body_binary())
+                .map(move |req: LeaderTransferRequest| {
+                    raft_network_copy.begin_leader_transfer(
+                        req.target,
+                        Duration::from_millis(req.timeout_in_ms),
+                        false,
+                    );
+                    warp_reply_binary(&())
+                });
+
+            let raft_copy = raft.clone();
+            let raft_log_index_rpc = warp::get()
+                .and(warp::path(CLIENT_RAFT_LOG_INDEX_ROUTE_PATH))
+                .map(move || warp_reply_binary(&raft_copy.metrics().borrow().last_log_index));
+
+            let raft_network_copy = raft_network.clone();
+            let block_proposal_rpc = warp::post()
+                .and(warp::path(CLIENT_BLOCK_PROPOSAL_ROUTE_PATH))
+                .and(warp_body_binary())
+                .map(move |height: BlockHeight| {
+                    warp_reply_binary(&raft_network_copy.recent_block_proposal(height))
+                });
+
+            let raft_copy = raft.clone();
+            let read_index_rpc = warp::get()
+                .and(warp::path(CLIENT_READ_INDEX_ROUTE_PATH))
+                .and_then(move || {
+                    let raft_copy = raft_copy.clone();
+                    async move {
+                        let resp = match leader_read_index(raft_copy.as_ref()).await {
+                            Ok(index) => warp_reply_binary(&index).into_response(),
+                            Err(e) => reply_error(&e),
+                        };
+                        Ok::<_, warp::Rejection>(resp)
+                    }
+                });
+
+            leader_id_rpc
+                .or(leader_req_rpc)
+                .or(read_index_rpc)
+                .or(snapshot_rpc)
+                .or(snapshot_delta_rpc)
+                .or(leader_transfer_rpc)
+                .or(raft_log_index_rpc)
+                .or(block_proposal_rpc)
+        };
+
+        let learners = Learners::new(raft_cfg.learners.iter().copied());
+        let learner_worker = LearnerWorker::new(raft.clone(), learners.clone());
+
+        let admin_srv = {
+            let raft_copy1 = raft.clone();
+            let raft_copy2 = raft.clone();
+            let raft_copy3 = raft.clone();
+            let raft_network_copy = raft_network.clone();
+            let learners_copy = learners.clone();
+            raft_admin_server(
+                move || RaftMembership {
+                    voters: current_members(raft_copy1.as_ref()),
+                    learners: learners_copy.get(),
+                },
+                move |change: MembershipChange| {
+                    let raft_copy = raft_copy2.clone();
+                    let route_table = net_route_table.clone();
+                    let learners = learners.clone();
+                    async move {
+                        let voters = match change {
+                            MembershipChange::AddVoter(peer_id)
+                            | MembershipChange::AddLearner(peer_id)
+                                if !route_table.all_client_peer_ids().contains(&peer_id.0) =>
+                            {
+                                bail!("{} is not a known client node.", peer_id);
+                            }
+                            MembershipChange::AddLearner(peer_id) => {
+                                learners.add(raft_copy.as_ref(), peer_id).await?;
+                                current_members(raft_copy.as_ref())
+                            }
+                            _ => change_membership(raft_copy.as_ref(), change).await?,
+                        };
+                        Ok(RaftMembership {
+                            voters,
+                            learners: learners.get(),
+                        })
+                    }
+                },
+                move |target| {
+                    let raft_copy = raft_copy3.clone();
+                    let raft_network_copy = raft_network_copy.clone();
+                    async move {
+                        transfer_leader(
+                            raft_copy.as_ref(),
+                            raft_network_copy.as_ref(),
+                            target,
+                            heartbeat_interval,
+                            leader_transfer_timeout,
+                        )
+                        .await
+                    }
+                },
+            )
         };
 
+        let db_rpc_srv = db_rpc_server(raft_storage.db());
+        let subscription_srv = subscription_server(raft_storage.latest_block_header());
+        let status_srv = status_server(
+            StatusService::new::<Block>(
+                Role::Client,
+                raft_storage.db(),
+                raft_storage.latest_block_header(),
+            )
+            .with_peer_counts(PeerCounts::from_route_table(&net_cfg.to_route_table()))
+            .with_queue_depth(admission.queue_depth()),
+        );
+
         info!("Create http server, listen on {}", net_cfg.http_listen);
-        let listen_addr: SocketAddr = net_cfg.http_listen.parse()?;
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
         let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
-        let (_, srv) = warp::serve(
-            client_rpc_srv.or(warp::path(NODE_RPC_ROUTE_PATH).and(raft_rpc_srv.or(leader_rpc_srv))),
-        )
-        .bind_with_graceful_shutdown(listen_addr, async {
+        let coordinator = ShutdownCoordinator::new();
+        let routes = client_rpc_srv
+            .or(shutdown_admin_server(coordinator.clone()))
+            .or(db_rpc_srv)
+            .or(subscription_srv)
+            .or(status_srv)
+            .or(metrics_server())
+            .or(admin_srv)
+            .or(warp::path(NODE_RPC_ROUTE_PATH).and(raft_rpc_srv.or(leader_rpc_srv)));
+        let srv = warp_serve!(routes, listen_addrs, net_cfg.tls.as_ref(), async {
             srv_shutdown_rx.await.ok();
         });
         let srv_handle = tokio::spawn(srv);
 
-        info!("Initialize Raft Node");
-        match raft.initialize(all_peers).await {
-            Ok(_) | Err(InitializeError::NotAllowed) => {}
-            Err(e) => return Err(Error::from(e)),
+        let grpc_srv = match net_cfg.grpc_listen.as_deref() {
+            Some(grpc_listen) => {
+                let network_worker_req_tx = network_worker.get_req_tx();
+                let raft_copy = raft.clone();
+                let service = NodeRpcService::<Block>::new(
+                    raft_storage.db(),
+                    raft_storage.latest_block_header(),
+                    move |reqs: Vec<TxHttpRequest>| {
+                        let mut network_worker_req_tx = network_worker_req_tx.clone();
+                        async move {
+                            network_worker_req_tx
+                                .send_all(&mut stream::iter(reqs).map(Ok))
+                                .await
+                                .map_err(Error::msg)
+                        }
+                    },
+                )
+                .with_leader_fn(move || {
+                    let raft_copy = raft_copy.clone();
+                    async move { get_current_leader(raft_copy.as_ref()).await }
+                })
+                .with_admission(admission.clone());
+                Some(spawn_node_rpc_server(grpc_listen, service)?)
+            }
+            None => None,
+        };
+
+        if initial_members.contains(&peer_id.0) {
+            info!("Initialize Raft Node");
+            match raft.initialize(initial_members).await {
+                Ok(_) | Err(InitializeError::NotAllowed) => {}
+                Err(e) => return Err(Error::from(e)),
+            }
+        } else {
+            info!("Wait to be added to the Raft cluster.");
         }
 
         Ok(Self {
             raft_storage,
             raft: Some(raft),
             srv: Some((srv_shutdown_tx, srv_handle)),
+            grpc_srv,
             proposal_worker,
             network_worker,
+            learner_worker,
+            admission,
+            coordinator,
         })
     }
 
+    pub fn shutdown_coordinator(&self) -> ShutdownCoordinator {
+        self.coordinator.clone()
+    }
+
+    pub fn admission(&self) -> Arc<Admission> {
+        self.admission.clone()
+    }
+
     pub async fn shutdown(&mut self) -> Result<()> {
+        self.admission.close();
+
+        info!("Shutting down LearnerWorker...");
+        self.learner_worker.shutdown().await?;
+
         info!("Shutting down BlockProposalWorker...");
         self.proposal_worker.shutdown().await?;
 
@@ -252,6 +536,11 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> ClientNode<T
             bail!("Already shutdown.");
         }
 
+        if let Some(grpc_srv) = self.grpc_srv.take() {
+            info!("Shutting down gRPC Server...");
+            grpc_srv.shutdown().await?;
+        }
+
         Ok(())
     }
 }
@@ -1,8 +1,11 @@
-use crate::behavior::raft::{
-    client::ClientNodeRaft,
-    client_network::ClientNodeNetwork,
-    client_storage::ClientNodeStorage,
-    message::{NewBlockRequest, NewBlockResponse},
+use crate::{
+    behavior::raft::{
+        client::ClientNodeRaft,
+        client_network::ClientNodeNetwork,
+        client_storage::ClientNodeStorage,
+        message::{NewBlockRequest, NewBlockResponse},
+    },
+    tx_route::InFlightTxIds,
 };
 use async_raft::{
     error::ClientWriteError,
@@ -18,19 +21,184 @@ use slimchain_chain::{
     block_proposal::BlockProposal,
     config::{ChainConfig, MinerConfig},
     consensus::raft::{create_new_block, Block},
+    discard_tx,
+    epoch::EpochConfig,
+    snapshot::Snapshot,
 };
 use slimchain_common::{
-    error::{bail, Result},
+    basic::BlockHeight,
+    error::{bail, Context as _, Result},
     tx::TxTrait,
 };
-use slimchain_tx_state::TxProposal;
-use slimchain_utils::record_event;
-use std::sync::Arc;
+use slimchain_tx_state::{TxProposal, TxTrie};
+use std::{collections::HashSet, sync::Arc};
 use tokio::task::JoinHandle;
 
+// Switch the raft voters to the committee of the next epoch. If the leader is not in the new
+// committee, it steps down once the membership change is committed.
+async fn rotate_raft_voters<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static>(
+    raft: &ClientNodeRaft<Tx>,
+    epoch_cfg: &EpochConfig,
+    height: BlockHeight,
+) -> Result<()> {
+    let next_epoch = epoch_cfg.epoch_of(height.next_height());
+    let voters = epoch_cfg
+        .committee(next_epoch)
+        .into_iter()
+        .map(|member| {
+            member
+                .parse::<u64>()
+                .with_context(|| format!("Invalid raft peer id in epoch members: {}.", member))
+        })
+        .collect::<Result<HashSet<_>>>()?;
+    info!("Rotate raft voters for epoch {}: {:?}", next_epoch, voters);
+    raft.change_membership(voters).await?;
+    Ok(())
+}
+
+enum CommitError {
+    Rejected,
+    ForwardToLeader,
+    Raft,
+}
+
+// Write the block proposal to raft and broadcast it once committed. The txs of a rejected block
+// proposal are discarded.
+async fn commit_block_proposal<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static>(
+    raft_storage: &ClientNodeStorage<Tx>,
+    raft_network: &ClientNodeNetwork<Tx>,
+    raft: &ClientNodeRaft<Tx>,
+    block_proposal_broadcast_tx: &mut mpsc::UnboundedSender<BlockProposal<Block, Tx>>,
+    async_broadcast_storage: bool,
+    blk_proposal: BlockProposal<Block, Tx>,
+    snapshot: Snapshot<Block, TxTrie>,
+) -> Result<(), CommitError> {
+    raft_storage
+        .set_miner_snapshot(&blk_proposal, snapshot)
+        .await;
+
+    match raft
+        .client_write(ClientWriteRequest::new(NewBlockRequest(
+            blk_proposal.clone(),
+        )))
+        .await
+    {
+        Ok(ClientWriteResponse { data, .. }) => match data {
+            NewBlockResponse::Ok => {}
+            NewBlockResponse::Err(e) => {
+                error!("Raft write error from response. Error: {}", e);
+
+                for tx in blk_proposal.get_txs() {
+                    let tx_id = tx.id();
+                    discard_tx!(tx_id, "raft_write_response", std::format!("{}", e));
+                }
+
+                return Err(CommitError::Rejected);
+            }
+        },
+        Err(ClientWriteError::ForwardToLeader(_, leader)) => {
+            error!("Raft write should be forward to leader ({:?}).", leader);
+            raft_storage.reset_miner_snapshot().await;
+
+            for tx in blk_proposal.get_txs() {
+                let tx_id = tx.id();
+                discard_tx!(
+                    tx_id,
+                    "raft_write_non_leader",
+                    std::format!("leader={:?}", leader)
+                );
+            }
+
+            if let Some(leader_id) = leader {
+                raft_network.set_leader(leader_id.into()).await;
+            }
+
+            return Err(CommitError::ForwardToLeader);
+        }
+        Err(ClientWriteError::RaftError(e)) => {
+            error!("Raft write error from raft. Error: {}", e);
+            raft_storage.reset_miner_snapshot().await;
+
+            for tx in blk_proposal.get_txs() {
+                let tx_id = tx.id();
+                discard_tx!(tx_id, "raft_write_error", std::format!("{}", e));
+            }
+
+            return Err(CommitError::Raft);
+        }
+    }
+
+    let blk_height = blk_proposal.get_block_height();
+
+    if async_broadcast_storage {
+        block_proposal_broadcast_tx.send(blk_proposal).await.ok();
+    } else {
+        raft_network
+            .broadcast_block_proposal_to_storage_node(&vec![blk_proposal])
+            .await
+            .ok();
+    }
+
+    if let Some(epoch_cfg) = EpochConfig::get() {
+        if epoch_cfg.is_last_block_of_epoch(blk_height) {
+            if let Err(e) = rotate_raft_voters(raft, epoch_cfg, blk_height).await {
+                error!("Failed to rotate raft voters. Error: {}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Handle the tx proposals buffered in the channel after a failed raft write.
+async fn handle_buffered_txs<Tx, TxStream>(
+    err: CommitError,
+    raft_network: &ClientNodeNetwork<Tx>,
+    in_flight_tx_ids: &InFlightTxIds,
+    tx_rx: &mut TxStream,
+) where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    TxStream: Stream<Item = TxProposal<Tx>> + Unpin,
+{
+    match err {
+        CommitError::Rejected => {}
+        CommitError::ForwardToLeader => {
+            let mut txs = Vec::with_capacity(tx_rx.size_hint().0);
+
+            while let Some(Some(tx)) = tx_rx.next().now_or_never() {
+                txs.push(tx);
+            }
+            in_flight_tx_ids.remove(txs.iter().map(|tx| tx.tx.id()));
+
+            if let Err(e) = raft_network.forward_tx_proposal_to_leader(&txs).await {
+                error!("Failed to forward buffered tx to leader. Error: {}", e);
+
+                for tx in txs {
+                    let tx_id = tx.tx.id();
+                    discard_tx!(tx_id, "raft_forward_leader_error");
+                }
+            }
+        }
+        CommitError::Raft => {
+            while let Some(Some(tx)) = tx_rx.next().now_or_never() {
+                let tx_id = tx.tx.id();
+                in_flight_tx_ids.remove(Some(tx_id));
+                discard_tx!(tx_id, "raft_write_error_buffered_tx");
+            }
+        }
+    }
+}
+
+// The block proposal being committed in the background, together with the snapshot after it.
+struct PendingCommit {
+    handle: JoinHandle<Result<(), CommitError>>,
+    snapshot: Snapshot<Block, TxTrie>,
+}
+
 pub struct BlockProposalWorker<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> {
     handle: Option<JoinHandle<()>>,
     tx_tx: mpsc::UnboundedSender<TxProposal<Tx>>,
+    in_flight_tx_ids: Arc<InFlightTxIds>,
     shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
@@ -47,11 +215,15 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> BlockProposa
         let (tx_tx, tx_rx) = mpsc::unbounded::<TxProposal<Tx>>();
         let mut tx_rx = tx_rx.fuse().peekable();
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+        let in_flight_tx_ids = Arc::new(InFlightTxIds::new());
+        let in_flight_tx_ids_copy = in_flight_tx_ids.clone();
 
         let chain_cfg = chain_cfg.clone();
         let miner_cfg = miner_cfg.clone();
 
         let handle: JoinHandle<()> = tokio::spawn(async move {
+            let mut pending: Option<PendingCommit> = None;
+
             loop {
                 tokio::select! {
                     _ = &mut shutdown_rx => break,
@@ -62,19 +234,29 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> BlockProposa
                     }
                 }
 
-                let mut snapshot = raft_storage.latest_snapshot().await;
-                let blk_proposal = match propose_block(
-                    &chain_cfg,
-                    &miner_cfg,
-                    &mut snapshot,
-                    &mut tx_rx,
-                    create_new_block,
-                )
-                .await
-                {
+                // With pipelining, the next block is proposed on top of the one being committed.
+                let mut snapshot = match &pending {
+                    Some(pending) => pending.snapshot.clone(),
+                    None => raft_storage.latest_snapshot().await,
+                };
+                // The txs taken by the block are in flight until it is committed or discarded.
+                let mut tx_ids = Vec::new();
+                let res = {
+                    let mut tx_proposals = (&mut tx_rx).inspect(|tx| tx_ids.push(tx.tx.id()));
+                    propose_block(
+                        &chain_cfg,
+                        &miner_cfg,
+                        &mut snapshot,
+                        &mut tx_proposals,
+                        create_new_block,
+                    )
+                    .await
+                };
+                let blk_proposal = match res {
                     Ok(blk_proposal) => blk_proposal,
                     Err(e) => {
                         error!("Failed to build the new block. Error: {}", e);
+                        in_flight_tx_ids_copy.remove(tx_ids);
                         continue;
                     }
                 };
@@ -84,91 +266,70 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> BlockProposa
                     None => break,
                 };
 
-                raft_storage
-                    .set_miner_snapshot(&blk_proposal, snapshot)
-                    .await;
-
-                match raft
-                    .client_write(ClientWriteRequest::new(NewBlockRequest(
-                        blk_proposal.clone(),
-                    )))
-                    .await
-                {
-                    Ok(ClientWriteResponse { data, .. }) => match data {
-                        NewBlockResponse::Ok => {}
-                        NewBlockResponse::Err(e) => {
-                            error!("Raft write error from response. Error: {}", e);
-
-                            for tx in blk_proposal.get_txs() {
-                                let tx_id = tx.id();
-                                record_event!("discard_tx", "tx_id": tx_id, "reason": "raft_write_response", "detail": std::format!("{}", e));
-                            }
-
-                            continue;
-                        }
-                    },
-                    Err(ClientWriteError::ForwardToLeader(_, leader)) => {
-                        error!("Raft write should be forward to leader ({:?}).", leader);
-                        raft_storage.reset_miner_snapshot().await;
-
-                        for tx in blk_proposal.get_txs() {
-                            let tx_id = tx.id();
-                            record_event!("discard_tx", "tx_id": tx_id, "reason": "raft_write_non_leader", "detail": std::format!("leader={:?}", leader));
-                        }
-
-                        if let Some(leader_id) = leader {
-                            raft_network.set_leader(leader_id.into()).await;
-                        }
-
-                        let mut txs = Vec::with_capacity(tx_rx.size_hint().0);
-
-                        while let Some(Some(tx)) = tx_rx.next().now_or_never() {
-                            txs.push(tx);
-                        }
-
-                        if let Err(e) = raft_network.forward_tx_proposal_to_leader(&txs).await {
-                            error!("Failed to forward buffered tx to leader. Error: {}", e);
-
-                            for tx in txs {
-                                let tx_id = tx.tx.id();
-                                record_event!("discard_tx", "tx_id": tx_id, "reason": "raft_forward_leader_error");
-                            }
-                        }
-
-                        continue;
-                    }
-                    Err(ClientWriteError::RaftError(e)) => {
-                        error!("Raft write error from raft. Error: {}", e);
-                        raft_storage.reset_miner_snapshot().await;
-
+                if let Some(pending) = pending.take() {
+                    let res = pending.handle.await.unwrap_or(Err(CommitError::Raft));
+                    if let Err(e) = res {
                         for tx in blk_proposal.get_txs() {
                             let tx_id = tx.id();
-                            record_event!("discard_tx", "tx_id": tx_id, "reason": "raft_write_error", "detail": std::format!("{}", e));
+                            discard_tx!(tx_id, "pipeline_parent_failed");
                         }
-
-                        while let Some(Some(tx)) = tx_rx.next().now_or_never() {
-                            let tx_id = tx.tx.id();
-                            record_event!("discard_tx", "tx_id": tx_id, "reason": "raft_write_error_buffered_tx");
-                        }
-
+                        in_flight_tx_ids_copy.remove(tx_ids);
+                        handle_buffered_txs(e, &raft_network, &in_flight_tx_ids_copy, &mut tx_rx)
+                            .await;
                         continue;
                     }
                 }
 
-                if async_broadcast_storage {
-                    block_proposal_broadcast_tx.send(blk_proposal).await.ok();
+                if miner_cfg.pipeline {
+                    let raft_storage = raft_storage.clone();
+                    let raft_network = raft_network.clone();
+                    let raft = raft.clone();
+                    let mut block_proposal_broadcast_tx = block_proposal_broadcast_tx.clone();
+                    let in_flight_tx_ids = in_flight_tx_ids_copy.clone();
+                    let snapshot_copy = snapshot.clone();
+                    let handle = tokio::spawn(async move {
+                        let res = commit_block_proposal(
+                            &raft_storage,
+                            &raft_network,
+                            &raft,
+                            &mut block_proposal_broadcast_tx,
+                            async_broadcast_storage,
+                            blk_proposal,
+                            snapshot_copy,
+                        )
+                        .await;
+                        in_flight_tx_ids.remove(tx_ids);
+                        res
+                    });
+                    pending = Some(PendingCommit { handle, snapshot });
                 } else {
-                    raft_network
-                        .broadcast_block_proposal_to_storage_node(&vec![blk_proposal])
-                        .await
-                        .ok();
+                    let res = commit_block_proposal(
+                        &raft_storage,
+                        &raft_network,
+                        &raft,
+                        &mut block_proposal_broadcast_tx,
+                        async_broadcast_storage,
+                        blk_proposal,
+                        snapshot,
+                    )
+                    .await;
+                    in_flight_tx_ids_copy.remove(tx_ids);
+                    if let Err(e) = res {
+                        handle_buffered_txs(e, &raft_network, &in_flight_tx_ids_copy, &mut tx_rx)
+                            .await;
+                    }
                 }
             }
+
+            if let Some(pending) = pending.take() {
+                pending.handle.await.ok();
+            }
         });
 
         Self {
             handle: Some(handle),
             tx_tx,
+            in_flight_tx_ids,
             shutdown_tx: Some(shutdown_tx),
         }
     }
@@ -177,6 +338,10 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> BlockProposa
         self.tx_tx.clone()
     }
 
+    pub fn get_in_flight_tx_ids(&self) -> Arc<InFlightTxIds> {
+        self.in_flight_tx_ids.clone()
+    }
+
     pub async fn shutdown(&mut self) -> Result<()> {
         self.tx_tx.close_channel();
         if let Some(shutdown_tx) = self.shutdown_tx.take() {
@@ -0,0 +1,134 @@
+use crate::{
+    behavior::raft::utils::{
+        fetch_agreed_blocks, other_client_nodes, random_other_client_node, random_storage_node,
+    },
+    http::{
+        client_rpc::{get_block_height, LeaderClient},
+        config::NetworkRouteTable,
+        node_rpc::*,
+    },
+};
+use futures::future;
+use serde::Deserialize;
+use slimchain_chain::{
+    block::BlockTrait,
+    config::ChainConfig,
+    consensus::raft::{verify_consensus, Block},
+    db::DBPtr,
+    snapshot::{Snapshot, SnapshotDelta},
+};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    collections::HashMap,
+    digest::Digestible,
+    error::{ensure, Context as _, Result},
+    tx::TxTrait,
+};
+use slimchain_tx_state::TxTrie;
+
+// The snapshot is rejected if it is more than this many blocks behind the leader.
+const MAX_SNAPSHOT_LAG: u64 = 64;
+
+// Initialize the database using the latest snapshot of another client. An empty database fetches
+// the full snapshot, while a non-empty one only fetches the delta since its local snapshot. The
+// result is verified against the block agreed by a majority of the storage nodes.
+pub async fn bootstrap_from_snapshot<Tx: TxTrait + for<'de> Deserialize<'de>>(
+    db: &DBPtr,
+    chain_cfg: &ChainConfig,
+    route_table: &NetworkRouteTable,
+) -> Result<()> {
+    let client_addr = random_other_client_node(route_table)?;
+    let mut snapshot = if db.get_meta_object::<BlockHeight>("height")?.is_some() {
+        let mut snapshot = Snapshot::<Block, TxTrie>::load_from_db(db, chain_cfg.state_len)?;
+        let latest_block = snapshot
+            .get_latest_block()
+            .context("Failed to get the latest block.")?;
+        let (base_height, base_hash) = (latest_block.block_height(), latest_block.to_digest());
+        info!(
+            "Fetch snapshot delta since #{} from {}.",
+            base_height, client_addr
+        );
+        let delta: SnapshotDelta<Block> =
+            fetch_snapshot_delta_from_client(client_addr, base_height, base_hash).await?;
+        snapshot
+            .apply_delta(&delta)
+            .context("Failed to apply the snapshot delta.")?;
+        snapshot
+    } else {
+        info!("Fetch snapshot from {}.", client_addr);
+        fetch_snapshot_from_client(client_addr).await?
+    };
+    let height = snapshot.current_height();
+
+    let trusted_block = fetch_trusted_block(route_table, height).await?;
+    let txs = fetch_recent_txs::<Tx>(route_table, &snapshot).await?;
+    snapshot
+        .verify(&trusted_block, chain_cfg.state_len, &txs, verify_consensus)
+        .context("Failed to verify the snapshot.")?;
+
+    db.write_async(snapshot.write_bootstrap_db_tx()?).await?;
+    info!(
+        "Bootstrap from snapshot at #{} (state root: {}).",
+        height,
+        trusted_block.state_root()
+    );
+    Ok(())
+}
+
+// The block at the height of the snapshot, agreed by a majority of all the storage nodes. The
+// snapshot is also rejected if it lags far behind the leader, as an outdated but genuine one
+// would still pass the verification.
+async fn fetch_trusted_block(
+    route_table: &NetworkRouteTable,
+    height: BlockHeight,
+) -> Result<Block> {
+    let leader_height = fetch_leader_height(route_table).await?;
+    ensure!(
+        leader_height.0.saturating_sub(height.0) <= MAX_SNAPSHOT_LAG,
+        "The snapshot at #{} lags too far behind the leader at #{}.",
+        height,
+        leader_height
+    );
+
+    fetch_agreed_blocks::<Block>(route_table, height, 1)
+        .await?
+        .into_iter()
+        .next()
+        .with_context(|| {
+            format!(
+                "Block #{} is not agreed by a majority of the storage nodes.",
+                height
+            )
+        })
+}
+
+async fn fetch_leader_height(route_table: &NetworkRouteTable) -> Result<BlockHeight> {
+    let endpoints = other_client_nodes(route_table)?.into_iter().cloned();
+    let leader = LeaderClient::new(endpoints).leader().await?;
+    get_block_height(&leader)
+        .await
+        .with_context(|| format!("Failed to get the block height of the leader {}.", leader))
+}
+
+// The txs of the recent blocks in the snapshot, which rebuild its access map.
+async fn fetch_recent_txs<Tx: TxTrait + for<'de> Deserialize<'de>>(
+    route_table: &NetworkRouteTable,
+    snapshot: &Snapshot<Block, TxTrie>,
+) -> Result<HashMap<H256, Tx>> {
+    let storage_addr = random_storage_node(route_table)?;
+    let mut txs = HashMap::new();
+    for blk in snapshot.recent_blocks() {
+        let resps = future::join_all(
+            blk.tx_list()
+                .iter()
+                .map(|&tx_hash| fetch_tx_from_storage::<Tx>(storage_addr, tx_hash)),
+        )
+        .await;
+        for (&tx_hash, tx) in blk.tx_list().iter().zip(resps) {
+            let tx = tx
+                .with_context(|| format!("Tx {} is not available in {}.", tx_hash, storage_addr))?;
+            txs.insert(tx_hash, tx);
+        }
+    }
+    Ok(txs)
+}
@@ -0,0 +1,154 @@
+use crate::{
+    behavior::raft::{
+        client::ClientNodeRaft,
+        client_network::ClientNodeNetwork,
+        utils::{current_members, node_is_leader},
+    },
+    http::{config::PeerId, node_rpc::*},
+};
+use futures::future;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    error::{anyhow, bail, ensure, ConsensusError, Error, Result},
+    tx::TxTrait,
+};
+use std::time::{Duration, Instant};
+
+// The leader transfer sent to the followers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderTransferRequest {
+    pub target: PeerId,
+    pub timeout_in_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingLeaderTransfer {
+    pub target: PeerId,
+    pub from_leader: bool,
+    deadline: Instant,
+}
+
+impl PendingLeaderTransfer {
+    pub fn new(target: PeerId, timeout: Duration, from_leader: bool) -> Self {
+        Self {
+            target,
+            from_leader,
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    // Whether the vote from the candidate should be handled by the raft.
+    pub fn accept_candidate(&self, candidate: PeerId) -> bool {
+        candidate == self.target
+    }
+}
+
+// Move the leadership to the target, or the follower with the longest log if missing.
+//
+// async-raft has no leadership transfer, so it is done on top of the election. The target is
+// caught up first. Then the other voters are told to vote for the target only, and the leader
+// stops the heartbeats until the followers time out and elect the target.
+pub async fn transfer_leader<Tx>(
+    raft: &ClientNodeRaft<Tx>,
+    network: &ClientNodeNetwork<Tx>,
+    target: Option<PeerId>,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> Result<PeerId>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    ensure!(node_is_leader(raft), ConsensusError::NotLeader);
+    let route_table = network.route_table();
+    let peer_id = route_table.peer_id();
+    let followers: Vec<PeerId> = current_members(raft)
+        .into_iter()
+        .filter(|&id| id != peer_id)
+        .collect();
+
+    let target = match target {
+        Some(target) if target == peer_id => return Ok(peer_id),
+        Some(target) => {
+            ensure!(followers.contains(&target), "{} is not a voter.", target);
+            target
+        }
+        None => {
+            let log_indexes = future::join_all(followers.iter().map(|&id| async move {
+                let addr = route_table.peer_address(id)?;
+                Ok::<_, Error>((get_raft_log_index(addr).await?, id))
+            }))
+            .await;
+            log_indexes
+                .into_iter()
+                .filter_map(Result::ok)
+                .max()
+                .map(|(_, id)| id)
+                .ok_or_else(|| anyhow!("No follower is available."))?
+        }
+    };
+    info!("Transfer the leadership to {}.", target);
+
+    let deadline = Instant::now() + timeout;
+    let target_addr = route_table.peer_address(target)?;
+    loop {
+        let last_log_index = raft.metrics().borrow().last_log_index;
+        match get_raft_log_index(target_addr).await {
+            Ok(index) if index >= last_log_index => break,
+            Ok(_) => {}
+            Err(e) => warn!("Failed to get the log index of {}. Error: {}", target, e),
+        }
+        if Instant::now() >= deadline {
+            bail!("{} failed to catch up with the log.", target);
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+
+    let req = LeaderTransferRequest {
+        target,
+        timeout_in_ms: deadline
+            .saturating_duration_since(Instant::now())
+            .as_millis() as u64,
+    };
+    let reqs = followers.iter().filter_map(|&id| {
+        let addr = route_table.peer_address(id).ok()?;
+        Some(async move { (id, send_leader_transfer_to_client(addr, &req).await) })
+    });
+    for (id, resp) in future::join_all(reqs).await {
+        if let Err(e) = resp {
+            warn!("Failed to send the leader transfer to {}. Error: {}", id, e);
+        }
+    }
+    network.begin_leader_transfer(target, Duration::from_millis(req.timeout_in_ms), true);
+
+    let res = loop {
+        if raft.metrics().borrow().current_leader == Some(target.into()) {
+            break Ok(target);
+        }
+        if Instant::now() >= deadline {
+            break Err(anyhow!("Timeout in electing {} as the leader.", target));
+        }
+        tokio::time::sleep(poll_interval).await;
+    };
+    network.end_leader_transfer();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_leader_transfer() {
+        let transfer = PendingLeaderTransfer::new(PeerId(2), Duration::from_secs(60), true);
+        assert!(!transfer.is_expired());
+        assert!(transfer.accept_candidate(PeerId(2)));
+        assert!(!transfer.accept_candidate(PeerId(3)));
+
+        let transfer = PendingLeaderTransfer::new(PeerId(2), Duration::from_secs(0), false);
+        assert!(transfer.is_expired());
+    }
+}
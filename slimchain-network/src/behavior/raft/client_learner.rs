@@ -0,0 +1,130 @@
+use crate::{
+    behavior::raft::{
+        client::ClientNodeRaft,
+        utils::{current_members, node_is_leader},
+    },
+    http::config::PeerId,
+};
+use async_raft::error::ChangeConfigError;
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    error::{bail, ensure, ConsensusError, Error, Result},
+    tx::TxTrait,
+};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+use tokio::task::JoinHandle;
+
+async fn add_non_voter<Tx>(raft: &ClientNodeRaft<Tx>, peer_id: PeerId) -> Result<()>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    match raft.add_non_voter(peer_id.into()).await {
+        Ok(_) | Err(ChangeConfigError::Noop) => Ok(()),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+// The learners are the non-voters of async-raft, which are only kept in the memory of the
+// leader. So they are tracked here and added again whenever the node becomes the leader.
+#[derive(Debug, Default, Clone)]
+pub struct Learners(Arc<Mutex<BTreeSet<PeerId>>>);
+
+impl Learners {
+    pub fn new(learners: impl IntoIterator<Item = PeerId>) -> Self {
+        Self(Arc::new(Mutex::new(learners.into_iter().collect())))
+    }
+
+    pub fn get(&self) -> Vec<PeerId> {
+        self.0
+            .lock()
+            .expect("Failed to lock learners.")
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    // It returns once the learner catches up with the log.
+    pub async fn add<Tx>(&self, raft: &ClientNodeRaft<Tx>, peer_id: PeerId) -> Result<()>
+    where
+        Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    {
+        ensure!(node_is_leader(raft), ConsensusError::NotLeader);
+        ensure!(
+            !current_members(raft).contains(&peer_id),
+            "{} is already a voter.",
+            peer_id
+        );
+        add_non_voter(raft, peer_id).await?;
+        self.0
+            .lock()
+            .expect("Failed to lock learners.")
+            .insert(peer_id);
+        Ok(())
+    }
+}
+
+pub struct LearnerWorker {
+    handle: Option<JoinHandle<()>>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
+
+impl LearnerWorker {
+    pub fn new<Tx>(raft: Arc<ClientNodeRaft<Tx>>, learners: Learners) -> Self
+    where
+        Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    {
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+        let mut metrics = raft.metrics();
+        let handle = tokio::spawn(async move {
+            let mut is_leader = false;
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    res = metrics.changed() => {
+                        if res.is_err() {
+                            break;
+                        }
+                    }
+                }
+
+                let was_leader = is_leader;
+                is_leader = metrics.borrow().state.is_leader();
+                if !is_leader || was_leader {
+                    continue;
+                }
+
+                for peer_id in learners.get() {
+                    let raft = raft.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = add_non_voter(raft.as_ref(), peer_id).await {
+                            warn!("Failed to add the learner {}. Error: {}", peer_id, e);
+                        }
+                    });
+                }
+            }
+        });
+
+        Self {
+            handle: Some(handle),
+            shutdown_tx: Some(shutdown_tx),
+        }
+    }
+
+    pub async fn shutdown(&mut self) -> Result<()> {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            shutdown_tx.send(()).ok();
+        } else {
+            bail!("Already shutdown.");
+        }
+        if let Some(handler) = self.handle.take() {
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
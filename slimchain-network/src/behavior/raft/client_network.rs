@@ -1,11 +1,16 @@
 use crate::{
-    behavior::raft::message::NewBlockRequest,
+    behavior::raft::{client_leader_transfer::PendingLeaderTransfer, message::NewBlockRequest},
     http::{
+        admission::QueueDepth,
         client_rpc::TxHttpRequest,
         common::*,
         config::{NetworkRouteTable, PeerId},
         node_rpc::*,
     },
+    tx_route::{
+        record_tx_retried, record_tx_route_failed, record_tx_routed, resolve_tx_shard,
+        MAX_ROUTE_ATTEMPTS,
+    },
 };
 use async_raft::{
     raft::{
@@ -21,14 +26,24 @@ use futures::{
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
-use slimchain_chain::{block_proposal::BlockProposal, consensus::raft::Block, role::Role};
+use slimchain_chain::{
+    block_proposal::BlockProposal, consensus::raft::Block, discard_tx, role::Role,
+};
 use slimchain_common::{
+    basic::BlockHeight,
     error::{anyhow, bail, Result},
     tx::TxTrait,
+    tx_req::SignedTxRequest,
 };
 use slimchain_tx_state::TxProposal;
 use slimchain_utils::{bytes::Bytes, record_event, serde::binary_encode};
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    cmp,
+    collections::VecDeque,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::{sync::RwLock, task::JoinHandle};
 
 pub async fn fetch_leader_id(route_table: &NetworkRouteTable) -> Result<PeerId> {
@@ -39,12 +54,18 @@ pub async fn fetch_leader_id(route_table: &NetworkRouteTable) -> Result<PeerId>
     get_leader(rand_client).await
 }
 
+// The number of the latest broadcast block proposals kept for the storage nodes missing them.
+const RECENT_BLOCK_PROPOSALS: usize = 64;
+
 pub struct ClientNodeNetwork<Tx>
 where
     Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
 {
     route_table: NetworkRouteTable,
+    tx_redundancy: usize,
     leader_id: RwLock<Option<PeerId>>,
+    leader_transfer: Mutex<Option<PendingLeaderTransfer>>,
+    recent_block_proposals: Mutex<VecDeque<BlockProposal<Block, Tx>>>,
     _marker: PhantomData<Tx>,
 }
 
@@ -55,50 +76,153 @@ where
     pub fn new(route_table: NetworkRouteTable) -> Self {
         Self {
             route_table,
+            tx_redundancy: 1,
             leader_id: RwLock::new(None),
+            leader_transfer: Mutex::new(None),
+            recent_block_proposals: Mutex::new(VecDeque::with_capacity(RECENT_BLOCK_PROPOSALS)),
             _marker: PhantomData,
         }
     }
 
+    // Forward each tx to this number of the storage nodes at once.
+    pub fn with_tx_redundancy(mut self, tx_redundancy: usize) -> Self {
+        self.tx_redundancy = cmp::max(tx_redundancy, 1);
+        self
+    }
+
+    pub fn recent_block_proposal(&self, height: BlockHeight) -> Option<BlockProposal<Block, Tx>> {
+        self.recent_block_proposals
+            .lock()
+            .expect("Failed to lock recent block proposals.")
+            .iter()
+            .find(|blk_proposal| blk_proposal.get_block_height() == height)
+            .cloned()
+    }
+
+    fn keep_recent_block_proposals(&self, block_proposals: &[BlockProposal<Block, Tx>]) {
+        let mut recent = self
+            .recent_block_proposals
+            .lock()
+            .expect("Failed to lock recent block proposals.");
+        for blk_proposal in block_proposals {
+            if recent.len() == RECENT_BLOCK_PROPOSALS {
+                recent.pop_front();
+            }
+            recent.push_back(blk_proposal.clone());
+        }
+    }
+
+    // Send the tx to `tx_redundancy` storage nodes of the shard at once, so that a failed one
+    // does not hold it up. If none of them accepts it, try the rest one by one.
     #[tracing::instrument(level = "debug", skip(self, tx_req))]
     pub async fn forward_tx_to_storage_node(&self, tx_req: TxHttpRequest) {
         let TxHttpRequest { req, shard_id } = tx_req;
         let tx_req_id = req.id();
+        let shard_id = resolve_tx_shard(&req, shard_id, self.route_table.storage_shard_totals());
 
-        let storage_node_peer_id = match self.route_table.random_peer(&Role::Storage(shard_id)) {
-            Some(peer) => peer,
-            None => {
-                error!(%tx_req_id , "Failed to find the storage node. ShardId: {:?}", shard_id);
+        let storage_node_peer_ids = self.route_table.shuffled_peers(&Role::Storage(shard_id));
+        if storage_node_peer_ids.is_empty() {
+            error!(%tx_req_id , "Failed to find the storage node. ShardId: {:?}", shard_id);
+            record_tx_route_failed();
+            discard_tx!(tx_req_id, "no_storage_node", format!("{:?}", shard_id));
+            return;
+        }
+
+        record_event!("tx_begin", "tx_id": tx_req_id);
+
+        let max_attempts = cmp::max(MAX_ROUTE_ATTEMPTS, self.tx_redundancy);
+        let mut peer_ids = storage_node_peer_ids.into_iter().take(max_attempts);
+        let first_round = peer_ids
+            .by_ref()
+            .take(self.tx_redundancy)
+            .map(|peer_id| self.send_tx_to_storage_node(peer_id, &req).boxed());
+        let mut last_err = match future::select_ok(first_round).await {
+            Ok(_) => {
+                record_tx_routed();
                 return;
             }
+            Err(e) => Some(e),
         };
-        debug_assert_ne!(storage_node_peer_id, self.route_table.peer_id());
 
-        let storage_node_addr = match self.route_table.peer_address(storage_node_peer_id) {
-            Ok(addr) => addr,
-            Err(_) => {
-                error!(%tx_req_id , "Failed to get the storage address. PeerId: {}", storage_node_peer_id);
-                return;
+        for (attempt, storage_node_peer_id) in peer_ids.enumerate() {
+            record_tx_retried();
+            record_event!("tx_route_retry", "tx_id": tx_req_id, "peer_id": storage_node_peer_id.0, "attempt": attempt + self.tx_redundancy);
+
+            match self
+                .send_tx_to_storage_node(storage_node_peer_id, &req)
+                .await
+            {
+                Ok(()) => {
+                    record_tx_routed();
+                    return;
+                }
+                Err(e) => last_err = Some(e),
             }
-        };
+        }
 
-        record_event!("tx_begin", "tx_id": tx_req_id);
+        let e = last_err.map(|e| e.to_string()).unwrap_or_default();
+        error!(%tx_req_id, "Failed to forward TX to any storage node. Error: {}", e);
+        record_tx_route_failed();
+        discard_tx!(tx_req_id, "storage_route_failed", e);
+    }
 
+    async fn send_tx_to_storage_node(
+        &self,
+        storage_node_peer_id: PeerId,
+        req: &SignedTxRequest,
+    ) -> Result<()> {
+        debug_assert_ne!(storage_node_peer_id, self.route_table.peer_id());
+        let storage_node_addr = self.route_table.peer_address(storage_node_peer_id)?;
         let resp: Result<()> = send_post_request_using_binary(
             &format!(
-                "http://{}/{}/{}",
-                storage_node_addr, NODE_RPC_ROUTE_PATH, STORAGE_TX_REQ_ROUTE_PATH
+                "{}/{}/{}",
+                endpoint_url(storage_node_addr),
+                NODE_RPC_ROUTE_PATH,
+                STORAGE_TX_REQ_ROUTE_PATH
             ),
-            &req,
+            req,
         )
         .await;
-
-        if let Err(e) = resp {
-            error!(
-                %tx_req_id,
+        if let Err(e) = resp.as_ref() {
+            warn!(
+                tx_req_id = %req.id(),
+                %storage_node_peer_id,
                 "Failed to forward TX to storage node. Error: {}", e
             );
         }
+        resp
+    }
+
+    pub fn route_table(&self) -> &NetworkRouteTable {
+        &self.route_table
+    }
+
+    // Only grant the votes to the target until the transfer ends or times out. The leader also
+    // stops the heartbeats so that the followers start the election.
+    pub fn begin_leader_transfer(&self, target: PeerId, timeout: Duration, from_leader: bool) {
+        *self
+            .leader_transfer
+            .lock()
+            .expect("Failed to lock leader transfer.") =
+            Some(PendingLeaderTransfer::new(target, timeout, from_leader));
+    }
+
+    pub fn end_leader_transfer(&self) {
+        *self
+            .leader_transfer
+            .lock()
+            .expect("Failed to lock leader transfer.") = None;
+    }
+
+    pub fn leader_transfer(&self) -> Option<PendingLeaderTransfer> {
+        let mut transfer = self
+            .leader_transfer
+            .lock()
+            .expect("Failed to lock leader transfer.");
+        if transfer.map_or(false, |t| t.is_expired()) {
+            *transfer = None;
+        }
+        *transfer
     }
 
     pub async fn set_leader(&self, leader_id: PeerId) {
@@ -132,6 +256,7 @@ where
         }
     }
 
+    // The auditor nodes receive the same block proposals as the storage nodes.
     #[allow(clippy::ptr_arg)]
     #[tracing::instrument(level = "debug", skip(self, block_proposals), err)]
     pub async fn broadcast_block_proposal_to_storage_node(
@@ -142,26 +267,30 @@ where
             return Ok(());
         }
 
+        self.keep_recent_block_proposals(block_proposals);
         let bytes = Bytes::from(binary_encode(block_proposals)?);
         let reqs = self
             .route_table
             .role_table()
             .iter()
-            .filter(|(role, _)| matches!(role, Role::Storage(_)))
-            .flat_map(|(_, list)| list.iter())
-            .filter_map(|&peer_id| match self.route_table.peer_address(peer_id) {
-                Ok(addr) => Some((
-                    peer_id,
-                    format!(
-                        "http://{}/{}/{}",
-                        addr, NODE_RPC_ROUTE_PATH, STORAGE_BLOCK_IMPORT_ROUTE_PATH
-                    ),
-                )),
-                Err(_) => {
-                    warn!("Failed to get the peer address. PeerId: {}", peer_id);
-                    None
-                }
+            .filter_map(|(role, list)| match role {
+                Role::Storage(_) => Some((STORAGE_BLOCK_IMPORT_ROUTE_PATH, list)),
+                Role::Auditor => Some((AUDITOR_BLOCK_IMPORT_ROUTE_PATH, list)),
+                _ => None,
             })
+            .flat_map(|(route, list)| list.iter().map(move |&peer_id| (route, peer_id)))
+            .filter_map(
+                |(route, peer_id)| match self.route_table.peer_address(peer_id) {
+                    Ok(addr) => Some((
+                        peer_id,
+                        format!("{}/{}/{}", endpoint_url(addr), NODE_RPC_ROUTE_PATH, route),
+                    )),
+                    Err(_) => {
+                        warn!("Failed to get the peer address. PeerId: {}", peer_id);
+                        None
+                    }
+                },
+            )
             .map(|(peer_id, uri)| {
                 let bytes = bytes.clone();
                 async move {
@@ -203,11 +332,18 @@ where
     ) -> Result<AppendEntriesResponse> {
         let peer_id = PeerId::from(target);
         debug_assert_ne!(peer_id, self.route_table.peer_id());
+        if let Some(transfer) = self.leader_transfer() {
+            if transfer.from_leader {
+                bail!("Leadership is being transferred to {}.", transfer.target);
+            }
+        }
         let addr = self.route_table.peer_address(peer_id)?;
         send_post_request_using_binary(
             &format!(
-                "http://{}/{}/{}",
-                addr, NODE_RPC_ROUTE_PATH, RAFT_APPEND_ENTRIES_ROUTE_PATH
+                "{}/{}/{}",
+                endpoint_url(addr),
+                NODE_RPC_ROUTE_PATH,
+                RAFT_APPEND_ENTRIES_ROUTE_PATH
             ),
             &rpc,
         )
@@ -225,8 +361,10 @@ where
         let addr = self.route_table.peer_address(peer_id)?;
         send_post_request_using_binary(
             &format!(
-                "http://{}/{}/{}",
-                addr, NODE_RPC_ROUTE_PATH, RAFT_INSTALL_SNAPSHOT_ROUTE_PATH
+                "{}/{}/{}",
+                endpoint_url(addr),
+                NODE_RPC_ROUTE_PATH,
+                RAFT_INSTALL_SNAPSHOT_ROUTE_PATH
             ),
             &rpc,
         )
@@ -240,8 +378,10 @@ where
         let addr = self.route_table.peer_address(peer_id)?;
         send_post_request_using_binary(
             &format!(
-                "http://{}/{}/{}",
-                addr, NODE_RPC_ROUTE_PATH, RAFT_VOTE_ROUTE_PATH
+                "{}/{}/{}",
+                endpoint_url(addr),
+                NODE_RPC_ROUTE_PATH,
+                RAFT_VOTE_ROUTE_PATH
             ),
             &rpc,
         )
@@ -265,10 +405,15 @@ impl<Tx> ClientNodeNetworkWorker<Tx>
 where
     Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
 {
-    pub fn new(network: Arc<ClientNodeNetwork<Tx>>, async_broadcast_storage: bool) -> Self {
+    pub fn new(
+        network: Arc<ClientNodeNetwork<Tx>>,
+        async_broadcast_storage: bool,
+        queue_depth: QueueDepth,
+    ) -> Self {
         let (req_tx, req_rx) = mpsc::unbounded();
         let req_fut = {
             let network = network.clone();
+            let req_rx = req_rx.inspect(move |_| queue_depth.decr(1));
             req_rx.for_each_concurrent(64, move |req| {
                 let network = network.clone();
                 async move { network.forward_tx_to_storage_node(req).await }
@@ -5,29 +5,34 @@ use crate::{
 use async_raft::{
     raft::{Entry, EntryPayload, MembershipConfig},
     storage::{CurrentSnapshotData, HardState, InitialState},
-    RaftStorage,
+    AppData, RaftStorage,
 };
 use async_trait::async_trait;
 use itertools::process_results;
 use serde::{Deserialize, Serialize};
 use slimchain_chain::{
     behavior::{commit_block, verify_block},
+    block::BlockTrait,
     block_proposal::BlockProposal,
     config::ChainConfig,
     consensus::raft::{verify_consensus, Block},
     db::{DBPtr, Transaction as DBTransaction},
     latest::{LatestBlockHeaderPtr, LatestTxCount, LatestTxCountPtr},
-    snapshot::Snapshot,
+    snapshot::{Snapshot, SnapshotDelta},
 };
 use slimchain_common::{
-    basic::H256,
+    basic::{BlockHeight, H256},
     digest::Digestible,
-    error::{anyhow, Result},
+    error::{anyhow, Context as _, Result},
     tx::TxTrait,
 };
 use slimchain_tx_state::TxTrie;
 use slimchain_utils::serde::{binary_decode, binary_encode};
-use std::{collections::BTreeSet, io::Cursor, marker::PhantomData};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    io::Cursor,
+    marker::PhantomData,
+};
 use tokio::sync::{Mutex, RwLock};
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -44,6 +49,20 @@ struct RaftStateMachine {
     snapshot: Snapshot<Block, TxTrie>,
 }
 
+// The number of previous snapshots kept in memory to serve snapshot deltas.
+const MAX_SNAPSHOT_HISTORY: usize = 64;
+
+// The membership set by the entry. The changes made through the admin endpoints are
+// persisted as the config change entries, and carried over by the snapshot pointers once the
+// log is compacted.
+fn entry_membership<D: AppData>(entry: &Entry<D>) -> Option<MembershipConfig> {
+    match &entry.payload {
+        EntryPayload::ConfigChange(cfg) => Some(cfg.membership.clone()),
+        EntryPayload::SnapshotPointer(snap) => Some(snap.membership.clone()),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Copy, Clone, thiserror::Error)]
 pub enum ShutdownError {}
 
@@ -56,6 +75,7 @@ pub struct ClientNodeStorage<Tx: TxTrait> {
     raft_log: RwLock<BTreeSet<u64>>,
     raft_snapshot: RwLock<Option<RaftSnapshot>>,
     raft_sm: RwLock<RaftStateMachine>,
+    snapshot_history: RwLock<VecDeque<Snapshot<Block, TxTrie>>>,
     miner_snapshot: Mutex<Option<(H256, Snapshot<Block, TxTrie>)>>,
     _marker: PhantomData<Tx>,
 }
@@ -85,11 +105,16 @@ where
                 last_applied_log,
                 snapshot,
             }),
+            snapshot_history: RwLock::new(VecDeque::new()),
             miner_snapshot: Mutex::new(None),
             _marker: PhantomData,
         })
     }
 
+    pub fn db(&self) -> DBPtr {
+        self.db.clone()
+    }
+
     pub fn latest_block_header(&self) -> LatestBlockHeaderPtr {
         self.latest_block_header.clone()
     }
@@ -103,6 +128,27 @@ where
         sm.snapshot.clone()
     }
 
+    // Compute the changes of the latest snapshot since the one whose latest block is
+    // `base_height` with hash `base_hash`.
+    pub async fn snapshot_delta(
+        &self,
+        base_height: BlockHeight,
+        base_hash: H256,
+    ) -> Result<SnapshotDelta<Block>> {
+        let sm = self.raft_sm.read().await;
+        let history = self.snapshot_history.read().await;
+        let base = history
+            .iter()
+            .chain(std::iter::once(&sm.snapshot))
+            .find(|snapshot| {
+                snapshot.get_latest_block().is_some_and(|blk| {
+                    blk.block_height() == base_height && blk.to_digest() == base_hash
+                })
+            })
+            .with_context(|| format!("Snapshot at #{} is not available.", base_height))?;
+        sm.snapshot.diff(base)
+    }
+
     pub async fn set_miner_snapshot(
         &self,
         blk_proposal: &BlockProposal<Block, Tx>,
@@ -149,13 +195,7 @@ where
         let log = self.raft_log.read().await;
         let cfg_opt = process_results(
             log.iter().rev().map(|idx| self.read_log(*idx)),
-            |mut iter| {
-                iter.find_map(|entry| match &entry.payload {
-                    EntryPayload::ConfigChange(cfg) => Some(cfg.membership.clone()),
-                    EntryPayload::SnapshotPointer(snap) => Some(snap.membership.clone()),
-                    _ => None,
-                })
-            },
+            |mut iter| iter.find_map(|entry| entry_membership(&entry)),
         )?;
         Ok(match cfg_opt {
             Some(cfg) => cfg,
@@ -323,7 +363,12 @@ where
         }
 
         sm.last_applied_log = *index;
-        sm.snapshot = snapshot;
+        let prev_snapshot = std::mem::replace(&mut sm.snapshot, snapshot);
+        let mut history = self.snapshot_history.write().await;
+        if history.len() == MAX_SNAPSHOT_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(prev_snapshot);
 
         Ok(NewBlockResponse::Ok)
     }
@@ -347,10 +392,7 @@ where
             let log = self.raft_log.read().await;
             process_results(log.iter().rev().map(|idx| self.read_log(*idx)), |iter| {
                 iter.skip_while(|entry| entry.index > last_applied_log)
-                    .find_map(|entry| match &entry.payload {
-                        EntryPayload::ConfigChange(cfg) => Some(cfg.membership.clone()),
-                        _ => None,
-                    })
+                    .find_map(|entry| entry_membership(&entry))
             })?
             .unwrap_or_else(|| MembershipConfig::new_initial(self.peer_id.into()))
         };
@@ -432,12 +474,9 @@ where
             let membership_config =
                 process_results(log.iter().rev().map(|idx| self.read_log(*idx)), |iter| {
                     iter.skip_while(|entry| entry.index > index)
-                        .find_map(|entry| match &entry.payload {
-                            EntryPayload::ConfigChange(cfg) => Some(cfg.membership.clone()),
-                            _ => None,
-                        })
+                        .find_map(|entry| entry_membership(&entry))
                 })?
-                .unwrap_or_else(|| MembershipConfig::new_initial(self.peer_id.into()));
+                .unwrap_or_else(|| new_snapshot.membership.clone());
 
             match &delete_through {
                 Some(through) => {
@@ -475,6 +514,7 @@ where
             let mut sm = self.raft_sm.write().await;
             sm.last_applied_log = new_snapshot.index;
             sm.snapshot = new_snapshot.snapshot.clone();
+            self.snapshot_history.write().await.clear();
             self.latest_block_header.set_from_block(
                 sm.snapshot
                     .get_latest_block()
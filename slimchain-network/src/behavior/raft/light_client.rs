@@ -0,0 +1,219 @@
+use crate::{
+    behavior::raft::utils::{fetch_agreed_blocks, random_storage_node, random_storage_node_for},
+    http::{
+        common::warp_serve,
+        config::{NetworkConfig, NetworkRouteTable},
+        db_rpc::*,
+        light_client_rpc::*,
+        metrics::metrics_server,
+        node_rpc::*,
+        status::{status_server, PeerCounts, StatusService},
+    },
+};
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockTrait,
+    consensus::raft::{verify_consensus, Block},
+    db::DBPtr,
+    latest::LatestBlockHeaderPtr,
+    light_client::{verify_tx_inclusion_proof, HeaderChain},
+    loader::BlockLoaderTrait,
+    role::Role,
+};
+use slimchain_common::{
+    basic::BlockHeight,
+    error::{bail, ensure, Result},
+    tx::TxTrait,
+};
+use std::{marker::PhantomData, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+const SYNC_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_BLOCKS_PER_SYNC: u64 = 64;
+
+// The raft blocks carry no consensus proof. So the headers are only imported once a majority of
+// the storage nodes agree on them, instead of trusting whichever node is asked.
+async fn sync_headers(
+    chain: &mut HeaderChain<Block>,
+    route_table: &NetworkRouteTable,
+) -> Result<()> {
+    loop {
+        let next_height = chain.latest_block().block_height().next_height();
+        let blocks: Vec<Block> =
+            fetch_agreed_blocks(route_table, next_height, MAX_BLOCKS_PER_SYNC).await?;
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        chain.import_blocks(blocks, verify_consensus).await?;
+    }
+}
+
+struct HeaderSyncWorker {
+    handle: Option<JoinHandle<()>>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
+
+impl HeaderSyncWorker {
+    fn new(mut chain: HeaderChain<Block>, route_table: Arc<NetworkRouteTable>) -> Self {
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SYNC_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    _ = interval.tick() => {
+                        if let Err(e) = sync_headers(&mut chain, &route_table).await {
+                            warn!("Failed to sync block headers. Error: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            handle: Some(handle),
+            shutdown_tx: Some(shutdown_tx),
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            shutdown_tx.send(()).ok();
+        } else {
+            bail!("Already shutdown.");
+        }
+        if let Some(handler) = self.handle.take() {
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
+
+fn get_synced_block(
+    db: &DBPtr,
+    latest_block_header: &LatestBlockHeaderPtr,
+    height: BlockHeight,
+) -> Result<Block> {
+    ensure!(
+        height <= latest_block_header.get_height(),
+        "Block #{} has not been synced yet.",
+        height
+    );
+    db.get_block(height)
+}
+
+// A client that only keeps the block headers. Tx bodies and their inclusion proofs are fetched
+// from the storage nodes on demand and verified against the synced headers.
+pub struct LightClientNode<Tx: TxTrait + 'static> {
+    srv: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+    sync_worker: HeaderSyncWorker,
+    _marker: PhantomData<Tx>,
+}
+
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> LightClientNode<Tx> {
+    pub async fn new(db: DBPtr, net_cfg: &NetworkConfig) -> Result<Self> {
+        let route_table = Arc::new(net_cfg.to_route_table());
+        let chain = HeaderChain::<Block>::load_from_db(db.clone())?;
+        let latest_block_header = chain.latest_block_header();
+        info!(
+            "Start syncing block headers from #{}.",
+            latest_block_header.get_height()
+        );
+        let sync_worker = HeaderSyncWorker::new(chain, route_table.clone());
+
+        let db_rpc_srv = db_rpc_server(db.clone());
+        let status_srv = status_server(
+            StatusService::new::<Block>(Role::LightClient, db.clone(), latest_block_header.clone())
+                .with_peer_counts(PeerCounts::from_route_table(&route_table)),
+        );
+
+        let light_client_rpc_srv = {
+            let latest_block_header_copy1 = latest_block_header.clone();
+            let latest_block_header_copy2 = latest_block_header.clone();
+            let latest_block_header_copy3 = latest_block_header.clone();
+            let db_copy1 = db.clone();
+            let db_copy2 = db.clone();
+            let route_table_copy = route_table.clone();
+            light_client_rpc_server(
+                move || latest_block_header_copy1.get_height(),
+                move |height| {
+                    get_synced_block(&db_copy1, &latest_block_header_copy2, height)
+                        .map(|blk| blk.block_header().clone())
+                },
+                move |req: VerifyTxHttpRequest| {
+                    let block = get_synced_block(&db, &latest_block_header, req.height);
+                    let route_table = route_table.clone();
+                    async move {
+                        let block = block?;
+                        let storage_addr = random_storage_node(&route_table)?;
+                        let tx: Tx = fetch_tx_from_storage(storage_addr, req.tx_hash).await?;
+                        let proof = fetch_tx_proof_from_storage(storage_addr, tx.id()).await?;
+                        verify_tx_inclusion_proof(block.block_header(), req.tx_hash, &tx, &proof)?;
+                        Ok(tx)
+                    }
+                },
+                move |req: VerifyStateHttpRequest| {
+                    let block = get_synced_block(&db_copy2, &latest_block_header_copy3, req.height);
+                    let route_table = route_table_copy.clone();
+                    async move {
+                        let block = block?;
+                        let storage_addr = random_storage_node_for(&route_table, req.address)?;
+                        let proof = fetch_state_proof_from_storage(
+                            storage_addr,
+                            req.height,
+                            req.address,
+                            req.key,
+                        )
+                        .await?;
+                        ensure!(
+                            proof.address == req.address && proof.key == req.key,
+                            "State proof mismatched with the request."
+                        );
+                        proof.verify(block.block_header())?;
+                        Ok(proof)
+                    }
+                },
+            )
+        };
+
+        info!("Create http server, listen on {}", net_cfg.http_listen);
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
+        let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
+        let srv = warp_serve!(
+            light_client_rpc_srv
+                .or(db_rpc_srv)
+                .or(status_srv)
+                .or(metrics_server()),
+            listen_addrs,
+            net_cfg.tls.as_ref(),
+            async {
+                srv_shutdown_rx.await.ok();
+            }
+        );
+        let srv_handle = tokio::spawn(srv);
+
+        Ok(Self {
+            srv: Some((srv_shutdown_tx, srv_handle)),
+            sync_worker,
+            _marker: PhantomData,
+        })
+    }
+
+    pub async fn shutdown(&mut self) -> Result<()> {
+        info!("Shutting down HeaderSyncWorker...");
+        self.sync_worker.shutdown().await?;
+        info!("Shutting down HTTP Server...");
+        if let Some((shutdown_tx, handler)) = self.srv.take() {
+            shutdown_tx.send(()).ok();
+            handler.await?;
+        } else {
+            bail!("Already shutdown.");
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,19 @@
-use super::client_network::fetch_leader_id;
-use crate::http::{
-    common::*,
-    config::{NetworkConfig, NetworkRouteTable, PeerId},
-    node_rpc::*,
+use super::{
+    client_network::fetch_leader_id,
+    storage_batch::{split_batches, AdaptiveBatchSize},
+};
+use crate::{
+    control::{drain_worker, ShutdownCoordinator},
+    http::{
+        admin::shutdown_admin_server,
+        admission::{Admission, QueueDepth},
+        common::*,
+        config::{LeaderBatchConfig, NetworkConfig, NetworkRouteTable, PeerId},
+        db_rpc::*,
+        metrics::{metrics_server, register_queue_depth},
+        node_rpc::*,
+        status::{status_server, PeerCounts, StatusService},
+    },
 };
 use futures::{
     channel::{mpsc, oneshot},
@@ -11,23 +22,34 @@ use futures::{
 };
 use serde::{Deserialize, Serialize};
 use slimchain_chain::{
-    behavior::{commit_block_storage_node, verify_block, TxExecuteStream},
+    behavior::{
+        call_view, commit_block_storage_node, verify_block, TxExecuteStream, ViewCallRequest,
+    },
     block_proposal::BlockProposal,
-    config::ChainConfig,
+    config::{ChainConfig, ShardBlockConfig},
     consensus::raft::{verify_consensus, Block},
     db::DBPtr,
+    discard_tx,
     latest::{LatestBlockHeaderPtr, LatestTxCount, LatestTxCountPtr},
+    loader::TxLoaderTrait,
+    role::Role,
+    shard_block::aggregate_tx_proposals,
     snapshot::Snapshot,
+    state_proof::prove_at_height,
+    tx_proof::get_tx_inclusion_proof,
 };
 use slimchain_common::{
-    basic::ShardId,
-    error::{bail, Result},
+    basic::{Address, BlockHeight, ShardId, StateKey, H256},
+    error::{bail, ensure, Error, Result},
     tx::TxTrait,
     tx_req::SignedTxRequest,
 };
 use slimchain_tx_engine::TxEngine;
 use slimchain_tx_state::{StorageTxTrie, TxProposal};
-use slimchain_utils::{ordered_stream::OrderedStream, record_event};
+use slimchain_utils::{
+    ordered_stream::{GapResolution, OrderedStream},
+    record_event,
+};
 use std::{
     marker::PhantomData,
     net::SocketAddr,
@@ -35,9 +57,10 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 use tokio::{sync::RwLock, task::JoinHandle};
-use warp::Filter;
+use warp::{Filter, Reply};
 
 const MAX_RETRIES: usize = 3;
 
@@ -89,21 +112,46 @@ struct TxExecWorker {
 impl TxExecWorker {
     fn new<Tx: TxTrait + Serialize>(
         route_table: NetworkRouteTable,
+        batch_cfg: &LeaderBatchConfig,
         engine: TxEngine<Tx>,
         db: &DBPtr,
         latest_block_header: &LatestBlockHeaderPtr,
+        queue_depth: QueueDepth,
     ) -> Self {
         let send_to_leader = Arc::new(SendToLeader::new(route_table));
+        let batch_size = Arc::new(AdaptiveBatchSize::new(batch_cfg));
+        let batch_size_copy = batch_size.clone();
+        let shard_block_cfg = ShardBlockConfig::get();
         let engine_shutdown_token = engine.shutdown_token();
         let (tx_req_tx, tx_req_rx) = mpsc::unbounded::<SignedTxRequest>();
+        let tx_req_rx = tx_req_rx.inspect(move |_| queue_depth.decr(1));
+        // Keep up to `window` batches in flight, so that the leader is not waited one batch at a
+        // time.
         let tx_exec_fut = TxExecuteStream::new(tx_req_rx, engine, &db, &latest_block_header)
-            .ready_chunks(8)
-            .for_each_concurrent(8, move |tx_proposals| {
+            .ready_chunks(batch_cfg.max_batch.max(1))
+            .flat_map(move |tx_proposals| {
+                stream::iter(split_batches(tx_proposals, batch_size_copy.get()))
+            })
+            // Each batch becomes a shard block, so the leader does not receive the txs bound to
+            // conflict with another one in the same batch.
+            .map(move |tx_proposals| {
+                if shard_block_cfg.enabled {
+                    aggregate_tx_proposals(tx_proposals)
+                } else {
+                    tx_proposals
+                }
+            })
+            .for_each_concurrent(batch_cfg.window.max(1), move |tx_proposals| {
                 let send_to_leader = send_to_leader.clone();
+                let batch_size = batch_size.clone();
                 async move {
                     for i in 1..=MAX_RETRIES {
+                        let begin = Instant::now();
                         match send_to_leader.send_tx_proposals(&tx_proposals).await {
-                            Ok(_) => break,
+                            Ok(_) => {
+                                batch_size.record(tx_proposals.len(), begin.elapsed());
+                                break;
+                            }
                             Err(e) => {
                                 if i == MAX_RETRIES {
                                     error!(
@@ -112,7 +160,11 @@ impl TxExecWorker {
                                     );
                                     for tx in &tx_proposals {
                                         let tx_id = tx.tx.id();
-                                        record_event!("discard_tx", "tx_id": tx_id, "reason": "storage_send_to_leader", "detail": std::format!("{}", e));
+                                        discard_tx!(
+                                            tx_id,
+                                            "storage_send_to_leader",
+                                            std::format!("{}", e)
+                                        );
                                     }
                                 }
                             }
@@ -141,36 +193,70 @@ impl TxExecWorker {
         self.tx_req_tx.clone()
     }
 
+    // The accepted txs are executed and sent to the leader before the engine is stopped.
     async fn shutdown(&mut self) -> Result<()> {
         self.tx_req_tx.close_channel();
-        self.engine_shutdown_token.store(true, Ordering::Release);
-        if let Some(shutdown_tx) = self.shutdown_tx.take() {
-            shutdown_tx.send(()).ok();
-        } else {
-            bail!("Already shutdown.");
-        }
+        let shutdown_tx = match self.shutdown_tx.take() {
+            Some(shutdown_tx) => shutdown_tx,
+            None => bail!("Already shutdown."),
+        };
         if let Some(handler) = self.handle.take() {
-            handler.await?;
+            drain_worker("TxExecWorker", handler, move || {
+                shutdown_tx.send(()).ok();
+            })
+            .await?;
         } else {
             bail!("Already shutdown.");
         }
+        self.engine_shutdown_token.store(true, Ordering::Release);
         Ok(())
     }
 }
 
+// Ask the client nodes for the block proposal lost on the way. Keep waiting if none of them has
+// it, since a storage node cannot import the later blocks without it.
+async fn fetch_missing_block_proposal<Tx: TxTrait + for<'de> Deserialize<'de>>(
+    route_table: Arc<NetworkRouteTable>,
+    height: BlockHeight,
+) -> GapResolution<BlockProposal<Block, Tx>> {
+    warn!(%height, "Missing block proposal. Fetch it from the client nodes.");
+    record_event!("block_gap", "height": height.0);
+
+    for peer_id in route_table.shuffled_peers(&Role::Client) {
+        let addr = match route_table.peer_address(peer_id) {
+            Ok(addr) => addr,
+            Err(_) => continue,
+        };
+        match fetch_block_proposal_from_client(addr, height).await {
+            Ok(Some(blk_proposal)) => {
+                info!(%height, %peer_id, "Fetched the missing block proposal.");
+                return GapResolution::Fill(blk_proposal);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(%height, %peer_id, "Failed to fetch the block proposal. Error: {}", e);
+            }
+        }
+    }
+
+    GapResolution::Wait
+}
+
 struct BlockImportWorker<Tx: TxTrait + 'static> {
     handle: Option<JoinHandle<()>>,
     blk_tx: mpsc::UnboundedSender<BlockProposal<Block, Tx>>,
     shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
-impl<Tx: TxTrait + Serialize> BlockImportWorker<Tx> {
+impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de>> BlockImportWorker<Tx> {
     fn new(
         chain_cfg: ChainConfig,
         mut snapshot: Snapshot<Block, StorageTxTrie>,
         latest_block_header: LatestBlockHeaderPtr,
         latest_tx_count: LatestTxCountPtr,
         db: DBPtr,
+        route_table: NetworkRouteTable,
+        gap_timeout: Duration,
     ) -> Self {
         let (blk_tx, blk_rx) = mpsc::unbounded::<BlockProposal<Block, Tx>>();
         let mut blk_rx = OrderedStream::new(
@@ -178,6 +264,12 @@ impl<Tx: TxTrait + Serialize> BlockImportWorker<Tx> {
             latest_block_header.get_height().next_height(),
             |height| height.next_height(),
         );
+        if gap_timeout > Duration::ZERO {
+            let route_table = Arc::new(route_table);
+            blk_rx = blk_rx.with_gap_handler(gap_timeout, move |&height| {
+                fetch_missing_block_proposal(route_table.clone(), height).boxed()
+            });
+        }
         let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
         let handle: JoinHandle<()> = tokio::spawn(async move {
@@ -258,10 +350,20 @@ struct StorageNodeReqError(mpsc::SendError);
 
 impl warp::reject::Reject for StorageNodeReqError {}
 
+#[derive(Debug)]
+struct StorageNodeQueryError(Error);
+
+impl warp::reject::Reject for StorageNodeQueryError {}
+
+// Max number of blocks returned in one request.
+const MAX_BLOCKS_PER_REQ: u64 = 64;
+
 pub struct StorageNode<Tx: TxTrait + 'static> {
     srv: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
     exec_worker: TxExecWorker,
     import_worker: BlockImportWorker<Tx>,
+    admission: Arc<Admission>,
+    coordinator: ShutdownCoordinator,
 }
 
 impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> StorageNode<Tx> {
@@ -277,31 +379,51 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> StorageNode<
         let latest_block_header = snapshot.to_latest_block_header();
         let latest_tx_count = LatestTxCount::new(0);
 
-        let exec_worker =
-            TxExecWorker::new(net_cfg.to_route_table(), engine, &db, &latest_block_header);
+        let admission = Arc::new(Admission::new(&net_cfg.admission));
+        register_queue_depth(admission.queue_depth());
+        let exec_worker = TxExecWorker::new(
+            net_cfg.to_route_table(),
+            &net_cfg.leader_batch,
+            engine,
+            &db,
+            &latest_block_header,
+            admission.queue_depth(),
+        );
         let exec_worker_tx_req_tx = exec_worker.get_tx_req_tx();
 
         let import_worker = BlockImportWorker::new(
             chain_cfg.clone(),
             snapshot,
-            latest_block_header,
+            latest_block_header.clone(),
             latest_tx_count,
-            db,
+            db.clone(),
+            net_cfg.to_route_table(),
+            Duration::from_millis(net_cfg.block_gap_timeout),
         );
         let import_worker_blk_tx = import_worker.get_blk_tx();
 
+        let admission_copy = admission.clone();
         let tx_exec_srv = warp::post()
             .and(warp::path(STORAGE_TX_REQ_ROUTE_PATH))
+            .and(warp::addr::remote())
             .and(warp_body_binary())
-            .and_then(move |req: SignedTxRequest| {
+            .and_then(move |addr: Option<SocketAddr>, req: SignedTxRequest| {
                 record_event!("storage_recv_tx", "tx_id": req.id());
+                let admitted = admission_copy.admit(addr.map(|addr| addr.ip()), 1);
+                let queue_depth = admission_copy.queue_depth();
                 let mut exec_worker_tx_req_tx = exec_worker_tx_req_tx.clone();
                 async move {
-                    exec_worker_tx_req_tx
-                        .send(req)
-                        .await
-                        .map(|_| warp_reply_binary(&()))
-                        .map_err(|e| warp::reject::custom(StorageNodeReqError(e)))
+                    if let Err(e) = admitted {
+                        discard_tx!(req.id(), "storage_rate_limit", e.to_string());
+                        return Ok(reply_error(&e.into()));
+                    }
+                    match exec_worker_tx_req_tx.send(req).await {
+                        Ok(_) => Ok(warp_reply_binary(&()).into_response()),
+                        Err(e) => {
+                            queue_depth.decr(1);
+                            Err(warp::reject::custom(StorageNodeReqError(e)))
+                        }
+                    }
                 }
             });
 
@@ -320,24 +442,150 @@ impl<Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static> StorageNode<
                 }
             });
 
+        let db_copy = db.clone();
+        let latest_block_header_copy = latest_block_header.clone();
+        let blocks_srv = warp::post()
+            .and(warp::path(STORAGE_BLOCKS_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(move |(from, count): (BlockHeight, u64)| {
+                let end = from
+                    .0
+                    .saturating_add(count.min(MAX_BLOCKS_PER_REQ))
+                    .min(latest_block_header_copy.get_height().0 + 1);
+                let blocks: Result<Vec<Block>> = db_copy
+                    .iter_blocks(from..BlockHeight(end))
+                    .and_then(|blocks| blocks.collect());
+                async move {
+                    blocks
+                        .map(|blocks| warp_reply_binary(&blocks))
+                        .map_err(|e| warp::reject::custom(StorageNodeQueryError(e)))
+                }
+            });
+
+        let db_copy = db.clone();
+        let tx_srv = warp::post()
+            .and(warp::path(STORAGE_TX_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(move |tx_hash: H256| {
+                let tx: Result<Tx> = db_copy.get_tx(tx_hash);
+                async move {
+                    tx.map(|tx| warp_reply_binary(&tx))
+                        .map_err(|e| warp::reject::custom(StorageNodeQueryError(e)))
+                }
+            });
+
+        let db_rpc_srv = db_rpc_server(db.clone());
+        let status_srv = status_server(
+            StatusService::new::<Block>(
+                Role::Storage(shard_id),
+                db.clone(),
+                latest_block_header.clone(),
+            )
+            .with_peer_counts(PeerCounts::from_route_table(&net_cfg.to_route_table()))
+            .with_queue_depth(admission.queue_depth()),
+        );
+
+        let db_copy = db.clone();
+        let latest_block_header_copy = latest_block_header.clone();
+        let call_srv = warp::post()
+            .and(warp::path(STORAGE_CALL_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(move |req: ViewCallRequest| {
+                let db = db_copy.clone();
+                let latest_block_header = latest_block_header_copy.clone();
+                async move {
+                    // The EVM blocks the thread until the call returns.
+                    tokio::task::spawn_blocking(move || call_view(&db, &latest_block_header, req))
+                        .await
+                        .map_err(Error::from)
+                        .and_then(|res| res)
+                        .map(|output| warp_reply_binary(&output))
+                        .map_err(|e| warp::reject::custom(StorageNodeQueryError(e)))
+                }
+            });
+
+        let db_copy = db.clone();
+        let latest_block_header_copy = latest_block_header.clone();
+        let state_proof_srv = warp::post()
+            .and(warp::path(STORAGE_STATE_PROOF_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(
+                move |(height, address, key): (BlockHeight, Address, Option<StateKey>)| {
+                    let proof = (|| {
+                        ensure!(
+                            shard_id.contains(address),
+                            "Account {} is not in shard {:?}.",
+                            address,
+                            shard_id
+                        );
+                        ensure!(
+                            height <= latest_block_header_copy.get_height(),
+                            "Block #{} has not been committed yet.",
+                            height
+                        );
+                        prove_at_height::<Block>(&db_copy, height, address, key)
+                    })();
+                    async move {
+                        proof
+                            .map(|proof| warp_reply_binary(&proof))
+                            .map_err(|e| warp::reject::custom(StorageNodeQueryError(e)))
+                    }
+                },
+            );
+
+        let tx_proof_srv = warp::post()
+            .and(warp::path(STORAGE_TX_PROOF_ROUTE_PATH))
+            .and(warp_body_binary())
+            .and_then(move |tx_id: H256| {
+                let proof = get_tx_inclusion_proof::<Block>(&db, tx_id);
+                async move {
+                    proof
+                        .map(|proof| warp_reply_binary(&proof))
+                        .map_err(|e| warp::reject::custom(StorageNodeQueryError(e)))
+                }
+            });
+
         info!("Create http server, listen on {}", net_cfg.http_listen);
-        let listen_addr: SocketAddr = net_cfg.http_listen.parse()?;
+        let listen_addrs: Vec<SocketAddr> = net_cfg.http_listen.parse()?;
         let (srv_shutdown_tx, srv_shutdown_rx) = oneshot::channel::<()>();
-        let (_, srv) =
-            warp::serve(warp::path(NODE_RPC_ROUTE_PATH).and(tx_exec_srv.or(block_import_srv)))
-                .bind_with_graceful_shutdown(listen_addr, async {
-                    srv_shutdown_rx.await.ok();
-                });
+        let coordinator = ShutdownCoordinator::new();
+        let routes = shutdown_admin_server(coordinator.clone())
+            .or(db_rpc_srv)
+            .or(status_srv)
+            .or(metrics_server())
+            .or(warp::path(NODE_RPC_ROUTE_PATH).and(
+                tx_exec_srv
+                    .or(block_import_srv)
+                    .or(blocks_srv)
+                    .or(tx_srv)
+                    .or(tx_proof_srv)
+                    .or(state_proof_srv)
+                    .or(call_srv),
+            ));
+        let srv = warp_serve!(routes, listen_addrs, net_cfg.tls.as_ref(), async {
+            srv_shutdown_rx.await.ok();
+        });
         let srv_handle = tokio::spawn(srv);
 
         Ok(Self {
             srv: Some((srv_shutdown_tx, srv_handle)),
             exec_worker,
             import_worker,
+            admission,
+            coordinator,
         })
     }
 
+    pub fn shutdown_coordinator(&self) -> ShutdownCoordinator {
+        self.coordinator.clone()
+    }
+
+    pub fn admission(&self) -> Arc<Admission> {
+        self.admission.clone()
+    }
+
     pub async fn shutdown(&mut self) -> Result<()> {
+        self.admission.close();
         info!("Shutting down TxExecWorker...");
         self.exec_worker.shutdown().await?;
         info!("Shutting down BlockImportWorker...");
@@ -0,0 +1,102 @@
+use crate::http::config::LeaderBatchConfig;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// The size of the tx proposal batches sent to the leader. It doubles while the leader keeps up
+// with the full batches, and halves once the leader slows down.
+#[derive(Debug)]
+pub struct AdaptiveBatchSize {
+    min: usize,
+    max: usize,
+    target_latency: Duration,
+    size: AtomicUsize,
+}
+
+impl AdaptiveBatchSize {
+    pub fn new(cfg: &LeaderBatchConfig) -> Self {
+        let min = cfg.min_batch.max(1);
+        let max = cfg.max_batch.max(min);
+        Self {
+            min,
+            max,
+            target_latency: Duration::from_millis(cfg.target_latency),
+            size: AtomicUsize::new(min),
+        }
+    }
+
+    pub fn get(&self) -> usize {
+        self.size.load(Ordering::Acquire)
+    }
+
+    pub fn record(&self, batch_len: usize, latency: Duration) {
+        self.size
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |size| {
+                if latency > self.target_latency {
+                    Some((size / 2).max(self.min))
+                } else if batch_len >= size {
+                    Some((size * 2).min(self.max))
+                } else {
+                    None
+                }
+            })
+            .ok();
+    }
+}
+
+// Split the ready tx proposals into the batches of the given size, in order.
+pub fn split_batches<T>(mut items: Vec<T>, size: usize) -> Vec<Vec<T>> {
+    let size = size.max(1);
+    let mut batches = Vec::new();
+    while items.len() > size {
+        let rest = items.split_off(size);
+        batches.push(items);
+        items = rest;
+    }
+    if !items.is_empty() {
+        batches.push(items);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_batch_size() {
+        let cfg = LeaderBatchConfig {
+            min_batch: 8,
+            max_batch: 32,
+            window: 4,
+            target_latency: 50,
+        };
+        let size = AdaptiveBatchSize::new(&cfg);
+        assert_eq!(8, size.get());
+
+        size.record(4, Duration::from_millis(10));
+        assert_eq!(8, size.get());
+        size.record(8, Duration::from_millis(10));
+        assert_eq!(16, size.get());
+        size.record(16, Duration::from_millis(10));
+        size.record(32, Duration::from_millis(10));
+        assert_eq!(32, size.get());
+
+        size.record(32, Duration::from_millis(100));
+        assert_eq!(16, size.get());
+        size.record(16, Duration::from_millis(100));
+        size.record(8, Duration::from_millis(100));
+        assert_eq!(8, size.get());
+    }
+
+    #[test]
+    fn test_split_batches() {
+        assert_eq!(
+            vec![vec![1, 2, 3], vec![4, 5, 6], vec![7]],
+            split_batches((1..=7).collect(), 3)
+        );
+        assert_eq!(vec![vec![1, 2]], split_batches(vec![1, 2], 3));
+        assert!(split_batches(Vec::<u32>::new(), 3).is_empty());
+    }
+}
@@ -1,6 +1,26 @@
-use crate::http::config::PeerId;
-use async_raft::{AppData, AppDataResponse, Raft, RaftNetwork, RaftStorage};
-use slimchain_common::error::{anyhow, Result};
+use crate::http::{
+    admin::MembershipChange,
+    config::{NetworkRouteTable, PeerId},
+    node_rpc::{fetch_blocks_from_storage, get_read_index},
+};
+use async_raft::{
+    error::{ChangeConfigError, ClientReadError},
+    AppData, AppDataResponse, Raft, RaftNetwork, RaftStorage,
+};
+use futures::future;
+use rand::seq::{IteratorRandom, SliceRandom};
+use serde::Deserialize;
+use slimchain_chain::{block::BlockTrait, role::Role};
+use slimchain_common::{
+    basic::{Address, BlockHeight, H256},
+    collections::HashMap,
+    digest::Digestible,
+    error::{anyhow, bail, ensure, ConsensusError, Context as _, Error, Result},
+};
+use std::time::Duration;
+
+// How long a linearizable read waits for the node to apply the log up to the read index.
+const READ_INDEX_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub async fn get_current_leader<D, R, N, S>(raft: &Raft<D, R, N, S>) -> Result<PeerId>
 where
@@ -25,3 +45,242 @@ where
 {
     raft.metrics().borrow().state.is_leader()
 }
+
+pub fn current_members<D, R, N, S>(raft: &Raft<D, R, N, S>) -> Vec<PeerId>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    raft.metrics()
+        .borrow()
+        .membership_config
+        .members
+        .iter()
+        .map(|&id| PeerId::from(id))
+        .collect()
+}
+
+// The read index of the leader. The log up to the index is all committed, and it is taken
+// before the leadership is confirmed, so that no newer leader can have committed more.
+pub async fn leader_read_index<D, R, N, S>(raft: &Raft<D, R, N, S>) -> Result<u64>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    let read_index = raft.metrics().borrow().last_log_index;
+    match raft.client_read().await {
+        Ok(()) => Ok(read_index),
+        Err(ClientReadError::ForwardToLeader(leader)) => Err(Error::msg(ConsensusError::NotLeader))
+            .with_context(|| format!("Failed to get the read index. Leader: {:?}.", leader)),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+pub async fn wait_for_applied<D, R, N, S>(
+    raft: &Raft<D, R, N, S>,
+    index: u64,
+    timeout: Duration,
+) -> Result<()>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    let mut metrics = raft.metrics();
+    let wait = async {
+        while metrics.borrow().last_applied < index {
+            metrics
+                .changed()
+                .await
+                .context("The raft node is shut down.")?;
+        }
+        Ok::<_, Error>(())
+    };
+    tokio::time::timeout(timeout, wait)
+        .await
+        .with_context(|| format!("Timeout in applying the log up to {}.", index))?
+}
+
+// Block until the local state is at least as new as the leader's, so that the following read
+// is linearizable. The followers ask the leader for the read index.
+pub async fn linearizable_read_barrier<D, R, N, S>(
+    raft: &Raft<D, R, N, S>,
+    route_table: &NetworkRouteTable,
+) -> Result<()>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    let read_index = if node_is_leader(raft) {
+        leader_read_index(raft).await?
+    } else {
+        let leader = get_current_leader(raft).await?;
+        get_read_index(route_table.peer_address(leader)?).await?
+    };
+    wait_for_applied(raft, read_index, READ_INDEX_TIMEOUT).await
+}
+
+// Apply the change on the leader and return the new voters. A new voter is added as a
+// non-voter first, which returns once it catches up with the log.
+pub async fn change_membership<D, R, N, S>(
+    raft: &Raft<D, R, N, S>,
+    change: MembershipChange,
+) -> Result<Vec<PeerId>>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D>,
+    S: RaftStorage<D, R>,
+{
+    if !node_is_leader(raft) {
+        let leader = get_current_leader(raft).await.ok();
+        return Err(Error::msg(ConsensusError::NotLeader))
+            .with_context(|| format!("Failed to change the membership. Leader: {:?}.", leader));
+    }
+
+    let mut members = raft.metrics().borrow().membership_config.members.clone();
+    match change {
+        MembershipChange::AddVoter(peer_id) => {
+            if !members.insert(peer_id.0) {
+                return Ok(current_members(raft));
+            }
+            match raft.add_non_voter(peer_id.0).await {
+                Ok(_) | Err(ChangeConfigError::Noop) => {}
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+        MembershipChange::RemoveVoter(peer_id) => {
+            if !members.remove(&peer_id.0) {
+                bail!("{} is not a voter.", peer_id);
+            }
+        }
+        MembershipChange::AddLearner(peer_id) => {
+            bail!(
+                "{} should be added as a learner without changing the voters.",
+                peer_id
+            );
+        }
+    }
+
+    match raft.change_membership(members.clone()).await {
+        Ok(_) | Err(ChangeConfigError::Noop) => {}
+        Err(e) => return Err(Error::from(e)),
+    }
+    Ok(members.into_iter().map(PeerId::from).collect())
+}
+
+pub fn random_storage_node(route_table: &NetworkRouteTable) -> Result<&String> {
+    let mut rng = rand::thread_rng();
+    route_table
+        .role_table()
+        .iter()
+        .filter(|(role, _)| matches!(role, Role::Storage(_)))
+        .flat_map(|(_, list)| list.iter())
+        .choose(&mut rng)
+        .ok_or_else(|| anyhow!("Failed to find the storage node."))
+        .and_then(|&peer_id| route_table.peer_address(peer_id))
+}
+
+// All the storage nodes in random order.
+pub fn storage_nodes(route_table: &NetworkRouteTable) -> Result<Vec<&String>> {
+    let mut peers: Vec<_> = route_table
+        .role_table()
+        .iter()
+        .filter(|(role, _)| matches!(role, Role::Storage(_)))
+        .flat_map(|(_, list)| list.iter())
+        .collect();
+    ensure!(!peers.is_empty(), "Failed to find the storage node.");
+    peers.shuffle(&mut rand::thread_rng());
+    peers
+        .into_iter()
+        .map(|&peer_id| route_table.peer_address(peer_id))
+        .collect()
+}
+
+// Fetch the blocks from all the storage nodes, and keep the leading ones that a majority of them
+// agree on. So that a minority of faulty nodes can neither forge the blocks nor fork the chain.
+// A lagging node only shortens the result once it leaves less than a majority with the block.
+pub async fn fetch_agreed_blocks<Block>(
+    route_table: &NetworkRouteTable,
+    from: BlockHeight,
+    count: u64,
+) -> Result<Vec<Block>>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de>,
+{
+    let storage_addrs = storage_nodes(route_table)?;
+    let quorum = storage_addrs.len() / 2 + 1;
+    let resps = future::join_all(
+        storage_addrs
+            .iter()
+            .map(|&addr| fetch_blocks_from_storage::<Block>(addr, from, count)),
+    )
+    .await;
+    let mut candidates: Vec<Vec<Block>> = Vec::with_capacity(resps.len());
+    for (addr, resp) in storage_addrs.into_iter().zip(resps) {
+        match resp {
+            Ok(blocks) => candidates.push(blocks),
+            Err(e) => warn!("Failed to fetch the blocks from {}. Error: {}", addr, e),
+        }
+    }
+
+    let mut agreed = Vec::new();
+    for i in 0..count as usize {
+        let mut votes: HashMap<H256, (usize, &Block)> = HashMap::new();
+        for blk in candidates.iter().filter_map(|blocks| blocks.get(i)) {
+            votes.entry(blk.to_digest()).or_insert((0, blk)).0 += 1;
+        }
+        match votes.values().find(|&&(n, _)| n >= quorum) {
+            Some(&(_, blk)) => agreed.push(blk.clone()),
+            None => break,
+        }
+    }
+    Ok(agreed)
+}
+
+// A storage node whose shard holds the account.
+pub fn random_storage_node_for(
+    route_table: &NetworkRouteTable,
+    address: Address,
+) -> Result<&String> {
+    let mut rng = rand::thread_rng();
+    route_table
+        .role_table()
+        .iter()
+        .filter(|(role, _)| matches!(role, Role::Storage(shard_id) if shard_id.contains(address)))
+        .flat_map(|(_, list)| list.iter())
+        .choose(&mut rng)
+        .ok_or_else(|| anyhow!("Failed to find the storage node of account {}.", address))
+        .and_then(|&peer_id| route_table.peer_address(peer_id))
+}
+
+pub fn other_client_nodes(route_table: &NetworkRouteTable) -> Result<Vec<&String>> {
+    route_table
+        .role_table()
+        .get(&Role::Client)
+        .into_iter()
+        .flat_map(|list| list.iter())
+        .filter(|&&peer_id| peer_id != route_table.peer_id())
+        .map(|&peer_id| route_table.peer_address(peer_id))
+        .collect()
+}
+
+pub fn random_other_client_node(route_table: &NetworkRouteTable) -> Result<&String> {
+    let mut rng = rand::thread_rng();
+    route_table
+        .role_table()
+        .get(&Role::Client)
+        .into_iter()
+        .flat_map(|list| list.iter())
+        .filter(|&&peer_id| peer_id != route_table.peer_id())
+        .choose(&mut rng)
+        .ok_or_else(|| anyhow!("Failed to find other client nodes."))
+        .and_then(|&peer_id| route_table.peer_address(peer_id))
+}
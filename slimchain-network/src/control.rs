@@ -0,0 +1,94 @@
+use slimchain_common::error::Result;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Notify, task::JoinHandle};
+
+// Max time given to a worker to finish the accepted work before it is cancelled.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The shutdown is requested either by SIGINT/SIGTERM or through the admin RPC. The node then
+// stops accepting new txs, drains its workers and flushes the snapshot before it exits.
+#[derive(Debug, Default, Clone)]
+pub struct ShutdownCoordinator {
+    requested: Arc<Notify>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        info!("Shutdown is requested.");
+        self.requested.notify_one();
+    }
+
+    pub async fn wait(&self) -> Result<()> {
+        info!("Press Ctrl-C to quit.");
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res?,
+            res = terminate_signal() => res?,
+            _ = self.requested.notified() => {}
+        }
+        info!("Quitting.");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+async fn terminate_signal() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    signal(SignalKind::terminate())?.recv().await;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn terminate_signal() -> Result<()> {
+    futures::future::pending().await
+}
+
+// Wait for the worker to finish once its input is closed. It is cancelled on timeout.
+pub async fn drain_worker(
+    name: &str,
+    mut handle: JoinHandle<()>,
+    cancel: impl FnOnce(),
+) -> Result<()> {
+    info!("Draining {}...", name);
+    match tokio::time::timeout(DRAIN_TIMEOUT, &mut handle).await {
+        Ok(res) => res?,
+        Err(_) => {
+            warn!("Timeout in draining {}. Cancel the remaining work.", name);
+            cancel();
+            handle.await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_coordinator() {
+        let coordinator = ShutdownCoordinator::new();
+        let waiter = tokio::spawn({
+            let coordinator = coordinator.clone();
+            async move { coordinator.wait().await }
+        });
+        coordinator.request();
+        waiter.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drain_worker() {
+        let (tx, rx) = futures::channel::oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            rx.await.ok();
+        });
+        tx.send(()).unwrap();
+        drain_worker("test", handle, || panic!("Should not cancel."))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+use crate::http::client_rpc::TxHttpRequest;
+use slimchain_chain::{block::BlockHeader, receipt::TxReceipt, tx_event::TxEvent};
+use slimchain_common::{
+    basic::{Address, BlockHeight, Code, Nonce, ShardId, H160, H256, U256},
+    digest::Digestible,
+    ed25519,
+    error::{ensure, Context as _, Error, Result},
+    secp256k1,
+    signature::SignatureScheme,
+    tx_req::{SignedTxRequest, TxRequest},
+};
+use tonic::transport::Channel;
+
+pub mod server;
+
+pub mod pb {
+    tonic::include_proto!("slimchain.node_rpc");
+}
+
+pub use pb::node_rpc_client::NodeRpcClient;
+
+pub async fn connect_node_rpc(endpoint: &str) -> Result<NodeRpcClient<Channel>> {
+    NodeRpcClient::connect(format!("http://{}", endpoint))
+        .await
+        .map_err(Error::msg)
+}
+
+fn h256_from_pb(bytes: &[u8]) -> Result<H256> {
+    ensure!(bytes.len() == 32, "Invalid hash length {}.", bytes.len());
+    Ok(H256::from_slice(bytes))
+}
+
+fn nonce_to_pb(nonce: Nonce) -> Vec<u8> {
+    let mut bytes = vec![0u8; 32];
+    nonce.0.to_big_endian(&mut bytes);
+    bytes
+}
+
+fn nonce_from_pb(bytes: &[u8]) -> Result<Nonce> {
+    ensure!(bytes.len() <= 32, "Invalid nonce length {}.", bytes.len());
+    Ok(Nonce(U256::from_big_endian(bytes)))
+}
+
+fn address_from_pb(bytes: &[u8]) -> Result<Address> {
+    ensure!(bytes.len() == 20, "Invalid address length {}.", bytes.len());
+    Ok(Address(H160::from_slice(bytes)))
+}
+
+impl From<H256> for pb::TxId {
+    fn from(id: H256) -> Self {
+        Self {
+            id: id.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&pb::TxId> for H256 {
+    type Error = Error;
+
+    fn try_from(id: &pb::TxId) -> Result<Self> {
+        h256_from_pb(&id.id)
+    }
+}
+
+pub fn signed_tx_req_to_pb(req: &SignedTxRequest, shard_id: ShardId) -> pb::SignedTxRequest {
+    use pb::signed_tx_request::Input;
+
+    let input = match &req.input {
+        TxRequest::Create { nonce, code, .. } => Input::Create(pb::CreateTx {
+            nonce: nonce_to_pb(*nonce),
+            code: code.0.clone(),
+        }),
+        TxRequest::Call {
+            nonce,
+            address,
+            data,
+            ..
+        } => Input::Call(pb::CallTx {
+            nonce: nonce_to_pb(*nonce),
+            address: address.as_bytes().to_vec(),
+            data: data.clone(),
+        }),
+    };
+
+    let (scheme, public_key, signature) = match &req.pk_sig {
+        SignatureScheme::Ed25519(pk_sig) => (
+            pb::SignatureScheme::Ed25519,
+            pk_sig.public().to_bytes().to_vec(),
+            pk_sig.signature().to_bytes().to_vec(),
+        ),
+        SignatureScheme::Secp256k1(pk_sig) => (
+            pb::SignatureScheme::Secp256k1,
+            pk_sig.public().serialize_compressed().to_vec(),
+            pk_sig.signature().serialize().to_vec(),
+        ),
+    };
+
+    pb::SignedTxRequest {
+        input: Some(input),
+        public_key,
+        signature,
+        shard_id: shard_id.id,
+        shard_total: shard_id.total,
+        scheme: scheme as i32,
+        chain_id: req.input.chain_id(),
+        expiry: req.input.expiry().map_or(0, |h| h.0),
+    }
+}
+
+pub fn signed_tx_req_from_pb(req: pb::SignedTxRequest) -> Result<TxHttpRequest> {
+    use pb::signed_tx_request::Input;
+
+    let chain_id = req.chain_id;
+    let expiry = (req.expiry != 0).then(|| BlockHeight(req.expiry));
+    let input = match req.input.context("Missing tx input.")? {
+        Input::Create(pb::CreateTx { nonce, code }) => TxRequest::Create {
+            nonce: nonce_from_pb(&nonce)?,
+            code: Code(code),
+            chain_id,
+            expiry,
+        },
+        Input::Call(pb::CallTx {
+            nonce,
+            address,
+            data,
+        }) => TxRequest::Call {
+            nonce: nonce_from_pb(&nonce)?,
+            address: address_from_pb(&address)?,
+            data,
+            chain_id,
+            expiry,
+        },
+    };
+    let scheme = pb::SignatureScheme::from_i32(req.scheme).context("Unknown signature scheme.")?;
+    let pk_sig = match scheme {
+        pb::SignatureScheme::Ed25519 => SignatureScheme::Ed25519(ed25519::PubSigPair {
+            pk: ed25519::PublicKey::from_bytes(&req.public_key).map_err(Error::msg)?,
+            sig: ed25519::Signature::try_from(&req.signature[..]).map_err(Error::msg)?,
+        }),
+        pb::SignatureScheme::Secp256k1 => SignatureScheme::Secp256k1(secp256k1::PubSigPair {
+            pk: secp256k1::pk_from_bytes(&req.public_key)?,
+            sig: secp256k1::sig_from_bytes(&req.signature)?,
+        }),
+    };
+    // Keep the same default as the HTTP RPC when the shard is not given.
+    let shard_id = if req.shard_total == 0 {
+        ShardId::default()
+    } else {
+        ShardId::new(req.shard_id, req.shard_total)
+    };
+
+    Ok(TxHttpRequest {
+        req: SignedTxRequest { input, pk_sig },
+        shard_id,
+    })
+}
+
+impl From<&BlockHeader> for pb::BlockHeader {
+    fn from(header: &BlockHeader) -> Self {
+        Self {
+            height: header.height.0,
+            prev_blk_hash: header.prev_blk_hash.as_bytes().to_vec(),
+            time_stamp: header.time_stamp.timestamp_millis(),
+            tx_list: header.tx_list.iter().map(|&id| id.into()).collect(),
+            tx_root: header.tx_root.as_bytes().to_vec(),
+            state_root: header.state_root.as_bytes().to_vec(),
+            epoch_root: header.epoch_root.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<&BlockHeader> for pb::Block {
+    fn from(header: &BlockHeader) -> Self {
+        Self {
+            header: Some(header.into()),
+            hash: header.to_digest().as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<&TxReceipt> for pb::TxReceipt {
+    fn from(receipt: &TxReceipt) -> Self {
+        Self {
+            block_height: receipt.block_height.0,
+            index: receipt.index as u64,
+            gas_used: receipt.gas_used.unwrap_or_default(),
+            logs: receipt
+                .logs
+                .iter()
+                .map(|log| pb::TxLog {
+                    address: log.address.as_bytes().to_vec(),
+                    topics: log.topics.iter().map(|t| t.as_bytes().to_vec()).collect(),
+                    data: log.data.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&TxEvent> for pb::TxEvent {
+    fn from(event: &TxEvent) -> Self {
+        use pb::tx_event::Event;
+
+        match event {
+            TxEvent::Committed { tx_id, height } => Self {
+                tx_id: Some((*tx_id).into()),
+                event: Some(Event::CommittedHeight(height.0)),
+            },
+            TxEvent::Discarded {
+                tx_id,
+                reason,
+                detail,
+            } => Self {
+                tx_id: Some((*tx_id).into()),
+                event: Some(Event::Discarded(pb::TxDiscarded {
+                    reason: reason.clone(),
+                    detail: detail.clone().unwrap_or_default(),
+                })),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::ed25519::Keypair;
+
+    #[test]
+    fn test_signed_tx_req_pb() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let req = TxRequest::Call {
+            nonce: 42.into(),
+            address: H160::repeat_byte(0xf).into(),
+            data: b"data".to_vec(),
+            chain_id: 1,
+            expiry: Some(BlockHeight(10)),
+        }
+        .sign(&keypair);
+        let shard_id = ShardId::new(1, 2);
+
+        let pb_req = signed_tx_req_to_pb(&req, shard_id);
+        let actual = signed_tx_req_from_pb(pb_req.clone()).unwrap();
+        assert_eq!(req, actual.req);
+        assert_eq!(shard_id, actual.shard_id);
+        assert!(actual.req.verify().is_ok());
+
+        let mut bad_req = pb_req;
+        bad_req.public_key.pop();
+        assert!(signed_tx_req_from_pb(bad_req).is_err());
+    }
+
+    #[test]
+    fn test_secp256k1_signed_tx_req_pb() {
+        let mut rng = rand::thread_rng();
+        let keypair = secp256k1::Keypair::generate(&mut rng);
+        let req = TxRequest::Call {
+            nonce: 42.into(),
+            address: H160::repeat_byte(0xf).into(),
+            data: b"data".to_vec(),
+            chain_id: 0,
+            expiry: None,
+        }
+        .sign(&keypair);
+
+        let pb_req = signed_tx_req_to_pb(&req, ShardId::default());
+        assert_eq!(pb::SignatureScheme::Secp256k1 as i32, pb_req.scheme);
+        let actual = signed_tx_req_from_pb(pb_req.clone()).unwrap();
+        assert_eq!(req, actual.req);
+        assert!(actual.req.verify().is_ok());
+
+        // The signature from the Ethereum wallets carries the recovery id.
+        let mut wallet_req = pb_req.clone();
+        wallet_req.signature.push(27);
+        assert_eq!(req, signed_tx_req_from_pb(wallet_req).unwrap().req);
+
+        let mut bad_req = pb_req;
+        bad_req.scheme = pb::SignatureScheme::Ed25519 as i32;
+        assert!(signed_tx_req_from_pb(bad_req).is_err());
+    }
+}
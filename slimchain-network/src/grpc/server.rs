@@ -0,0 +1,373 @@
+use super::{pb, signed_tx_req_from_pb};
+use crate::{
+    http::{
+        admission::Admission,
+        client_rpc::{RecordEventHttpRequest, TxHttpRequest},
+        common::check_admin_authorization,
+        config::PeerId,
+    },
+    tx_trace::record_tx_trace,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::BoxFuture,
+    prelude::*,
+};
+use serde::Deserialize;
+use slimchain_chain::{
+    block::{BlockHeader, BlockTrait},
+    db::DBPtr,
+    latest::LatestBlockHeaderPtr,
+    loader::BlockLoaderTrait,
+    tx_event::subscribe_tx_events,
+};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    error::{bail, error_code, Error, ErrorCode, Result},
+};
+use std::{marker::PhantomData, net::SocketAddr, path::PathBuf, pin::Pin, sync::Arc};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tonic::{Request, Response, Status};
+
+pub use pb::node_rpc_server::NodeRpcServer;
+
+// The buffer of each stream. A subscriber falling behind blocks its own stream only.
+const STREAM_BUFFER_SIZE: usize = 64;
+
+type TxReqFn = dyn Fn(Vec<TxHttpRequest>) -> BoxFuture<'static, Result<()>> + Send + Sync;
+type LeaderFn = dyn Fn() -> BoxFuture<'static, Result<PeerId>> + Send + Sync;
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + Sync>>;
+
+// The metadata carrying the `ErrorCode` of the failed call.
+pub const ERROR_CODE_METADATA: &str = "x-error-code";
+
+fn grpc_code(code: ErrorCode) -> tonic::Code {
+    match code {
+        ErrorCode::Internal | ErrorCode::ExecutionFailed => tonic::Code::Internal,
+        ErrorCode::InvalidSignature
+        | ErrorCode::InvalidChainId
+        | ErrorCode::TxExpired
+        | ErrorCode::InvalidProof
+        | ErrorCode::InvalidBlock
+        | ErrorCode::InvalidRequest => tonic::Code::InvalidArgument,
+        ErrorCode::Conflict | ErrorCode::Outdated => tonic::Code::Aborted,
+        ErrorCode::NotFound => tonic::Code::NotFound,
+        ErrorCode::NotLeader => tonic::Code::FailedPrecondition,
+        ErrorCode::RateLimited | ErrorCode::Overloaded => tonic::Code::ResourceExhausted,
+        ErrorCode::ShuttingDown => tonic::Code::Unavailable,
+        ErrorCode::Unauthorized => tonic::Code::Unauthenticated,
+    }
+}
+
+fn to_status(e: Error) -> Status {
+    let code = error_code(&e);
+    let mut status = Status::new(grpc_code(code), e.to_string());
+    status
+        .metadata_mut()
+        .insert(ERROR_CODE_METADATA, code.as_u16().into());
+    status
+}
+
+pub struct NodeRpcService<Block> {
+    db: DBPtr,
+    latest_block_header: LatestBlockHeaderPtr,
+    tx_req_fn: Arc<TxReqFn>,
+    leader_fn: Option<Arc<LeaderFn>>,
+    admission: Option<Arc<Admission>>,
+    _marker: PhantomData<fn() -> Block>,
+}
+
+impl<Block> NodeRpcService<Block> {
+    pub fn new<TxReqOutput>(
+        db: DBPtr,
+        latest_block_header: LatestBlockHeaderPtr,
+        tx_req_fn: impl Fn(Vec<TxHttpRequest>) -> TxReqOutput + Send + Sync + 'static,
+    ) -> Self
+    where
+        TxReqOutput: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self {
+            db,
+            latest_block_header,
+            tx_req_fn: Arc::new(move |reqs| tx_req_fn(reqs).boxed()),
+            leader_fn: None,
+            admission: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_leader_fn<LeaderOutput>(
+        mut self,
+        leader_fn: impl Fn() -> LeaderOutput + Send + Sync + 'static,
+    ) -> Self
+    where
+        LeaderOutput: Future<Output = Result<PeerId>> + Send + 'static,
+    {
+        self.leader_fn = Some(Arc::new(move || leader_fn().boxed()));
+        self
+    }
+
+    // Share the rate limits and the queue depth with the HTTP tx endpoint.
+    pub fn with_admission(mut self, admission: Arc<Admission>) -> Self {
+        self.admission = Some(admission);
+        self
+    }
+}
+
+// The admin calls carry the admin token in the metadata, the same as the `Authorization` header of
+// the admin HTTP endpoints.
+fn check_admin<T>(request: &Request<T>) -> Result<(), Status> {
+    let authorization = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok());
+    check_admin_authorization(authorization).map_err(|e| to_status(e.into()))
+}
+
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(Error::msg)
+        .and_then(|res| res)
+        .map_err(to_status)
+}
+
+// Forward the blocks to the subscriber. The blocks skipped by the watch channel are loaded
+// from the database, so that no block is missed.
+async fn forward_blocks<Block>(
+    db: DBPtr,
+    mut block_rx: tokio::sync::watch::Receiver<Arc<BlockHeader>>,
+    mut next_height: Option<BlockHeight>,
+    mut tx: mpsc::Sender<Result<pb::Block, Status>>,
+) -> Result<()>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de> + 'static,
+{
+    loop {
+        let header = block_rx.borrow().clone();
+        if let Some(height) = next_height {
+            if height < header.height {
+                for block in db.iter_blocks::<Block>(height..header.height)? {
+                    tx.send(Ok(block?.block_header().into())).await?;
+                }
+            }
+            if height <= header.height {
+                tx.send(Ok(header.as_ref().into())).await?;
+            }
+        }
+        next_height = Some(header.height.next_height());
+
+        if block_rx.changed().await.is_err() {
+            bail!("The latest block header is dropped.");
+        }
+    }
+}
+
+async fn forward_tx_events(
+    mut tx_rx: broadcast::Receiver<slimchain_chain::tx_event::TxEvent>,
+    mut tx: mpsc::Sender<Result<pb::TxEvent, Status>>,
+) -> Result<()> {
+    loop {
+        let event = match tx_rx.recv().await {
+            Ok(event) => Ok((&event).into()),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Err(Status::data_loss(format!(
+                "The subscriber is lagged. {} events are dropped.",
+                skipped
+            ))),
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        let lagged = event.is_err();
+        tx.send(event).await?;
+        if lagged {
+            return Ok(());
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<Block> pb::node_rpc_server::NodeRpc for NodeRpcService<Block>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de> + 'static,
+{
+    async fn submit_tx(
+        &self,
+        request: Request<pb::SubmitTxRequest>,
+    ) -> Result<Response<pb::SubmitTxResponse>, Status> {
+        let remote_ip = request.remote_addr().map(|addr| addr.ip());
+        let reqs = request
+            .into_inner()
+            .reqs
+            .into_iter()
+            .map(signed_tx_req_from_pb)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let tx_ids = reqs.iter().map(|req| req.req.id().into()).collect();
+        let num_reqs = reqs.len();
+        if let Some(admission) = self.admission.as_ref() {
+            admission
+                .admit(remote_ip, num_reqs)
+                .map_err(|e| to_status(e.into()))?;
+        }
+        record_tx_trace(&reqs);
+        if let Err(e) = (self.tx_req_fn)(reqs).await {
+            if let Some(admission) = self.admission.as_ref() {
+                admission.queue_depth().decr(num_reqs);
+            }
+            return Err(to_status(e));
+        }
+        Ok(Response::new(pb::SubmitTxResponse { tx_ids }))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<pb::BlockHeightResponse>, Status> {
+        Ok(Response::new(pb::BlockHeightResponse {
+            height: self.latest_block_header.get_height().0,
+        }))
+    }
+
+    async fn get_block(
+        &self,
+        request: Request<pb::GetBlockRequest>,
+    ) -> Result<Response<pb::Block>, Status> {
+        let height = BlockHeight(request.into_inner().height);
+        if height > self.latest_block_header.get_height() {
+            return Err(Status::not_found(format!("Block #{} not found.", height)));
+        }
+        let db = self.db.clone();
+        let block: Block = run_blocking(move || db.get_block(height)).await?;
+        Ok(Response::new(block.block_header().into()))
+    }
+
+    async fn get_tx_receipt(
+        &self,
+        request: Request<pb::TxId>,
+    ) -> Result<Response<pb::TxReceiptResponse>, Status> {
+        let tx_id = H256::try_from(request.get_ref())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let db = self.db.clone();
+        let receipt = run_blocking(move || db.get_tx_receipt(tx_id)).await?;
+        Ok(Response::new(pb::TxReceiptResponse {
+            receipt: receipt.as_ref().map(Into::into),
+        }))
+    }
+
+    async fn get_leader(
+        &self,
+        request: Request<pb::Empty>,
+    ) -> Result<Response<pb::LeaderResponse>, Status> {
+        check_admin(&request)?;
+        let leader_fn = self
+            .leader_fn
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("The node has no leader."))?;
+        let leader = leader_fn().await.map_err(to_status)?;
+        Ok(Response::new(pb::LeaderResponse { peer_id: leader.0 }))
+    }
+
+    async fn record_event(
+        &self,
+        request: Request<pb::RecordEventRequest>,
+    ) -> Result<Response<pb::Empty>, Status> {
+        let pb::RecordEventRequest { info, data } = request.into_inner();
+        let data = if data.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&data).map_err(|e| Status::invalid_argument(e.to_string()))?)
+        };
+        RecordEventHttpRequest { info, data }.emit_record_event();
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn backup_db(
+        &self,
+        request: Request<pb::BackupDbRequest>,
+    ) -> Result<Response<pb::BackupDbResponse>, Status> {
+        check_admin(&request)?;
+        let path = PathBuf::from(request.into_inner().path);
+        let db = self.db.clone();
+        let info = run_blocking(move || db.backup(&path)).await?;
+        Ok(Response::new(pb::BackupDbResponse {
+            height: info.height.0,
+            num_keys: info.num_keys,
+        }))
+    }
+
+    type SubscribeBlocksStream = ResponseStream<pb::Block>;
+
+    async fn subscribe_blocks(
+        &self,
+        request: Request<pb::SubscribeBlocksRequest>,
+    ) -> Result<Response<Self::SubscribeBlocksStream>, Status> {
+        let req = request.into_inner();
+        let next_height = if req.replay {
+            Some(BlockHeight(req.from_height))
+        } else {
+            None
+        };
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let db = self.db.clone();
+        let block_rx = self.latest_block_header.subscribe();
+        tokio::spawn(async move {
+            let mut err_tx = tx.clone();
+            if let Err(e) = forward_blocks::<Block>(db, block_rx, next_height, tx).await {
+                err_tx.send(Err(to_status(e))).await.ok();
+            }
+        });
+        Ok(Response::new(Box::pin(rx)))
+    }
+
+    type SubscribeTxEventsStream = ResponseStream<pb::TxEvent>;
+
+    async fn subscribe_tx_events(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<Self::SubscribeTxEventsStream>, Status> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let tx_rx = subscribe_tx_events();
+        tokio::spawn(async move {
+            forward_tx_events(tx_rx, tx).await.ok();
+        });
+        Ok(Response::new(Box::pin(rx)))
+    }
+}
+
+pub struct NodeRpcServerHandle {
+    shutdown_tx: oneshot::Sender<()>,
+    handle: JoinHandle<Result<()>>,
+}
+
+impl NodeRpcServerHandle {
+    pub async fn shutdown(self) -> Result<()> {
+        self.shutdown_tx.send(()).ok();
+        self.handle.await?
+    }
+}
+
+pub fn spawn_node_rpc_server<Block>(
+    endpoint: &str,
+    service: NodeRpcService<Block>,
+) -> Result<NodeRpcServerHandle>
+where
+    Block: BlockTrait + for<'de> Deserialize<'de> + 'static,
+{
+    info!("Create gRPC server, listen on {}", endpoint);
+    let listen_addr: SocketAddr = endpoint.parse()?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(NodeRpcServer::new(service))
+            .serve_with_shutdown(listen_addr, async {
+                shutdown_rx.await.ok();
+            })
+            .await
+            .map_err(Error::msg)
+    });
+    Ok(NodeRpcServerHandle {
+        shutdown_tx,
+        handle,
+    })
+}
@@ -1,4 +1,13 @@
+pub mod admin;
+pub mod admission;
+pub mod auditor_rpc;
 pub mod client_rpc;
 pub mod common;
 pub mod config;
+pub mod db_rpc;
+pub mod light_client_rpc;
+pub mod metrics;
+pub mod mining_rpc;
 pub mod node_rpc;
+pub mod status;
+pub mod subscription;
@@ -0,0 +1,356 @@
+use super::{common::*, config::PeerId};
+use crate::control::ShutdownCoordinator;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use slimchain_common::error::{Error, Result};
+use std::sync::Arc;
+use warp::{Filter, Reply};
+
+pub const ADMIN_ROUTE_PATH: &str = "admin";
+const MEMBERSHIP_ROUTE_PATH: &str = "membership";
+const ADD_VOTER_ROUTE_PATH: &str = "add_voter";
+const REMOVE_VOTER_ROUTE_PATH: &str = "remove_voter";
+const ADD_LEARNER_ROUTE_PATH: &str = "add_learner";
+const TRANSFER_LEADER_ROUTE_PATH: &str = "transfer_leader";
+const SHUTDOWN_ROUTE_PATH: &str = "shutdown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipChange {
+    AddVoter(PeerId),
+    RemoveVoter(PeerId),
+    AddLearner(PeerId),
+}
+
+// The learners replicate the log without voting.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaftMembership {
+    pub voters: Vec<PeerId>,
+    pub learners: Vec<PeerId>,
+}
+
+impl RaftMembership {
+    fn sorted(mut self) -> Self {
+        self.voters.sort_unstable();
+        self.learners.sort_unstable();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipHttpRequest {
+    pub peer_id: PeerId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderTransferHttpRequest {
+    #[serde(default)]
+    pub peer_id: Option<PeerId>,
+}
+
+pub async fn get_membership(endpoint: &str) -> Result<RaftMembership> {
+    send_admin_get_request(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        ADMIN_ROUTE_PATH,
+        MEMBERSHIP_ROUTE_PATH
+    ))
+    .await
+}
+
+// Add the client node as a voter. It is caught up with the log before joining the consensus.
+// The request must be sent to the leader.
+pub async fn add_voter(endpoint: &str, peer_id: PeerId) -> Result<RaftMembership> {
+    send_membership_request(endpoint, ADD_VOTER_ROUTE_PATH, peer_id).await
+}
+
+// Remove the voter from the consensus. The request must be sent to the leader.
+pub async fn remove_voter(endpoint: &str, peer_id: PeerId) -> Result<RaftMembership> {
+    send_membership_request(endpoint, REMOVE_VOTER_ROUTE_PATH, peer_id).await
+}
+
+// Add the client node as a learner, which follows the chain without being counted in the
+// quorum. The request must be sent to the leader.
+pub async fn add_learner(endpoint: &str, peer_id: PeerId) -> Result<RaftMembership> {
+    send_membership_request(endpoint, ADD_LEARNER_ROUTE_PATH, peer_id).await
+}
+
+// Move the leadership to the peer, or the best caught-up follower if missing. It returns the
+// new leader once it is elected. The request must be sent to the leader.
+pub async fn transfer_leader(endpoint: &str, peer_id: Option<PeerId>) -> Result<PeerId> {
+    send_admin_post_request(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            ADMIN_ROUTE_PATH,
+            TRANSFER_LEADER_ROUTE_PATH
+        ),
+        &LeaderTransferHttpRequest { peer_id },
+    )
+    .await
+}
+
+// Shut down the node gracefully, the same as SIGINT. It returns once the shutdown begins.
+pub async fn request_shutdown(endpoint: &str) -> Result<()> {
+    send_admin_post_request(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            ADMIN_ROUTE_PATH,
+            SHUTDOWN_ROUTE_PATH
+        ),
+        &(),
+    )
+    .await
+}
+
+async fn send_membership_request(
+    endpoint: &str,
+    route: &str,
+    peer_id: PeerId,
+) -> Result<RaftMembership> {
+    send_admin_post_request(
+        &format!("{}/{}/{}", endpoint_url(endpoint), ADMIN_ROUTE_PATH, route),
+        &MembershipHttpRequest { peer_id },
+    )
+    .await
+}
+
+// The membership is returned sorted after each membership request. All the routes require the
+// admin token if it is set.
+pub fn raft_admin_server<ChangeOutput, TransferOutput>(
+    membership_fn: impl Fn() -> RaftMembership + Send + Sync + 'static,
+    change_fn: impl Fn(MembershipChange) -> ChangeOutput + Send + Sync + 'static,
+    transfer_fn: impl Fn(Option<PeerId>) -> TransferOutput + Send + Sync + 'static,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)>
+where
+    ChangeOutput: TryFuture<Ok = RaftMembership, Error = Error> + Send + 'static,
+    TransferOutput: TryFuture<Ok = PeerId, Error = Error> + Send + 'static,
+{
+    let membership_fn = Arc::new(membership_fn);
+    let membership_route = warp::get()
+        .and(warp::path(MEMBERSHIP_ROUTE_PATH))
+        .and(warp::path::end())
+        .map(move || warp::reply::json(&membership_fn().sorted()));
+
+    let change_fn = Arc::new(change_fn);
+    let change_route = |route: &'static str, change: fn(PeerId) -> MembershipChange| {
+        let change_fn = change_fn.clone();
+        warp::post()
+            .and(warp::path(route))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and_then(move |req: MembershipHttpRequest| {
+                change_membership(change_fn(change(req.peer_id)))
+            })
+    };
+    let add_voter_route = change_route(ADD_VOTER_ROUTE_PATH, MembershipChange::AddVoter);
+    let remove_voter_route = change_route(REMOVE_VOTER_ROUTE_PATH, MembershipChange::RemoveVoter);
+    let add_learner_route = change_route(ADD_LEARNER_ROUTE_PATH, MembershipChange::AddLearner);
+
+    let transfer_fn = Arc::new(transfer_fn);
+    let transfer_leader_route = warp::post()
+        .and(warp::path(TRANSFER_LEADER_ROUTE_PATH))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and_then(move |req: LeaderTransferHttpRequest| {
+            let fut = transfer_fn(req.peer_id);
+            async move {
+                let resp = match fut.into_future().await {
+                    Ok(leader) => warp::reply::json(&leader).into_response(),
+                    Err(e) => {
+                        warn!("Failed to transfer the leadership. Error: {}", e);
+                        reply_error(&e)
+                    }
+                };
+                Ok::<_, warp::Rejection>(resp)
+            }
+        });
+
+    warp::path(ADMIN_ROUTE_PATH)
+        .and(warp_admin_auth())
+        .and(
+            membership_route
+                .or(add_voter_route)
+                .or(remove_voter_route)
+                .or(add_learner_route)
+                .or(transfer_leader_route),
+        )
+        .recover(recover_admin_auth)
+        .boxed()
+}
+
+pub fn shutdown_admin_server(
+    coordinator: ShutdownCoordinator,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path(ADMIN_ROUTE_PATH))
+        .and(warp::path(SHUTDOWN_ROUTE_PATH))
+        .and(warp::path::end())
+        .and(warp_admin_auth())
+        .map(move || {
+            coordinator.request();
+            warp::reply::json(&())
+        })
+        .recover(recover_admin_auth)
+        .boxed()
+}
+
+async fn change_membership(
+    fut: impl TryFuture<Ok = RaftMembership, Error = Error>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    match fut.into_future().await {
+        Ok(membership) => Ok(warp::reply::json(&membership.sorted()).into_response()),
+        Err(e) => {
+            warn!("Failed to change the membership. Error: {}", e);
+            Ok(reply_error(&e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::error::{bail, ConsensusError, ErrorCode};
+    use std::sync::Mutex;
+
+    async fn post_membership(
+        filter: &warp::filters::BoxedFilter<(impl warp::Reply + 'static,)>,
+        route: &str,
+        peer_id: u64,
+    ) -> warp::http::Response<warp::hyper::body::Bytes> {
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/admin/{}", route))
+            .json(&MembershipHttpRequest {
+                peer_id: PeerId(peer_id),
+            })
+            .reply(filter)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_raft_admin_server() {
+        let membership = Arc::new(Mutex::new(RaftMembership {
+            voters: vec![PeerId(2), PeerId(1)],
+            learners: Vec::new(),
+        }));
+        let membership_copy = membership.clone();
+        let filter = raft_admin_server(
+            move || membership_copy.lock().unwrap().clone(),
+            move |change| {
+                let membership = membership.clone();
+                async move {
+                    let mut membership = membership.lock().unwrap();
+                    match change {
+                        MembershipChange::AddVoter(id) => membership.voters.push(id),
+                        MembershipChange::RemoveVoter(id) if membership.voters.contains(&id) => {
+                            membership.voters.retain(|&m| m != id)
+                        }
+                        MembershipChange::RemoveVoter(id) => bail!("{} is not a voter.", id),
+                        MembershipChange::AddLearner(id) => membership.learners.push(id),
+                    }
+                    Ok(membership.clone())
+                }
+            },
+            |peer_id: Option<PeerId>| async move {
+                match peer_id {
+                    Some(PeerId(0)) => bail!("Unknown peer."),
+                    Some(PeerId(9)) => bail!(ConsensusError::NotLeader),
+                    Some(id) => Ok(id),
+                    None => Ok(PeerId(3)),
+                }
+            },
+        );
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/admin/membership")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        assert_eq!(
+            &br#"{"voters":[1,2],"learners":[]}"#[..],
+            resp.body().as_ref()
+        );
+
+        let resp = post_membership(&filter, "add_voter", 3).await;
+        assert_eq!(200, resp.status());
+        assert_eq!(
+            &br#"{"voters":[1,2,3],"learners":[]}"#[..],
+            resp.body().as_ref()
+        );
+
+        let resp = post_membership(&filter, "remove_voter", 1).await;
+        assert_eq!(200, resp.status());
+        assert_eq!(
+            &br#"{"voters":[2,3],"learners":[]}"#[..],
+            resp.body().as_ref()
+        );
+
+        let resp = post_membership(&filter, "remove_voter", 1).await;
+        assert_eq!(500, resp.status());
+
+        let resp = post_membership(&filter, "add_learner", 4).await;
+        assert_eq!(200, resp.status());
+        assert_eq!(
+            &br#"{"voters":[2,3],"learners":[4]}"#[..],
+            resp.body().as_ref()
+        );
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/admin/transfer_leader")
+            .json(&LeaderTransferHttpRequest {
+                peer_id: Some(PeerId(2)),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        assert_eq!(b"2", resp.body().as_ref());
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/admin/transfer_leader")
+            .body("{}")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        assert_eq!(b"3", resp.body().as_ref());
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/admin/transfer_leader")
+            .json(&LeaderTransferHttpRequest {
+                peer_id: Some(PeerId(0)),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(500, resp.status());
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/admin/transfer_leader")
+            .json(&LeaderTransferHttpRequest {
+                peer_id: Some(PeerId(9)),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(421, resp.status());
+        let reply: ErrorReply = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ErrorCode::NotLeader, reply.code);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_admin_server() {
+        let coordinator = ShutdownCoordinator::new();
+        let filter = shutdown_admin_server(coordinator.clone());
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/admin/shutdown")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        coordinator.wait().await.unwrap();
+    }
+}
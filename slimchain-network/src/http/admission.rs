@@ -0,0 +1,289 @@
+use serde::Deserialize;
+use slimchain_common::{collections::HashMap, error::NetworkError};
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Instant,
+};
+
+// Drop the idle per-ip buckets once there are more than this number of them.
+const MAX_IDLE_IP_BUCKETS: usize = 4096;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct AdmissionConfig {
+    /// Max number of txs per second accepted from a single IP. 0 for unlimited.
+    #[serde(default)]
+    pub per_ip_rate: u64,
+
+    /// Max number of txs per second accepted in total. 0 for unlimited.
+    #[serde(default)]
+    pub global_rate: u64,
+
+    /// Max number of accepted txs waiting to be processed. 0 for unlimited.
+    #[serde(default)]
+    pub max_queue_depth: usize,
+}
+
+// The number of txs accepted but not yet handed over by the node.
+#[derive(Debug, Default, Clone)]
+pub struct QueueDepth(Arc<AtomicUsize>);
+
+impl QueueDepth {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+
+    pub fn incr(&self, n: usize) {
+        self.0.fetch_add(n, Ordering::AcqRel);
+    }
+
+    pub fn decr(&self, n: usize) {
+        self.0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |depth| {
+                Some(depth.saturating_sub(n))
+            })
+            .ok();
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    // Allow a burst of one second worth of txs.
+    fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.rate
+    }
+
+    // A batch larger than the burst is let through when the bucket is full. The bucket then goes
+    // into debt, which delays the following batches accordingly.
+    fn try_take(&mut self, n: usize, now: Instant) -> bool {
+        self.refill(now);
+        let n = n as f64;
+        if self.tokens >= n.min(self.rate) {
+            self.tokens -= n;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Admission {
+    cfg: RwLock<AdmissionConfig>,
+    global: Mutex<Option<TokenBucket>>,
+    per_ip: Mutex<HashMap<IpAddr, TokenBucket>>,
+    queue_depth: QueueDepth,
+    closed: AtomicBool,
+}
+
+impl Admission {
+    pub fn new(cfg: &AdmissionConfig) -> Self {
+        Self {
+            cfg: RwLock::new(cfg.clone()),
+            global: Mutex::new(Self::global_bucket(cfg)),
+            per_ip: Mutex::new(HashMap::new()),
+            queue_depth: QueueDepth::default(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(&AdmissionConfig::default())
+    }
+
+    fn global_bucket(cfg: &AdmissionConfig) -> Option<TokenBucket> {
+        if cfg.global_rate > 0 {
+            Some(TokenBucket::new(cfg.global_rate, Instant::now()))
+        } else {
+            None
+        }
+    }
+
+    // Apply the limits reloaded from the config. The buckets start over with the new rates.
+    pub fn reconfigure(&self, cfg: &AdmissionConfig) {
+        *self.global.lock().expect("Failed to lock global bucket.") = Self::global_bucket(cfg);
+        self.per_ip
+            .lock()
+            .expect("Failed to lock per ip buckets.")
+            .clear();
+        *self.cfg.write().expect("Failed to lock admission config.") = cfg.clone();
+        info!("Admission Cfg: {:?}", cfg);
+    }
+
+    pub fn queue_depth(&self) -> QueueDepth {
+        self.queue_depth.clone()
+    }
+
+    // Refuse all the txs from now on, e.g., when the node is shutting down.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    // Check whether `n` txs from `ip` are admitted. On success, they are counted in the queue
+    // depth, which should be decreased by whoever consumes them.
+    pub fn admit(&self, ip: Option<IpAddr>, n: usize) -> Result<(), NetworkError> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(NetworkError::ShuttingDown);
+        }
+
+        let cfg = self
+            .cfg
+            .read()
+            .expect("Failed to lock admission config.")
+            .clone();
+
+        if cfg.max_queue_depth > 0 && self.queue_depth.get() >= cfg.max_queue_depth {
+            return Err(NetworkError::Overloaded(self.queue_depth.get()));
+        }
+
+        let now = Instant::now();
+
+        if let (true, Some(ip)) = (cfg.per_ip_rate > 0, ip) {
+            let mut per_ip = self.per_ip.lock().expect("Failed to lock per ip buckets.");
+            if per_ip.len() >= MAX_IDLE_IP_BUCKETS {
+                per_ip.retain(|_, bucket| {
+                    bucket.refill(now);
+                    !bucket.is_full()
+                });
+            }
+            let bucket = per_ip
+                .entry(ip)
+                .or_insert_with(|| TokenBucket::new(cfg.per_ip_rate, now));
+            if !bucket.try_take(n, now) {
+                return Err(NetworkError::RateLimited(format!(
+                    "Too many txs from {}.",
+                    ip
+                )));
+            }
+        }
+
+        if let Some(global) = self
+            .global
+            .lock()
+            .expect("Failed to lock global bucket.")
+            .as_mut()
+        {
+            if !global.try_take(n, now) {
+                return Err(NetworkError::RateLimited(
+                    "Too many txs in total.".to_string(),
+                ));
+            }
+        }
+
+        self.queue_depth.incr(n);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::error::ErrorCode;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, now);
+        assert!(bucket.try_take(6, now));
+        assert!(bucket.try_take(4, now));
+        assert!(!bucket.try_take(1, now));
+        assert!(bucket.try_take(5, now + Duration::from_millis(500)));
+        assert!(!bucket.try_take(1, now + Duration::from_millis(500)));
+
+        let mut bucket = TokenBucket::new(10, now);
+        assert!(bucket.try_take(20, now));
+        assert!(!bucket.try_take(1, now + Duration::from_secs(1)));
+        assert!(bucket.try_take(1, now + Duration::from_millis(2100)));
+    }
+
+    #[test]
+    fn test_admission() {
+        let ip1: IpAddr = "127.0.0.1".parse().unwrap();
+        let ip2: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let admission = Admission::new(&AdmissionConfig {
+            per_ip_rate: 2,
+            global_rate: 3,
+            max_queue_depth: 0,
+        });
+        assert!(admission.admit(Some(ip1), 2).is_ok());
+        let err = admission.admit(Some(ip1), 1).unwrap_err();
+        assert_eq!(ErrorCode::RateLimited, err.code());
+        assert!(admission.admit(Some(ip2), 1).is_ok());
+        assert!(admission.admit(Some(ip2), 1).is_err());
+        assert_eq!(3, admission.queue_depth().get());
+
+        let admission = Admission::new(&AdmissionConfig {
+            per_ip_rate: 0,
+            global_rate: 0,
+            max_queue_depth: 2,
+        });
+        assert!(admission.admit(None, 2).is_ok());
+        let err = admission.admit(None, 1).unwrap_err();
+        assert_eq!(ErrorCode::Overloaded, err.code());
+        admission.queue_depth().decr(1);
+        assert!(admission.admit(None, 1).is_ok());
+        admission.queue_depth().decr(10);
+        assert_eq!(0, admission.queue_depth().get());
+
+        let admission = Admission::unlimited();
+        for _ in 0..100 {
+            assert!(admission.admit(Some(ip1), 100).is_ok());
+        }
+        admission.close();
+        let err = admission.admit(Some(ip1), 1).unwrap_err();
+        assert_eq!(ErrorCode::ShuttingDown, err.code());
+    }
+
+    #[test]
+    fn test_admission_reconfigure() {
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        let admission = Admission::unlimited();
+        assert!(admission.admit(Some(ip), 5).is_ok());
+        admission.reconfigure(&AdmissionConfig {
+            per_ip_rate: 2,
+            global_rate: 0,
+            max_queue_depth: 0,
+        });
+        assert!(admission.admit(Some(ip), 2).is_ok());
+        let err = admission.admit(Some(ip), 1).unwrap_err();
+        assert_eq!(ErrorCode::RateLimited, err.code());
+
+        admission.reconfigure(&AdmissionConfig {
+            per_ip_rate: 0,
+            global_rate: 0,
+            max_queue_depth: 7,
+        });
+        let err = admission.admit(Some(ip), 1).unwrap_err();
+        assert_eq!(ErrorCode::Overloaded, err.code());
+        admission.queue_depth().decr(1);
+        assert!(admission.admit(Some(ip), 1).is_ok());
+    }
+}
@@ -0,0 +1,75 @@
+use super::common::*;
+use slimchain_chain::audit::AuditAttestation;
+use slimchain_common::{
+    basic::BlockHeight,
+    error::{Error, Result},
+};
+use std::sync::Arc;
+use warp::Filter;
+
+const AUDITOR_RPC_ROUTE_PATH: &str = "auditor_rpc";
+const AUDITED_HEIGHT_ROUTE_PATH: &str = "audited_height";
+const ATTESTATION_ROUTE_PATH: &str = "attestation";
+
+pub async fn get_audited_height(endpoint: &str) -> Result<BlockHeight> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        AUDITOR_RPC_ROUTE_PATH,
+        AUDITED_HEIGHT_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn get_audit_attestation(
+    endpoint: &str,
+    height: BlockHeight,
+) -> Result<Option<AuditAttestation>> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            AUDITOR_RPC_ROUTE_PATH,
+            ATTESTATION_ROUTE_PATH
+        ),
+        &height,
+    )
+    .await
+}
+
+#[derive(Debug)]
+struct AuditorRpcServerError(Error);
+
+impl warp::reject::Reject for AuditorRpcServerError {}
+
+pub fn auditor_rpc_server<AttestationFn>(
+    audited_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+    attestation_fn: AttestationFn,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)>
+where
+    AttestationFn: Fn(BlockHeight) -> Result<Option<AuditAttestation>> + Send + Sync + 'static,
+{
+    let audited_height_fn = Arc::new(audited_height_fn);
+    let audited_height_route =
+        warp::get()
+            .and(warp::path(AUDITED_HEIGHT_ROUTE_PATH))
+            .map(move || {
+                let height = audited_height_fn();
+                warp_reply_binary(&height)
+            });
+    let attestation_fn = Arc::new(attestation_fn);
+    let attestation_route = warp::post()
+        .and(warp::path(ATTESTATION_ROUTE_PATH))
+        .and(warp_body_binary())
+        .and_then(move |height: BlockHeight| {
+            let attestation = attestation_fn(height);
+            async move {
+                attestation
+                    .map(|attestation| warp_reply_binary(&attestation))
+                    .map_err(|e| warp::reject::custom(AuditorRpcServerError(e)))
+            }
+        });
+    warp::path(AUDITOR_RPC_ROUTE_PATH)
+        .and(audited_height_route.or(attestation_route))
+        .boxed()
+}
@@ -1,20 +1,47 @@
-use super::common::*;
-use futures::prelude::*;
+use super::{admission::Admission, common::*};
+use crate::tx_trace::record_tx_trace;
+use futures::{future::BoxFuture, prelude::*};
 use serde::{Deserialize, Serialize};
+use slimchain_chain::receipt::TxReceipt;
 use slimchain_common::{
-    basic::{BlockHeight, ShardId},
-    error::{Error, Result},
+    basic::{BlockHeight, ShardId, H256},
+    error::{anyhow, Error, ErrorCode, NetworkError, Result},
     tx_req::SignedTxRequest,
 };
 use slimchain_utils::record_event;
-use std::{iter, sync::Arc};
-use warp::Filter;
+use std::{
+    iter,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use warp::{http::StatusCode, Filter, Reply};
 
 const CLIENT_RPC_ROUTE_PATH: &str = "client_rpc";
 const TX_REQ_ROUTE_PATH: &str = "tx_req";
 const RECORD_EVENT_ROUTE_PATH: &str = "record_event";
 const TX_COUNT_ROUTE_PATH: &str = "tx_count";
 const BLOCK_HEIGHT_ROUTE_PATH: &str = "block_height";
+const TX_RECEIPT_ROUTE_PATH: &str = "tx_receipt";
+const LEADER_ROUTE_PATH: &str = "leader";
+
+// The tx requests with this header are refused by the nodes other than the raft leader.
+pub const REQUIRE_LEADER_HEADER: &str = "x-require-leader";
+const NOT_LEADER_MSG: &str = "NotLeader";
+// Max number of attempts to send the txs, re-resolving the leader in between.
+const MAX_LEADER_ATTEMPTS: usize = 10;
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+// The reads with this header are served once the node catches up with the raft leader.
+pub const LINEARIZABLE_READ_HEADER: &str = "x-linearizable-read";
+
+static LINEARIZABLE_READ: AtomicBool = AtomicBool::new(false);
+
+// Wait until the state of the node is at least as new as the one of the raft leader.
+pub type ReadBarrier = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TxHttpRequest {
@@ -30,7 +57,7 @@ pub struct RecordEventHttpRequest {
 }
 
 impl RecordEventHttpRequest {
-    fn emit_record_event(&self) {
+    pub(crate) fn emit_record_event(&self) {
         match self.data.as_ref() {
             Some(data) => record_event!("client_event", "info": self.info, "data": data),
             None => record_event!("client_event", "info": self.info),
@@ -61,8 +88,10 @@ pub async fn send_tx_requests_with_shard(
 
     send_post_request_using_binary(
         &format!(
-            "http://{}/{}/{}",
-            endpoint, CLIENT_RPC_ROUTE_PATH, TX_REQ_ROUTE_PATH
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            CLIENT_RPC_ROUTE_PATH,
+            TX_REQ_ROUTE_PATH
         ),
         &reqs,
     )
@@ -98,39 +127,170 @@ pub async fn send_record_event_with_data(
 async fn send_record_event_inner(endpoint: &str, req: RecordEventHttpRequest) -> Result<()> {
     send_post_request_using_json(
         &format!(
-            "http://{}/{}/{}",
-            endpoint, CLIENT_RPC_ROUTE_PATH, RECORD_EVENT_ROUTE_PATH
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            CLIENT_RPC_ROUTE_PATH,
+            RECORD_EVENT_ROUTE_PATH
         ),
         &req,
     )
     .await
 }
 
+// Ask for linearizable reads of the tx count, the block height and the tx receipts. The
+// nodes other than the raft ones refuse them.
+pub fn set_linearizable_read(enable: bool) {
+    LINEARIZABLE_READ.store(enable, Ordering::Release);
+}
+
+async fn send_read_request<Resp: for<'de> Deserialize<'de>>(uri: &str) -> Result<Resp> {
+    if LINEARIZABLE_READ.load(Ordering::Acquire) {
+        send_get_request_using_binary_with_headers(uri, &[(LINEARIZABLE_READ_HEADER, "1")]).await
+    } else {
+        send_get_request_using_binary(uri).await
+    }
+}
+
 pub async fn get_tx_count(endpoint: &str) -> Result<usize> {
-    send_get_request_using_binary(&format!(
-        "http://{}/{}/{}",
-        endpoint, CLIENT_RPC_ROUTE_PATH, TX_COUNT_ROUTE_PATH
+    send_read_request(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        CLIENT_RPC_ROUTE_PATH,
+        TX_COUNT_ROUTE_PATH
     ))
     .await
 }
 
 pub async fn get_block_height(endpoint: &str) -> Result<BlockHeight> {
-    send_get_request_using_binary(&format!(
-        "http://{}/{}/{}",
-        endpoint, CLIENT_RPC_ROUTE_PATH, BLOCK_HEIGHT_ROUTE_PATH
+    send_read_request(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        CLIENT_RPC_ROUTE_PATH,
+        BLOCK_HEIGHT_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn get_tx_receipt(endpoint: &str, tx_id: H256) -> Result<Option<TxReceipt>> {
+    send_read_request(&format!(
+        "{}/{}/{}/{:x}",
+        endpoint_url(endpoint),
+        CLIENT_RPC_ROUTE_PATH,
+        TX_RECEIPT_ROUTE_PATH,
+        tx_id
+    ))
+    .await
+}
+
+// The HTTP endpoint of the raft leader known to the node. It requires the admin token.
+pub async fn get_leader_endpoint(endpoint: &str) -> Result<Option<String>> {
+    send_admin_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        CLIENT_RPC_ROUTE_PATH,
+        LEADER_ROUTE_PATH
     ))
     .await
 }
 
-#[derive(Debug)]
-struct ClientRpcServerError(Error);
+// Sending the same txs again does not help if the node replies that they are invalid.
+fn is_refused_by_node(e: &Error) -> bool {
+    match e.downcast_ref::<NetworkError>() {
+        Some(NetworkError::Remote { code, .. }) => {
+            !code.is_retryable() && *code != ErrorCode::Internal
+        }
+        _ => false,
+    }
+}
+
+// Send the txs to the raft leader, which is resolved through any of the known nodes and
+// cached. The leader is resolved again once it fails or steps down.
+pub struct LeaderClient {
+    endpoints: Vec<String>,
+    leader: Mutex<Option<String>>,
+}
+
+impl LeaderClient {
+    pub fn new(endpoints: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            endpoints: endpoints.into_iter().collect(),
+            leader: Mutex::new(None),
+        }
+    }
+
+    pub async fn leader(&self) -> Result<String> {
+        if let Some(leader) = self.leader.lock().expect("Failed to lock leader.").clone() {
+            return Ok(leader);
+        }
 
-impl warp::reject::Reject for ClientRpcServerError {}
+        let mut last_err = anyhow!("No endpoint is known.");
+        for endpoint in &self.endpoints {
+            match get_leader_endpoint(endpoint).await {
+                Ok(Some(leader)) => {
+                    info!("Raft leader: {} (resolved by {})", leader, endpoint);
+                    *self.leader.lock().expect("Failed to lock leader.") = Some(leader.clone());
+                    return Ok(leader);
+                }
+                Ok(None) => last_err = anyhow!("Leader unknown by {}.", endpoint),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err.context("Failed to resolve the leader."))
+    }
+
+    pub fn reset_leader(&self) {
+        *self.leader.lock().expect("Failed to lock leader.") = None;
+    }
+
+    pub async fn send_tx_requests_with_shard(
+        &self,
+        reqs: impl Iterator<Item = (SignedTxRequest, ShardId)>,
+    ) -> Result<()> {
+        let reqs: Vec<_> = reqs
+            .into_iter()
+            .map(|(req, shard_id)| TxHttpRequest { req, shard_id })
+            .collect();
+
+        let mut last_err = anyhow!("No attempt is made.");
+        for _ in 0..MAX_LEADER_ATTEMPTS {
+            let res = match self.leader().await {
+                Ok(leader) => {
+                    send_post_request_using_binary_with_headers(
+                        &format!(
+                            "{}/{}/{}",
+                            endpoint_url(&leader),
+                            CLIENT_RPC_ROUTE_PATH,
+                            TX_REQ_ROUTE_PATH
+                        ),
+                        &[(REQUIRE_LEADER_HEADER, "1")],
+                        &reqs,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(()) => return Ok(()),
+                Err(e) if is_refused_by_node(&e) => return Err(e),
+                Err(e) => {
+                    warn!("Failed to send txs to the leader. Retry. Error: {}", e);
+                    self.reset_leader();
+                    last_err = e;
+                    tokio::time::sleep(LEADER_RETRY_INTERVAL).await;
+                }
+            }
+        }
+        Err(last_err)
+    }
+}
 
 pub fn client_rpc_server<TxReqOutput>(
+    admission: Arc<Admission>,
     tx_req_fn: impl Fn(Vec<TxHttpRequest>) -> TxReqOutput + Send + Sync + 'static,
     tx_count_fn: impl Fn() -> usize + Send + Sync + 'static,
     block_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+    tx_receipt_fn: impl Fn(H256) -> Result<Option<TxReceipt>> + Send + Sync + 'static,
+    read_barrier: Option<ReadBarrier>,
 ) -> warp::filters::BoxedFilter<(impl warp::Reply,)>
 where
     TxReqOutput: TryFuture<Ok = (), Error = Error> + Send + 'static,
@@ -138,11 +298,30 @@ where
     let tx_req_fn = Arc::new(tx_req_fn);
     let tx_req_route = warp::post()
         .and(warp::path(TX_REQ_ROUTE_PATH))
+        .and(warp::addr::remote())
         .and(warp_body_binary())
-        .and_then(move |reqs: Vec<TxHttpRequest>| {
-            tx_req_fn(reqs)
-                .map_ok(|_| warp_reply_binary(&()))
-                .map_err(|e| warp::reject::custom(ClientRpcServerError(e)))
+        .and_then(move |addr: Option<SocketAddr>, reqs: Vec<TxHttpRequest>| {
+            let num_reqs = reqs.len();
+            let queue_depth = admission.queue_depth();
+            let fut = admission
+                .admit(addr.map(|addr| addr.ip()), num_reqs)
+                .map(|_| {
+                    record_tx_trace(&reqs);
+                    tx_req_fn(reqs)
+                });
+            async move {
+                let resp = match fut {
+                    Ok(fut) => match fut.into_future().await {
+                        Ok(_) => warp_reply_binary(&()).into_response(),
+                        Err(e) => {
+                            queue_depth.decr(num_reqs);
+                            reply_error(&e)
+                        }
+                    },
+                    Err(e) => reply_error(&e.into()),
+                };
+                Ok::<_, warp::Rejection>(resp)
+            }
         });
     let record_event_route = warp::post()
         .and(warp::path(RECORD_EVENT_ROUTE_PATH))
@@ -152,25 +331,227 @@ where
             warp::reply::json(&())
         });
     let tx_count_fn = Arc::new(tx_count_fn);
+    let read_barrier_copy = read_barrier.clone();
     let tx_count_route = warp::get()
         .and(warp::path(TX_COUNT_ROUTE_PATH))
-        .map(move || {
-            let tx_count = tx_count_fn();
-            warp_reply_binary(&tx_count)
+        .and(warp::header::optional::<String>(LINEARIZABLE_READ_HEADER))
+        .and_then(move |linearizable: Option<String>| {
+            let tx_count_fn = tx_count_fn.clone();
+            let barrier = wait_read_barrier(read_barrier_copy.clone(), linearizable);
+            async move {
+                let resp = match barrier.await {
+                    Ok(()) => warp_reply_binary(&tx_count_fn()).into_response(),
+                    Err(e) => reply_error(&e),
+                };
+                Ok::<_, warp::Rejection>(resp)
+            }
         });
     let block_height_fn = Arc::new(block_height_fn);
+    let read_barrier_copy = read_barrier.clone();
     let block_height_route = warp::get()
         .and(warp::path(BLOCK_HEIGHT_ROUTE_PATH))
-        .map(move || {
-            let block_height = block_height_fn();
-            warp_reply_binary(&block_height)
+        .and(warp::header::optional::<String>(LINEARIZABLE_READ_HEADER))
+        .and_then(move |linearizable: Option<String>| {
+            let block_height_fn = block_height_fn.clone();
+            let barrier = wait_read_barrier(read_barrier_copy.clone(), linearizable);
+            async move {
+                let resp = match barrier.await {
+                    Ok(()) => warp_reply_binary(&block_height_fn()).into_response(),
+                    Err(e) => reply_error(&e),
+                };
+                Ok::<_, warp::Rejection>(resp)
+            }
+        });
+    let tx_receipt_fn = Arc::new(tx_receipt_fn);
+    let tx_receipt_route = warp::get()
+        .and(warp::path(TX_RECEIPT_ROUTE_PATH))
+        .and(warp::path::param::<H256>())
+        .and(warp::header::optional::<String>(LINEARIZABLE_READ_HEADER))
+        .and_then(move |tx_id: H256, linearizable: Option<String>| {
+            let tx_receipt_fn = tx_receipt_fn.clone();
+            let barrier = wait_read_barrier(read_barrier.clone(), linearizable);
+            async move {
+                let resp = match barrier.await.and_then(|_| tx_receipt_fn(tx_id)) {
+                    Ok(receipt) => warp_reply_binary(&receipt).into_response(),
+                    Err(e) => reply_error(&e),
+                };
+                Ok::<_, warp::Rejection>(resp)
+            }
         });
     warp::path(CLIENT_RPC_ROUTE_PATH)
         .and(
             tx_req_route
                 .or(record_event_route)
                 .or(tx_count_route)
-                .or(block_height_route),
+                .or(block_height_route)
+                .or(tx_receipt_route),
         )
         .boxed()
 }
+
+async fn wait_read_barrier(
+    read_barrier: Option<ReadBarrier>,
+    linearizable: Option<String>,
+) -> Result<()> {
+    if linearizable.as_deref() != Some("1") {
+        return Ok(());
+    }
+    match read_barrier {
+        Some(read_barrier) => read_barrier().await,
+        None => Err(Error::msg(NetworkError::InvalidRequest(
+            "Linearizable reads are not supported by the node.".to_string(),
+        ))),
+    }
+}
+
+// Serve the leader endpoint, which requires the admin token. The tx requests requiring the leader
+// are refused by the other nodes, and passed to `client_rpc_server` otherwise.
+pub fn leader_rpc_server(
+    leader_fn: impl Fn() -> Option<String> + Send + Sync + 'static,
+    is_leader_fn: impl Fn() -> bool + Send + Sync + 'static,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let leader_fn = Arc::new(leader_fn);
+    let leader_route = warp::get()
+        .and(warp::path(LEADER_ROUTE_PATH))
+        .and(warp::path::end())
+        .and(warp_admin_auth())
+        .map(move || warp_reply_binary(&leader_fn()))
+        .recover(recover_admin_auth);
+    let is_leader_fn = Arc::new(is_leader_fn);
+    let not_leader_route = warp::post()
+        .and(warp::path(TX_REQ_ROUTE_PATH))
+        .and(warp::header::exact(REQUIRE_LEADER_HEADER, "1"))
+        .and_then(move || {
+            let is_leader = is_leader_fn();
+            async move {
+                if is_leader {
+                    Err(warp::reject::not_found())
+                } else {
+                    Ok(warp::reply::with_status(
+                        NOT_LEADER_MSG,
+                        StatusCode::MISDIRECTED_REQUEST,
+                    ))
+                }
+            }
+        });
+    warp::path(CLIENT_RPC_ROUTE_PATH)
+        .and(leader_route.or(not_leader_route))
+        .boxed()
+}
+
+// The client RPC of the raft client nodes, shared by slimchain and the baselines, so that the
+// same tools drive all of them.
+#[allow(clippy::too_many_arguments)]
+pub fn raft_client_rpc_server<TxReqOutput>(
+    admission: Arc<Admission>,
+    tx_req_fn: impl Fn(Vec<TxHttpRequest>) -> TxReqOutput + Send + Sync + 'static,
+    tx_count_fn: impl Fn() -> usize + Send + Sync + 'static,
+    block_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+    tx_receipt_fn: impl Fn(H256) -> Result<Option<TxReceipt>> + Send + Sync + 'static,
+    leader_fn: impl Fn() -> Option<String> + Send + Sync + 'static,
+    is_leader_fn: impl Fn() -> bool + Send + Sync + 'static,
+    read_barrier: ReadBarrier,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)>
+where
+    TxReqOutput: TryFuture<Ok = (), Error = Error> + Send + 'static,
+{
+    leader_rpc_server(leader_fn, is_leader_fn)
+        .or(client_rpc_server(
+            admission,
+            tx_req_fn,
+            tx_count_fn,
+            block_height_fn,
+            tx_receipt_fn,
+            Some(read_barrier),
+        ))
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_utils::serde::binary_decode;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_leader_rpc_server() {
+        let is_leader = Arc::new(AtomicBool::new(false));
+        let is_leader_copy = is_leader.clone();
+        let filter = leader_rpc_server(
+            || Some("127.0.0.1:8001".to_string()),
+            move || is_leader_copy.load(Ordering::Acquire),
+        )
+        .or(warp::path!("client_rpc" / "tx_req").map(|| "accepted"));
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/client_rpc/leader")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        let leader: Option<String> = binary_decode(resp.body()).unwrap();
+        assert_eq!(Some("127.0.0.1:8001".to_string()), leader);
+
+        let tx_req = |require_leader: bool| {
+            let req = warp::test::request()
+                .method("POST")
+                .path("/client_rpc/tx_req");
+            if require_leader {
+                req.header(REQUIRE_LEADER_HEADER, "1")
+            } else {
+                req
+            }
+        };
+        let resp = tx_req(true).reply(&filter).await;
+        assert_eq!(StatusCode::MISDIRECTED_REQUEST, resp.status());
+        assert_eq!(NOT_LEADER_MSG.as_bytes(), resp.body().as_ref());
+        let resp = tx_req(false).reply(&filter).await;
+        assert_eq!(b"accepted", resp.body().as_ref());
+
+        is_leader.store(true, Ordering::Release);
+        let resp = tx_req(true).reply(&filter).await;
+        assert_eq!(b"accepted", resp.body().as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_linearizable_read() {
+        let barrier_calls = Arc::new(AtomicUsize::new(0));
+        let barrier_calls_copy = barrier_calls.clone();
+        let read_barrier: ReadBarrier = Arc::new(move || {
+            barrier_calls_copy.fetch_add(1, Ordering::AcqRel);
+            future::ok(()).boxed()
+        });
+        let server = |read_barrier: Option<ReadBarrier>| {
+            client_rpc_server(
+                Arc::new(Admission::unlimited()),
+                |_| future::ok::<(), Error>(()),
+                || 1,
+                || BlockHeight(2),
+                |_| Ok(None),
+                read_barrier,
+            )
+        };
+        let block_height = |linearizable: bool| {
+            let req = warp::test::request()
+                .method("GET")
+                .path("/client_rpc/block_height");
+            if linearizable {
+                req.header(LINEARIZABLE_READ_HEADER, "1")
+            } else {
+                req
+            }
+        };
+
+        let filter = server(Some(read_barrier));
+        let resp = block_height(false).reply(&filter).await;
+        assert_eq!(BlockHeight(2), binary_decode(resp.body()).unwrap());
+        assert_eq!(0, barrier_calls.load(Ordering::Acquire));
+        let resp = block_height(true).reply(&filter).await;
+        assert_eq!(BlockHeight(2), binary_decode(resp.body()).unwrap());
+        assert_eq!(1, barrier_calls.load(Ordering::Acquire));
+
+        let filter = server(None);
+        let resp = block_height(true).reply(&filter).await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    }
+}
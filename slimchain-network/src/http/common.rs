@@ -1,44 +1,503 @@
 use futures::io::Cursor;
+use http_client::isahc::IsahcClient;
+use isahc::config::{
+    CaCertificate, ClientCertificate, Configurable, PrivateKey, VersionNegotiation,
+};
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
-use slimchain_common::error::{ensure, Error, Result};
+use slimchain_common::error::{
+    anyhow, ensure, error_code, Context as _, Error, ErrorCode, NetworkError, Result,
+};
 use slimchain_utils::{
     bytes::Bytes,
+    otel::{set_parent_from_headers, trace_context_headers},
     serde::{binary_decode, binary_encode},
 };
+use std::{
+    io::Read,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
 use warp::{
-    http::{self, HeaderValue, Response, StatusCode},
+    http::{self, header, HeaderValue, Response, StatusCode},
     hyper,
     reject::Reject,
-    Filter, Rejection,
+    Filter, Rejection, Reply,
 };
 
+const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+const JSON_CONTENT_TYPE: &str = "application/json";
+const ZSTD_ENCODING: &str = "zstd";
+const ZSTD_LEVEL: i32 = 3;
+// The payloads smaller than this are not worth compressing.
+const MIN_COMPRESS_SIZE: usize = 4 * 1024;
+// Guard against the decompression bombs.
+const MAX_DECOMPRESS_SIZE: u64 = 1024 * 1024 * 1024;
+
+static HTTP_CLIENT: OnceCell<surf::Client> = OnceCell::new();
+static HTTP_COMPRESSION: AtomicBool = AtomicBool::new(false);
+static HTTP2: AtomicBool = AtomicBool::new(false);
+static ADMIN_TOKEN: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
 macro_rules! check_resp {
     ($resp:ident) => {
-        ensure!(
-            $resp.status().is_success(),
-            "Failed to send http req. Status code: {}. Msg: {}.",
-            $resp.status(),
-            $resp
+        if !$resp.status().is_success() {
+            let status = $resp.status();
+            let msg = $resp
                 .body_string()
                 .await
-                .unwrap_or_else(|e| format!("(Failed to decode http response: {})", e)),
-        );
+                .unwrap_or_else(|e| format!("(Failed to decode http response: {})", e));
+            return Err(resp_error(status, &msg));
+        }
+    };
+}
+
+// Bind the warp server on every address, with TLS if it is configured. The shutdown signal is
+// shared by all of them. Return the boxed server future, which resolves once all are stopped.
+macro_rules! warp_serve {
+    ($filter:expr, $addrs:expr, $tls:expr, $signal:expr) => {{
+        let filter = $filter;
+        let addrs: Vec<::std::net::SocketAddr> = $addrs;
+        let signal = ::futures::FutureExt::shared($signal);
+        let tls: Option<&$crate::http::config::TlsConfig> = $tls;
+        // Reply in JSON if the request asks for it. Otherwise, compress the binary replies if the
+        // request accepts zstd.
+        let filter = warp::header::optional::<String>("accept-encoding")
+            .and(warp::header::optional::<String>("accept"))
+            .and(filter)
+            .and_then(
+                |encoding: Option<String>, accept: Option<String>, reply| async move {
+                    let resp =
+                        $crate::http::common::negotiate_reply(accept.as_deref(), reply).await;
+                    Ok::<_, warp::Rejection>(
+                        $crate::http::common::compress_reply(encoding.as_deref(), resp).await,
+                    )
+                },
+            )
+            .with(warp::trace($crate::http::common::request_span));
+        let srvs: Vec<::futures::future::BoxFuture<'static, ()>> = addrs
+            .into_iter()
+            .map(|addr| match tls {
+                Some(tls) => {
+                    let mut server = warp::serve(filter.clone())
+                        .tls()
+                        .cert_path(&tls.cert)
+                        .key_path(&tls.key);
+                    if let Some(ca) = tls.peer_ca.as_ref() {
+                        server = server.client_auth_required_path(ca);
+                    }
+                    ::futures::FutureExt::boxed(
+                        server.bind_with_graceful_shutdown(addr, signal.clone()).1,
+                    )
+                }
+                None => ::futures::FutureExt::boxed(
+                    warp::serve(filter.clone())
+                        .bind_with_graceful_shutdown(addr, signal.clone())
+                        .1,
+                ),
+            })
+            .collect();
+        ::futures::FutureExt::boxed(::futures::FutureExt::map(
+            ::futures::future::join_all(srvs),
+            |_| (),
+        ))
+    }};
+}
+pub(crate) use warp_serve;
+
+// The typed error replied in JSON, so that the client gets back its code.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReply {
+    pub code: ErrorCode,
+    pub msg: String,
+}
+
+fn error_status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::Internal | ErrorCode::ExecutionFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorCode::InvalidSignature
+        | ErrorCode::InvalidChainId
+        | ErrorCode::TxExpired
+        | ErrorCode::InvalidProof
+        | ErrorCode::InvalidBlock
+        | ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+        ErrorCode::Conflict | ErrorCode::Outdated => StatusCode::CONFLICT,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::NotLeader => StatusCode::MISDIRECTED_REQUEST,
+        ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::Overloaded | ErrorCode::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+    }
+}
+
+pub fn reply_error(e: &Error) -> Response<hyper::Body> {
+    let code = error_code(e);
+    let reply = ErrorReply {
+        code,
+        msg: format!("{:#}", e),
+    };
+    warp::reply::with_status(warp::reply::json(&reply), error_status(code)).into_response()
+}
+
+// Recover the typed error from the reply. The replies from the older nodes are left untyped.
+fn resp_error(status: surf::StatusCode, msg: &str) -> Error {
+    match serde_json::from_str::<ErrorReply>(msg) {
+        Ok(ErrorReply { code, msg }) => Error::msg(NetworkError::Remote { code, msg }),
+        Err(_) => anyhow!(
+            "Failed to send http req. Status code: {}. Msg: {}.",
+            status,
+            msg
+        ),
+    }
+}
+
+// The endpoint can be prefixed with the scheme, e.g., `https://1.2.3.4:8000`, to reach a server
+// with TLS. Default to http.
+pub fn endpoint_url(endpoint: &str) -> String {
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        endpoint.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", endpoint)
+    }
+}
+
+fn new_http_client(mut builder: isahc::HttpClientBuilder) -> Result<surf::Client> {
+    // The servers of the peers speak HTTP/2 without the upgrade, so go with the prior knowledge.
+    if HTTP2.load(Ordering::Acquire) {
+        builder = builder.version_negotiation(VersionNegotiation::http2());
+    }
+    // Leave the content encoding to us. Otherwise, curl may decode zstd on its own.
+    let client = builder
+        .automatic_decompression(false)
+        .build()
+        .map_err(Error::msg)?;
+    Ok(surf::Client::with_http_client(IsahcClient::from_client(client)).with(TraceContext))
+}
+
+// Send the context of the current span to the peer, so that its spans join the same trace.
+#[derive(Debug)]
+struct TraceContext;
+
+#[surf::utils::async_trait]
+impl surf::middleware::Middleware for TraceContext {
+    async fn handle(
+        &self,
+        mut req: surf::Request,
+        client: surf::Client,
+        next: surf::middleware::Next<'_>,
+    ) -> surf::Result<surf::Response> {
+        for (name, value) in trace_context_headers() {
+            req.insert_header(name.as_str(), value.as_str());
+        }
+        next.run(req, client).await
+    }
+}
+
+// The span of each incoming request, following the trace of the peer if any.
+pub fn request_span(info: warp::trace::Info<'_>) -> tracing::Span {
+    let span = info_span!("http_request", method = %info.method(), path = %info.path());
+    set_parent_from_headers(&span, |name| {
+        info.request_headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    });
+    span
+}
+
+// Present the certificate to the peers and trust only the servers signed by the peer CA.
+pub(crate) fn install_peer_http_client(cert: &Path, key: &Path, ca: &Path) -> Result<()> {
+    let builder = isahc::HttpClient::builder()
+        .ssl_client_certificate(ClientCertificate::pem_file(
+            cert,
+            PrivateKey::pem_file(key, None::<String>),
+        ))
+        .ssl_ca_certificate(CaCertificate::file(ca));
+    HTTP_CLIENT
+        .set(new_http_client(builder)?)
+        .map_err(|_| anyhow!("Failed to set the peer HTTP client."))
+}
+
+// The client used by all the outgoing HTTP requests.
+fn http_client() -> &'static surf::Client {
+    HTTP_CLIENT.get_or_init(|| {
+        new_http_client(isahc::HttpClient::builder()).expect("Failed to create the HTTP client.")
+    })
+}
+
+// Whether to compress the binary payloads sent by this node and ask for compressed replies.
+// The compressed payloads are always accepted regardless.
+pub fn set_http_compression(enable: bool) {
+    HTTP_COMPRESSION.store(enable, Ordering::Release);
+}
+
+// It must be set before the first request, as the client is created once.
+pub fn set_http2(enable: bool) {
+    HTTP2.store(enable, Ordering::Release);
+}
+
+// The token guarding the admin endpoints of this node. The admin requests sent by this node
+// carry it as well. Without it, the admin endpoints are open to anyone who can reach the node.
+pub fn set_admin_token(token: Option<String>) {
+    let token = token.filter(|token| !token.is_empty());
+    if token.is_none() {
+        warn!("No admin_token is set. The admin endpoints are open to anyone reaching the node!");
+    }
+    *ADMIN_TOKEN
+        .write()
+        .expect("Failed to lock the admin token.") = token;
+}
+
+fn admin_token() -> Option<String> {
+    ADMIN_TOKEN
+        .read()
+        .expect("Failed to lock the admin token.")
+        .clone()
+}
+
+// The `Authorization` header of the admin requests, if a token is set.
+fn admin_authorization() -> Option<String> {
+    admin_token().map(|token| format!("Bearer {}", token))
+}
+
+// Compare in constant time, so that the token cannot be guessed byte by byte from the timing.
+fn token_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn authorize(token: Option<&str>, authorization: Option<&str>) -> Result<(), NetworkError> {
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(()),
+    };
+    match authorization.and_then(|auth| auth.strip_prefix("Bearer ")) {
+        Some(req_token) if token_eq(req_token.trim().as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(NetworkError::Unauthorized),
+    }
+}
+
+// Check the `Authorization` header against the admin token. Always passes without a token.
+pub fn check_admin_authorization(authorization: Option<&str>) -> Result<(), NetworkError> {
+    authorize(admin_token().as_deref(), authorization)
+}
+
+#[derive(Debug)]
+struct AdminAuthError(NetworkError);
+
+impl Reject for AdminAuthError {}
+
+// Reject the admin requests without the admin token. The rejection should be turned into a reply
+// by `recover_admin_auth`, once the path of the admin route is matched.
+pub fn warp_admin_auth() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(|authorization: Option<String>| async move {
+            check_admin_authorization(authorization.as_deref())
+                .map_err(|e| warp::reject::custom(AdminAuthError(e)))
+        })
+        .untuple_one()
+}
+
+// Reply 401 to the requests rejected by `warp_admin_auth`. The other rejections are passed on to
+// the next routes.
+pub async fn recover_admin_auth(rejection: Rejection) -> Result<Response<hyper::Body>, Rejection> {
+    match rejection.find::<AdminAuthError>() {
+        Some(AdminAuthError(e)) => Ok(reply_error(&Error::msg(e.clone()))),
+        None => Err(rejection),
+    }
+}
+
+fn http_compression() -> bool {
+    HTTP_COMPRESSION.load(Ordering::Acquire)
+}
+
+fn is_zstd(encoding: Option<&str>) -> bool {
+    encoding.map_or(false, |encoding| {
+        encoding
+            .split(',')
+            .any(|e| e.split(';').next().unwrap_or_default().trim() == ZSTD_ENCODING)
+    })
+}
+
+fn is_json(content_type: Option<&str>) -> bool {
+    content_type.map_or(false, |content_type| {
+        content_type
+            .split(',')
+            .any(|t| t.split(';').next().unwrap_or_default().trim() == JSON_CONTENT_TYPE)
+    })
+}
+
+fn zstd_encode(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(data, ZSTD_LEVEL).context("Failed to compress with zstd.")
+}
+
+fn zstd_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    zstd::stream::read::Decoder::new(data)?
+        .take(MAX_DECOMPRESS_SIZE + 1)
+        .read_to_end(&mut out)
+        .context("Failed to decompress with zstd.")?;
+    ensure!(
+        (out.len() as u64) <= MAX_DECOMPRESS_SIZE,
+        "The decompressed payload is too large."
+    );
+    Ok(out)
+}
+
+// Attach the binary payload to the request, compressed if it is enabled and worthwhile.
+fn binary_request(mut req: surf::RequestBuilder, body: &[u8]) -> Result<surf::RequestBuilder> {
+    if !http_compression() {
+        return Ok(req.body(surf::Body::from_bytes(body.to_vec())));
+    }
+
+    req = req.header("Accept-Encoding", ZSTD_ENCODING);
+    if body.len() < MIN_COMPRESS_SIZE {
+        return Ok(req.body(surf::Body::from_bytes(body.to_vec())));
+    }
+    Ok(req
+        .header("Content-Encoding", ZSTD_ENCODING)
+        .body(surf::Body::from_bytes(zstd_encode(body)?)))
+}
+
+async fn binary_response<Resp: for<'de> Deserialize<'de>>(
+    mut resp: surf::Response,
+) -> Result<Resp> {
+    check_resp!(resp);
+    let encoding = resp
+        .header("Content-Encoding")
+        .map(|values| values.last().as_str().to_string());
+    let resp_bytes = resp.body_bytes().await.map_err(Error::msg)?;
+    if is_zstd(encoding.as_deref()) {
+        binary_decode(&zstd_decode(&resp_bytes)?)
+    } else {
+        binary_decode(&resp_bytes)
+    }
+}
+
+// Compress the binary reply if it is enabled and the client accepts zstd. The other replies,
+// e.g., JSON and websocket upgrades, are left as they are.
+pub async fn compress_reply(accept: Option<&str>, reply: impl Reply) -> Response<hyper::Body> {
+    let resp = reply.into_response();
+    if !http_compression()
+        || !is_zstd(accept)
+        || resp.headers().contains_key(header::CONTENT_ENCODING)
+        || resp.headers().get(header::CONTENT_TYPE)
+            != Some(&HeaderValue::from_static(BINARY_CONTENT_TYPE))
+    {
+        return resp;
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let buf = match hyper::body::to_bytes(body).await {
+        Ok(buf) => buf,
+        Err(e) => {
+            error!("compress_reply error: {}", e);
+            let mut resp = Response::new(hyper::Body::empty());
+            *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return resp;
+        }
+    };
+    if buf.len() < MIN_COMPRESS_SIZE {
+        return Response::from_parts(parts, hyper::Body::from(buf));
+    }
+    match zstd_encode(&buf) {
+        Ok(compressed) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(ZSTD_ENCODING),
+            );
+            Response::from_parts(parts, hyper::Body::from(compressed))
+        }
+        Err(e) => {
+            warn!("Failed to compress the reply. Error: {}", e);
+            Response::from_parts(parts, hyper::Body::from(buf))
+        }
+    }
+}
+
+// Postcard is not self-describing, so the binary reply carries the way to decode it, in case it
+// is asked in JSON.
+#[derive(Clone, Copy)]
+struct JsonTranscoder(fn(&[u8]) -> Result<Vec<u8>>);
+
+fn binary_to_json<T: Serialize + for<'de> Deserialize<'de>>(buf: &[u8]) -> Result<Vec<u8>> {
+    serde_json::to_vec(&binary_decode::<T>(buf)?).map_err(Error::from)
+}
+
+// Turn the binary reply into JSON if the client accepts JSON, e.g., when it is inspected with
+// curl. The other replies are left as they are.
+pub async fn negotiate_reply(accept: Option<&str>, reply: impl Reply) -> Response<hyper::Body> {
+    let resp = reply.into_response();
+    let transcoder = match resp.extensions().get::<JsonTranscoder>() {
+        Some(transcoder) if is_json(accept) => *transcoder,
+        _ => return resp,
+    };
+
+    let (mut parts, body) = resp.into_parts();
+    let json = match hyper::body::to_bytes(body).await {
+        Ok(buf) => (transcoder.0)(&buf),
+        Err(e) => Err(Error::from(e)),
     };
+    match json {
+        Ok(json) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(JSON_CONTENT_TYPE),
+            );
+            Response::from_parts(parts, hyper::Body::from(json))
+        }
+        Err(e) => {
+            error!("negotiate_reply error: {}", e);
+            let mut resp = Response::new(hyper::Body::empty());
+            *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            resp
+        }
+    }
 }
 
 pub async fn send_get_request_using_json<Resp: for<'de> Deserialize<'de>>(
     uri: &str,
 ) -> Result<Resp> {
-    let mut resp = surf::get(uri).await.map_err(Error::msg)?;
+    send_get_request_using_json_with_headers(uri, &[]).await
+}
+
+pub async fn send_post_request_using_json<Req: Serialize, Resp: for<'de> Deserialize<'de>>(
+    uri: &str,
+    req: &Req,
+) -> Result<Resp> {
+    send_post_request_using_json_with_headers(uri, &[], req).await
+}
+
+pub async fn send_get_request_using_json_with_headers<Resp: for<'de> Deserialize<'de>>(
+    uri: &str,
+    headers: &[(&str, &str)],
+) -> Result<Resp> {
+    let mut req = http_client().get(uri);
+    for &(name, value) in headers {
+        req = req.header(name, value);
+    }
+    let mut resp = req.await.map_err(Error::msg)?;
     check_resp!(resp);
     resp.body_json().await.map_err(Error::msg)
 }
 
-pub async fn send_post_request_using_json<Req: Serialize, Resp: for<'de> Deserialize<'de>>(
+pub async fn send_post_request_using_json_with_headers<
+    Req: Serialize,
+    Resp: for<'de> Deserialize<'de>,
+>(
     uri: &str,
+    headers: &[(&str, &str)],
     req: &Req,
 ) -> Result<Resp> {
-    let mut resp = surf::post(uri)
+    let mut builder = http_client().post(uri);
+    for &(name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    let mut resp = builder
         .body(surf::Body::from_json(&req).map_err(Error::msg)?)
         .await
         .map_err(Error::msg)?;
@@ -46,40 +505,99 @@ pub async fn send_post_request_using_json<Req: Serialize, Resp: for<'de> Deseria
     resp.body_json().await.map_err(Error::msg)
 }
 
+// Send the request to an admin endpoint, with the admin token if it is set.
+pub async fn send_admin_get_request<Resp: for<'de> Deserialize<'de>>(uri: &str) -> Result<Resp> {
+    let authorization = admin_authorization();
+    let headers: Vec<_> = authorization
+        .iter()
+        .map(|auth| ("Authorization", auth.as_str()))
+        .collect();
+    send_get_request_using_json_with_headers(uri, &headers).await
+}
+
+pub async fn send_admin_get_request_using_binary<Resp: for<'de> Deserialize<'de>>(
+    uri: &str,
+) -> Result<Resp> {
+    let authorization = admin_authorization();
+    let headers: Vec<_> = authorization
+        .iter()
+        .map(|auth| ("Authorization", auth.as_str()))
+        .collect();
+    send_get_request_using_binary_with_headers(uri, &headers).await
+}
+
+pub async fn send_admin_post_request<Req: Serialize, Resp: for<'de> Deserialize<'de>>(
+    uri: &str,
+    req: &Req,
+) -> Result<Resp> {
+    let authorization = admin_authorization();
+    let headers: Vec<_> = authorization
+        .iter()
+        .map(|auth| ("Authorization", auth.as_str()))
+        .collect();
+    send_post_request_using_json_with_headers(uri, &headers, req).await
+}
+
 pub async fn send_get_request_using_binary<Resp: for<'de> Deserialize<'de>>(
     uri: &str,
 ) -> Result<Resp> {
-    let mut resp = surf::get(uri).await.map_err(Error::msg)?;
-    check_resp!(resp);
-    let resp_bytes = resp.body_bytes().await.map_err(Error::msg)?;
-    binary_decode(&resp_bytes)
+    send_get_request_using_binary_with_headers(uri, &[]).await
+}
+
+pub async fn send_get_request_using_binary_with_headers<Resp: for<'de> Deserialize<'de>>(
+    uri: &str,
+    headers: &[(&str, &str)],
+) -> Result<Resp> {
+    let mut req = http_client().get(uri);
+    for &(name, value) in headers {
+        req = req.header(name, value);
+    }
+    if http_compression() {
+        req = req.header("Accept-Encoding", ZSTD_ENCODING);
+    }
+    let resp = req.await.map_err(Error::msg)?;
+    binary_response(resp).await
 }
 
 pub async fn send_post_request_using_binary<Req: Serialize, Resp: for<'de> Deserialize<'de>>(
     uri: &str,
     req: &Req,
 ) -> Result<Resp> {
-    let mut resp = surf::post(uri)
-        .body(surf::Body::from_bytes(binary_encode(req)?))
+    send_post_request_using_binary_with_headers(uri, &[], req).await
+}
+
+pub async fn send_post_request_using_binary_with_headers<
+    Req: Serialize,
+    Resp: for<'de> Deserialize<'de>,
+>(
+    uri: &str,
+    headers: &[(&str, &str)],
+    req: &Req,
+) -> Result<Resp> {
+    let mut builder = http_client().post(uri);
+    for &(name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    let resp = binary_request(builder, &binary_encode(req)?)?
         .await
         .map_err(Error::msg)?;
-    check_resp!(resp);
-    let resp_bytes = resp.body_bytes().await.map_err(Error::msg)?;
-    binary_decode(&resp_bytes)
+    binary_response(resp).await
 }
 
 pub async fn send_post_request_using_binary_bytes<Resp: for<'de> Deserialize<'de>>(
     uri: &str,
     req: Bytes,
 ) -> Result<Resp> {
-    let req_len = req.len();
-    let mut resp = surf::post(uri)
-        .body(surf::Body::from_reader(Cursor::new(req), Some(req_len)))
-        .await
-        .map_err(Error::msg)?;
-    check_resp!(resp);
-    let resp_bytes = resp.body_bytes().await.map_err(Error::msg)?;
-    binary_decode(&resp_bytes)
+    let req = if http_compression() {
+        binary_request(http_client().post(uri), req.as_ref())?
+    } else {
+        let req_len = req.len();
+        http_client()
+            .post(uri)
+            .body(surf::Body::from_reader(Cursor::new(req), Some(req_len)))
+    };
+    let resp = req.await.map_err(Error::msg)?;
+    binary_response(resp).await
 }
 
 #[derive(Debug)]
@@ -87,24 +605,46 @@ struct PostcardDecodeError(Error);
 
 impl Reject for PostcardDecodeError {}
 
+fn decode_body<T: for<'de> Deserialize<'de>>(buf: &[u8], json: bool) -> Result<T> {
+    if json {
+        serde_json::from_slice(buf).map_err(Error::from)
+    } else {
+        binary_decode(buf)
+    }
+}
+
+// The body is in postcard, or in JSON with `Content-Type: application/json`.
 pub fn warp_body_binary<T: for<'de> Deserialize<'de> + Send>(
 ) -> impl Filter<Extract = (T,), Error = Rejection> + Copy {
-    warp::filters::body::bytes().and_then(|buf: Bytes| async move {
-        binary_decode(buf.as_ref()).map_err(|err| {
-            debug!("request decode body error: {}", err);
-            warp::reject::custom(PostcardDecodeError(err))
-        })
-    })
+    warp::header::optional::<String>("content-encoding")
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::filters::body::bytes())
+        .and_then(
+            |encoding: Option<String>, content_type: Option<String>, buf: Bytes| async move {
+                let json = is_json(content_type.as_deref());
+                let res = if is_zstd(encoding.as_deref()) {
+                    zstd_decode(buf.as_ref()).and_then(|buf| decode_body(&buf, json))
+                } else {
+                    decode_body(buf.as_ref(), json)
+                };
+                res.map_err(|err| {
+                    debug!("request decode body error: {}", err);
+                    warp::reject::custom(PostcardDecodeError(err))
+                })
+            },
+        )
 }
 
-pub fn warp_reply_binary<T: Serialize>(val: &T) -> impl warp::Reply {
+pub fn warp_reply_binary<T: Serialize + for<'de> Deserialize<'de>>(val: &T) -> impl warp::Reply {
     match binary_encode(val) {
         Ok(buf) => {
             let mut resp = Response::new(hyper::Body::from(buf));
             resp.headers_mut().insert(
                 http::header::CONTENT_TYPE,
-                HeaderValue::from_static("application/octet-stream"),
+                HeaderValue::from_static(BINARY_CONTENT_TYPE),
             );
+            resp.extensions_mut()
+                .insert(JsonTranscoder(binary_to_json::<T>));
             resp
         }
         Err(e) => {
@@ -115,3 +655,136 @@ pub fn warp_reply_binary<T: Serialize>(val: &T) -> impl warp::Reply {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use slimchain_common::error::StateError;
+
+    #[test]
+    fn test_is_zstd() {
+        assert!(is_zstd(Some("zstd")));
+        assert!(is_zstd(Some("gzip, zstd;q=0.5")));
+        assert!(!is_zstd(Some("gzip")));
+        assert!(!is_zstd(None));
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let data = vec![42u8; 2 * MIN_COMPRESS_SIZE];
+        let compressed = zstd_encode(&data).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(data, zstd_decode(&compressed).unwrap());
+        assert!(zstd_decode(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compress_reply() {
+        set_http_compression(true);
+        // The binary encoding is compressed by snappy already, so the data should not be
+        // compressible to stay above `MIN_COMPRESS_SIZE`.
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+        let data: Vec<u8> = (0..2 * MIN_COMPRESS_SIZE).map(|_| rng.gen()).collect();
+
+        let resp = compress_reply(Some("zstd"), warp_reply_binary(&data)).await;
+        assert_eq!(
+            Some(&HeaderValue::from_static(ZSTD_ENCODING)),
+            resp.headers().get(header::CONTENT_ENCODING)
+        );
+        let buf = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let actual: Vec<u8> = binary_decode(&zstd_decode(&buf).unwrap()).unwrap();
+        assert_eq!(data, actual);
+
+        let resp = compress_reply(None, warp_reply_binary(&data)).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        let resp = compress_reply(Some("zstd"), warp::reply::json(&data)).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_reply() {
+        let data = vec![1u32, 2, 3];
+
+        let resp = negotiate_reply(Some("application/json"), warp_reply_binary(&data)).await;
+        assert_eq!(
+            Some(&HeaderValue::from_static(JSON_CONTENT_TYPE)),
+            resp.headers().get(header::CONTENT_TYPE)
+        );
+        let buf = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(b"[1,2,3]", buf.as_ref());
+
+        let resp = negotiate_reply(None, warp_reply_binary(&data)).await;
+        let buf = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let actual: Vec<u32> = binary_decode(&buf).unwrap();
+        assert_eq!(data, actual);
+    }
+
+    #[tokio::test]
+    async fn test_warp_body_binary() {
+        let filter = warp_body_binary::<Vec<u32>>();
+
+        let actual = warp::test::request()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body("[1,2,3]")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], actual);
+
+        let actual = warp::test::request()
+            .method("POST")
+            .body(binary_encode(&vec![4u32, 5]).unwrap())
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(vec![4, 5], actual);
+    }
+
+    #[tokio::test]
+    async fn test_reply_error() {
+        let e = Error::msg(StateError::Conflict).context("Failed to admit the tx.");
+        let resp = reply_error(&e);
+        assert_eq!(StatusCode::CONFLICT, resp.status());
+        let buf = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let e = resp_error(
+            surf::StatusCode::Conflict,
+            std::str::from_utf8(&buf).unwrap(),
+        );
+        assert_eq!(ErrorCode::Conflict, error_code(&e));
+        assert_eq!(
+            "Failed to admit the tx.: Tx with conflict. (code: 200).",
+            e.to_string()
+        );
+
+        let e = resp_error(surf::StatusCode::InternalServerError, "Unhandled rejection");
+        assert_eq!(ErrorCode::Internal, error_code(&e));
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert!(authorize(None, None).is_ok());
+        assert!(authorize(None, Some("Bearer abc")).is_ok());
+        assert!(authorize(Some("abc"), Some("Bearer abc")).is_ok());
+
+        let err = authorize(Some("abc"), None).unwrap_err();
+        assert_eq!(ErrorCode::Unauthorized, err.code());
+        assert!(authorize(Some("abc"), Some("Bearer abd")).is_err());
+        assert!(authorize(Some("abc"), Some("Bearer ab")).is_err());
+        assert!(authorize(Some("abc"), Some("abc")).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_url() {
+        assert_eq!("http://127.0.0.1:8000", endpoint_url("127.0.0.1:8000"));
+        assert_eq!(
+            "http://127.0.0.1:8000",
+            endpoint_url("http://127.0.0.1:8000")
+        );
+        assert_eq!(
+            "https://127.0.0.1:8000",
+            endpoint_url("https://127.0.0.1:8000/")
+        );
+    }
+}
@@ -1,12 +1,13 @@
-use rand::seq::IteratorRandom;
+use super::admission::AdmissionConfig;
+use rand::seq::{IteratorRandom, SliceRandom};
 use serde::{Deserialize, Serialize};
 use slimchain_chain::role::Role;
 use slimchain_common::{
     collections::HashMap,
-    error::{anyhow, Result},
+    error::{anyhow, ensure, Context, Result},
     utils::derive_more,
 };
-use std::sync::Arc;
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
 
 #[derive(
     Debug,
@@ -33,20 +34,217 @@ pub struct NetworkConfig {
     /// The peer id of this node
     pub peer_id: PeerId,
 
-    /// Listen address for HTTP server
+    /// Listen address for HTTP server. A list to listen on several ones, e.g., both IPv4 and
+    /// IPv6.
     #[serde(default = "default_http_listen")]
-    pub http_listen: String,
+    pub http_listen: ListenAddrs,
+
+    /// Listen address for gRPC server (Client only). Disabled if missing.
+    #[serde(default)]
+    pub grpc_listen: Option<String>,
+
+    /// TLS for HTTP server. Plaintext if missing.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Rate limiting on the tx submission. Unlimited if missing.
+    #[serde(default)]
+    pub admission: AdmissionConfig,
+
+    /// Compress the binary RPC payloads with zstd.
+    #[serde(default)]
+    pub compression: bool,
+
+    /// Talk to the peers in HTTP/2, so that the concurrent requests share one connection.
+    #[serde(default)]
+    pub http2: bool,
+
+    /// Bearer token required by the admin endpoints, including the leader lookup. Open to anyone
+    /// if missing, with a warning at the startup.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+
+    /// Batching of the tx proposals sent to the leader (Storage only).
+    #[serde(default)]
+    pub leader_batch: LeaderBatchConfig,
+
+    /// Time (in ms) a storage node waits for a missing block proposal before fetching it from
+    /// the client nodes (Storage only). 0 to wait forever.
+    #[serde(default = "default_block_gap_timeout")]
+    pub block_gap_timeout: u64,
+
+    /// Number of the storage nodes of the shard each tx is forwarded to at once (Client only).
+    /// The first one accepting it wins. The leader drops the duplicated tx proposals.
+    #[serde(default = "default_tx_redundancy")]
+    pub tx_redundancy: usize,
 
     /// Known peers
     #[serde(default = "Vec::new")]
     pub peers: Vec<PeerConfig>,
 }
 
-fn default_http_listen() -> String {
+fn default_http_listen() -> ListenAddrs {
     "127.0.0.1:8000".into()
 }
 
+// One or more addresses a server listens on. A single address can be given as a plain string in
+// the config.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(from = "ListenAddrsRepr")]
+pub struct ListenAddrs(Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ListenAddrsRepr {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<ListenAddrsRepr> for ListenAddrs {
+    fn from(repr: ListenAddrsRepr) -> Self {
+        match repr {
+            ListenAddrsRepr::One(addr) => Self(vec![addr]),
+            ListenAddrsRepr::Many(addrs) => Self(addrs),
+        }
+    }
+}
+
+impl From<&str> for ListenAddrs {
+    fn from(addr: &str) -> Self {
+        Self(vec![addr.to_string()])
+    }
+}
+
+impl From<String> for ListenAddrs {
+    fn from(addr: String) -> Self {
+        Self(vec![addr])
+    }
+}
+
+impl From<Vec<String>> for ListenAddrs {
+    fn from(addrs: Vec<String>) -> Self {
+        Self(addrs)
+    }
+}
+
+impl fmt::Display for ListenAddrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(", "))
+    }
+}
+
+impl ListenAddrs {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|addr| addr.as_str())
+    }
+
+    // Parse every address, e.g., into `SocketAddr` or `Multiaddr`.
+    pub fn parse<T>(&self) -> Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        ensure!(!self.0.is_empty(), "No listen address.");
+        self.iter()
+            .map(|addr| {
+                addr.parse::<T>()
+                    .with_context(|| format!("Invalid listen address {}.", addr))
+            })
+            .collect()
+    }
+}
+
+fn default_block_gap_timeout() -> u64 {
+    5_000
+}
+
+fn default_tx_redundancy() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LeaderBatchConfig {
+    /// Min number of tx proposals in one batch.
+    #[serde(default = "default_min_batch")]
+    pub min_batch: usize,
+
+    /// Max number of tx proposals in one batch.
+    #[serde(default = "default_max_batch")]
+    pub max_batch: usize,
+
+    /// Max number of batches in flight.
+    #[serde(default = "default_batch_window")]
+    pub window: usize,
+
+    /// The batch grows while the leader replies within this latency (in ms), and shrinks
+    /// otherwise.
+    #[serde(default = "default_batch_target_latency")]
+    pub target_latency: u64,
+}
+
+impl Default for LeaderBatchConfig {
+    fn default() -> Self {
+        Self {
+            min_batch: default_min_batch(),
+            max_batch: default_max_batch(),
+            window: default_batch_window(),
+            target_latency: default_batch_target_latency(),
+        }
+    }
+}
+
+fn default_min_batch() -> usize {
+    8
+}
+
+fn default_max_batch() -> usize {
+    256
+}
+
+fn default_batch_window() -> usize {
+    16
+}
+
+fn default_batch_target_latency() -> u64 {
+    50
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// Path to the certificate chain in PEM
+    pub cert: PathBuf,
+    /// Path to the private key in PEM
+    pub key: PathBuf,
+    /// Path to the CA certificate of the peers in PEM. Mutual TLS if set: the requests without
+    /// a client certificate signed by it are refused, and the requests sent to the peers carry
+    /// the certificate above.
+    #[serde(default)]
+    pub peer_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    // Use the peer certificates in the requests sent to the other nodes. No-op without mutual
+    // TLS.
+    pub fn install_as_global(&self) -> Result<()> {
+        match self.peer_ca.as_ref() {
+            Some(ca) => super::common::install_peer_http_client(&self.cert, &self.key, ca),
+            None => Ok(()),
+        }
+    }
+}
+
 impl NetworkConfig {
+    // Set up the HTTP client used to reach the peers.
+    pub fn install_as_global(&self) -> Result<()> {
+        super::common::set_http_compression(self.compression);
+        super::common::set_http2(self.http2);
+        super::common::set_admin_token(self.admin_token.clone());
+        match self.tls.as_ref() {
+            Some(tls) => tls.install_as_global(),
+            None => Ok(()),
+        }
+    }
+
     pub fn to_route_table(&self) -> NetworkRouteTable {
         let mut peer_table = HashMap::new();
         for peer in &self.peers {
@@ -103,6 +301,20 @@ impl NetworkRouteTable {
             .ok_or_else(|| anyhow!("Failed to get peer address. PeerId: {}.", peer_id))
     }
 
+    // All peers of the role in random order.
+    pub fn shuffled_peers(&self, role: &Role) -> Vec<PeerId> {
+        let mut peers = self.role_table.get(role).cloned().unwrap_or_default();
+        peers.shuffle(&mut rand::thread_rng());
+        peers
+    }
+
+    pub fn storage_shard_totals(&self) -> impl Iterator<Item = u64> + '_ {
+        self.role_table.keys().filter_map(|role| match role {
+            Role::Storage(shard_id) => Some(shard_id.total),
+            _ => None,
+        })
+    }
+
     pub fn random_peer(&self, role: &Role) -> Option<PeerId> {
         match self.role_table.get(role) {
             Some(list) => {
@@ -163,6 +375,18 @@ pub struct RaftConfig {
     /// How to broadcast the block to storage node
     #[serde(default)]
     pub async_broadcast_storage: bool,
+    /// Whether a new client should start from the latest snapshot of another client
+    /// instead of replaying all blocks, or catch up using the snapshot delta if it has a local one
+    #[serde(default)]
+    pub snapshot_bootstrap: bool,
+    /// The client nodes forming the cluster on the first start. Default to this node and all the
+    /// client peers. The others wait to be added as voters through the admin endpoints.
+    #[serde(default)]
+    pub initial_members: Option<Vec<PeerId>>,
+    /// The client nodes replicating the log without voting, e.g., the observers. They are never
+    /// counted in the quorum.
+    #[serde(default)]
+    pub learners: Vec<PeerId>,
 }
 
 impl RaftConfig {
@@ -201,6 +425,22 @@ impl RaftConfig {
 
         Ok(Arc::new(cfg_builder.validate()?))
     }
+
+    // The peers may not list the client node itself, so it is added to the default members.
+    pub fn initial_members(
+        &self,
+        route_table: &NetworkRouteTable,
+    ) -> std::collections::HashSet<async_raft::NodeId> {
+        match self.initial_members.as_ref() {
+            Some(members) => members.iter().map(|id| id.0).collect(),
+            None => route_table
+                .all_client_peer_ids()
+                .into_iter()
+                .chain(std::iter::once(route_table.peer_id().0))
+                .filter(|&id| !self.learners.contains(&PeerId(id)))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -257,4 +497,84 @@ mod tests {
         assert_eq!("127.0.0.1:8000", &peer.address);
         assert_eq!(Role::Storage(ShardId::new(1, 2)), peer.role);
     }
+
+    #[test]
+    fn test_listen_addrs() {
+        use slimchain_utils::{config::Config, toml};
+        use std::net::SocketAddr;
+
+        let input = toml::toml! {
+            [network]
+            peer_id = 0
+        };
+        let cfg: NetworkConfig = Config::from_toml(input).get("network").unwrap();
+        assert_eq!(ListenAddrs::from("127.0.0.1:8000"), cfg.http_listen);
+
+        let input = toml::toml! {
+            [network]
+            peer_id = 0
+            http_listen = "0.0.0.0:8000"
+        };
+        let cfg: NetworkConfig = Config::from_toml(input).get("network").unwrap();
+        let addrs: Vec<SocketAddr> = cfg.http_listen.parse().unwrap();
+        assert_eq!(vec!["0.0.0.0:8000".parse::<SocketAddr>().unwrap()], addrs);
+
+        let input = toml::toml! {
+            [network]
+            peer_id = 0
+            http_listen = ["0.0.0.0:8000", "[::]:8000"]
+        };
+        let cfg: NetworkConfig = Config::from_toml(input).get("network").unwrap();
+        let addrs: Vec<SocketAddr> = cfg.http_listen.parse().unwrap();
+        assert_eq!(2, addrs.len());
+        assert!(addrs[1].is_ipv6());
+        assert_eq!("0.0.0.0:8000, [::]:8000", cfg.http_listen.to_string());
+
+        assert!(ListenAddrs::from(Vec::new()).parse::<SocketAddr>().is_err());
+        assert!(ListenAddrs::from("localhost")
+            .parse::<SocketAddr>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_route_table_storage_peers() {
+        use slimchain_common::basic::ShardId;
+
+        let peer = |peer_id: u64, role: Role| PeerConfig {
+            peer_id: PeerId(peer_id),
+            address: format!("127.0.0.1:{}", 8000 + peer_id),
+            role,
+        };
+        let cfg = NetworkConfig {
+            peer_id: PeerId(0),
+            http_listen: default_http_listen(),
+            grpc_listen: None,
+            tls: None,
+            admission: AdmissionConfig::default(),
+            compression: false,
+            http2: false,
+            admin_token: None,
+            leader_batch: LeaderBatchConfig::default(),
+            block_gap_timeout: default_block_gap_timeout(),
+            tx_redundancy: default_tx_redundancy(),
+            peers: vec![
+                peer(0, Role::Client),
+                peer(1, Role::Storage(ShardId::new(0, 2))),
+                peer(2, Role::Storage(ShardId::new(1, 2))),
+                peer(3, Role::Storage(ShardId::new(1, 2))),
+            ],
+        };
+        let route_table = cfg.to_route_table();
+
+        let mut totals: Vec<_> = route_table.storage_shard_totals().collect();
+        totals.sort_unstable();
+        assert_eq!(vec![2, 2], totals);
+
+        let mut peers = route_table.shuffled_peers(&Role::Storage(ShardId::new(1, 2)));
+        peers.sort_unstable();
+        assert_eq!(vec![PeerId(2), PeerId(3)], peers);
+        assert!(route_table
+            .shuffled_peers(&Role::Storage(ShardId::default()))
+            .is_empty());
+    }
 }
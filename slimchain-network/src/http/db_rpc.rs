@@ -0,0 +1,87 @@
+use super::common::*;
+use serde::{Deserialize, Serialize};
+use slimchain_chain::db::{BackupInfo, DBPtr, DBStats};
+use slimchain_common::error::{Error, Result};
+use std::path::PathBuf;
+use warp::Filter;
+
+const DB_STATS_ROUTE_PATH: &str = "db_stats";
+const ADMIN_ROUTE_PATH: &str = "admin";
+const DB_BACKUP_ROUTE_PATH: &str = "backup";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DBBackupRequest {
+    // The path where the backup is written to, relative to the backup_dir of the node. It must not
+    // exist yet.
+    pub path: PathBuf,
+}
+
+pub async fn get_db_stats(endpoint: &str) -> Result<DBStats> {
+    send_get_request_using_json(&format!(
+        "{}/{}",
+        endpoint_url(endpoint),
+        DB_STATS_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn backup_db(endpoint: &str, path: PathBuf) -> Result<BackupInfo> {
+    send_admin_post_request(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            ADMIN_ROUTE_PATH,
+            DB_BACKUP_ROUTE_PATH
+        ),
+        &DBBackupRequest { path },
+    )
+    .await
+}
+
+#[derive(Debug)]
+struct DBRpcError(Error);
+
+impl warp::reject::Reject for DBRpcError {}
+
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, warp::Rejection> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(Error::msg)
+        .and_then(|res| res)
+        .map_err(|e| warp::reject::custom(DBRpcError(e)))
+}
+
+pub fn db_rpc_server(db: DBPtr) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let db_copy = db.clone();
+    let db_stats_route = warp::get()
+        .and(warp::path(DB_STATS_ROUTE_PATH))
+        .and(warp::path::end())
+        .and_then(move || {
+            let db = db_copy.clone();
+            async move {
+                run_blocking(move || db.stats())
+                    .await
+                    .map(|stats| warp::reply::json(&stats))
+            }
+        });
+
+    let db_backup_route = warp::post()
+        .and(warp::path(ADMIN_ROUTE_PATH))
+        .and(warp::path(DB_BACKUP_ROUTE_PATH))
+        .and(warp::path::end())
+        .and(warp_admin_auth())
+        .and(warp::body::json())
+        .and_then(move |req: DBBackupRequest| {
+            let db = db.clone();
+            async move {
+                run_blocking(move || db.backup(&req.path))
+                    .await
+                    .map(|info| warp::reply::json(&info))
+            }
+        })
+        .recover(recover_admin_auth);
+
+    db_stats_route.or(db_backup_route).boxed()
+}
@@ -0,0 +1,158 @@
+use super::common::*;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{block::BlockHeader, state_proof::StateProof};
+use slimchain_common::{
+    basic::{Address, BlockHeight, StateKey, H256},
+    error::{Error, Result},
+};
+use std::sync::Arc;
+use warp::Filter;
+
+const LIGHT_CLIENT_RPC_ROUTE_PATH: &str = "light_client_rpc";
+const BLOCK_HEIGHT_ROUTE_PATH: &str = "block_height";
+const BLOCK_HEADER_ROUTE_PATH: &str = "block_header";
+const VERIFY_TX_ROUTE_PATH: &str = "verify_tx";
+const VERIFY_STATE_ROUTE_PATH: &str = "verify_state";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VerifyTxHttpRequest {
+    pub height: BlockHeight,
+    pub tx_hash: H256,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VerifyStateHttpRequest {
+    pub height: BlockHeight,
+    pub address: Address,
+    pub key: Option<StateKey>,
+}
+
+pub async fn get_synced_block_height(endpoint: &str) -> Result<BlockHeight> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        LIGHT_CLIENT_RPC_ROUTE_PATH,
+        BLOCK_HEIGHT_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn get_block_header(endpoint: &str, height: BlockHeight) -> Result<BlockHeader> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            LIGHT_CLIENT_RPC_ROUTE_PATH,
+            BLOCK_HEADER_ROUTE_PATH
+        ),
+        &height,
+    )
+    .await
+}
+
+// Return the tx once its inclusion in the block has been verified by the light client. See
+// `verify_tx_inclusion_proof` for what is checked on the signature.
+pub async fn verify_tx<Tx: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    height: BlockHeight,
+    tx_hash: H256,
+) -> Result<Tx> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            LIGHT_CLIENT_RPC_ROUTE_PATH,
+            VERIFY_TX_ROUTE_PATH
+        ),
+        &VerifyTxHttpRequest { height, tx_hash },
+    )
+    .await
+}
+
+// Return the account (and the value) in the state of a past block, once its proof has been
+// verified by the light client.
+pub async fn verify_state(
+    endpoint: &str,
+    height: BlockHeight,
+    address: Address,
+    key: Option<StateKey>,
+) -> Result<StateProof> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            LIGHT_CLIENT_RPC_ROUTE_PATH,
+            VERIFY_STATE_ROUTE_PATH
+        ),
+        &VerifyStateHttpRequest {
+            height,
+            address,
+            key,
+        },
+    )
+    .await
+}
+
+#[derive(Debug)]
+struct LightClientRpcServerError(Error);
+
+impl warp::reject::Reject for LightClientRpcServerError {}
+
+pub fn light_client_rpc_server<Tx, VerifyTxOutput, VerifyStateOutput>(
+    block_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+    block_header_fn: impl Fn(BlockHeight) -> Result<BlockHeader> + Send + Sync + 'static,
+    verify_tx_fn: impl Fn(VerifyTxHttpRequest) -> VerifyTxOutput + Send + Sync + 'static,
+    verify_state_fn: impl Fn(VerifyStateHttpRequest) -> VerifyStateOutput + Send + Sync + 'static,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)>
+where
+    Tx: Serialize + for<'de> Deserialize<'de>,
+    VerifyTxOutput: TryFuture<Ok = Tx, Error = Error> + Send + 'static,
+    VerifyStateOutput: TryFuture<Ok = StateProof, Error = Error> + Send + 'static,
+{
+    let block_height_fn = Arc::new(block_height_fn);
+    let block_height_route = warp::get()
+        .and(warp::path(BLOCK_HEIGHT_ROUTE_PATH))
+        .map(move || {
+            let block_height = block_height_fn();
+            warp_reply_binary(&block_height)
+        });
+    let block_header_fn = Arc::new(block_header_fn);
+    let block_header_route = warp::post()
+        .and(warp::path(BLOCK_HEADER_ROUTE_PATH))
+        .and(warp_body_binary())
+        .and_then(move |height: BlockHeight| {
+            let header = block_header_fn(height);
+            async move {
+                header
+                    .map(|header| warp_reply_binary(&header))
+                    .map_err(|e| warp::reject::custom(LightClientRpcServerError(e)))
+            }
+        });
+    let verify_tx_fn = Arc::new(verify_tx_fn);
+    let verify_tx_route = warp::post()
+        .and(warp::path(VERIFY_TX_ROUTE_PATH))
+        .and(warp_body_binary())
+        .and_then(move |req: VerifyTxHttpRequest| {
+            verify_tx_fn(req)
+                .map_ok(|tx| warp_reply_binary(&tx))
+                .map_err(|e| warp::reject::custom(LightClientRpcServerError(e)))
+        });
+    let verify_state_fn = Arc::new(verify_state_fn);
+    let verify_state_route = warp::post()
+        .and(warp::path(VERIFY_STATE_ROUTE_PATH))
+        .and(warp_body_binary())
+        .and_then(move |req: VerifyStateHttpRequest| {
+            verify_state_fn(req)
+                .map_ok(|proof| warp_reply_binary(&proof))
+                .map_err(|e| warp::reject::custom(LightClientRpcServerError(e)))
+        });
+    warp::path(LIGHT_CLIENT_RPC_ROUTE_PATH)
+        .and(
+            block_height_route
+                .or(block_header_route)
+                .or(verify_tx_route)
+                .or(verify_state_route),
+        )
+        .boxed()
+}
@@ -0,0 +1,109 @@
+use super::{
+    admission::QueueDepth,
+    common::warp_serve,
+    config::TlsConfig,
+    status::{status_server, StatusService},
+};
+use crate::tx_route::tx_route_stats;
+use futures::future;
+use slimchain_common::error::Result;
+use slimchain_utils::prometheus::{self, MetricKind};
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use warp::{http::header::CONTENT_TYPE, Filter};
+
+const METRICS_ROUTE_PATH: &str = "metrics";
+
+// Expose the txs waiting in the admission queue of the node.
+pub fn register_queue_depth(queue_depth: QueueDepth) {
+    prometheus::register_value(
+        "slimchain_tx_queue_depth",
+        "Number of the accepted txs waiting to be processed.",
+        MetricKind::Gauge,
+        move || queue_depth.get() as f64,
+    );
+}
+
+fn register_tx_route_stats() {
+    prometheus::register_value(
+        "slimchain_tx_routed_total",
+        "Number of the txs accepted by a storage node.",
+        MetricKind::Counter,
+        || tx_route_stats().routed as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tx_route_retried_total",
+        "Number of the txs resent to another storage node after a failure.",
+        MetricKind::Counter,
+        || tx_route_stats().retried as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tx_route_failed_total",
+        "Number of the txs dropped after all routing attempts failed.",
+        MetricKind::Counter,
+        || tx_route_stats().failed as f64,
+    );
+}
+
+// The Prometheus scrape endpoint. The recorded metrics are aggregated from the time it is
+// created.
+pub fn metrics_server() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    prometheus::enable();
+    register_tx_route_stats();
+    warp::get()
+        .and(warp::path(METRICS_ROUTE_PATH))
+        .and(warp::path::end())
+        .map(|| {
+            warp::reply::with_header(prometheus::render(), CONTENT_TYPE, prometheus::CONTENT_TYPE)
+        })
+        .boxed()
+}
+
+// A standalone server for the nodes which serve no HTTP otherwise. It also reports the node
+// status.
+pub fn spawn_metrics_server(
+    endpoint: &str,
+    tls: Option<&TlsConfig>,
+    status: StatusService,
+) -> Result<JoinHandle<()>> {
+    info!("Create metrics server, listen on {}", endpoint);
+    let listen_addr: SocketAddr = endpoint.parse()?;
+    let srv = warp_serve!(
+        metrics_server().or(status_server(status)),
+        vec![listen_addr],
+        tls,
+        future::pending()
+    );
+    Ok(tokio::spawn(srv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_utils::record_event;
+
+    #[tokio::test]
+    async fn test_metrics_server() {
+        let filter = metrics_server();
+        let queue_depth = QueueDepth::default();
+        queue_depth.incr(3);
+        register_queue_depth(queue_depth);
+        record_event!("tx_commit", "tx_ids": ["a", "b"], "height": 1);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        assert_eq!(
+            prometheus::CONTENT_TYPE,
+            resp.headers()[CONTENT_TYPE].to_str().unwrap()
+        );
+        let body = String::from_utf8(resp.body().to_vec()).unwrap();
+        assert!(body.contains("slimchain_tx_queue_depth 3\n"));
+        assert!(body.contains("# TYPE slimchain_tx_routed_total counter\n"));
+        assert!(body.contains("slimchain_events_total{label=\"tx_commit\"}"));
+        assert!(body.contains("slimchain_block_height "));
+    }
+}
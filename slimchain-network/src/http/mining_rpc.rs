@@ -0,0 +1,119 @@
+use super::{common::*, config::TlsConfig, metrics::metrics_server};
+use futures::future;
+use slimchain_chain::consensus::pow::{ExternalMiner, PoWWork};
+use slimchain_common::{basic::Nonce, error::Result};
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use warp::{Filter, Reply};
+
+const MINING_RPC_ROUTE_PATH: &str = "mining_rpc";
+const GET_WORK_ROUTE_PATH: &str = "getwork";
+const SUBMIT_WORK_ROUTE_PATH: &str = "submitwork";
+
+// None if the miner has no block waiting for the nonce.
+pub async fn get_work(endpoint: &str) -> Result<Option<PoWWork>> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        MINING_RPC_ROUTE_PATH,
+        GET_WORK_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn submit_work(endpoint: &str, id: u64, nonce: Nonce) -> Result<()> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            MINING_RPC_ROUTE_PATH,
+            SUBMIT_WORK_ROUTE_PATH
+        ),
+        &(id, nonce),
+    )
+    .await
+}
+
+pub fn mining_rpc_server(miner: ExternalMiner) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    let miner_copy = miner.clone();
+    let get_work_route = warp::get()
+        .and(warp::path(GET_WORK_ROUTE_PATH))
+        .and(warp::path::end())
+        .map(move || warp_reply_binary(&miner_copy.get_work()));
+    let submit_work_route = warp::post()
+        .and(warp::path(SUBMIT_WORK_ROUTE_PATH))
+        .and(warp::path::end())
+        .and(warp_body_binary())
+        .map(
+            move |(id, nonce): (u64, Nonce)| match miner.submit_work(id, nonce) {
+                Ok(()) => warp_reply_binary(&()).into_response(),
+                Err(e) => {
+                    warn!(id, "Refused the submitted work. Error: {}", e);
+                    reply_error(&e.into())
+                }
+            },
+        );
+    warp::path(MINING_RPC_ROUTE_PATH)
+        .and(get_work_route.or(submit_work_route))
+        .boxed()
+}
+
+pub fn spawn_mining_server(
+    endpoint: &str,
+    tls: Option<&TlsConfig>,
+    miner: ExternalMiner,
+) -> Result<JoinHandle<()>> {
+    info!("Create mining server, listen on {}", endpoint);
+    let listen_addr: SocketAddr = endpoint.parse()?;
+    let srv = warp_serve!(
+        mining_rpc_server(miner).or(metrics_server()),
+        vec![listen_addr],
+        tls,
+        future::pending()
+    );
+    Ok(tokio::spawn(srv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_chain::{block::BlockTrait, consensus::pow::Block};
+    use slimchain_utils::serde::{binary_decode, binary_encode};
+
+    #[tokio::test]
+    async fn test_mining_rpc_server() {
+        let miner = ExternalMiner::new();
+        let filter = mining_rpc_server(miner.clone());
+        let get_work = || async {
+            let resp = warp::test::request()
+                .method("GET")
+                .path("/mining_rpc/getwork")
+                .reply(&filter)
+                .await;
+            binary_decode::<Option<PoWWork>>(resp.body()).unwrap()
+        };
+        assert_eq!(None, get_work().await);
+
+        let prev_blk = Block::genesis_block();
+        let mut header = prev_blk.block_header().clone();
+        header.height = header.height.next_height();
+        let blk_fut = miner.create_new_block(header, &prev_blk);
+        let work = get_work().await.unwrap();
+
+        let nonce = (0u64..)
+            .map(Nonce::from)
+            .find(|&nonce| work.nonce_is_valid(nonce))
+            .unwrap();
+        let submit_work = |id: u64| {
+            warp::test::request()
+                .method("POST")
+                .path("/mining_rpc/submitwork")
+                .body(binary_encode(&(id, nonce)).unwrap())
+                .reply(&filter)
+        };
+        assert_eq!(400, submit_work(work.id + 1).await.status());
+        assert_eq!(200, submit_work(work.id).await.status());
+        assert_eq!(None, get_work().await);
+        blk_fut.await.unwrap();
+    }
+}
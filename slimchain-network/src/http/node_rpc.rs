@@ -1,6 +1,14 @@
 use super::{common::*, config::PeerId};
-use serde::Serialize;
-use slimchain_common::error::Result;
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    behavior::{ViewCallOutput, ViewCallRequest},
+    state_proof::StateProof,
+    tx_proof::TxInclusionProof,
+};
+use slimchain_common::{
+    basic::{Address, BlockHeight, StateKey, H256},
+    error::Result,
+};
 
 pub const NODE_RPC_ROUTE_PATH: &str = "node_rpc";
 
@@ -10,14 +18,40 @@ pub const RAFT_VOTE_ROUTE_PATH: &str = "raft_vote";
 
 pub const STORAGE_BLOCK_IMPORT_ROUTE_PATH: &str = "storage_block_import";
 pub const STORAGE_TX_REQ_ROUTE_PATH: &str = "storage_tx_req";
+pub const STORAGE_BLOCKS_ROUTE_PATH: &str = "storage_blocks";
+pub const STORAGE_TX_ROUTE_PATH: &str = "storage_tx";
+pub const STORAGE_TX_PROOF_ROUTE_PATH: &str = "storage_tx_proof";
+pub const STORAGE_STATE_PROOF_ROUTE_PATH: &str = "storage_state_proof";
+pub const STORAGE_CALL_ROUTE_PATH: &str = "storage_call";
+
+pub const AUDITOR_BLOCK_IMPORT_ROUTE_PATH: &str = "auditor_block_import";
 
 pub const CLIENT_LEADER_ID_ROUTE_PATH: &str = "leader_id";
 pub const CLIENT_LEADER_REQ_ROUTE_PATH: &str = "leader_req";
+pub const CLIENT_SNAPSHOT_ROUTE_PATH: &str = "snapshot";
+pub const CLIENT_SNAPSHOT_DELTA_ROUTE_PATH: &str = "snapshot_delta";
+pub const CLIENT_LEADER_TRANSFER_ROUTE_PATH: &str = "leader_transfer";
+pub const CLIENT_RAFT_LOG_INDEX_ROUTE_PATH: &str = "raft_log_index";
+pub const CLIENT_BLOCK_PROPOSAL_ROUTE_PATH: &str = "block_proposal";
+pub const CLIENT_READ_INDEX_ROUTE_PATH: &str = "read_index";
 
 pub async fn get_leader(endpoint: &str) -> Result<PeerId> {
     send_get_request_using_binary(&format!(
-        "http://{}/{}/{}",
-        endpoint, NODE_RPC_ROUTE_PATH, CLIENT_LEADER_ID_ROUTE_PATH
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        NODE_RPC_ROUTE_PATH,
+        CLIENT_LEADER_ID_ROUTE_PATH
+    ))
+    .await
+}
+
+// The read index confirmed by the raft leader. It fails on the other nodes.
+pub async fn get_read_index(endpoint: &str) -> Result<u64> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        NODE_RPC_ROUTE_PATH,
+        CLIENT_READ_INDEX_ROUTE_PATH
     ))
     .await
 }
@@ -26,10 +60,164 @@ pub async fn get_leader(endpoint: &str) -> Result<PeerId> {
 pub async fn send_reqs_to_leader<Req: Serialize>(endpoint: &str, reqs: &Vec<Req>) -> Result<()> {
     send_post_request_using_binary(
         &format!(
-            "http://{}/{}/{}",
-            endpoint, NODE_RPC_ROUTE_PATH, CLIENT_LEADER_REQ_ROUTE_PATH,
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            CLIENT_LEADER_REQ_ROUTE_PATH,
         ),
         reqs,
     )
     .await
 }
+
+pub async fn fetch_blocks_from_storage<Block: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    from: BlockHeight,
+    count: u64,
+) -> Result<Vec<Block>> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            STORAGE_BLOCKS_ROUTE_PATH,
+        ),
+        &(from, count),
+    )
+    .await
+}
+
+// Fetch the block proposal recently broadcast by the client. None if it is not kept any more.
+pub async fn fetch_block_proposal_from_client<Proposal: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    height: BlockHeight,
+) -> Result<Option<Proposal>> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            CLIENT_BLOCK_PROPOSAL_ROUTE_PATH,
+        ),
+        &height,
+    )
+    .await
+}
+
+pub async fn fetch_tx_from_storage<Tx: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    tx_hash: H256,
+) -> Result<Tx> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            STORAGE_TX_ROUTE_PATH,
+        ),
+        &tx_hash,
+    )
+    .await
+}
+
+pub async fn fetch_tx_proof_from_storage(endpoint: &str, tx_id: H256) -> Result<TxInclusionProof> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            STORAGE_TX_PROOF_ROUTE_PATH,
+        ),
+        &tx_id,
+    )
+    .await
+}
+
+// Prove the account (and the value) in the state of a past block. Only the accounts in the shard
+// of the storage node can be proven.
+pub async fn fetch_state_proof_from_storage(
+    endpoint: &str,
+    height: BlockHeight,
+    address: Address,
+    key: Option<StateKey>,
+) -> Result<StateProof> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            STORAGE_STATE_PROOF_ROUTE_PATH,
+        ),
+        &(height, address, key),
+    )
+    .await
+}
+
+// Run a read-only call on the latest state of the storage node.
+pub async fn call_view_on_storage(endpoint: &str, req: &ViewCallRequest) -> Result<ViewCallOutput> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            STORAGE_CALL_ROUTE_PATH,
+        ),
+        req,
+    )
+    .await
+}
+
+pub async fn fetch_snapshot_from_client<Snapshot: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+) -> Result<Snapshot> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        NODE_RPC_ROUTE_PATH,
+        CLIENT_SNAPSHOT_ROUTE_PATH
+    ))
+    .await
+}
+
+pub async fn fetch_snapshot_delta_from_client<SnapshotDelta: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    base_height: BlockHeight,
+    base_hash: H256,
+) -> Result<SnapshotDelta> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            CLIENT_SNAPSHOT_DELTA_ROUTE_PATH,
+        ),
+        &(base_height, base_hash),
+    )
+    .await
+}
+
+pub async fn send_leader_transfer_to_client<Transfer: Serialize>(
+    endpoint: &str,
+    transfer: &Transfer,
+) -> Result<()> {
+    send_post_request_using_binary(
+        &format!(
+            "{}/{}/{}",
+            endpoint_url(endpoint),
+            NODE_RPC_ROUTE_PATH,
+            CLIENT_LEADER_TRANSFER_ROUTE_PATH,
+        ),
+        transfer,
+    )
+    .await
+}
+
+pub async fn get_raft_log_index(endpoint: &str) -> Result<u64> {
+    send_get_request_using_binary(&format!(
+        "{}/{}/{}",
+        endpoint_url(endpoint),
+        NODE_RPC_ROUTE_PATH,
+        CLIENT_RAFT_LOG_INDEX_ROUTE_PATH
+    ))
+    .await
+}
@@ -0,0 +1,194 @@
+use super::{admission::QueueDepth, common::*, config::NetworkRouteTable};
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockTrait, db::DBPtr, latest::LatestBlockHeaderPtr, loader::BlockLoaderTrait,
+    role::Role,
+};
+use slimchain_common::{
+    basic::{BlockHeight, H256},
+    error::{Error, Result},
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+use warp::Filter;
+
+const STATUS_ROUTE_PATH: &str = "status";
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+type BlockHashFn = dyn Fn(BlockHeight) -> Result<H256> + Send + Sync;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    #[serde(flatten)]
+    pub role: Role,
+    pub height: BlockHeight,
+    // None if the latest block is not stored by the node.
+    pub block_hash: Option<H256>,
+    // Number of the peers by role, e.g., `Storage-0-1`.
+    pub peers: BTreeMap<String, usize>,
+    // Number of the accepted txs waiting to be processed.
+    pub queue_depth: usize,
+    pub version: String,
+}
+
+pub async fn get_status(endpoint: &str) -> Result<NodeStatus> {
+    send_get_request_using_json(&format!("{}/{}", endpoint_url(endpoint), STATUS_ROUTE_PATH)).await
+}
+
+// The peers known to the node, shared with whoever discovers them.
+#[derive(Debug, Default, Clone)]
+pub struct PeerCounts(Arc<Mutex<BTreeMap<String, usize>>>);
+
+impl PeerCounts {
+    // The peers listed in the configure.
+    pub fn from_route_table(route_table: &NetworkRouteTable) -> Self {
+        let counts = Self::default();
+        for (role, peers) in route_table.role_table() {
+            counts.set(role, peers.len());
+        }
+        counts
+    }
+
+    pub fn set(&self, role: &Role, count: usize) {
+        let mut counts = self.0.lock().expect("Failed to lock peer counts.");
+        if count == 0 {
+            counts.remove(&role.to_string());
+        } else {
+            counts.insert(role.to_string(), count);
+        }
+    }
+
+    pub fn get(&self) -> BTreeMap<String, usize> {
+        self.0.lock().expect("Failed to lock peer counts.").clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct StatusService {
+    role: Role,
+    latest_block_header: LatestBlockHeaderPtr,
+    block_hash_fn: Arc<BlockHashFn>,
+    peer_counts: PeerCounts,
+    queue_depth: Option<QueueDepth>,
+}
+
+impl StatusService {
+    pub fn new<Block>(role: Role, db: DBPtr, latest_block_header: LatestBlockHeaderPtr) -> Self
+    where
+        Block: BlockTrait + for<'de> Deserialize<'de> + 'static,
+    {
+        Self {
+            role,
+            latest_block_header,
+            block_hash_fn: Arc::new(move |height| {
+                let blk: Block = db.get_block(height)?;
+                Ok(blk.to_digest())
+            }),
+            peer_counts: PeerCounts::default(),
+            queue_depth: None,
+        }
+    }
+
+    pub fn with_peer_counts(mut self, peer_counts: PeerCounts) -> Self {
+        self.peer_counts = peer_counts;
+        self
+    }
+
+    pub fn with_queue_depth(mut self, queue_depth: QueueDepth) -> Self {
+        self.queue_depth = Some(queue_depth);
+        self
+    }
+
+    pub fn status(&self) -> NodeStatus {
+        let height = self.latest_block_header.get_height();
+        let block_hash = match (self.block_hash_fn)(height) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                debug!(
+                    "Failed to load block #{} for the status. Error: {}",
+                    height, e
+                );
+                None
+            }
+        };
+        NodeStatus {
+            role: self.role,
+            height,
+            block_hash,
+            peers: self.peer_counts.get(),
+            queue_depth: self.queue_depth.as_ref().map_or(0, |depth| depth.get()),
+            version: VERSION.to_string(),
+        }
+    }
+}
+
+pub fn status_server(service: StatusService) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path(STATUS_ROUTE_PATH))
+        .and(warp::path::end())
+        .and_then(move || {
+            let service = service.clone();
+            async move {
+                tokio::task::spawn_blocking(move || service.status())
+                    .await
+                    .map(|status| warp::reply::json(&status))
+                    .map_err(|e| warp::reject::custom(StatusError(Error::msg(e))))
+            }
+        })
+        .boxed()
+}
+
+#[derive(Debug)]
+struct StatusError(Error);
+
+impl warp::reject::Reject for StatusError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_chain::{consensus::raft::Block, latest::LatestBlockHeader};
+    use slimchain_common::{basic::ShardId, digest::Digestible};
+
+    #[tokio::test]
+    async fn test_status_server() {
+        let genesis = Block::genesis_block();
+        let genesis_hash = genesis.to_digest();
+        let role = Role::Storage(ShardId::new(1, 2));
+        let peer_counts = PeerCounts::default();
+        peer_counts.set(&Role::Client, 2);
+        peer_counts.set(&Role::Miner, 1);
+        peer_counts.set(&Role::Miner, 0);
+        let queue_depth = QueueDepth::default();
+        queue_depth.incr(5);
+        let service = StatusService {
+            role,
+            latest_block_header: LatestBlockHeader::new_from_block(&genesis),
+            block_hash_fn: Arc::new(move |_| Ok(genesis_hash)),
+            peer_counts: PeerCounts::default(),
+            queue_depth: None,
+        }
+        .with_peer_counts(peer_counts)
+        .with_queue_depth(queue_depth);
+        let filter = status_server(service);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/status")
+            .reply(&filter)
+            .await;
+        assert_eq!(200, resp.status());
+        let status: NodeStatus = serde_json::from_slice(resp.body()).unwrap();
+        let expect = NodeStatus {
+            role,
+            height: BlockHeight::default(),
+            block_hash: Some(genesis_hash),
+            peers: vec![("Client".to_string(), 2)].into_iter().collect(),
+            queue_depth: 5,
+            version: VERSION.to_string(),
+        };
+        assert_eq!(expect, status);
+    }
+}
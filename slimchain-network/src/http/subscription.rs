@@ -0,0 +1,103 @@
+use super::common::endpoint_url;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockHeader,
+    latest::LatestBlockHeaderPtr,
+    tx_event::{subscribe_tx_events, TxEvent},
+};
+use slimchain_common::error::{Error, Result};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsClientMessage};
+use warp::{
+    ws::{Message, WebSocket, Ws},
+    Filter,
+};
+
+const SUBSCRIBE_ROUTE_PATH: &str = "subscribe";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionEvent {
+    NewBlock { header: BlockHeader },
+    Tx(TxEvent),
+    // The subscriber is too slow to catch up. Some tx events are dropped.
+    Lagged { skipped: u64 },
+}
+
+// Subscribe to the new blocks and the tx events pushed by the client node.
+pub async fn subscribe_events(
+    endpoint: &str,
+) -> Result<impl Stream<Item = Result<SubscriptionEvent>>> {
+    // http -> ws, https -> wss
+    let url = endpoint_url(endpoint).replacen("http", "ws", 1);
+    let (ws, _) = connect_async(format!("{}/{}", url, SUBSCRIBE_ROUTE_PATH))
+        .await
+        .map_err(Error::msg)?;
+    Ok(ws
+        .try_filter_map(|msg| async move {
+            Ok(match msg {
+                WsClientMessage::Text(text) => Some(text),
+                _ => None,
+            })
+        })
+        .map_err(Error::msg)
+        .and_then(|text| async move { serde_json::from_str(&text).map_err(Error::msg) }))
+}
+
+async fn serve_subscription(
+    socket: WebSocket,
+    mut block_rx: watch::Receiver<Arc<BlockHeader>>,
+    mut tx_rx: broadcast::Receiver<TxEvent>,
+) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    loop {
+        let event = tokio::select! {
+            res = block_rx.changed() => match res {
+                Ok(()) => {
+                    let header = block_rx.borrow().as_ref().clone();
+                    SubscriptionEvent::NewBlock { header }
+                }
+                Err(_) => break,
+            },
+            res = tx_rx.recv() => match res {
+                Ok(event) => SubscriptionEvent::Tx(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    SubscriptionEvent::Lagged { skipped }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = ws_rx.next() => match msg {
+                Some(Ok(msg)) if !msg.is_close() => continue,
+                _ => break,
+            },
+        };
+
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to encode the subscription event. Error: {}", e);
+                continue;
+            }
+        };
+        if ws_tx.send(Message::text(text)).await.is_err() {
+            break;
+        }
+    }
+    ws_tx.close().await.ok();
+}
+
+pub fn subscription_server(
+    latest_block_header: LatestBlockHeaderPtr,
+) -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
+    warp::path(SUBSCRIBE_ROUTE_PATH)
+        .and(warp::path::end())
+        .and(warp::ws())
+        .map(move |ws: Ws| {
+            let block_rx = latest_block_header.subscribe();
+            let tx_rx = subscribe_tx_events();
+            ws.on_upgrade(move |socket| serve_subscription(socket, block_rx, tx_rx))
+        })
+        .boxed()
+}
@@ -2,5 +2,9 @@
 extern crate tracing;
 
 pub mod behavior;
+pub mod control;
+pub mod grpc;
 pub mod http;
 pub mod p2p;
+pub mod tx_route;
+pub mod tx_trace;
@@ -1,6 +1,10 @@
 pub mod config;
+pub mod conn_limit;
 pub mod control;
 pub mod discovery;
+pub mod dnsaddr;
 pub mod http;
 pub mod pubsub;
+pub mod relay;
 pub mod rpc;
+pub mod sync;
@@ -3,30 +3,86 @@ use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializ
 use slimchain_common::error::{Error, Result};
 use std::fmt;
 
+pub use crate::{
+    http::{
+        admission::AdmissionConfig,
+        config::{ListenAddrs, TlsConfig},
+    },
+    p2p::{conn_limit::ConnLimitConfig, pubsub::PubSubConfig, relay::RelayConfig},
+};
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct NetworkConfig {
-    /// Listen address for node
+    /// Listen address for node. A list to listen on several ones, e.g., both
+    /// /ip4/0.0.0.0/tcp/6000 and /ip6/::/tcp/6000. All of them are advertised to the peers.
     #[serde(default = "default_listen")]
-    pub listen: String,
-    /// Listen address for HTTP server (Client only)
+    pub listen: ListenAddrs,
+    /// Listen address for QUIC, e.g., /ip4/0.0.0.0/udp/6000/quic. Disabled if missing.
+    #[serde(default)]
+    pub quic_listen: Option<String>,
+    /// Listen address for HTTP server (Client and LightClient only). A list to listen on several
+    /// ones.
     #[serde(default = "default_http_listen")]
-    pub http_listen: String,
+    pub http_listen: ListenAddrs,
+    /// Listen address for gRPC server (Client only). Disabled if missing.
+    #[serde(default)]
+    pub grpc_listen: Option<String>,
+    /// Listen address for the Prometheus metrics and the node status (Miner and Storage only).
+    /// The client serves them on the HTTP server instead. Disabled if missing.
+    #[serde(default)]
+    pub metrics_listen: Option<String>,
+    /// Listen address for the getwork/submitwork interface of the external miners (Miner only).
+    /// The nonce search is then left to them instead of running in the node. Disabled if missing.
+    #[serde(default)]
+    pub mining_listen: Option<String>,
+    /// TLS for HTTP server and metrics endpoint. Plaintext if missing.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Rate limiting on the tx submission (Client only). Unlimited if missing.
+    #[serde(default)]
+    pub admission: AdmissionConfig,
+    /// Bearer token required by the admin endpoints, i.e., the database backup (Client and
+    /// LightClient only). Open to anyone if missing.
+    #[serde(default)]
+    pub admin_token: Option<String>,
     /// Ed25519 key
     #[serde(default = "default_keypair")]
     pub keypair: KeypairConfig,
     /// Whether to enable mDNS
     #[serde(default = "default_mdns")]
     pub mdns: bool,
+    /// Message signing and validation of the gossip.
+    #[serde(default)]
+    pub pubsub: PubSubConfig,
+    /// Caps on the connections and the backoff of dialing the unreachable peers.
+    #[serde(default)]
+    pub conn_limit: ConnLimitConfig,
+    /// Circuit relay for the nodes behind NAT. Disabled if missing.
+    #[serde(default)]
+    pub relay: RelayConfig,
     /// Known peers
     #[serde(default = "Vec::new")]
     pub peers: Vec<PeerConfig>,
+    /// Domains listing the bootstrap peers in the TXT records of `_dnsaddr.<domain>`.
+    #[serde(default = "Vec::new")]
+    pub dnsaddr: Vec<String>,
+    /// Interval (in seconds) of resolving the dnsaddr domains again. 0 to resolve them only once.
+    #[serde(default = "default_dnsaddr_refresh")]
+    pub dnsaddr_refresh: u64,
 }
 
-fn default_listen() -> String {
+impl NetworkConfig {
+    // The admin requests sent by this node carry the admin token as well.
+    pub fn install_as_global(&self) {
+        crate::http::common::set_admin_token(self.admin_token.clone());
+    }
+}
+
+fn default_listen() -> ListenAddrs {
     "/ip4/0.0.0.0/tcp/6000".into()
 }
 
-fn default_http_listen() -> String {
+fn default_http_listen() -> ListenAddrs {
     "127.0.0.1:8000".into()
 }
 
@@ -41,6 +97,10 @@ fn default_mdns() -> bool {
     true
 }
 
+fn default_dnsaddr_refresh() -> u64 {
+    300
+}
+
 #[derive(Clone)]
 pub struct KeypairConfig(pub libp2p::identity::ed25519::Keypair);
 
@@ -0,0 +1,220 @@
+use libp2p::{
+    core::{connection::ConnectionId, network::ConnectionLimits},
+    swarm::{
+        protocols_handler::DummyProtocolsHandler, NetworkBehaviour, NetworkBehaviourAction,
+        PollParameters, ProtocolsHandler,
+    },
+    Multiaddr, PeerId,
+};
+use serde::Deserialize;
+use slimchain_chain::role::Role;
+use slimchain_common::collections::HashMap;
+use std::{
+    cmp,
+    collections::VecDeque,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnLimitConfig {
+    /// Max number of the established inbound connections. Unlimited if missing.
+    #[serde(default)]
+    pub max_incoming: Option<u32>,
+
+    /// Max number of the established outbound connections. Unlimited if missing.
+    #[serde(default)]
+    pub max_outgoing: Option<u32>,
+
+    /// Max number of the connections being set up in each direction. Unlimited if missing.
+    #[serde(default)]
+    pub max_pending: Option<u32>,
+
+    /// Max number of the established connections to a single peer. Unlimited if missing.
+    #[serde(default)]
+    pub max_per_peer: Option<u32>,
+
+    /// Max number of the connected peers of each role. The storage peers are counted per shard.
+    #[serde(default)]
+    pub max_peers_per_role: RolePeerLimits,
+
+    /// Time (in ms) before dialing an unreachable peer again. It doubles on every consecutive
+    /// failure. 0 to disable the backoff.
+    #[serde(default = "default_dial_backoff_init")]
+    pub dial_backoff_init: u64,
+
+    /// Upper bound (in ms) of the dial backoff.
+    #[serde(default = "default_dial_backoff_max")]
+    pub dial_backoff_max: u64,
+}
+
+impl Default for ConnLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_incoming: None,
+            max_outgoing: None,
+            max_pending: None,
+            max_per_peer: None,
+            max_peers_per_role: RolePeerLimits::default(),
+            dial_backoff_init: default_dial_backoff_init(),
+            dial_backoff_max: default_dial_backoff_max(),
+        }
+    }
+}
+
+fn default_dial_backoff_init() -> u64 {
+    1_000
+}
+
+fn default_dial_backoff_max() -> u64 {
+    60_000
+}
+
+impl ConnLimitConfig {
+    pub fn to_connection_limits(&self) -> ConnectionLimits {
+        ConnectionLimits::default()
+            .with_max_established_incoming(self.max_incoming)
+            .with_max_established_outgoing(self.max_outgoing)
+            .with_max_pending_incoming(self.max_pending)
+            .with_max_pending_outgoing(self.max_pending)
+            .with_max_established_per_peer(self.max_per_peer)
+    }
+
+    pub fn dial_backoff(&self) -> Option<DialBackoff> {
+        match self.dial_backoff_init {
+            0 => None,
+            init => Some(DialBackoff::new(
+                Duration::from_millis(init),
+                Duration::from_millis(cmp::max(init, self.dial_backoff_max)),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct RolePeerLimits {
+    #[serde(default)]
+    pub client: Option<usize>,
+    #[serde(default)]
+    pub light_client: Option<usize>,
+    #[serde(default)]
+    pub miner: Option<usize>,
+    #[serde(default)]
+    pub storage: Option<usize>,
+    #[serde(default)]
+    pub auditor: Option<usize>,
+}
+
+impl RolePeerLimits {
+    pub fn get(&self, role: &Role) -> Option<usize> {
+        match role {
+            Role::Client => self.client,
+            Role::LightClient => self.light_client,
+            Role::Miner => self.miner,
+            Role::Storage(_) => self.storage,
+            Role::Auditor => self.auditor,
+        }
+    }
+}
+
+// The exponential backoff of dialing the unreachable peers. The count is reset once the peer is
+// connected again.
+#[derive(Debug)]
+pub struct DialBackoff {
+    init: Duration,
+    max: Duration,
+    failures: HashMap<PeerId, u32>,
+}
+
+impl DialBackoff {
+    pub fn new(init: Duration, max: Duration) -> Self {
+        Self {
+            init,
+            max,
+            failures: HashMap::new(),
+        }
+    }
+
+    // Return the time to wait before dialing the peer again.
+    pub fn on_failure(&mut self, peer_id: PeerId) -> Duration {
+        let failures = self.failures.entry(peer_id).or_insert(0);
+        let backoff = self
+            .init
+            .checked_mul(1u32 << cmp::min(*failures, 16))
+            .map_or(self.max, |backoff| cmp::min(backoff, self.max));
+        *failures = failures.saturating_add(1);
+        backoff
+    }
+
+    pub fn on_success(&mut self, peer_id: &PeerId) {
+        self.failures.remove(peer_id);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConnGuardEvent {
+    Connected(PeerId),
+    Disconnected(PeerId),
+    DialFailed(PeerId),
+}
+
+// Report the connections and the dial failures, which are otherwise only seen by the swarm.
+#[derive(Default)]
+pub struct ConnGuard {
+    pending_events: VecDeque<ConnGuardEvent>,
+}
+
+impl NetworkBehaviour for ConnGuard {
+    type ProtocolsHandler = DummyProtocolsHandler;
+    type OutEvent = ConnGuardEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        DummyProtocolsHandler::default()
+    }
+
+    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+        vec![]
+    }
+
+    fn inject_connected(&mut self, peer_id: &PeerId) {
+        self.pending_events
+            .push_back(ConnGuardEvent::Connected(*peer_id));
+    }
+
+    fn inject_disconnected(&mut self, peer_id: &PeerId) {
+        self.pending_events
+            .push_back(ConnGuardEvent::Disconnected(*peer_id));
+    }
+
+    fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+        self.pending_events
+            .push_back(ConnGuardEvent::DialFailed(*peer_id));
+    }
+
+    fn inject_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        _: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
+    ) {
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context,
+        _: &mut impl PollParameters,
+    ) -> Poll<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        match self.pending_events.pop_front() {
+            Some(event) => Poll::Ready(NetworkBehaviourAction::GenerateEvent(event)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,58 @@
+use super::*;
+use slimchain_common::basic::ShardId;
+use slimchain_utils::{config::Config, toml};
+
+#[test]
+fn test_dial_backoff() {
+    let mut backoff = DialBackoff::new(Duration::from_secs(1), Duration::from_secs(5));
+    let peer1 = PeerId::random();
+    let peer2 = PeerId::random();
+
+    assert_eq!(Duration::from_secs(1), backoff.on_failure(peer1));
+    assert_eq!(Duration::from_secs(2), backoff.on_failure(peer1));
+    assert_eq!(Duration::from_secs(4), backoff.on_failure(peer1));
+    assert_eq!(Duration::from_secs(5), backoff.on_failure(peer1));
+    assert_eq!(Duration::from_secs(1), backoff.on_failure(peer2));
+
+    for _ in 0..100 {
+        assert_eq!(Duration::from_secs(5), backoff.on_failure(peer1));
+    }
+
+    backoff.on_success(&peer1);
+    assert_eq!(Duration::from_secs(1), backoff.on_failure(peer1));
+    assert_eq!(Duration::from_secs(2), backoff.on_failure(peer2));
+}
+
+#[test]
+fn test_deserialize_conn_limit_config() {
+    let input = toml::toml! {
+        [conn_limit]
+    };
+    let cfg: ConnLimitConfig = Config::from_toml(input).get("conn_limit").unwrap();
+    assert_eq!(None, cfg.max_incoming);
+    assert_eq!(None, cfg.max_peers_per_role.get(&Role::Client));
+    assert!(cfg.dial_backoff().is_some());
+
+    let input = toml::toml! {
+        [conn_limit]
+        max_incoming = 100
+        max_per_peer = 2
+        dial_backoff_init = 0
+
+        [conn_limit.max_peers_per_role]
+        client = 10
+        storage = 4
+    };
+    let cfg: ConnLimitConfig = Config::from_toml(input).get("conn_limit").unwrap();
+    assert_eq!(Some(100), cfg.max_incoming);
+    assert_eq!(None, cfg.max_outgoing);
+    assert_eq!(Some(2), cfg.max_per_peer);
+    assert_eq!(Some(10), cfg.max_peers_per_role.get(&Role::Client));
+    assert_eq!(
+        Some(4),
+        cfg.max_peers_per_role
+            .get(&Role::Storage(ShardId::new(1, 2)))
+    );
+    assert_eq!(None, cfg.max_peers_per_role.get(&Role::Miner));
+    assert!(cfg.dial_backoff().is_none());
+}
@@ -1,3 +1,7 @@
+use crate::p2p::{
+    config::NetworkConfig,
+    relay::{RelayConfig, WithRelay},
+};
 use async_trait::async_trait;
 use futures::{
     channel::{mpsc, oneshot},
@@ -5,13 +9,16 @@ use futures::{
     prelude::*,
 };
 use libp2p::{
-    core::{muxing, transport},
+    core::{muxing, network::ConnectionLimits, transport, upgrade},
+    dns,
     identity::Keypair,
+    noise,
+    relay::{self, Relay},
     swarm::{
         protocols_handler::ProtocolsHandler, IntoProtocolsHandler, NetworkBehaviour, Swarm,
-        SwarmEvent,
+        SwarmBuilder, SwarmEvent,
     },
-    Multiaddr, PeerId,
+    tcp, yamux, Multiaddr, PeerId, Transport,
 };
 use slimchain_common::error::{bail, Error, Result};
 use std::{pin::Pin, task::Poll, time::Duration};
@@ -23,11 +30,38 @@ const YAMUX_MAX_NUM_STREAM: usize = 8192;
 pub(crate) async fn build_transport(
     keypair: &Keypair,
 ) -> Result<transport::Boxed<(PeerId, muxing::StreamMuxerBox)>> {
-    use libp2p::{core::upgrade, dns, noise, tcp, yamux, Transport};
+    let tcp = build_tcp_transport().await?;
+    Ok(upgrade_transport(tcp, keypair))
+}
+
+// The relay transport dials and listens on the /p2p-circuit addresses through the returned relay
+// behaviour. The rest of the addresses go to TCP.
+pub(crate) async fn build_relay_transport(
+    keypair: &Keypair,
+    cfg: &RelayConfig,
+) -> Result<(transport::Boxed<(PeerId, muxing::StreamMuxerBox)>, Relay)> {
+    let tcp = build_tcp_transport().await?;
+    let (transport, relay) = relay::new_transport_and_behaviour(cfg.to_libp2p_config(), tcp);
+    Ok((upgrade_transport(transport, keypair), relay))
+}
 
+async fn build_tcp_transport() -> Result<dns::DnsConfig<tcp::TcpConfig>> {
     let tcp = tcp::TcpConfig::new().nodelay(true);
-    let transport = dns::DnsConfig::system(tcp).await?;
+    Ok(dns::DnsConfig::system(tcp).await?)
+}
 
+fn upgrade_transport<T>(
+    transport: T,
+    keypair: &Keypair,
+) -> transport::Boxed<(PeerId, muxing::StreamMuxerBox)>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+    T::Output: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    T::Error: Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::Listener: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
     let noise_keys = noise::Keypair::<noise::X25519Spec>::new()
         .into_authentic(keypair)
         .expect("Signing libp2p-noise static DH keypair failed.");
@@ -37,14 +71,65 @@ pub(crate) async fn build_transport(
     mux_cfg.set_max_num_streams(YAMUX_MAX_NUM_STREAM);
     mux_cfg.set_window_update_mode(yamux::WindowUpdateMode::on_read());
 
-    Ok(transport
+    transport
         .upgrade(upgrade::Version::V1Lazy)
         .authenticate(noise::NoiseConfig::xx(noise_keys).into_authenticated())
         .multiplex(mux_cfg)
         .timeout(Duration::from_secs(20))
+        .boxed()
+}
+
+// QUIC runs over a UDP socket bound to `quic_listen`. The TCP transport is kept to reach the
+// peers without QUIC.
+#[cfg(feature = "quic")]
+pub(crate) async fn build_quic_transport(
+    keypair: &Keypair,
+    quic_listen: Multiaddr,
+    tcp: transport::Boxed<(PeerId, muxing::StreamMuxerBox)>,
+) -> Result<transport::Boxed<(PeerId, muxing::StreamMuxerBox)>> {
+    use libp2p::core::either::EitherOutput;
+    use libp2p_quic::{QuicConfig, TlsCrypto};
+
+    // libp2p-quic takes the ed25519-dalek keypair instead.
+    let quic_keypair = match keypair {
+        Keypair::Ed25519(keypair) => {
+            libp2p_quic::Keypair::from_bytes(&keypair.encode()[..]).map_err(Error::msg)?
+        }
+        _ => bail!("QUIC only supports the ed25519 keypair."),
+    };
+    let quic = QuicConfig::<TlsCrypto>::new(quic_keypair)
+        .listen_on(quic_listen)
+        .await
+        .map_err(Error::msg)?;
+    // `QuicTransport` is a stream as well, so `map` has to be called through `Transport`.
+    let quic = Transport::map(quic, |(peer_id, muxer), _| {
+        (peer_id, muxing::StreamMuxerBox::new(muxer))
+    });
+
+    Ok(quic
+        .or_transport(tcp)
+        .map(|output, _| match output {
+            EitherOutput::First(output) | EitherOutput::Second(output) => output,
+        })
         .boxed())
 }
 
+#[cfg(not(feature = "quic"))]
+pub(crate) async fn build_quic_transport(
+    _keypair: &Keypair,
+    _quic_listen: Multiaddr,
+    _tcp: transport::Boxed<(PeerId, muxing::StreamMuxerBox)>,
+) -> Result<transport::Boxed<(PeerId, muxing::StreamMuxerBox)>> {
+    bail!("QUIC is not supported. Rebuild with the `quic` feature.");
+}
+
+fn parse_quic_listen(net_cfg: &NetworkConfig) -> Result<Option<Multiaddr>> {
+    match net_cfg.quic_listen.as_deref() {
+        Some(quic_listen) => Ok(Some(quic_listen.parse()?)),
+        None => Ok(None),
+    }
+}
+
 #[async_trait]
 pub trait Shutdown {
     async fn shutdown(&mut self) -> Result<()>;
@@ -61,6 +146,8 @@ where
     peer_id: PeerId,
     key_pair: Keypair,
     swarm: Swarm<Behaviour>,
+    quic_listen: Option<Multiaddr>,
+    relay_listen: Vec<Multiaddr>,
 }
 
 impl<Behaviour> Swarmer<Behaviour>
@@ -72,22 +159,107 @@ where
     <<<Behaviour as NetworkBehaviour>::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent: Send + 'static,
 {
     pub async fn new(key_pair: Keypair, behaviour: Behaviour) -> Result<Self> {
+        Self::new_with_limits(key_pair, behaviour, None, ConnectionLimits::default()).await
+    }
+
+    pub async fn new_with_quic(
+        key_pair: Keypair,
+        behaviour: Behaviour,
+        quic_listen: Multiaddr,
+    ) -> Result<Self> {
+        Self::new_with_limits(
+            key_pair,
+            behaviour,
+            Some(quic_listen),
+            ConnectionLimits::default(),
+        )
+        .await
+    }
+
+    // The connections beyond the limits are refused by the swarm.
+    pub async fn new_with_limits(
+        key_pair: Keypair,
+        behaviour: Behaviour,
+        quic_listen: Option<Multiaddr>,
+        limits: ConnectionLimits,
+    ) -> Result<Self> {
+        let tcp = build_transport(&key_pair).await?;
+        Self::new_with_transport(key_pair, behaviour, tcp, quic_listen, limits).await
+    }
+
+    async fn new_with_transport(
+        key_pair: Keypair,
+        behaviour: Behaviour,
+        tcp: transport::Boxed<(PeerId, muxing::StreamMuxerBox)>,
+        quic_listen: Option<Multiaddr>,
+        limits: ConnectionLimits,
+    ) -> Result<Self> {
         let peer_id = key_pair.public().into_peer_id();
-        let transport = build_transport(&key_pair).await?;
-        let swarm = Swarm::new(transport, behaviour, peer_id);
+        let transport = match quic_listen.clone() {
+            Some(quic_listen) => build_quic_transport(&key_pair, quic_listen, tcp).await?,
+            None => tcp,
+        };
+        let swarm = SwarmBuilder::new(transport, behaviour, peer_id)
+            .connection_limits(limits)
+            .build();
 
         Ok(Self {
             peer_id,
             key_pair,
             swarm,
+            quic_listen,
+            relay_listen: Vec::new(),
         })
     }
 
+    pub async fn from_net_cfg(net_cfg: &NetworkConfig, behaviour: Behaviour) -> Result<Self> {
+        Self::new_with_limits(
+            net_cfg.keypair.to_libp2p_keypair(),
+            behaviour,
+            parse_quic_listen(net_cfg)?,
+            net_cfg.conn_limit.to_connection_limits(),
+        )
+        .await
+    }
+
+    // Same as `from_net_cfg`, plus the circuit relay if enabled.
+    pub async fn from_net_cfg_with_relay(
+        net_cfg: &NetworkConfig,
+        mut behaviour: Behaviour,
+    ) -> Result<Self>
+    where
+        Behaviour: WithRelay,
+    {
+        if !net_cfg.relay.enabled {
+            return Self::from_net_cfg(net_cfg, behaviour).await;
+        }
+
+        let key_pair = net_cfg.keypair.to_libp2p_keypair();
+        let relay_listen = net_cfg.relay.listen_addrs()?;
+        let (tcp, relay) = build_relay_transport(&key_pair, &net_cfg.relay).await?;
+        behaviour.set_relay(relay);
+        let mut swarmer = Self::new_with_transport(
+            key_pair,
+            behaviour,
+            tcp,
+            parse_quic_listen(net_cfg)?,
+            net_cfg.conn_limit.to_connection_limits(),
+        )
+        .await?;
+        swarmer.relay_listen = relay_listen;
+        Ok(swarmer)
+    }
+
     pub async fn listen_on(&mut self, address: Multiaddr) -> Result<Multiaddr> {
-        Swarm::listen_on(&mut self.swarm, address).map_err(Error::msg)?;
+        let id = Swarm::listen_on(&mut self.swarm, address).map_err(Error::msg)?;
+        // Skip the addresses reported by the other listeners, e.g., the rest of the interfaces
+        // behind an earlier 0.0.0.0.
         let address = loop {
             match self.swarm.select_next_some().await {
-                SwarmEvent::NewListenAddr { address, .. } => break address,
+                SwarmEvent::NewListenAddr {
+                    listener_id,
+                    address,
+                } if listener_id == id => break address,
                 SwarmEvent::ListenerError { error, .. } => {
                     bail!("Error during listen. Error: {:?}", error);
                 }
@@ -157,10 +329,27 @@ where
         Control { tx, swarm_rx, handler }
     }
 
-    pub async fn spawn_app(mut self, address: &str) -> Result<Control<Behaviour>> {
-        let listen_addr = self.listen_on_str(address).await?;
-        let peer_cfg = crate::p2p::config::PeerConfig::new(self.peer_id, listen_addr);
-        peer_cfg.print_config_msg();
+    // Listen on every address. The peers learn all of them through identify.
+    pub async fn spawn_app(
+        mut self,
+        addresses: &crate::p2p::config::ListenAddrs,
+    ) -> Result<Control<Behaviour>> {
+        for address in addresses.parse::<Multiaddr>()? {
+            let listen_addr = self.listen_on(address).await?;
+            let peer_cfg = crate::p2p::config::PeerConfig::new(self.peer_id, listen_addr);
+            peer_cfg.print_config_msg();
+        }
+        if let Some(quic_listen) = self.quic_listen.clone() {
+            let listen_addr = self.listen_on(quic_listen).await?;
+            let peer_cfg = crate::p2p::config::PeerConfig::new(self.peer_id, listen_addr);
+            peer_cfg.print_config_msg();
+        }
+        // The peers behind NAT are reached via the relays.
+        for relay_addr in std::mem::take(&mut self.relay_listen) {
+            let listen_addr = self.listen_on(relay_addr).await?;
+            let peer_cfg = crate::p2p::config::PeerConfig::new(self.peer_id, listen_addr);
+            peer_cfg.print_config_msg();
+        }
         Ok(self.spawn())
     }
 }
@@ -229,9 +418,7 @@ where
     }
 
     pub async fn run_until_interrupt(self) -> Result<()> {
-        info!("Press Ctrl-C to quit.");
-        tokio::signal::ctrl_c().await?;
-        info!("Quitting.");
+        crate::control::ShutdownCoordinator::new().wait().await?;
         self.shutdown().await?;
         Ok(())
     }
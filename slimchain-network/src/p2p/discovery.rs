@@ -1,5 +1,16 @@
-use crate::p2p::config::NetworkConfig;
-use futures::{channel::oneshot, prelude::*};
+use crate::{
+    http::status::PeerCounts,
+    p2p::{
+        config::{NetworkConfig, PeerConfig},
+        conn_limit::{ConnGuard, ConnGuardEvent, ConnLimitConfig, DialBackoff, RolePeerLimits},
+        dnsaddr::spawn_dnsaddr_resolver,
+        relay::{Relay, WithRelay},
+    },
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    prelude::*,
+};
 use futures_timer::Delay;
 use libp2p::{
     identify::{Identify, IdentifyConfig, IdentifyEvent},
@@ -11,12 +22,16 @@ use libp2p::{
     },
     mdns::{Mdns, MdnsConfig, MdnsEvent},
     ping::{Ping, PingConfig, PingEvent},
-    swarm::{toggle::Toggle, NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
+    swarm::{
+        toggle::Toggle, CloseConnection, NetworkBehaviourAction, NetworkBehaviourEventProcess,
+        PollParameters,
+    },
     Multiaddr, NetworkBehaviour, PeerId,
 };
 use rand::seq::IteratorRandom;
 use slimchain_chain::role::Role;
 use slimchain_common::{
+    basic::ShardId,
     collections::{HashMap, HashSet},
     create_id_type_u64,
     error::{anyhow, Result},
@@ -55,6 +70,8 @@ pub struct Discovery {
     identify: Identify,
     ping: Ping,
     mdns: Toggle<Mdns>,
+    relay: Toggle<Relay>,
+    conn_guard: ConnGuard,
     #[behaviour(ignore)]
     peer_id: PeerId,
     #[behaviour(ignore)]
@@ -62,6 +79,8 @@ pub struct Discovery {
     #[behaviour(ignore)]
     rev_peer_table: HashMap<PeerId, Role>,
     #[behaviour(ignore)]
+    peer_counts: PeerCounts,
+    #[behaviour(ignore)]
     duration_to_next_kad: Duration,
     #[behaviour(ignore)]
     next_kad_query: Delay,
@@ -75,6 +94,20 @@ pub struct Discovery {
     pending_events: VecDeque<DiscoveryEvent>,
     #[behaviour(ignore)]
     pending_queries_using_ret: HashMap<QueryId, oneshot::Sender<Result<PeerId>>>,
+    #[behaviour(ignore)]
+    dnsaddr_peers: Option<mpsc::UnboundedReceiver<Vec<PeerConfig>>>,
+    #[behaviour(ignore)]
+    role_limits: RolePeerLimits,
+    #[behaviour(ignore)]
+    connected_peers: HashMap<PeerId, Role>,
+    #[behaviour(ignore)]
+    connected_per_role: HashMap<Role, usize>,
+    #[behaviour(ignore)]
+    pending_closes: VecDeque<PeerId>,
+    #[behaviour(ignore)]
+    dial_backoff: Option<DialBackoff>,
+    #[behaviour(ignore)]
+    backoff_peers: DelayQueue<(PeerId, Vec<Multiaddr>)>,
 }
 
 impl Discovery {
@@ -117,9 +150,12 @@ impl Discovery {
             identify,
             ping,
             mdns: mdns.into(),
+            relay: None.into(),
+            conn_guard: ConnGuard::default(),
             peer_id,
             peer_table: HashMap::new(),
             rev_peer_table: HashMap::new(),
+            peer_counts: PeerCounts::default(),
             duration_to_next_kad: KAD_INIT_INTERVAL,
             next_kad_query: Delay::new(Duration::from_secs(0)),
             pending_queries: HashMap::new(),
@@ -127,9 +163,22 @@ impl Discovery {
             pending_retry_queries: DelayQueue::new(),
             pending_events: VecDeque::new(),
             pending_queries_using_ret: HashMap::new(),
+            dnsaddr_peers: None,
+            role_limits: RolePeerLimits::default(),
+            connected_peers: HashMap::new(),
+            connected_per_role: HashMap::new(),
+            pending_closes: VecDeque::new(),
+            dial_backoff: ConnLimitConfig::default().dial_backoff(),
+            backoff_peers: DelayQueue::new(),
         })
     }
 
+    pub fn with_conn_limit(mut self, cfg: &ConnLimitConfig) -> Self {
+        self.role_limits = cfg.max_peers_per_role;
+        self.dial_backoff = cfg.dial_backoff();
+        self
+    }
+
     pub fn add_address(&mut self, peer_id: PeerId, address: Multiaddr) {
         if peer_id != self.peer_id {
             self.kad.add_address(&peer_id, address);
@@ -140,6 +189,15 @@ impl Discovery {
         for peer in cfg.peers.iter() {
             self.add_address(peer.peer_id, peer.address.clone());
         }
+
+        // The peers behind the dnsaddr domains are added once resolved.
+        if !cfg.dnsaddr.is_empty() {
+            let refresh = match cfg.dnsaddr_refresh {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            };
+            self.dnsaddr_peers = Some(spawn_dnsaddr_resolver(cfg.dnsaddr.clone(), refresh));
+        }
     }
 
     pub fn report_known_peers(&self) {
@@ -153,6 +211,18 @@ impl Discovery {
         self.peer_table.get(role).cloned().unwrap_or_default()
     }
 
+    // Kept up to date as the peers come and go.
+    pub fn peer_counts(&self) -> PeerCounts {
+        self.peer_counts.clone()
+    }
+
+    pub fn known_roles(&self) -> impl Iterator<Item = &Role> {
+        self.peer_table
+            .iter()
+            .filter(|(_, list)| !list.is_empty())
+            .map(|(role, _)| role)
+    }
+
     pub fn known_peer_num(&self, role: &Role) -> usize {
         self.peer_table.get(role).map_or(0, |list| list.len())
     }
@@ -196,6 +266,11 @@ impl Discovery {
         query_id
     }
 
+    // Find a storage peer serving the shard.
+    pub fn find_shard_peer(&mut self, shard_id: ShardId, timeout: Duration) -> QueryId {
+        self.find_random_peer(Role::Storage(shard_id), timeout)
+    }
+
     pub fn find_random_peer_with_ret(
         &mut self,
         role: Role,
@@ -206,6 +281,10 @@ impl Discovery {
         self.pending_queries_using_ret.insert(id, ret);
     }
 
+    fn update_peer_count(&self, role: &Role) {
+        self.peer_counts.set(role, self.known_peer_num(role));
+    }
+
     fn peer_table_add_node(&mut self, peer_id: PeerId, role: Role) {
         use slimchain_common::collections::hash_map::Entry;
 
@@ -219,12 +298,15 @@ impl Discovery {
                         .get_mut(&old_role)
                         .map(|list| list.remove(&peer_id));
                     self.peer_table.entry(role).or_default().insert(peer_id);
+                    self.update_peer_count(&old_role);
+                    self.update_peer_count(&role);
                 }
             }
             Entry::Vacant(v) => {
                 trace!("Add node {} with role {}", peer_id, role);
                 v.insert(role);
                 self.peer_table.entry(role).or_default().insert(peer_id);
+                self.update_peer_count(&role);
             }
         }
     }
@@ -240,6 +322,56 @@ impl Discovery {
         self.peer_table
             .get_mut(&role)
             .map(|list| list.remove(&peer_id));
+        self.update_peer_count(&role);
+    }
+
+    // Admit the connected peer unless there are enough peers of its role already. The rejected
+    // one is disconnected and not dialed again until its backoff expires.
+    fn admit_peer(&mut self, peer_id: PeerId, role: Role) -> bool {
+        if let Some(old_role) = self.connected_peers.get(&peer_id).copied() {
+            if old_role == role {
+                return true;
+            }
+            self.release_peer(&peer_id);
+        }
+
+        let count = self.connected_per_role.entry(role).or_default();
+        if let Some(limit) = self.role_limits.get(&role) {
+            if *count >= limit {
+                debug!(
+                    "Reject peer {} with role {}, as {} peers are connected.",
+                    peer_id, role, count
+                );
+                self.pending_closes.push_back(peer_id);
+                self.back_off(peer_id);
+                return false;
+            }
+        }
+        *count += 1;
+        self.connected_peers.insert(peer_id, role);
+        true
+    }
+
+    fn release_peer(&mut self, peer_id: &PeerId) {
+        if let Some(role) = self.connected_peers.remove(peer_id) {
+            if let Some(count) = self.connected_per_role.get_mut(&role) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    // Take the peer out of the routing table, so that it is not dialed until the backoff
+    // expires. Its addresses are added back afterward.
+    fn back_off(&mut self, peer_id: PeerId) {
+        let delay = match self.dial_backoff.as_mut() {
+            Some(backoff) => backoff.on_failure(peer_id),
+            None => return,
+        };
+        if let Some(entry) = self.kad.remove_peer(&peer_id) {
+            trace!("Back off dialing peer {} for {:?}.", peer_id, delay);
+            self.backoff_peers
+                .insert((peer_id, entry.node.value.into_vec()), delay);
+        }
     }
 
     fn poll_inner<T>(
@@ -247,6 +379,20 @@ impl Discovery {
         cx: &mut Context,
         _: &mut impl PollParameters,
     ) -> Poll<NetworkBehaviourAction<T, DiscoveryEvent>> {
+        if let Some(peer_id) = self.pending_closes.pop_front() {
+            return Poll::Ready(NetworkBehaviourAction::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+
+        while let Poll::Ready(Some(Ok(entry))) = self.backoff_peers.poll_expired(cx) {
+            let (peer_id, addrs) = entry.into_inner();
+            for addr in addrs {
+                self.add_address(peer_id, addr);
+            }
+        }
+
         if let Some(event) = self.pending_events.pop_front() {
             let DiscoveryEvent::FindPeerResult { query_id, peer } = event;
             if let Some(tx) = self.pending_queries_using_ret.remove(&query_id) {
@@ -258,6 +404,23 @@ impl Discovery {
             }
         }
 
+        let mut dnsaddr_peers = Vec::new();
+        if let Some(rx) = self.dnsaddr_peers.as_mut() {
+            loop {
+                match rx.poll_next_unpin(cx) {
+                    Poll::Ready(Some(peers)) => dnsaddr_peers.extend(peers),
+                    Poll::Ready(None) => {
+                        self.dnsaddr_peers = None;
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            }
+        }
+        for peer in dnsaddr_peers {
+            self.add_address(peer.peer_id, peer.address);
+        }
+
         while Pin::new(&mut self.next_kad_query).poll(cx).is_ready() {
             self.kad.get_closest_peers(PeerId::random());
 
@@ -325,6 +488,9 @@ impl NetworkBehaviourEventProcess<IdentifyEvent> for Discovery {
                     return;
                 }
             };
+            if !self.admit_peer(peer_id, role) {
+                return;
+            }
             self.peer_table_add_node(peer_id, role);
 
             for addr in info.listen_addrs {
@@ -334,6 +500,20 @@ impl NetworkBehaviourEventProcess<IdentifyEvent> for Discovery {
     }
 }
 
+impl NetworkBehaviourEventProcess<ConnGuardEvent> for Discovery {
+    fn inject_event(&mut self, event: ConnGuardEvent) {
+        match event {
+            ConnGuardEvent::Connected(peer_id) => {
+                if let Some(backoff) = self.dial_backoff.as_mut() {
+                    backoff.on_success(&peer_id);
+                }
+            }
+            ConnGuardEvent::Disconnected(peer_id) => self.release_peer(&peer_id),
+            ConnGuardEvent::DialFailed(peer_id) => self.back_off(peer_id),
+        }
+    }
+}
+
 impl NetworkBehaviourEventProcess<PingEvent> for Discovery {
     fn inject_event(&mut self, event: PingEvent) {
         let PingEvent { peer, result } = event;
@@ -343,6 +523,17 @@ impl NetworkBehaviourEventProcess<PingEvent> for Discovery {
     }
 }
 
+impl WithRelay for Discovery {
+    fn set_relay(&mut self, relay: Relay) {
+        self.relay = Some(relay).into();
+    }
+}
+
+// The relay emits no events.
+impl NetworkBehaviourEventProcess<()> for Discovery {
+    fn inject_event(&mut self, _event: ()) {}
+}
+
 impl NetworkBehaviourEventProcess<MdnsEvent> for Discovery {
     fn inject_event(&mut self, event: MdnsEvent) {
         if let MdnsEvent::Discovered(list) = event {
@@ -402,8 +593,17 @@ impl NetworkBehaviourEventProcess<KademliaEvent> for Discovery {
     }
 }
 
+// The key of the provider record announcing the role. The storage nodes are keyed by their
+// shard, e.g., `storage/3-of-8`, so that a peer serving the shard can be found directly.
 fn role_to_kad_key(role: Role) -> KadKey {
-    KadKey::new(&format!("{}", role).as_bytes())
+    let key = match role {
+        Role::Client => "client".to_string(),
+        Role::LightClient => "light_client".to_string(),
+        Role::Miner => "miner".to_string(),
+        Role::Storage(ShardId { id, total }) => format!("storage/{}-of-{}", id, total),
+        Role::Auditor => "auditor".to_string(),
+    };
+    KadKey::new(&key)
 }
 
 #[cfg(test)]
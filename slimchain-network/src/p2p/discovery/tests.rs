@@ -227,3 +227,16 @@ async fn test_without_mdns() {
     ctrl3.shutdown().await.unwrap();
     ctrl4.shutdown().await.unwrap();
 }
+
+#[test]
+fn test_role_to_kad_key() {
+    assert_eq!(KadKey::new(b"miner"), role_to_kad_key(Role::Miner));
+    assert_eq!(
+        KadKey::new(b"storage/3-of-8"),
+        role_to_kad_key(Role::Storage(ShardId::new(3, 8)))
+    );
+    assert_ne!(
+        role_to_kad_key(Role::Storage(ShardId::new(0, 8))),
+        role_to_kad_key(Role::Storage(ShardId::new(0, 4)))
+    );
+}
@@ -0,0 +1,142 @@
+use crate::p2p::config::PeerConfig;
+use async_std_resolver::{resolver_from_system_conf, AsyncStdResolver};
+use futures::channel::mpsc;
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use slimchain_common::error::Result;
+use std::time::Duration;
+
+const DNSADDR_TXT_PREFIX: &str = "dnsaddr=";
+// Bound the nested `/dnsaddr/...` entries, which may refer to each other.
+const MAX_DNSADDR_DEPTH: usize = 8;
+
+// Parse the TXT record, e.g., `dnsaddr=/ip4/1.2.3.4/tcp/6000/p2p/<peer_id>`.
+pub fn parse_dnsaddr_txt(txt: &str) -> Option<Multiaddr> {
+    txt.strip_prefix(DNSADDR_TXT_PREFIX)?.parse().ok()
+}
+
+// Split the address ended with `/p2p/<peer_id>` into the peer id and the address to dial.
+pub fn split_peer_address(mut address: Multiaddr) -> Option<PeerConfig> {
+    match address.pop()? {
+        Protocol::P2p(hash) => {
+            let peer_id = PeerId::from_multihash(hash).ok()?;
+            Some(PeerConfig::new(peer_id, address))
+        }
+        _ => None,
+    }
+}
+
+// The domain of the nested entry, e.g., `/dnsaddr/sub.example.com`.
+fn nested_dnsaddr_domain(address: &Multiaddr) -> Option<String> {
+    match address.iter().next()? {
+        Protocol::Dnsaddr(domain) => Some(domain.to_string()),
+        _ => None,
+    }
+}
+
+// Follow the nested entries. The entries without the peer id are skipped.
+pub async fn resolve_dnsaddr(resolver: &AsyncStdResolver, domain: &str) -> Result<Vec<PeerConfig>> {
+    let mut peers = Vec::new();
+    let mut pending = vec![(domain.to_string(), 0)];
+    while let Some((domain, depth)) = pending.pop() {
+        let txts = resolver.txt_lookup(format!("_dnsaddr.{}", domain)).await?;
+        for txt in txts.iter() {
+            let data: Vec<u8> = txt
+                .txt_data()
+                .iter()
+                .flat_map(|d| d.iter().copied())
+                .collect();
+            let address = match std::str::from_utf8(&data).ok().and_then(parse_dnsaddr_txt) {
+                Some(address) => address,
+                None => continue,
+            };
+
+            if let Some(nested) = nested_dnsaddr_domain(&address) {
+                if depth < MAX_DNSADDR_DEPTH {
+                    pending.push((nested, depth + 1));
+                } else {
+                    warn!("Skip the dnsaddr {} nested too deep.", nested);
+                }
+                continue;
+            }
+
+            match split_peer_address(address.clone()) {
+                Some(peer) => peers.push(peer),
+                None => warn!("Skip the dnsaddr {} without the peer id.", address),
+            }
+        }
+    }
+    Ok(peers)
+}
+
+// Resolve the domains at startup and again at every refresh. The task stops once the receiver
+// is dropped.
+pub fn spawn_dnsaddr_resolver(
+    domains: Vec<String>,
+    refresh: Option<Duration>,
+) -> mpsc::UnboundedReceiver<Vec<PeerConfig>> {
+    let (tx, rx) = mpsc::unbounded();
+    tokio::spawn(async move {
+        let resolver = match resolver_from_system_conf().await {
+            Ok(resolver) => resolver,
+            Err(e) => {
+                error!("Failed to create the DNS resolver. Error: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            let mut peers = Vec::new();
+            for domain in &domains {
+                match resolve_dnsaddr(&resolver, domain).await {
+                    Ok(list) => {
+                        debug!("Resolved {} peers from dnsaddr {}.", list.len(), domain);
+                        peers.extend(list);
+                    }
+                    Err(e) => warn!("Failed to resolve dnsaddr {}. Error: {}", domain, e),
+                }
+            }
+
+            if tx.unbounded_send(peers).is_err() {
+                break;
+            }
+
+            match refresh {
+                Some(refresh) => tokio::time::sleep(refresh).await,
+                None => break,
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    #[test]
+    fn test_parse_dnsaddr() {
+        let peer_id = Keypair::generate_ed25519().public().into_peer_id();
+
+        let address =
+            parse_dnsaddr_txt(&format!("dnsaddr=/ip4/1.2.3.4/tcp/6000/p2p/{}", peer_id)).unwrap();
+        assert_eq!(None, nested_dnsaddr_domain(&address));
+        let peer = split_peer_address(address).unwrap();
+        assert_eq!(
+            PeerConfig::new(peer_id, "/ip4/1.2.3.4/tcp/6000".parse().unwrap()),
+            peer
+        );
+
+        let address = parse_dnsaddr_txt("dnsaddr=/dnsaddr/sub.example.com").unwrap();
+        assert_eq!(
+            Some("sub.example.com".to_string()),
+            nested_dnsaddr_domain(&address)
+        );
+
+        let address = parse_dnsaddr_txt("dnsaddr=/ip4/1.2.3.4/tcp/6000").unwrap();
+        assert_eq!(None, split_peer_address(address));
+
+        assert_eq!(None, parse_dnsaddr_txt("v=spf1 -all"));
+        assert_eq!(None, parse_dnsaddr_txt("dnsaddr=invalid"));
+    }
+}
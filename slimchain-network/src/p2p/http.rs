@@ -1,5 +1,18 @@
-use crate::http::client_rpc::client_rpc_server;
-use futures::{channel::mpsc, future::BoxFuture, prelude::*, stream};
+use crate::http::{
+    admission::{Admission, AdmissionConfig},
+    client_rpc::client_rpc_server,
+    common::warp_serve,
+    config::{ListenAddrs, TlsConfig},
+    metrics::{metrics_server, register_queue_depth},
+    status::{status_server, StatusService},
+    subscription::subscription_server,
+};
+use futures::{
+    channel::mpsc,
+    future::{self, BoxFuture},
+    prelude::*,
+    stream,
+};
 use libp2p::{
     core::connection::ConnectionId,
     swarm::{
@@ -9,39 +22,135 @@ use libp2p::{
     Multiaddr, PeerId,
 };
 
+use slimchain_chain::{latest::LatestBlockHeaderPtr, receipt::TxReceipt};
 use slimchain_common::{
-    basic::BlockHeight,
+    basic::{BlockHeight, H256},
     error::{Error, Result},
 };
 use std::{
     net::SocketAddr,
+    sync::Arc,
     task::{Context, Poll},
 };
+use warp::Filter;
 
 pub use crate::http::client_rpc::TxHttpRequest;
 
 pub struct ClientHttpServer {
     srv: BoxFuture<'static, ()>,
+    send: mpsc::Sender<TxHttpRequest>,
     recv: mpsc::Receiver<TxHttpRequest>,
+    admission: Arc<Admission>,
 }
 
 impl ClientHttpServer {
     pub fn new(
-        endpoint: &str,
+        endpoint: &ListenAddrs,
         tx_count_fn: impl Fn() -> usize + Send + Sync + 'static,
         block_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+        tx_receipt_fn: impl Fn(H256) -> Result<Option<TxReceipt>> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        Self::new_inner(
+            endpoint,
+            None,
+            Admission::unlimited(),
+            tx_count_fn,
+            block_height_fn,
+            tx_receipt_fn,
+            None,
+            None,
+        )
+    }
+
+    // Also push the new blocks and the tx events to the WebSocket subscribers, and report the
+    // node status.
+    pub fn new_with_subscription(
+        endpoint: &ListenAddrs,
+        tls: Option<&TlsConfig>,
+        admission: &AdmissionConfig,
+        tx_count_fn: impl Fn() -> usize + Send + Sync + 'static,
+        latest_block_header: LatestBlockHeaderPtr,
+        tx_receipt_fn: impl Fn(H256) -> Result<Option<TxReceipt>> + Send + Sync + 'static,
+        status: StatusService,
+    ) -> Result<Self> {
+        let latest_block_header_copy = latest_block_header.clone();
+        Self::new_inner(
+            endpoint,
+            tls,
+            Admission::new(admission),
+            tx_count_fn,
+            move || latest_block_header_copy.get_height(),
+            tx_receipt_fn,
+            Some(latest_block_header),
+            Some(status),
+        )
+    }
+
+    fn new_inner(
+        endpoint: &ListenAddrs,
+        tls: Option<&TlsConfig>,
+        admission: Admission,
+        tx_count_fn: impl Fn() -> usize + Send + Sync + 'static,
+        block_height_fn: impl Fn() -> BlockHeight + Send + Sync + 'static,
+        tx_receipt_fn: impl Fn(H256) -> Result<Option<TxReceipt>> + Send + Sync + 'static,
+        latest_block_header: Option<LatestBlockHeaderPtr>,
+        status: Option<StatusService>,
     ) -> Result<Self> {
         info!("Create tx http server, listen on {}", endpoint);
-        let listen_addr: SocketAddr = endpoint.parse()?;
+        let listen_addrs: Vec<SocketAddr> = endpoint.parse()?;
         let (tx, rx) = mpsc::channel(1024);
+        let tx_copy = tx.clone();
         let tx_req_fn = move |reqs: Vec<TxHttpRequest>| {
-            let mut tx = tx.clone();
+            let mut tx = tx_copy.clone();
             let mut reqs = stream::iter(reqs).map(Ok);
             async move { tx.send_all(&mut reqs).await.map_err(Error::msg) }
         };
-        let route = client_rpc_server(tx_req_fn, tx_count_fn, block_height_fn);
-        let srv = warp::serve(route).bind(listen_addr).boxed();
-        Ok(Self { srv, recv: rx })
+        let admission = Arc::new(admission);
+        register_queue_depth(admission.queue_depth());
+        let route = client_rpc_server(
+            admission.clone(),
+            tx_req_fn,
+            tx_count_fn,
+            block_height_fn,
+            tx_receipt_fn,
+            None,
+        );
+        // The server runs as long as the behaviour is polled.
+        let srv = match (latest_block_header, status) {
+            (Some(latest_block_header), Some(status)) => {
+                let status = status.with_queue_depth(admission.queue_depth());
+                warp_serve!(
+                    route
+                        .or(subscription_server(latest_block_header))
+                        .or(status_server(status))
+                        .or(metrics_server()),
+                    listen_addrs,
+                    tls,
+                    future::pending()
+                )
+            }
+            _ => warp_serve!(
+                route.or(metrics_server()),
+                listen_addrs,
+                tls,
+                future::pending()
+            ),
+        };
+        Ok(Self {
+            srv,
+            send: tx,
+            recv: rx,
+            admission,
+        })
+    }
+
+    // The requests sent through it are handled as if they are received by the server.
+    pub fn tx_req_sender(&self) -> mpsc::Sender<TxHttpRequest> {
+        self.send.clone()
+    }
+
+    pub fn admission(&self) -> Arc<Admission> {
+        self.admission.clone()
     }
 }
 
@@ -80,6 +189,7 @@ impl NetworkBehaviour for ClientHttpServer {
         >,
     > {
         if let Poll::Ready(Some(req)) = self.recv.poll_next_unpin(cx) {
+            self.admission.queue_depth().decr(1);
             return Poll::Ready(NetworkBehaviourAction::GenerateEvent(req));
         }
 
@@ -1,5 +1,6 @@
 use super::*;
 use crate::{http::client_rpc::*, p2p::control::build_transport};
+use futures::channel::oneshot;
 use libp2p::swarm::SwarmEvent;
 use rand::SeedableRng;
 use serial_test::serial;
@@ -18,7 +19,7 @@ async fn test() {
         let transport = build_transport(&keypair).await.unwrap();
         libp2p::swarm::Swarm::new(
             transport,
-            ClientHttpServer::new(endpoint, || 1, || 1.into()).unwrap(),
+            ClientHttpServer::new(&endpoint.into(), || 1, || 1.into(), |_| Ok(None)).unwrap(),
             peer_id,
         )
     };
@@ -29,15 +30,22 @@ async fn test() {
         let tx_req = TxRequest::Create {
             nonce: Default::default(),
             code: Default::default(),
+            chain_id: 0,
+            expiry: None,
         };
         tx_req.sign(&keypair)
     };
 
+    // Keep polling the swarm after the tx is received, as the server is shut down once the swarm
+    // is dropped.
+    let (req_tx, req_rx) = oneshot::channel();
     let handler = tokio::spawn(async move {
+        let mut req_tx = Some(req_tx);
         loop {
-            match swarm.select_next_some().await {
-                SwarmEvent::Behaviour(event) => break event,
-                _ => {}
+            if let SwarmEvent::Behaviour(event) = swarm.select_next_some().await {
+                if let Some(req_tx) = req_tx.take() {
+                    req_tx.send(event).ok();
+                }
             }
         }
     });
@@ -45,7 +53,7 @@ async fn test() {
     send_tx_request(endpoint, signed_tx_req.clone())
         .await
         .unwrap();
-    let req = handler.await.unwrap();
+    let req = req_rx.await.unwrap();
     assert_eq!(req.req, signed_tx_req);
 
     send_record_event(endpoint, "test_event").await.unwrap();
@@ -57,4 +65,9 @@ async fn test() {
         .unwrap();
     assert_eq!(get_block_height(endpoint).await.unwrap(), 1.into());
     assert_eq!(get_tx_count(endpoint).await.unwrap(), 1);
+    assert_eq!(
+        get_tx_receipt(endpoint, signed_tx_req.id()).await.unwrap(),
+        None
+    );
+    handler.abort();
 }
@@ -2,15 +2,16 @@ use crate::p2p::config::NetworkConfig;
 use libp2p::{
     gossipsub::{
         error::PublishError, Gossipsub, GossipsubConfigBuilder, GossipsubEvent, GossipsubMessage,
-        IdentTopic, MessageAuthenticity, MessageId, TopicHash,
+        IdentTopic, MessageAcceptance, MessageAuthenticity, MessageId, PeerScoreParams,
+        PeerScoreThresholds, TopicHash, TopicScoreParams, ValidationMode,
     },
     identity::Keypair,
     swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
     NetworkBehaviour, PeerId,
 };
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
+    basic::{ShardId, H256},
     collections::{HashMap, HashSet},
     digest::Digestible,
     error::{anyhow, ensure, Result},
@@ -32,25 +33,58 @@ const CHECK_EXPLICIT_PEERS_TICKS: u64 = 2;
 const PUB_MAX_RETRIES: usize = 10;
 const PUB_INIT_RETRY_DELAY: Duration = Duration::from_secs(1);
 const PUB_MAX_RETRY_DELAY: Duration = Duration::from_secs(16);
+const SEEN_CACHE_SIZE: usize = 65_536;
 
-static TOPIC_MAP: Lazy<HashMap<TopicHash, PubSubTopic>> = Lazy::new(|| {
-    let mut map = HashMap::with_capacity(2);
-    for &topic in &[PubSubTopic::TxProposal, PubSubTopic::BlockProposal] {
-        map.insert(topic.into_topic_hash(), topic);
+#[derive(Debug, Clone, Deserialize)]
+pub struct PubSubConfig {
+    /// Whether to reject the messages that are unsigned, or whose signature does not match the
+    /// origin peer. Otherwise, the signatures are only checked when present.
+    #[serde(default = "default_strict")]
+    pub strict: bool,
+
+    /// Whether to score the peers per topic. The peers relaying the invalid messages are
+    /// graylisted once their scores drop below the threshold.
+    #[serde(default)]
+    pub peer_score: bool,
+}
+
+impl Default for PubSubConfig {
+    fn default() -> Self {
+        Self {
+            strict: default_strict(),
+            peer_score: false,
+        }
     }
-    map
-});
+}
+
+fn default_strict() -> bool {
+    true
+}
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub enum PubSubTopic {
+    /// The tx proposals of the full shard, i.e., without sharding.
     TxProposal,
+    /// The tx proposals of a single shard.
+    ShardTxProposal(ShardId),
     BlockProposal,
 }
 
 impl PubSubTopic {
+    pub fn tx_proposal(shard_id: ShardId) -> Self {
+        if shard_id.is_full_shard() {
+            PubSubTopic::TxProposal
+        } else {
+            PubSubTopic::ShardTxProposal(shard_id)
+        }
+    }
+
     pub fn into_topic(self) -> IdentTopic {
         match self {
             PubSubTopic::TxProposal => IdentTopic::new("tx_proposal".to_string()),
+            PubSubTopic::ShardTxProposal(shard_id) => {
+                IdentTopic::new(format!("tx_proposal/{}/{}", shard_id.id, shard_id.total))
+            }
             PubSubTopic::BlockProposal => IdentTopic::new("block_proposal".to_string()),
         }
     }
@@ -58,6 +92,78 @@ impl PubSubTopic {
     pub fn into_topic_hash(self) -> TopicHash {
         self.into_topic().hash()
     }
+
+    // A forged block proposal costs the receivers far more than a forged tx proposal, so its
+    // invalid deliveries weigh more. The mesh delivery rate is not scored, since the block
+    // proposals are sparse by design.
+    fn score_params(self) -> TopicScoreParams {
+        let (topic_weight, invalid_message_deliveries_weight) = match self {
+            PubSubTopic::TxProposal | PubSubTopic::ShardTxProposal(_) => (0.5, -100.0),
+            PubSubTopic::BlockProposal => (1.0, -1_000.0),
+        };
+        TopicScoreParams {
+            topic_weight,
+            mesh_message_deliveries_weight: 0.0,
+            mesh_failure_penalty_weight: 0.0,
+            invalid_message_deliveries_weight,
+            ..TopicScoreParams::default()
+        }
+    }
+}
+
+// The nodes of a local deployment share the same IP, so the IP colocation is not penalized.
+fn peer_score_params() -> PeerScoreParams {
+    PeerScoreParams {
+        ip_colocation_factor_weight: 0.0,
+        ..PeerScoreParams::default()
+    }
+}
+
+// Validate the received message before it is delivered and relayed to the other peers.
+// Return the id of the message, which is used to drop the duplicates.
+pub type MessageValidator<T> = Box<dyn Fn(&T) -> Result<H256> + Send + Sync>;
+
+// The ids of the recently received messages. The oldest ones are evicted once it is full.
+struct SeenCache {
+    ids: HashSet<H256>,
+    order: VecDeque<H256>,
+    cap: usize,
+}
+
+impl SeenCache {
+    fn new(cap: usize) -> Self {
+        Self {
+            ids: HashSet::with_capacity(cap),
+            order: VecDeque::with_capacity(cap),
+            cap,
+        }
+    }
+
+    // Return false if the id is seen before.
+    fn insert(&mut self, id: H256) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > self.cap {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        true
+    }
+}
+
+fn decode_and_validate<T: for<'de> Deserialize<'de>>(
+    data: &[u8],
+    validator: Option<&MessageValidator<T>>,
+) -> Result<(H256, T)> {
+    let input: T = binary_decode(data)?;
+    let id = match validator {
+        Some(validator) => validator(&input)?,
+        None => data.to_digest(),
+    };
+    Ok((id, input))
 }
 
 #[derive(Debug)]
@@ -84,7 +190,17 @@ where
     #[behaviour(ignore)]
     sub_topics: HashSet<PubSubTopic>,
     #[behaviour(ignore)]
+    topic_map: HashMap<TopicHash, PubSubTopic>,
+    #[behaviour(ignore)]
     retry_messages: DelayQueue<(PubSubTopic, Vec<u8>, usize, Duration)>,
+    #[behaviour(ignore)]
+    seen: SeenCache,
+    #[behaviour(ignore)]
+    tx_proposal_validator: Option<MessageValidator<TxProposal>>,
+    #[behaviour(ignore)]
+    block_proposal_validator: Option<MessageValidator<BlockProposal>>,
+    #[behaviour(ignore)]
+    peer_score: bool,
 }
 
 impl<TxProposal, BlockProposal> PubSub<TxProposal, BlockProposal>
@@ -94,11 +210,17 @@ where
 {
     pub fn new(
         keypair: Keypair,
+        cfg: &PubSubConfig,
         sub_topics: &[PubSubTopic],
         relay_topics: &[PubSubTopic],
     ) -> Result<Self> {
         let peer_id = PeerId::from(keypair.public());
-        let cfg = GossipsubConfigBuilder::default()
+        let validation_mode = if cfg.strict {
+            ValidationMode::Strict
+        } else {
+            ValidationMode::Permissive
+        };
+        let gossipsub_cfg = GossipsubConfigBuilder::default()
             .protocol_id_prefix("/slimchain/pubsub/1")
             .flood_publish(false)
             .duplicate_cache_time(DUPLICATE_CACHE_TTL)
@@ -109,25 +231,86 @@ where
             .heartbeat_interval(HEARTBEAT_INTERVAL)
             .check_explicit_peers_ticks(CHECK_EXPLICIT_PEERS_TICKS)
             .max_transmit_size(MAX_TRANSMIT_SIZE)
+            .validate_messages()
+            .validation_mode(validation_mode)
             .build()
             .map_err(|e| anyhow!("Failed to create gossipsub config. Error: {}", e))?;
 
-        let mut gossipsub = Gossipsub::new(MessageAuthenticity::Signed(keypair), cfg)
+        // The messages are signed with the node identity key.
+        let mut gossipsub = Gossipsub::new(MessageAuthenticity::Signed(keypair), gossipsub_cfg)
             .map_err(|e| anyhow!("Failed to create gossipsub. Error: {}", e))?;
-
-        for topic in sub_topics.iter().chain(relay_topics.iter()) {
+        if cfg.peer_score {
             gossipsub
-                .subscribe(&topic.into_topic())
-                .map_err(|e| anyhow!("Failed to subscribe. Error: {:?}", e))?;
+                .with_peer_score(peer_score_params(), PeerScoreThresholds::default())
+                .map_err(|e| anyhow!("Failed to enable peer scoring. Error: {}", e))?;
         }
 
-        Ok(Self {
+        let mut pubsub = Self {
             gossipsub,
             peer_id,
             pending_events: VecDeque::new(),
-            sub_topics: sub_topics.iter().copied().collect(),
+            sub_topics: HashSet::new(),
+            topic_map: HashMap::new(),
             retry_messages: DelayQueue::new(),
-        })
+            seen: SeenCache::new(SEEN_CACHE_SIZE),
+            tx_proposal_validator: None,
+            block_proposal_validator: None,
+            peer_score: cfg.peer_score,
+        };
+
+        for &topic in sub_topics {
+            pubsub.subscribe(topic)?;
+        }
+        for &topic in relay_topics {
+            pubsub.relay(topic)?;
+        }
+
+        Ok(pubsub)
+    }
+
+    // Subscribe to the topic. Its messages are delivered as the events.
+    pub fn subscribe(&mut self, topic: PubSubTopic) -> Result<()> {
+        self.relay(topic)?;
+        self.sub_topics.insert(topic);
+        Ok(())
+    }
+
+    // Join the topic to relay its messages, without delivering them.
+    pub fn relay(&mut self, topic: PubSubTopic) -> Result<()> {
+        let topic_hash = topic.into_topic_hash();
+        if self.topic_map.contains_key(&topic_hash) {
+            return Ok(());
+        }
+        self.gossipsub
+            .subscribe(&topic.into_topic())
+            .map_err(|e| anyhow!("Failed to subscribe. Error: {:?}", e))?;
+        if self.peer_score {
+            self.gossipsub
+                .set_topic_params(topic.into_topic(), topic.score_params())
+                .map_err(|e| anyhow!("Failed to set the topic score. Error: {}", e))?;
+        }
+        self.topic_map.insert(topic_hash, topic);
+        Ok(())
+    }
+
+    pub fn is_subscribed(&self, topic: PubSubTopic) -> bool {
+        self.sub_topics.contains(&topic)
+    }
+
+    pub fn with_tx_proposal_validator(
+        mut self,
+        validator: impl Fn(&TxProposal) -> Result<H256> + Send + Sync + 'static,
+    ) -> Self {
+        self.tx_proposal_validator = Some(Box::new(validator));
+        self
+    }
+
+    pub fn with_block_proposal_validator(
+        mut self,
+        validator: impl Fn(&BlockProposal) -> Result<H256> + Send + Sync + 'static,
+    ) -> Self {
+        self.block_proposal_validator = Some(Box::new(validator));
+        self
     }
 
     fn publish_message(
@@ -202,7 +385,7 @@ where
                 peer_id,
                 topic_hashes
                     .iter()
-                    .map(|hash| TOPIC_MAP.get(&hash))
+                    .map(|hash| self.topic_map.get(hash))
                     .collect::<Vec<_>>()
             );
         }
@@ -215,6 +398,14 @@ where
     BlockProposal: Serialize + Send + 'static,
 {
     pub fn publish_tx_proposal(&mut self, input: &TxProposal) -> Result<()> {
+        self.publish_shard_tx_proposal(input, ShardId::default())
+    }
+
+    pub fn publish_shard_tx_proposal(
+        &mut self,
+        input: &TxProposal,
+        shard_id: ShardId,
+    ) -> Result<()> {
         let data = binary_encode(input)?;
         ensure!(
             data.len() < MAX_MESSAGE_SIZE,
@@ -222,7 +413,7 @@ where
             data.len()
         );
         self.publish_message(
-            PubSubTopic::TxProposal,
+            PubSubTopic::tx_proposal(shard_id),
             data,
             PUB_MAX_RETRIES,
             PUB_INIT_RETRY_DELAY,
@@ -247,6 +438,44 @@ where
     }
 }
 
+impl<TxProposal, BlockProposal> PubSub<TxProposal, BlockProposal>
+where
+    TxProposal: for<'de> Deserialize<'de> + Send + 'static,
+    BlockProposal: for<'de> Deserialize<'de> + Send + 'static,
+{
+    // The messages of the relay topics are validated as well, so that the invalid ones are not
+    // relayed. But only those of the subscribed topics are delivered.
+    fn handle_message(&mut self, topic: PubSubTopic, data: &[u8]) -> MessageAcceptance {
+        let res = match topic {
+            PubSubTopic::TxProposal | PubSubTopic::ShardTxProposal(_) => {
+                decode_and_validate(data, self.tx_proposal_validator.as_ref())
+                    .map(|(id, input)| (id, PubSubEvent::TxProposal(input)))
+            }
+            PubSubTopic::BlockProposal => {
+                decode_and_validate(data, self.block_proposal_validator.as_ref())
+                    .map(|(id, input)| (id, PubSubEvent::BlockProposal(input)))
+            }
+        };
+
+        match res {
+            Ok((id, event)) => {
+                if !self.seen.insert(id) {
+                    trace!(?topic, %id, "PubSub: Duplicate message.");
+                    return MessageAcceptance::Ignore;
+                }
+                if self.sub_topics.contains(&topic) {
+                    self.pending_events.push_back(event);
+                }
+                MessageAcceptance::Accept
+            }
+            Err(e) => {
+                warn!(?topic, %e, "PubSub: Invalid message.");
+                MessageAcceptance::Reject
+            }
+        }
+    }
+}
+
 impl<TxProposal, BlockProposal> NetworkBehaviourEventProcess<GossipsubEvent>
     for PubSub<TxProposal, BlockProposal>
 where
@@ -255,41 +484,110 @@ where
 {
     fn inject_event(&mut self, event: GossipsubEvent) {
         if let GossipsubEvent::Message {
+            propagation_source,
+            message_id,
             message:
                 GossipsubMessage {
                     data,
                     topic: topic_hash,
                     ..
                 },
-            ..
         } = event
         {
-            let topic = match TOPIC_MAP.get(&topic_hash) {
-                Some(topic) => topic,
+            let acceptance = match self.topic_map.get(&topic_hash).copied() {
+                Some(topic) => self.handle_message(topic, &data),
                 None => {
                     warn!(?topic_hash, "PubSub: Unknown topic.");
-                    return;
+                    MessageAcceptance::Reject
                 }
             };
 
-            if !self.sub_topics.contains(topic) {
-                return;
+            if let Err(e) = self.gossipsub.report_message_validation_result(
+                &message_id,
+                &propagation_source,
+                acceptance,
+            ) {
+                warn!(?e, "PubSub: Failed to report the validation result.");
             }
+        }
+    }
+}
 
-            match topic {
-                PubSubTopic::TxProposal => {
-                    let input =
-                        binary_decode(data.as_slice()).expect("PubSub: Failed to decode message.");
-                    self.pending_events
-                        .push_back(PubSubEvent::TxProposal(input));
-                }
-                PubSubTopic::BlockProposal => {
-                    let input =
-                        binary_decode(data.as_slice()).expect("PubSub: Failed to decode message.");
-                    self.pending_events
-                        .push_back(PubSubEvent::BlockProposal(input));
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_proposal_topic() {
+        assert_eq!(
+            PubSubTopic::TxProposal,
+            PubSubTopic::tx_proposal(ShardId::default())
+        );
+        assert_eq!(
+            PubSubTopic::ShardTxProposal(ShardId::new(1, 2)),
+            PubSubTopic::tx_proposal(ShardId::new(1, 2))
+        );
+        let hashes: HashSet<_> = [
+            PubSubTopic::TxProposal,
+            PubSubTopic::tx_proposal(ShardId::new(0, 2)),
+            PubSubTopic::tx_proposal(ShardId::new(1, 2)),
+            PubSubTopic::tx_proposal(ShardId::new(1, 3)),
+            PubSubTopic::BlockProposal,
+        ]
+        .iter()
+        .map(|topic| topic.into_topic_hash())
+        .collect();
+        assert_eq!(5, hashes.len());
+    }
+
+    #[test]
+    fn test_score_params() {
+        for topic in [
+            PubSubTopic::TxProposal,
+            PubSubTopic::ShardTxProposal(ShardId::new(1, 2)),
+            PubSubTopic::BlockProposal,
+        ] {
+            topic.score_params().validate().unwrap();
         }
+        assert!(
+            PubSubTopic::BlockProposal
+                .score_params()
+                .invalid_message_deliveries_weight
+                < PubSubTopic::TxProposal
+                    .score_params()
+                    .invalid_message_deliveries_weight
+        );
+        peer_score_params().validate().unwrap();
+    }
+
+    #[test]
+    fn test_seen_cache() {
+        let mut cache = SeenCache::new(2);
+        let id = |x: u8| H256::repeat_byte(x);
+        assert!(cache.insert(id(1)));
+        assert!(!cache.insert(id(1)));
+        assert!(cache.insert(id(2)));
+        assert!(cache.insert(id(3)));
+        assert!(!cache.insert(id(3)));
+        assert!(cache.insert(id(1)));
+    }
+
+    #[test]
+    fn test_decode_and_validate() {
+        let data = binary_encode(&42u64).unwrap();
+        let (id, input) = decode_and_validate::<u64>(&data, None).unwrap();
+        assert_eq!(data.to_digest(), id);
+        assert_eq!(42, input);
+
+        let validator: MessageValidator<u64> = Box::new(|input| {
+            ensure!(*input < 10, "Too large.");
+            Ok(H256::repeat_byte(*input as u8))
+        });
+        assert!(decode_and_validate(&data, Some(&validator)).is_err());
+        let data = binary_encode(&1u64).unwrap();
+        let (id, input) = decode_and_validate(&data, Some(&validator)).unwrap();
+        assert_eq!(H256::repeat_byte(1), id);
+        assert_eq!(1, input);
+        assert!(decode_and_validate::<u64>(&[], None).is_err());
     }
 }
@@ -0,0 +1,78 @@
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use serde::Deserialize;
+use slimchain_common::error::{ensure, Result};
+use std::time::Duration;
+
+pub use libp2p::relay::Relay;
+
+// NAT traversal is limited to circuit relay v1, as the p2p stack is pinned to libp2p 0.39. AutoNAT,
+// circuit relay v2 and DCUtR hole punching need libp2p 0.43 or later, so a node behind NAT has to
+// be given a public relay in `listen_via` instead of discovering its reachability.
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelayConfig {
+    /// Whether to enable the circuit relay (PoW only). The node then relays the connections
+    /// between the other peers, and can dial or be dialed through the relays.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Relays to listen on for the relayed connections, e.g.,
+    /// /ip4/1.2.3.4/tcp/6000/p2p/<relay peer id>/p2p-circuit. Used by the nodes behind NAT.
+    #[serde(default = "Vec::new")]
+    pub listen_via: Vec<String>,
+
+    /// Whether to dial the destination peer not connected yet when relaying a connection.
+    #[serde(default)]
+    pub connect_to_dst: bool,
+
+    /// Time (in seconds) to keep an idle relayed connection open.
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_via: Vec::new(),
+            connect_to_dst: false,
+            idle_timeout: default_idle_timeout(),
+        }
+    }
+}
+
+fn default_idle_timeout() -> u64 {
+    10
+}
+
+impl RelayConfig {
+    pub fn to_libp2p_config(&self) -> libp2p::relay::RelayConfig {
+        libp2p::relay::RelayConfig {
+            connection_idle_timeout: Duration::from_secs(self.idle_timeout),
+            actively_connect_to_dst_nodes: self.connect_to_dst,
+        }
+    }
+
+    pub fn listen_addrs(&self) -> Result<Vec<Multiaddr>> {
+        let mut addrs = Vec::with_capacity(self.listen_via.len());
+        for addr in &self.listen_via {
+            let addr: Multiaddr = addr.parse()?;
+            ensure!(
+                addr.iter().any(|p| matches!(p, Protocol::P2pCircuit)),
+                "Relay address {} should end with /p2p-circuit.",
+                addr
+            );
+            addrs.push(addr);
+        }
+        Ok(addrs)
+    }
+}
+
+// The relay behaviour lives in `Discovery`, while its transport has to be set up with the swarm.
+// So the swarm hands it over to the behaviours built on `Discovery`.
+pub trait WithRelay {
+    fn set_relay(&mut self, relay: Relay);
+}
+
+#[cfg(test)]
+mod tests;
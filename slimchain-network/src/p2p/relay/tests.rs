@@ -0,0 +1,38 @@
+use super::*;
+use slimchain_utils::{config::Config, toml};
+
+#[test]
+fn test_deserialize_relay_config() {
+    let input = toml::toml! {
+        [relay]
+    };
+    let cfg: RelayConfig = Config::from_toml(input).get("relay").unwrap();
+    assert!(!cfg.enabled);
+    assert!(cfg.listen_addrs().unwrap().is_empty());
+    assert_eq!(
+        Duration::from_secs(10),
+        cfg.to_libp2p_config().connection_idle_timeout
+    );
+
+    let input = toml::toml! {
+        [relay]
+        enabled = true
+        listen_via = ["/ip4/127.0.0.1/tcp/6000/p2p/12D3KooWLZC1nrJ2LMEFbGCLrxg9wLRbWDqXYzcZK4hCvTq8NrU9/p2p-circuit"]
+        connect_to_dst = true
+        idle_timeout = 60
+    };
+    let cfg: RelayConfig = Config::from_toml(input).get("relay").unwrap();
+    assert!(cfg.enabled);
+    assert_eq!(1, cfg.listen_addrs().unwrap().len());
+    let libp2p_cfg = cfg.to_libp2p_config();
+    assert!(libp2p_cfg.actively_connect_to_dst_nodes);
+    assert_eq!(Duration::from_secs(60), libp2p_cfg.connection_idle_timeout);
+
+    let input = toml::toml! {
+        [relay]
+        enabled = true
+        listen_via = ["/ip4/127.0.0.1/tcp/6000"]
+    };
+    let cfg: RelayConfig = Config::from_toml(input).get("relay").unwrap();
+    assert!(cfg.listen_addrs().is_err());
+}
@@ -84,6 +84,15 @@ where
     create_request_response(protocol_name, ProtocolSupport::Outbound)
 }
 
+// Both serve and send the requests, e.g., between the peers of the same kind.
+pub fn create_request_response_full<Req, Resp>(protocol_name: &str) -> RpcInstant<Req, Resp>
+where
+    Req: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    Resp: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    create_request_response(protocol_name, ProtocolSupport::Full)
+}
+
 pub fn handle_request_response_server_event<Req, Resp>(
     event: RpcRequestResponseEvent<Req, Resp>,
 ) -> Option<(Req, RpcResponseChannel<Resp>)>
@@ -0,0 +1,346 @@
+use crate::p2p::rpc::{
+    create_request_response_full, RpcInstant, RpcRequestId, RpcRequestResponseEvent,
+};
+use futures::prelude::*;
+use futures_timer::Delay;
+use libp2p::{
+    request_response::RequestResponseMessage,
+    swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
+    NetworkBehaviour, PeerId,
+};
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockTrait, block_proposal::BlockProposal, db::DBPtr, latest::LatestBlockHeaderPtr,
+};
+use slimchain_common::{basic::BlockHeight, tx::TxTrait};
+use std::{
+    cmp,
+    collections::{BTreeMap, VecDeque},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+pub const SYNC_PROTOCOL_NAME: &str = "/sync/1";
+
+// Max number of block proposals in a single response.
+pub const MAX_SYNC_BATCH: u64 = 16;
+// Number of the recent block proposals kept to serve the peers.
+const RECENT_PROPOSALS_CAPACITY: usize = 1024;
+// The import is considered stalled if the height does not move within this interval.
+const SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub from: BlockHeight,
+    pub count: u64,
+}
+
+pub type SyncResponse<Block, Tx> = Vec<BlockProposal<Block, Tx>>;
+
+// The block proposals recently imported or proposed by the node.
+pub struct RecentBlockProposals<Block: BlockTrait, Tx: TxTrait> {
+    inner: Arc<Mutex<BTreeMap<BlockHeight, BlockProposal<Block, Tx>>>>,
+    capacity: usize,
+}
+
+impl<Block: BlockTrait, Tx: TxTrait> Clone for RecentBlockProposals<Block, Tx> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<Block: BlockTrait, Tx: TxTrait> Default for RecentBlockProposals<Block, Tx> {
+    fn default() -> Self {
+        Self::new(RECENT_PROPOSALS_CAPACITY)
+    }
+}
+
+impl<Block: BlockTrait, Tx: TxTrait> RecentBlockProposals<Block, Tx> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(BTreeMap::new())),
+            capacity,
+        }
+    }
+
+    pub fn insert(&self, proposal: BlockProposal<Block, Tx>) {
+        let mut inner = self.inner.lock().expect("Failed to lock recent proposals.");
+        inner.insert(proposal.get_block_height(), proposal);
+        while inner.len() > self.capacity {
+            let oldest = *inner.keys().next().expect("Empty recent proposals.");
+            inner.remove(&oldest);
+        }
+    }
+
+    // The consecutive proposals starting from `from`. Stop at the first missing one.
+    pub fn get_range(&self, from: BlockHeight, count: u64) -> Vec<BlockProposal<Block, Tx>> {
+        let inner = self.inner.lock().expect("Failed to lock recent proposals.");
+        let mut out = Vec::new();
+        let mut height = from;
+        while (out.len() as u64) < count {
+            match inner.get(&height) {
+                Some(proposal) => out.push(proposal.clone()),
+                None => break,
+            }
+            height = height.next_height();
+        }
+        out
+    }
+}
+
+#[derive(Debug)]
+pub enum BlockSyncEvent<Block: BlockTrait, Tx: TxTrait> {
+    BlockProposals(Vec<BlockProposal<Block, Tx>>),
+}
+
+// Fetch the missing block proposals from the peers by the height range, and serve those of
+// this node in turn.
+#[derive(NetworkBehaviour)]
+#[behaviour(poll_method = "poll_inner", out_event = "BlockSyncEvent<Block, Tx>")]
+pub struct BlockSync<Block, Tx>
+where
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    request_response: RpcInstant<SyncRequest, SyncResponse<Block, Tx>>,
+    #[behaviour(ignore)]
+    recent: RecentBlockProposals<Block, Tx>,
+    #[behaviour(ignore)]
+    db: Option<DBPtr>,
+    #[behaviour(ignore)]
+    latest_block_header: Option<LatestBlockHeaderPtr>,
+    #[behaviour(ignore)]
+    max_seen_height: BlockHeight,
+    #[behaviour(ignore)]
+    last_checked_height: BlockHeight,
+    #[behaviour(ignore)]
+    next_check: Delay,
+    #[behaviour(ignore)]
+    in_flight: Option<RpcRequestId>,
+    #[behaviour(ignore)]
+    pending_events: VecDeque<BlockSyncEvent<Block, Tx>>,
+}
+
+impl<Block, Tx> BlockSync<Block, Tx>
+where
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    pub fn new(recent: RecentBlockProposals<Block, Tx>) -> Self {
+        Self {
+            request_response: create_request_response_full(SYNC_PROTOCOL_NAME),
+            recent,
+            db: None,
+            latest_block_header: None,
+            max_seen_height: BlockHeight::default(),
+            last_checked_height: BlockHeight::default(),
+            next_check: Delay::new(SYNC_CHECK_INTERVAL),
+            in_flight: None,
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    // Serve the proposals missing in the recent ones from the database. It requires the full
+    // state, i.e., only works on the storage nodes of the full shard.
+    pub fn with_db(mut self, db: DBPtr) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    // Track the import progress so that the missing proposals are requested once it stalls.
+    pub fn with_latest_block_header(mut self, latest_block_header: LatestBlockHeaderPtr) -> Self {
+        self.last_checked_height = latest_block_header.get_height();
+        self.max_seen_height = self.last_checked_height;
+        self.latest_block_header = Some(latest_block_header);
+        self
+    }
+
+    // Record the height of a block proposal received from the gossip.
+    pub fn observe_height(&mut self, height: BlockHeight) {
+        self.max_seen_height = cmp::max(self.max_seen_height, height);
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    pub fn request_blocks(&mut self, peer: &PeerId, from: BlockHeight, count: u64) {
+        debug!(%peer, %from, count, "BlockSync: request block proposals.");
+        let count = cmp::min(count, MAX_SYNC_BATCH);
+        let request_id = self
+            .request_response
+            .send_request(peer, SyncRequest { from, count });
+        self.in_flight = Some(request_id);
+    }
+
+    // Return the request to send if the import stalls behind the gossip. The caller should pick
+    // the peer and call `request_blocks`.
+    pub fn poll_stalled(&mut self, cx: &mut Context<'_>) -> Poll<SyncRequest> {
+        let mut stalled = None;
+        while Pin::new(&mut self.next_check).poll(cx).is_ready() {
+            self.next_check = Delay::new(SYNC_CHECK_INTERVAL);
+
+            let latest_height = match self.latest_block_header.as_ref() {
+                Some(header) => header.get_height(),
+                None => continue,
+            };
+            let last_checked = self.last_checked_height;
+            self.last_checked_height = latest_height;
+            if self.is_syncing()
+                || latest_height != last_checked
+                || latest_height.next_height() >= self.max_seen_height
+            {
+                continue;
+            }
+
+            let from = latest_height.next_height();
+            stalled = Some(SyncRequest {
+                from,
+                count: self.max_seen_height.0 - from.0,
+            });
+        }
+
+        match stalled {
+            Some(req) => Poll::Ready(req),
+            None => Poll::Pending,
+        }
+    }
+
+    fn load_block_proposals(&self, req: SyncRequest) -> SyncResponse<Block, Tx> {
+        let count = cmp::min(req.count, MAX_SYNC_BATCH);
+        let mut proposals = self.recent.get_range(req.from, count);
+        if proposals.is_empty() {
+            if let Some(db) = self.db.as_ref() {
+                let mut height = req.from;
+                while (proposals.len() as u64) < count {
+                    match BlockProposal::from_db(db, height) {
+                        Ok(proposal) => proposals.push(proposal),
+                        Err(_) => break,
+                    }
+                    height = height.next_height();
+                }
+            }
+        }
+        proposals
+    }
+
+    fn poll_inner<T>(
+        &mut self,
+        _: &mut Context,
+        _: &mut impl PollParameters,
+    ) -> Poll<NetworkBehaviourAction<T, BlockSyncEvent<Block, Tx>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<Block, Tx>
+    NetworkBehaviourEventProcess<RpcRequestResponseEvent<SyncRequest, SyncResponse<Block, Tx>>>
+    for BlockSync<Block, Tx>
+where
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    fn inject_event(
+        &mut self,
+        event: RpcRequestResponseEvent<SyncRequest, SyncResponse<Block, Tx>>,
+    ) {
+        match event {
+            RpcRequestResponseEvent::Message {
+                peer,
+                message:
+                    RequestResponseMessage::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let proposals = self.load_block_proposals(request);
+                trace!(%peer, ?request, num = proposals.len(), "BlockSync: serve block proposals.");
+                if self
+                    .request_response
+                    .send_response(channel, proposals)
+                    .is_err()
+                {
+                    warn!(%peer, "BlockSync: failed to send the response.");
+                }
+            }
+            RpcRequestResponseEvent::Message {
+                peer,
+                message:
+                    RequestResponseMessage::Response {
+                        request_id,
+                        response,
+                    },
+            } => {
+                if self.in_flight != Some(request_id) {
+                    return;
+                }
+                self.in_flight = None;
+                debug!(%peer, num = response.len(), "BlockSync: recv block proposals.");
+                if let Some(last) = response.last() {
+                    self.observe_height(last.get_block_height());
+                    self.pending_events
+                        .push_back(BlockSyncEvent::BlockProposals(response));
+                }
+            }
+            RpcRequestResponseEvent::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                if self.in_flight == Some(request_id) {
+                    self.in_flight = None;
+                }
+                warn!(%peer, ?error, "BlockSync: failed to fetch block proposals.");
+            }
+            RpcRequestResponseEvent::InboundFailure { peer, error, .. } => {
+                warn!(%peer, ?error, "BlockSync: failed to serve block proposals.");
+            }
+            RpcRequestResponseEvent::ResponseSent { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_chain::{block_proposal::BlockProposalTrie, consensus::raft::Block};
+    use slimchain_common::tx::RawTx;
+
+    fn proposal(height: u64) -> BlockProposal<Block, RawTx> {
+        let mut block = Block::genesis_block();
+        block.block_header_mut().height = BlockHeight(height);
+        BlockProposal::new(
+            block,
+            Vec::new(),
+            BlockProposalTrie::Diff(Default::default()),
+        )
+    }
+
+    #[test]
+    fn test_recent_block_proposals() {
+        let recent = RecentBlockProposals::<Block, RawTx>::new(3);
+        for height in 1..=5 {
+            recent.insert(proposal(height));
+        }
+        assert!(recent.get_range(BlockHeight(2), 2).is_empty());
+        let heights = |proposals: Vec<BlockProposal<Block, RawTx>>| {
+            proposals
+                .iter()
+                .map(|p| p.get_block_height().0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![3, 4], heights(recent.get_range(BlockHeight(3), 2)));
+        assert_eq!(vec![4, 5], heights(recent.get_range(BlockHeight(4), 10)));
+
+        recent.insert(proposal(7));
+        assert_eq!(vec![5], heights(recent.get_range(BlockHeight(5), 10)));
+    }
+}
@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use slimchain_common::{
+    basic::{Address, ShardId, H256},
+    collections::HashSet,
+    tx_req::{SignedTxRequest, TxRequest},
+};
+use slimchain_utils::contract::contract_address;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+// Max number of storage nodes tried for a single tx.
+pub const MAX_ROUTE_ATTEMPTS: usize = 3;
+
+static TX_ROUTE_STATS: Lazy<TxRouteStats> = Lazy::new(TxRouteStats::default);
+
+#[derive(Debug, Default)]
+struct TxRouteStats {
+    routed: AtomicU64,
+    retried: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxRouteStatsSnapshot {
+    /// Number of txs accepted by a storage node.
+    pub routed: u64,
+    /// Number of attempts sent to another storage node after a failure.
+    pub retried: u64,
+    /// Number of txs dropped after all attempts failed.
+    pub failed: u64,
+}
+
+pub fn record_tx_routed() {
+    TX_ROUTE_STATS.routed.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_tx_retried() {
+    TX_ROUTE_STATS.retried.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_tx_route_failed() {
+    TX_ROUTE_STATS.failed.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn tx_route_stats() -> TxRouteStatsSnapshot {
+    TxRouteStatsSnapshot {
+        routed: TX_ROUTE_STATS.routed.load(Ordering::Relaxed),
+        retried: TX_ROUTE_STATS.retried.load(Ordering::Relaxed),
+        failed: TX_ROUTE_STATS.failed.load(Ordering::Relaxed),
+    }
+}
+
+// The ids of the tx proposals received by the leader but not committed or discarded yet. A tx
+// forwarded to several storage nodes comes back once from each of them, and only the first copy
+// is proposed. Once the tx is settled, a retry of it is accepted again.
+#[derive(Debug, Default)]
+pub struct InFlightTxIds {
+    ids: Mutex<HashSet<H256>>,
+}
+
+impl InFlightTxIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Return false if the tx is in flight already.
+    pub fn insert(&self, tx_id: H256) -> bool {
+        self.ids
+            .lock()
+            .expect("Failed to lock in-flight tx ids.")
+            .insert(tx_id)
+    }
+
+    pub fn remove(&self, tx_ids: impl IntoIterator<Item = H256>) {
+        let mut ids = self.ids.lock().expect("Failed to lock in-flight tx ids.");
+        for tx_id in tx_ids {
+            ids.remove(&tx_id);
+        }
+    }
+}
+
+// The contract touched by the tx. A new contract lives at the address derived from the caller
+// and the nonce.
+pub fn tx_contract_address(req: &SignedTxRequest) -> Address {
+    match &req.input {
+        TxRequest::Create { nonce, .. } => contract_address(req.caller_address(), *nonce),
+        TxRequest::Call { address, .. } => *address,
+    }
+}
+
+// Resolve the shard of the storage node which should execute the tx.
+//
+// A sharded request is corrected to the shard containing the contract, in case the sender
+// computes it wrongly. A request without the shard is sent to the full shard if it is served.
+// Otherwise, it goes to the first served shard containing the contract.
+pub fn resolve_tx_shard(
+    req: &SignedTxRequest,
+    shard_id: ShardId,
+    served_shard_totals: impl Iterator<Item = u64>,
+) -> ShardId {
+    let address = tx_contract_address(req);
+    if !shard_id.is_full_shard() {
+        return ShardId::find_remote_shard(address, std::iter::once(shard_id.total))
+            .next()
+            .unwrap_or(shard_id);
+    }
+
+    let mut totals: Vec<u64> = served_shard_totals.filter(|&total| total > 0).collect();
+    if totals.is_empty() || totals.contains(&1) {
+        return shard_id;
+    }
+    totals.sort_unstable();
+    totals.dedup();
+    ShardId::find_remote_shard(address, totals.into_iter())
+        .next()
+        .unwrap_or(shard_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::{basic::H160, ed25519::Keypair};
+
+    #[test]
+    fn test_in_flight_tx_ids() {
+        let ids = InFlightTxIds::new();
+        assert!(ids.insert(H256::from_low_u64_be(1)));
+        assert!(!ids.insert(H256::from_low_u64_be(1)));
+        assert!(ids.insert(H256::from_low_u64_be(2)));
+        // A settled tx can be proposed again.
+        ids.remove(vec![H256::from_low_u64_be(1)]);
+        assert!(ids.insert(H256::from_low_u64_be(1)));
+        assert!(!ids.insert(H256::from_low_u64_be(2)));
+    }
+
+    #[test]
+    fn test_resolve_tx_shard() {
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let call = |address: Address| {
+            TxRequest::Call {
+                nonce: 0.into(),
+                address,
+                data: Vec::new(),
+                chain_id: 0,
+                expiry: None,
+            }
+            .sign(&keypair)
+        };
+        let odd = call(H160::repeat_byte(0xff).into());
+        let even = call(H160::repeat_byte(0x00).into());
+
+        let full = ShardId::default();
+        assert_eq!(full, resolve_tx_shard(&odd, full, std::iter::empty()));
+        assert_eq!(full, resolve_tx_shard(&odd, full, vec![1, 2].into_iter()));
+        assert_eq!(
+            ShardId::new(1, 2),
+            resolve_tx_shard(&odd, full, vec![2, 2].into_iter())
+        );
+        assert_eq!(
+            ShardId::new(0, 2),
+            resolve_tx_shard(&even, full, vec![2].into_iter())
+        );
+        assert_eq!(
+            ShardId::new(1, 2),
+            resolve_tx_shard(&odd, ShardId::new(0, 2), std::iter::empty())
+        );
+
+        let create = TxRequest::Create {
+            nonce: 1.into(),
+            code: Default::default(),
+            chain_id: 0,
+            expiry: None,
+        }
+        .sign(&keypair);
+        let address = contract_address(create.caller_address(), 1.into());
+        let shard_id = resolve_tx_shard(&create, full, vec![4].into_iter());
+        assert_eq!(4, shard_id.total);
+        assert!(shard_id.contains(address));
+    }
+
+    #[test]
+    fn test_tx_route_stats() {
+        let before = tx_route_stats();
+        record_tx_routed();
+        record_tx_retried();
+        record_tx_route_failed();
+        let after = tx_route_stats();
+        assert!(after.routed > before.routed);
+        assert!(after.retried > before.retried);
+        assert!(after.failed > before.failed);
+    }
+}
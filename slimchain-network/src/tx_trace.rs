@@ -0,0 +1,132 @@
+use crate::http::client_rpc::TxHttpRequest;
+use once_cell::sync::OnceCell;
+use slimchain_common::error::{anyhow, ensure, Context as _, Result};
+use slimchain_utils::serde::{binary_decode_raw, binary_encode_raw};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TxTraceRecord {
+    // The time since the UNIX epoch when the client node accepted the request.
+    pub time: Duration,
+    pub req: TxHttpRequest,
+}
+
+// The tx requests submitted to a client node, so that the same workload can be replayed
+// against another deployment. Each record is stored as `time_in_us (u64 LE) || len (u32 LE) ||
+// bytes`. The requests submitted together share the same time.
+pub struct TxTraceWriter(Mutex<BufWriter<File>>);
+
+impl TxTraceWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        info!("Record the tx requests to {}", path.display());
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}.", path.display()))?;
+        Ok(Self(Mutex::new(BufWriter::new(file))))
+    }
+
+    pub fn record(&self, reqs: &[TxHttpRequest]) -> Result<()> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+        let mut buf = Vec::new();
+        for req in reqs {
+            let bytes = binary_encode_raw(req)?;
+            buf.extend_from_slice(&time.to_le_bytes());
+            buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&bytes);
+        }
+        let mut file = self.0.lock().expect("Failed to lock TxTraceWriter.");
+        file.write_all(&buf)?;
+        // Flush it per batch, so that the trace survives the node being killed.
+        file.flush()?;
+        Ok(())
+    }
+}
+
+pub fn read_tx_trace(path: &Path) -> Result<Vec<TxTraceRecord>> {
+    let buf = fs::read(path).with_context(|| format!("Failed to open {}.", path.display()))?;
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        ensure!(pos + 12 <= buf.len(), "Corrupted tx trace.");
+        let mut time_bytes = [0u8; 8];
+        time_bytes.copy_from_slice(&buf[pos..pos + 8]);
+        let mut len_bytes = [0u8; 4];
+        len_bytes.copy_from_slice(&buf[pos + 8..pos + 12]);
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        pos += 12;
+        ensure!(pos + len <= buf.len(), "Corrupted tx trace.");
+        records.push(TxTraceRecord {
+            time: Duration::from_micros(u64::from_le_bytes(time_bytes)),
+            req: binary_decode_raw(&buf[pos..pos + len])?,
+        });
+        pos += len;
+    }
+    Ok(records)
+}
+
+static GLOBAL_TX_TRACE: OnceCell<TxTraceWriter> = OnceCell::new();
+
+pub fn install_tx_trace(writer: TxTraceWriter) -> Result<()> {
+    GLOBAL_TX_TRACE
+        .set(writer)
+        .map_err(|_| anyhow!("Failed to set TxTraceWriter."))
+}
+
+// Called with the tx requests accepted by the client node. A failure only loses the trace, so
+// the requests are still processed.
+pub fn record_tx_trace(reqs: &[TxHttpRequest]) {
+    if let Some(writer) = GLOBAL_TX_TRACE.get() {
+        if let Err(e) = writer.record(reqs) {
+            warn!("Failed to record the tx trace. Error: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slimchain_common::{basic::ShardId, ed25519::Keypair, tx_req::TxRequest};
+
+    #[test]
+    fn test_tx_trace() {
+        let path =
+            std::env::temp_dir().join(format!("slimchain-test-trace-{}.bin", std::process::id()));
+
+        let mut rng = rand::thread_rng();
+        let keypair = Keypair::generate(&mut rng);
+        let reqs: Vec<TxHttpRequest> = (0..3)
+            .map(|i| TxHttpRequest {
+                req: TxRequest::Call {
+                    nonce: (i as u64).into(),
+                    address: Default::default(),
+                    data: vec![i as u8; i],
+                    chain_id: 0,
+                    expiry: None,
+                }
+                .sign(&keypair),
+                shard_id: ShardId::new(i as u64 % 2, 2),
+            })
+            .collect();
+
+        {
+            let writer = TxTraceWriter::create(&path).unwrap();
+            writer.record(&reqs[..2]).unwrap();
+            writer.record(&reqs[2..]).unwrap();
+        }
+
+        let records = read_tx_trace(&path).unwrap();
+        assert_eq!(
+            reqs,
+            records.iter().map(|r| r.req.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(records[0].time, records[1].time);
+        assert!(records[1].time <= records[2].time);
+
+        std::fs::remove_file(&path).ok();
+    }
+}
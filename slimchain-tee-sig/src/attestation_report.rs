@@ -5,7 +5,8 @@ use serde_json::Value as JsonValue;
 use sgx_types::sgx_quote_t;
 use slimchain_common::{
     basic::H256,
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
+    encoding::{write_len, write_var_bytes, CanonicalDecode, CanonicalEncode, Reader},
     error::{anyhow, bail, ensure, Context as _, Error, Result},
 };
 use webpki::{EndEntityCert, TlsClientTrustAnchors, TrustAnchor};
@@ -41,18 +42,40 @@ pub struct AttestationReport {
 
 impl Digestible for AttestationReport {
     fn to_digest(&self) -> H256 {
-        let mut cert_hash_state = default_blake2().to_state();
+        let mut cert_hash_state = digest_state();
         for c in &self.cert {
             cert_hash_state.update(c.to_digest().as_bytes());
         }
-        let cert_hash = blake2b_hash_to_h256(cert_hash_state.finalize());
+        let cert_hash = cert_hash_state.finalize();
 
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.sig.to_digest().as_bytes());
         hash_state.update(cert_hash.as_bytes());
         hash_state.update(self.report.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
+    }
+}
+
+impl CanonicalEncode for AttestationReport {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        write_var_bytes(out, &self.sig);
+        write_len(out, self.cert.len());
+        for c in &self.cert {
+            write_var_bytes(out, c);
+        }
+        write_var_bytes(out, &self.report);
+    }
+}
+
+impl CanonicalDecode for AttestationReport {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        let sig = reader.read_var_bytes()?.to_vec();
+        let len = reader.read_len()?;
+        let cert = (0..len)
+            .map(|_| reader.read_var_bytes().map(|c| c.to_vec()))
+            .collect::<Result<_>>()?;
+        let report = reader.read_var_bytes()?.to_vec();
+        Ok(AttestationReport { sig, cert, report })
     }
 }
 
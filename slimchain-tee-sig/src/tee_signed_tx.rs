@@ -2,8 +2,9 @@ use crate::attestation_report::AttestationReport;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{Address, BlockHeight, H256},
-    digest::{blake2b_hash_to_h256, default_blake2, Digestible},
+    digest::{digest_state, Digestible},
     ed25519::PubSigPair,
+    encoding::{CanonicalDecode, CanonicalEncode, Reader},
     error::Result,
     rw_set::{TxReadSet, TxWriteData},
     tx::{RawTx, TxTrait},
@@ -19,12 +20,31 @@ pub struct TEESignedTx {
 
 impl Digestible for TEESignedTx {
     fn to_digest(&self) -> H256 {
-        let mut hash_state = default_blake2().to_state();
+        let mut hash_state = digest_state();
         hash_state.update(self.raw_tx.to_digest().as_bytes());
         hash_state.update(self.pk_sig.to_digest().as_bytes());
         hash_state.update(self.attest_report.to_digest().as_bytes());
-        let hash = hash_state.finalize();
-        blake2b_hash_to_h256(hash)
+        hash_state.finalize()
+    }
+}
+
+impl CanonicalEncode for TEESignedTx {
+    const ENCODING_VERSION: u8 = RawTx::ENCODING_VERSION;
+
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.raw_tx.encode_to(out);
+        self.pk_sig.encode_to(out);
+        self.attest_report.encode_to(out);
+    }
+}
+
+impl CanonicalDecode for TEESignedTx {
+    fn decode_from(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(TEESignedTx {
+            raw_tx: RawTx::decode_from(reader)?,
+            pk_sig: PubSigPair::decode_from(reader)?,
+            attest_report: AttestationReport::decode_from(reader)?,
+        })
     }
 }
 
@@ -41,6 +61,10 @@ impl TxTrait for TEESignedTx {
         self.raw_tx.tx_block_height()
     }
 
+    fn tx_block_timestamp(&self) -> u64 {
+        self.raw_tx.tx_block_timestamp()
+    }
+
     fn tx_state_root(&self) -> H256 {
         self.raw_tx.tx_state_root()
     }
@@ -54,10 +78,17 @@ impl TxTrait for TEESignedTx {
     }
 
     fn verify_sig(&self) -> Result<()> {
-        self.attest_report
-            .verify(&self.pk_sig.public().as_bytes()[..])?;
-
-        let hash = self.raw_tx.to_digest();
+        self.verify_sig_except_batch()?;
+        let hash = self.raw_tx.signing_digest();
         self.pk_sig.verify(hash)
     }
+
+    fn batch_sig(&self) -> Option<(H256, &PubSigPair)> {
+        Some((self.raw_tx.signing_digest(), &self.pk_sig))
+    }
+
+    fn verify_sig_except_batch(&self) -> Result<()> {
+        self.attest_report
+            .verify(&self.pk_sig.public().as_bytes()[..])
+    }
 }
@@ -0,0 +1,250 @@
+use slimchain_chain::{consensus::Consensus, role::Role};
+use slimchain_common::{
+    basic::ShardId,
+    error::{ensure, Context as _, Error, Result},
+};
+use slimchain_network::p2p::config::KeypairConfig;
+use slimchain_utils::config::Config;
+use std::{fmt::Write as _, net::TcpListener};
+
+// The layout of a test network and the settings shared by all of its nodes.
+#[derive(Debug, Clone)]
+pub struct TestNetConfig {
+    pub consensus: Consensus,
+    pub clients: usize,
+    // Only used in PoW.
+    pub miners: usize,
+    pub shards: u64,
+    pub replicas: usize,
+    pub state_len: usize,
+    pub max_txs: usize,
+    // In milliseconds. Shorter than a real deployment, so that the tests do not wait for long.
+    pub max_block_interval: u64,
+    pub pow_init_diff: u64,
+    pub engine_threads: usize,
+    // Appended to the config of every node, e.g., the `[prune]` or `[access_map]` sections.
+    pub extra_config: String,
+}
+
+impl TestNetConfig {
+    pub fn new(consensus: Consensus) -> Self {
+        Self {
+            consensus,
+            clients: 1,
+            miners: 1,
+            shards: 1,
+            replicas: 1,
+            state_len: 16,
+            max_txs: 256,
+            max_block_interval: 200,
+            pow_init_diff: 1_000,
+            engine_threads: 2,
+            extra_config: String::new(),
+        }
+    }
+
+    pub fn with_clients(mut self, clients: usize) -> Self {
+        self.clients = clients;
+        self
+    }
+
+    pub fn with_miners(mut self, miners: usize) -> Self {
+        self.miners = miners;
+        self
+    }
+
+    pub fn with_shards(mut self, shards: u64, replicas: usize) -> Self {
+        self.shards = shards;
+        self.replicas = replicas;
+        self
+    }
+
+    pub fn with_state_len(mut self, state_len: usize) -> Self {
+        self.state_len = state_len;
+        self
+    }
+
+    pub fn with_max_txs(mut self, max_txs: usize) -> Self {
+        self.max_txs = max_txs;
+        self
+    }
+
+    pub fn with_max_block_interval(mut self, max_block_interval: u64) -> Self {
+        self.max_block_interval = max_block_interval;
+        self
+    }
+
+    pub fn with_extra_config(mut self, extra_config: &str) -> Self {
+        self.extra_config = extra_config.to_string();
+        self
+    }
+
+    pub(crate) fn plan_nodes(&self) -> Result<Vec<TestNodeSpec>> {
+        ensure!(self.clients > 0, "At least one client node is required.");
+        ensure!(
+            self.shards > 0 && self.replicas > 0,
+            "At least one storage node is required."
+        );
+
+        let mut roles = vec![Role::Client; self.clients];
+        if self.consensus == Consensus::PoW {
+            ensure!(self.miners > 0, "At least one miner node is required.");
+            roles.extend(std::iter::repeat(Role::Miner).take(self.miners));
+        }
+        for shard in 0..self.shards {
+            roles.extend(
+                std::iter::repeat(Role::Storage(ShardId::new(shard, self.shards)))
+                    .take(self.replicas),
+            );
+        }
+
+        let mut nodes = Vec::with_capacity(roles.len());
+        let mut role_counts = Vec::new();
+        for (i, role) in roles.into_iter().enumerate() {
+            let idx = match role_counts.iter_mut().find(|(r, _)| *r == role) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    role_counts.push((role, 0));
+                    0
+                }
+            };
+            let name = match role {
+                Role::Storage(shard_id) => format!("storage-{}-{}", shard_id.id, idx),
+                _ => format!("{}-{}", role.to_string().to_lowercase(), idx),
+            };
+            nodes.push(TestNodeSpec {
+                name,
+                peer_id: i as u64 + 1,
+                role,
+                http_port: free_port()?,
+                p2p_port: free_port()?,
+                keypair: KeypairConfig::generate(),
+            });
+        }
+        Ok(nodes)
+    }
+
+    // Render the config of the node in the same format as `config.toml`.
+    pub(crate) fn node_config(
+        &self,
+        nodes: &[TestNodeSpec],
+        node: &TestNodeSpec,
+    ) -> Result<Config> {
+        let mut out = String::new();
+        writeln!(out, "[role]")?;
+        out.push_str(&role_config(node.role));
+        writeln!(out, "[chain]")?;
+        writeln!(out, "conflict_check = \"ssi\"")?;
+        writeln!(out, "state_len = {}", self.state_len)?;
+        writeln!(out, "consensus = \"{}\"", consensus_name(self.consensus))?;
+        writeln!(out, "[miner]")?;
+        writeln!(out, "max_txs = {}", self.max_txs)?;
+        writeln!(out, "min_txs = 1")?;
+        writeln!(out, "max_block_interval = {}", self.max_block_interval)?;
+
+        writeln!(out, "[network]")?;
+        writeln!(out, "http_listen = \"{}\"", node.http_address())?;
+        match self.consensus {
+            Consensus::Raft => writeln!(out, "peer_id = {}", node.peer_id)?,
+            Consensus::PoW => {
+                writeln!(out, "listen = \"{}\"", node.p2p_address())?;
+                writeln!(out, "keypair = \"{}\"", node.keypair.to_base58())?;
+                writeln!(out, "mdns = false")?;
+            }
+        }
+        for peer in nodes.iter().filter(|peer| peer.name != node.name) {
+            writeln!(out, "[[network.peers]]")?;
+            match self.consensus {
+                Consensus::Raft => {
+                    writeln!(out, "peer_id = {}", peer.peer_id)?;
+                    writeln!(out, "address = \"{}\"", peer.http_address())?;
+                    out.push_str(&role_config(peer.role));
+                }
+                Consensus::PoW => {
+                    writeln!(out, "peer_id = \"{}\"", peer.libp2p_peer_id())?;
+                    writeln!(out, "address = \"{}\"", peer.p2p_address())?;
+                }
+            }
+        }
+
+        match self.consensus {
+            Consensus::Raft => {
+                writeln!(out, "[raft]")?;
+                writeln!(out, "election_timeout_min = 150")?;
+                writeln!(out, "election_timeout_max = 300")?;
+                writeln!(out, "heartbeat_interval = 50")?;
+                writeln!(out, "async_broadcast_storage = true")?;
+            }
+            Consensus::PoW => {
+                writeln!(out, "[pow]")?;
+                writeln!(out, "init_diff = {}", self.pow_init_diff)?;
+            }
+        }
+        out.push_str(&self.extra_config);
+
+        let value = out
+            .parse::<toml::Value>()
+            .map_err(Error::msg)
+            .with_context(|| format!("Invalid config of {}.", node.name))?;
+        Ok(Config::from_toml(value))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TestNodeSpec {
+    pub(crate) name: String,
+    // The peer id in raft. The PoW nodes are identified by their libp2p keypairs instead.
+    pub(crate) peer_id: u64,
+    pub(crate) role: Role,
+    pub(crate) http_port: u16,
+    pub(crate) p2p_port: u16,
+    pub(crate) keypair: KeypairConfig,
+}
+
+impl TestNodeSpec {
+    pub(crate) fn http_address(&self) -> String {
+        format!("127.0.0.1:{}", self.http_port)
+    }
+
+    fn p2p_address(&self) -> String {
+        format!("/ip4/127.0.0.1/tcp/{}", self.p2p_port)
+    }
+
+    fn libp2p_peer_id(&self) -> String {
+        self.keypair
+            .to_libp2p_keypair()
+            .public()
+            .into_peer_id()
+            .to_base58()
+    }
+}
+
+// Let the OS pick an unused port. It is released right away, so there is a small chance that
+// another process takes it before the node binds to it.
+fn free_port() -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+fn role_config(role: Role) -> String {
+    match role {
+        Role::Client => "role = \"client\"\n".to_string(),
+        Role::LightClient => "role = \"light_client\"\n".to_string(),
+        Role::Miner => "role = \"miner\"\n".to_string(),
+        Role::Auditor => "role = \"auditor\"\n".to_string(),
+        Role::Storage(ShardId { id, total }) => format!(
+            "role = \"storage\"\nshard_id = {}\nshard_total = {}\n",
+            id, total
+        ),
+    }
+}
+
+fn consensus_name(consensus: Consensus) -> &'static str {
+    match consensus {
+        Consensus::Raft => "raft",
+        Consensus::PoW => "pow",
+    }
+}
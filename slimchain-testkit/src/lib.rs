@@ -0,0 +1,11 @@
+#[macro_use]
+extern crate tracing;
+
+pub mod config;
+pub use config::*;
+
+pub mod net;
+pub use net::*;
+
+#[cfg(test)]
+mod tests;
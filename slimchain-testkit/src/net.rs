@@ -0,0 +1,403 @@
+use crate::config::{TestNetConfig, TestNodeSpec};
+use rand::seq::SliceRandom;
+use slimchain_chain::{
+    config::{AccessMapConfig, ChainConfig, DBConfig, MinerConfig, PoWConfig, PruneConfig},
+    consensus::Consensus,
+    db::{DBPtr, MemoryBackend, DB, TOTAL_COLS},
+    epoch::EpochConfig,
+    role::Role,
+};
+use slimchain_common::{
+    basic::{set_shard_assignment, BlockHeight, H256},
+    digest::set_digest_algorithm,
+    ed25519::Keypair,
+    error::{bail, ensure, Context as _, Result},
+    tx::SignedTx,
+    tx_req::SignedTxRequest,
+};
+use slimchain_network::{
+    behavior::{pow, raft},
+    http::client_rpc::{get_block_height, get_leader_endpoint, get_tx_receipt, send_tx_requests},
+    p2p::control::{Control, Swarmer},
+};
+use slimchain_tx_engine::TxEngine;
+use slimchain_tx_engine_simple::SimpleTxEngineWorker;
+use slimchain_utils::config::Config;
+use std::{
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const FIND_MINER_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Tx = SignedTx;
+
+enum NodeHandle {
+    RaftClient(raft::client::ClientNode<Tx>),
+    RaftStorage(raft::storage::StorageNode<Tx>),
+    PoWClient(Control<pow::ClientBehavior<Tx>>),
+    PoWMiner(Control<pow::MinerBehavior<Tx>>),
+    PoWStorage(Control<pow::StorageBehavior<Tx>>),
+}
+
+impl NodeHandle {
+    async fn shutdown(self) -> Result<()> {
+        match self {
+            NodeHandle::RaftClient(mut node) => node.shutdown().await,
+            NodeHandle::RaftStorage(mut node) => node.shutdown().await,
+            NodeHandle::PoWClient(ctrl) => ctrl.shutdown().await,
+            NodeHandle::PoWMiner(ctrl) => ctrl.shutdown().await,
+            NodeHandle::PoWStorage(ctrl) => ctrl.shutdown().await,
+        }
+    }
+}
+
+// A node running inside the test process. Its in-memory database is kept across restarts.
+pub struct TestNode {
+    spec: TestNodeSpec,
+    cfg: Config,
+    db: DBPtr,
+    handle: Option<NodeHandle>,
+}
+
+impl TestNode {
+    pub fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    pub fn role(&self) -> Role {
+        self.spec.role
+    }
+
+    pub fn http_address(&self) -> String {
+        self.spec.http_address()
+    }
+
+    pub fn db(&self) -> &DBPtr {
+        &self.db
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.cfg
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    async fn start(&mut self, consensus: Consensus, engine_threads: usize) -> Result<()> {
+        ensure!(self.handle.is_none(), "{} is already running.", self.name());
+        info!("Start {} ({}).", self.name(), self.role());
+        let chain_cfg: ChainConfig = self.cfg.get("chain")?;
+        let db = self.db.clone();
+
+        let handle = match consensus {
+            Consensus::Raft => {
+                use slimchain_network::http::config::{NetworkConfig, RaftConfig};
+
+                let net_cfg: NetworkConfig = self.cfg.get("network")?;
+                match self.role() {
+                    Role::Client => {
+                        let miner_cfg: MinerConfig = self.cfg.get("miner")?;
+                        let raft_cfg: RaftConfig = self.cfg.get("raft")?;
+                        let node = raft::client::ClientNode::new(
+                            db, &chain_cfg, &miner_cfg, &net_cfg, &raft_cfg,
+                        )
+                        .await?;
+                        NodeHandle::RaftClient(node)
+                    }
+                    Role::Storage(shard_id) => {
+                        let engine = create_tx_engine(engine_threads);
+                        let node = raft::storage::StorageNode::new(
+                            db, engine, shard_id, &chain_cfg, &net_cfg,
+                        )
+                        .await?;
+                        NodeHandle::RaftStorage(node)
+                    }
+                    role => bail!("{} is not supported by the testkit in raft.", role),
+                }
+            }
+            Consensus::PoW => {
+                use slimchain_network::p2p::config::NetworkConfig;
+
+                let net_cfg: NetworkConfig = self.cfg.get("network")?;
+                match self.role() {
+                    Role::Client => {
+                        let behavior =
+                            pow::ClientBehavior::<Tx>::new(db, &chain_cfg, &net_cfg).await?;
+                        let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
+                        let mut ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
+                        ctrl.call_with_sender(|swarm, ret| {
+                            swarm.behaviour_mut().discv_mut().find_random_peer_with_ret(
+                                Role::Miner,
+                                FIND_MINER_TIMEOUT,
+                                ret,
+                            )
+                        })
+                        .await?
+                        .context("Failed to find miner.")?;
+                        NodeHandle::PoWClient(ctrl)
+                    }
+                    Role::Miner => {
+                        let miner_cfg: MinerConfig = self.cfg.get("miner")?;
+                        let behavior =
+                            pow::MinerBehavior::<Tx>::new(db, &chain_cfg, &miner_cfg, &net_cfg)
+                                .await?;
+                        let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
+                        NodeHandle::PoWMiner(swarmer.spawn_app(&net_cfg.listen).await?)
+                    }
+                    Role::Storage(shard_id) => {
+                        let engine = create_tx_engine(engine_threads);
+                        let behavior = pow::StorageBehavior::<Tx>::new(
+                            db, engine, shard_id, &chain_cfg, &net_cfg,
+                        )
+                        .await?;
+                        let swarmer = Swarmer::from_net_cfg_with_relay(&net_cfg, behavior).await?;
+                        let mut ctrl = swarmer.spawn_app(&net_cfg.listen).await?;
+                        ctrl.call_with_sender(|swarm, ret| {
+                            swarm.behaviour_mut().discv_mut().find_random_peer_with_ret(
+                                Role::Miner,
+                                FIND_MINER_TIMEOUT,
+                                ret,
+                            )
+                        })
+                        .await?
+                        .context("Failed to find miner.")?;
+                        NodeHandle::PoWStorage(ctrl)
+                    }
+                    role => bail!("{} is not supported by the testkit in PoW.", role),
+                }
+            }
+        };
+
+        self.handle = Some(handle);
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        let handle = self
+            .handle
+            .take()
+            .with_context(|| format!("{} is not running.", self.name()))?;
+        info!("Stop {} ({}).", self.name(), self.role());
+        handle.shutdown().await
+    }
+}
+
+// A complete network of client, miner and storage nodes running in the current tokio runtime.
+// The nodes talk to each other through the loopback interface, and keep their data in memory.
+pub struct TestNet {
+    cfg: TestNetConfig,
+    nodes: Vec<TestNode>,
+}
+
+impl TestNet {
+    pub async fn start(cfg: TestNetConfig) -> Result<Self> {
+        let specs = cfg.plan_nodes()?;
+        let mut nodes = Vec::with_capacity(specs.len());
+        for spec in &specs {
+            let node_cfg = cfg.node_config(&specs, spec)?;
+            let db = DB::new(
+                Box::new(MemoryBackend::new(TOTAL_COLS)),
+                &node_cfg.get::<DBConfig>("db").unwrap_or_default(),
+            )?;
+            nodes.push(TestNode {
+                spec: spec.clone(),
+                cfg: node_cfg,
+                db,
+                handle: None,
+            });
+        }
+        install_globals(&nodes[0].cfg)?;
+
+        let mut net = Self { cfg, nodes };
+        // The storage nodes and the PoW clients look for the miners (or the raft leader) once
+        // started, so that they come up last.
+        let mut order: Vec<usize> = (0..net.nodes.len()).collect();
+        order.sort_by_key(|&i| match net.nodes[i].role() {
+            Role::Miner => 0,
+            Role::Client => 1,
+            _ => 2,
+        });
+        for i in order {
+            let (consensus, engine_threads) = (net.cfg.consensus, net.cfg.engine_threads);
+            net.nodes[i].start(consensus, engine_threads).await?;
+        }
+
+        if net.cfg.consensus == Consensus::Raft {
+            net.leader(Duration::from_secs(10)).await?;
+        }
+        Ok(net)
+    }
+
+    pub fn config(&self) -> &TestNetConfig {
+        &self.cfg
+    }
+
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, name: &str) -> Result<&TestNode> {
+        self.nodes
+            .iter()
+            .find(|node| node.name() == name)
+            .with_context(|| format!("Unknown node {}.", name))
+    }
+
+    fn node_mut(&mut self, name: &str) -> Result<&mut TestNode> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.name() == name)
+            .with_context(|| format!("Unknown node {}.", name))
+    }
+
+    // The HTTP addresses of the client nodes still running.
+    pub fn client_endpoints(&self) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter(|node| node.role() == Role::Client && node.is_running())
+            .map(|node| node.http_address())
+            .collect()
+    }
+
+    fn random_client_endpoint(&self) -> Result<String> {
+        self.client_endpoints()
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .context("No client node is running.")
+    }
+
+    // Wait until a raft leader is known by the running client nodes, and return the name of it.
+    pub async fn leader(&self, timeout: Duration) -> Result<String> {
+        ensure!(
+            self.cfg.consensus == Consensus::Raft,
+            "Only raft has a leader."
+        );
+        let deadline = Instant::now() + timeout;
+        loop {
+            for endpoint in self.client_endpoints() {
+                if let Ok(Some(leader)) = get_leader_endpoint(&endpoint).await {
+                    if let Some(node) = self
+                        .nodes
+                        .iter()
+                        .find(|node| node.http_address() == leader && node.is_running())
+                    {
+                        return Ok(node.name().to_string());
+                    }
+                }
+            }
+            ensure!(Instant::now() < deadline, "No raft leader is elected.");
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn stop_node(&mut self, name: &str) -> Result<()> {
+        self.node_mut(name)?.stop().await
+    }
+
+    // Start a stopped node again, on top of the data it had before being stopped.
+    pub async fn restart_node(&mut self, name: &str) -> Result<()> {
+        let (consensus, engine_threads) = (self.cfg.consensus, self.cfg.engine_threads);
+        self.node_mut(name)?.start(consensus, engine_threads).await
+    }
+
+    // Submit the txs through a random client node, which forwards them to the storage nodes.
+    pub async fn send_tx_requests(&self, reqs: Vec<SignedTxRequest>) -> Result<()> {
+        let endpoint = self.random_client_endpoint()?;
+        send_tx_requests(&endpoint, reqs.into_iter()).await
+    }
+
+    // Wait until all the running client nodes reach the block height.
+    pub async fn wait_for_height(&self, height: BlockHeight, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        for endpoint in self.client_endpoints() {
+            loop {
+                if get_block_height(&endpoint).await.unwrap_or_default() >= height {
+                    break;
+                }
+                ensure!(
+                    Instant::now() < deadline,
+                    "{} does not reach block #{}.",
+                    endpoint,
+                    height
+                );
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+        Ok(())
+    }
+
+    // Wait until the tx is committed, and return the height of the block including it.
+    pub async fn wait_for_tx(&self, tx_id: H256, timeout: Duration) -> Result<BlockHeight> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let endpoint = self.random_client_endpoint()?;
+            if let Ok(Some(receipt)) = get_tx_receipt(&endpoint, tx_id).await {
+                return Ok(receipt.block_height);
+            }
+            ensure!(Instant::now() < deadline, "Tx {} is not committed.", tx_id);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn shutdown(mut self) -> Result<()> {
+        let mut last_err = None;
+        // Stop the storage nodes first, and the client nodes last.
+        for node in self.nodes.iter_mut().rev().filter(|node| node.is_running()) {
+            if let Err(e) = node.stop().await {
+                warn!("Failed to stop {}. Error: {}", node.name(), e);
+                last_err = Some(e);
+            }
+        }
+        match last_err {
+            Some(e) => Err(e.context("Failed to shut down the test network.")),
+            None => Ok(()),
+        }
+    }
+}
+
+fn create_tx_engine(threads: usize) -> TxEngine<Tx> {
+    TxEngine::new(threads, || {
+        Box::new(SimpleTxEngineWorker::new(Keypair::generate(
+            &mut rand::thread_rng(),
+        )))
+    })
+}
+
+static INSTALL_GLOBALS: Once = Once::new();
+
+// Some settings are process-wide. They are taken from the first network started in the process,
+// and stay the same for the later ones.
+fn install_globals(cfg: &Config) -> Result<()> {
+    let mut res = Ok(());
+    INSTALL_GLOBALS.call_once(|| {
+        res = (|| -> Result<()> {
+            let chain_cfg: ChainConfig = cfg.get("chain")?;
+            set_digest_algorithm(chain_cfg.digest);
+            set_shard_assignment(chain_cfg.shard_assignment);
+            cfg.get::<AccessMapConfig>("access_map")
+                .unwrap_or_default()
+                .install_as_global()?;
+            cfg.get::<PruneConfig>("prune")
+                .unwrap_or_default()
+                .install_as_global()?;
+            cfg.get::<EpochConfig>("epoch")
+                .unwrap_or_default()
+                .install_as_global()?;
+            cfg.get::<DBConfig>("db")
+                .unwrap_or_default()
+                .install_as_global()?;
+            cfg.get::<PoWConfig>("pow")
+                .unwrap_or_default()
+                .install_as_global()?;
+            if chain_cfg.consensus == Consensus::Raft {
+                cfg.get::<slimchain_network::http::config::NetworkConfig>("network")?
+                    .install_as_global()?;
+            }
+            Ok(())
+        })();
+    });
+    res
+}
@@ -0,0 +1,129 @@
+use crate::{TestNet, TestNetConfig};
+use rand::SeedableRng;
+use serial_test::serial;
+use slimchain_chain::consensus::Consensus;
+use slimchain_common::{
+    basic::{BlockHeight, U256},
+    ed25519::Keypair,
+    tx_req::{caller_address_from_pk, SignedTxRequest, TxRequest},
+};
+use slimchain_utils::{
+    contract::{contract_address, Contract, Token},
+    init_tracing_for_test,
+};
+use std::{path::PathBuf, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Deploy the SimpleStorage contract, and then call it `num_calls` times.
+fn create_tx_reqs(keypair: &Keypair, num_calls: usize) -> Vec<SignedTxRequest> {
+    let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("contracts/build/contracts/SimpleStorage.json");
+    let contract = Contract::from_json_file(&contract_file).unwrap();
+    let caller_address = caller_address_from_pk(&keypair.public);
+    let contract_address = contract_address(caller_address, U256::from(0).into());
+
+    let mut tx_reqs = vec![TxRequest::Create {
+        nonce: U256::from(0).into(),
+        code: contract.code().clone(),
+        chain_id: 0,
+        expiry: None,
+    }];
+    for i in 0..num_calls {
+        tx_reqs.push(TxRequest::Call {
+            address: contract_address,
+            nonce: U256::from(i + 1).into(),
+            data: contract
+                .encode_tx_input(
+                    "set",
+                    &[Token::Uint(U256::from(i)), Token::Uint(U256::from(i))],
+                )
+                .unwrap(),
+            chain_id: 0,
+            expiry: None,
+        });
+    }
+    tx_reqs.into_iter().map(|req| req.sign(keypair)).collect()
+}
+
+// Send the txs one by one, as a later call depends on the contract being created.
+async fn send_and_wait(net: &TestNet, tx_reqs: Vec<SignedTxRequest>) -> BlockHeight {
+    let mut height = BlockHeight::default();
+    for tx_req in tx_reqs {
+        let tx_id = tx_req.id();
+        net.send_tx_requests(vec![tx_req]).await.unwrap();
+        height = net.wait_for_tx(tx_id, TIMEOUT).await.unwrap();
+    }
+    height
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+async fn test_raft_net() {
+    let _guard = init_tracing_for_test();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+    let keypair = Keypair::generate(&mut rng);
+
+    let net = TestNet::start(
+        TestNetConfig::new(Consensus::Raft)
+            .with_clients(2)
+            .with_shards(2, 1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(4, net.nodes().len());
+
+    let height = send_and_wait(&net, create_tx_reqs(&keypair, 3)).await;
+    net.wait_for_height(height, TIMEOUT).await.unwrap();
+
+    net.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+async fn test_raft_leader_failover() {
+    let _guard = init_tracing_for_test();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+    let keypair = Keypair::generate(&mut rng);
+    let mut tx_reqs = create_tx_reqs(&keypair, 2);
+    let later_tx_reqs = tx_reqs.split_off(2);
+
+    let mut net = TestNet::start(TestNetConfig::new(Consensus::Raft).with_clients(3))
+        .await
+        .unwrap();
+    send_and_wait(&net, tx_reqs).await;
+
+    let leader = net.leader(TIMEOUT).await.unwrap();
+    net.stop_node(&leader).await.unwrap();
+    let new_leader = net.leader(TIMEOUT).await.unwrap();
+    assert_ne!(leader, new_leader);
+
+    let height = send_and_wait(&net, later_tx_reqs).await;
+
+    // The old leader catches up as a follower.
+    net.restart_node(&leader).await.unwrap();
+    net.wait_for_height(height, TIMEOUT).await.unwrap();
+
+    net.shutdown().await.unwrap();
+}
+
+// Ignored by default, as mining is slow in the debug build.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+#[ignore]
+async fn test_pow_net() {
+    let _guard = init_tracing_for_test();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+    let keypair = Keypair::generate(&mut rng);
+
+    let net = TestNet::start(TestNetConfig::new(Consensus::PoW).with_miners(1))
+        .await
+        .unwrap();
+
+    let height = send_and_wait(&net, create_tx_reqs(&keypair, 1)).await;
+    net.wait_for_height(height, TIMEOUT).await.unwrap();
+
+    net.shutdown().await.unwrap();
+}
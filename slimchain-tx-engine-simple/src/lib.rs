@@ -9,6 +9,7 @@ use slimchain_merkle_trie::prelude::*;
 use slimchain_tx_engine::{TxEngineWorker, TxTaskId};
 use slimchain_tx_executor::execute_tx;
 use slimchain_tx_state::{
+    read_code,
     trie_view::{AccountTrieView, StateTrieView},
     TxStateView,
 };
@@ -47,7 +48,8 @@ impl<'a, StateView: TxStateView + ?Sized> slimchain_tx_executor::Backend
     }
 
     fn get_code(&self, acc_address: Address) -> Result<Code> {
-        self.map_acc_data(acc_address, Default::default, |d| d.code.clone())
+        let code_hash = self.map_acc_data(acc_address, H256::zero, |d| d.code_hash)?;
+        read_code(self.state_view, code_hash)
     }
 
     fn get_value(&self, acc_address: Address, key: StateKey) -> Result<StateValue> {
@@ -69,18 +71,27 @@ impl TxEngineWorker for SimpleTxEngineWorker {
     fn execute(
         &self,
         _id: TxTaskId,
+        chain_id: u64,
         block_height: BlockHeight,
+        block_timestamp: u64,
         state_view: Arc<dyn TxStateView + Sync + Send>,
         state_root: H256,
         signed_tx_req: SignedTxRequest,
     ) -> Result<Self::Output> {
         let backend = ExecutorBackend::new(state_view.as_ref(), state_root);
-        let output = execute_tx(signed_tx_req, &backend)?;
+        let output = execute_tx(
+            signed_tx_req,
+            chain_id,
+            block_height,
+            block_timestamp,
+            &backend,
+        )?;
 
         let raw_tx = RawTx {
             caller: output.caller,
             input: output.input,
             block_height,
+            block_timestamp,
             state_root,
             reads: output.reads.to_set(),
             writes: output.writes,
@@ -139,6 +150,8 @@ mod tests {
         let tx_req1 = TxRequest::Create {
             nonce: U256::from(0).into(),
             code: contract.code().clone(),
+            chain_id: 0,
+            expiry: None,
         };
         let signed_tx_req1 = tx_req1.sign(&keypair);
 
@@ -146,7 +159,7 @@ mod tests {
         let task1 = TxTask::new(
             states.state_view(),
             signed_tx_req1,
-            move || -> (BlockHeight, H256) { (1.into(), state_root) },
+            move || -> (BlockHeight, u64, H256) { (1.into(), 1_000, state_root) },
         );
         task_engine.push_task(task1);
         assert_eq!(task_engine.remaining_tasks(), 1);
@@ -161,6 +174,7 @@ mod tests {
         assert_eq!(task_engine.remaining_tasks(), 0);
         write_trie1.verify(states.state_root()).unwrap();
         tx1.verify_sig().unwrap();
+        assert_eq!(1_000, tx1.tx_block_timestamp());
 
         assert!(tx1
             .raw_tx
@@ -172,6 +186,10 @@ mod tests {
             .any(|(_k, v)| v.to_low_u64_be() == 42));
         states.apply_writes(&tx1.raw_tx.writes).unwrap();
 
+        // The remaining worker should pick up the task alone.
+        task_engine.thread_limit().set(1);
+        assert_eq!(task_engine.thread_limit().get(), 1);
+
         let tx_req2 = TxRequest::Call {
             address: contract_address,
             nonce: U256::from(1).into(),
@@ -181,6 +199,8 @@ mod tests {
                     &[Token::Uint(U256::from(1)), Token::Uint(U256::from(43))],
                 )
                 .unwrap(),
+            chain_id: 0,
+            expiry: None,
         };
         let signed_tx_req2 = tx_req2.sign(&keypair);
 
@@ -188,7 +208,7 @@ mod tests {
         let task2 = TxTask::new(
             states.state_view(),
             signed_tx_req2,
-            move || -> (BlockHeight, H256) { (2.into(), state_root) },
+            move || -> (BlockHeight, u64, H256) { (2.into(), 2_000, state_root) },
         );
         task_engine.push_task(task2);
         assert_eq!(task_engine.remaining_tasks(), 1);
@@ -213,4 +233,50 @@ mod tests {
             .iter()
             .any(|(_k, v)| v.to_low_u64_be() == 43));
     }
+
+    #[tokio::test]
+    async fn test_write_trie_threads() {
+        let _guard = init_tracing_for_test();
+
+        let states = MemTxState::new();
+        let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("contracts/build/contracts/SimpleStorage.json");
+        let contract = Contract::from_json_file(&contract_file).unwrap();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+        let mut task_engine = TxEngine::new_with_write_trie_threads(2, 2, || {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+            Box::new(SimpleTxEngineWorker::new(Keypair::generate(&mut rng)))
+        });
+
+        // The txs are deployed from different accounts, so that they are all executed on the
+        // same state.
+        let state_root = states.state_root();
+        for _ in 0..4 {
+            let tx_req = TxRequest::Create {
+                nonce: U256::from(0).into(),
+                code: contract.code().clone(),
+                chain_id: 0,
+                expiry: None,
+            };
+            let task = TxTask::new(
+                states.state_view(),
+                tx_req.sign(&Keypair::generate(&mut rng)),
+                move || -> (BlockHeight, u64, H256) { (1.into(), 1_000, state_root) },
+            );
+            task_engine.push_task(task);
+        }
+
+        for _ in 0..4 {
+            let TxTaskOutput {
+                tx_proposal: TxProposal { tx, write_trie },
+                ..
+            } = task_engine.pop_result().await;
+            write_trie.verify(state_root).unwrap();
+            tx.verify_sig().unwrap();
+        }
+        assert_eq!(task_engine.remaining_tasks(), 0);
+    }
 }
@@ -1,13 +1,18 @@
+use crate::metrics::TaskMetricsRecorder;
 use sgx_types::*;
 use slimchain_common::{
     basic::{Address, BlockHeight, Code, Nonce, StateKey, StateValue, H256, U256},
-    error::{anyhow, ensure, Result},
+    digest::{set_digest_algorithm, DigestAlgorithm},
+    error::{anyhow, ensure, Error, Result},
     tx::{RawTx, SignedTx},
     tx_req::SignedTxRequest,
 };
 use slimchain_tx_state::TxReadProof;
 use std::prelude::v1::*;
-use std::{mem::MaybeUninit, slice};
+use std::{
+    mem::{size_of, MaybeUninit},
+    slice,
+};
 
 extern "C" {
     fn ocall_get_nonce(
@@ -52,13 +57,26 @@ extern "C" {
 }
 
 #[no_mangle]
+#[allow(clippy::too_many_arguments)]
 pub unsafe extern "C" fn ecall_exec_tx(
     id: u32,
+    digest_algorithm: u8,
+    chain_id: u64,
     block_height: u64,
+    block_timestamp: u64,
     state_root: *const u8,
     signed_tx_req: *const u8,
     req_len: usize,
 ) -> i32 {
+    // The digests signed by the enclave should match those computed by the nodes.
+    match DigestAlgorithm::from_u8(digest_algorithm) {
+        Ok(algorithm) => set_digest_algorithm(algorithm),
+        Err(e) => {
+            eprintln!("[Enclave Error] Invalid digest algorithm.");
+            eprintln!(" DETAIL: {}", e);
+            return 1;
+        }
+    }
     let state_root = {
         let buf = slice::from_raw_parts(state_root, 32);
         H256::from_slice(buf)
@@ -74,7 +92,16 @@ pub unsafe extern "C" fn ecall_exec_tx(
             }
         }
     };
-    let signed_tx = match exec_tx(id, block_height.into(), state_root, signed_tx_req) {
+    let metrics = TaskMetricsRecorder::default();
+    let signed_tx = match exec_tx(
+        id,
+        chain_id,
+        block_height.into(),
+        block_timestamp,
+        state_root,
+        signed_tx_req,
+        &metrics,
+    ) {
         Ok(tx) => tx,
         Err(e) => {
             eprintln!("[Enclave Error] Failed to execute tx.");
@@ -102,14 +129,21 @@ pub unsafe extern "C" fn ecall_exec_tx(
         eprintln!(" DETAIL: sgx_ret={}, retval={}.", sgx_ret, retval);
         return 1;
     }
+    metrics.record_ocall(0, signed_tx_buf.len());
+    // The metrics are only for monitoring, so a failure does not fail the task.
+    if let Err(e) = metrics.report(id) {
+        eprintln!("[Enclave Error] Failed to report metrics.");
+        eprintln!(" DETAIL: {}", e);
+    }
     0
 }
 
-struct Backend {
+struct Backend<'a> {
     id: u32,
+    metrics: &'a TaskMetricsRecorder,
 }
 
-impl slimchain_tx_executor::Backend for Backend {
+impl<'a> slimchain_tx_executor::Backend for Backend<'a> {
     fn get_nonce(&self, acc_address: Address) -> Result<Nonce> {
         let mut retval: i32 = 0;
         let mut nonce = MaybeUninit::<[u8; 32]>::uninit();
@@ -132,6 +166,7 @@ impl slimchain_tx_executor::Backend for Backend {
             "Failed to get nonce (address: {}).",
             acc_address
         );
+        self.metrics.record_ocall(32, 20);
         let nonce = unsafe { nonce.assume_init() };
         Ok(U256::from_little_endian(&nonce[..]).into())
     }
@@ -157,6 +192,7 @@ impl slimchain_tx_executor::Backend for Backend {
             "Failed to get code len (address: {}).",
             acc_address
         );
+        self.metrics.record_ocall(size_of::<usize>(), 20);
 
         if code_len == 0 {
             return Ok(Code::default());
@@ -179,6 +215,7 @@ impl slimchain_tx_executor::Backend for Backend {
             sgx_ret
         );
         ensure!(retval == 0, "Failed to get code (address:{}).", acc_address);
+        self.metrics.record_ocall(code_len, 20);
         unsafe {
             code.set_len(code_len);
         }
@@ -209,12 +246,13 @@ impl slimchain_tx_executor::Backend for Backend {
             acc_address,
             key
         );
+        self.metrics.record_ocall(32, 52);
         let value = unsafe { value.assume_init() };
         Ok(H256::from_slice(&value[..]).into())
     }
 }
 
-fn get_read_proof(id: u32) -> Result<TxReadProof> {
+fn get_read_proof(id: u32, metrics: &TaskMetricsRecorder) -> Result<TxReadProof> {
     let mut retval: i32 = 0;
 
     let mut proof_len: usize = 0;
@@ -226,6 +264,7 @@ fn get_read_proof(id: u32) -> Result<TxReadProof> {
         sgx_ret
     );
     ensure!(retval == 0, "Failed to get read proof len.");
+    metrics.record_ocall(size_of::<usize>(), 0);
 
     let mut proof_buf = Vec::with_capacity(proof_len);
     let sgx_ret = unsafe {
@@ -242,6 +281,7 @@ fn get_read_proof(id: u32) -> Result<TxReadProof> {
         sgx_ret
     );
     ensure!(retval == 0, "Failed to get read proof.");
+    metrics.record_ocall(proof_len, 0);
     unsafe {
         proof_buf.set_len(proof_len);
     }
@@ -251,20 +291,32 @@ fn get_read_proof(id: u32) -> Result<TxReadProof> {
 
 fn exec_tx(
     id: u32,
+    chain_id: u64,
     block_height: BlockHeight,
+    block_timestamp: u64,
     state_root: H256,
     signed_tx_req: SignedTxRequest,
+    metrics: &TaskMetricsRecorder,
 ) -> Result<SignedTx> {
-    let backend = Backend { id };
+    let backend = Backend { id, metrics };
 
-    let exec_output = slimchain_tx_executor::execute_tx(signed_tx_req, &backend)?;
-    let read_proof = get_read_proof(id)?;
-    read_proof.verify(&exec_output.reads, state_root)?;
+    let exec_output = slimchain_tx_executor::execute_tx(
+        signed_tx_req,
+        chain_id,
+        block_height,
+        block_timestamp,
+        &backend,
+    )?;
+    let read_proof = get_read_proof(id, metrics)?;
+    read_proof
+        .verify(&exec_output.reads, state_root)
+        .map_err(Error::msg)?;
 
     let raw_tx = RawTx {
         caller: exec_output.caller,
         input: exec_output.input,
         block_height,
+        block_timestamp,
         state_root,
         reads: exec_output.reads.to_set(),
         writes: exec_output.writes,
@@ -9,6 +9,7 @@ use slimchain_common::ed25519::Keypair;
 use std::boxed::Box;
 
 pub(crate) mod exec_tx;
+pub(crate) mod metrics;
 pub(crate) mod quote_pk;
 pub(crate) mod rand;
 
@@ -0,0 +1,54 @@
+use core::cell::Cell;
+use serde::Serialize;
+use sgx_types::*;
+use slimchain_common::error::{anyhow, ensure, Result};
+
+extern "C" {
+    fn ocall_report_metrics(
+        retval: *mut i32,
+        id: u32,
+        metrics: *const u8,
+        metrics_len: usize,
+    ) -> sgx_status_t;
+}
+
+// Used by the host to calibrate the cost of entering and leaving the enclave.
+#[no_mangle]
+pub extern "C" fn ecall_nop() {}
+
+// The OCALLs issued by a task and the bytes copied across the enclave boundary. It must match
+// `EnclaveTaskMetrics` on the host side.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub(crate) struct TaskMetrics {
+    ocalls: u32,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TaskMetricsRecorder(Cell<TaskMetrics>);
+
+impl TaskMetricsRecorder {
+    pub(crate) fn record_ocall(&self, bytes_in: usize, bytes_out: usize) {
+        let mut metrics = self.0.get();
+        metrics.ocalls += 1;
+        metrics.bytes_in += bytes_in as u64;
+        metrics.bytes_out += bytes_out as u64;
+        self.0.set(metrics);
+    }
+
+    pub(crate) fn report(&self, id: u32) -> Result<()> {
+        let buf = postcard::to_allocvec(&self.0.get())
+            .map_err(|e| anyhow!("Failed to serialize metrics. Reason: {}.", e))?;
+        let mut retval: i32 = 0;
+        let sgx_ret =
+            unsafe { ocall_report_metrics(&mut retval as *mut _, id, buf.as_ptr(), buf.len()) };
+        ensure!(
+            sgx_ret == sgx_status_t::SGX_SUCCESS,
+            "Failed to report metrics. Reason: {}.",
+            sgx_ret
+        );
+        ensure!(retval == 0, "Failed to report metrics.");
+        Ok(())
+    }
+}
@@ -9,4 +9,20 @@ pub struct TEEConfig {
     pub spid: Vec<u8>,
     /// Whether to sign linkable quote
     pub linkable: bool,
+    /// Max number of the threads executing in the enclave at the same time. It is capped by
+    /// TCSNum in the enclave's config.xml, which is also the default.
+    #[serde(default)]
+    pub enclave_threads: Option<usize>,
+    /// Base URL of the Intel Attestation Service. The development one if missing.
+    #[serde(default)]
+    pub ias_url: Option<String>,
+}
+
+impl TEEConfig {
+    pub fn ias_url(&self) -> &str {
+        self.ias_url
+            .as_deref()
+            .unwrap_or(crate::intel_api::BASE_URL)
+            .trim_end_matches('/')
+    }
 }
@@ -1,19 +1,61 @@
 use crate::{
     config::TEEConfig,
-    engine::SharedSgxEnclave,
+    engine::Enclave,
     intel_api::{get_intel_report, get_intel_sigrl},
 };
-use rand::{thread_rng, Rng};
 use sgx_types::*;
 use slimchain_common::{
     basic::{BlockHeight, H256},
-    error::{ensure, Result},
+    error::Result,
     tx_req::SignedTxRequest,
 };
 use slimchain_tee_sig::AttestationReport;
 use slimchain_tx_engine::TxTaskId;
-use std::ptr;
 
+// What the engine needs from the enclave. It is implemented by `SgxEnclave`, and by a mock
+// running on the host in the tests, so that the engine can be tested without SGX.
+pub(crate) trait EnclaveBackend: Send + Sync {
+    // Execute the tx in the enclave. The result is returned through `ocall_return_result`.
+    fn exec_tx(
+        &self,
+        id: TxTaskId,
+        chain_id: u64,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        state_root: H256,
+        signed_tx_req: &SignedTxRequest,
+    ) -> Result<()>;
+
+    fn nop(&self) -> Result<()>;
+
+    // Return the target info of the quoting enclave and the EPID group id.
+    fn init_quote(&self) -> Result<(sgx_target_info_t, sgx_epid_group_id_t)>;
+
+    // Return the quote binding the enclave's public key.
+    fn get_quote(
+        &self,
+        quote_target: &sgx_target_info_t,
+        sigrl: &[u8],
+        config: &TEEConfig,
+    ) -> Result<Vec<u8>>;
+}
+
+pub(crate) fn quote_pk(enclave: &Enclave, config: &TEEConfig) -> Result<AttestationReport> {
+    let (quote_target, quote_gid) = enclave.init_quote()?;
+    let sigrl = if cfg!(sim_enclave) {
+        Vec::new()
+    } else {
+        get_intel_sigrl(quote_gid, config)?
+    };
+    let quote = enclave.get_quote(&quote_target, &sigrl, config)?;
+
+    if cfg!(sim_enclave) {
+        return Ok(AttestationReport::default());
+    }
+    get_intel_report(&quote, config)
+}
+
+#[cfg(not(no_sgx))]
 mod ffi {
     #![allow(clippy::all)]
     #![allow(dead_code)]
@@ -22,117 +64,155 @@ mod ffi {
     include!(concat!(env!("OUT_DIR"), "/enclave_ffi.rs"));
 }
 
-pub(crate) fn exec_tx(
-    enclave: &SharedSgxEnclave,
-    id: TxTaskId,
-    block_height: BlockHeight,
-    state_root: H256,
-    signed_tx_req: &SignedTxRequest,
-) -> Result<()> {
-    let mut ret: i32 = 0;
-    let tx_req_data = postcard::to_allocvec(signed_tx_req)?;
-    let sgx_ret = unsafe {
-        ffi::ecall_exec_tx(
-            enclave.geteid(),
-            &mut ret as *mut _,
-            id.into(),
-            block_height.into(),
-            state_root.as_bytes().as_ptr(),
-            tx_req_data.as_ptr(),
-            tx_req_data.len(),
-        )
+#[cfg(not(no_sgx))]
+mod sgx {
+    use super::{ffi, EnclaveBackend};
+    use crate::config::TEEConfig;
+    use rand::{thread_rng, Rng};
+    use sgx_types::*;
+    use sgx_urts::SgxEnclave;
+    use slimchain_common::{
+        basic::{BlockHeight, H256},
+        digest::digest_algorithm,
+        error::{ensure, Result},
+        tx_req::SignedTxRequest,
     };
-    ensure!(
-        sgx_ret == sgx_status_t::SGX_SUCCESS,
-        "TEETxEngine: SGX error {:?}.",
-        sgx_ret
-    );
-    ensure!(ret == 0, "TEETxEngine: Failed to execute tx.");
-    Ok(())
-}
+    use slimchain_tx_engine::TxTaskId;
+    use std::ptr;
 
-pub(crate) fn quote_pk(
-    enclave: &SharedSgxEnclave,
-    config: &TEEConfig,
-) -> Result<AttestationReport> {
-    // init quote
-    let mut quote_target = sgx_target_info_t::default();
-    let mut quote_gid = sgx_epid_group_id_t::default();
-    let sgx_ret = unsafe { sgx_init_quote(&mut quote_target as *mut _, &mut quote_gid as *mut _) };
-    ensure!(
-        sgx_ret == sgx_status_t::SGX_SUCCESS,
-        "TEETxEngine: Failed to init quote. SGX error {:?}.",
-        sgx_ret
-    );
+    impl EnclaveBackend for SgxEnclave {
+        fn exec_tx(
+            &self,
+            id: TxTaskId,
+            chain_id: u64,
+            block_height: BlockHeight,
+            block_timestamp: u64,
+            state_root: H256,
+            signed_tx_req: &SignedTxRequest,
+        ) -> Result<()> {
+            let mut ret: i32 = 0;
+            let tx_req_data = postcard::to_allocvec(signed_tx_req)?;
+            let sgx_ret = unsafe {
+                ffi::ecall_exec_tx(
+                    self.geteid(),
+                    &mut ret as *mut _,
+                    id.into(),
+                    digest_algorithm().to_u8(),
+                    chain_id,
+                    block_height.into(),
+                    block_timestamp,
+                    state_root.as_bytes().as_ptr(),
+                    tx_req_data.as_ptr(),
+                    tx_req_data.len(),
+                )
+            };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: SGX error {:?}.",
+                sgx_ret
+            );
+            ensure!(ret == 0, "TEETxEngine: Failed to execute tx.");
+            Ok(())
+        }
 
-    // get quote report
-    let mut ret: i32 = 0;
-    let mut report = sgx_report_t::default();
-    let sgx_ret = unsafe {
-        ffi::ecall_quote_pk(
-            enclave.geteid(),
-            &mut ret as *mut _,
-            &quote_target as *const _,
-            &mut report as *mut _,
-        )
-    };
-    ensure!(
-        sgx_ret == sgx_status_t::SGX_SUCCESS,
-        "TEETxEngine: Failed to get quote report. SGX error {:?}.",
-        sgx_ret
-    );
-    ensure!(ret == 0, "TEETxEngine: Failed to get quote report.");
+        fn nop(&self) -> Result<()> {
+            let sgx_ret = unsafe { ffi::ecall_nop(self.geteid()) };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: SGX error {:?}.",
+                sgx_ret
+            );
+            Ok(())
+        }
 
-    // calculate quote size
-    let sigrl = get_intel_sigrl(quote_gid, &config.api_key)?;
-    let mut quote_size: u32 = 0;
-    let (p_sigrl, sigrl_len) = if sigrl.is_empty() {
-        (ptr::null(), 0)
-    } else {
-        (sigrl.as_ptr(), sigrl.len() as u32)
-    };
-    let sgx_ret = unsafe { sgx_calc_quote_size(p_sigrl, sigrl_len, &mut quote_size as *mut _) };
-    ensure!(
-        sgx_ret == sgx_status_t::SGX_SUCCESS,
-        "TEETxEngine: Failed to calculate quote size. SGX error {:?}.",
-        sgx_ret
-    );
+        fn init_quote(&self) -> Result<(sgx_target_info_t, sgx_epid_group_id_t)> {
+            let mut quote_target = sgx_target_info_t::default();
+            let mut quote_gid = sgx_epid_group_id_t::default();
+            let sgx_ret =
+                unsafe { sgx_init_quote(&mut quote_target as *mut _, &mut quote_gid as *mut _) };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: Failed to init quote. SGX error {:?}.",
+                sgx_ret
+            );
+            Ok((quote_target, quote_gid))
+        }
 
-    // get quote
-    let quote_type = if config.linkable {
-        sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE
-    } else {
-        sgx_quote_sign_type_t::SGX_UNLINKABLE_SIGNATURE
-    };
-    let nonce = {
-        let mut nonce = sgx_quote_nonce_t::default();
-        let mut rng = thread_rng();
-        rng.fill(&mut nonce.rand);
-        nonce
-    };
-    let mut quote_buf = Vec::with_capacity(quote_size as usize);
-    let mut _quote_report = sgx_report_t::default();
-    let sgx_ret = unsafe {
-        sgx_get_quote(
-            &report,
-            quote_type,
-            config.spid.as_ptr() as *const _,
-            &nonce,
-            p_sigrl,
-            sigrl_len,
-            &mut _quote_report as *mut _,
-            quote_buf.as_mut_ptr() as *mut _,
-            quote_size,
-        )
-    };
-    ensure!(
-        sgx_ret == sgx_status_t::SGX_SUCCESS,
-        "TEETxEngine: Failed to get quote. SGX error {:?}.",
-        sgx_ret
-    );
-    unsafe {
-        quote_buf.set_len(quote_size as usize);
-    }
+        fn get_quote(
+            &self,
+            quote_target: &sgx_target_info_t,
+            sigrl: &[u8],
+            config: &TEEConfig,
+        ) -> Result<Vec<u8>> {
+            // get quote report
+            let mut ret: i32 = 0;
+            let mut report = sgx_report_t::default();
+            let sgx_ret = unsafe {
+                ffi::ecall_quote_pk(
+                    self.geteid(),
+                    &mut ret as *mut _,
+                    quote_target as *const _,
+                    &mut report as *mut _,
+                )
+            };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: Failed to get quote report. SGX error {:?}.",
+                sgx_ret
+            );
+            ensure!(ret == 0, "TEETxEngine: Failed to get quote report.");
 
-    get_intel_report(&quote_buf[..], &config.api_key)
+            // calculate quote size
+            let mut quote_size: u32 = 0;
+            let (p_sigrl, sigrl_len) = if sigrl.is_empty() {
+                (ptr::null(), 0)
+            } else {
+                (sigrl.as_ptr(), sigrl.len() as u32)
+            };
+            let sgx_ret =
+                unsafe { sgx_calc_quote_size(p_sigrl, sigrl_len, &mut quote_size as *mut _) };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: Failed to calculate quote size. SGX error {:?}.",
+                sgx_ret
+            );
+
+            // get quote
+            let quote_type = if config.linkable {
+                sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE
+            } else {
+                sgx_quote_sign_type_t::SGX_UNLINKABLE_SIGNATURE
+            };
+            let nonce = {
+                let mut nonce = sgx_quote_nonce_t::default();
+                let mut rng = thread_rng();
+                rng.fill(&mut nonce.rand);
+                nonce
+            };
+            let mut quote_buf = Vec::with_capacity(quote_size as usize);
+            let mut _quote_report = sgx_report_t::default();
+            let sgx_ret = unsafe {
+                sgx_get_quote(
+                    &report,
+                    quote_type,
+                    config.spid.as_ptr() as *const _,
+                    &nonce,
+                    p_sigrl,
+                    sigrl_len,
+                    &mut _quote_report as *mut _,
+                    quote_buf.as_mut_ptr() as *mut _,
+                    quote_size,
+                )
+            };
+            ensure!(
+                sgx_ret == sgx_status_t::SGX_SUCCESS,
+                "TEETxEngine: Failed to get quote. SGX error {:?}.",
+                sgx_ret
+            );
+            unsafe {
+                quote_buf.set_len(quote_size as usize);
+            }
+            Ok(quote_buf)
+        }
+    }
 }
@@ -1,12 +1,18 @@
-use crate::config::TEEConfig;
+use crate::{
+    config::TEEConfig,
+    ecall::EnclaveBackend,
+    metrics::{
+        calibrate_transition_cost, record_task_metrics, register_tee_metrics, EnclaveTaskMetrics,
+        PagingStats, TaskMetrics,
+    },
+};
 use dashmap::{mapref::one::RefMut as DashMapRefMut, DashMap};
 use once_cell::sync::Lazy;
-use parking_lot::RwLock;
+use parking_lot::{Condvar, Mutex, RwLock};
 use sgx_types::*;
-use sgx_urts::SgxEnclave;
 use slimchain_common::{
     basic::{BlockHeight, H256},
-    error::{anyhow, Context as _, Error, Result},
+    error::{anyhow, Context as _, Result},
     tx::SignedTx,
     tx_req::SignedTxRequest,
 };
@@ -22,32 +28,147 @@ use std::{
 };
 
 pub(crate) static TASK_STATES: Lazy<DashMap<TxTaskId, TaskState>> = Lazy::new(DashMap::new);
-pub(crate) type SharedSgxEnclave = Arc<SgxEnclave>;
+pub(crate) type SharedEnclave = Arc<Enclave>;
+
+// TCSNum in the enclave's config.xml, i.e., the max number of threads inside the enclave.
+const ENCLAVE_TCS_NUM: usize = 16;
+
+// The enclave shared by all the workers. Each thread inside the enclave occupies a TCS, and an
+// ECALL finding none available fails with SGX_ERROR_OUT_OF_TCS. So the ECALLs beyond the limit
+// wait for a thread to leave instead.
+pub(crate) struct Enclave {
+    backend: Box<dyn EnclaveBackend>,
+    max_threads: usize,
+    threads: Mutex<usize>,
+    thread_left: Condvar,
+}
+
+impl Enclave {
+    fn new(backend: Box<dyn EnclaveBackend>, max_threads: usize) -> Self {
+        Self {
+            backend,
+            max_threads,
+            threads: Mutex::new(0),
+            thread_left: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn exec_tx(
+        &self,
+        id: TxTaskId,
+        chain_id: u64,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        state_root: H256,
+        signed_tx_req: &SignedTxRequest,
+    ) -> Result<()> {
+        let _thread = self.enter();
+        self.backend.exec_tx(
+            id,
+            chain_id,
+            block_height,
+            block_timestamp,
+            state_root,
+            signed_tx_req,
+        )
+    }
+
+    pub(crate) fn nop(&self) -> Result<()> {
+        let _thread = self.enter();
+        self.backend.nop()
+    }
+
+    // It talks to the quoting enclave instead, so no thread is taken.
+    pub(crate) fn init_quote(&self) -> Result<(sgx_target_info_t, sgx_epid_group_id_t)> {
+        self.backend.init_quote()
+    }
+
+    pub(crate) fn get_quote(
+        &self,
+        quote_target: &sgx_target_info_t,
+        sigrl: &[u8],
+        config: &TEEConfig,
+    ) -> Result<Vec<u8>> {
+        let _thread = self.enter();
+        self.backend.get_quote(quote_target, sigrl, config)
+    }
+
+    // Hold it for the duration of an ECALL.
+    fn enter(&self) -> EnclaveThread<'_> {
+        let mut threads = self.threads.lock();
+        while *threads >= self.max_threads {
+            self.thread_left.wait(&mut threads);
+        }
+        *threads += 1;
+        EnclaveThread { enclave: self }
+    }
+}
+
+struct EnclaveThread<'a> {
+    enclave: &'a Enclave,
+}
+
+impl<'a> Drop for EnclaveThread<'a> {
+    fn drop(&mut self) {
+        *self.enclave.threads.lock() -= 1;
+        self.enclave.thread_left.notify_one();
+    }
+}
 
 pub struct TEETxEngineWorkerFactory {
-    enclave: SharedSgxEnclave,
+    enclave: SharedEnclave,
     attest_pk: Arc<AttestTEEPublicKey>,
+    transition_cost: Duration,
 }
 
 impl TEETxEngineWorkerFactory {
+    #[cfg(not(no_sgx))]
     pub fn new(config: TEEConfig, enclave_path: &Path) -> Result<Self> {
         info!("Init SGX enclave from {}.", enclave_path.display());
         let debug = 1;
         let mut launch_token: sgx_launch_token_t = unsafe { mem::zeroed() };
         let mut launch_token_updated: i32 = 0;
         let mut misc_attr: sgx_misc_attribute_t = unsafe { mem::zeroed() };
-        let enclave = SgxEnclave::create(
+        let enclave = sgx_urts::SgxEnclave::create(
             enclave_path,
             debug,
             &mut launch_token,
             &mut launch_token_updated,
             &mut misc_attr,
         )
-        .map_err(Error::msg)?;
-        let enclave = Arc::new(enclave);
+        .map_err(slimchain_common::error::Error::msg)?;
+        Self::from_backend(config, Box::new(enclave))
+    }
+
+    #[cfg(no_sgx)]
+    pub fn new(_config: TEEConfig, enclave_path: &Path) -> Result<Self> {
+        slimchain_common::error::bail!(
+            "Cannot load the enclave from {}. Built without the SGX SDK.",
+            enclave_path.display()
+        );
+    }
+
+    // Without SGX, it is only used by the tests with the mock enclave.
+    #[cfg_attr(no_sgx, allow(dead_code))]
+    pub(crate) fn from_backend(
+        config: TEEConfig,
+        backend: Box<dyn EnclaveBackend>,
+    ) -> Result<Self> {
+        let max_threads = config
+            .enclave_threads
+            .unwrap_or(ENCLAVE_TCS_NUM)
+            .clamp(1, ENCLAVE_TCS_NUM);
+        info!("Allow up to {} threads in the enclave.", max_threads);
+        let enclave = Arc::new(Enclave::new(backend, max_threads));
         let attest_pk = AttestTEEPublicKey::new(config, enclave.clone())?;
+        let transition_cost = calibrate_transition_cost(&enclave)?;
+        register_tee_metrics();
 
-        Ok(Self { enclave, attest_pk })
+        Ok(Self {
+            enclave,
+            attest_pk,
+            transition_cost,
+        })
     }
 
     pub fn use_enclave_in_the_same_dir(config: TEEConfig) -> Result<Self> {
@@ -66,18 +187,28 @@ impl TEETxEngineWorkerFactory {
         Box::new(TEETxEngineWorker::new(
             self.enclave.clone(),
             self.attest_pk.clone(),
+            self.transition_cost,
         ))
     }
 }
 
 pub struct TEETxEngineWorker {
-    enclave: SharedSgxEnclave,
+    enclave: SharedEnclave,
     attest_pk: Arc<AttestTEEPublicKey>,
+    transition_cost: Duration,
 }
 
 impl TEETxEngineWorker {
-    fn new(enclave: SharedSgxEnclave, attest_pk: Arc<AttestTEEPublicKey>) -> Self {
-        Self { enclave, attest_pk }
+    fn new(
+        enclave: SharedEnclave,
+        attest_pk: Arc<AttestTEEPublicKey>,
+        transition_cost: Duration,
+    ) -> Self {
+        Self {
+            enclave,
+            attest_pk,
+            transition_cost,
+        }
     }
 }
 
@@ -87,15 +218,45 @@ impl TxEngineWorker for TEETxEngineWorker {
     fn execute(
         &self,
         id: TxTaskId,
+        chain_id: u64,
         block_height: BlockHeight,
+        block_timestamp: u64,
         state_view: Arc<dyn TxStateView + Sync + Send>,
         state_root: H256,
         signed_tx_req: SignedTxRequest,
     ) -> Result<Self::Output> {
         let task_state_guard = TaskStateGuard::new(id, state_root, state_view.clone());
-        crate::ecall::exec_tx(&self.enclave, id, block_height, state_root, &signed_tx_req)?;
-        let SignedTx { raw_tx, pk_sig } = TaskState::get_task_state(id)?.take_result()?;
+        let paging_before = PagingStats::read();
+        let begin = Instant::now();
+        self.enclave.exec_tx(
+            id,
+            chain_id,
+            block_height,
+            block_timestamp,
+            state_root,
+            &signed_tx_req,
+        )?;
+        let ecall_time = begin.elapsed();
+        let (SignedTx { raw_tx, pk_sig }, ocall_time, enclave_metrics) = {
+            let mut task_state = TaskState::get_task_state(id)?;
+            let result = task_state.take_result()?;
+            (result, task_state.ocall_time, task_state.enclave_metrics)
+        };
         task_state_guard.finish();
+        record_task_metrics(
+            id,
+            self.transition_cost,
+            TaskMetrics {
+                ecall_time,
+                ocall_time,
+                enclave_metrics,
+                paging_before,
+            },
+        );
+        // The enclave signs with its ed25519 key, which is bound by the attestation report.
+        let pk_sig = *pk_sig
+            .as_ed25519()
+            .context("The enclave signature is not ed25519.")?;
         let attest_report = self.attest_pk.get_attest_report()?;
         Ok(TEESignedTx {
             raw_tx,
@@ -109,6 +270,8 @@ pub(crate) struct TaskState {
     read_ctx: TxStateReadContext,
     read_proof: Option<Vec<u8>>,
     signed_tx: Option<SignedTx>,
+    ocall_time: Duration,
+    enclave_metrics: Option<EnclaveTaskMetrics>,
 }
 
 impl TaskState {
@@ -142,6 +305,14 @@ impl TaskState {
             .take()
             .context("Task result is not available.")
     }
+
+    pub(crate) fn add_ocall_time(&mut self, time: Duration) {
+        self.ocall_time += time;
+    }
+
+    pub(crate) fn set_enclave_metrics(&mut self, metrics: EnclaveTaskMetrics) {
+        self.enclave_metrics = Some(metrics);
+    }
 }
 
 struct TaskStateGuard {
@@ -158,6 +329,8 @@ impl TaskStateGuard {
             read_ctx: TxStateReadContext::new(state_view, state_root),
             read_proof: None,
             signed_tx: None,
+            ocall_time: Duration::ZERO,
+            enclave_metrics: None,
         };
         TASK_STATES.insert(task_id, state);
         Self { task_id }
@@ -176,12 +349,12 @@ const ATTEST_REPORT_TTL: Duration = Duration::from_secs(21_600); // 6 hours
 
 struct AttestTEEPublicKey {
     config: TEEConfig,
-    enclave: SharedSgxEnclave,
+    enclave: SharedEnclave,
     attest_report: RwLock<(AttestationReport, Instant)>,
 }
 
 impl AttestTEEPublicKey {
-    fn new(config: TEEConfig, enclave: SharedSgxEnclave) -> Result<Arc<Self>> {
+    fn new(config: TEEConfig, enclave: SharedEnclave) -> Result<Arc<Self>> {
         let attest_report = crate::ecall::quote_pk(&enclave, &config)?;
         Ok(Arc::new(Self {
             config,
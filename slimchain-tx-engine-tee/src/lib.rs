@@ -7,6 +7,9 @@ pub(crate) mod config;
 pub(crate) mod ecall;
 pub(crate) mod engine;
 pub(crate) mod intel_api;
+pub(crate) mod metrics;
+#[cfg(test)]
+pub(crate) mod mock;
 pub(crate) mod ocall;
 
 pub use config::TEEConfig;
@@ -22,5 +25,6 @@ pub const fn is_sim_mode() -> bool {
     false
 }
 
-#[cfg(test)]
+// They need the enclave. See `mock` for the tests runnable without SGX.
+#[cfg(all(test, not(no_sgx)))]
 mod tests;
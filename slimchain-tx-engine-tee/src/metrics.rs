@@ -0,0 +1,192 @@
+use crate::engine::SharedEnclave;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use slimchain_common::error::Result;
+use slimchain_tx_engine::TxTaskId;
+use slimchain_utils::{
+    prometheus::{self, MetricKind},
+    record_time,
+};
+use std::{
+    fs,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+// The EPC statistics exported by the out-of-tree isgx driver. They are missing with the
+// in-kernel driver, in which case the EPC metrics are not reported.
+const ISGX_PARAMS_DIR: &str = "/sys/module/isgx/parameters";
+const EPC_PAGE_SIZE: u64 = 4096;
+
+// Number of the no-op ECALLs used to estimate the cost of entering and leaving the enclave.
+const CALIBRATION_ROUNDS: u32 = 1_000;
+
+static TEE_STATS: Lazy<TEEStats> = Lazy::new(TEEStats::default);
+
+#[derive(Debug, Default)]
+struct TEEStats {
+    tasks: AtomicU64,
+    ocalls: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+// Reported by the enclave at the end of each task through `ocall_report_metrics`. It must
+// match `TaskMetrics` in the enclave.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct EnclaveTaskMetrics {
+    // Excluding `ocall_report_metrics` itself.
+    pub(crate) ocalls: u32,
+    // Bytes copied into the enclave.
+    pub(crate) bytes_in: u64,
+    // Bytes copied out of the enclave.
+    pub(crate) bytes_out: u64,
+}
+
+fn read_isgx_param(name: &str) -> Option<u64> {
+    let value = fs::read_to_string(Path::new(ISGX_PARAMS_DIR).join(name)).ok()?;
+    value.trim().parse().ok()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PagingStats {
+    evicted: u64,
+    loaded_back: u64,
+}
+
+impl PagingStats {
+    pub(crate) fn read() -> Option<Self> {
+        Some(Self {
+            evicted: read_isgx_param("sgx_nr_evicted")?,
+            loaded_back: read_isgx_param("sgx_loaded_back")?,
+        })
+    }
+}
+
+// Estimate the time of a single enclave transition, i.e., either entering or leaving it.
+pub(crate) fn calibrate_transition_cost(enclave: &SharedEnclave) -> Result<Duration> {
+    let begin = Instant::now();
+    for _ in 0..CALIBRATION_ROUNDS {
+        enclave.nop()?;
+    }
+    let cost = begin.elapsed() / (2 * CALIBRATION_ROUNDS);
+    debug!("TEETxEngine: Enclave transition takes {:?}.", cost);
+    Ok(cost)
+}
+
+pub(crate) struct TaskMetrics {
+    pub(crate) ecall_time: Duration,
+    pub(crate) ocall_time: Duration,
+    pub(crate) enclave_metrics: Option<EnclaveTaskMetrics>,
+    pub(crate) paging_before: Option<PagingStats>,
+}
+
+// Split the time of an ECALL into the parts spent inside the enclave, in the transitions and
+// in the OCALL handlers. The transitions cannot be timed directly, and are estimated with the
+// calibrated cost instead.
+pub(crate) fn record_task_metrics(id: TxTaskId, transition_cost: Duration, metrics: TaskMetrics) {
+    let TaskMetrics {
+        ecall_time,
+        ocall_time,
+        enclave_metrics,
+        paging_before,
+    } = metrics;
+
+    TEE_STATS.tasks.fetch_add(1, Ordering::Relaxed);
+    let enclave_metrics = match enclave_metrics {
+        Some(m) => m,
+        None => {
+            record_time!("tee_ecall_time", ecall_time, "task_id": id.0);
+            return;
+        }
+    };
+    TEE_STATS
+        .ocalls
+        .fetch_add(enclave_metrics.ocalls as u64, Ordering::Relaxed);
+    TEE_STATS
+        .bytes_in
+        .fetch_add(enclave_metrics.bytes_in, Ordering::Relaxed);
+    TEE_STATS
+        .bytes_out
+        .fetch_add(enclave_metrics.bytes_out, Ordering::Relaxed);
+
+    // The ECALL itself and the OCALLs including `ocall_report_metrics`, each in and out.
+    let transitions = 2 * (enclave_metrics.ocalls + 2);
+    let transition_time = transition_cost * transitions;
+    let enclave_time = ecall_time.saturating_sub(ocall_time + transition_time);
+
+    // The driver counters are global, so the paging of the concurrent tasks is included.
+    let (pages_evicted, pages_loaded_back) = match (paging_before, PagingStats::read()) {
+        (Some(before), Some(after)) => (
+            Some(after.evicted.saturating_sub(before.evicted)),
+            Some(after.loaded_back.saturating_sub(before.loaded_back)),
+        ),
+        _ => (None, None),
+    };
+
+    record_time!("tee_ecall_time", ecall_time, "task_id": id.0, "ocalls": enclave_metrics.ocalls, "bytes_in": enclave_metrics.bytes_in, "bytes_out": enclave_metrics.bytes_out, "pages_evicted": pages_evicted, "pages_loaded_back": pages_loaded_back);
+    record_time!("tee_enclave_time", enclave_time, "task_id": id.0);
+    record_time!("tee_transition_time", transition_time, "task_id": id.0, "transitions": transitions);
+    record_time!("tee_ocall_time", ocall_time, "task_id": id.0);
+}
+
+pub(crate) fn register_tee_metrics() {
+    prometheus::register_value(
+        "slimchain_tee_tasks_total",
+        "Number of the txs executed in the enclave.",
+        MetricKind::Counter,
+        || TEE_STATS.tasks.load(Ordering::Relaxed) as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tee_ocalls_total",
+        "Number of the OCALLs issued by the enclave to read the states.",
+        MetricKind::Counter,
+        || TEE_STATS.ocalls.load(Ordering::Relaxed) as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tee_ocall_bytes_in_total",
+        "Bytes copied into the enclave by the OCALLs.",
+        MetricKind::Counter,
+        || TEE_STATS.bytes_in.load(Ordering::Relaxed) as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tee_ocall_bytes_out_total",
+        "Bytes copied out of the enclave by the OCALLs.",
+        MetricKind::Counter,
+        || TEE_STATS.bytes_out.load(Ordering::Relaxed) as f64,
+    );
+
+    if read_isgx_param("sgx_nr_total_epc_pages").is_none() {
+        warn!("TEETxEngine: EPC statistics are not available from the isgx driver.");
+        return;
+    }
+    prometheus::register_value(
+        "slimchain_tee_epc_total_bytes",
+        "Size of the EPC.",
+        MetricKind::Gauge,
+        || (read_isgx_param("sgx_nr_total_epc_pages").unwrap_or(0) * EPC_PAGE_SIZE) as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tee_epc_used_bytes",
+        "Size of the EPC in use by all the enclaves on the machine.",
+        MetricKind::Gauge,
+        || {
+            let total = read_isgx_param("sgx_nr_total_epc_pages").unwrap_or(0);
+            let free = read_isgx_param("sgx_nr_free_pages").unwrap_or(total);
+            (total.saturating_sub(free) * EPC_PAGE_SIZE) as f64
+        },
+    );
+    prometheus::register_value(
+        "slimchain_tee_epc_evicted_pages_total",
+        "Number of the EPC pages evicted to the untrusted memory.",
+        MetricKind::Counter,
+        || PagingStats::read().map_or(0, |s| s.evicted) as f64,
+    );
+    prometheus::register_value(
+        "slimchain_tee_epc_loaded_back_pages_total",
+        "Number of the evicted EPC pages loaded back.",
+        MetricKind::Counter,
+        || PagingStats::read().map_or(0, |s| s.loaded_back) as f64,
+    );
+}
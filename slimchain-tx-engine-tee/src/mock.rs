@@ -0,0 +1,364 @@
+// Stand-ins for the enclave and the Intel Attestation Service, so that the engine can be tested
+// on the machines without SGX.
+
+use crate::{
+    config::TEEConfig,
+    ecall::EnclaveBackend,
+    metrics::EnclaveTaskMetrics,
+    ocall::{
+        ocall_get_code, ocall_get_code_len, ocall_get_nonce, ocall_get_read_proof,
+        ocall_get_read_proof_len, ocall_get_value, ocall_report_metrics, ocall_return_result,
+    },
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::SeedableRng;
+use serde_json::Value as JsonValue;
+use sgx_types::*;
+use slimchain_common::{
+    basic::{Address, BlockHeight, Code, Nonce, StateKey, StateValue, H256, U256},
+    ed25519::{ed25519_dalek::PUBLIC_KEY_LENGTH, Keypair},
+    error::{ensure, Context as _, Result},
+    tx::RawTx,
+    tx_req::SignedTxRequest,
+};
+use slimchain_tx_engine::TxTaskId;
+use slimchain_tx_state::TxReadProof;
+use std::{
+    cell::Cell,
+    io::{BufRead, BufReader, Read, Write},
+    mem::{self, size_of},
+    net::{TcpListener, TcpStream},
+    slice,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+pub(crate) const MOCK_API_KEY: &str = "mock_api_key";
+pub(crate) const MOCK_SIGNATURE: &[u8] = b"mock signature";
+pub(crate) const MOCK_REPORT_TIMESTAMP: &str = "2021-01-01T00:00:00.000000";
+
+// A self-signed report signing cert followed by the Intel root CA, in the same order as IAS.
+const MOCK_CERT_CHAIN: &str = include_str!("mock/ias_certs.pem");
+
+// Execute the txs on the host, and talk to the engine through the same OCALL handlers as the
+// enclave does.
+pub(crate) struct MockEnclave {
+    keypair: Keypair,
+}
+
+impl MockEnclave {
+    pub(crate) fn new() -> Self {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        Self {
+            keypair: Keypair::generate(&mut rng),
+        }
+    }
+}
+
+impl EnclaveBackend for MockEnclave {
+    fn exec_tx(
+        &self,
+        id: TxTaskId,
+        chain_id: u64,
+        block_height: BlockHeight,
+        block_timestamp: u64,
+        state_root: H256,
+        signed_tx_req: &SignedTxRequest,
+    ) -> Result<()> {
+        let backend = MockBackend {
+            id: id.into(),
+            metrics: Cell::default(),
+        };
+        let exec_output = slimchain_tx_executor::execute_tx(
+            signed_tx_req.clone(),
+            chain_id,
+            block_height,
+            block_timestamp,
+            &backend,
+        )?;
+        let read_proof = backend.get_read_proof()?;
+        read_proof.verify(&exec_output.reads, state_root)?;
+
+        let raw_tx = RawTx {
+            caller: exec_output.caller,
+            input: exec_output.input,
+            block_height,
+            block_timestamp,
+            state_root,
+            reads: exec_output.reads.to_set(),
+            writes: exec_output.writes,
+        };
+        let signed_tx = raw_tx.sign(&self.keypair);
+
+        let buf = postcard::to_allocvec(&signed_tx)?;
+        let ret = unsafe { ocall_return_result(backend.id, buf.as_ptr(), buf.len()) };
+        ensure!(ret == 0, "Failed to return signed_tx.");
+        backend.record_ocall(0, buf.len());
+
+        let buf = postcard::to_allocvec(&backend.metrics.get())?;
+        let ret = unsafe { ocall_report_metrics(backend.id, buf.as_ptr(), buf.len()) };
+        ensure!(ret == 0, "Failed to report metrics.");
+        Ok(())
+    }
+
+    fn nop(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn init_quote(&self) -> Result<(sgx_target_info_t, sgx_epid_group_id_t)> {
+        Ok((sgx_target_info_t::default(), [0x12, 0x34, 0x56, 0x78]))
+    }
+
+    // An unsigned quote with the public key in the report data, which is all that is checked
+    // by `AttestationReport::verify` besides the IAS signature.
+    fn get_quote(
+        &self,
+        _quote_target: &sgx_target_info_t,
+        _sigrl: &[u8],
+        config: &TEEConfig,
+    ) -> Result<Vec<u8>> {
+        let mut quote: sgx_quote_t = unsafe { mem::zeroed() };
+        quote.version = 2;
+        quote.sign_type = config.linkable as u16;
+        quote.report_body.report_data.d[..PUBLIC_KEY_LENGTH]
+            .copy_from_slice(&self.keypair.public.as_bytes()[..]);
+        let buf = unsafe {
+            slice::from_raw_parts(&quote as *const _ as *const u8, size_of::<sgx_quote_t>())
+        };
+        Ok(buf.to_vec())
+    }
+}
+
+struct MockBackend {
+    id: u32,
+    metrics: Cell<EnclaveTaskMetrics>,
+}
+
+impl MockBackend {
+    fn record_ocall(&self, bytes_in: usize, bytes_out: usize) {
+        let mut metrics = self.metrics.get();
+        metrics.ocalls += 1;
+        metrics.bytes_in += bytes_in as u64;
+        metrics.bytes_out += bytes_out as u64;
+        self.metrics.set(metrics);
+    }
+
+    fn get_read_proof(&self) -> Result<TxReadProof> {
+        let mut proof_len: usize = 0;
+        let ret = unsafe { ocall_get_read_proof_len(self.id, &mut proof_len as *mut _) };
+        ensure!(ret == 0, "Failed to get read proof len.");
+        self.record_ocall(size_of::<usize>(), 0);
+
+        let mut proof = vec![0; proof_len];
+        let ret = unsafe { ocall_get_read_proof(self.id, proof.as_mut_ptr(), proof_len) };
+        ensure!(ret == 0, "Failed to get read proof.");
+        self.record_ocall(proof_len, 0);
+        Ok(postcard::from_bytes(&proof)?)
+    }
+}
+
+impl slimchain_tx_executor::Backend for MockBackend {
+    fn get_nonce(&self, acc_address: Address) -> Result<Nonce> {
+        let mut nonce = [0u8; 32];
+        let ret = unsafe {
+            ocall_get_nonce(self.id, acc_address.as_bytes().as_ptr(), nonce.as_mut_ptr())
+        };
+        ensure!(ret == 0, "Failed to get nonce (address: {}).", acc_address);
+        self.record_ocall(32, 20);
+        Ok(U256::from_little_endian(&nonce[..]).into())
+    }
+
+    fn get_code(&self, acc_address: Address) -> Result<Code> {
+        let mut code_len: usize = 0;
+        let ret = unsafe {
+            ocall_get_code_len(
+                self.id,
+                acc_address.as_bytes().as_ptr(),
+                &mut code_len as *mut _,
+            )
+        };
+        ensure!(
+            ret == 0,
+            "Failed to get code len (address: {}).",
+            acc_address
+        );
+        self.record_ocall(size_of::<usize>(), 20);
+
+        if code_len == 0 {
+            return Ok(Code::default());
+        }
+
+        let mut code = vec![0; code_len];
+        let ret = unsafe {
+            ocall_get_code(
+                self.id,
+                acc_address.as_bytes().as_ptr(),
+                code.as_mut_ptr(),
+                code_len,
+            )
+        };
+        ensure!(ret == 0, "Failed to get code (address: {}).", acc_address);
+        self.record_ocall(code_len, 20);
+        Ok(code.into())
+    }
+
+    fn get_value(&self, acc_address: Address, key: StateKey) -> Result<StateValue> {
+        let mut value = [0u8; 32];
+        let ret = unsafe {
+            ocall_get_value(
+                self.id,
+                acc_address.as_bytes().as_ptr(),
+                key.as_bytes().as_ptr(),
+                value.as_mut_ptr(),
+            )
+        };
+        ensure!(
+            ret == 0,
+            "Failed to get value (address: {}, key: {}).",
+            acc_address,
+            key
+        );
+        self.record_ocall(32, 52);
+        Ok(H256::from_slice(&value[..]).into())
+    }
+}
+
+// Serve the sigrl and the report APIs of IAS on a local port. The sigrl is always empty, and
+// the report accepts any quote.
+pub(crate) struct MockIas {
+    url: String,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockIas {
+    pub(crate) fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock IAS.");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let requests_copy = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                requests_copy.fetch_add(1, Ordering::SeqCst);
+                if let Err(e) = handle_ias_request(stream) {
+                    error!("MockIas: Failed to handle request. Reason: {}.", e);
+                }
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub(crate) fn tee_config(&self) -> TEEConfig {
+        TEEConfig {
+            api_key: MOCK_API_KEY.to_string(),
+            spid: vec![0; 16],
+            linkable: false,
+            enclave_threads: None,
+            ias_url: Some(self.url.clone()),
+        }
+    }
+
+    pub(crate) fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+struct IasResponse {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl IasResponse {
+    fn new(status: &'static str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+fn handle_ias_request(stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().context("Missing method.")?.to_string();
+    let path = request_line.next().context("Missing path.")?.to_string();
+
+    let mut api_key = None;
+    let mut content_len = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_len = value.trim().parse()?,
+                "ocp-apim-subscription-key" => api_key = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    let mut body = vec![0; content_len];
+    reader.read_exact(&mut body)?;
+
+    let resp = if api_key.as_deref() != Some(MOCK_API_KEY) {
+        IasResponse::new("401 Unauthorized")
+    } else if method == "GET" && path.starts_with("/attestation/v4/sigrl/") {
+        IasResponse::new("200 OK")
+    } else if method == "POST" && path == "/attestation/v4/report" {
+        mock_report(&body)?
+    } else {
+        IasResponse::new("404 Not Found")
+    };
+
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {}\r\n", resp.status)?;
+    for (name, value) in &resp.headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        resp.body.len()
+    )?;
+    stream.write_all(&resp.body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn mock_report(body: &[u8]) -> Result<IasResponse> {
+    let req: JsonValue = serde_json::from_slice(body)?;
+    let quote = req["isvEnclaveQuote"]
+        .as_str()
+        .context("Failed to get isvEnclaveQuote.")?;
+    let report = serde_json::json!({
+        "id": "0",
+        "timestamp": MOCK_REPORT_TIMESTAMP,
+        "version": 4,
+        "isvEnclaveQuoteStatus": "OK",
+        "isvEnclaveQuoteBody": quote,
+    });
+
+    let mut resp = IasResponse::new("200 OK");
+    resp.headers
+        .push(("Content-Type", "application/json".to_string()));
+    resp.headers
+        .push(("X-IASReport-Signature", base64::encode(MOCK_SIGNATURE)));
+    resp.headers.push((
+        "X-IASReport-Signing-Certificate",
+        utf8_percent_encode(MOCK_CERT_CHAIN, NON_ALPHANUMERIC).to_string(),
+    ));
+    resp.body = serde_json::to_vec(&report)?;
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,166 @@
+use super::*;
+use crate::{
+    engine::TEETxEngineWorkerFactory,
+    intel_api::{get_intel_report, get_intel_sigrl},
+};
+use slimchain_common::{
+    encoding::CanonicalEncode,
+    tx::TxTrait,
+    tx_req::{caller_address_from_pk, TxRequest},
+};
+use slimchain_tx_engine::{TxEngine, TxTask, TxTaskOutput};
+use slimchain_tx_state::{MemTxState, TxProposal};
+use slimchain_utils::{
+    contract::{contract_address, Contract, Token},
+    init_tracing_for_test,
+};
+use std::{path::PathBuf, ptr};
+
+fn quote_report_data(report: &[u8]) -> Vec<u8> {
+    let report: JsonValue = serde_json::from_slice(report).unwrap();
+    let quote = base64::decode(report["isvEnclaveQuoteBody"].as_str().unwrap()).unwrap();
+    assert_eq!(size_of::<sgx_quote_t>(), quote.len());
+    let quote: sgx_quote_t = unsafe { ptr::read(quote.as_ptr() as *const _) };
+    quote.report_body.report_data.d.to_vec()
+}
+
+#[test]
+fn test_mock_ias() {
+    let _guard = init_tracing_for_test();
+
+    let ias = MockIas::start();
+    let config = ias.tee_config();
+
+    assert!(get_intel_sigrl([1, 2, 3, 4], &config).unwrap().is_empty());
+
+    let report = get_intel_report(b"quote", &config).unwrap();
+    assert_eq!(MOCK_SIGNATURE, &report.sig[..]);
+    // The Intel root CA is removed from the chain.
+    assert_eq!(1, report.cert.len());
+    let body: JsonValue = serde_json::from_slice(&report.report).unwrap();
+    assert_eq!(Some(MOCK_REPORT_TIMESTAMP), body["timestamp"].as_str());
+    assert_eq!(Some("OK"), body["isvEnclaveQuoteStatus"].as_str());
+    assert_eq!(
+        Some(base64::encode(b"quote").as_str()),
+        body["isvEnclaveQuoteBody"].as_str()
+    );
+
+    let mut wrong_key = config.clone();
+    wrong_key.api_key = "wrong_key".to_string();
+    assert!(get_intel_sigrl([1, 2, 3, 4], &wrong_key).is_err());
+    assert!(get_intel_report(b"quote", &wrong_key).is_err());
+
+    assert_eq!(4, ias.requests());
+}
+
+#[tokio::test]
+async fn test_mock_enclave() {
+    let _guard = init_tracing_for_test();
+
+    let mut states = MemTxState::new();
+
+    let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("contracts/build/contracts/SimpleStorage.json");
+    let contract = Contract::from_json_file(&contract_file).unwrap();
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+    let keypair = Keypair::generate(&mut rng);
+    let caller_address = caller_address_from_pk(&keypair.public);
+    let contract_address = contract_address(caller_address, U256::from(0).into());
+
+    let ias = MockIas::start();
+    let factory =
+        TEETxEngineWorkerFactory::from_backend(ias.tee_config(), Box::new(MockEnclave::new()))
+            .unwrap();
+    // The sigrl and the report, which are skipped in the simulation mode.
+    if !crate::is_sim_mode() {
+        assert_eq!(2, ias.requests());
+    }
+
+    let mut task_engine = TxEngine::new(2, || factory.worker());
+
+    let tx_req1 = TxRequest::Create {
+        nonce: U256::from(0).into(),
+        code: contract.code().clone(),
+        chain_id: 0,
+        expiry: None,
+    };
+    let state_root = states.state_root();
+    let task1 = TxTask::new(
+        states.state_view(),
+        tx_req1.sign(&keypair),
+        move || -> (BlockHeight, u64, H256) { (1.into(), 1_000, state_root) },
+    );
+    task_engine.push_task(task1);
+    let TxTaskOutput {
+        tx_proposal:
+            TxProposal {
+                tx: tx1,
+                write_trie: write_trie1,
+            },
+        ..
+    } = task_engine.pop_result().await;
+    write_trie1.verify(states.state_root()).unwrap();
+    // The report is not signed by Intel, so only the tx signature is verified.
+    tx1.pk_sig.verify(tx1.raw_tx.signing_digest()).unwrap();
+    assert_eq!(1_000, tx1.tx_block_timestamp());
+    assert_eq!(MockEnclave::new().keypair.public, *tx1.pk_sig.public());
+    if !crate::is_sim_mode() {
+        assert_eq!(
+            &tx1.pk_sig.public().as_bytes()[..],
+            &quote_report_data(&tx1.attest_report.report)[..PUBLIC_KEY_LENGTH]
+        );
+    }
+
+    assert!(tx1
+        .raw_tx
+        .writes
+        .get(&contract_address)
+        .unwrap()
+        .values
+        .iter()
+        .any(|(_k, v)| v.to_low_u64_be() == 42));
+    states.apply_writes(&tx1.raw_tx.writes).unwrap();
+
+    let tx_req2 = TxRequest::Call {
+        address: contract_address,
+        nonce: U256::from(1).into(),
+        data: contract
+            .encode_tx_input(
+                "set",
+                &[Token::Uint(U256::from(1)), Token::Uint(U256::from(43))],
+            )
+            .unwrap(),
+        chain_id: 0,
+        expiry: None,
+    };
+    let state_root = states.state_root();
+    let task2 = TxTask::new(
+        states.state_view(),
+        tx_req2.sign(&keypair),
+        move || -> (BlockHeight, u64, H256) { (2.into(), 2_000, state_root) },
+    );
+    task_engine.push_task(task2);
+    let TxTaskOutput {
+        tx_proposal:
+            TxProposal {
+                tx: tx2,
+                write_trie: write_trie2,
+            },
+        ..
+    } = task_engine.pop_result().await;
+    write_trie2.verify(states.state_root()).unwrap();
+    tx2.pk_sig.verify(tx2.raw_tx.signing_digest()).unwrap();
+    assert_eq!(tx1.attest_report, tx2.attest_report);
+
+    assert!(tx2
+        .raw_tx
+        .writes
+        .get(&contract_address)
+        .unwrap()
+        .values
+        .iter()
+        .any(|(_k, v)| v.to_low_u64_be() == 43));
+}
@@ -3,7 +3,7 @@ use slimchain_common::{
     error::Result,
 };
 use slimchain_tx_engine::TxTaskId;
-use std::{cmp::min, ptr::copy_nonoverlapping, slice};
+use std::{cmp::min, ptr::copy_nonoverlapping, slice, time::Instant};
 
 #[inline]
 unsafe fn get_address_from_raw(acc_address: *const u8) -> Address {
@@ -17,6 +17,30 @@ unsafe fn get_state_key_from_raw(acc_address: *const u8) -> StateKey {
     H256::from_slice(buf).into()
 }
 
+// Add the time spent in an OCALL handler to the task. It must be created before the task state
+// is borrowed, so that the borrow is released by the time it is dropped.
+struct OcallTimer {
+    id: TxTaskId,
+    begin: Instant,
+}
+
+impl OcallTimer {
+    fn new(id: u32) -> Self {
+        Self {
+            id: id.into(),
+            begin: Instant::now(),
+        }
+    }
+}
+
+impl Drop for OcallTimer {
+    fn drop(&mut self) {
+        if let Ok(mut task_state) = crate::engine::TaskState::get_task_state(self.id) {
+            task_state.add_ocall_time(self.begin.elapsed());
+        }
+    }
+}
+
 macro_rules! try_run {
     ($x: expr) => {
         match $x {
@@ -36,6 +60,7 @@ macro_rules! try_run {
 
 #[no_mangle]
 pub unsafe extern "C" fn ocall_get_nonce(id: u32, acc_address: *const u8, nonce: *mut u8) -> i32 {
+    let _timer = OcallTimer::new(id);
     let acc_address = get_address_from_raw(acc_address);
     let n = try_run!(get_nonce(id.into(), acc_address));
     let dst = slice::from_raw_parts_mut(nonce, 32);
@@ -55,6 +80,7 @@ pub unsafe extern "C" fn ocall_get_code_len(
     acc_address: *const u8,
     code_len: *mut usize,
 ) -> i32 {
+    let _timer = OcallTimer::new(id);
     let acc_address = get_address_from_raw(acc_address);
     *code_len = try_run!(get_code_len(id.into(), acc_address));
     0
@@ -73,6 +99,7 @@ pub unsafe extern "C" fn ocall_get_code(
     code: *mut u8,
     code_len: usize,
 ) -> i32 {
+    let _timer = OcallTimer::new(id);
     let acc_address = get_address_from_raw(acc_address);
     let c = try_run!(get_code(id.into(), acc_address));
     let len = min(code_len, c.len());
@@ -93,6 +120,7 @@ pub unsafe extern "C" fn ocall_get_value(
     key: *const u8,
     value: *mut u8,
 ) -> i32 {
+    let _timer = OcallTimer::new(id);
     let acc_address = get_address_from_raw(acc_address);
     let key = get_state_key_from_raw(key);
     let v = try_run!(get_value(id.into(), acc_address, key));
@@ -108,6 +136,7 @@ fn get_value(id: TxTaskId, acc_address: Address, key: StateKey) -> Result<StateV
 
 #[no_mangle]
 pub unsafe extern "C" fn ocall_get_read_proof_len(id: u32, proof_len: *mut usize) -> i32 {
+    let _timer = OcallTimer::new(id);
     *proof_len = try_run!(get_read_proof_len(id.into()));
     0
 }
@@ -120,6 +149,7 @@ fn get_read_proof_len(id: TxTaskId) -> Result<usize> {
 
 #[no_mangle]
 pub unsafe extern "C" fn ocall_get_read_proof(id: u32, proof: *mut u8, proof_len: usize) -> i32 {
+    let _timer = OcallTimer::new(id);
     let mut task_state = try_run!(crate::engine::TaskState::get_task_state(id.into()));
     let p = try_run!(task_state.get_read_proof());
     let len = min(proof_len, p.len());
@@ -129,6 +159,7 @@ pub unsafe extern "C" fn ocall_get_read_proof(id: u32, proof: *mut u8, proof_len
 
 #[no_mangle]
 pub unsafe extern "C" fn ocall_return_result(id: u32, result: *const u8, result_len: usize) -> i32 {
+    let _timer = OcallTimer::new(id);
     let mut task_state = try_run!(crate::engine::TaskState::get_task_state(id.into()));
     let signed_tx = {
         let buf = slice::from_raw_parts(result, result_len);
@@ -137,3 +168,19 @@ pub unsafe extern "C" fn ocall_return_result(id: u32, result: *const u8, result_
     task_state.set_result(signed_tx);
     0
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn ocall_report_metrics(
+    id: u32,
+    metrics: *const u8,
+    metrics_len: usize,
+) -> i32 {
+    let _timer = OcallTimer::new(id);
+    let mut task_state = try_run!(crate::engine::TaskState::get_task_state(id.into()));
+    let metrics = {
+        let buf = slice::from_raw_parts(metrics, metrics_len);
+        try_run!(postcard::from_bytes(buf))
+    };
+    task_state.set_enclave_metrics(metrics);
+    0
+}
@@ -40,6 +40,8 @@ async fn test() {
     let tx_req1 = TxRequest::Create {
         nonce: U256::from(0).into(),
         code: contract.code().clone(),
+        chain_id: 0,
+        expiry: None,
     };
     let signed_tx_req1 = tx_req1.sign(&keypair);
 
@@ -47,7 +49,7 @@ async fn test() {
     let task1 = TxTask::new(
         states.state_view(),
         signed_tx_req1,
-        move || -> (BlockHeight, H256) { (1.into(), state_root) },
+        move || -> (BlockHeight, u64, H256) { (1.into(), 1_000, state_root) },
     );
     task_engine.push_task(task1);
     let TxTaskOutput {
@@ -60,6 +62,7 @@ async fn test() {
     } = task_engine.pop_result().await;
     write_trie1.verify(states.state_root()).unwrap();
     tx1.verify_sig().unwrap();
+    assert_eq!(1_000, tx1.tx_block_timestamp());
 
     assert!(tx1
         .raw_tx
@@ -80,6 +83,8 @@ async fn test() {
                 &[Token::Uint(U256::from(1)), Token::Uint(U256::from(43))],
             )
             .unwrap(),
+        chain_id: 0,
+        expiry: None,
     };
     let signed_tx_req2 = tx_req2.sign(&keypair);
 
@@ -87,7 +92,7 @@ async fn test() {
     let task2 = TxTask::new(
         states.state_view(),
         signed_tx_req2,
-        move || -> (BlockHeight, H256) { (2.into(), state_root) },
+        move || -> (BlockHeight, u64, H256) { (2.into(), 2_000, state_root) },
     );
     task_engine.push_task(task2);
     let TxTaskOutput {
@@ -110,3 +115,49 @@ async fn test() {
         .iter()
         .any(|(_k, v)| v.to_low_u64_be() == 43));
 }
+
+#[tokio::test]
+async fn test_concurrent_ecalls() {
+    let _guard = init_tracing_for_test();
+
+    let states = MemTxState::new();
+    let contract_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("contracts/build/contracts/SimpleStorage.json");
+    let contract = Contract::from_json_file(&contract_file).unwrap();
+
+    // More workers than the threads allowed in the enclave, so that some of them have to wait.
+    let cfg = Config::load_test().unwrap();
+    let mut tee_cfg: TEEConfig = cfg.get("tee").unwrap();
+    tee_cfg.enclave_threads = Some(2);
+    let factory = TEETxEngineWorkerFactory::use_test(tee_cfg).unwrap();
+    let mut task_engine = TxEngine::new(8, || factory.worker());
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1u64);
+    let state_root = states.state_root();
+    for _ in 0..32 {
+        let tx_req = TxRequest::Create {
+            nonce: U256::from(0).into(),
+            code: contract.code().clone(),
+            chain_id: 0,
+            expiry: None,
+        };
+        let task = TxTask::new(
+            states.state_view(),
+            tx_req.sign(&Keypair::generate(&mut rng)),
+            move || -> (BlockHeight, u64, H256) { (1.into(), 1_000, state_root) },
+        );
+        task_engine.push_task(task);
+    }
+
+    for _ in 0..32 {
+        let TxTaskOutput {
+            tx_proposal: TxProposal { tx, write_trie },
+            ..
+        } = task_engine.pop_result().await;
+        write_trie.verify(state_root).unwrap();
+        tx.verify_sig().unwrap();
+    }
+    assert_eq!(task_engine.remaining_tasks(), 0);
+}
@@ -8,6 +8,7 @@ use crossbeam::{
     sync::{Parker, Unparker},
     utils::Backoff,
 };
+use serde::Deserialize;
 use slimchain_common::{
     basic::{BlockHeight, H256},
     create_id_type_u32,
@@ -15,13 +16,13 @@ use slimchain_common::{
     tx::TxTrait,
     tx_req::SignedTxRequest,
 };
-use slimchain_tx_state::{TxProposal, TxStateView, TxWriteSetTrie};
-use slimchain_utils::{record_event, record_time};
+use slimchain_tx_state::{ReadProofCache, TxProposal, TxStateView, TxWriteSetTrie};
+use slimchain_utils::{record_event, record_time, serde::serialized_size};
 use std::{
     iter,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     task::{Context, Poll},
     thread::{self, JoinHandle},
@@ -34,10 +35,15 @@ create_id_type_u32!(TxTaskId);
 pub trait TxEngineWorker: Send {
     type Output: TxTrait;
 
+    // The tx is executed on top of the block at `block_height`, whose timestamp (in ms) is
+    // `block_timestamp`.
+    #[allow(clippy::too_many_arguments)]
     fn execute(
         &self,
         id: TxTaskId,
+        chain_id: u64,
         block_height: BlockHeight,
+        block_timestamp: u64,
         state_view: Arc<dyn TxStateView + Sync + Send>,
         state_root: H256,
         signed_tx_req: SignedTxRequest,
@@ -46,27 +52,38 @@ pub trait TxEngineWorker: Send {
 
 pub struct TxTask {
     id: TxTaskId,
+    chain_id: u64,
     state_view: Arc<dyn TxStateView + Sync + Send>,
     signed_tx_req: SignedTxRequest,
-    block_state_fn: Box<dyn FnOnce() -> (BlockHeight, H256) + Sync + Send>,
+    // The height, the timestamp (in ms) and the state root of the block to execute on.
+    block_state_fn: Box<dyn FnOnce() -> (BlockHeight, u64, H256) + Sync + Send>,
+    // The span submitting the task, so that its execution joins the same trace.
+    span: tracing::Span,
 }
 
 impl TxTask {
     pub fn new(
         state_view: Arc<dyn TxStateView + Sync + Send>,
         signed_tx_req: SignedTxRequest,
-        block_state_fn: impl FnOnce() -> (BlockHeight, H256) + Sync + Send + 'static,
+        block_state_fn: impl FnOnce() -> (BlockHeight, u64, H256) + Sync + Send + 'static,
     ) -> Self {
         let id = TxTaskId::next_id();
 
         Self {
             id,
+            chain_id: 0,
             state_view,
             signed_tx_req,
             block_state_fn: Box::new(block_state_fn),
+            span: tracing::Span::current(),
         }
     }
 
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
     pub fn get_id(&self) -> TxTaskId {
         self.id
     }
@@ -77,6 +94,76 @@ pub struct TxTaskOutput<Tx: TxTrait> {
     pub tx_proposal: TxProposal<Tx>,
 }
 
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct TxEngineConfig {
+    /// Number of workers taking tasks, capped by the spawned ones. All of them if missing.
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Max number of the proof paths cached for building the write set tries. 0 to disable it.
+    #[serde(default)]
+    pub proof_cache: usize,
+    /// Number of threads building the write set tries after the execution. 0 to build them in
+    /// the workers.
+    #[serde(default)]
+    pub write_trie_threads: usize,
+}
+
+// Limit the number of workers taking tasks, without respawning them. The workers beyond the limit
+// park until the limit is raised, while their queued tasks are stolen by the others.
+#[derive(Debug, Clone)]
+pub struct TxEngineThreadLimit {
+    max: usize,
+    active: Arc<AtomicUsize>,
+    idle_unparkers: Arc<Mutex<Vec<Unparker>>>,
+}
+
+impl TxEngineThreadLimit {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            active: Arc::new(AtomicUsize::new(max)),
+            idle_unparkers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn get(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    // The limit is capped by the number of spawned workers.
+    pub fn set(&self, threads: usize) {
+        let threads = threads.clamp(1, self.max);
+        if self.active.swap(threads, Ordering::AcqRel) != threads {
+            info!("TxEngine: {} of {} workers are active.", threads, self.max);
+        }
+        self.wake_idle_workers();
+    }
+
+    pub fn apply(&self, cfg: &TxEngineConfig) {
+        self.set(cfg.threads.unwrap_or(self.max));
+    }
+
+    fn is_active(&self, index: usize) -> bool {
+        index < self.get()
+    }
+
+    fn wake_idle_workers(&self) {
+        let unparkers = std::mem::take(
+            &mut *self
+                .idle_unparkers
+                .lock()
+                .expect("TxEngine: Failed to lock idle unparkers."),
+        );
+        for unparker in unparkers {
+            unparker.unpark();
+        }
+    }
+}
+
 pub struct TxEngine<Tx: TxTrait + 'static> {
     task_queue: Arc<Injector<TxTask>>,
     result_rx: UnboundedReceiver<TxTaskOutput<Tx>>,
@@ -84,13 +171,30 @@ pub struct TxEngine<Tx: TxTrait + 'static> {
     shutdown_flag: Arc<AtomicBool>,
     worker_threads: Vec<JoinHandle<()>>,
     remaining_tasks: Arc<AtomicUsize>,
+    thread_limit: TxEngineThreadLimit,
+    proof_cache: Arc<ReadProofCache>,
+    write_trie_threads: Vec<JoinHandle<()>>,
 }
 
 impl<Tx: TxTrait + 'static> TxEngine<Tx> {
-    #[tracing::instrument(name = "tx_engine_init", skip(threads, worker_factory))]
     pub fn new(
         threads: usize,
         worker_factory: impl Fn() -> Box<dyn TxEngineWorker<Output = Tx>>,
+    ) -> Self {
+        Self::new_with_write_trie_threads(threads, 0, worker_factory)
+    }
+
+    // Same as `new`, but the write set tries are built by a separate pool of
+    // `write_trie_threads` threads, so that the workers move on to the next tx as soon as the
+    // execution finishes. With 0 threads, they are built by the workers themselves.
+    #[tracing::instrument(
+        name = "tx_engine_init",
+        skip(threads, write_trie_threads, worker_factory)
+    )]
+    pub fn new_with_write_trie_threads(
+        threads: usize,
+        write_trie_threads: usize,
+        worker_factory: impl Fn() -> Box<dyn TxEngineWorker<Output = Tx>>,
     ) -> Self {
         info!("Spawning TxEngine workers in {} threads.", threads);
 
@@ -99,10 +203,41 @@ impl<Tx: TxTrait + 'static> TxEngine<Tx> {
         let unparker_queue = Arc::new(ArrayQueue::new(threads));
         let shutdown_flag = Arc::new(AtomicBool::new(false));
         let remaining_tasks = Arc::new(AtomicUsize::new(0));
+        let thread_limit = TxEngineThreadLimit::new(threads);
+        let proof_cache = Arc::new(ReadProofCache::default());
+
+        let (write_trie_tx, write_trie_threads) = if write_trie_threads > 0 {
+            info!(
+                "Spawning TxEngine write trie builders in {} threads.",
+                write_trie_threads
+            );
+            let (write_trie_tx, write_trie_rx) = channel::unbounded::<WriteTrieTask<Tx>>();
+            let handles = (0..write_trie_threads)
+                .map(|_| {
+                    let write_trie_rx = write_trie_rx.clone();
+                    let stage = WriteTrieStage {
+                        result_tx: result_tx.clone(),
+                        shutdown_flag: shutdown_flag.clone(),
+                        remaining_tasks: remaining_tasks.clone(),
+                        proof_cache: proof_cache.clone(),
+                    };
+                    thread::spawn(move || {
+                        // Exit once all the workers are gone.
+                        for task in write_trie_rx {
+                            stage.run(task);
+                        }
+                    })
+                })
+                .collect();
+            (Some(write_trie_tx), handles)
+        } else {
+            (None, Vec::new())
+        };
 
         let mut workers: Vec<_> = (0..threads)
-            .map(|_| {
+            .map(|index| {
                 TxEngineWorkerInstance::new(
+                    index,
                     worker_factory(),
                     task_queue.clone(),
                     threads - 1,
@@ -110,9 +245,13 @@ impl<Tx: TxTrait + 'static> TxEngine<Tx> {
                     unparker_queue.clone(),
                     shutdown_flag.clone(),
                     remaining_tasks.clone(),
+                    thread_limit.clone(),
+                    proof_cache.clone(),
+                    write_trie_tx.clone(),
                 )
             })
             .collect();
+        drop(write_trie_tx);
 
         let stealers: Vec<_> = workers.iter().map(|w| w.get_local_stealer()).collect();
 
@@ -136,9 +275,21 @@ impl<Tx: TxTrait + 'static> TxEngine<Tx> {
             shutdown_flag,
             worker_threads,
             remaining_tasks,
+            thread_limit,
+            proof_cache,
+            write_trie_threads,
         }
     }
 
+    pub fn thread_limit(&self) -> TxEngineThreadLimit {
+        self.thread_limit.clone()
+    }
+
+    // Disabled until a capacity is set, e.g., from `TxEngineConfig::proof_cache`.
+    pub fn proof_cache(&self) -> Arc<ReadProofCache> {
+        self.proof_cache.clone()
+    }
+
     pub fn remaining_tasks(&self) -> usize {
         self.remaining_tasks.load(Ordering::SeqCst)
     }
@@ -190,11 +341,13 @@ impl<Tx: TxTrait + 'static> Drop for TxEngine<Tx> {
         self.shutdown_flag.store(true, Ordering::Release);
 
         let unparker_queue = self.unparker_queue.clone();
+        let thread_limit = self.thread_limit.clone();
         let (tx, rx) = channel::bounded(1);
         let unparker_thread = thread::spawn(move || loop {
             while let Some(unpacker) = unparker_queue.pop() {
                 unpacker.unpark();
             }
+            thread_limit.wake_idle_workers();
 
             if rx.try_recv().is_ok() {
                 break;
@@ -208,6 +361,11 @@ impl<Tx: TxTrait + 'static> Drop for TxEngine<Tx> {
             w.join()
                 .expect("TxEngine: Failed to join the worker thread.");
         }
+        // The builders stop once the workers holding the senders are joined.
+        for w in self.write_trie_threads.drain(..) {
+            w.join()
+                .expect("TxEngine: Failed to join the write trie thread.");
+        }
 
         tx.send(()).ok();
         unparker_thread
@@ -218,18 +376,23 @@ impl<Tx: TxTrait + 'static> Drop for TxEngine<Tx> {
 }
 
 struct TxEngineWorkerInstance<Tx: TxTrait> {
+    index: usize,
     global_task_queue: Arc<Injector<TxTask>>,
     local_task_queue: Worker<TxTask>,
     stealers: Vec<Stealer<TxTask>>,
-    result_tx: UnboundedSender<TxTaskOutput<Tx>>,
     unparker_queue: Arc<ArrayQueue<Unparker>>,
     shutdown_flag: Arc<AtomicBool>,
     remaining_tasks: Arc<AtomicUsize>,
+    thread_limit: TxEngineThreadLimit,
+    stage: WriteTrieStage<Tx>,
+    write_trie_tx: Option<channel::Sender<WriteTrieTask<Tx>>>,
     worker: Box<dyn TxEngineWorker<Output = Tx>>,
 }
 
 impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
+        index: usize,
         worker: Box<dyn TxEngineWorker<Output = Tx>>,
         global_task_queue: Arc<Injector<TxTask>>,
         stealer_num: usize,
@@ -237,17 +400,29 @@ impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
         unparker_queue: Arc<ArrayQueue<Unparker>>,
         shutdown_flag: Arc<AtomicBool>,
         remaining_tasks: Arc<AtomicUsize>,
+        thread_limit: TxEngineThreadLimit,
+        proof_cache: Arc<ReadProofCache>,
+        write_trie_tx: Option<channel::Sender<WriteTrieTask<Tx>>>,
     ) -> Self {
         let local_task_queue = Worker::new_fifo();
+        let stage = WriteTrieStage {
+            result_tx,
+            shutdown_flag: shutdown_flag.clone(),
+            remaining_tasks: remaining_tasks.clone(),
+            proof_cache,
+        };
 
         Self {
+            index,
             global_task_queue,
             local_task_queue,
             stealers: Vec::with_capacity(stealer_num),
-            result_tx,
             unparker_queue,
             shutdown_flag,
             remaining_tasks,
+            thread_limit,
+            stage,
+            write_trie_tx,
             worker,
         }
     }
@@ -272,6 +447,33 @@ impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
         })
     }
 
+    // Park until the worker is back under the thread limit. The limit is checked again after the
+    // unparker is registered, so that a concurrent raise is not missed.
+    fn wait_until_active(&self) {
+        // The worker may have been woken up for a new task. Pass it on to an active one.
+        if let Some(unparker) = self.unparker_queue.pop() {
+            unparker.unpark();
+        }
+
+        while !self.thread_limit.is_active(self.index) {
+            if self.shutdown_flag.load(Ordering::Acquire) {
+                return;
+            }
+
+            let parker = Parker::new();
+            self.thread_limit
+                .idle_unparkers
+                .lock()
+                .expect("TxEngine: Failed to lock idle unparkers.")
+                .push(parker.unparker().clone());
+            if self.thread_limit.is_active(self.index) || self.shutdown_flag.load(Ordering::Acquire)
+            {
+                return;
+            }
+            parker.park();
+        }
+    }
+
     fn wait_until_task(&self) -> Option<TxTask> {
         if self.shutdown_flag.load(Ordering::Acquire) {
             return None;
@@ -279,6 +481,14 @@ impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
 
         let backoff = Backoff::new();
         loop {
+            if !self.thread_limit.is_active(self.index) {
+                self.wait_until_active();
+                if self.shutdown_flag.load(Ordering::Acquire) {
+                    return None;
+                }
+                backoff.reset();
+            }
+
             match self.find_task() {
                 Some(task) => return Some(task),
                 None => {
@@ -302,17 +512,19 @@ impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
 
     fn run(&self) {
         while let Some(task) = self.wait_until_task() {
-            let span = debug_span!("execute_task", id = task.id.0);
+            let span = debug_span!(parent: &task.span, "execute_task", id = task.id.0);
             let _enter = span.enter();
 
             let begin = Instant::now();
             let task_id = task.get_id();
             let tx_id = task.signed_tx_req.id();
             let state_view = task.state_view.clone();
-            let (block_height, state_root) = (task.block_state_fn)();
+            let (block_height, block_timestamp, state_root) = (task.block_state_fn)();
             let tx = match self.worker.execute(
                 task.id,
+                task.chain_id,
                 block_height,
+                block_timestamp,
                 task.state_view,
                 state_root,
                 task.signed_tx_req,
@@ -325,22 +537,95 @@ impl<Tx: TxTrait> TxEngineWorkerInstance<Tx> {
                     continue;
                 }
             };
-            let write_trie = match TxWriteSetTrie::new(&state_view, state_root, tx.tx_writes()) {
-                Ok(trie) => trie,
-                Err(e) => {
-                    error!("Failed to create TxWriteSetTrie. Error: {}", e);
-                    record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_exec_error_write_set_failure", "detail": std::format!("{}", e));
-                    self.remaining_tasks.fetch_sub(1, Ordering::SeqCst);
-                    continue;
-                }
+            let task = WriteTrieTask {
+                task_id,
+                tx_id,
+                tx,
+                state_view,
+                block_height,
+                state_root,
+                begin,
+                span: span.clone(),
             };
-            record_time!("exec_time", Instant::now() - begin, "task_id": task_id.0, "tx_id": tx_id, "exec_block_height": block_height.0);
-            self.result_tx
-                .send(TxTaskOutput {
-                    task_id,
-                    tx_proposal: TxProposal::new(tx, write_trie),
-                })
-                .ok();
+            match &self.write_trie_tx {
+                Some(write_trie_tx) => {
+                    if let Err(channel::SendError(task)) = write_trie_tx.send(task) {
+                        self.stage.run(task);
+                    }
+                }
+                None => self.stage.run(task),
+            }
         }
     }
 }
+
+// An executed tx waiting for its write set trie.
+struct WriteTrieTask<Tx: TxTrait> {
+    task_id: TxTaskId,
+    tx_id: H256,
+    tx: Tx,
+    state_view: Arc<dyn TxStateView + Sync + Send>,
+    block_height: BlockHeight,
+    state_root: H256,
+    // When the execution began, so that `exec_time` covers both stages.
+    begin: Instant,
+    span: tracing::Span,
+}
+
+// Build the write set trie of an executed tx and send out the tx proposal. Loading the trie
+// nodes is IO bound, so it may run in its own threads to keep the workers busy executing.
+struct WriteTrieStage<Tx: TxTrait> {
+    result_tx: UnboundedSender<TxTaskOutput<Tx>>,
+    shutdown_flag: Arc<AtomicBool>,
+    remaining_tasks: Arc<AtomicUsize>,
+    proof_cache: Arc<ReadProofCache>,
+}
+
+impl<Tx: TxTrait> WriteTrieStage<Tx> {
+    fn run(&self, task: WriteTrieTask<Tx>) {
+        let WriteTrieTask {
+            task_id,
+            tx_id,
+            tx,
+            state_view,
+            block_height,
+            state_root,
+            begin,
+            span,
+        } = task;
+        let _enter = span.enter();
+
+        if self.shutdown_flag.load(Ordering::Acquire) {
+            self.remaining_tasks.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+
+        let write_trie = if self.proof_cache.is_enabled() {
+            TxWriteSetTrie::new_with_cache(
+                &state_view,
+                state_root,
+                tx.tx_writes(),
+                &self.proof_cache,
+            )
+        } else {
+            TxWriteSetTrie::new(&state_view, state_root, tx.tx_writes())
+        };
+        let write_trie = match write_trie {
+            Ok(trie) => trie,
+            Err(e) => {
+                error!("Failed to create TxWriteSetTrie. Error: {}", e);
+                record_event!("discard_tx", "tx_id": tx_id, "reason": "tx_exec_error_write_set_failure", "detail": std::format!("{}", e));
+                self.remaining_tasks.fetch_sub(1, Ordering::SeqCst);
+                return;
+            }
+        };
+        let proof_size = serialized_size(&write_trie).unwrap_or_default();
+        record_time!("exec_time", Instant::now() - begin, "task_id": task_id.0, "tx_id": tx_id, "exec_block_height": block_height.0, "proof_size": proof_size);
+        self.result_tx
+            .send(TxTaskOutput {
+                task_id,
+                tx_proposal: TxProposal::new(tx, write_trie),
+            })
+            .ok();
+    }
+}
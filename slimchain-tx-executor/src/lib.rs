@@ -4,13 +4,15 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 use core::cell::{Cell, RefCell};
 use evm::backend::Backend as _;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
-    basic::{Address, Code, Nonce, StateKey, StateValue, H160, H256, U256},
-    error::{ensure, Context as _, Error, Result},
+    basic::{Address, BlockHeight, Code, Nonce, StateKey, StateValue, H160, H256, U256},
+    collections::HashMap,
+    digest::Digestible,
+    error::{ensure, Context as _, Error, ExecutionError, Result},
     rw_set::{TxReadData, TxWriteData},
     tx_req::{SignedTxRequest, TxRequest},
 };
@@ -23,15 +25,25 @@ pub trait Backend {
 
 struct EVMBackend<'a, B: Backend> {
     backend: &'a B,
+    chain_id: u64,
+    block_height: BlockHeight,
+    // In ms, as in the block header.
+    block_timestamp: u64,
     reads: RefCell<TxReadData>,
+    // The codes loaded, whose hashes are what go into the reads.
+    codes: RefCell<HashMap<Address, Code>>,
     error: Cell<Option<Error>>,
 }
 
 impl<'a, B: Backend> EVMBackend<'a, B> {
-    fn new(backend: &'a B) -> Self {
+    fn new(backend: &'a B, chain_id: u64, block_height: BlockHeight, block_timestamp: u64) -> Self {
         Self {
             backend,
+            chain_id,
+            block_height,
+            block_timestamp,
             reads: RefCell::new(TxReadData::default()),
+            codes: RefCell::new(HashMap::new()),
             error: Cell::new(None),
         }
     }
@@ -64,16 +76,22 @@ impl<'a, B: Backend> EVMBackend<'a, B> {
     }
 
     fn get_code(&self, acc_address: Address) -> Code {
+        if let Some(code) = self.codes.borrow().get(&acc_address) {
+            return code.clone();
+        }
+
+        let code = match self.backend.get_code(acc_address) {
+            Ok(code) => code,
+            Err(err) => {
+                self.set_error(err);
+                Default::default()
+            }
+        };
         self.reads
             .borrow_mut()
-            .get_or_add_code(acc_address, || match self.backend.get_code(acc_address) {
-                Ok(code) => code,
-                Err(err) => {
-                    self.set_error(err);
-                    Default::default()
-                }
-            })
-            .clone()
+            .add_code_hash(acc_address, code.to_digest());
+        self.codes.borrow_mut().insert(acc_address, code.clone());
+        code
     }
 
     fn get_value(&self, acc_address: Address, key: StateKey) -> StateValue {
@@ -102,13 +120,14 @@ impl<'a, B: Backend> evm::backend::Backend for EVMBackend<'a, B> {
         unimplemented!();
     }
     fn block_number(&self) -> U256 {
-        unimplemented!();
+        U256::from(self.block_height.0)
     }
     fn block_coinbase(&self) -> H160 {
         unimplemented!();
     }
     fn block_timestamp(&self) -> U256 {
-        unimplemented!();
+        // The EVM counts in seconds.
+        U256::from(self.block_timestamp / 1_000)
     }
     fn block_difficulty(&self) -> U256 {
         unimplemented!();
@@ -120,7 +139,7 @@ impl<'a, B: Backend> evm::backend::Backend for EVMBackend<'a, B> {
         U256::zero()
     }
     fn chain_id(&self) -> U256 {
-        unimplemented!();
+        U256::from(self.chain_id)
     }
     fn exists(&self, _address: H160) -> bool {
         true
@@ -155,15 +174,28 @@ pub struct ExecuteOutput {
     pub writes: TxWriteData,
 }
 
-pub fn execute_tx(signed_tx_req: SignedTxRequest, backend: &impl Backend) -> Result<ExecuteOutput> {
+// The tx is executed on top of the block at `block_height`, whose timestamp (in ms) is
+// `block_timestamp`. Hence, it can be included no earlier than the next block.
+pub fn execute_tx(
+    signed_tx_req: SignedTxRequest,
+    chain_id: u64,
+    block_height: BlockHeight,
+    block_timestamp: u64,
+    backend: &impl Backend,
+) -> Result<ExecuteOutput> {
     use evm::executor::stack::*;
 
-    signed_tx_req.verify().context("Invalid signature.")?;
+    // Not `?`, as the typed errors only convert into `Error` with std.
+    signed_tx_req.verify().map_err(Error::msg)?;
+    signed_tx_req
+        .input
+        .check_replay(chain_id, block_height.next_height())
+        .map_err(Error::msg)?;
 
     let caller = signed_tx_req.caller_address();
     let tx_req = signed_tx_req.input;
 
-    let evm_backend = EVMBackend::new(backend);
+    let evm_backend = EVMBackend::new(backend, chain_id, block_height, block_timestamp);
     let evm_config = evm::Config::istanbul();
     let evm_metadata = StackSubstateMetadata::new(u64::max_value(), &&evm_config);
     let evm_state = MemoryStackState::new(evm_metadata, &evm_backend);
@@ -208,8 +240,7 @@ pub fn execute_tx(signed_tx_req: SignedTxRequest, backend: &impl Backend) -> Res
 
     ensure!(
         execute_result.is_succeed(),
-        "Failed to execute the tx (reason: {:?}).",
-        execute_result
+        ExecutionError::Failed(format!("{:?}", execute_result))
     );
 
     let mut reads = evm_backend.take_reads();
@@ -258,3 +289,43 @@ pub fn execute_tx(signed_tx_req: SignedTxRequest, backend: &impl Backend) -> Res
         writes,
     })
 }
+
+// Run the call on the state without a signed tx, e.g., to query a view function. Only the
+// returned data is kept, and the writes, if any, are dropped.
+pub fn call_view(
+    caller: Address,
+    address: Address,
+    data: Vec<u8>,
+    chain_id: u64,
+    block_height: BlockHeight,
+    block_timestamp: u64,
+    backend: &impl Backend,
+) -> Result<Vec<u8>> {
+    use evm::executor::stack::*;
+
+    let evm_backend = EVMBackend::new(backend, chain_id, block_height, block_timestamp);
+    let evm_config = evm::Config::istanbul();
+    let evm_metadata = StackSubstateMetadata::new(u64::max_value(), &&evm_config);
+    let evm_state = MemoryStackState::new(evm_metadata, &evm_backend);
+    let mut executor = StackExecutor::new_with_precompiles(evm_state, &evm_config, &());
+
+    let (execute_result, output) = executor.transact_call(
+        caller.into(),
+        address.into(),
+        U256::zero(),
+        data,
+        u64::max_value(),
+        Vec::new(),
+    );
+
+    evm_backend
+        .check_error()
+        .context("Error when accessing the backend.")?;
+
+    ensure!(
+        execute_result.is_succeed(),
+        ExecutionError::Failed(format!("{:?}", execute_result))
+    );
+
+    Ok(output)
+}
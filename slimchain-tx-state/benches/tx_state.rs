@@ -0,0 +1,137 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use slimchain_common::{
+    basic::{Address, Nonce, StateKey, StateValue, H160, U256},
+    digest::Digestible,
+    rw_set::TxWriteData,
+};
+use slimchain_tx_state::*;
+use std::sync::Arc;
+
+// The fixture state has 100k state values in total.
+const NUM_ACCOUNTS: u64 = 1_000;
+const VALUES_PER_ACCOUNT: u64 = 100;
+// A block touches one account per tx, with two state values each.
+const BLOCK_TXS: u64 = 100;
+const VALUES_PER_TX: u64 = 2;
+
+fn address(i: u64) -> Address {
+    H160::from_low_u64_be(i + 1).into()
+}
+
+fn state_key(i: u64) -> StateKey {
+    i.to_digest().into()
+}
+
+fn nonce(i: u64) -> Nonce {
+    U256::from(i).into()
+}
+
+fn create_state() -> Arc<MemTxState> {
+    let mut writes = TxWriteData::default();
+    for acc in 0..NUM_ACCOUNTS {
+        writes.add_nonce(address(acc), nonce(1));
+        for k in 0..VALUES_PER_ACCOUNT {
+            writes.add_value(address(acc), state_key(k), (k + 1).into());
+        }
+    }
+
+    let mut state = MemTxState::new();
+    let update = update_tx_state(&state.state_view(), state.state_root(), &writes).unwrap();
+    state.apply_update(update).unwrap();
+    state
+}
+
+// The accounts touched by the block `blk`. Different blocks touch different accounts.
+fn block_accounts(blk: u64) -> impl Iterator<Item = Address> {
+    let step = NUM_ACCOUNTS / BLOCK_TXS;
+    (0..BLOCK_TXS).map(move |i| address(i * step + blk))
+}
+
+fn block_writes(blk: u64) -> TxWriteData {
+    let mut writes = TxWriteData::default();
+    for acc in block_accounts(blk) {
+        writes.add_nonce(acc, nonce(2));
+        for k in 0..VALUES_PER_TX {
+            writes.add_value(acc, state_key(k), StateValue::from(blk + k + 2));
+        }
+    }
+    writes
+}
+
+fn bench_update(c: &mut Criterion) {
+    let state = create_state();
+    let writes = block_writes(1);
+    c.bench_function("tx_state_update", |b| {
+        b.iter(|| {
+            black_box(update_tx_state(&state.state_view(), state.state_root(), &writes).unwrap())
+        })
+    });
+}
+
+fn bench_read_proof(c: &mut Criterion) {
+    let state = create_state();
+    c.bench_function("tx_state_read_proof", |b| {
+        b.iter(|| {
+            let mut ctx = TxStateReadContext::new(state.state_view(), state.state_root());
+            for acc in block_accounts(1) {
+                ctx.get_nonce(acc).unwrap();
+                for k in 0..VALUES_PER_TX {
+                    ctx.get_value(acc, state_key(k)).unwrap();
+                }
+            }
+            black_box(ctx.generate_proof().unwrap())
+        })
+    });
+}
+
+// A client holds the partial tries of the previous block, and then verifies the next one.
+fn bench_tx_trie(c: &mut Criterion) {
+    let state = create_state();
+    let root = state.state_root();
+
+    let prev_write_trie = TxWriteSetTrie::new(&state.state_view(), root, &block_writes(0)).unwrap();
+    let mut client = TxTrie::from_root_hash(root);
+    let prev_diff = client.diff_missing_branches(&prev_write_trie);
+    client.apply_diff(&prev_diff, true).unwrap();
+
+    let writes = block_writes(1);
+    let write_trie = TxWriteSetTrie::new(&state.state_view(), root, &writes).unwrap();
+    let diff = client.diff_missing_branches(&write_trie);
+    let mut applied = client.clone();
+    applied.apply_diff(&diff, true).unwrap();
+
+    let mut group = c.benchmark_group("tx_trie");
+    group.bench_function("write_set_trie", |b| {
+        b.iter(|| black_box(TxWriteSetTrie::new(&state.state_view(), root, &writes).unwrap()))
+    });
+    group.bench_function("verify_write_set_trie", |b| {
+        b.iter(|| write_trie.verify(root).unwrap())
+    });
+    group.bench_function("diff", |b| {
+        b.iter(|| black_box(client.diff_missing_branches(&write_trie)))
+    });
+    group.bench_function("apply_diff", |b| {
+        b.iter_batched(
+            || client.clone(),
+            |mut trie| {
+                trie.apply_diff(&diff, true).unwrap();
+                trie
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("apply_writes", |b| {
+        b.iter_batched(
+            || applied.clone(),
+            |mut trie| {
+                black_box(trie.apply_writes(&writes).unwrap());
+                trie
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_update, bench_read_proof, bench_tx_trie);
+criterion_main!(benches);
@@ -2,7 +2,7 @@ use crate::view::TxStateView;
 #[cfg(feature = "write")]
 use crate::write::TxStateUpdate;
 use slimchain_common::{
-    basic::{AccountData, Address, StateValue, H256},
+    basic::{AccountData, Address, Code, StateValue, H256},
     collections::HashMap,
     error::{Context as _, Result},
     rw_set::TxWriteData,
@@ -15,6 +15,7 @@ pub struct MemTxStateInternal {
     pub state_root: H256,
     pub acc_nodes: HashMap<H256, TrieNode<AccountData>>,
     pub state_nodes: HashMap<Address, HashMap<H256, TrieNode<StateValue>>>,
+    pub codes: HashMap<H256, Code>,
 }
 
 pub struct MemTxState(RwLock<MemTxStateInternal>);
@@ -43,6 +44,7 @@ impl MemTxState {
             root,
             acc_nodes,
             state_nodes,
+            codes,
         } = update;
         let mut internal = self.0.write().expect("Failed to lock MemTxState.");
         internal.state_root = root;
@@ -54,6 +56,7 @@ impl MemTxState {
                 .or_default()
                 .extend(nodes.into_iter());
         }
+        internal.codes.extend(codes.into_iter());
         Ok(())
     }
 
@@ -88,4 +91,12 @@ impl TxStateView for MemTxState {
             .cloned()
             .context("Unknown node")
     }
+    fn code(&self, code_hash: H256) -> Result<Code> {
+        let internal = self.get_internal();
+        internal
+            .codes
+            .get(&code_hash)
+            .cloned()
+            .context("Unknown code")
+    }
 }
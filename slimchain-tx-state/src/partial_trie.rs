@@ -13,5 +13,8 @@ pub use storage_tx_trie::*;
 pub mod diff;
 pub use diff::*;
 
+pub mod proof_cache;
+pub use proof_cache::*;
+
 pub mod tx_proposal;
 pub use tx_proposal::*;
@@ -0,0 +1,168 @@
+use crate::view::{
+    trie_view::{AccountTrieView, StateTrieView},
+    TxStateView,
+};
+use alloc::collections::VecDeque;
+use slimchain_common::{
+    basic::{AccountData, Address, StateKey, H256},
+    collections::HashMap,
+    error::Result,
+};
+use slimchain_merkle_trie::prelude::*;
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Mutex,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum PathKey {
+    Account(H256, Address),
+    Value(H256, StateKey),
+}
+
+#[derive(Debug, Default)]
+struct ProofPaths {
+    accounts: HashMap<(H256, Address), (Option<AccountData>, PartialTrie)>,
+    values: HashMap<(H256, StateKey), PartialTrie>,
+    order: VecDeque<PathKey>,
+}
+
+impl ProofPaths {
+    fn len(&self) -> usize {
+        self.accounts.len() + self.values.len()
+    }
+
+    fn evict_to(&mut self, capacity: usize) {
+        while self.len() > capacity {
+            match self.order.pop_front() {
+                Some(PathKey::Account(root, address)) => {
+                    self.accounts.remove(&(root, address));
+                }
+                Some(PathKey::Value(root, key)) => {
+                    self.values.remove(&(root, key));
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+// The proof paths of the recently read keys, keyed by the trie root and the key. As the trie
+// nodes are content addressed, a path is reused as long as its root stays the same, i.e., by the
+// txs executed on the same block, and across the blocks for the accounts whose state is
+// untouched. The oldest paths are evicted once it is full. A capacity of 0 disables it.
+#[derive(Debug, Default)]
+pub struct ReadProofCache {
+    capacity: AtomicUsize,
+    paths: Mutex<ProofPaths>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ReadProofCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: AtomicUsize::new(capacity),
+            ..Default::default()
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Acquire)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity() > 0
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Release);
+        self.lock().evict_to(capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The number of the paths served from the cache, and that of the ones read from the state.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProofPaths> {
+        self.paths
+            .lock()
+            .expect("ReadProofCache: Failed to lock the proof paths.")
+    }
+
+    // The same path may be read by several workers at once. Only the first one is queued.
+    fn insert(&self, path_key: PathKey, insert_fn: impl FnOnce(&mut ProofPaths) -> bool) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let mut paths = self.lock();
+        if insert_fn(&mut paths) {
+            paths.order.push_back(path_key);
+            paths.evict_to(capacity);
+        }
+    }
+
+    pub fn read_account(
+        &self,
+        state_view: &impl TxStateView,
+        root: H256,
+        address: Address,
+    ) -> Result<(Option<AccountData>, PartialTrie)> {
+        if let Some(path) = self.lock().accounts.get(&(root, address)).cloned() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(path);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let (acc_data, proof) = read_trie(&AccountTrieView::new(state_view), root, &address)?;
+        let path = (acc_data, PartialTrie::from(proof));
+        self.insert(PathKey::Account(root, address), |paths| {
+            paths
+                .accounts
+                .insert((root, address), path.clone())
+                .is_none()
+        });
+        Ok(path)
+    }
+
+    pub fn read_value(
+        &self,
+        state_view: &impl TxStateView,
+        acc_address: Address,
+        acc_state_root: H256,
+        key: StateKey,
+    ) -> Result<PartialTrie> {
+        if let Some(path) = self.lock().values.get(&(acc_state_root, key)).cloned() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(path);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let (_, proof) = read_trie(
+            &StateTrieView::new(state_view, acc_address),
+            acc_state_root,
+            &key,
+        )?;
+        let path = PartialTrie::from(proof);
+        self.insert(PathKey::Value(acc_state_root, key), |paths| {
+            paths
+                .values
+                .insert((acc_state_root, key), path.clone())
+                .is_none()
+        });
+        Ok(path)
+    }
+}
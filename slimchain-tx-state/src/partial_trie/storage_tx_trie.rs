@@ -208,7 +208,10 @@ impl TxTrieTrait for StorageTxTrie {
                 let state_apply = state_write_ctx.changes();
                 let acc_data = AccountData {
                     nonce: acc_data.nonce.unwrap_or(old_acc_data.nonce),
-                    code: acc_data.code.clone().unwrap_or(old_acc_data.code),
+                    code_hash: acc_data
+                        .code
+                        .as_ref()
+                        .map_or(old_acc_data.code_hash, |code| updates.add_code(code)),
                     acc_state_root: state_apply.root,
                 };
 
@@ -247,7 +250,10 @@ impl TxTrieTrait for StorageTxTrie {
 
                 let acc_data = AccountData {
                     nonce: acc_data.nonce.unwrap_or(old_acc_data.nonce),
-                    code: acc_data.code.clone().unwrap_or(old_acc_data.code),
+                    code_hash: acc_data
+                        .code
+                        .as_ref()
+                        .map_or(old_acc_data.code_hash, |code| updates.add_code(code)),
                     acc_state_root,
                 };
                 acc_write_ctx.insert(&acc_addr, acc_data)?;
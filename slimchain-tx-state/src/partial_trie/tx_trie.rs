@@ -1,6 +1,6 @@
 use super::{AccountTrieDiff, AccountWriteSetTrie, TxTrieDiff, TxTrieTrait, TxWriteSetTrie};
 use crate::write::TxStateUpdate;
-use alloc::format;
+use alloc::{format, vec::Vec};
 #[cfg(feature = "cache_hash")]
 use crossbeam_utils::atomic::AtomicCell;
 use serde::{Deserialize, Serialize};
@@ -198,6 +198,13 @@ pub struct TxTrie {
 }
 
 impl TxTrie {
+    pub fn from_root_hash(root_hash: H256) -> Self {
+        Self {
+            main_trie: PartialTrie::from_root_hash(root_hash),
+            acc_tries: imbl::HashMap::new(),
+        }
+    }
+
     pub fn diff_missing_branches(&self, fork: &TxWriteSetTrie) -> TxTrieDiff {
         let main_trie_diff = diff_missing_branches(&self.main_trie, &fork.main_trie);
         let mut acc_trie_diffs = HashMap::new();
@@ -241,6 +248,57 @@ impl TxTrie {
     }
 }
 
+// The changes of a tx trie since an older version of it. Only the account tries that have been
+// changed are included, which is much smaller than the whole tx trie.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxTrieDelta {
+    pub(crate) main_trie: PartialTrie,
+    pub(crate) updated_acc_tries: Vec<(Address, AccountTrie)>,
+    pub(crate) removed_acc_tries: Vec<Address>,
+}
+
+impl TxTrie {
+    pub fn diff(&self, older: &TxTrie) -> TxTrieDelta {
+        let updated_acc_tries = self
+            .acc_tries
+            .iter()
+            .filter(|(acc_addr, acc_trie)| older.acc_tries.get(*acc_addr) != Some(*acc_trie))
+            .map(|(acc_addr, acc_trie)| (*acc_addr, acc_trie.clone()))
+            .collect();
+        let removed_acc_tries = older
+            .acc_tries
+            .keys()
+            .filter(|acc_addr| !self.acc_tries.contains_key(*acc_addr))
+            .copied()
+            .collect();
+
+        TxTrieDelta {
+            main_trie: self.main_trie.clone(),
+            updated_acc_tries,
+            removed_acc_tries,
+        }
+    }
+
+    pub fn apply_delta(&mut self, delta: &TxTrieDelta) -> Result<()> {
+        self.main_trie = delta.main_trie.clone();
+
+        for acc_addr in &delta.removed_acc_tries {
+            self.acc_tries.remove(acc_addr);
+        }
+
+        for (acc_addr, acc_trie) in &delta.updated_acc_tries {
+            ensure!(
+                self.main_trie.value_hash(acc_addr) == Some(acc_trie.acc_hash_inner()),
+                "TxTrie#apply_delta: Hash mismatched (address: {}).",
+                acc_addr
+            );
+            self.acc_tries.insert(*acc_addr, acc_trie.clone());
+        }
+
+        Ok(())
+    }
+}
+
 impl TxTrieTrait for TxTrie {
     fn root_hash(&self) -> H256 {
         if cfg!(debug_assertions) {
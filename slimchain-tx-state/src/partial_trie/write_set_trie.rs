@@ -1,14 +1,15 @@
+use super::ReadProofCache;
 use crate::view::{
     trie_view::{AccountTrieView, StateTrieView},
     TxStateView,
 };
-use alloc::format;
+use alloc::{format, string::ToString};
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{account_data_to_digest, Address, Nonce, H256},
     collections::HashMap,
     digest::Digestible,
-    error::{ensure, Result},
+    error::{ensure, Result, StateError},
     rw_set::TxWriteData,
 };
 use slimchain_merkle_trie::prelude::*;
@@ -66,7 +67,7 @@ impl TxWriteSetTrie {
 
             let acc_proof = AccountWriteSetTrie {
                 nonce: acc_data.map(|acc| acc.nonce).unwrap_or_default(),
-                code_hash: acc_data.map(|acc| acc.code.to_digest()).unwrap_or_default(),
+                code_hash: acc_data.map(|acc| acc.code_hash).unwrap_or_default(),
                 state_trie: state_partial_trie,
             };
 
@@ -79,7 +80,58 @@ impl TxWriteSetTrie {
         })
     }
 
-    pub fn verify(&self, state_root: H256) -> Result<()> {
+    // Same as `new`, but the trie is assembled from the proof paths of the individual keys,
+    // which are shared with the other txs through the cache.
+    pub fn new_with_cache(
+        state_view: &impl TxStateView,
+        root_address: H256,
+        writes: &TxWriteData,
+        cache: &ReadProofCache,
+    ) -> Result<Self> {
+        let mut acc_tries: HashMap<Address, AccountWriteSetTrie> = HashMap::new();
+        let mut main_trie = PartialTrie::from_root_hash(root_address);
+
+        for (acc_address, acc_write) in writes.iter() {
+            let (acc_data, acc_path) =
+                cache.read_account(state_view, root_address, *acc_address)?;
+            main_trie = update_missing_branches(&main_trie, &acc_path)?;
+            let acc_state_root = acc_data
+                .as_ref()
+                .map(|acc| acc.acc_state_root)
+                .unwrap_or_default();
+            let mut state_partial_trie = PartialTrie::from_root_hash(acc_state_root);
+            if !acc_write.reset_values {
+                for key in acc_write.values.keys() {
+                    let value_path =
+                        cache.read_value(state_view, *acc_address, acc_state_root, *key)?;
+                    state_partial_trie = update_missing_branches(&state_partial_trie, &value_path)?;
+                }
+            }
+
+            let acc_proof = AccountWriteSetTrie {
+                nonce: acc_data.as_ref().map(|acc| acc.nonce).unwrap_or_default(),
+                code_hash: acc_data
+                    .as_ref()
+                    .map(|acc| acc.code_hash)
+                    .unwrap_or_default(),
+                state_trie: state_partial_trie,
+            };
+
+            acc_tries.insert(*acc_address, acc_proof);
+        }
+
+        Ok(Self {
+            main_trie,
+            acc_tries,
+        })
+    }
+
+    pub fn verify(&self, state_root: H256) -> Result<(), StateError> {
+        self.verify_inner(state_root)
+            .map_err(|e| StateError::InvalidProof(e.to_string()))
+    }
+
+    fn verify_inner(&self, state_root: H256) -> Result<()> {
         for (acc_address, acc_trie) in self.acc_tries.iter() {
             let acc_hash = acc_trie.acc_hash();
             let main_trie_acc_hash = self.main_trie.value_hash(acc_address);
@@ -1,6 +1,7 @@
 use crate::{
     read_proof::{AccountReadProof, TxReadProof},
     view::{
+        read_code,
         trie_view_sync::{AccountTrieView, StateTrieView},
         TxStateView,
     },
@@ -39,12 +40,12 @@ impl TxStateReadContext {
 
     pub fn get_code(&mut self, acc_address: Address) -> Result<Code> {
         let acc_data = self.get_account(acc_address)?;
-        Ok(acc_data.map(|d| d.code.clone()).unwrap_or_default())
+        let code_hash = acc_data.map(|d| d.code_hash).unwrap_or_default();
+        read_code(&self.state_view, code_hash)
     }
 
     pub fn get_code_len(&mut self, acc_address: Address) -> Result<usize> {
-        let acc_data = self.get_account(acc_address)?;
-        Ok(acc_data.map(|d| d.code.len()).unwrap_or_default())
+        self.get_code(acc_address).map(|code| code.len())
     }
 
     pub fn get_value(&mut self, acc_address: Address, key: StateKey) -> Result<StateValue> {
@@ -70,7 +71,7 @@ impl TxStateReadContext {
                 Some(acc_data) => {
                     let acc_proof = AccountReadProof {
                         nonce: acc_data.nonce,
-                        code_hash: acc_data.code.to_digest(),
+                        code_hash: acc_data.code_hash,
                         state_read_proof: self.values_read_ctx.get(acc_address).map_or_else(
                             || Proof::from_root_hash(acc_data.acc_state_root),
                             |ctx| ctx.get_proof().clone(),
@@ -1,8 +1,9 @@
+use alloc::string::ToString;
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
     basic::{account_data_to_digest, Address, Nonce, StateValue, H256},
     collections::HashMap,
-    error::{anyhow, ensure, Result},
+    error::{anyhow, ensure, Result, StateError},
     rw_set::TxReadData,
 };
 use slimchain_merkle_trie::prelude::*;
@@ -21,7 +22,12 @@ pub struct TxReadProof {
 }
 
 impl TxReadProof {
-    pub fn verify(&self, read_data: &TxReadData, state_root: H256) -> Result<()> {
+    pub fn verify(&self, read_data: &TxReadData, state_root: H256) -> Result<(), StateError> {
+        self.verify_inner(read_data, state_root)
+            .map_err(|e| StateError::InvalidProof(e.to_string()))
+    }
+
+    fn verify_inner(&self, read_data: &TxReadData, state_root: H256) -> Result<()> {
         for (acc_address, acc_reads) in read_data.iter() {
             let acc_proof = self.acc_proofs.get(acc_address).ok_or_else(|| {
                 anyhow!(
@@ -40,8 +46,7 @@ impl TxReadProof {
                 );
             }
 
-            if let Some(code) = &acc_reads.code {
-                let code_hash = code.to_digest();
+            if let Some(code_hash) = acc_reads.code_hash {
                 ensure!(
                     code_hash == acc_proof.code_hash,
                     "TxReadProof: Invalid code (address: {}, expect: {}, actual: {}).",
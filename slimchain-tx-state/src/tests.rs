@@ -172,6 +172,45 @@ fn test_read_write() {
     read_proof3.verify(&read3, state.state_root()).unwrap();
 }
 
+#[cfg(all(feature = "read", feature = "write"))]
+#[test]
+fn test_code_dedup() {
+    let write = create_tx_write_set! {
+        "0000000000000000000000000000000000000001" => {
+            code: b"code",
+        },
+        "0000000000000000000000000000000000000002" => {
+            code: b"code",
+        },
+        "0000000000000000000000000000000000000003" => {
+            nonce: 1,
+        },
+    };
+
+    let mut state = MemTxState::new();
+    let update = update_tx_state(&state.state_view(), state.state_root(), &write).unwrap();
+    assert_eq!(update.codes.len(), 1);
+    state.apply_update(update).unwrap();
+
+    let mut read_ctx = TxStateReadContext::new(state.state_view(), state.state_root());
+    let acc_addr1 = create_address!("0000000000000000000000000000000000000001");
+    let acc_addr2 = create_address!("0000000000000000000000000000000000000002");
+    let acc_addr3 = create_address!("0000000000000000000000000000000000000003");
+    let code_hash = read_ctx.get_account(acc_addr1).unwrap().unwrap().code_hash;
+    assert_eq!(
+        read_ctx.get_account(acc_addr2).unwrap().unwrap().code_hash,
+        code_hash
+    );
+    assert_eq!(
+        read_ctx.get_code(acc_addr2).unwrap(),
+        b"code".to_vec().into()
+    );
+    assert_eq!(read_ctx.get_code_len(acc_addr3).unwrap(), 0);
+
+    let proof = read_ctx.generate_proof().unwrap();
+    assert_eq!(proof.acc_proofs[&acc_addr1].code_hash, code_hash);
+}
+
 #[cfg(feature = "partial_trie")]
 #[test]
 fn test_tx_trie() {
@@ -610,3 +649,114 @@ fn test_prune() {
     assert_eq!(trie3.out_shard.len(), 0);
     assert_eq!(trie3.root_hash(), root);
 }
+
+#[cfg(feature = "partial_trie")]
+#[test]
+fn test_tx_trie_delta() {
+    let write_set1 = create_tx_write_set! {
+        "0000000000000000000000000000000000000000" => {
+            nonce: 1,
+        },
+        "0000000000000000000000000000000000000001" => {
+            code: b"code",
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000000" => 1,
+                "0000000000000000000000000000000000000000000000000000000000000001" => 2,
+            }
+        },
+    };
+    let write_set2 = create_tx_write_set! {
+        "0000000000000000000000000000000000000001" => {
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000001" => 3,
+            }
+        },
+        "0000000000000000000000000000000000000002" => {
+            nonce: 1,
+        },
+    };
+
+    let mut trie1 = TxTrie::default();
+    trie1.apply_writes(&write_set1).unwrap();
+    let mut trie2 = trie1.clone();
+    trie2.apply_writes(&write_set2).unwrap();
+
+    let delta = trie2.diff(&trie1);
+    let mut applied = trie1.clone();
+    applied.apply_delta(&delta).unwrap();
+    assert_eq!(trie2, applied);
+    assert_eq!(trie2.root_hash(), applied.root_hash());
+
+    let delta = trie1.diff(&trie2);
+    let mut applied = trie2.clone();
+    applied.apply_delta(&delta).unwrap();
+    assert_eq!(trie1, applied);
+
+    let mut bad_delta = trie2.diff(&trie1);
+    bad_delta.main_trie = trie1.main_trie.clone();
+    assert!(trie1.clone().apply_delta(&bad_delta).is_err());
+}
+
+#[cfg(feature = "partial_trie")]
+#[test]
+fn test_write_set_trie_with_cache() {
+    let write_set1 = create_tx_write_set! {
+        "0000000000000000000000000000000000000001" => {
+            nonce: 1,
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000001" => 1,
+                "0000000000000000000000000000000000000000000000000000000000000002" => 2,
+            }
+        },
+        "0000000000000000000000000000000000000002" => {
+            nonce: 1,
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000001" => 1,
+            }
+        },
+    };
+    let write_set2 = create_tx_write_set! {
+        "0000000000000000000000000000000000000001" => {
+            values: {
+                "0000000000000000000000000000000000000000000000000000000000000002" => 3,
+                "0000000000000000000000000000000000000000000000000000000000000003" => 4,
+            }
+        },
+        "0000000000000000000000000000000000000003" => {
+            nonce: 1,
+        },
+    };
+
+    let mut state = MemTxState::new();
+    let update = update_tx_state(&state.state_view(), state.state_root(), &write_set1).unwrap();
+    state.apply_update(update).unwrap();
+    let root = state.state_root();
+
+    let cache = ReadProofCache::new(16);
+    let expect = TxWriteSetTrie::new(&state.state_view(), root, &write_set2).unwrap();
+    let actual =
+        TxWriteSetTrie::new_with_cache(&state.state_view(), root, &write_set2, &cache).unwrap();
+    actual.verify(root).unwrap();
+    assert_eq!(expect.main_trie.root_hash(), actual.main_trie.root_hash());
+    assert_eq!((0, 4), cache.stats());
+    assert_eq!(4, cache.len());
+
+    // The paths are reused by the next tx on the same state.
+    let actual =
+        TxWriteSetTrie::new_with_cache(&state.state_view(), root, &write_set2, &cache).unwrap();
+    actual.verify(root).unwrap();
+    assert_eq!((4, 4), cache.stats());
+
+    let mut client = TxTrie::from_root_hash(root);
+    client.update_missing_branches(&actual).unwrap();
+    client.apply_writes(&write_set2).unwrap();
+    let update = update_tx_state(&state.state_view(), root, &write_set2).unwrap();
+    state.apply_update(update).unwrap();
+    assert_eq!(state.state_root(), client.root_hash());
+
+    cache.set_capacity(2);
+    assert_eq!(2, cache.len());
+    cache.set_capacity(0);
+    assert!(cache.is_empty());
+    assert!(!cache.is_enabled());
+}
@@ -1,6 +1,6 @@
 use alloc::sync::Arc;
 use slimchain_common::{
-    basic::{AccountData, Address, StateValue, H256},
+    basic::{AccountData, Address, Code, StateValue, H256},
     error::Result,
 };
 pub use slimchain_merkle_trie::storage::TrieNode;
@@ -13,6 +13,18 @@ pub trait TxStateView {
         acc_address: Address,
         node_address: H256,
     ) -> Result<TrieNode<StateValue>>;
+
+    // Each code is stored once, no matter how many accounts share it.
+    fn code(&self, code_hash: H256) -> Result<Code>;
+}
+
+// Load the code of the account. The empty code is not in the code store.
+pub fn read_code(view: &(impl TxStateView + ?Sized), code_hash: H256) -> Result<Code> {
+    if code_hash.is_zero() {
+        Ok(Code::new())
+    } else {
+        view.code(code_hash)
+    }
 }
 
 impl<T: TxStateView + ?Sized> TxStateView for Arc<T> {
@@ -27,4 +39,8 @@ impl<T: TxStateView + ?Sized> TxStateView for Arc<T> {
     ) -> Result<TrieNode<StateValue>> {
         self.as_ref().state_trie_node(acc_address, node_address)
     }
+
+    fn code(&self, code_hash: H256) -> Result<Code> {
+        self.as_ref().code(code_hash)
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{TxStateUpdate, TxStateView};
 use slimchain_common::{
-    basic::{AccountData, Address, StateValue, H256},
+    basic::{AccountData, Address, Code, StateValue, H256},
     error::Result,
 };
 use slimchain_merkle_trie::storage::TrieNode;
@@ -41,4 +41,11 @@ impl<'a, View: TxStateView + ?Sized> TxStateView for TxStateViewWithUpdate<'a, V
 
         self.view.state_trie_node(acc_address, node_address)
     }
+    fn code(&self, code_hash: H256) -> Result<Code> {
+        if let Some(code) = self.update.codes.get(&code_hash) {
+            return Ok(code.clone());
+        }
+
+        self.view.code(code_hash)
+    }
 }
@@ -4,7 +4,7 @@ use crate::view::{
 };
 use serde::{Deserialize, Serialize};
 use slimchain_common::{
-    basic::{AccountData, Address, StateKey, StateValue, H256},
+    basic::{AccountData, Address, Code, StateKey, StateValue, H256},
     collections::HashMap,
     error::Result,
     rw_set::TxWriteData,
@@ -16,6 +16,8 @@ pub struct TxStateUpdate {
     pub root: H256,
     pub acc_nodes: HashMap<H256, TrieNode<AccountData>>,
     pub state_nodes: HashMap<Address, HashMap<H256, TrieNode<StateValue>>>,
+    // The new codes, keyed by the code hash.
+    pub codes: HashMap<H256, Code>,
 }
 
 impl TxStateUpdate {
@@ -28,6 +30,16 @@ impl TxStateUpdate {
                 .or_default()
                 .extend(nodes.into_iter());
         }
+        self.codes.extend(other.codes.into_iter());
+    }
+
+    // Record the code written to an account and return its hash.
+    pub fn add_code(&mut self, code: &Code) -> H256 {
+        let code_hash = code.to_digest();
+        if !code_hash.is_zero() {
+            self.codes.entry(code_hash).or_insert_with(|| code.clone());
+        }
+        code_hash
     }
 }
 
@@ -62,7 +74,10 @@ pub fn update_tx_state(
         let state_apply = state_write_ctx.changes();
         let acc_data = AccountData {
             nonce: acc_data.nonce.unwrap_or(old_acc_data.nonce),
-            code: acc_data.code.clone().unwrap_or(old_acc_data.code),
+            code_hash: acc_data
+                .code
+                .as_ref()
+                .map_or(old_acc_data.code_hash, |code| updates.add_code(code)),
             acc_state_root: state_apply.root,
         };
 
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use slimchain_common::digest::{set_digest_algorithm, DigestAlgorithm};
+use structopt::StructOpt;
+
+// The digest option of the tools working on the chain data, which should be flattened into their
+// command line options.
+#[derive(Debug, Clone, StructOpt, Serialize, Deserialize)]
+pub struct DigestOpts {
+    /// Digest algorithm of the chain, which should match the chain config of the nodes.
+    /// Possible values: blake2b, keccak256, blake3 (requires the blake3 feature).
+    #[structopt(long, default_value = "blake2b")]
+    pub digest: DigestAlgorithm,
+}
+
+impl DigestOpts {
+    // It should be called on start, before any digest is computed.
+    pub fn install_as_global(&self) {
+        set_digest_algorithm(self.digest);
+    }
+}
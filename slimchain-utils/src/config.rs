@@ -1,7 +1,12 @@
 use hex::{FromHex, FromHexError};
 use serde::{de::Error as SerdeError, Deserialize, Deserializer};
 use slimchain_common::error::{anyhow, Error, Result};
-use std::{fs, path::Path, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::task::JoinHandle;
 use toml::Value as TomlValue;
 
 pub const CONFIG_FILE_NAME: &str = "config.toml";
@@ -41,6 +46,137 @@ impl Config {
     }
 }
 
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct LogConfig {
+    /// The log level, e.g., info, debug. Overridden by `--log-level` at startup.
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+impl LogConfig {
+    pub fn apply(&self) -> Result<()> {
+        match self.level.as_deref() {
+            Some(level) => crate::set_log_level(level),
+            None => Ok(()),
+        }
+    }
+}
+
+// Check the modified time of the config file at this interval.
+pub const DEFAULT_CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type ReloadFn = Box<dyn Fn(&Config) -> Result<()> + Send + Sync>;
+
+// Re-read the config file on SIGHUP or once it is modified, and hand it over to the registered
+// callbacks. Each callback picks the dynamic settings it cares about. The others in the file
+// only take effect after restarting the node.
+pub struct ConfigWatcher {
+    file: PathBuf,
+    poll_interval: Duration,
+    callbacks: Vec<ReloadFn>,
+}
+
+impl ConfigWatcher {
+    pub fn new(file: &Path) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            poll_interval: DEFAULT_CONFIG_POLL_INTERVAL,
+            callbacks: Vec::new(),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn on_reload(mut self, f: impl Fn(&Config) -> Result<()> + Send + Sync + 'static) -> Self {
+        self.callbacks.push(Box::new(f));
+        self
+    }
+
+    // A broken file is skipped as a whole, so that a half-saved edit does not change anything.
+    // A failed callback does not prevent the others from running.
+    pub fn reload(&self) -> Result<()> {
+        let cfg = Config::load(&self.file)?;
+        for callback in &self.callbacks {
+            if let Err(e) = callback(&cfg) {
+                warn!("Failed to apply the reloaded config. Error: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.file).and_then(|m| m.modified()).ok()
+    }
+
+    // The watcher is stopped once the returned handle is dropped.
+    pub fn spawn(self) -> ConfigWatcherHandle {
+        let handle = tokio::spawn(async move {
+            let mut last_modified = self.modified_time();
+            let mut hangup = hangup_signal();
+            let mut ticker = tokio::time::interval(self.poll_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let modified = self.modified_time();
+                        if modified == last_modified {
+                            continue;
+                        }
+                        last_modified = modified;
+                        info!("{} is modified. Reload it.", self.file.display());
+                    }
+                    Some(_) = hangup.recv() => {
+                        info!("Received SIGHUP. Reload {}.", self.file.display());
+                    }
+                }
+
+                if let Err(e) = self.reload() {
+                    warn!("Failed to reload the config. Error: {}", e);
+                }
+            }
+        });
+        ConfigWatcherHandle(handle)
+    }
+}
+
+pub struct ConfigWatcherHandle(JoinHandle<()>);
+
+impl Drop for ConfigWatcherHandle {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(unix)]
+fn hangup_signal() -> tokio::sync::mpsc::UnboundedReceiver<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    match signal(SignalKind::hangup()) {
+        Ok(mut hangup) => {
+            tokio::spawn(async move {
+                while hangup.recv().await.is_some() {
+                    if tx.send(()).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        Err(e) => warn!("Failed to listen to SIGHUP. Error: {}", e),
+    }
+    rx
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> tokio::sync::mpsc::UnboundedReceiver<()> {
+    let (_tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    rx
+}
+
 pub fn deserialize_from_hex<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
@@ -57,3 +193,41 @@ where
     let ms = u64::deserialize(deserializer)?;
     Ok(Duration::from_millis(ms))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_config_watcher_reload() {
+        let file =
+            std::env::temp_dir().join(format!("slimchain-config-{}.toml", std::process::id()));
+        fs::write(&file, "[miner]\nmax_block_interval = 1000\n").unwrap();
+
+        let intervals = Arc::new(Mutex::new(Vec::new()));
+        let watcher = ConfigWatcher::new(&file)
+            .on_reload({
+                let intervals = intervals.clone();
+                move |cfg| {
+                    let miner: TomlValue = cfg.get("miner")?;
+                    let interval = miner
+                        .get("max_block_interval")
+                        .and_then(|v| v.as_integer())
+                        .ok_or_else(|| anyhow!("Missing max_block_interval."))?;
+                    intervals.lock().unwrap().push(interval);
+                    Ok(())
+                }
+            })
+            .on_reload(|_| Err(anyhow!("Failed callback.")));
+
+        watcher.reload().unwrap();
+        fs::write(&file, "[miner]\nmax_block_interval = 500\n").unwrap();
+        watcher.reload().unwrap();
+        fs::write(&file, "[miner\n").unwrap();
+        assert!(watcher.reload().is_err());
+        fs::remove_file(&file).ok();
+
+        assert_eq!(*intervals.lock().unwrap(), vec![1000, 500]);
+    }
+}
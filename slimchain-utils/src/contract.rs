@@ -3,11 +3,17 @@ use sha3::{Digest, Keccak256};
 use slimchain_common::{
     basic::{Address, Code, Nonce, H160, H256, U256},
     collections::HashMap,
-    error::{Context as _, Result},
+    error::{anyhow, ensure, Context as _, Result},
 };
 use std::{fs::File, io::BufReader, path::Path};
 
-pub use ethabi::{self, Function, Token};
+pub use ethabi::{self, Event, Function, LogParam, Token};
+
+pub mod human_readable;
+pub use human_readable::*;
+
+pub mod solc;
+pub use solc::*;
 
 // Ref: https://github.com/rust-blockchain/evm/blob/60f4020ab38dc8f21311e44f0f4174192bb1769d/src/executor/stack.rs#L328-L334
 pub fn contract_address(creator: Address, nonce: Nonce) -> Address {
@@ -20,10 +26,21 @@ pub fn contract_address(creator: Address, nonce: Nonce) -> Address {
     address.into()
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedLog {
+    pub event: String,
+    pub params: Vec<LogParam>,
+}
+
 #[derive(Debug)]
 pub struct Contract {
+    // Empty until all the libraries are linked.
     code: Code,
+    // The hex of the bytecode with the library placeholders left.
+    unlinked_code: Option<String>,
     funcs: HashMap<String, Function>,
+    // Keyed by the event signature, i.e., the first topic of the log.
+    events: HashMap<H256, Event>,
 }
 
 impl Contract {
@@ -36,26 +53,117 @@ impl Contract {
         Self::from_json_value(serde_json::from_slice(data)?)
     }
 
+    // The truffle and hardhat artifacts keep the bytecode as a string, while the foundry ones and
+    // the solc standard json output keep it under `object`.
     pub fn from_json_value(json_data: JsonValue) -> Result<Self> {
-        let bytecode = json_data["bytecode"]
-            .as_str()
-            .context("Failed to read `bytecode`.")?;
-        let code = hex::decode(&bytecode[2..])?.into();
+        let bytecode = [
+            &json_data["bytecode"],
+            &json_data["bytecode"]["object"],
+            &json_data["evm"]["bytecode"]["object"],
+        ]
+        .into_iter()
+        .find_map(|bytecode| bytecode.as_str())
+        .context("Failed to read `bytecode`.")?;
 
-        let mut funcs = HashMap::new();
         let abi_data = json_data["abi"]
             .as_array()
             .context("Failed to read `abi`.")?;
+        let mut contract = Self::from_abi(Code::new(), abi_data)?;
+        contract.set_bytecode(bytecode.trim_start_matches("0x"))?;
+        Ok(contract)
+    }
+
+    // Compile the solidity source with solc, and return the contracts keyed by the name.
+    pub fn compile_source(
+        path: &Path,
+        solc_version: Option<&str>,
+    ) -> Result<HashMap<String, Self>> {
+        compile_solidity(path, solc_version)?
+            .into_iter()
+            .map(|(name, artifact)| {
+                let contract = Self::from_json_value(artifact)
+                    .with_context(|| format!("Failed to load contract {}.", name))?;
+                Ok((name, contract))
+            })
+            .collect()
+    }
+
+    // Build from the human-readable abi, so that no compiled artifact is needed.
+    pub fn from_human_readable(code: Code, abi: &[&str]) -> Result<Self> {
+        let abi_data = abi
+            .iter()
+            .map(|abi| parse_human_readable_abi(abi))
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_abi(code, &abi_data)
+    }
+
+    fn from_abi(code: Code, abi_data: &[JsonValue]) -> Result<Self> {
+        let mut funcs = HashMap::new();
+        let mut events = HashMap::new();
 
         for abi in abi_data {
             if abi["type"] == "function" {
                 let func: Function =
                     serde_json::from_value(abi.clone()).context("Failed to decode abi.")?;
                 funcs.insert(func.name.clone(), func);
+            } else if abi["type"] == "event" {
+                let event: Event =
+                    serde_json::from_value(abi.clone()).context("Failed to decode abi.")?;
+                // The anonymous events cannot be told apart from the topics.
+                if !event.anonymous {
+                    events.insert(event.signature(), event);
+                }
             }
         }
 
-        Ok(Self { code, funcs })
+        Ok(Self {
+            code,
+            unlinked_code: None,
+            funcs,
+            events,
+        })
+    }
+
+    fn set_bytecode(&mut self, bytecode: &str) -> Result<()> {
+        // The hex digits never contain `_`, which only comes from the placeholders.
+        if bytecode.contains("__") {
+            self.unlinked_code = Some(bytecode.to_string());
+        } else {
+            self.code = hex::decode(bytecode)?.into();
+            self.unlinked_code = None;
+        }
+        Ok(())
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.unlinked_code.is_none()
+    }
+
+    // Replace the placeholders of the library with its deployed address. The `name` is the
+    // library name for the truffle placeholders, i.e., `__LibName___...`, or the fully qualified
+    // name, e.g., `contracts/Lib.sol:Lib`, for the solc placeholders, i.e., `__$<hash>$__`.
+    pub fn link_library(&mut self, name: &str, address: Address) -> Result<()> {
+        let unlinked_code = self
+            .unlinked_code
+            .as_ref()
+            .context("The contract is already linked.")?;
+        let truffle_placeholder = format!("__{:_<38}", &name[..name.len().min(36)]);
+        let solc_placeholder = format!(
+            "__${}$__",
+            &hex::encode(Keccak256::digest(name.as_bytes()))[..34]
+        );
+        ensure!(
+            unlinked_code.contains(&truffle_placeholder)
+                || unlinked_code.contains(&solc_placeholder),
+            "Failed to find library `{}` in the bytecode.",
+            name
+        );
+
+        let address = hex::encode(address.as_bytes());
+        let bytecode = unlinked_code
+            .replace(&truffle_placeholder, &address)
+            .replace(&solc_placeholder, &address);
+        self.set_bytecode(&bytecode)
     }
 
     pub fn code(&self) -> &Code {
@@ -69,11 +177,38 @@ impl Contract {
             .encode_input(args)
             .context("Failed to encode inputs.")
     }
+
+    pub fn decode_output(&self, name: &str, data: &[u8]) -> Result<Vec<Token>> {
+        self.funcs
+            .get(name)
+            .context("Failed to find function.")?
+            .decode_output(data)
+            .context("Failed to decode outputs.")
+    }
+
+    pub fn decode_log(&self, topics: &[H256], data: &[u8]) -> Result<DecodedLog> {
+        let signature = topics.first().context("Log without topics.")?;
+        let event = self
+            .events
+            .get(signature)
+            .ok_or_else(|| anyhow!("Failed to find event. Signature: {:?}", signature))?;
+        let log = event
+            .parse_log(ethabi::RawLog {
+                topics: topics.to_vec(),
+                data: data.to_vec(),
+            })
+            .context("Failed to decode log.")?;
+        Ok(DecodedLog {
+            event: event.name.clone(),
+            params: log.params,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
     use slimchain_common::create_address;
     use std::path::PathBuf;
 
@@ -85,6 +220,20 @@ mod tests {
         assert_eq!(expect, actual);
     }
 
+    #[test]
+    #[ignore]
+    fn test_compile_source() {
+        let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("contracts/contracts/simple_storage.sol");
+        let contracts = Contract::compile_source(&file, None).unwrap();
+        let contract = &contracts["SimpleStorage"];
+        assert!(!contract.code().is_empty());
+        let args = [Token::Uint(U256::from(1)), Token::Uint(U256::from(43))];
+        assert!(contract.encode_tx_input("set", &args).is_ok());
+    }
+
     #[test]
     fn test_encode_tx_input() {
         let file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -97,4 +246,178 @@ mod tests {
         let expect = hex::decode("1ab06ee50000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000002b").unwrap();
         assert_eq!(encoded_input, expect);
     }
+
+    #[test]
+    fn test_from_human_readable() {
+        let contract = Contract::from_human_readable(
+            Code::from(vec![0x60, 0x80]),
+            &[
+                "function set(uint256 k, uint256 v)",
+                "function get(uint256 k) view returns (uint256)",
+                "event Set(uint256 indexed k, uint256 v)",
+            ],
+        )
+        .unwrap();
+        let args = [Token::Uint(U256::from(1)), Token::Uint(U256::from(43))];
+        let encoded_input = contract.encode_tx_input("set", &args).unwrap();
+        let expect = hex::decode("1ab06ee50000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000002b").unwrap();
+        assert_eq!(encoded_input, expect);
+        assert_eq!(
+            contract
+                .decode_output("get", &ethabi::encode(&[Token::Uint(U256::from(43))]))
+                .unwrap(),
+            vec![Token::Uint(U256::from(43))]
+        );
+        assert!(Contract::from_human_readable(Code::new(), &["function set(uint8x k)"]).is_err());
+    }
+
+    #[test]
+    fn test_artifact_formats() {
+        let abi = json!([{
+            "type": "function",
+            "name": "set",
+            "inputs": [
+                { "name": "k", "type": "uint256" },
+                { "name": "v", "type": "uint256" },
+            ],
+            "outputs": [],
+            "stateMutability": "nonpayable",
+        }]);
+        let artifacts = [
+            // truffle
+            json!({ "contractName": "SimpleStorage", "abi": abi, "bytecode": "0x6080" }),
+            // hardhat
+            json!({
+                "_format": "hh-sol-artifact-1",
+                "contractName": "SimpleStorage",
+                "abi": abi,
+                "bytecode": "0x6080",
+                "deployedBytecode": "0x6081",
+                "linkReferences": {},
+            }),
+            // foundry
+            json!({
+                "abi": abi,
+                "bytecode": { "object": "0x6080", "linkReferences": {} },
+                "deployedBytecode": { "object": "0x6081", "linkReferences": {} },
+            }),
+            // solc standard json output
+            json!({ "abi": abi, "evm": { "bytecode": { "object": "6080" } } }),
+        ];
+
+        for artifact in artifacts {
+            let contract = Contract::from_json_value(artifact).unwrap();
+            assert_eq!(contract.code(), &Code::from(vec![0x60, 0x80]));
+            assert!(contract
+                .encode_tx_input(
+                    "set",
+                    &[Token::Uint(U256::from(1)), Token::Uint(U256::from(2))]
+                )
+                .is_ok());
+        }
+
+        assert!(Contract::from_json_value(json!({ "abi": abi })).is_err());
+    }
+
+    #[test]
+    fn test_link_library() {
+        let lib1 = create_address!("29ed001a09cd53e21e50a027f47b66f8e034534a");
+        let lib2 = create_address!("334174c99836bcc7c983b4fa13d702407354f003");
+        let solc_placeholder = format!(
+            "__${}$__",
+            &hex::encode(Keccak256::digest(b"contracts/Lib2.sol:Lib2"))[..34]
+        );
+        let mut contract = Contract::from_json_value(json!({
+            "bytecode": format!("0x6080__Lib1__________________________________73{}", solc_placeholder),
+            "abi": [],
+        }))
+        .unwrap();
+        assert!(!contract.is_linked());
+        assert!(contract.code().is_empty());
+
+        assert!(contract.link_library("Lib3", lib1).is_err());
+        contract.link_library("Lib1", lib1).unwrap();
+        assert!(!contract.is_linked());
+        contract
+            .link_library("contracts/Lib2.sol:Lib2", lib2)
+            .unwrap();
+        assert!(contract.is_linked());
+        let expect = hex::decode(format!(
+            "6080{}73{}",
+            hex::encode(lib1.as_bytes()),
+            hex::encode(lib2.as_bytes())
+        ))
+        .unwrap();
+        assert_eq!(contract.code(), &Code::from(expect));
+        assert!(contract.link_library("Lib1", lib1).is_err());
+    }
+
+    #[test]
+    fn test_decode_output() {
+        let contract = Contract::from_json_value(json!({
+            "bytecode": "0x6080",
+            "abi": [{
+                "type": "function",
+                "name": "get",
+                "constant": true,
+                "stateMutability": "view",
+                "inputs": [{ "name": "key", "type": "uint256" }],
+                "outputs": [
+                    { "name": "", "type": "uint256" },
+                    { "name": "", "type": "string" },
+                ],
+            }],
+        }))
+        .unwrap();
+
+        let expect = vec![
+            Token::Uint(U256::from(43)),
+            Token::String("value".to_string()),
+        ];
+        let data = ethabi::encode(&expect);
+        assert_eq!(contract.decode_output("get", &data).unwrap(), expect);
+        assert!(contract.decode_output("get", &data[..32]).is_err());
+        assert!(contract.decode_output("set", &data).is_err());
+    }
+
+    #[test]
+    fn test_decode_log() {
+        let contract = Contract::from_json_value(json!({
+            "bytecode": "0x6080",
+            "abi": [{
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false },
+                ],
+            }],
+        }))
+        .unwrap();
+
+        let from = H160::from_low_u64_be(1);
+        let to = H160::from_low_u64_be(2);
+        let topics = [
+            H256::from_slice(&Keccak256::digest(b"Transfer(address,address,uint256)")),
+            from.into(),
+            to.into(),
+        ];
+        let data = ethabi::encode(&[Token::Uint(U256::from(43))]);
+        let log = contract.decode_log(&topics, &data).unwrap();
+        assert_eq!(log.event, "Transfer");
+        let params: Vec<_> = log.params.into_iter().map(|p| (p.name, p.value)).collect();
+        assert_eq!(
+            params,
+            vec![
+                ("from".to_string(), Token::Address(from)),
+                ("to".to_string(), Token::Address(to)),
+                ("value".to_string(), Token::Uint(U256::from(43))),
+            ]
+        );
+
+        assert!(contract.decode_log(&topics[1..], &data).is_err());
+        assert!(contract.decode_log(&[], &data).is_err());
+    }
 }
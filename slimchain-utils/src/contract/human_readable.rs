@@ -0,0 +1,226 @@
+// Parse the human-readable ABI, e.g., `function set(uint256 k, uint256 v)` or
+// `event Transfer(address indexed from, address indexed to, uint256 value)`, into the JSON ABI
+// entries used by the compiled artifacts.
+//
+// Ref: https://docs.ethers.io/v5/api/utils/abi/formats/#abi-formats--human-readable-abi
+
+use serde_json::{json, Value as JsonValue};
+use slimchain_common::error::{bail, ensure, Context as _, Result};
+
+const DATA_LOCATIONS: [&str; 3] = ["memory", "calldata", "storage"];
+const STATE_MUTABILITIES: [&str; 4] = ["pure", "view", "nonpayable", "payable"];
+
+pub fn parse_human_readable_abi(abi: &str) -> Result<JsonValue> {
+    parse_abi_entry(abi).with_context(|| format!("Failed to parse abi `{}`.", abi))
+}
+
+fn parse_abi_entry(abi: &str) -> Result<JsonValue> {
+    let abi = abi.trim().trim_end_matches(';').trim_end();
+    let (kind, rest) = abi.split_once(' ').context("Missing abi type.")?;
+    let (name, rest) = rest.split_once('(').context("Missing parameters.")?;
+    let name = name.trim();
+    let (params, modifiers) = split_parens(rest)?;
+
+    match kind {
+        "function" => {
+            ensure!(!name.is_empty(), "Missing function name.");
+            let mut state_mutability = "nonpayable";
+            let mut outputs = Vec::new();
+            let mut modifiers = modifiers.trim();
+            while !modifiers.is_empty() {
+                if let Some(rest) = modifiers.strip_prefix("returns") {
+                    let rest = rest
+                        .trim_start()
+                        .strip_prefix('(')
+                        .context("Missing outputs.")?;
+                    let (returns, rest) = split_parens(rest)?;
+                    outputs = parse_params(returns, false)?;
+                    modifiers = rest.trim_start();
+                    continue;
+                }
+
+                let (modifier, rest) = modifiers.split_once(' ').unwrap_or((modifiers, ""));
+                if STATE_MUTABILITIES.contains(&modifier) {
+                    state_mutability = modifier;
+                } else if modifier != "external" && modifier != "public" {
+                    bail!("Unknown modifier `{}`.", modifier);
+                }
+                modifiers = rest.trim_start();
+            }
+
+            Ok(json!({
+                "type": "function",
+                "name": name,
+                "inputs": parse_params(params, false)?,
+                "outputs": outputs,
+                "constant": state_mutability == "view" || state_mutability == "pure",
+                "stateMutability": state_mutability,
+            }))
+        }
+        "event" => {
+            ensure!(!name.is_empty(), "Missing event name.");
+            let anonymous = match modifiers.trim() {
+                "" => false,
+                "anonymous" => true,
+                modifier => bail!("Unknown modifier `{}`.", modifier),
+            };
+
+            Ok(json!({
+                "type": "event",
+                "name": name,
+                "inputs": parse_params(params, true)?,
+                "anonymous": anonymous,
+            }))
+        }
+        _ => bail!("Unsupported abi type `{}`.", kind),
+    }
+}
+
+// Split `a, (b, c) d) rest` at the parenthesis closing the list, which is already opened.
+fn split_parens(input: &str) -> Result<(&str, &str)> {
+    let mut depth = 0usize;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Ok((&input[..i], &input[i + 1..])),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    bail!("Unbalanced parentheses.");
+}
+
+fn split_params(input: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                params.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&input[start..]);
+    params
+}
+
+fn parse_params(input: &str, allow_indexed: bool) -> Result<Vec<JsonValue>> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    split_params(input)
+        .into_iter()
+        .map(|param| {
+            let param = param.trim();
+            let (ty, rest) = split_type(param)?;
+            let ty = canonical_type(ty)?;
+            ensure!(!ty.is_empty(), "Missing parameter type.");
+
+            let mut indexed = false;
+            let mut name = "";
+            for word in rest.split_whitespace() {
+                if word == "indexed" && allow_indexed {
+                    indexed = true;
+                } else if DATA_LOCATIONS.contains(&word) {
+                    continue;
+                } else if name.is_empty() {
+                    name = word;
+                } else {
+                    bail!("Invalid parameter `{}`.", param);
+                }
+            }
+
+            let mut out = json!({ "name": name, "type": ty });
+            if allow_indexed {
+                out["indexed"] = json!(indexed);
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+// Split the type off the parameter. The tuple type may contain the spaces.
+fn split_type(param: &str) -> Result<(&str, &str)> {
+    let type_len = match param.strip_prefix('(') {
+        Some(rest) => {
+            let (inner, rest) = split_parens(rest)?;
+            inner.len() + 2 + rest.find(char::is_whitespace).unwrap_or(rest.len())
+        }
+        None => param.find(char::is_whitespace).unwrap_or(param.len()),
+    };
+    Ok(param.split_at(type_len))
+}
+
+// `uint` and `int` are the aliases of `uint256` and `int256`. The names of the tuple components
+// are dropped.
+fn canonical_type(ty: &str) -> Result<String> {
+    if let Some(rest) = ty.strip_prefix('(') {
+        let (inner, suffix) = split_parens(rest)?;
+        let components = split_params(inner)
+            .into_iter()
+            .map(|component| canonical_type(split_type(component.trim())?.0))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(format!("({}){}", components.join(","), suffix));
+    }
+
+    let base_len = ty.find('[').unwrap_or(ty.len());
+    let (base, suffix) = ty.split_at(base_len);
+    Ok(match base {
+        "uint" => format!("uint256{}", suffix),
+        "int" => format!("int256{}", suffix),
+        _ => ty.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_human_readable_abi() {
+        assert_eq!(
+            parse_human_readable_abi(
+                "function get(uint k, string memory s) external view returns (uint256, (uint a, bool) t)"
+            )
+            .unwrap(),
+            json!({
+                "type": "function",
+                "name": "get",
+                "inputs": [
+                    { "name": "k", "type": "uint256" },
+                    { "name": "s", "type": "string" },
+                ],
+                "outputs": [
+                    { "name": "", "type": "uint256" },
+                    { "name": "t", "type": "(uint256,bool)" },
+                ],
+                "constant": true,
+                "stateMutability": "view",
+            })
+        );
+        assert_eq!(
+            parse_human_readable_abi("event Transfer(address indexed from, address to, uint[2])")
+                .unwrap(),
+            json!({
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": false },
+                    { "name": "", "type": "uint256[2]", "indexed": false },
+                ],
+                "anonymous": false,
+            })
+        );
+        assert!(parse_human_readable_abi("function set(uint256 k").is_err());
+        assert!(parse_human_readable_abi("function set(uint256 indexed k)").is_err());
+        assert!(parse_human_readable_abi("function set() internal").is_err());
+        assert!(parse_human_readable_abi("modifier onlyOwner()").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+// Compile the solidity source with solc's standard json interface, so that the tests do not
+// depend on the truffle build step.
+//
+// The solc binary is picked in order from:
+// 1. the `SOLC` environment variable,
+// 2. `~/.svm/<version>/solc-<version>` installed by svm/foundry, when the version is given,
+// 3. `solc` in `PATH`, with `SOLC_VERSION` set for solc-select.
+// Its version is checked against the requested one. No binary is downloaded.
+
+use serde_json::{json, Value as JsonValue};
+use slimchain_common::{
+    collections::HashMap,
+    error::{bail, ensure, Context as _, Result},
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+fn solc_binary(version: Option<&str>) -> PathBuf {
+    if let Some(solc) = std::env::var_os("SOLC") {
+        return solc.into();
+    }
+
+    if let (Some(version), Some(home)) = (version, std::env::var_os("HOME")) {
+        let svm_solc = Path::new(&home)
+            .join(".svm")
+            .join(version)
+            .join(format!("solc-{}", version));
+        if svm_solc.exists() {
+            return svm_solc;
+        }
+    }
+
+    PathBuf::from("solc")
+}
+
+fn solc_command(version: Option<&str>) -> Result<Command> {
+    let solc = solc_binary(version);
+    let mut cmd = Command::new(&solc);
+    if let Some(version) = version {
+        cmd.env("SOLC_VERSION", version);
+    }
+
+    let out = Command::new(&solc)
+        .arg("--version")
+        .envs(version.map(|v| ("SOLC_VERSION", v)))
+        .output()
+        .with_context(|| format!("Failed to run {}. Is solc installed?", solc.display()))?;
+    ensure!(out.status.success(), "Failed to get the solc version.");
+    if let Some(version) = version {
+        let out = String::from_utf8_lossy(&out.stdout);
+        ensure!(
+            out.contains(&format!("Version: {}+", version)),
+            "Mismatched solc version (expect: {}, actual: {}).",
+            version,
+            out.trim()
+        );
+    }
+
+    Ok(cmd)
+}
+
+// Compile the source file, and return the abi and the bytecode of each contract, keyed by the
+// contract name, in the layout of the solc standard json output.
+pub fn compile_solidity(path: &Path, version: Option<&str>) -> Result<HashMap<String, JsonValue>> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to find {}.", path.display()))?;
+    let dir = path.parent().context("Invalid source path.")?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid source path.")?;
+
+    // Same as the optimizer settings in `contracts/truffle-config.js`.
+    let input = json!({
+        "language": "Solidity",
+        "sources": { file_name: { "urls": [file_name] } },
+        "settings": {
+            "optimizer": { "enabled": true, "runs": 200 },
+            "outputSelection": { "*": { "*": ["abi", "evm.bytecode.object"] } },
+        },
+    });
+
+    let mut child = solc_command(version)?
+        .arg("--standard-json")
+        .arg("--allow-paths")
+        .arg(".")
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run solc.")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin of solc.")?
+        .write_all(&serde_json::to_vec(&input)?)?;
+    let out = child.wait_with_output()?;
+    ensure!(
+        out.status.success(),
+        "Failed to run solc. Error: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    parse_solc_output(
+        serde_json::from_slice(&out.stdout).context("Failed to decode the solc output.")?,
+    )
+}
+
+fn parse_solc_output(output: JsonValue) -> Result<HashMap<String, JsonValue>> {
+    let errors: Vec<&str> = output["errors"]
+        .as_array()
+        .map(|errors| {
+            errors
+                .iter()
+                .filter(|e| e["severity"] == "error")
+                .filter_map(|e| e["formattedMessage"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !errors.is_empty() {
+        bail!("Failed to compile. Error: {}", errors.join("\n"));
+    }
+
+    let mut contracts = HashMap::new();
+    let sources = output["contracts"]
+        .as_object()
+        .context("Failed to read `contracts`.")?;
+    for source in sources.values() {
+        for (name, contract) in source.as_object().context("Failed to read `contracts`.")? {
+            contracts.insert(name.clone(), contract.clone());
+        }
+    }
+    Ok(contracts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_solc_output() {
+        let contracts = parse_solc_output(json!({
+            "errors": [{ "severity": "warning", "formattedMessage": "Unused variable." }],
+            "contracts": {
+                "simple_storage.sol": {
+                    "SimpleStorage": {
+                        "abi": [],
+                        "evm": { "bytecode": { "object": "6080" } },
+                    },
+                },
+            },
+        }))
+        .unwrap();
+        assert_eq!(
+            contracts["SimpleStorage"]["evm"]["bytecode"]["object"],
+            "6080"
+        );
+
+        assert!(parse_solc_output(json!({
+            "errors": [{ "severity": "error", "formattedMessage": "Parser error." }],
+        }))
+        .is_err());
+    }
+}
@@ -1,34 +1,89 @@
 #[macro_use]
 pub extern crate tracing;
 
+use once_cell::sync::OnceCell;
 use slimchain_common::error::{Error, Result};
 use std::path::Path;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{prelude::*, reload, EnvFilter, Registry};
 
+pub mod cli;
 pub mod config;
 pub mod contract;
 pub mod metrics;
 pub mod ordered_stream;
+pub mod otel;
 pub mod path;
+pub mod prometheus;
 pub mod serde;
 
 pub use bytes;
 pub use chrono;
 pub use toml;
 
-pub fn init_tracing_subscriber(default_level: &str) -> Result<()> {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new(format!("slimchain={},warp::reject=off,warn", default_level))
-    });
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
+type LogLevelReloader = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+static LOG_LEVEL_RELOADER: OnceCell<LogLevelReloader> = OnceCell::new();
+
+// `directives` turns the log level into the filter directives, e.g., to set the level of the
+// crates in the binary.
+pub fn init_tracing_subscriber_with_directives(
+    default_level: &str,
+    directives: fn(&str) -> String,
+) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(directives(default_level)));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(otel::layer::<Registry>()?)
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
         .try_init()
-        .map_err(Error::msg)
+        .map_err(Error::msg)?;
+    LOG_LEVEL_RELOADER
+        .set(Box::new(move |level| {
+            let filter = EnvFilter::try_new(directives(level)).map_err(Error::msg)?;
+            handle.reload(filter).map_err(Error::msg)
+        }))
+        .ok();
+    Ok(())
+}
+
+pub fn init_tracing_subscriber(default_level: &str) -> Result<()> {
+    init_tracing_subscriber_with_directives(default_level, |level| {
+        format!("slimchain={},warp::reject=off,warn", level)
+    })
+}
+
+// Change the log level of the running node. It overrides the filter set by `RUST_LOG`.
+pub fn set_log_level(level: &str) -> Result<()> {
+    let reloader = LOG_LEVEL_RELOADER
+        .get()
+        .ok_or_else(|| Error::msg("The tracing subscriber is not initialized."))?;
+    reloader(level)?;
+    info!("Log level is changed to {}.", level);
+    Ok(())
+}
+
+// Flush the metrics and the exported traces once dropped.
+pub struct TracingGuard {
+    _metrics: metrics::Guard,
+}
+
+impl From<metrics::Guard> for TracingGuard {
+    fn from(metrics: metrics::Guard) -> Self {
+        Self { _metrics: metrics }
+    }
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        otel::shutdown();
+    }
 }
 
-pub fn init_tracing(default_level: &str, metrics_file: &Path) -> Result<metrics::Guard> {
+pub fn init_tracing(default_level: &str, metrics_file: &Path) -> Result<TracingGuard> {
     init_tracing_subscriber(default_level)?;
-    metrics::init_metrics_subscriber_using_file(metrics_file)
+    metrics::init_metrics_subscriber_using_file(metrics_file).map(TracingGuard::from)
 }
 
 pub fn init_tracing_for_test() -> Option<metrics::Guard> {
@@ -1,17 +1,20 @@
 pub use serde_json;
 
-use crossbeam_channel::{bounded, Sender};
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
 use once_cell::sync::OnceCell;
-use serde_json::Value as JsonValue;
+use serde_json::{json, Value as JsonValue};
 use slimchain_common::error::{anyhow, Result};
 use std::{
+    collections::BTreeMap,
     fs,
     io::{BufWriter, Write},
     path::Path,
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 const BUFFERED_ENTRY_SIZE: usize = 10_000;
+pub const DEFAULT_SUMMARY_INTERVAL: Duration = Duration::from_secs(10);
 pub static METRICS_DISPATCH: OnceCell<Dispatch> = OnceCell::new();
 
 pub struct Dispatch {
@@ -29,28 +32,97 @@ enum DispatchEvent {
     Entry(JsonValue),
 }
 
+// Collect the samples of `record_time!` per label, and summarize them into the percentiles
+// when flushed.
+#[derive(Debug, Default)]
+struct TimeAggregator {
+    samples: BTreeMap<String, Vec<u64>>,
+}
+
+impl TimeAggregator {
+    fn add_entry(&mut self, entry: &JsonValue) {
+        if entry["k"] != "time" {
+            return;
+        }
+
+        if let (Some(label), Some(t)) = (entry["l"].as_str(), entry["t_in_us"].as_u64()) {
+            match self.samples.get_mut(label) {
+                Some(samples) => samples.push(t),
+                None => {
+                    self.samples.insert(label.to_string(), vec![t]);
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Vec<JsonValue> {
+        let ts = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+        let samples = std::mem::take(&mut self.samples);
+        samples
+            .into_iter()
+            .map(|(label, mut samples)| {
+                samples.sort_unstable();
+                json!({
+                    "k": "summary",
+                    "l": label,
+                    "ts": ts.as_str(),
+                    "count": samples.len(),
+                    "p50_in_us": percentile(&samples, 50),
+                    "p95_in_us": percentile(&samples, 95),
+                    "p99_in_us": percentile(&samples, 99),
+                    "max_in_us": samples.last().copied().unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+// The nearest-rank percentile of the sorted samples.
+pub fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * p).div_ceil(100);
+    sorted[rank.max(1) - 1]
+}
+
 pub struct Guard {
     sender: Sender<DispatchEvent>,
     handler: Option<JoinHandle<()>>,
 }
 
 impl Guard {
-    fn new(writer: impl Write + Send + Sync + 'static) -> Result<Self> {
+    fn new(writer: impl Write + Send + Sync + 'static, summary_interval: Duration) -> Result<Self> {
         let (tx, rx) = bounded(BUFFERED_ENTRY_SIZE);
         METRICS_DISPATCH
             .set(Dispatch { sender: tx.clone() })
             .map_err(|_e| anyhow!("Metrics already init."))?;
         let handler = thread::spawn(move || {
             let mut writer = writer;
-            while let Ok(entry) = rx.recv() {
-                match entry {
-                    DispatchEvent::Shutdown => break,
-                    DispatchEvent::Entry(value) => {
+            let mut aggregator = TimeAggregator::default();
+            let mut next_summary = Instant::now() + summary_interval;
+            loop {
+                match rx.recv_deadline(next_summary) {
+                    Ok(DispatchEvent::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+                    Ok(DispatchEvent::Entry(value)) => {
+                        aggregator.add_entry(&value);
                         serde_json::to_writer(&mut writer, &value).ok();
                         writeln!(writer).ok();
                     }
+                    Err(RecvTimeoutError::Timeout) => {
+                        for summary in aggregator.flush() {
+                            serde_json::to_writer(&mut writer, &summary).ok();
+                            writeln!(writer).ok();
+                        }
+                        writer.flush().ok();
+                        next_summary = Instant::now() + summary_interval;
+                    }
                 }
             }
+            for summary in aggregator.flush() {
+                serde_json::to_writer(&mut writer, &summary).ok();
+                writeln!(writer).ok();
+            }
             writer.flush().ok();
         });
         Ok(Self {
@@ -71,11 +143,13 @@ impl Drop for Guard {
 
 #[macro_export]
 macro_rules! __record_entry {
-    ($x:expr) => {
+    ($x:expr) => {{
+        let entry = $x;
+        $crate::prometheus::observe_entry(&entry);
         if let Some(dispatch) = $crate::metrics::METRICS_DISPATCH.get() {
-            dispatch.add_entry($x);
+            dispatch.add_entry(entry);
         }
-    };
+    }};
 }
 
 #[macro_export]
@@ -120,7 +194,16 @@ macro_rules! record_event {
 }
 
 pub fn init_metrics_subscriber(writer: impl Write + Send + Sync + 'static) -> Result<Guard> {
-    Guard::new(writer)
+    init_metrics_subscriber_with_summary_interval(writer, DEFAULT_SUMMARY_INTERVAL)
+}
+
+// The percentiles of `record_time!` are written to the metrics every `summary_interval`, as the
+// entries of kind `summary`.
+pub fn init_metrics_subscriber_with_summary_interval(
+    writer: impl Write + Send + Sync + 'static,
+    summary_interval: Duration,
+) -> Result<Guard> {
+    Guard::new(writer, summary_interval)
 }
 
 pub fn init_metrics_subscriber_using_file(file: impl AsRef<Path>) -> Result<Guard> {
@@ -134,7 +217,7 @@ pub fn init_metrics_subscriber_using_file(file: impl AsRef<Path>) -> Result<Guar
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use super::*;
 
     #[test]
     fn test() {
@@ -147,4 +230,25 @@ mod tests {
         tracing::error!("An error");
         tracing::info!("An info");
     }
+    #[test]
+    fn test_time_aggregator() {
+        let mut aggregator = TimeAggregator::default();
+        for t in 1..=100u64 {
+            aggregator.add_entry(&json!({ "k": "time", "l": "exec", "t_in_us": t }));
+        }
+        aggregator.add_entry(&json!({ "k": "time", "l": "mining", "t_in_us": 7 }));
+        aggregator.add_entry(&json!({ "k": "event", "l": "exec", "v": {} }));
+
+        let summaries = aggregator.flush();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0]["l"], "exec");
+        assert_eq!(summaries[0]["count"], 100);
+        assert_eq!(summaries[0]["p50_in_us"], 50);
+        assert_eq!(summaries[0]["p95_in_us"], 95);
+        assert_eq!(summaries[0]["p99_in_us"], 99);
+        assert_eq!(summaries[0]["max_in_us"], 100);
+        assert_eq!(summaries[1]["l"], "mining");
+        assert_eq!(summaries[1]["p99_in_us"], 7);
+        assert!(aggregator.flush().is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use futures::{prelude::*, ready, stream::Fuse};
+use futures::{future::BoxFuture, prelude::*, stream::Fuse};
 use pin_project::pin_project;
 use slimchain_common::collections::HashMap;
 use std::{
@@ -7,7 +7,22 @@ use std::{
     hash::Hash,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
+use tokio::time::{sleep, Sleep};
+
+// What to do with the missing item once the gap timeout is reached.
+#[derive(Debug)]
+pub enum GapResolution<V> {
+    // The missing item is recovered, e.g., fetched from a peer.
+    Fill(V),
+    // Give up the missing item and move on to the next one.
+    Skip,
+    // Keep waiting, and call the handler again after another timeout.
+    Wait,
+}
+
+pub type GapHandler<K, V> = Box<dyn Fn(&K) -> BoxFuture<'static, GapResolution<V>> + Send + Sync>;
 
 #[pin_project]
 pub struct OrderedStream<S, K, V, F>
@@ -20,6 +35,10 @@ where
     current: K,
     cache: HashMap<K, V>,
     next_key_fn: F,
+    gap_timeout: Duration,
+    gap_handler: Option<GapHandler<K, V>>,
+    gap_timer: Option<Pin<Box<Sleep>>>,
+    gap_fut: Option<BoxFuture<'static, GapResolution<V>>>,
 }
 
 impl<S, K, V, F> OrderedStream<S, K, V, F>
@@ -33,8 +52,24 @@ where
             current,
             cache: HashMap::new(),
             next_key_fn,
+            gap_timeout: Duration::default(),
+            gap_handler: None,
+            gap_timer: None,
+            gap_fut: None,
         }
     }
+
+    // Without it, a lost item stalls the stream forever. With it, the handler is called once the
+    // later items have been waiting for the missing one longer than `timeout`.
+    pub fn with_gap_handler(
+        mut self,
+        timeout: Duration,
+        handler: impl Fn(&K) -> BoxFuture<'static, GapResolution<V>> + Send + Sync + 'static,
+    ) -> Self {
+        self.gap_timeout = timeout;
+        self.gap_handler = Some(Box::new(handler));
+        self
+    }
 }
 
 impl<S, K, V, F> Stream for OrderedStream<S, K, V, F>
@@ -48,33 +83,81 @@ where
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
 
+        macro_rules! advance {
+            () => {{
+                *this.current = (this.next_key_fn)(this.current);
+                *this.gap_timer = None;
+                *this.gap_fut = None;
+            }};
+        }
+
         loop {
             if let Some(value) = this.cache.remove(&this.current) {
-                *this.current = (this.next_key_fn)(this.current);
+                advance!();
                 return Poll::Ready(Some(value));
             }
 
-            let item = ready!(this.stream.as_mut().poll_next(cx));
-
-            if let Some((key, value)) = item {
-                match key.cmp(this.current) {
-                    Ordering::Less => {
-                        debug!(
-                            "Received outdated item. Got {:?}. Expect {:?}.",
-                            key, this.current
-                        );
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some((key, value))) => {
+                    match key.cmp(this.current) {
+                        Ordering::Less => {
+                            debug!(
+                                "Received outdated item. Got {:?}. Expect {:?}.",
+                                key, this.current
+                            );
+                        }
+                        Ordering::Equal => {
+                            advance!();
+                            return Poll::Ready(Some(value));
+                        }
+                        Ordering::Greater => {
+                            this.cache.insert(key, value);
+                        }
                     }
-                    Ordering::Equal => {
-                        *this.current = (this.next_key_fn)(this.current);
+                    continue;
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => {}
+            }
+
+            // There is a gap only if some later items are waiting.
+            let handler = match this.gap_handler.as_ref() {
+                Some(handler) if !this.cache.is_empty() => handler,
+                _ => return Poll::Pending,
+            };
+
+            if let Some(gap_fut) = this.gap_fut.as_mut() {
+                let resolution = match gap_fut.as_mut().poll(cx) {
+                    Poll::Ready(resolution) => resolution,
+                    Poll::Pending => return Poll::Pending,
+                };
+                *this.gap_fut = None;
+                match resolution {
+                    GapResolution::Fill(value) => {
+                        advance!();
                         return Poll::Ready(Some(value));
                     }
-                    Ordering::Greater => {
-                        this.cache.insert(key, value);
+                    GapResolution::Skip => {
+                        warn!("Skip the missing item {:?}.", this.current);
+                        advance!();
                     }
+                    GapResolution::Wait => {}
                 }
-            } else {
-                return Poll::Ready(None);
+                continue;
             }
+
+            let gap_timeout = *this.gap_timeout;
+            let gap_timer = this
+                .gap_timer
+                .get_or_insert_with(|| Box::pin(sleep(gap_timeout)));
+            if gap_timer.as_mut().poll(cx).is_ready() {
+                debug!("Timeout in waiting for item {:?}.", this.current);
+                *this.gap_timer = None;
+                *this.gap_fut = Some(handler(this.current));
+                continue;
+            }
+
+            return Poll::Pending;
         }
     }
 }
@@ -113,4 +196,41 @@ mod tests {
 
         handle.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_ordered_stream_gap() {
+        let (mut tx, rx) = mpsc::unbounded::<(i32, i32)>();
+        let mut stream = OrderedStream::new(rx, 0, |x: &i32| x + 1).with_gap_handler(
+            Duration::from_millis(50),
+            |&key: &i32| {
+                async move {
+                    match key {
+                        1 => GapResolution::Fill(1),
+                        3 => GapResolution::Skip,
+                        _ => GapResolution::Wait,
+                    }
+                }
+                .boxed()
+            },
+        );
+
+        let handle = tokio::spawn(async move {
+            for i in [0, 1, 2, 4, 5, 6] {
+                assert_eq!(Some(i), stream.next().await);
+            }
+            assert_eq!(None, stream.next().await);
+        });
+
+        tx.send((0, 0)).await.unwrap();
+        tx.send((2, 2)).await.unwrap();
+        tx.send((4, 4)).await.unwrap();
+        tx.send((6, 6)).await.unwrap();
+        // 5 is not resolved by the handler, so it waits until 5 is resent.
+        sleep(Duration::from_millis(300)).await;
+        tx.send((5, 5)).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        tx.close_channel();
+
+        handle.await.unwrap();
+    }
 }
@@ -0,0 +1,112 @@
+// Export the spans to an OpenTelemetry collector (e.g., Jaeger) over OTLP, and carry the trace
+// context across the nodes in the W3C `traceparent` header of the HTTP RPCs.
+//
+// It requires the `otel` feature, and is enabled at runtime by setting
+// `OTEL_EXPORTER_OTLP_ENDPOINT`, e.g., `http://127.0.0.1:4317`. The service name is read from
+// `OTEL_SERVICE_NAME` (default `slimchain`). Without them, all the functions below are no-op.
+
+use slimchain_common::error::Result;
+use tracing::{Span, Subscriber};
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+pub const OTEL_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const OTEL_SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+#[cfg(feature = "otel")]
+pub fn layer<S>() -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    use opentelemetry::{
+        global,
+        sdk::{propagation::TraceContextPropagator, trace, Resource},
+        KeyValue,
+    };
+    use opentelemetry_otlp::WithExportConfig;
+    use slimchain_common::error::Error;
+
+    let endpoint = match std::env::var(OTEL_ENDPOINT_ENV) {
+        Ok(endpoint) => endpoint,
+        Err(_) => return Ok(None),
+    };
+    let service_name =
+        std::env::var(OTEL_SERVICE_NAME_ENV).unwrap_or_else(|_| "slimchain".to_string());
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name,
+            )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(Error::msg)?;
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn layer<S>() -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if std::env::var_os(OTEL_ENDPOINT_ENV).is_some() {
+        eprintln!(
+            "{} is ignored. Rebuild with the otel feature to export the traces.",
+            OTEL_ENDPOINT_ENV
+        );
+    }
+    Ok(None::<tracing_subscriber::layer::Identity>)
+}
+
+// Flush the pending spans before the process exits.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+// The headers carrying the context of the current span to the peer.
+pub fn trace_context_headers() -> Vec<(String, String)> {
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::global;
+        use std::collections::HashMap;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let cx = Span::current().context();
+        let mut headers = HashMap::new();
+        global::get_text_map_propagator(|propagator| propagator.inject_context(&cx, &mut headers));
+        headers.into_iter().collect()
+    }
+
+    #[cfg(not(feature = "otel"))]
+    Vec::new()
+}
+
+// Continue the trace of the peer in `span`, given the headers of its request.
+pub fn set_parent_from_headers(span: &Span, get_header: impl Fn(&str) -> Option<String>) {
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::global;
+        use std::collections::HashMap;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let cx = global::get_text_map_propagator(|propagator| {
+            let headers: HashMap<String, String> = propagator
+                .fields()
+                .filter_map(|name| get_header(name).map(|value| (name.to_string(), value)))
+                .collect();
+            propagator.extract(&headers)
+        });
+        span.set_parent(cx);
+    }
+
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, get_header);
+}
@@ -0,0 +1,404 @@
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// Upper bounds (in seconds) of the buckets of the timings.
+const TIME_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+// Upper bounds (in bytes) of the buckets of the proof sizes.
+const SIZE_BUCKETS: &[f64] = &[
+    256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0,
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+type ValueFn = Box<dyn Fn() -> f64 + Send + Sync>;
+
+struct ValueMetric {
+    help: &'static str,
+    kind: MetricKind,
+    value_fn: ValueFn,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(pos) = self.bounds.iter().position(|&bound| value <= bound) {
+            self.counts[pos] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter()) {
+            cumulative += count;
+            writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, sep, bound, cumulative
+            )
+            .ok();
+        }
+        writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, sep, self.count
+        )
+        .ok();
+        writeln!(out, "{}_sum{} {}", name, braced(labels), self.sum).ok();
+        writeln!(out, "{}_count{} {}", name, braced(labels), self.count).ok();
+    }
+}
+
+struct Registry {
+    events: BTreeMap<String, u64>,
+    timings: BTreeMap<String, Histogram>,
+    proof_sizes: BTreeMap<String, Histogram>,
+    committed_txs: u64,
+    committed_blocks: u64,
+    block_height: u64,
+    last_commit: Option<Instant>,
+    block_interval: Histogram,
+    values: BTreeMap<&'static str, ValueMetric>,
+}
+
+impl Registry {
+    fn new() -> Self {
+        Self {
+            events: BTreeMap::new(),
+            timings: BTreeMap::new(),
+            proof_sizes: BTreeMap::new(),
+            committed_txs: 0,
+            committed_blocks: 0,
+            block_height: 0,
+            last_commit: None,
+            block_interval: Histogram::new(TIME_BUCKETS),
+            values: BTreeMap::new(),
+        }
+    }
+
+    fn observe(&mut self, entry: &JsonValue, now: Instant) {
+        let label = match entry["l"].as_str() {
+            Some(label) => label,
+            None => return,
+        };
+
+        match entry["k"].as_str() {
+            Some("event") => *self.events.entry(label.to_string()).or_default() += 1,
+            Some("time") => {
+                if let Some(t_in_us) = entry["t_in_us"].as_u64() {
+                    self.timings
+                        .entry(label.to_string())
+                        .or_insert_with(|| Histogram::new(TIME_BUCKETS))
+                        .observe(t_in_us as f64 / 1_000_000.0);
+                }
+            }
+            _ => return,
+        }
+
+        let fields = &entry["v"];
+        if let Some(size) = fields["proof_size"].as_u64() {
+            self.proof_sizes
+                .entry(label.to_string())
+                .or_insert_with(|| Histogram::new(SIZE_BUCKETS))
+                .observe(size as f64);
+        }
+
+        if label == "tx_commit" {
+            let num_txs = fields["tx_ids"].as_array().map_or(0, |ids| ids.len());
+            self.committed_txs += num_txs as u64;
+            self.committed_blocks += 1;
+            if let Some(height) = fields["height"].as_u64() {
+                self.block_height = self.block_height.max(height);
+            }
+            if let Some(last) = self.last_commit.replace(now) {
+                self.block_interval
+                    .observe(now.saturating_duration_since(last).as_secs_f64());
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "slimchain_events_total",
+            "Number of the recorded events by label.",
+            "counter",
+        );
+        for (label, count) in &self.events {
+            writeln!(
+                out,
+                "slimchain_events_total{{label=\"{}\"}} {}",
+                escape(label),
+                count
+            )
+            .ok();
+        }
+
+        header(
+            &mut out,
+            "slimchain_time_seconds",
+            "The recorded timings by label, e.g., mining, verify_block and exec_time.",
+            "histogram",
+        );
+        for (label, hist) in &self.timings {
+            hist.render(
+                &mut out,
+                "slimchain_time_seconds",
+                &format!("label=\"{}\"", escape(label)),
+            );
+        }
+
+        header(
+            &mut out,
+            "slimchain_proof_size_bytes",
+            "Size of the proofs attached to the recorded entries by label.",
+            "histogram",
+        );
+        for (label, hist) in &self.proof_sizes {
+            hist.render(
+                &mut out,
+                "slimchain_proof_size_bytes",
+                &format!("label=\"{}\"", escape(label)),
+            );
+        }
+
+        header(
+            &mut out,
+            "slimchain_committed_txs_total",
+            "Number of the committed txs.",
+            "counter",
+        );
+        writeln!(out, "slimchain_committed_txs_total {}", self.committed_txs).ok();
+
+        header(
+            &mut out,
+            "slimchain_committed_blocks_total",
+            "Number of the committed blocks.",
+            "counter",
+        );
+        writeln!(
+            out,
+            "slimchain_committed_blocks_total {}",
+            self.committed_blocks
+        )
+        .ok();
+
+        header(
+            &mut out,
+            "slimchain_block_height",
+            "Height of the latest committed block.",
+            "gauge",
+        );
+        writeln!(out, "slimchain_block_height {}", self.block_height).ok();
+
+        header(
+            &mut out,
+            "slimchain_block_interval_seconds",
+            "Time between two consecutive block commits.",
+            "histogram",
+        );
+        self.block_interval
+            .render(&mut out, "slimchain_block_interval_seconds", "");
+
+        for (name, metric) in &self.values {
+            header(&mut out, name, metric.help, metric.kind.as_str());
+            writeln!(out, "{} {}", name, (metric.value_fn)()).ok();
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// Start aggregating the entries recorded by `record_event!` and `record_time!`. Nothing is
+// kept before it is called, so that the nodes not scraped pay nothing.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+#[doc(hidden)]
+pub fn observe_entry(entry: &JsonValue) {
+    if !is_enabled() {
+        return;
+    }
+    REGISTRY
+        .lock()
+        .expect("Failed to lock prometheus registry.")
+        .observe(entry, Instant::now());
+}
+
+// Expose a value maintained elsewhere, e.g., the queue depth. It is read on each scrape.
+// Registering the same name again replaces the previous one.
+pub fn register_value(
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    value_fn: impl Fn() -> f64 + Send + Sync + 'static,
+) {
+    REGISTRY
+        .lock()
+        .expect("Failed to lock prometheus registry.")
+        .values
+        .insert(
+            name,
+            ValueMetric {
+                help,
+                kind,
+                value_fn: Box::new(value_fn),
+            },
+        );
+}
+
+// Render the metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    REGISTRY
+        .lock()
+        .expect("Failed to lock prometheus registry.")
+        .render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_histogram() {
+        let mut hist = Histogram::new(&[1.0, 2.0]);
+        hist.observe(0.5);
+        hist.observe(1.5);
+        hist.observe(3.0);
+        let mut out = String::new();
+        hist.render(&mut out, "foo", "label=\"a\"");
+        assert_eq!(
+            "foo_bucket{label=\"a\",le=\"1\"} 1\n\
+             foo_bucket{label=\"a\",le=\"2\"} 2\n\
+             foo_bucket{label=\"a\",le=\"+Inf\"} 3\n\
+             foo_sum{label=\"a\"} 5\n\
+             foo_count{label=\"a\"} 3\n",
+            out
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        let now = Instant::now();
+        registry.observe(&json!({"k": "event", "l": "tx_begin", "v": {}}), now);
+        registry.observe(&json!({"k": "event", "l": "tx_begin", "v": {}}), now);
+        registry.observe(
+            &json!({"k": "time", "l": "mining", "t_in_us": 1_500_000, "v": {"height": 1}}),
+            now,
+        );
+        registry.observe(
+            &json!({"k": "event", "l": "propose_end", "v": {"height": 1, "proof_size": 2000}}),
+            now,
+        );
+        registry.observe(
+            &json!({"k": "event", "l": "tx_commit", "v": {"tx_ids": ["a", "b"], "height": 1}}),
+            now,
+        );
+        registry.observe(
+            &json!({"k": "event", "l": "tx_commit", "v": {"tx_ids": ["c"], "height": 2}}),
+            now + Duration::from_millis(300),
+        );
+        registry.observe(&json!({"k": "event", "l": "say \"hi\"", "v": {}}), now);
+        registry.values.insert(
+            "slimchain_test_value",
+            ValueMetric {
+                help: "A test value.",
+                kind: MetricKind::Gauge,
+                value_fn: Box::new(|| 42.0),
+            },
+        );
+
+        let out = registry.render();
+        assert!(out.contains("slimchain_events_total{label=\"tx_begin\"} 2\n"));
+        assert!(out.contains("slimchain_events_total{label=\"say \\\"hi\\\"\"} 1\n"));
+        assert!(out.contains("slimchain_time_seconds_bucket{label=\"mining\",le=\"1\"} 0\n"));
+        assert!(out.contains("slimchain_time_seconds_bucket{label=\"mining\",le=\"2.5\"} 1\n"));
+        assert!(out.contains("slimchain_time_seconds_sum{label=\"mining\"} 1.5\n"));
+        assert!(out
+            .contains("slimchain_proof_size_bytes_bucket{label=\"propose_end\",le=\"4096\"} 1\n"));
+        assert!(out.contains("slimchain_committed_txs_total 3\n"));
+        assert!(out.contains("slimchain_committed_blocks_total 2\n"));
+        assert!(out.contains("slimchain_block_height 2\n"));
+        assert!(out.contains("slimchain_block_interval_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(out.contains("slimchain_block_interval_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(out.contains("slimchain_block_interval_seconds_count 1\n"));
+        assert!(out.contains("# TYPE slimchain_test_value gauge\nslimchain_test_value 42\n"));
+    }
+}
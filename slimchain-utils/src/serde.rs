@@ -13,6 +13,20 @@ pub fn binary_decode<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T> {
     bincode::deserialize_from(decoder).map_err(Error::msg)
 }
 
+// Size of the value encoded by `binary_encode_raw`, without allocating the buffer.
+pub fn serialized_size<T: Serialize>(value: &T) -> Result<u64> {
+    bincode::serialized_size(value).map_err(Error::msg)
+}
+
+// Encode without compression, for callers that compress the bytes on their own.
+pub fn binary_encode_raw<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    bincode::serialize(value).map_err(Error::msg)
+}
+
+pub fn binary_decode_raw<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T> {
+    bincode::deserialize(bytes).map_err(Error::msg)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -22,5 +36,8 @@ mod tests {
         let value = String::from("hello world");
         let bin = binary_encode(&value).unwrap();
         assert_eq!(binary_decode::<String>(bin.as_ref()).unwrap(), value);
+        let bin = binary_encode_raw(&value).unwrap();
+        assert_eq!(binary_decode_raw::<String>(bin.as_ref()).unwrap(), value);
+        assert_eq!(serialized_size(&value).unwrap(), bin.len() as u64);
     }
 }
@@ -0,0 +1,8 @@
+use slimchain_common::error::Result;
+
+use slimchain_chain::consensus::{pow::Block, Consensus};
+use slimchain_tee_sig::TEESignedTx as Tx;
+
+fn main() -> Result<()> {
+    slimchain::export::export_main::<Tx, Block>(Consensus::PoW)
+}
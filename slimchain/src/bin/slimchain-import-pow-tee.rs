@@ -0,0 +1,11 @@
+use slimchain_common::error::Result;
+
+use slimchain_chain::consensus::{
+    pow::{verify_consensus, Block},
+    Consensus,
+};
+use slimchain_tee_sig::TEESignedTx as Tx;
+
+fn main() -> Result<()> {
+    slimchain::export::import_main::<Tx, Block>(Consensus::PoW, verify_consensus)
+}
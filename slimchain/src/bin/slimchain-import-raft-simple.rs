@@ -0,0 +1,11 @@
+use slimchain_common::error::Result;
+
+use slimchain_chain::consensus::{
+    raft::{verify_consensus, Block},
+    Consensus,
+};
+use slimchain_common::tx::SignedTx as Tx;
+
+fn main() -> Result<()> {
+    slimchain::export::import_main::<Tx, Block>(Consensus::Raft, verify_consensus)
+}
@@ -1,4 +1,6 @@
-use slimchain_chain::db::{BLOCK_DB_COL, DB, LOG_DB_COL, META_DB_COL, STATE_DB_COL, TX_DB_COL};
+use slimchain_chain::db::{
+    BLOCK_DB_COL, CODE_DB_COL, DB, LOG_DB_COL, META_DB_COL, RECEIPT_DB_COL, STATE_DB_COL, TX_DB_COL,
+};
 use slimchain_common::{
     basic::BlockHeight,
     error::{bail, Context as _, Result},
@@ -45,7 +47,9 @@ fn main() -> Result<()> {
     let block_db_size = db.get_table_size(BLOCK_DB_COL);
     let tx_db_size = db.get_table_size(TX_DB_COL);
     let state_db_size = db.get_table_size(STATE_DB_COL);
-    let chain_db_size = block_db_size + tx_db_size + state_db_size;
+    let receipt_db_size = db.get_table_size(RECEIPT_DB_COL);
+    let code_db_size = db.get_table_size(CODE_DB_COL);
+    let chain_db_size = block_db_size + tx_db_size + state_db_size + code_db_size;
 
     println!("Database size breakdown:");
     println!(" Height = {}", height);
@@ -67,7 +71,13 @@ fn main() -> Result<()> {
         state_db_size as f64 / height.0 as f64
     );
     println!(
-        " BLOCK + TX + STATE = {} ({} per block)",
+        " RECEIPT = {} ({} per block)",
+        receipt_db_size,
+        receipt_db_size as f64 / height.0 as f64
+    );
+    println!(" CODE = {}", code_db_size);
+    println!(
+        " BLOCK + TX + STATE + CODE = {} ({} per block)",
         chain_db_size,
         chain_db_size as f64 / height.0 as f64
     );
@@ -80,6 +90,8 @@ fn main() -> Result<()> {
             "block_db_size": block_db_size,
             "tx_db_size": tx_db_size,
             "state_db_size": state_db_size,
+            "receipt_db_size": receipt_db_size,
+            "code_db_size": code_db_size,
             "chain_db_size": chain_db_size,
         });
 
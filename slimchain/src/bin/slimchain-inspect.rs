@@ -0,0 +1,343 @@
+use slimchain_chain::{
+    block::BlockTrait,
+    consensus::{pow, raft, Consensus},
+    db::DB,
+    loader::BlockLoaderTrait,
+};
+use slimchain_common::{
+    basic::{AccountData, Address, BlockHeight, StateKey, H160, H256},
+    digest::Digestible,
+    error::{anyhow, bail, ensure, Context as _, Result},
+};
+use slimchain_merkle_trie::{
+    draw::Graph,
+    prelude::{read_trie_without_proof, NodeLoader, TrieNode},
+    traits::Value,
+    u4::U4,
+};
+use slimchain_tx_state::{
+    read_code,
+    trie_view::{AccountTrieView, StateTrieView},
+};
+use slimchain_utils::{cli::DigestOpts, init_tracing_subscriber};
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(version = git_version::git_version!(prefix = concat!(env!("CARGO_PKG_VERSION"), " ("), suffix = ")", fallback = "unknown"))]
+struct Opts {
+    /// Path to the database of the node, e.g., storage.db. It is opened read-only.
+    #[structopt(short, long, parse(from_os_str))]
+    db_path: PathBuf,
+
+    /// The consensus of the chain, which decides the block format. Possible values: raft, pow.
+    #[structopt(short, long, default_value = "raft", parse(try_from_str = parse_consensus))]
+    consensus: Consensus,
+
+    #[structopt(flatten)]
+    digest: DigestOpts,
+
+    /// Set trace log level. Default: no tracing.
+    #[structopt(long)]
+    log_level: Option<String>,
+
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Print the block headers.
+    Headers {
+        /// Start block
+        #[structopt(short, long, default_value = "0")]
+        start: BlockHeight,
+
+        /// End block. Default: the latest block.
+        #[structopt(short, long)]
+        end: Option<BlockHeight>,
+    },
+
+    /// Dump the state of an account.
+    Account {
+        /// The account address.
+        address: Address,
+
+        /// The block height of the state. Default: the latest block.
+        #[structopt(long)]
+        height: Option<BlockHeight>,
+
+        /// Also dump the storage of the account.
+        #[structopt(long)]
+        storage: bool,
+    },
+
+    /// Recompute the state root from the trie nodes, and verify it against the block header.
+    StateRoot {
+        /// The block height of the state. Default: the latest block.
+        #[structopt(long)]
+        height: Option<BlockHeight>,
+    },
+
+    /// Render the account trie, or the storage trie of an account, in the DOT format.
+    Draw {
+        /// The block height of the state. Default: the latest block.
+        #[structopt(long)]
+        height: Option<BlockHeight>,
+
+        /// Render the storage trie of the account instead.
+        #[structopt(long)]
+        account: Option<Address>,
+
+        /// Path to output.dot. Default: stdout.
+        #[structopt(short, long, parse(from_os_str))]
+        out: Option<PathBuf>,
+    },
+}
+
+fn parse_consensus(input: &str) -> Result<Consensus> {
+    match input.to_lowercase().as_str() {
+        "raft" => Ok(Consensus::Raft),
+        "pow" => Ok(Consensus::PoW),
+        _ => bail!("Unknown consensus: {}.", input),
+    }
+}
+
+fn main() -> Result<()> {
+    color_backtrace::install();
+
+    let opts = Opts::from_args();
+    opts.digest.install_as_global();
+
+    if let Some(log_level) = opts.log_level.as_deref() {
+        init_tracing_subscriber(log_level)?;
+    }
+
+    if !opts.db_path.exists() {
+        bail!("DB {:?} not existed.", opts.db_path);
+    }
+    let db = DB::open_read_only(&opts.db_path)?;
+
+    match opts.consensus {
+        Consensus::Raft => run::<raft::Block>(&db, &opts.cmd),
+        Consensus::PoW => run::<pow::Block>(&db, &opts.cmd),
+    }
+}
+
+fn run<Block>(db: &DB, cmd: &Command) -> Result<()>
+where
+    Block: BlockTrait + for<'de> serde::Deserialize<'de>,
+{
+    let latest_height: BlockHeight = db
+        .get_existing_meta_object("height")
+        .context("Failed to get block height from the database.")?;
+    let state_root_at = |height: Option<BlockHeight>| -> Result<H256> {
+        let height = height.unwrap_or(latest_height);
+        ensure!(
+            height <= latest_height,
+            "Block #{} is beyond the latest block #{}.",
+            height,
+            latest_height
+        );
+        let block: Block = db.get_block(height)?;
+        Ok(block.state_root())
+    };
+
+    match cmd {
+        Command::Headers { start, end } => {
+            let end = end.unwrap_or(latest_height).min(latest_height);
+            for height in start.0..=end.0 {
+                let block: Block = db.get_block(height.into())?;
+                println!("Block #{}", block.block_height());
+                println!(" hash = {:#x}", block.to_digest());
+                println!(" prev_blk_hash = {:#x}", block.prev_blk_hash());
+                println!(" time_stamp = {}", block.time_stamp());
+                println!(" #tx = {}", block.tx_list().len());
+                println!(" tx_root = {:#x}", block.tx_root());
+                println!(" state_root = {:#x}", block.state_root());
+                println!(" epoch_root = {:#x}", block.epoch_root());
+            }
+        }
+        Command::Account {
+            address,
+            height,
+            storage,
+        } => {
+            let state_root = state_root_at(*height)?;
+            let acc_view = AccountTrieView::new(db);
+            let acc_data: AccountData =
+                match read_trie_without_proof(&acc_view, state_root, address)? {
+                    Some(acc_data) => acc_data,
+                    None => {
+                        println!("Account {} does not exist.", address);
+                        return Ok(());
+                    }
+                };
+            let code = read_code(db, acc_data.code_hash)?;
+            println!("Account {}", address);
+            println!(" nonce = {}", acc_data.nonce);
+            println!(
+                " code_hash = {:#x} ({} bytes)",
+                acc_data.code_hash,
+                code.len()
+            );
+            println!(" state_root = {:#x}", acc_data.acc_state_root);
+
+            if *storage {
+                let state_view = StateTrieView::new(db, *address);
+                let mut stats = TrieStats::default();
+                for (nibbles, value) in walk_trie(&state_view, acc_data.acc_state_root, &mut stats)?
+                {
+                    let key = StateKey(H256::from_slice(&nibbles_to_bytes(&nibbles, 32)?));
+                    println!(" {:#x} = {:#x}", key.0, value.0);
+                }
+                stats.ensure_valid()?;
+            }
+        }
+        Command::StateRoot { height } => {
+            let state_root = state_root_at(*height)?;
+            let acc_view = AccountTrieView::new(db);
+            let mut acc_stats = TrieStats::default();
+            let mut state_stats = TrieStats::default();
+            let mut missing_code = 0;
+            let accounts = walk_trie(&acc_view, state_root, &mut acc_stats)?;
+            for (nibbles, acc_data) in &accounts {
+                let address = Address(H160::from_slice(&nibbles_to_bytes(nibbles, 20)?));
+                let state_view = StateTrieView::new(db, address);
+                walk_trie(&state_view, acc_data.acc_state_root, &mut state_stats)?;
+                if read_code(db, acc_data.code_hash).is_err() {
+                    println!("Code {:#x} of {} is missing.", acc_data.code_hash, address);
+                    missing_code += 1;
+                }
+            }
+
+            println!("State at {:#x}:", state_root);
+            println!(" #account = {}", accounts.len());
+            println!(" #account_trie_node = {}", acc_stats.nodes);
+            println!(" #state_trie_node = {}", state_stats.nodes);
+            println!(" computed_root = {:#x}", acc_stats.root);
+            acc_stats.ensure_valid()?;
+            state_stats.ensure_valid()?;
+            ensure!(
+                missing_code == 0,
+                "{} code entries are missing.",
+                missing_code
+            );
+            ensure!(
+                acc_stats.root == state_root,
+                "The computed state root {:#x} does not match the block header.",
+                acc_stats.root
+            );
+            println!("The state root is verified.");
+        }
+        Command::Draw {
+            height,
+            account,
+            out,
+        } => {
+            let state_root = state_root_at(*height)?;
+            let acc_view = AccountTrieView::new(db);
+            let graph = match account {
+                Some(address) => {
+                    let acc_data: AccountData =
+                        read_trie_without_proof(&acc_view, state_root, address)?
+                            .ok_or_else(|| anyhow!("Account {} does not exist.", address))?;
+                    let state_view = StateTrieView::new(db, *address);
+                    Graph::from_trie("state_trie", &state_view, acc_data.acc_state_root)?
+                }
+                None => Graph::from_trie("account_trie", &acc_view, state_root)?,
+            };
+            let dot = graph.to_dot(false);
+            match out {
+                Some(out_path) => fs::write(out_path, dot)?,
+                None => print!("{}", dot),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct TrieStats {
+    nodes: usize,
+    // The digest of the root node, which should equal the root address.
+    root: H256,
+    // The nodes whose digest differs from the address they are stored at.
+    mismatched: Vec<H256>,
+}
+
+impl TrieStats {
+    fn ensure_valid(&self) -> Result<()> {
+        for address in &self.mismatched {
+            println!("Node {:#x} does not match its digest.", address);
+        }
+        ensure!(
+            self.mismatched.is_empty(),
+            "{} trie nodes are corrupted.",
+            self.mismatched.len()
+        );
+        Ok(())
+    }
+}
+
+// Visit all the nodes reachable from the root, and return the leaves along with their full nibbles.
+fn walk_trie<V: Value>(
+    loader: &impl NodeLoader<V>,
+    root: H256,
+    stats: &mut TrieStats,
+) -> Result<Vec<(Vec<U4>, V)>> {
+    let mut leaves = Vec::new();
+    let mut stack: Vec<(H256, Vec<U4>)> = vec![(root, Vec::new())];
+
+    while let Some((address, nibbles)) = stack.pop() {
+        let node = match loader.check_address_and_load_node(address)? {
+            Some(node) => node,
+            None => continue,
+        };
+        stats.nodes += 1;
+        let digest = node.to_digest();
+        if address == root {
+            stats.root = digest;
+        }
+        if digest != address {
+            stats.mismatched.push(address);
+        }
+
+        match node {
+            TrieNode::Extension(n) => {
+                let mut c_nibbles = nibbles;
+                c_nibbles.extend(n.nibbles.iter());
+                stack.push((n.child, c_nibbles));
+            }
+            TrieNode::Branch(n) => {
+                for (i, child) in n.children.iter().enumerate() {
+                    if let Some(c) = child {
+                        let mut c_nibbles = nibbles.clone();
+                        c_nibbles.push(U4::from_u8(i as u8));
+                        stack.push((*c, c_nibbles));
+                    }
+                }
+            }
+            TrieNode::Leaf(n) => {
+                let mut c_nibbles = nibbles;
+                c_nibbles.extend(n.nibbles.iter());
+                leaves.push((c_nibbles, n.value));
+            }
+        }
+    }
+
+    Ok(leaves)
+}
+
+fn nibbles_to_bytes(nibbles: &[U4], len: usize) -> Result<Vec<u8>> {
+    ensure!(
+        nibbles.len() == len * 2,
+        "Invalid key length: {} nibbles.",
+        nibbles.len()
+    );
+    Ok(nibbles
+        .chunks(2)
+        .map(|pair| (u8::from(pair[0]) << 4) | u8::from(pair[1]))
+        .collect())
+}
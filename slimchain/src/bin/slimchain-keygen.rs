@@ -0,0 +1,470 @@
+use slimchain_chain::{
+    config::ChainConfig, consensus::Consensus, genesis::GENESIS_FILE_NAME, role::Role,
+};
+use slimchain_common::{
+    basic::{Address, ShardId},
+    ed25519,
+    error::{bail, ensure, Context as _, Result},
+    hd::{seed_from_mnemonic, DerivationPath},
+    secp256k1,
+    tx_req::caller_address_from_pk,
+    utils::hex,
+};
+use slimchain_network::p2p::config::KeypairConfig;
+use slimchain_utils::config::{Config, CONFIG_FILE_NAME};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(version = git_version::git_version!(prefix = concat!(env!("CARGO_PKG_VERSION"), " ("), suffix = ")", fallback = "unknown"))]
+enum Opts {
+    /// Generate a libp2p identity, used by the PoW nodes.
+    Network {
+        /// Output in toml format.
+        #[structopt(short, long)]
+        toml: bool,
+    },
+
+    /// Generate the account keys signing the txs.
+    Account {
+        /// Signature scheme. Possible values: ed25519, secp256k1.
+        #[structopt(short, long, default_value = "ed25519", parse(try_from_str = parse_key_scheme))]
+        scheme: KeyScheme,
+
+        /// Number of accounts.
+        #[structopt(short = "n", long, default_value = "1")]
+        count: u32,
+
+        /// BIP-39 mnemonic to derive the accounts at m/44'/60'/0'/0'/i', the same as
+        /// slimchain-send-tx, instead of generating them randomly.
+        #[structopt(long, env = "SLIMCHAIN_MNEMONIC", hide_env_values = true)]
+        mnemonic: Option<String>,
+    },
+
+    /// Print the stub of the TEE config, used by the storage nodes with TEE.
+    Tee,
+
+    /// Generate config.toml and genesis.toml of every node in a deployment.
+    Deploy(DeployOpts),
+}
+
+#[derive(Debug, StructOpt)]
+struct DeployOpts {
+    /// Consensus method. Possible values: raft, pow.
+    #[structopt(short, long, default_value = "raft", parse(try_from_str = parse_consensus))]
+    consensus: Consensus,
+
+    /// Number of client nodes.
+    #[structopt(long, default_value = "1")]
+    clients: usize,
+
+    /// Number of miner nodes. Only used by PoW.
+    #[structopt(long, default_value = "1")]
+    miners: usize,
+
+    /// Number of storage shards.
+    #[structopt(long, default_value = "1")]
+    shards: u64,
+
+    /// Number of storage nodes in each shard.
+    #[structopt(long, default_value = "1")]
+    replicas: usize,
+
+    /// Hosts of the nodes, assigned in turn. The nodes on the same host listen on the
+    /// consecutive ports.
+    #[structopt(long, default_value = "127.0.0.1", use_delimiter = true)]
+    hosts: Vec<String>,
+
+    /// The first port of the HTTP servers.
+    #[structopt(long, default_value = "8000")]
+    http_port: u16,
+
+    /// The first port of the libp2p listeners. Only used by PoW.
+    #[structopt(long, default_value = "6000")]
+    p2p_port: u16,
+
+    /// The chain id in genesis.toml.
+    #[structopt(long, default_value = "0")]
+    chain_id: u64,
+
+    /// Add the TEE config stub to the storage nodes.
+    #[structopt(long)]
+    tee: bool,
+
+    /// Output directory. The files of each node are put in its own sub-directory.
+    #[structopt(short, long, parse(from_os_str))]
+    out_dir: PathBuf,
+
+    /// Overwrite the existing files.
+    #[structopt(short, long)]
+    force: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum KeyScheme {
+    Ed25519,
+    Secp256k1,
+}
+
+fn parse_key_scheme(input: &str) -> Result<KeyScheme> {
+    match input.to_lowercase().as_str() {
+        "ed25519" => Ok(KeyScheme::Ed25519),
+        "secp256k1" => Ok(KeyScheme::Secp256k1),
+        _ => bail!("Unknown signature scheme: {}.", input),
+    }
+}
+
+fn parse_consensus(input: &str) -> Result<Consensus> {
+    match input.to_lowercase().as_str() {
+        "raft" => Ok(Consensus::Raft),
+        "pow" => Ok(Consensus::PoW),
+        _ => bail!("Unknown consensus: {}.", input),
+    }
+}
+
+fn main() -> Result<()> {
+    color_backtrace::install();
+
+    match Opts::from_args() {
+        Opts::Network { toml } => {
+            let keypair = KeypairConfig::generate();
+            keypair.print_config_msg(toml);
+        }
+        Opts::Account {
+            scheme,
+            count,
+            mnemonic,
+        } => gen_accounts(scheme, count, mnemonic.as_deref())?,
+        Opts::Tee => print!("{}", tee_config_stub()),
+        Opts::Deploy(opts) => deploy(&opts)?,
+    }
+
+    Ok(())
+}
+
+fn gen_accounts(scheme: KeyScheme, count: u32, mnemonic: Option<&str>) -> Result<()> {
+    let seed = mnemonic
+        .map(|mnemonic| seed_from_mnemonic(mnemonic, ""))
+        .transpose()?;
+    let mut rng = rand::thread_rng();
+
+    for i in 0..count {
+        let path = DerivationPath::bip44(0, i, true);
+        let (address, public, secret): (Address, Vec<u8>, Vec<u8>) = match scheme {
+            KeyScheme::Ed25519 => {
+                let keypair = match seed.as_deref() {
+                    Some(seed) => ed25519::derive_keypair(seed, &path)?,
+                    None => ed25519::Keypair::generate(&mut rng),
+                };
+                (
+                    caller_address_from_pk(&keypair.public),
+                    keypair.public.to_bytes().to_vec(),
+                    keypair.secret.to_bytes().to_vec(),
+                )
+            }
+            KeyScheme::Secp256k1 => {
+                let keypair = match seed.as_deref() {
+                    Some(seed) => secp256k1::derive_keypair(seed, &path)?,
+                    None => secp256k1::Keypair::generate(&mut rng),
+                };
+                (
+                    caller_address_from_pk(&keypair.public),
+                    keypair.public.serialize_compressed().to_vec(),
+                    keypair.secret.serialize().to_vec(),
+                )
+            }
+        };
+
+        println!("# Account {}", i);
+        println!("address = \"{}\"", address);
+        println!("public_key = \"0x{}\"", hex::encode(public));
+        println!("secret_key = \"0x{}\"", hex::encode(secret));
+        println!();
+    }
+
+    Ok(())
+}
+
+fn tee_config_stub() -> String {
+    let mut out = String::new();
+    out.push_str(
+        "# Obtain keys from https://api.portal.trustedservices.intel.com/EPID-attestation\n",
+    );
+    out.push_str("[tee]\n");
+    out.push_str("# Subscription Key that provides access to the Intel API\n");
+    out.push_str("api_key = \"YOUR_API_KEY\"\n");
+    out.push_str("# Service Provider ID (SPID) in hex\n");
+    out.push_str("spid = \"YOUR_SPID\"\n");
+    out.push_str("# Whether to sign linkable quote\n");
+    out.push_str("linkable = false\n");
+    out
+}
+
+struct NodeSpec {
+    name: String,
+    // The peer id in raft. The PoW nodes are identified by their libp2p keypairs instead.
+    peer_id: u64,
+    role: Role,
+    host: String,
+    http_port: u16,
+    p2p_port: u16,
+    keypair: KeypairConfig,
+}
+
+impl NodeSpec {
+    fn libp2p_peer_id(&self) -> String {
+        self.keypair
+            .to_libp2p_keypair()
+            .public()
+            .into_peer_id()
+            .to_base58()
+    }
+
+    fn http_address(&self) -> String {
+        format!("{}:{}", self.host, self.http_port)
+    }
+
+    fn p2p_address(&self) -> String {
+        let protocol = if self.host.parse::<Ipv4Addr>().is_ok() {
+            "ip4"
+        } else {
+            "dns4"
+        };
+        format!("/{}/{}/tcp/{}", protocol, self.host, self.p2p_port)
+    }
+}
+
+fn role_config(role: Role) -> String {
+    match role {
+        Role::Client => "role = \"client\"\n".to_string(),
+        Role::LightClient => "role = \"light_client\"\n".to_string(),
+        Role::Miner => "role = \"miner\"\n".to_string(),
+        Role::Auditor => "role = \"auditor\"\n".to_string(),
+        Role::Storage(ShardId { id, total }) => format!(
+            "role = \"storage\"\nshard_id = {}\nshard_total = {}\n",
+            id, total
+        ),
+    }
+}
+
+fn consensus_name(consensus: Consensus) -> &'static str {
+    match consensus {
+        Consensus::Raft => "raft",
+        Consensus::PoW => "pow",
+    }
+}
+
+fn plan_nodes(opts: &DeployOpts) -> Result<Vec<NodeSpec>> {
+    ensure!(opts.clients > 0, "At least one client node is required.");
+    ensure!(
+        opts.shards > 0 && opts.replicas > 0,
+        "At least one storage node is required."
+    );
+    ensure!(!opts.hosts.is_empty(), "At least one host is required.");
+
+    let mut roles = vec![Role::Client; opts.clients];
+    if opts.consensus == Consensus::PoW {
+        ensure!(opts.miners > 0, "At least one miner node is required.");
+        roles.extend(std::iter::repeat(Role::Miner).take(opts.miners));
+    }
+    for shard in 0..opts.shards {
+        roles.extend(
+            std::iter::repeat(Role::Storage(ShardId::new(shard, opts.shards))).take(opts.replicas),
+        );
+    }
+
+    let mut used_ports: HashMap<&str, u16> = HashMap::new();
+    let mut nodes = Vec::with_capacity(roles.len());
+    for (i, role) in roles.into_iter().enumerate() {
+        let host = &opts.hosts[i % opts.hosts.len()];
+        let offset = used_ports.entry(host.as_str()).or_insert(0);
+        let name = match role {
+            Role::Storage(shard_id) => format!("node-{}-storage-{}", i + 1, shard_id.id),
+            _ => format!("node-{}-{}", i + 1, role.to_string().to_lowercase()),
+        };
+        nodes.push(NodeSpec {
+            name,
+            peer_id: i as u64 + 1,
+            role,
+            host: host.clone(),
+            http_port: opts
+                .http_port
+                .checked_add(*offset)
+                .context("HTTP port overflows.")?,
+            p2p_port: opts
+                .p2p_port
+                .checked_add(*offset)
+                .context("libp2p port overflows.")?,
+            keypair: KeypairConfig::generate(),
+        });
+        *offset += 1;
+    }
+    Ok(nodes)
+}
+
+fn node_config(opts: &DeployOpts, nodes: &[NodeSpec], node: &NodeSpec) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "# vim: set ft=toml:")?;
+    writeln!(
+        out,
+        "# Generated by slimchain-keygen. See config-example for the other options."
+    )?;
+    writeln!(out)?;
+    writeln!(out, "[role]")?;
+    out.push_str(&role_config(node.role));
+    writeln!(out)?;
+    writeln!(out, "[chain]")?;
+    writeln!(out, "conflict_check = \"ssi\"")?;
+    writeln!(out, "state_len = 16")?;
+    writeln!(out, "consensus = \"{}\"", consensus_name(opts.consensus))?;
+    writeln!(out)?;
+
+    let mines = match opts.consensus {
+        Consensus::Raft => node.role == Role::Client,
+        Consensus::PoW => node.role == Role::Miner,
+    };
+    if mines {
+        writeln!(out, "[miner]")?;
+        writeln!(out, "compress_trie = true")?;
+        writeln!(out, "max_txs = 256")?;
+        writeln!(out, "min_txs = 1")?;
+        writeln!(out, "max_block_interval = 2000")?;
+        writeln!(out)?;
+    }
+
+    if opts.tee && matches!(node.role, Role::Storage(_)) {
+        out.push_str(&tee_config_stub());
+        writeln!(out)?;
+    }
+
+    writeln!(out, "[network]")?;
+    match opts.consensus {
+        Consensus::Raft => {
+            writeln!(out, "peer_id = {}", node.peer_id)?;
+            writeln!(out, "http_listen = \"0.0.0.0:{}\"", node.http_port)?;
+        }
+        Consensus::PoW => {
+            writeln!(out, "listen = \"/ip4/0.0.0.0/tcp/{}\"", node.p2p_port)?;
+            writeln!(out, "http_listen = \"0.0.0.0:{}\"", node.http_port)?;
+            writeln!(out, "keypair = \"{}\"", node.keypair.to_base58())?;
+            // The peers are listed below. mDNS would only find the ones in the same LAN.
+            writeln!(out, "mdns = false")?;
+        }
+    }
+
+    for peer in nodes.iter().filter(|peer| peer.name != node.name) {
+        writeln!(out)?;
+        writeln!(out, "# {}", peer.name)?;
+        writeln!(out, "[[network.peers]]")?;
+        match opts.consensus {
+            Consensus::Raft => {
+                writeln!(out, "peer_id = {}", peer.peer_id)?;
+                writeln!(out, "address = \"{}\"", peer.http_address())?;
+                out.push_str(&role_config(peer.role));
+            }
+            Consensus::PoW => {
+                writeln!(out, "peer_id = \"{}\"", peer.libp2p_peer_id())?;
+                writeln!(out, "address = \"{}\"", peer.p2p_address())?;
+            }
+        }
+    }
+
+    match opts.consensus {
+        Consensus::Raft => {
+            writeln!(out)?;
+            writeln!(out, "[raft]")?;
+            writeln!(out, "election_timeout_min = 150")?;
+            writeln!(out, "election_timeout_max = 300")?;
+            writeln!(out, "heartbeat_interval = 50")?;
+            writeln!(out, "async_broadcast_storage = true")?;
+        }
+        Consensus::PoW => {
+            writeln!(out)?;
+            writeln!(out, "[pow]")?;
+            writeln!(out, "init_diff = 5000000")?;
+        }
+    }
+
+    Ok(out)
+}
+
+fn genesis_config(opts: &DeployOpts) -> String {
+    format!(
+        "# vim: set ft=toml:\n\
+         # Generated by slimchain-keygen. All nodes in the same chain must use the same file.\n\
+         \n\
+         chain_id = {}\n\
+         shard_total = {}\n",
+        opts.chain_id, opts.shards
+    )
+}
+
+// Read back the generated config as the node does, so that a malformed one fails here instead
+// of on the remote host.
+fn check_node_config(opts: &DeployOpts, cfg_file: &Path, node: &NodeSpec) -> Result<()> {
+    let cfg = Config::load(cfg_file)?;
+    let role: Role = cfg.get("role")?;
+    ensure!(
+        role == node.role,
+        "Role mismatched in {}.",
+        cfg_file.display()
+    );
+    let _: ChainConfig = cfg.get("chain")?;
+    match opts.consensus {
+        Consensus::Raft => {
+            let _: slimchain_network::http::config::NetworkConfig = cfg.get("network")?;
+        }
+        Consensus::PoW => {
+            let _: slimchain_network::p2p::config::NetworkConfig = cfg.get("network")?;
+        }
+    }
+    Ok(())
+}
+
+fn deploy(opts: &DeployOpts) -> Result<()> {
+    let nodes = plan_nodes(opts)?;
+    let genesis = genesis_config(opts);
+
+    for node in &nodes {
+        let node_dir = opts.out_dir.join(&node.name);
+        let cfg_file = node_dir.join(CONFIG_FILE_NAME);
+        if cfg_file.exists() && !opts.force {
+            bail!(
+                "{} already exists. Use --force to overwrite it.",
+                cfg_file.display()
+            );
+        }
+        fs::create_dir_all(&node_dir)?;
+        fs::write(&cfg_file, node_config(opts, &nodes, node)?)?;
+        fs::write(node_dir.join(GENESIS_FILE_NAME), &genesis)?;
+        check_node_config(opts, &cfg_file, node)
+            .with_context(|| format!("Invalid config generated for {}.", node.name))?;
+
+        println!(
+            "{}: role = {}, http = {}",
+            node.name,
+            node.role,
+            node.http_address()
+        );
+    }
+
+    println!();
+    println!(
+        "Copy each sub-directory of {} to its host, and run the node in it:",
+        opts.out_dir.display()
+    );
+    if opts.tee {
+        println!("  slimchain-node-tee -c config.toml -g genesis.toml -d .");
+        println!("Fill in api_key and spid of the [tee] section of the storage nodes first.");
+    } else {
+        println!("  slimchain-node-simple -c config.toml -g genesis.toml -d .");
+    }
+
+    Ok(())
+}
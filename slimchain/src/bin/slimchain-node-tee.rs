@@ -7,6 +7,7 @@ use slimchain_tee_sig::TEESignedTx as Tx;
 
 #[cfg(target_os = "linux")]
 fn create_tx_engine(cfg: &Config, enclave: &Option<PathBuf>) -> Result<TxEngine<Tx>> {
+    use slimchain_tx_engine::TxEngineConfig;
     use slimchain_tx_engine_tee::{TEEConfig, TEETxEngineWorkerFactory};
     use slimchain_utils::tx_engine_threads;
 
@@ -15,7 +16,12 @@ fn create_tx_engine(cfg: &Config, enclave: &Option<PathBuf>) -> Result<TxEngine<
         Some(enclave) => TEETxEngineWorkerFactory::new(tee_cfg, enclave)?,
         None => TEETxEngineWorkerFactory::use_enclave_in_the_same_dir(tee_cfg)?,
     };
-    Ok(TxEngine::new(tx_engine_threads(), || factory.worker()))
+    let tx_engine_cfg: TxEngineConfig = cfg.get("tx_engine").unwrap_or_default();
+    Ok(TxEngine::new_with_write_trie_threads(
+        tx_engine_threads(),
+        tx_engine_cfg.write_trie_threads,
+        || factory.worker(),
+    ))
 }
 
 #[cfg(not(target_os = "linux"))]
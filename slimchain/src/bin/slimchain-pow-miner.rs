@@ -0,0 +1,117 @@
+#[macro_use]
+extern crate tracing;
+
+use slimchain_chain::consensus::pow::PoWWork;
+use slimchain_common::{basic::Nonce, error::Result};
+use slimchain_network::http::mining_rpc::{get_work, submit_work};
+use slimchain_utils::{cli::DigestOpts, init_tracing_subscriber};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use structopt::StructOpt;
+use tokio::{
+    sync::mpsc,
+    time::{interval, Duration},
+};
+
+// How many nonces a thread tries before checking whether the work is still current.
+const NONCE_BATCH: u64 = 1024;
+
+#[derive(Debug, StructOpt)]
+#[structopt(version = git_version::git_version!(prefix = concat!(env!("CARGO_PKG_VERSION"), " ("), suffix = ")", fallback = "unknown"))]
+struct Opts {
+    /// Endpoint to the mining server of the miner node, i.e., `mining_listen` in its config.
+    #[structopt(long, default_value = "127.0.0.1:8200")]
+    endpoint: String,
+
+    /// Number of threads searching the nonce. Default to the number of CPUs.
+    #[structopt(long)]
+    threads: Option<usize>,
+
+    /// Interval in milliseconds of polling the miner node for new work.
+    #[structopt(long, default_value = "100")]
+    poll_interval: u64,
+
+    #[structopt(flatten)]
+    digest: DigestOpts,
+}
+
+// Search the nonce until it is found or the work is replaced. Each thread starts from a
+// random nonce, so that the miners sharing the same work do not repeat each other.
+fn search_nonce(
+    work: PoWWork,
+    current_work: Arc<AtomicU64>,
+    found_tx: mpsc::UnboundedSender<(u64, Nonce)>,
+) {
+    let mut nonce = Nonce::from(rand::random::<u64>());
+    loop {
+        for _ in 0..NONCE_BATCH {
+            if work.nonce_is_valid(nonce) {
+                found_tx.send((work.id, nonce)).ok();
+                return;
+            }
+            nonce += 1.into();
+        }
+        if current_work.load(Ordering::Acquire) != work.id {
+            return;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_backtrace::install();
+    init_tracing_subscriber("info")?;
+
+    let opts = Opts::from_args();
+    info!("Opts: {:#?}", opts);
+    opts.digest.install_as_global();
+    let threads = opts.threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+
+    // The work ids start from 0. Use u64::MAX for no work.
+    let current_work = Arc::new(AtomicU64::new(u64::MAX));
+    let (found_tx, mut found_rx) = mpsc::unbounded_channel();
+    let mut poll = interval(Duration::from_millis(opts.poll_interval));
+
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {
+                let work = match get_work(&opts.endpoint).await {
+                    Ok(work) => work,
+                    Err(e) => {
+                        warn!("Failed to get the work. Error: {}", e);
+                        continue;
+                    }
+                };
+                let work_id = work.as_ref().map_or(u64::MAX, |work| work.id);
+                if current_work.swap(work_id, Ordering::AcqRel) == work_id {
+                    continue;
+                }
+                if let Some(work) = work {
+                    info!(id = work.id, height = work.height.0, diff = work.diff, "New work.");
+                    for _ in 0..threads {
+                        let work = work.clone();
+                        let current_work = current_work.clone();
+                        let found_tx = found_tx.clone();
+                        tokio::task::spawn_blocking(move || search_nonce(work, current_work, found_tx));
+                    }
+                }
+            }
+            Some((id, nonce)) = found_rx.recv() => {
+                // Other threads may find a nonce of the same work as well.
+                if current_work.compare_exchange(id, u64::MAX, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                    continue;
+                }
+                match submit_work(&opts.endpoint, id, nonce).await {
+                    Ok(()) => info!(id, %nonce, "Submitted the work."),
+                    Err(e) => warn!(id, "Failed to submit the work. Error: {}", e),
+                }
+            }
+        }
+    }
+}
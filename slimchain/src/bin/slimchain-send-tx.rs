@@ -1,42 +1,166 @@
 #[macro_use]
 extern crate tracing;
 
+use futures::prelude::*;
 use once_cell::sync::{Lazy, OnceCell};
-use rand::{distributions::Uniform, prelude::*, rngs::StdRng};
+use rand::{
+    distributions::{Alphanumeric, Uniform},
+    prelude::*,
+    rngs::StdRng,
+};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use slimchain_chain::{behavior::ViewCallRequest, role::Role, tx_event::TxEvent};
 use slimchain_common::{
-    basic::{Address, Nonce, ShardId, U256},
-    ed25519::Keypair,
-    error::{anyhow, bail, Context as _, Result},
+    basic::{
+        set_shard_assignment, Address, BlockHeight, Nonce, ShardAssignment, ShardId, H256, U256,
+    },
+    ed25519::{derive_keypair, Keypair, PublicKey, SecretKey},
+    error::{anyhow, bail, ensure, error_code, Context as _, Result},
+    hd::{seed_from_mnemonic, DerivationPath},
     tx_req::{caller_address_from_pk, SignedTxRequest, TxRequest},
+    utils::hex,
 };
-use slimchain_network::http::{
-    client_rpc::{
-        get_block_height, get_tx_count, send_record_event, send_record_event_with_data,
-        send_tx_requests_with_shard,
+use slimchain_network::{
+    http::{
+        client_rpc::{
+            get_block_height, get_tx_receipt, send_record_event, send_record_event_with_data,
+            send_tx_requests_with_shard, set_linearizable_read, LeaderClient,
+        },
+        common::set_admin_token,
+        node_rpc::call_view_on_storage,
+        status::get_status,
+        subscription::{subscribe_events, SubscriptionEvent},
     },
-    node_rpc::get_leader,
+    tx_trace::read_tx_trace,
 };
 use slimchain_utils::{
+    cli::DigestOpts,
     contract::{contract_address, Contract, Token},
     init_tracing_subscriber,
+    metrics::percentile,
 };
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     fs::File,
     io::{self, prelude::*},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
 };
 use structopt::StructOpt;
-use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::{interval, sleep, sleep_until, timeout, Duration, Instant},
+};
 
 static YCSB: OnceCell<Mutex<io::BufReader<File>>> = OnceCell::new();
 static YCSB_READ_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^READ usertable (\w+) \[.+\]$").unwrap());
 static YCSB_WRITE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^UPDATE usertable (\w+) \[ field\d+=(.+) \]$").unwrap());
+static TPCC: OnceCell<TpccConfig> = OnceCell::new();
+static ZIPF_THETA: OnceCell<f64> = OnceCell::new();
+
+#[derive(Debug, Copy, Clone)]
+struct TpccConfig {
+    warehouses: u64,
+    // Percentage of the new-order txs. The rest are the payment txs.
+    new_order_ratio: u32,
+}
+
+// The cardinality of the tables in the TPC-C spec.
+const TPCC_DISTRICTS_PER_WAREHOUSE: u64 = 10;
+const TPCC_CUSTOMERS_PER_DISTRICT: u64 = 3_000;
+const TPCC_ITEMS: u64 = 100_000;
+
+// The non-uniform random of the TPC-C spec, which skews the customers and the items.
+fn tpcc_nurand(rng: &mut impl Rng, a: u64, c: u64, x: u64, y: u64) -> u64 {
+    (((rng.gen_range(0, a + 1) | rng.gen_range(x, y + 1)) + c) % (y - x + 1)) + x
+}
+
+// Pick a warehouse other than `w_id` for the remote accesses.
+fn tpcc_remote_warehouse(rng: &mut impl Rng, warehouses: u64, w_id: u64) -> u64 {
+    if warehouses == 1 {
+        return w_id;
+    }
+    let id = rng.gen_range(1, warehouses);
+    if id >= w_id {
+        id + 1
+    } else {
+        id
+    }
+}
+
+// The number of keys accessed by the ioheavy, kvstore, and smallbank TX.
+const IO_HEAVY_KEYS: u64 = 999_999;
+const KV_STORE_KEYS: u64 = 100_000;
+const SMALL_BANK_ACCOUNTS: u64 = 99_999;
+
+// Sample the keys in [1, n] from a Zipf distribution with the skew theta in [0, 1), so that key 1
+// is the hottest one. It follows the generator in YCSB, which is from Gray et al., "Quickly
+// Generating Billion-Record Synthetic Databases", SIGMOD 1994. Theta 0 is the uniform
+// distribution.
+#[derive(Debug, Copy, Clone)]
+struct ZipfKeys {
+    n: u64,
+    theta: f64,
+    alpha: f64,
+    zeta_n: f64,
+    eta: f64,
+}
+
+impl ZipfKeys {
+    fn new(n: u64, theta: f64) -> Self {
+        if theta == 0. {
+            return Self {
+                n,
+                theta,
+                alpha: 1.,
+                zeta_n: n as f64,
+                eta: 1.,
+            };
+        }
+
+        let zeta = |n: u64| -> f64 { (1..=n).map(|i| 1. / (i as f64).powf(theta)).sum() };
+        let zeta_n = zeta(n);
+        Self {
+            n,
+            theta,
+            alpha: 1. / (1. - theta),
+            zeta_n,
+            eta: (1. - (2. / n as f64).powf(1. - theta)) / (1. - zeta(2) / zeta_n),
+        }
+    }
+}
+
+impl Distribution<u64> for ZipfKeys {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        if self.theta == 0. {
+            // Keep the same sequence as before the skew is supported.
+            return Uniform::new(1, self.n + 1).sample(rng);
+        }
+
+        let u: f64 = rng.gen();
+        let uz = u * self.zeta_n;
+        if uz < 1. {
+            return 1;
+        }
+        if uz < 1. + 0.5f64.powf(self.theta) {
+            return 2;
+        }
+        let key = 1 + (self.n as f64 * (self.eta * u - self.eta + 1.).powf(self.alpha)) as u64;
+        key.min(self.n)
+    }
+}
+
+macro_rules! zipf_keys {
+    ($n: expr) => {{
+        static KEYS: OnceCell<ZipfKeys> = OnceCell::new();
+        KEYS.get_or_init(|| ZipfKeys::new($n, ZIPF_THETA.get().copied().unwrap_or_default()))
+    }};
+}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -46,6 +170,7 @@ enum ContractArg {
     IoHeavy,
     KVStore,
     SmallBank,
+    Tpcc,
 }
 
 macro_rules! load_contract {
@@ -71,6 +196,18 @@ impl ContractArg {
             ContractArg::IoHeavy => load_contract!("IO"),
             ContractArg::KVStore => load_contract!("KVstore"),
             ContractArg::SmallBank => load_contract!("SmallBank"),
+            ContractArg::Tpcc => load_contract!("TPCC"),
+        }
+    }
+
+    // The distribution of the keys shared by the TX and the view calls, so that the reads
+    // contend on the same hot keys.
+    fn key_gen(self) -> &'static ZipfKeys {
+        match self {
+            ContractArg::IoHeavy => zipf_keys!(IO_HEAVY_KEYS),
+            ContractArg::KVStore => zipf_keys!(KV_STORE_KEYS),
+            ContractArg::SmallBank => zipf_keys!(SMALL_BANK_ACCOUNTS),
+            _ => unreachable!("No keys in {:?}.", self),
         }
     }
 
@@ -100,7 +237,7 @@ impl ContractArg {
             }
             ContractArg::IoHeavy => {
                 let op_gen = Uniform::new(1, 4);
-                let key_gen = Uniform::new(1, 1_000_000);
+                let key_gen = self.key_gen();
                 let size_gen = Uniform::new(1, 8);
                 let contract = self.get_contract();
                 match op_gen.sample(rng) {
@@ -160,14 +297,21 @@ impl ContractArg {
 
                         warn!("Skip line in ycsb file: {}", buf);
                     }
+                }
+
+                // Without the ycsb file, follow the update-heavy workload A of YCSB.
+                let key = format!("user{}", self.key_gen().sample(rng));
+                if rng.gen_bool(0.5) {
+                    contract.encode_tx_input("get", &[Token::String(key)])
                 } else {
-                    bail!("Failed to access ycsb file.");
+                    let value: String = rng.sample_iter(Alphanumeric).take(100).collect();
+                    contract.encode_tx_input("set", &[Token::String(key), Token::String(value)])
                 }
             }
             ContractArg::SmallBank => {
                 // https://github.com/ooibc88/blockbench/blob/master/src/macro/smallbank/smallbank.cc
                 let op_gen = Uniform::new(1, 7);
-                let acc_gen = Uniform::new(1, 100_000);
+                let acc_gen = self.key_gen();
                 let bal_gen = Uniform::new(1, 100);
                 let contract = self.get_contract();
                 match op_gen.sample(rng) {
@@ -214,6 +358,106 @@ impl ContractArg {
                     _ => unreachable!(),
                 }
             }
+            ContractArg::Tpcc => {
+                let cfg = TPCC.get().context("Failed to access tpcc config.")?;
+                let contract = self.get_contract();
+                let w_id = rng.gen_range(1, cfg.warehouses + 1);
+                let d_id = rng.gen_range(1, TPCC_DISTRICTS_PER_WAREHOUSE + 1);
+                let c_id = tpcc_nurand(rng, 1023, 259, 1, TPCC_CUSTOMERS_PER_DISTRICT);
+                if rng.gen_range(0, 100) < cfg.new_order_ratio {
+                    let ol_cnt = rng.gen_range(5, 16);
+                    let mut item_ids = Vec::with_capacity(ol_cnt);
+                    let mut supply_w_ids = Vec::with_capacity(ol_cnt);
+                    let mut quantities = Vec::with_capacity(ol_cnt);
+                    for _ in 0..ol_cnt {
+                        item_ids.push(Token::Uint(
+                            tpcc_nurand(rng, 8191, 7911, 1, TPCC_ITEMS).into(),
+                        ));
+                        // 1% of the order lines are supplied by a remote warehouse.
+                        let supply_w_id = if rng.gen_ratio(1, 100) {
+                            tpcc_remote_warehouse(rng, cfg.warehouses, w_id)
+                        } else {
+                            w_id
+                        };
+                        supply_w_ids.push(Token::Uint(supply_w_id.into()));
+                        quantities.push(Token::Uint(rng.gen_range(1u64, 11).into()));
+                    }
+                    contract.encode_tx_input(
+                        "newOrder",
+                        &[
+                            Token::Uint(w_id.into()),
+                            Token::Uint(d_id.into()),
+                            Token::Uint(c_id.into()),
+                            Token::Array(item_ids),
+                            Token::Array(supply_w_ids),
+                            Token::Array(quantities),
+                        ],
+                    )
+                } else {
+                    // 15% of the customers pay through a remote warehouse.
+                    let (c_w_id, c_d_id) = if rng.gen_ratio(15, 100) {
+                        (
+                            tpcc_remote_warehouse(rng, cfg.warehouses, w_id),
+                            rng.gen_range(1, TPCC_DISTRICTS_PER_WAREHOUSE + 1),
+                        )
+                    } else {
+                        (w_id, d_id)
+                    };
+                    contract.encode_tx_input(
+                        "payment",
+                        &[
+                            Token::Uint(w_id.into()),
+                            Token::Uint(d_id.into()),
+                            Token::Uint(c_w_id.into()),
+                            Token::Uint(c_d_id.into()),
+                            Token::Uint(c_id.into()),
+                            Token::Uint(rng.gen_range(1u64, 5_001).into()),
+                        ],
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl ContractArg {
+    // The input of a view call reading the states written by the txs.
+    fn gen_read_input(self, rng: &mut impl Rng) -> Result<Vec<u8>> {
+        let contract = self.get_contract();
+        match self {
+            ContractArg::IoHeavy => contract.encode_tx_input(
+                "scan",
+                &[
+                    Token::Uint(self.key_gen().sample(rng).into()),
+                    Token::Uint(rng.gen_range(1u64, 8).into()),
+                ],
+            ),
+            // The keys in the ycsb file are hashed, so any key is as likely to be read.
+            ContractArg::KVStore if YCSB.get().is_some() => contract
+                .encode_tx_input("get", &[Token::String(format!("user{}", rng.gen::<u64>()))]),
+            ContractArg::KVStore => contract.encode_tx_input(
+                "get",
+                &[Token::String(format!("user{}", self.key_gen().sample(rng)))],
+            ),
+            ContractArg::SmallBank => contract.encode_tx_input(
+                "getBalance",
+                &[Token::String(self.key_gen().sample(rng).to_string())],
+            ),
+            ContractArg::Tpcc => {
+                let cfg = TPCC.get().context("Failed to access tpcc config.")?;
+                contract.encode_tx_input(
+                    "orderStatus",
+                    &[
+                        Token::Uint(rng.gen_range(1, cfg.warehouses + 1).into()),
+                        Token::Uint(rng.gen_range(1, TPCC_DISTRICTS_PER_WAREHOUSE + 1).into()),
+                        Token::Uint(
+                            tpcc_nurand(rng, 1023, 259, 1, TPCC_CUSTOMERS_PER_DISTRICT).into(),
+                        ),
+                    ],
+                )
+            }
+            // No view function. The writes of the call are dropped anyway.
+            ContractArg::CpuHeavy | ContractArg::DoNothing => self.gen_tx_input(rng),
         }
     }
 }
@@ -225,8 +469,9 @@ fn parse_contract_arg(input: &str) -> Result<ContractArg> {
         "ioheavy" => ContractArg::IoHeavy,
         "kvstore" => ContractArg::KVStore,
         "smallbank" => ContractArg::SmallBank,
+        "tpcc" => ContractArg::Tpcc,
         _ => {
-            bail!("Accepted values: cpuheavy, donothing, ioheavy, kvstore, and smallbank.");
+            bail!("Accepted values: cpuheavy, donothing, ioheavy, kvstore, smallbank, and tpcc.");
         }
     })
 }
@@ -235,6 +480,7 @@ fn create_deploy_tx(
     rng: &mut (impl Rng + CryptoRng),
     contract: ContractArg,
     shard_id: ShardId,
+    chain_id: u64,
 ) -> (Address, SignedTxRequest) {
     info!(
         "Create deploy tx for contract {:?} at {:?}",
@@ -248,6 +494,8 @@ fn create_deploy_tx(
             let tx_req = TxRequest::Create {
                 nonce: U256::from(0).into(),
                 code: contract.get_contract().code().clone(),
+                chain_id,
+                expiry: None,
             };
             return (contract_address, tx_req.sign(&keypair));
         }
@@ -265,18 +513,40 @@ struct Opts {
     #[structopt(short, long, default_value = "1")]
     shard: u64,
 
+    /// Shard assignment of the contracts, which should match the chain config of the nodes.
+    /// Possible values: modulo, prefix, consistent_hash:<virtual_nodes>.
+    #[structopt(long, default_value = "modulo")]
+    shard_assignment: ShardAssignment,
+
+    #[structopt(flatten)]
+    digest: DigestOpts,
+
     /// Total number of TX.
-    #[structopt(short, long)]
-    total: usize,
+    #[structopt(short, long, required_unless = "replay")]
+    total: Option<usize>,
 
     /// Number of TX per seconds.
-    #[structopt(short, long)]
-    rate: usize,
+    #[structopt(short, long, required_unless_one = &["concurrency", "replay"])]
+    rate: Option<usize>,
+
+    /// Closed-loop mode: keep this number of TX in flight and send the next TX once one is
+    /// committed or discarded, instead of sending at a fixed rate. The latency of each TX is
+    /// reported.
+    #[structopt(long, conflicts_with = "rate")]
+    concurrency: Option<usize>,
+
+    /// Timeout in seconds for a TX in flight to be committed in the closed-loop mode.
+    #[structopt(long, default_value = "60")]
+    tx_timeout: u64,
 
     /// Wait period in seconds to check block committing after sending TX.
     #[structopt(short, long, default_value = "60")]
     wait: u64,
 
+    /// Chain id signed in the TX, which should match the genesis of the nodes.
+    #[structopt(long, default_value = "0")]
+    chain_id: u64,
+
     /// Seed used for RNG.
     #[structopt(long)]
     seed: Option<u64>,
@@ -285,12 +555,60 @@ struct Opts {
     #[structopt(short, long)]
     accounts: Option<usize>,
 
-    /// Exec additional raft related operations.
+    /// BIP-39 mnemonic to derive the accounts at m/44'/60'/0'/0'/i', instead of generating them
+    /// randomly. It is not recorded in the node events.
+    #[structopt(long, env = "SLIMCHAIN_MNEMONIC", hide_env_values = true)]
+    #[serde(skip)]
+    mnemonic: Option<String>,
+
+    /// Send TX to the raft leader, which is resolved through the endpoint and the peers, and
+    /// again once the leader changes.
     #[structopt(long)]
     raft: bool,
 
-    /// List of contracts. Accepted values: cpuheavy, donothing, ioheavy, kvstore, and smallbank.
-    #[structopt(parse(try_from_str = parse_contract_arg), required = true)]
+    /// Other endpoints used to resolve the raft leader if the endpoint is unreachable.
+    #[structopt(long = "peer", number_of_values = 1)]
+    peers: Vec<String>,
+
+    /// Admin token of the nodes, required to resolve the raft leader if they set one. It is not
+    /// recorded in the node events.
+    #[structopt(long, env = "SLIMCHAIN_ADMIN_TOKEN", hide_env_values = true)]
+    #[serde(skip)]
+    admin_token: Option<String>,
+
+    /// Read the block height and the tx receipts through the raft read index, so that they are
+    /// not stale on the followers. Only the raft nodes support it.
+    #[structopt(long)]
+    linearizable_read: bool,
+
+    /// Check the tx receipts after sending TX.
+    #[structopt(long)]
+    check_receipts: bool,
+
+    /// Write the outcome of each TX to this CSV file, i.e., the submit time, and the commit
+    /// height and latency or the discard reason. The TX are tracked through the tx events of the
+    /// node, and the receipts of the remaining TX are checked after sending TX.
+    #[structopt(long, parse(from_os_str))]
+    tx_log: Option<PathBuf>,
+
+    /// Number of view calls per seconds sent to the storage nodes, along with the TX.
+    #[structopt(long, requires = "read_endpoints")]
+    read_rate: Option<usize>,
+
+    /// Endpoints to the http servers of the storage nodes serving the view calls. The calls to
+    /// a contract go to the storage nodes of its shard.
+    #[structopt(long = "read-endpoint", number_of_values = 1)]
+    read_endpoints: Vec<String>,
+
+    /// Replay the TX recorded by a client node with `--tx-trace`, at the same pace as recorded,
+    /// instead of generating the TX. The trace includes the deploy TX, so the nodes should start
+    /// from the same genesis as the recorded ones.
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["rate", "concurrency", "read_rate"])]
+    replay: Option<PathBuf>,
+
+    /// List of contracts. Accepted values: cpuheavy, donothing, ioheavy, kvstore, smallbank, and
+    /// tpcc.
+    #[structopt(parse(try_from_str = parse_contract_arg), required_unless = "replay")]
     contract: Vec<ContractArg>,
 
     #[structopt(
@@ -298,7 +616,8 @@ struct Opts {
         long,
         parse(from_os_str),
         help = "Path to ycsb.txt. Used for kvstore smart contract.",
-        long_help = r#"Path to ycsb.txt. Used for kvstore smart contract.
+        long_help = r#"Path to ycsb.txt. Used for kvstore smart contract. Without it, the kvstore TX
+are half reads and half updates of the keys following --zipf.
 
 The file should contain content similar to the below:
     UPDATE usertable <user> [ field="<value>" ]
@@ -309,131 +628,788 @@ To generate it:
 "#
     )]
     ycsb: Option<PathBuf>,
+
+    /// Skew of the keys accessed by the ioheavy, kvstore, and smallbank TX, following a Zipf
+    /// distribution in which the larger value gives the more contention. It should be in [0, 1),
+    /// and 0 means uniform. The ycsb file has its own distribution, so it is ignored by kvstore
+    /// if --ycsb is given.
+    #[structopt(long, default_value = "0")]
+    zipf: f64,
+
+    /// Path to the state of send-tx, i.e., the deployed contracts, and the accounts with their
+    /// next nonces. If the file exists, the contracts and the accounts in it are used instead of
+    /// deploying or creating new ones. The state is saved back after sending TX, so that
+    /// consecutive runs (e.g., loading data and then running the benchmark) share the accounts
+    /// without reusing a nonce. The file contains the secret keys of the accounts.
+    #[structopt(long, parse(from_os_str), conflicts_with = "replay")]
+    state: Option<PathBuf>,
+
+    /// Number of warehouses. Used for tpcc smart contract.
+    #[structopt(long, default_value = "1")]
+    tpcc_warehouses: u64,
+
+    /// Percentage of the new-order TX, and the rest are the payment TX. Used for tpcc smart
+    /// contract. The default follows the ratio 45:43 in the TPC-C spec.
+    #[structopt(long, default_value = "51")]
+    tpcc_new_order_ratio: u32,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    color_backtrace::install();
-    init_tracing_subscriber("info")?;
+// Generate the calls to the deployed contracts, signed by the accounts in turn.
+struct TxGenerator {
+    rng: StdRng,
+    contracts: Vec<(Address, ShardId, ContractArg)>,
+    accounts: VecDeque<(Keypair, Nonce)>,
+    chain_id: u64,
+}
 
-    let opts = Opts::from_args();
-    info!("Opts: {:#?}", opts);
+impl TxGenerator {
+    fn next_tx(&mut self) -> Result<(SignedTxRequest, ShardId)> {
+        let (address, shard_id, contract) = self
+            .contracts
+            .choose(&mut self.rng)
+            .copied()
+            .expect("Failed to get contract.");
+        let (key, nonce) = self
+            .accounts
+            .pop_front()
+            .context("Failed to get account.")?;
+        let tx_req = TxRequest::Call {
+            nonce,
+            address,
+            data: contract.gen_tx_input(&mut self.rng)?,
+            chain_id: self.chain_id,
+            expiry: None,
+        };
+        let signed_tx_req = tx_req.sign(&key);
+        self.accounts
+            .push_back((key, (U256::from(nonce) + 1).into()));
+        Ok((signed_tx_req, shard_id))
+    }
+}
 
-    if let Some(ycsb) = opts.ycsb.as_ref() {
-        YCSB.set(Mutex::new(io::BufReader::new(File::open(ycsb)?)))
-            .map_err(|_e| anyhow!("Failed to set YCSB."))?;
+// The state kept across the runs of send-tx with `--state`.
+#[derive(Debug, Serialize, Deserialize)]
+struct SendTxState {
+    contracts: Vec<(Address, ShardId, ContractArg)>,
+    accounts: Vec<SavedAccount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedAccount {
+    address: Address,
+    secret_key: String,
+    nonce: Nonce,
+}
+
+impl SendTxState {
+    fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}.", path.display()))?;
+        serde_json::from_reader(io::BufReader::new(file))
+            .with_context(|| format!("Failed to parse {}.", path.display()))
     }
 
-    if opts.raft {
-        let mut i = 0;
-        loop {
-            match get_leader(&opts.endpoint).await {
-                Ok(leader) => {
-                    info!("Raft Leader: {}", leader);
-                    break;
-                }
-                Err(_) => {
-                    sleep(ONE_SECOND).await;
-                    i += 1;
-                    if i % 60 == 0 {
-                        info!("Waiting for leader election...");
-                    }
-                }
-            }
+    fn save(&self, path: &Path) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}.", path.display()))?;
+        serde_json::to_writer_pretty(io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    fn from_tx_gen(tx_gen: &TxGenerator) -> Self {
+        let accounts = tx_gen
+            .accounts
+            .iter()
+            .map(|(key, nonce)| SavedAccount {
+                address: caller_address_from_pk(&key.public),
+                secret_key: format!("0x{}", hex::encode(key.secret.to_bytes())),
+                nonce: *nonce,
+            })
+            .collect();
+        Self {
+            contracts: tx_gen.contracts.clone(),
+            accounts,
         }
     }
 
-    send_record_event_with_data(&opts.endpoint, "send-tx-opts", &opts).await?;
+    fn keypairs(&self) -> Result<VecDeque<(Keypair, Nonce)>> {
+        self.accounts
+            .iter()
+            .map(|acc| {
+                let bytes = hex::decode(acc.secret_key.trim_start_matches("0x"))?;
+                let secret = SecretKey::from_bytes(&bytes).map_err(|e| anyhow!(e))?;
+                let public = PublicKey::from(&secret);
+                ensure!(
+                    caller_address_from_pk(&public) == acc.address,
+                    "The secret key does not match the account {}.",
+                    acc.address
+                );
+                Ok((Keypair { secret, public }, acc.nonce))
+            })
+            .collect()
+    }
+}
 
-    let mut rng = match opts.seed {
-        Some(seed) => StdRng::seed_from_u64(seed),
-        None => StdRng::from_entropy(),
+// Return once no new block is committed within `wait`.
+async fn wait_for_blocks(endpoint: &str, wait: Duration) -> Result<()> {
+    let mut events = match subscribe_events(endpoint).await {
+        Ok(events) => Box::pin(events),
+        Err(e) => {
+            warn!(
+                "Failed to subscribe to the new blocks. Fall back to polling. Error: {}",
+                e
+            );
+            return poll_for_blocks(endpoint, wait).await;
+        }
     };
 
-    let mut contracts: Vec<(Address, ShardId, ContractArg)> =
-        Vec::with_capacity(opts.contract.len());
-    let deploy_txs: Vec<(SignedTxRequest, ShardId)> = opts
-        .contract
-        .iter()
-        .enumerate()
-        .map(|(id, &contract)| {
-            let id = (id as u64) % opts.shard;
-            let shard_id = ShardId::new(id as u64, opts.shard);
-            let (address, deploy_tx) = create_deploy_tx(&mut rng, contract, shard_id);
-            debug!("tx {} address {}", id, address);
-            contracts.push((address, shard_id, contract));
-            (deploy_tx, shard_id)
-        })
-        .collect();
+    loop {
+        match timeout(wait, events.next()).await {
+            Ok(Some(Ok(SubscriptionEvent::NewBlock { header }))) => {
+                debug!("New block #{}", header.height);
+            }
+            Ok(Some(Ok(_))) => {}
+            Ok(Some(Err(e))) => return Err(e),
+            Ok(None) => bail!("The subscription is closed by the node."),
+            Err(_) => break,
+        }
+    }
+
+    Ok(())
+}
 
-    info!("Deploy txs");
-    let tx_count = get_tx_count(&opts.endpoint).await?;
-    send_tx_requests_with_shard(&opts.endpoint, deploy_txs.into_iter()).await?;
+// Max number of attempts to send the txs while the node is busy, e.g., rate limited.
+const MAX_SEND_ATTEMPTS: u32 = 8;
+
+// The txs go to the leader in raft, and the endpoint otherwise.
+async fn send_txs(
+    opts: &Opts,
+    leader: Option<&LeaderClient>,
+    reqs: Vec<(SignedTxRequest, ShardId)>,
+) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        let res = match leader {
+            Some(leader) => {
+                leader
+                    .send_tx_requests_with_shard(reqs.iter().cloned())
+                    .await
+            }
+            None => send_tx_requests_with_shard(&opts.endpoint, reqs.iter().cloned()).await,
+        };
+        let e = match res {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let code = error_code(&e);
+        if !code.is_retryable() || attempt >= MAX_SEND_ATTEMPTS {
+            return Err(e.context(format!("Failed to send txs (code: {}).", code.as_u16())));
+        }
+        warn!("The node is busy. Retry. Error: {}", e);
+        sleep(Duration::from_millis(100 << attempt)).await;
+        attempt += 1;
+    }
+}
+
+// The node to query, which is the leader in raft so that it survives the failover of the
+// endpoint.
+async fn query_endpoint(opts: &Opts, leader: Option<&LeaderClient>) -> Result<String> {
+    match leader {
+        Some(leader) => leader.leader().await,
+        None => Ok(opts.endpoint.clone()),
+    }
+}
+
+async fn poll_for_blocks(endpoint: &str, wait: Duration) -> Result<()> {
+    let mut cur_block_height = get_block_height(endpoint).await?;
+    let mut block_update_time = Instant::now();
 
     loop {
         sleep(Duration::from_millis(500)).await;
-        let tx_count2 = get_tx_count(&opts.endpoint).await?;
+        let height = get_block_height(endpoint).await?;
 
-        if tx_count2 >= tx_count + opts.contract.len() {
+        if height > cur_block_height {
+            block_update_time = Instant::now();
+            cur_block_height = height;
+            continue;
+        } else if Instant::now() - block_update_time > wait {
             break;
         }
     }
-    info!("Deploy finished");
 
-    if opts.raft {
-        info!("Current Raft Leader: {}", get_leader(&opts.endpoint).await?);
+    Ok(())
+}
+
+// Interval to poll the receipts of the txs in flight, when the tx events are unavailable.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TxStatus {
+    // No outcome is observed, e.g., the tx events are dropped.
+    Pending,
+    Committed,
+    Discarded,
+    TimedOut,
+}
+
+impl TxStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TxStatus::Pending => "pending",
+            TxStatus::Committed => "committed",
+            TxStatus::Discarded => "discarded",
+            TxStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TxRecord {
+    tx_id: H256,
+    submit_time: SystemTime,
+    submit_instant: Instant,
+    status: TxStatus,
+    height: Option<BlockHeight>,
+    // Unknown if the commit is only found in the receipts after sending all the txs.
+    latency: Option<Duration>,
+    reason: Option<String>,
+}
+
+// The outcome of each submitted tx.
+#[derive(Debug, Default)]
+struct TxLog {
+    records: Vec<TxRecord>,
+    index: HashMap<H256, usize>,
+}
+
+impl TxLog {
+    fn submit(&mut self, tx_id: H256) {
+        self.index.insert(tx_id, self.records.len());
+        self.records.push(TxRecord {
+            tx_id,
+            submit_time: SystemTime::now(),
+            submit_instant: Instant::now(),
+            status: TxStatus::Pending,
+            height: None,
+            latency: None,
+            reason: None,
+        });
+    }
+
+    fn submit_all(&mut self, reqs: &[(SignedTxRequest, ShardId)]) {
+        for (tx, _) in reqs {
+            self.submit(tx.id());
+        }
+    }
+
+    fn pending_mut(&mut self, tx_id: H256) -> Option<&mut TxRecord> {
+        let record = &mut self.records[*self.index.get(&tx_id)?];
+        if record.status == TxStatus::Pending {
+            Some(record)
+        } else {
+            None
+        }
     }
 
-    let mut accounts: VecDeque<(Keypair, Nonce)> = {
-        std::iter::repeat_with(|| (Keypair::generate(&mut rng), Nonce::zero()))
-            .take(opts.accounts.unwrap_or(opts.total))
+    fn pending_tx_ids(&self) -> Vec<H256> {
+        self.records
+            .iter()
+            .filter(|r| r.status == TxStatus::Pending)
+            .map(|r| r.tx_id)
             .collect()
-    };
+    }
 
-    send_record_event(&opts.endpoint, "start-send-tx").await?;
-    let begin = Instant::now();
-    const ONE_SECOND: Duration = Duration::from_secs(1);
-    let mut next_epoch = begin + ONE_SECOND;
+    // Return whether the tx was pending.
+    fn commit(&mut self, tx_id: H256, height: BlockHeight, at: Option<Instant>) -> bool {
+        match self.pending_mut(tx_id) {
+            Some(record) => {
+                record.status = TxStatus::Committed;
+                record.height = Some(height);
+                record.latency = at.map(|at| at - record.submit_instant);
+                true
+            }
+            None => false,
+        }
+    }
 
-    let mut reqs = Vec::with_capacity(opts.rate + 1);
-    let mut next_epoch_fut = sleep_until(next_epoch);
-    for i in 0..opts.total {
-        let (address, shard_id, contract) = contracts
-            .choose(&mut rng)
-            .copied()
-            .expect("Failed to get contract.");
-        let (key, nonce) = accounts.pop_front().context("Failed to get account.")?;
-        let tx_req = TxRequest::Call {
-            nonce,
-            address,
-            data: contract.gen_tx_input(&mut rng)?,
+    // Return whether the tx was pending.
+    fn discard(&mut self, tx_id: H256, reason: String, at: Instant) -> bool {
+        match self.pending_mut(tx_id) {
+            Some(record) => {
+                record.status = TxStatus::Discarded;
+                record.latency = Some(at - record.submit_instant);
+                record.reason = Some(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn time_out(&mut self, tx_id: H256) {
+        if let Some(record) = self.pending_mut(tx_id) {
+            record.status = TxStatus::TimedOut;
+        }
+    }
+
+    // Apply the tx event received at `at`. Return the tx if it was pending.
+    fn apply_event(&mut self, event: TxEvent, at: Instant) -> Option<H256> {
+        let (tx_id, pending) = match event {
+            TxEvent::Committed { tx_id, height } => (tx_id, self.commit(tx_id, height, Some(at))),
+            TxEvent::Discarded { tx_id, reason, .. } => {
+                debug!("Tx {} is discarded. Reason: {}", tx_id, reason);
+                (tx_id, self.discard(tx_id, reason, at))
+            }
         };
-        let signed_tx_req = tx_req.sign(&key);
-        accounts.push_back((key, (U256::from(nonce) + 1).into()));
+        if pending {
+            Some(tx_id)
+        } else {
+            None
+        }
+    }
+
+    fn count(&self, status: TxStatus) -> usize {
+        self.records.iter().filter(|r| r.status == status).count()
+    }
 
-        reqs.push((signed_tx_req, shard_id));
+    fn summary(&self) -> serde_json::Value {
+        let mut latencies: Vec<u64> = self
+            .records
+            .iter()
+            .filter(|r| r.status == TxStatus::Committed)
+            .filter_map(|r| r.latency)
+            .map(|latency| latency.as_micros() as u64)
+            .collect();
+        latencies.sort_unstable();
+        let mean = if latencies.is_empty() {
+            0
+        } else {
+            latencies.iter().sum::<u64>() / latencies.len() as u64
+        };
+        serde_json::json! {{
+            "total": self.records.len(),
+            "committed": self.count(TxStatus::Committed),
+            "discarded": self.count(TxStatus::Discarded),
+            "timed_out": self.count(TxStatus::TimedOut),
+            "pending": self.count(TxStatus::Pending),
+            "mean_in_us": mean,
+            "p50_in_us": percentile(&latencies, 50),
+            "p95_in_us": percentile(&latencies, 95),
+            "p99_in_us": percentile(&latencies, 99),
+            "max_in_us": latencies.last().copied().unwrap_or_default(),
+        }}
+    }
 
-        if reqs.len() == opts.rate {
-            send_tx_requests_with_shard(&opts.endpoint, reqs.drain(..)).await?;
-            next_epoch_fut.await;
+    fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut w = io::BufWriter::new(File::create(path)?);
+        writeln!(
+            w,
+            "tx_id,submit_time_in_us,status,height,latency_in_us,reason"
+        )?;
+        for r in &self.records {
+            let submit_time = r.submit_time.duration_since(UNIX_EPOCH)?.as_micros();
+            let height = r.height.map(|h| h.0.to_string()).unwrap_or_default();
+            let latency = r
+                .latency
+                .map(|l| l.as_micros().to_string())
+                .unwrap_or_default();
+            // Quote the reason as it is free text.
+            let reason = r
+                .reason
+                .as_deref()
+                .map(|reason| format!("\"{}\"", reason.replace('"', "\"\"")))
+                .unwrap_or_default();
+            writeln!(
+                w,
+                "{:#x},{},{},{},{},{}",
+                r.tx_id,
+                submit_time,
+                r.status.as_str(),
+                height,
+                latency,
+                reason
+            )?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
 
-            next_epoch += ONE_SECOND;
-            next_epoch_fut = sleep_until(next_epoch);
+// Keep `concurrency` txs in flight, and send the next ones once the txs are committed, discarded,
+// or timed out. The txs are tracked by the tx events pushed by the node. The receipts are polled
+// instead if the subscription is unavailable, or for the txs in flight once some events are
+// dropped.
+async fn send_txs_closed_loop(
+    opts: &Opts,
+    leader: Option<&LeaderClient>,
+    tx_gen: &mut TxGenerator,
+    total: usize,
+    concurrency: usize,
+) -> Result<TxLog> {
+    let mut events = match subscribe_events(&query_endpoint(opts, leader).await?).await {
+        Ok(events) => Some(Box::pin(events)),
+        Err(e) => {
+            warn!(
+                "Failed to subscribe to the tx events. Fall back to polling. Error: {}",
+                e
+            );
+            None
         }
+    };
+    let mut lagged = false;
+    let mut poll_interval = interval(RECEIPT_POLL_INTERVAL);
+    let tx_timeout = Duration::from_secs(opts.tx_timeout);
+
+    let mut tx_log = TxLog::default();
+    let mut in_flight: HashMap<H256, Instant> = HashMap::with_capacity(concurrency);
+    let mut sent = 0;
 
-        if (i + 1) % 1_000 == 0 {
-            info!("Sent #{} txs", i + 1);
+    while sent < total || !in_flight.is_empty() {
+        let n = concurrency
+            .saturating_sub(in_flight.len())
+            .min(total - sent);
+        if n > 0 {
+            let reqs = (0..n)
+                .map(|_| tx_gen.next_tx())
+                .collect::<Result<Vec<_>>>()?;
+            let now = Instant::now();
+            for (tx, _) in &reqs {
+                tx_log.submit(tx.id());
+                in_flight.insert(tx.id(), now);
+            }
+            send_txs(opts, leader, reqs).await?;
+
+            if (sent + n) / 1_000 > sent / 1_000 {
+                info!("Sent #{} txs", sent + n);
+            }
+            sent += n;
         }
+
+        let polling = events.is_none() || lagged;
+        let next_timeout = in_flight.values().min().map(|&t| t + tx_timeout);
+        tokio::select! {
+            event = async {
+                match events.as_mut() {
+                    Some(events) => events.next().await,
+                    None => future::pending().await,
+                }
+            } => match event {
+                Some(Ok(SubscriptionEvent::Tx(event))) => {
+                    if let Some(tx_id) = tx_log.apply_event(event, Instant::now()) {
+                        in_flight.remove(&tx_id);
+                    }
+                }
+                Some(Ok(SubscriptionEvent::Lagged { skipped })) => {
+                    warn!("{} tx events are dropped. Poll the receipts.", skipped);
+                    lagged = true;
+                }
+                Some(Ok(SubscriptionEvent::NewBlock { .. })) => {}
+                Some(Err(e)) => {
+                    warn!("The subscription failed. Fall back to polling. Error: {}", e);
+                    events = None;
+                }
+                None => {
+                    warn!("The subscription is closed by the node. Fall back to polling.");
+                    events = None;
+                }
+            },
+            _ = poll_interval.tick(), if polling => {
+                let endpoint = query_endpoint(opts, leader).await?;
+                let tx_ids: Vec<H256> = in_flight.keys().copied().collect();
+                for tx_id in tx_ids {
+                    if let Some(receipt) = get_tx_receipt(&endpoint, tx_id).await? {
+                        tx_log.commit(tx_id, receipt.block_height, Some(Instant::now()));
+                        in_flight.remove(&tx_id);
+                    }
+                }
+                // All the txs sent before the events were dropped have been checked once. The
+                // remaining ones are either in flight or lost, which time out eventually.
+                lagged = false;
+            },
+            _ = async {
+                match next_timeout {
+                    Some(t) => sleep_until(t).await,
+                    None => future::pending().await,
+                }
+            } => {
+                let now = Instant::now();
+                in_flight.retain(|&tx_id, &mut t| {
+                    if now - t < tx_timeout {
+                        return true;
+                    }
+                    warn!("Tx {} is not committed within {:?}.", tx_id, tx_timeout);
+                    tx_log.time_out(tx_id);
+                    false
+                });
+            },
+        }
+    }
+
+    Ok(tx_log)
+}
+
+#[derive(Debug, Default)]
+struct ReadStats {
+    // The latency in us of each successful call.
+    latencies: Vec<u64>,
+    errors: usize,
+}
+
+impl ReadStats {
+    fn summary(&self, total_time: Duration) -> serde_json::Value {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        serde_json::json! {{
+            "total": latencies.len() + self.errors,
+            "errors": self.errors,
+            "real_rate": (latencies.len() as f64) / total_time.as_secs_f64(),
+            "p50_in_us": percentile(&latencies, 50),
+            "p95_in_us": percentile(&latencies, 95),
+            "p99_in_us": percentile(&latencies, 99),
+            "max_in_us": latencies.last().copied().unwrap_or_default(),
+        }}
     }
+}
 
-    if !reqs.is_empty() {
-        send_tx_requests_with_shard(&opts.endpoint, reqs.drain(..)).await?;
+// The deployed contracts and the storage nodes of their shards.
+async fn read_targets(
+    opts: &Opts,
+    contracts: &[(Address, ShardId, ContractArg)],
+) -> Result<Vec<(Address, ContractArg, Vec<String>)>> {
+    let mut storage_nodes = Vec::with_capacity(opts.read_endpoints.len());
+    for endpoint in &opts.read_endpoints {
+        match get_status(endpoint).await?.role {
+            Role::Storage(shard_id) => storage_nodes.push((shard_id, endpoint.clone())),
+            role => bail!("{} is not a storage node, but {}.", endpoint, role),
+        }
+    }
+
+    contracts
+        .iter()
+        .map(|&(address, _, contract)| {
+            let endpoints: Vec<String> = storage_nodes
+                .iter()
+                .filter(|(shard_id, _)| shard_id.contains(address))
+                .map(|(_, endpoint)| endpoint.clone())
+                .collect();
+            ensure!(
+                !endpoints.is_empty(),
+                "No storage node for the contract {:?} at {}.",
+                contract,
+                address
+            );
+            Ok((address, contract, endpoints))
+        })
+        .collect()
+}
+
+// Send `rate` view calls per second at a fixed rate until stopped, regardless of how long the
+// previous calls take.
+async fn send_reads(
+    rate: usize,
+    targets: Vec<(Address, ContractArg, Vec<String>)>,
+    mut rng: StdRng,
+    mut stop_rx: oneshot::Receiver<()>,
+) -> Result<ReadStats> {
+    let mut stats = ReadStats::default();
+    let mut record = |res: Result<Duration>| match res {
+        Ok(latency) => stats.latencies.push(latency.as_micros() as u64),
+        Err(e) => {
+            debug!("Failed to call the view. Error: {}", e);
+            stats.errors += 1;
+        }
+    };
+
+    let mut in_flight = stream::FuturesUnordered::new();
+    let mut ticker = interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = &mut stop_rx => break,
+            _ = ticker.tick() => {
+                for _ in 0..rate {
+                    let (address, contract, endpoints) =
+                        targets.choose(&mut rng).expect("Failed to get contract.");
+                    let endpoint = endpoints
+                        .choose(&mut rng)
+                        .expect("Failed to get storage node.")
+                        .clone();
+                    let req = ViewCallRequest {
+                        caller: Address::default(),
+                        address: *address,
+                        data: contract.gen_read_input(&mut rng)?,
+                    };
+                    in_flight.push(async move {
+                        let begin = Instant::now();
+                        call_view_on_storage(&endpoint, &req)
+                            .await
+                            .map(|_| begin.elapsed())
+                    });
+                }
+            }
+            Some(res) = in_flight.next() => record(res),
+        }
+    }
+
+    while let Some(res) = in_flight.next().await {
+        record(res);
+    }
+    Ok(stats)
+}
+
+// Stop the view calls once all the txs are sent, and report them.
+async fn finish_reads(
+    reader: Option<(oneshot::Sender<()>, JoinHandle<Result<ReadStats>>)>,
+    endpoint: &str,
+    begin: Instant,
+) -> Result<()> {
+    let (stop_tx, handle) = match reader {
+        Some(reader) => reader,
+        None => return Ok(()),
+    };
+    stop_tx.send(()).ok();
+    let stats = handle.await??;
+    let summary = stats.summary(Instant::now() - begin);
+    info!("View calls: {}", summary);
+    send_record_event_with_data(endpoint, "send-read-stats", summary).await
+}
+
+type TxEventCollector = (JoinHandle<()>, mpsc::UnboundedReceiver<(TxEvent, Instant)>);
+
+// Receive the tx events in the background, while the txs are sent at a fixed rate.
+fn spawn_tx_event_collector(
+    events: impl Stream<Item = Result<SubscriptionEvent>> + Send + 'static,
+) -> TxEventCollector {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(async move {
+        let mut events = Box::pin(events);
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(SubscriptionEvent::Tx(event)) => {
+                    if tx.send((event, Instant::now())).is_err() {
+                        break;
+                    }
+                }
+                Ok(SubscriptionEvent::Lagged { skipped }) => {
+                    warn!("{} tx events are dropped.", skipped);
+                }
+                Ok(SubscriptionEvent::NewBlock { .. }) => {}
+                Err(e) => {
+                    warn!("The subscription failed. Error: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    (handle, rx)
+}
+
+async fn open_loop_event_collector(
+    opts: &Opts,
+    leader: Option<&LeaderClient>,
+) -> Result<Option<TxEventCollector>> {
+    // The tx events are needed in the open-loop mode only to fill the tx log.
+    if opts.tx_log.is_none() {
+        return Ok(None);
+    }
+    match subscribe_events(&query_endpoint(opts, leader).await?).await {
+        Ok(events) => Ok(Some(spawn_tx_event_collector(events))),
+        Err(e) => {
+            warn!(
+                "Failed to subscribe to the tx events. Only the receipts are checked. Error: {}",
+                e
+            );
+            Ok(None)
+        }
+    }
+}
+
+// Wait for the blocks committing the txs sent in the open-loop mode, and settle the outcomes of
+// the tracked txs.
+async fn settle_open_loop(
+    opts: &Opts,
+    leader: Option<&LeaderClient>,
+    mut tx_log: TxLog,
+    event_collector: Option<TxEventCollector>,
+    total: usize,
+) -> Result<TxLog> {
+    let endpoint = query_endpoint(opts, leader).await?;
+    wait_for_blocks(&endpoint, Duration::from_secs(opts.wait)).await?;
+
+    if let Some((handle, mut events)) = event_collector {
+        handle.abort();
+        while let Some((event, at)) = events.recv().await {
+            tx_log.apply_event(event, at);
+        }
+    }
+
+    // The commits missed by the tx events, whose latency is unknown.
+    let endpoint = query_endpoint(opts, leader).await?;
+    for tx_id in tx_log.pending_tx_ids() {
+        if let Some(receipt) = get_tx_receipt(&endpoint, tx_id).await? {
+            tx_log.commit(tx_id, receipt.block_height, None);
+        }
+    }
+
+    if opts.check_receipts {
+        let committed = tx_log.count(TxStatus::Committed);
+        info!("Committed: {}/{} txs", committed, total);
+        send_record_event_with_data(
+            &endpoint,
+            "tx-receipts",
+            serde_json::json! {{
+                "committed": committed,
+                "total": total,
+            }},
+        )
+        .await?;
+    }
+    Ok(tx_log)
+}
+
+// Send the recorded txs at the same offsets from the first one as recorded. The txs recorded
+// together are sent together.
+async fn replay_trace(opts: &Opts, leader: Option<&LeaderClient>, path: &Path) -> Result<TxLog> {
+    let records = read_tx_trace(path)?;
+    ensure!(
+        !records.is_empty(),
+        "The tx trace {} is empty.",
+        path.display()
+    );
+    let total = records.len();
+    let first_time = records[0].time;
+    info!("Replay {} txs from {}", total, path.display());
+
+    let event_collector = open_loop_event_collector(opts, leader).await?;
+    send_record_event(&query_endpoint(opts, leader).await?, "start-send-tx").await?;
+    let begin = Instant::now();
+
+    let track_txs = opts.check_receipts || opts.tx_log.is_some();
+    let mut tx_log = TxLog::default();
+    let mut sent = 0;
+    let mut records = records.into_iter().peekable();
+    while let Some(record) = records.next() {
+        let time = record.time;
+        let mut reqs = vec![(record.req.req, record.req.shard_id)];
+        while let Some(record) = records.next_if(|record| record.time == time) {
+            reqs.push((record.req.req, record.req.shard_id));
+        }
+
+        sleep_until(begin + time.saturating_sub(first_time)).await;
+        if track_txs {
+            tx_log.submit_all(&reqs);
+        }
+        let n = reqs.len();
+        send_txs(opts, leader, reqs).await?;
+
+        if (sent + n) / 1_000 > sent / 1_000 {
+            info!("Sent #{} txs", sent + n);
+        }
+        sent += n;
     }
 
     let total_time = Instant::now() - begin;
-    let real_rate = (opts.total as f64) / total_time.as_secs_f64();
+    let real_rate = (total as f64) / total_time.as_secs_f64();
     send_record_event_with_data(
-        &opts.endpoint,
+        &query_endpoint(opts, leader).await?,
         "end-send-tx",
         serde_json::json! {{
             "total_time_in_us": total_time.as_micros() as u64,
@@ -441,33 +1417,296 @@ async fn main() -> Result<()> {
         }},
     )
     .await?;
-
     info!("Time: {:?}", total_time);
     info!("Real rate: {:?} tx/s", real_rate);
 
-    let mut cur_block_height = get_block_height(&opts.endpoint).await?;
-    let mut block_update_time = Instant::now();
-
-    loop {
-        sleep(Duration::from_millis(500)).await;
-        let height = get_block_height(&opts.endpoint).await?;
+    settle_open_loop(opts, leader, tx_log, event_collector, total).await
+}
 
-        if height > cur_block_height {
-            block_update_time = Instant::now();
-            cur_block_height = height;
-            continue;
-        } else if Instant::now() - block_update_time > Duration::from_secs(opts.wait) {
-            break;
-        }
+async fn finish(opts: &Opts, leader: Option<&LeaderClient>, tx_log: TxLog) -> Result<()> {
+    if let Some(path) = opts.tx_log.as_deref() {
+        tx_log.write_csv(path)?;
+        info!("Tx log: {}", tx_log.summary());
+        info!("Write the tx log to {}", path.display());
     }
 
+    let endpoint = query_endpoint(opts, leader).await?;
     info!("You can stop the nodes now by: kill -INT <pid>");
 
     if opts.raft {
-        info!("Current Raft Leader: {}", get_leader(&opts.endpoint).await?);
+        info!("Current Raft Leader: {}", endpoint);
     }
 
-    send_record_event(&opts.endpoint, "quit-send-tx").await?;
+    send_record_event(&endpoint, "quit-send-tx").await?;
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    const ONE_SECOND: Duration = Duration::from_secs(1);
+
+    color_backtrace::install();
+    init_tracing_subscriber("info")?;
+
+    let opts = Opts::from_args();
+    info!("Opts: {:#?}", opts);
+    opts.digest.install_as_global();
+    set_shard_assignment(opts.shard_assignment);
+
+    if let Some(ycsb) = opts.ycsb.as_ref() {
+        YCSB.set(Mutex::new(io::BufReader::new(File::open(ycsb)?)))
+            .map_err(|_e| anyhow!("Failed to set YCSB."))?;
+    }
+
+    ensure!(
+        opts.tpcc_warehouses > 0,
+        "At least one warehouse is required."
+    );
+    ensure!(
+        opts.tpcc_new_order_ratio <= 100,
+        "The ratio of the new-order TX should be a percentage."
+    );
+    TPCC.set(TpccConfig {
+        warehouses: opts.tpcc_warehouses,
+        new_order_ratio: opts.tpcc_new_order_ratio,
+    })
+    .map_err(|_e| anyhow!("Failed to set TPCC."))?;
+
+    ensure!(
+        (0. ..1.).contains(&opts.zipf),
+        "The Zipf skew should be in [0, 1)."
+    );
+    ZIPF_THETA
+        .set(opts.zipf)
+        .map_err(|_e| anyhow!("Failed to set ZIPF_THETA."))?;
+    set_linearizable_read(opts.linearizable_read);
+    if opts.admin_token.is_some() {
+        set_admin_token(opts.admin_token.clone());
+    }
+
+    let leader = if opts.raft {
+        let leader = LeaderClient::new(
+            std::iter::once(opts.endpoint.clone()).chain(opts.peers.iter().cloned()),
+        );
+        let mut i = 0;
+        while leader.leader().await.is_err() {
+            sleep(ONE_SECOND).await;
+            i += 1;
+            if i % 60 == 0 {
+                info!("Waiting for leader election...");
+            }
+        }
+        Some(leader)
+    } else {
+        None
+    };
+    let leader = leader.as_ref();
+
+    send_record_event_with_data(&query_endpoint(&opts, leader).await?, "send-tx-opts", &opts)
+        .await?;
+
+    if let Some(path) = opts.replay.as_deref() {
+        let tx_log = replay_trace(&opts, leader, path).await?;
+        return finish(&opts, leader, tx_log).await;
+    }
+    let total = opts.total.context("The total number of TX is required.")?;
+
+    let mut rng = match opts.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let saved_state = match opts.state.as_deref() {
+        Some(path) if path.exists() => {
+            let state = SendTxState::load(path)?;
+            let saved_contracts: Vec<ContractArg> =
+                state.contracts.iter().map(|&(_, _, c)| c).collect();
+            ensure!(
+                saved_contracts == opts.contract,
+                "The contracts {:?} differ from the saved ones {:?}.",
+                opts.contract,
+                saved_contracts
+            );
+            info!(
+                "Resume from {} with {} accounts.",
+                path.display(),
+                state.accounts.len()
+            );
+            Some(state)
+        }
+        _ => None,
+    };
+
+    let contracts: Vec<(Address, ShardId, ContractArg)> = match saved_state.as_ref() {
+        Some(state) => state.contracts.clone(),
+        None => {
+            let mut contracts = Vec::with_capacity(opts.contract.len());
+            let deploy_txs: Vec<(SignedTxRequest, ShardId)> = opts
+                .contract
+                .iter()
+                .enumerate()
+                .map(|(id, &contract)| {
+                    let id = (id as u64) % opts.shard;
+                    let shard_id = ShardId::new(id as u64, opts.shard);
+                    let (address, deploy_tx) =
+                        create_deploy_tx(&mut rng, contract, shard_id, opts.chain_id);
+                    debug!("tx {} address {}", id, address);
+                    contracts.push((address, shard_id, contract));
+                    (deploy_tx, shard_id)
+                })
+                .collect();
+
+            info!("Deploy txs");
+            let mut deploy_tx_ids: Vec<H256> = deploy_txs.iter().map(|(tx, _)| tx.id()).collect();
+            send_txs(&opts, leader, deploy_txs).await?;
+
+            while let Some(&tx_id) = deploy_tx_ids.last() {
+                match get_tx_receipt(&query_endpoint(&opts, leader).await?, tx_id).await? {
+                    Some(receipt) => {
+                        debug!("Deploy tx {} committed at #{}", tx_id, receipt.block_height);
+                        deploy_tx_ids.pop();
+                    }
+                    None => sleep(Duration::from_millis(500)).await,
+                }
+            }
+            info!("Deploy finished");
+            contracts
+        }
+    };
+
+    let num_accounts = opts.accounts.unwrap_or(total);
+    let accounts: VecDeque<(Keypair, Nonce)> =
+        match (saved_state.as_ref(), opts.mnemonic.as_deref()) {
+            (Some(state), _) => state.keypairs()?,
+            (None, Some(mnemonic)) => {
+                let seed = seed_from_mnemonic(mnemonic, "")?;
+                (0..num_accounts as u32)
+                    .map(|i| {
+                        let key = derive_keypair(&seed, &DerivationPath::bip44(0, i, true))?;
+                        Ok((key, Nonce::zero()))
+                    })
+                    .collect::<Result<_>>()?
+            }
+            (None, None) => std::iter::repeat_with(|| (Keypair::generate(&mut rng), Nonce::zero()))
+                .take(num_accounts)
+                .collect(),
+        };
+
+    let read_workload = match opts.read_rate {
+        Some(read_rate) => Some((
+            read_rate,
+            read_targets(&opts, &contracts).await?,
+            StdRng::from_rng(&mut rng)?,
+        )),
+        None => None,
+    };
+
+    let mut tx_gen = TxGenerator {
+        rng,
+        contracts,
+        accounts,
+        chain_id: opts.chain_id,
+    };
+
+    let event_collector = if opts.concurrency.is_none() {
+        open_loop_event_collector(&opts, leader).await?
+    } else {
+        None
+    };
+
+    send_record_event(&query_endpoint(&opts, leader).await?, "start-send-tx").await?;
+    let begin = Instant::now();
+
+    let reader = read_workload.map(|(read_rate, targets, read_rng)| {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let handle = tokio::spawn(send_reads(read_rate, targets, read_rng, stop_rx));
+        (stop_tx, handle)
+    });
+
+    let tx_log = if let Some(concurrency) = opts.concurrency {
+        let tx_log = send_txs_closed_loop(&opts, leader, &mut tx_gen, total, concurrency).await?;
+
+        let total_time = Instant::now() - begin;
+        let real_rate = (tx_log.count(TxStatus::Committed) as f64) / total_time.as_secs_f64();
+        let endpoint = query_endpoint(&opts, leader).await?;
+        send_record_event_with_data(
+            &endpoint,
+            "end-send-tx",
+            serde_json::json! {{
+                "total_time_in_us": total_time.as_micros() as u64,
+                "real_rate": real_rate,
+            }},
+        )
+        .await?;
+        finish_reads(reader, &endpoint, begin).await?;
+
+        let summary = tx_log.summary();
+        info!("Time: {:?}", total_time);
+        info!("Real rate: {:?} committed tx/s", real_rate);
+        info!("Latency: {}", summary);
+        send_record_event_with_data(&endpoint, "send-tx-latency", summary).await?;
+        tx_log
+    } else {
+        let rate = opts
+            .rate
+            .context("Either rate or concurrency is required.")?;
+        let track_txs = opts.check_receipts || opts.tx_log.is_some();
+        let mut next_epoch = begin + ONE_SECOND;
+
+        let mut tx_log = TxLog::default();
+        let mut reqs: Vec<(SignedTxRequest, ShardId)> = Vec::with_capacity(rate + 1);
+        let mut next_epoch_fut = sleep_until(next_epoch);
+        for i in 0..total {
+            reqs.push(tx_gen.next_tx()?);
+
+            if reqs.len() == rate {
+                if track_txs {
+                    tx_log.submit_all(&reqs);
+                }
+                send_txs(&opts, leader, reqs.drain(..).collect()).await?;
+                next_epoch_fut.await;
+
+                next_epoch += ONE_SECOND;
+                next_epoch_fut = sleep_until(next_epoch);
+            }
+
+            if (i + 1) % 1_000 == 0 {
+                info!("Sent #{} txs", i + 1);
+            }
+        }
+
+        if !reqs.is_empty() {
+            if track_txs {
+                tx_log.submit_all(&reqs);
+            }
+            send_txs(&opts, leader, reqs.drain(..).collect()).await?;
+        }
+
+        let total_time = Instant::now() - begin;
+        let real_rate = (total as f64) / total_time.as_secs_f64();
+        let endpoint = query_endpoint(&opts, leader).await?;
+        send_record_event_with_data(
+            &endpoint,
+            "end-send-tx",
+            serde_json::json! {{
+                "total_time_in_us": total_time.as_micros() as u64,
+                "real_rate": real_rate,
+            }},
+        )
+        .await?;
+        finish_reads(reader, &endpoint, begin).await?;
+
+        info!("Time: {:?}", total_time);
+        info!("Real rate: {:?} tx/s", real_rate);
+
+        settle_open_loop(&opts, leader, tx_log, event_collector, total).await?
+    };
+
+    if let Some(path) = opts.state.as_deref() {
+        SendTxState::from_tx_gen(&tx_gen).save(path)?;
+        info!("Saved the state to {}.", path.display());
+    }
+
+    finish(&opts, leader, tx_log).await
+}
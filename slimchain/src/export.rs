@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use slimchain_chain::{
+    block::BlockTrait,
+    consensus::Consensus,
+    db::DB,
+    export::{export_chain, import_chain},
+};
+use slimchain_common::{
+    basic::BlockHeight,
+    error::{bail, ConsensusError, Context as _, Result},
+    tx::TxTrait,
+};
+use slimchain_utils::{cli::DigestOpts, init_tracing_subscriber};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(version = git_version::git_version!(prefix = concat!(env!("CARGO_PKG_VERSION"), " ("), suffix = ")", fallback = "unknown"))]
+struct ExportOpts {
+    /// Path to the database of the node, e.g., storage.db. It is opened read-only.
+    #[structopt(short, long, parse(from_os_str))]
+    db_path: PathBuf,
+
+    /// Path to the exported chain.
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// Start block
+    #[structopt(short, long, default_value = "0")]
+    start: BlockHeight,
+
+    /// End block. Default: the latest block.
+    #[structopt(short, long)]
+    end: Option<BlockHeight>,
+
+    /// Also export the state at the end block. It requires the database of a storage node.
+    #[structopt(long)]
+    with_state: bool,
+
+    #[structopt(flatten)]
+    digest: DigestOpts,
+
+    /// Set trace log level. Default: no tracing.
+    #[structopt(long)]
+    log_level: Option<String>,
+}
+
+pub fn export_main<Tx, Block>(consensus: Consensus) -> Result<()>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de>,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
+{
+    color_backtrace::install();
+
+    let opts = ExportOpts::from_args();
+    opts.digest.install_as_global();
+
+    if let Some(log_level) = opts.log_level.as_deref() {
+        init_tracing_subscriber(log_level)?;
+    }
+
+    if !opts.db_path.exists() {
+        bail!("DB {:?} not existed.", opts.db_path);
+    }
+    let db = DB::open_read_only(&opts.db_path)?;
+
+    let latest_height: BlockHeight = db
+        .get_existing_meta_object("height")
+        .context("Failed to get block height from the database.")?;
+    let end = opts.end.unwrap_or(latest_height).min(latest_height);
+
+    let file = File::create(&opts.output)
+        .with_context(|| format!("Failed to create {}.", opts.output.display()))?;
+    let info = export_chain::<Tx, Block>(
+        &db,
+        consensus,
+        opts.start,
+        end,
+        opts.with_state,
+        &mut BufWriter::new(file),
+    )?;
+    println!(
+        "Exported block #{}..=#{} to {}. {:?}",
+        opts.start,
+        end,
+        opts.output.display(),
+        info
+    );
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(version = git_version::git_version!(prefix = concat!(env!("CARGO_PKG_VERSION"), " ("), suffix = ")", fallback = "unknown"))]
+struct ImportOpts {
+    /// Path to the exported chain.
+    #[structopt(short, long, parse(from_os_str))]
+    input: PathBuf,
+
+    /// Path to the new database, e.g., auditor.db.
+    #[structopt(short, long, parse(from_os_str))]
+    db_path: PathBuf,
+
+    #[structopt(flatten)]
+    digest: DigestOpts,
+
+    /// Set trace log level. Default: no tracing.
+    #[structopt(long)]
+    log_level: Option<String>,
+}
+
+// The chain is verified while being imported. The imported database can be checked further by
+// `slimchain-inspect`.
+pub fn import_main<Tx, Block>(
+    consensus: Consensus,
+    verify_consensus_fn: impl Fn(&Block, &Block) -> Result<(), ConsensusError>,
+) -> Result<()>
+where
+    Tx: TxTrait + Serialize + for<'de> Deserialize<'de>,
+    Block: BlockTrait + Serialize + for<'de> Deserialize<'de>,
+{
+    color_backtrace::install();
+
+    let opts = ImportOpts::from_args();
+    opts.digest.install_as_global();
+
+    if let Some(log_level) = opts.log_level.as_deref() {
+        init_tracing_subscriber(log_level)?;
+    }
+
+    if opts.db_path.exists() {
+        bail!("DB {:?} already existed.", opts.db_path);
+    }
+    let file = File::open(&opts.input)
+        .with_context(|| format!("Failed to open {}.", opts.input.display()))?;
+    let db = DB::open_or_create(&opts.db_path, false)?;
+
+    let (header, info) = import_chain::<Tx, Block>(
+        &db,
+        consensus,
+        &mut BufReader::new(file),
+        verify_consensus_fn,
+    )?;
+    println!(
+        "Imported block #{}..=#{} to {:?}. {:?}",
+        header.start, header.end, opts.db_path, info
+    );
+    Ok(())
+}
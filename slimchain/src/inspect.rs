@@ -1,15 +1,11 @@
 use serde::Deserialize;
-use slimchain_chain::{
-    block::BlockTrait,
-    db::DB,
-    loader::{BlockLoaderTrait, TxLoaderTrait},
-};
+use slimchain_chain::{block::BlockTrait, db::DB, loader::TxLoaderTrait};
 use slimchain_common::{
     basic::BlockHeight,
     error::{bail, Context as _, Result},
     tx::TxTrait,
 };
-use slimchain_utils::init_tracing_subscriber;
+use slimchain_utils::{cli::DigestOpts, init_tracing_subscriber};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -32,6 +28,9 @@ struct Opts {
     #[structopt(short, long)]
     write_set: bool,
 
+    #[structopt(flatten)]
+    digest: DigestOpts,
+
     /// Set trace log level. Default: no tracing.
     #[structopt(long)]
     log_level: Option<String>,
@@ -45,6 +44,7 @@ where
     color_backtrace::install();
 
     let opts = Opts::from_args();
+    opts.digest.install_as_global();
 
     if let Some(log_level) = opts.log_level.as_deref() {
         init_tracing_subscriber(log_level)?;
@@ -63,17 +63,13 @@ where
             .context("Failed to get block height from the database.")?,
     };
 
-    let mut height = start;
-    while height <= end {
-        if height.is_zero() {
-            height = height.next_height();
-            continue;
-        }
-
-        let block: Block = db.get_block(height)?;
+    // Skip the genesis block.
+    let start = start.max(BlockHeight(1));
+    for block in db.iter_blocks::<Block>(start..=end)? {
+        let block = block?;
         println!(
             "Block #{} [#tx={}, state_root={}]",
-            height,
+            block.block_height(),
             block.tx_list().len(),
             block.state_root()
         );
@@ -88,8 +84,6 @@ where
                 println!(" TX {} (not available)", tx_hash);
             }
         }
-
-        height = height.next_height();
     }
 
     Ok(())
@@ -1,5 +1,6 @@
 #[macro_use]
 extern crate tracing;
 
+pub mod export;
 pub mod inspect;
 pub mod node;